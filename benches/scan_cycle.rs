@@ -0,0 +1,128 @@
+//! Compares the throughput of the bytecode VM with the AST interpreter.
+//!
+//! Run with `cargo bench --bench scan_cycle [cycles]`.
+
+use std::time::{Duration, Instant};
+
+use strooct::{
+    parsing::{lexer::Lexer, parser::parse},
+    runtime::{interpreter::Interpreter, vm::Vm},
+    semantic::{checker::check, resolver::resolve},
+};
+
+/// A filling station: a state machine, a moving average over an array, a PI controller and a
+/// couple of function and function block calls per cycle.
+const PROGRAM: &str = r#"
+TYPE StationPhase : (Idle, Filling, Settling, Draining); END_TYPE
+
+FUNCTION Limit : REAL
+    VAR_INPUT value : REAL; low : REAL; high : REAL; END_VAR
+    Limit := value;
+    IF value < low THEN Limit := low; ELSIF value > high THEN Limit := high; END_IF;
+END_FUNCTION
+
+FUNCTION_BLOCK Controller
+    VAR_INPUT setpoint : REAL; actual : REAL; END_VAR
+    VAR_OUTPUT output : REAL; END_VAR
+    VAR integral : REAL; error : REAL; END_VAR
+    error := setpoint - actual;
+    integral := Limit(integral + error * 0.01, -50.0, 50.0);
+    output := Limit(error * 0.8 + integral, 0.0, 100.0);
+END_FUNCTION_BLOCK
+
+FUNCTION_BLOCK Average
+    VAR_INPUT sample : REAL; END_VAR
+    VAR_OUTPUT mean : REAL; END_VAR
+    VAR window : ARRAY[0..15] OF REAL; next : INT; i : INT; sum : REAL; END_VAR
+    window[next] := sample;
+    next := (next + 1) MOD 16;
+    sum := 0.0;
+    FOR i := 0 TO 15 DO
+        sum := sum + window[i];
+    END_FOR;
+    mean := sum / 16.0;
+END_FUNCTION_BLOCK
+
+PROGRAM Station
+    VAR
+        phase : StationPhase;
+        level : REAL;
+        valve : REAL;
+        filter : Average;
+        pid : Controller;
+        ticks : DINT;
+        batches : DINT;
+    END_VAR
+    ticks := ticks + 1;
+    filter(sample := level);
+    CASE phase OF
+        Idle:
+            IF ticks MOD 10 = 0 THEN phase := Filling; END_IF;
+        Filling:
+            pid(setpoint := 80.0, actual := filter.mean, output => valve);
+            level := level + valve * 0.05;
+            IF filter.mean >= 79.0 THEN phase := Settling; END_IF;
+        Settling:
+            IF ticks MOD 25 = 0 THEN phase := Draining; END_IF;
+        Draining:
+            level := level - 4.0;
+            IF level <= 0.0 THEN
+                level := 0.0;
+                batches := batches + 1;
+                phase := Idle;
+            END_IF;
+    END_CASE;
+END_PROGRAM
+"#;
+
+fn measure(mut cycle: impl FnMut(), cycles: usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..cycles {
+        cycle();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let cycles = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<usize>().ok())
+        .unwrap_or(50_000);
+
+    let ast = parse(Lexer::create("station.st", PROGRAM)).expect("The benchmark program parses");
+    let (table, mut diagnostics) = resolve(&ast);
+    diagnostics.extend(check(&ast, &table));
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    let mut interpreter = Interpreter::new(&ast, &table).expect("The interpreter starts");
+    let mut vm = Vm::new(&ast, &table).expect("The program compiles");
+    let station = vm.program("Station").expect("Station is a program");
+
+    let interpreted = measure(
+        || interpreter.run_cycle(station).expect("The cycle runs"),
+        cycles,
+    );
+    let compiled = measure(|| vm.run_cycle(station).expect("The cycle runs"), cycles);
+    assert_eq!(
+        interpreter.read("Station").map(|v| v.to_string()),
+        vm.read("Station").map(|v| v.to_string()),
+        "Both engines end in the same state"
+    );
+
+    let rate = |d: Duration| cycles as f64 / d.as_secs_f64();
+    println!("{cycles} cycles of Station");
+    println!(
+        "  interpreter  {:>10.3?}  {:>12.0} cycles/s",
+        interpreted,
+        rate(interpreted)
+    );
+    println!(
+        "  bytecode VM  {:>10.3?}  {:>12.0} cycles/s",
+        compiled,
+        rate(compiled)
+    );
+    println!(
+        "  speedup      {:>10.1}x",
+        interpreted.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
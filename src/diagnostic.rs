@@ -0,0 +1,108 @@
+use crate::parsing::{ast::Span, token::Marker};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<(Span, String)>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, span, message)
+    }
+
+    pub fn new(severity: Severity, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.notes.push((span, message.into()));
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn format_as_printable(&self, src_file: &str, src: &str) -> String {
+        self.format_located(|span| (src_file, src, span))
+    }
+
+    /// Formats a diagnostic whose spans may point into different files, `locate` gives the name,
+    /// source and span within that source of each span.
+    pub fn format_located<'a>(&self, locate: impl Fn(Span) -> (&'a str, &'a str, Span)) -> String {
+        let tag = match self.severity {
+            Severity::Error => "ERR",
+            Severity::Warning => "WARN",
+            Severity::Info => "INFO",
+        };
+
+        let mut printable = format!(
+            "[{tag}] in {} {}",
+            marker_at(locate(self.span)).format_as_printable(),
+            self.message
+        );
+        for (span, note) in &self.notes {
+            printable += &format!(
+                "\n[NOTE] in {} {}",
+                marker_at(locate(*span)).format_as_printable(),
+                note
+            );
+        }
+
+        printable
+    }
+}
+
+fn marker_at<'a>((src_file, src, span): (&'a str, &'a str, Span)) -> Marker<'a> {
+    let mut marker = Marker::create(src_file, src);
+    marker.set(span.pos.min(src.len()), span.line, span.col);
+    marker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_as_printable() {
+        let span = Span {
+            pos: 4,
+            len: 2,
+            line: 1,
+            col: 0,
+        };
+        let diagnostic = Diagnostic::warning(span, "Something is odd.").with_note(
+            Span {
+                pos: 0,
+                len: 3,
+                line: 0,
+                col: 0,
+            },
+            "Related.",
+        );
+
+        assert_eq!(
+            diagnostic.format_as_printable("main.st", "abc\nde"),
+            "[WARN] in main.st:2:0\n    |\n 2  | de\n    |  ^ Something is odd.\n[NOTE] in main.st:1:0\n    |\n 1  | abc\n    |  ^ Related."
+        );
+    }
+}
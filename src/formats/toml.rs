@@ -0,0 +1,396 @@
+use crate::formats::json::Json;
+
+/// Parses the subset of TOML used by configuration files into the equivalent JSON document.
+///
+/// Supported are tables, arrays of tables, dotted table headers, strings, integers, floats,
+/// booleans, arrays and inline tables. Dates and dotted keys are not.
+pub fn parse(src: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut root = Json::Object(Vec::new());
+    let mut current: Vec<String> = Vec::new();
+
+    loop {
+        parser.skip_blank();
+        let Some(c) = parser.peek() else {
+            return Ok(root);
+        };
+
+        if c == '[' {
+            parser.pos += 1;
+            let array = parser.peek() == Some('[');
+            if array {
+                parser.pos += 1;
+            }
+            let path = parser.header()?;
+            parser.expect(']')?;
+            if array {
+                parser.expect(']')?;
+            }
+            let line = parser.line;
+            open_table(&mut root, &path, array).map_err(|e| format!("Line {line}: {e}"))?;
+            current = path;
+        } else {
+            let key = parser.key()?;
+            parser.skip_spaces();
+            parser.expect('=')?;
+            let value = parser.value()?;
+            let line = parser.line;
+            let table = table_at(&mut root, &current);
+            if table.iter().any(|(k, _)| *k == key) {
+                return Err(format!("Line {line}: '{key}' is defined twice."));
+            }
+            table.push((key, value));
+        }
+        parser.end_of_line()?;
+    }
+}
+
+/// Creates the table a header names, appending a new element for arrays of tables.
+fn open_table(root: &mut Json, path: &[String], array: bool) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("Headers are never empty");
+    let table = table_at(root, parents);
+    let existing = table.iter_mut().find(|(k, _)| k == last);
+    match (existing, array) {
+        (None, false) => table.push((last.clone(), Json::Object(Vec::new()))),
+        (None, true) => table.push((last.clone(), Json::Array(vec![Json::Object(Vec::new())]))),
+        (Some((_, Json::Array(items))), true) => items.push(Json::Object(Vec::new())),
+        (Some((_, Json::Object(_))), false) => {}
+        _ => return Err(format!("'{}' is defined twice.", path.join("."))),
+    }
+    Ok(())
+}
+
+/// The table a path leads to, descending into the last element of arrays of tables.
+fn table_at<'j>(root: &'j mut Json, path: &[String]) -> &'j mut Vec<(String, Json)> {
+    let mut table = match root {
+        Json::Object(members) => members,
+        _ => unreachable!("The root is a table"),
+    };
+    for key in path {
+        if !table.iter().any(|(k, _)| k == key) {
+            table.push((key.clone(), Json::Object(Vec::new())));
+        }
+        let value = table
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .expect("The key was just inserted");
+        let value = match value {
+            Json::Array(items) => items.last_mut().expect("Arrays of tables are never empty"),
+            value => value,
+        };
+        table = match value {
+            Json::Object(members) => members,
+            other => {
+                *other = Json::Object(Vec::new());
+                match other {
+                    Json::Object(members) => members,
+                    _ => unreachable!(),
+                }
+            }
+        };
+    }
+    table
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("Line {}: {message}.", self.line)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips whitespace, newlines and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => {
+                    self.line += 1;
+                    self.pos += 1;
+                }
+                Some('\r') => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.peek() {
+            None | Some('\n' | '\r') => Ok(()),
+            _ => Err(self.error("Expected the end of the line")),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.peek() == Some(c) {
+            true => {
+                self.pos += 1;
+                Ok(())
+            }
+            false => Err(self.error(&format!("Expected '{c}'"))),
+        }
+    }
+
+    fn header(&mut self) -> Result<Vec<String>, String> {
+        let mut path = vec![self.key()?];
+        self.skip_spaces();
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            path.push(self.key()?);
+            self.skip_spaces();
+        }
+        Ok(path)
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.pos += 1;
+                }
+                match start == self.pos {
+                    true => Err(self.error("Expected a key")),
+                    false => Ok(self.chars[start..self.pos].iter().collect()),
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => self.basic_string().map(Json::String),
+            Some('\'') => self.literal_string().map(Json::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.scalar(),
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error("Unterminated string")),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    self.pos += 1;
+                    s.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        c => c,
+                    });
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != '\'' && c != '\n') {
+            self.pos += 1;
+        }
+        if self.peek() != Some('\'') {
+            return Err(self.error("Unterminated string"));
+        }
+        self.pos += 1;
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Json::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Json, String> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        loop {
+            self.skip_spaces();
+            if self.peek() == Some('}') {
+                self.pos += 1;
+                return Ok(Json::Object(members));
+            }
+            let key = self.key()?;
+            self.expect('=')?;
+            members.push((key, self.value()?));
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {}
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))
+        {
+            self.pos += 1;
+        }
+        let text = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect::<String>();
+        match text.as_str() {
+            "true" => Ok(Json::Bool(true)),
+            "false" => Ok(Json::Bool(false)),
+            "inf" | "+inf" => Ok(Json::Number(f64::INFINITY)),
+            "-inf" => Ok(Json::Number(f64::NEG_INFINITY)),
+            t => {
+                let number = match t.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+                    None => t.parse::<f64>().ok(),
+                };
+                number
+                    .map(Json::Number)
+                    .ok_or_else(|| self.error("Expected a value"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_and_values() {
+        let doc = parse(
+            r#"
+            # Project settings
+            name = "demo"   # trailing comment
+            [package]
+            version = '1.0'
+            threads = 4
+            ratio = 0.5
+            debug = true
+            [package.paths]
+            sources = ["src", "lib",
+                       "vendor"]
+            inline = { a = 1, b = "x" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(doc.get("name").and_then(Json::as_str), Some("demo"));
+        let package = doc.get("package").unwrap();
+        assert_eq!(package.get("version").and_then(Json::as_str), Some("1.0"));
+        assert_eq!(package.get("threads").and_then(Json::as_i64), Some(4));
+        assert_eq!(package.get("ratio").and_then(Json::as_f64), Some(0.5));
+        assert_eq!(package.get("debug").and_then(Json::as_bool), Some(true));
+        let paths = package.get("paths").unwrap();
+        assert_eq!(
+            paths
+                .get("sources")
+                .and_then(Json::as_array)
+                .map(|a| a.len()),
+            Some(3)
+        );
+        assert_eq!(
+            paths
+                .get("inline")
+                .and_then(|t| t.get("b"))
+                .and_then(Json::as_str),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn test_arrays_of_tables_and_errors() {
+        let doc = parse(
+            r#"
+            [[task]]
+            name = "Fast"
+            [[task]]
+            name = "Slow"
+            "#,
+        )
+        .unwrap();
+        let tasks = doc.get("task").and_then(Json::as_array).unwrap();
+        assert_eq!(tasks[1].get("name").and_then(Json::as_str), Some("Slow"));
+
+        assert_eq!(
+            parse("a = 1\na = 2"),
+            Err("Line 2: 'a' is defined twice.".to_string())
+        );
+        assert_eq!(
+            parse("a = \"open"),
+            Err("Line 1: Unterminated string.".to_string())
+        );
+        assert_eq!(
+            parse("a = 1 2"),
+            Err("Line 1: Expected the end of the line.".to_string())
+        );
+    }
+}
@@ -1 +1,9 @@
-pub mod parsing;
\ No newline at end of file
+pub mod diagnostic;
+pub mod formats;
+pub mod lint;
+pub mod lsp;
+pub mod parsing;
+pub mod project;
+pub mod refactor;
+pub mod runtime;
+pub mod semantic;
@@ -0,0 +1,537 @@
+use std::collections::HashSet;
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    parsing::ast::{Block, Identifier, Pou, Span, Statement, Statements},
+    project::{Project, manifest::LintLevel},
+    semantic::{
+        checker::Typer,
+        symbols::{SymbolId, SymbolTable},
+    },
+};
+
+pub mod dataflow;
+pub mod expressions;
+pub mod metrics;
+pub mod plcopen;
+pub mod reachability;
+pub mod recursion;
+pub mod statements;
+pub mod tasks;
+pub mod variables;
+
+/// A rule about code that is valid, but likely wrong or hard to maintain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lint {
+    /// Name of the lint in manifests and attributes, and at the end of its messages
+    pub id: &'static str,
+    /// Level unless a manifest changes it
+    pub level: LintLevel,
+    pub description: &'static str,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint {
+        id: "unused-variable",
+        level: LintLevel::Warn,
+        description: "A local variable is never used.",
+    },
+    Lint {
+        id: "write-only-variable",
+        level: LintLevel::Warn,
+        description: "A local variable is assigned, but never read.",
+    },
+    Lint {
+        id: "input-assignment",
+        level: LintLevel::Warn,
+        description: "A POU assigns to one of its own inputs.",
+    },
+    Lint {
+        id: "case-without-else",
+        level: LintLevel::Warn,
+        description: "A CASE over an enum has no branch for some of its values and no ELSE.",
+    },
+    Lint {
+        id: "empty-branch",
+        level: LintLevel::Warn,
+        description: "A branch of an IF does nothing.",
+    },
+    Lint {
+        id: "magic-number",
+        level: LintLevel::Allow,
+        description: "A statement uses a number other than 0 or 1 instead of a named constant.",
+    },
+    Lint {
+        id: "shadowing",
+        level: LintLevel::Warn,
+        description: "A variable hides a declaration of an outer scope.",
+    },
+    Lint {
+        id: "recursion",
+        level: LintLevel::Warn,
+        description: "A POU calls itself, directly or through others.",
+    },
+    Lint {
+        id: "deep-nesting",
+        level: LintLevel::Warn,
+        description: "Control statements are nested more than 4 levels deep.",
+    },
+    Lint {
+        id: "float-equality",
+        level: LintLevel::Warn,
+        description: "Real numbers are compared with = or <>.",
+    },
+    Lint {
+        id: "integer-division",
+        level: LintLevel::Warn,
+        description: "An integer division truncates a result used as a real number, or a quotient of literals.",
+    },
+    Lint {
+        id: "uninitialized-read",
+        level: LintLevel::Warn,
+        description: "A temporary variable is read before it is assigned.",
+    },
+    Lint {
+        id: "dead-store",
+        level: LintLevel::Warn,
+        description: "A value assigned to a temporary variable or an output of a function is never read.",
+    },
+    Lint {
+        id: "unassigned-output",
+        level: LintLevel::Warn,
+        description: "A function or method does not assign its result or an output on every path.",
+    },
+    Lint {
+        id: "unreachable-code",
+        level: LintLevel::Warn,
+        description: "Statements follow a RETURN, EXIT or CONTINUE, or other jumps that skip them.",
+    },
+    Lint {
+        id: "dead-branch",
+        level: LintLevel::Warn,
+        description: "A constant condition or constant bounds keep a branch or loop from ever running.",
+    },
+    Lint {
+        id: "infinite-loop",
+        level: LintLevel::Warn,
+        description: "A loop whose condition or step is constant never ends and has no EXIT or RETURN.",
+    },
+];
+
+/// The level of every lint, the defaults of [`LINTS`] as changed by the `[lint]` table of a
+/// manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintLevels(Vec<LintLevel>);
+
+impl Default for LintLevels {
+    fn default() -> Self {
+        Self(LINTS.iter().map(|l| l.level).collect())
+    }
+}
+
+impl LintLevels {
+    pub fn new(changes: &[(String, LintLevel)]) -> Result<Self, String> {
+        let mut levels = Self::default();
+        for (id, level) in changes {
+            let index = index_of(id).ok_or_else(|| format!("There is no lint '{id}'."))?;
+            levels.0[index] = *level;
+        }
+        Ok(levels)
+    }
+
+    pub fn level(&self, id: &str) -> LintLevel {
+        index_of(id).map_or(LintLevel::Allow, |i| self.0[i])
+    }
+}
+
+fn index_of(id: &str) -> Option<usize> {
+    LINTS.iter().position(|l| l.id.eq_ignore_ascii_case(id))
+}
+
+/// Lints a project, reporting warned lints as warnings and denied lints as errors.
+///
+/// A POU allows lints within itself, its methods and its actions with an attribute like
+/// `{attribute 'allow' := 'magic-number, deep-nesting'}`. Actions declared apart from their
+/// owner, like `ACTION Main.Reset`, take no attributes and inherit the allowances of the owner.
+pub fn lint(project: &Project, levels: &LintLevels) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut allowed = Vec::new();
+    allowances(&project.ast.blocks, &mut allowed, &mut diagnostics);
+    inherit_allowances(&project.ast.blocks, &project.table, &mut allowed);
+    for (id, diagnostic) in findings(project) {
+        let severity = match levels.level(id) {
+            LintLevel::Allow => continue,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        };
+        let within =
+            |span: &Span| span.pos <= diagnostic.span.pos && diagnostic.span.pos < span.end();
+        if allowed
+            .iter()
+            .any(|(span, ids)| within(span) && ids.contains(&id))
+        {
+            continue;
+        }
+        diagnostics.push(Diagnostic {
+            severity,
+            message: format!("{} [{id}]", diagnostic.message),
+            ..diagnostic
+        });
+    }
+    diagnostics.sort_by_key(|d| d.span.pos);
+    diagnostics
+}
+
+/// What every lint finds in a project, as warnings by the ids of the lints.
+fn findings(project: &Project) -> Vec<(&'static str, Diagnostic)> {
+    let mut linter = Linter {
+        table: &project.table,
+        typer: Typer::new(&project.table),
+        writes: HashSet::new(),
+        located: HashSet::new(),
+        body: None,
+        calls: Vec::new(),
+        findings: Vec::new(),
+    };
+    linter.lint_blocks(&project.ast.blocks);
+    linter.lint_variables();
+    linter.lint_recursion();
+    linter.findings
+}
+
+/// Collects the lints the POUs allow by their spans, reporting names that are no lints.
+fn allowances(
+    blocks: &[Block],
+    allowed: &mut Vec<(Span, Vec<&'static str>)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for block in blocks {
+        let pou = match block {
+            Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => pou,
+            Block::Namespace(namespace) => {
+                allowances(&namespace.blocks, allowed, diagnostics);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(attribute) = pou.attribute("allow") else {
+            continue;
+        };
+        let Some(value) = &attribute.value else {
+            diagnostics.push(Diagnostic::warning(
+                attribute.span,
+                "Name the lints to allow, like {attribute 'allow' := 'magic-number'}.",
+            ));
+            continue;
+        };
+        let mut ids = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            match index_of(name) {
+                Some(index) => ids.push(LINTS[index].id),
+                None => diagnostics.push(Diagnostic::warning(
+                    attribute.span,
+                    format!("There is no lint '{name}'."),
+                )),
+            }
+        }
+        allowed.push((pou.span, ids));
+    }
+}
+
+/// Gives the actions declared apart from their owner the allowances of the owner.
+fn inherit_allowances(
+    blocks: &[Block],
+    table: &SymbolTable,
+    allowed: &mut Vec<(Span, Vec<&'static str>)>,
+) {
+    for block in blocks {
+        let action = match block {
+            Block::Action(action) => action,
+            Block::Namespace(namespace) => {
+                inherit_allowances(&namespace.blocks, table, allowed);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(owner) = action
+            .owner
+            .as_ref()
+            .and_then(|owner| table.symbol_at(owner.span.pos))
+        else {
+            continue;
+        };
+        let declared = table.symbol(owner).span.pos;
+        let inherited = allowed
+            .iter()
+            .filter(|(span, _)| span.pos <= declared && declared < span.end())
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect::<Vec<_>>();
+        if !inherited.is_empty() {
+            allowed.push((action.span, inherited));
+        }
+    }
+}
+
+/// Nesting of control statements beyond which they are reported.
+const MAX_NESTING: usize = 4;
+
+struct Linter<'a> {
+    table: &'a SymbolTable,
+    typer: Typer<'a>,
+    /// Positions of the names of variables that are assigned to
+    writes: HashSet<usize>,
+    /// Positions of the names of variables declared AT a location
+    located: HashSet<usize>,
+    /// The POU, method or action whose statements are linted
+    body: Option<SymbolId>,
+    /// Callers, callees and the span of each call between POUs
+    calls: Vec<(SymbolId, SymbolId, Span)>,
+    findings: Vec<(&'static str, Diagnostic)>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, id: &'static str, span: Span, message: String) {
+        self.findings.push((id, Diagnostic::warning(span, message)));
+    }
+
+    fn lint_blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                    self.lint_pou(pou)
+                }
+                Block::Action(action) => self.lint_body(&action.name, &action.statements),
+                Block::Namespace(namespace) => self.lint_blocks(&namespace.blocks),
+                Block::Type(_) | Block::GlobalVariables(_) | Block::Configuration(_) => {}
+            }
+        }
+    }
+
+    fn lint_pou(&mut self, pou: &Pou) {
+        let located = pou
+            .variables
+            .iter()
+            .flat_map(|v| &v.declarations)
+            .filter(|d| d.location.is_some())
+            .flat_map(|d| &d.names);
+        self.located.extend(located.map(|name| name.span.pos));
+        for method in &pou.methods {
+            self.lint_pou(method);
+        }
+        for action in &pou.actions {
+            self.lint_body(&action.name, &action.statements);
+        }
+        self.lint_body(&pou.name, &pou.statements);
+        self.lint_dataflow(pou);
+    }
+
+    fn lint_body(&mut self, name: &Identifier, statements: &Statements) {
+        self.body = self.typer.env().binding(name.span);
+        self.check_reachability(statements);
+        self.lint_statements(statements, 0);
+    }
+
+    /// Lints statements within `depth` control statements.
+    fn lint_statements(&mut self, statements: &Statements, depth: usize) {
+        for statement in statements {
+            self.lint_statement(statement, depth);
+        }
+    }
+
+    fn lint_statement(&mut self, statement: &Statement, depth: usize) {
+        match statement {
+            Statement::Empty(_)
+            | Statement::Return(_)
+            | Statement::Exit(_)
+            | Statement::Continue(_) => {}
+            Statement::Expression(expression) => self.lint_expression(expression, false),
+            Statement::Assignment(assignment) => {
+                self.write(&assignment.target);
+                self.lint_expression(&assignment.target, false);
+                let real = self.is_real(&assignment.target);
+                self.lint_expression(&assignment.value, real);
+            }
+            Statement::If(x) => {
+                self.check_nesting(x.span, depth);
+                self.check_empty_branches(x);
+                self.check_constant_conditions(x);
+                for branch in std::iter::once(&x.branch).chain(&x.alt_branches) {
+                    self.lint_expression(&branch.condition, false);
+                    self.lint_statements(&branch.statements, depth + 1);
+                }
+                if let Some(fallback) = &x.fallback {
+                    self.lint_statements(fallback, depth + 1);
+                }
+            }
+            Statement::Case(x) => {
+                self.check_nesting(x.span, depth);
+                self.check_case_values(x);
+                // Labels are constants of their own, no magic numbers
+                self.lint_expression(&x.selector, false);
+                for branch in &x.branches {
+                    self.lint_statements(&branch.statements, depth + 1);
+                }
+                if let Some(fallback) = &x.fallback {
+                    self.lint_statements(fallback, depth + 1);
+                }
+            }
+            Statement::For(x) => {
+                self.check_nesting(keyword(x.span, "FOR"), depth);
+                self.check_input_write(&x.variable);
+                self.check_for_bounds(x);
+                for bound in [Some(&x.start), Some(&x.end), x.step.as_ref()]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.lint_expression(bound, false);
+                }
+                self.lint_statements(&x.statements, depth + 1);
+            }
+            Statement::While(x) => {
+                self.check_nesting(keyword(x.span, "WHILE"), depth);
+                self.check_while(&x.condition, &x.statements);
+                self.lint_expression(&x.condition, false);
+                self.lint_statements(&x.statements, depth + 1);
+            }
+            Statement::Repeat(x) => {
+                self.check_nesting(keyword(x.span, "REPEAT"), depth);
+                self.check_repeat(&x.condition, &x.statements);
+                self.lint_statements(&x.statements, depth + 1);
+                self.lint_expression(&x.condition, false);
+            }
+        }
+    }
+}
+
+/// The span of the keyword a statement starts with.
+fn keyword(span: Span, keyword: &str) -> Span {
+    Span {
+        len: keyword.len(),
+        ..span
+    }
+}
+
+#[cfg(test)]
+fn linted(src: &str, id: &str) -> Vec<String> {
+    use crate::project::SourceDb;
+
+    let mut sources = SourceDb::new();
+    sources.add("main.st", src);
+    let project = Project::analyze(sources).unwrap();
+    assert!(!project.has_errors(), "{:?}", project.diagnostics);
+    let levels = LINTS
+        .iter()
+        .map(|l| (l.id.to_string(), LintLevel::Allow))
+        .chain([(id.to_string(), LintLevel::Warn)])
+        .collect::<Vec<_>>();
+    let levels = LintLevels::new(&levels).unwrap();
+    lint(&project, &levels)
+        .into_iter()
+        .map(|d| d.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::SourceDb;
+
+    const SRC: &str = "PROGRAM Main
+VAR unused : INT; x : INT; END_VAR
+x := x * 7;
+END_PROGRAM
+{attribute 'allow' := 'unused-variable, Magic-Number'}
+FUNCTION_BLOCK Quiet
+VAR unused : INT; x : INT; END_VAR
+x := x * 7;
+END_FUNCTION_BLOCK
+";
+
+    fn diagnostics(src: &str, changes: &[(&str, LintLevel)]) -> Result<Vec<String>, String> {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", src);
+        let project = Project::analyze(sources).unwrap();
+        let changes = changes
+            .iter()
+            .map(|(id, level)| (id.to_string(), *level))
+            .collect::<Vec<_>>();
+        let levels = LintLevels::new(&changes)?;
+        let diagnostics = lint(&project, &levels);
+        Ok(diagnostics
+            .iter()
+            .map(|d| {
+                let (_, local) = project.sources.local(d.span);
+                format!(
+                    "{:?} {}:{} {}",
+                    d.severity,
+                    local.line + 1,
+                    local.col,
+                    d.message
+                )
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_applies_levels_and_allowances() {
+        assert_eq!(
+            diagnostics(SRC, &[]).unwrap(),
+            ["Warning 2:4 'unused' is declared, but never used. [unused-variable]"]
+        );
+        assert_eq!(
+            diagnostics(
+                SRC,
+                &[
+                    ("unused-variable", LintLevel::Allow),
+                    ("magic-number", LintLevel::Deny)
+                ]
+            )
+            .unwrap(),
+            ["Error 3:9 The number 7 has no name, declare it as a constant. [magic-number]"]
+        );
+        assert_eq!(
+            diagnostics(SRC, &[("naming", LintLevel::Deny)]),
+            Err("There is no lint 'naming'.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detached_actions_inherit_allowances() {
+        let src = "{attribute 'allow' := 'magic-number'}
+PROGRAM Main
+VAR x : INT; END_VAR
+x := x * 7;
+END_PROGRAM
+ACTION Main.Scale
+x := x * 3;
+END_ACTION
+PROGRAM Other
+VAR y : INT; END_VAR
+y := y * 5;
+END_PROGRAM
+ACTION Other.Scale
+y := y * 9;
+END_ACTION
+";
+        assert_eq!(
+            diagnostics(src, &[("magic-number", LintLevel::Warn)]).unwrap(),
+            [
+                "Warning 11:9 The number 5 has no name, declare it as a constant. [magic-number]",
+                "Warning 14:9 The number 9 has no name, declare it as a constant. [magic-number]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_unknown_allowances() {
+        let src = "{attribute 'allow' := 'recursion, naming'}\nPROGRAM Main END_PROGRAM
+{attribute 'allow'}\nPROGRAM Other END_PROGRAM";
+        assert_eq!(
+            diagnostics(src, &[]).unwrap(),
+            [
+                "Warning 1:0 There is no lint 'naming'.",
+                "Warning 3:0 Name the lints to allow, like {attribute 'allow' := 'magic-number'}."
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    lint::Linter,
+    parsing::ast::{Pou, VariableKind},
+    semantic::{
+        flow::{self, AccessKind, Graph, ReachingDefinitions},
+        symbols::{SymbolId, SymbolKind},
+        types::Type,
+    },
+};
+
+impl Linter<'_> {
+    /// Reports temporaries read before they are assigned, assignments that are never read, and
+    /// outputs of functions and methods that are not assigned on every path.
+    ///
+    /// Temporaries are the VAR_TEMP of every POU and the VAR of functions and methods, which
+    /// lose their values between calls, unless they are instances of function blocks.
+    pub(super) fn lint_dataflow(&mut self, pou: &Pou) {
+        let env = self.typer.env();
+        let Some(id) = env.binding(pou.name.span) else {
+            return;
+        };
+        let symbol = self.table.symbol(id);
+        let function = matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method);
+        let Some(members) = symbol.members else {
+            return;
+        };
+
+        let mut temporaries = Vec::new();
+        let mut outputs = Vec::new();
+        if function && symbol.data_type.is_some() {
+            outputs.push(id);
+        }
+        for variable in self.table.symbols_in(members) {
+            let symbol = self.table.symbol(variable);
+            let SymbolKind::Variable {
+                kind,
+                constant: false,
+            } = symbol.kind
+            else {
+                continue;
+            };
+            match kind {
+                VariableKind::Output if function => outputs.push(variable),
+                VariableKind::Temp | VariableKind::Local
+                    if (function || kind == VariableKind::Temp)
+                        && !self.located.contains(&symbol.span.pos)
+                        && !matches!(
+                            env.type_of_symbol(variable).dereferenced(),
+                            Type::FunctionBlock(_)
+                        ) =>
+                {
+                    temporaries.push(variable)
+                }
+                _ => {}
+            }
+        }
+        if temporaries.is_empty() && outputs.is_empty() {
+            return;
+        }
+
+        let graph = Graph::new(&pou.statements);
+        let accesses = graph
+            .nodes
+            .iter()
+            .map(|node| flow::accesses(node, env))
+            .collect::<Vec<_>>();
+        let tracked = temporaries
+            .iter()
+            .chain(&outputs)
+            .map(|v| (*v, self.table.symbol(*v).span))
+            .collect::<Vec<_>>();
+        let definitions = ReachingDefinitions::new(&graph, &accesses, &tracked);
+        // The definitions at the entry come first, in the order of the variables
+        let initial = |variable: SymbolId| tracked.iter().position(|(v, _)| *v == variable);
+
+        let mut findings = Vec::new();
+        for &variable in &temporaries {
+            if self.table.symbol(variable).value.is_some() {
+                continue;
+            }
+            let uninitialized = initial(variable);
+            let first = accesses
+                .iter()
+                .enumerate()
+                .flat_map(|(node, a)| a.iter().map(move |a| (node, a)))
+                .filter(|(node, a)| {
+                    a.symbol == variable
+                        && a.kind == AccessKind::Read
+                        && definitions.reaching[*node]
+                            .iter()
+                            .any(|d| Some(*d) == uninitialized)
+                })
+                .min_by_key(|(_, a)| a.span.pos);
+            if let Some((node, access)) = first {
+                let name = &self.table.symbol(variable).name;
+                let message = match definitions.of(node, variable).count() {
+                    1 => format!("'{name}' is read before it is assigned."),
+                    _ => format!("'{name}' is read before it is assigned on some paths."),
+                };
+                findings.push(("uninitialized-read", access.span, message));
+            }
+        }
+
+        for (index, definition) in definitions.definitions.iter().enumerate() {
+            let variable = definition.symbol;
+            if definition.node == Graph::ENTRY || definition.kind != AccessKind::Write {
+                continue;
+            }
+            let output = outputs.contains(&variable);
+            let reads = accesses
+                .iter()
+                .enumerate()
+                .flat_map(|(node, a)| a.iter().map(move |a| (node, a)))
+                .filter(|(_, a)| a.symbol == variable && a.kind == AccessKind::Read)
+                .collect::<Vec<_>>();
+            // Variables that are never read are write-only
+            if !output && reads.is_empty() {
+                continue;
+            }
+            let read = reads
+                .iter()
+                .any(|(node, _)| definitions.reaching[*node].contains(&index))
+                || output && definitions.reaching[Graph::EXIT].contains(&index);
+            if !read {
+                let name = &self.table.symbol(variable).name;
+                let message = format!("The value assigned to '{name}' is never read.");
+                findings.push(("dead-store", definition.span, message));
+            }
+        }
+
+        for &variable in &outputs {
+            let uninitialized = initial(variable);
+            if !definitions.reaching[Graph::EXIT]
+                .iter()
+                .any(|d| Some(*d) == uninitialized)
+            {
+                continue;
+            }
+            let symbol = self.table.symbol(variable);
+            let assigned = definitions
+                .definitions
+                .iter()
+                .any(|d| d.symbol == variable && d.node != Graph::ENTRY);
+            let (what, span) = match variable == id {
+                true => (format!("The result of '{}'", symbol.name), pou.name.span),
+                false => (format!("The output '{}'", symbol.name), symbol.span),
+            };
+            let message = match assigned {
+                true => format!("{what} is not assigned on every path."),
+                false => format!("{what} is never assigned."),
+            };
+            findings.push(("unassigned-output", span, message));
+        }
+
+        for (lint, span, message) in findings {
+            self.report(lint, span, message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    const SRC: &str = "FUNCTION Scale : INT
+VAR_INPUT x : INT; END_VAR
+VAR_OUTPUT clipped : BOOL; END_VAR
+VAR factor, offset : INT; bias : INT := 1; END_VAR
+IF x > 0 THEN factor := 2; END_IF;
+Scale := x * factor + offset;
+offset := bias;
+IF Scale > 100 THEN Scale := 100; clipped := TRUE; RETURN; END_IF;
+Scale := Scale + offset;
+END_FUNCTION
+FUNCTION_BLOCK Counter
+VAR_TEMP step : INT; END_VAR
+VAR count : INT; END_VAR
+VAR_OUTPUT done : BOOL; END_VAR
+step := 1;
+step := 2;
+count := count + step;
+END_FUNCTION_BLOCK
+FUNCTION Nothing : BOOL
+VAR_OUTPUT q : INT; END_VAR
+END_FUNCTION";
+
+    #[test]
+    fn test_reports_reads_before_writes() {
+        assert_eq!(
+            linted(SRC, "uninitialized-read"),
+            [
+                "'factor' is read before it is assigned on some paths. [uninitialized-read]",
+                "'offset' is read before it is assigned. [uninitialized-read]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_dead_stores_and_unassigned_outputs() {
+        assert_eq!(
+            linted(SRC, "dead-store"),
+            ["The value assigned to 'step' is never read. [dead-store]"]
+        );
+        assert_eq!(
+            linted(SRC, "unassigned-output"),
+            [
+                "The output 'clipped' is not assigned on every path. [unassigned-output]",
+                "The result of 'Nothing' is never assigned. [unassigned-output]",
+                "The output 'q' is never assigned. [unassigned-output]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    lint::Linter,
+    parsing::{
+        ast::{Argument, Expression, InfixOperator, LiteralExpression},
+        token::NumberValue,
+    },
+    semantic::types::Type,
+};
+
+impl Linter<'_> {
+    /// Lints an expression and the expressions within it, `real` if its value is used as a real
+    /// number.
+    pub(super) fn lint_expression(&mut self, expression: &Expression, real: bool) {
+        match expression {
+            Expression::Literal(LiteralExpression::Number(value), span) => {
+                let named = match value {
+                    NumberValue::Int(n) => *n <= 1,
+                    NumberValue::Float(f) => *f == 0.0 || *f == 1.0,
+                };
+                if !named {
+                    let text = match value {
+                        NumberValue::Int(n) => n.to_string(),
+                        NumberValue::Float(f) => format!("{f:?}"),
+                    };
+                    self.report(
+                        "magic-number",
+                        *span,
+                        format!("The number {text} has no name, declare it as a constant."),
+                    );
+                }
+            }
+            Expression::Literal(..) | Expression::Identifier(_) => {}
+            Expression::Prefix(x) => self.lint_expression(&x.operand, real),
+            Expression::Infix(x) => {
+                let (left, right) = (self.typer.type_of(&x.left), self.typer.type_of(&x.right));
+                let arithmetic = !x.op.is_comparison() && !x.op.is_logical();
+                match x.op {
+                    InfixOperator::Equals | InfixOperator::NotEquals
+                        if is_real(&left) || is_real(&right) =>
+                    {
+                        self.report(
+                            "float-equality",
+                            expression.span(),
+                            "Rounding makes real numbers unequal that should be equal, compare their difference with a tolerance.".to_string(),
+                        );
+                    }
+                    InfixOperator::Division if is_integer(&left) && is_integer(&right) => {
+                        self.check_division(expression, &x.left, &x.right, real);
+                    }
+                    _ => {}
+                }
+                // Operands are converted to the type of the other one before they are combined
+                let real = (arithmetic || x.op.is_comparison())
+                    && (is_real(&left) || is_real(&right))
+                    || arithmetic && real;
+                self.lint_expression(&x.left, real);
+                self.lint_expression(&x.right, real);
+            }
+            Expression::Member(x) => self.lint_expression(&x.target, false),
+            Expression::Index(x) => {
+                self.lint_expression(&x.target, false);
+                for index in &x.indices {
+                    self.lint_expression(index, false);
+                }
+            }
+            Expression::Call(x) => {
+                self.record_call(x);
+                self.lint_expression(&x.callee, false);
+                for argument in &x.arguments {
+                    if let Argument::Output(_, target) = argument {
+                        self.write(target);
+                    }
+                    self.lint_expression(argument.value(), false);
+                }
+            }
+            Expression::Deref(target, _) => self.lint_expression(target, false),
+            Expression::TypedLiteral(x) => self.lint_expression(&x.value, false),
+            Expression::Array(elements, _) => {
+                for element in elements {
+                    self.lint_expression(element, false);
+                }
+            }
+            Expression::Struct(fields, _) => {
+                for (_, value) in fields {
+                    self.lint_expression(value, false);
+                }
+            }
+        }
+    }
+
+    /// Whether the value of an expression is a real number.
+    pub(super) fn is_real(&mut self, expression: &Expression) -> bool {
+        is_real(&self.typer.type_of(expression))
+    }
+
+    /// Reports an integer division whose result is used as a real number, or whose literals do
+    /// not divide evenly.
+    fn check_division(
+        &mut self,
+        division: &Expression,
+        left: &Expression,
+        right: &Expression,
+        real: bool,
+    ) {
+        let literal = |e: &Expression| match e {
+            Expression::Literal(LiteralExpression::Number(NumberValue::Int(n)), _) => Some(*n),
+            _ => None,
+        };
+        let message = match (literal(left), literal(right)) {
+            (Some(l), Some(r)) if r != 0 && l % r != 0 => {
+                format!("{l} / {r} is truncated to {}.", l / r)
+            }
+            _ if real => {
+                "The integer division is truncated before its result becomes a real number."
+                    .to_string()
+            }
+            _ => return,
+        };
+        self.report("integer-division", division.span(), message);
+    }
+}
+
+fn is_real(ty: &Type) -> bool {
+    match ty.dereferenced() {
+        Type::RealLiteral => true,
+        t => t.elementary().is_some_and(|e| e.is_real()),
+    }
+}
+
+fn is_integer(ty: &Type) -> bool {
+    match ty.dereferenced() {
+        Type::IntegerLiteral => true,
+        t => t.elementary().is_some_and(|e| e.is_integer()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    const SRC: &str = "PROGRAM Main
+VAR n : INT; r : REAL; a : ARRAY[0..9] OF INT; END_VAR
+n := 7 / 2 + n / 2;
+r := n / 2;
+r := n / 2 * 2.5;
+r := REAL#1.0 * n / 2.0;
+IF r = 0.5 OR n = 3 OR r > 0.5 THEN a[5] := -1; END_IF;
+CASE n OF 2: n := 0; END_CASE;
+END_PROGRAM";
+
+    #[test]
+    fn test_reports_real_comparisons_and_truncation() {
+        assert_eq!(
+            linted(SRC, "float-equality"),
+            [
+                "Rounding makes real numbers unequal that should be equal, compare their difference with a tolerance. [float-equality]"
+            ]
+        );
+        assert_eq!(
+            linted(SRC, "integer-division"),
+            [
+                "7 / 2 is truncated to 3. [integer-division]",
+                "The integer division is truncated before its result becomes a real number. [integer-division]",
+                "The integer division is truncated before its result becomes a real number. [integer-division]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_numbers_without_names() {
+        assert_eq!(
+            linted(SRC, "magic-number"),
+            [
+                "The number 7 has no name, declare it as a constant. [magic-number]",
+                "The number 2 has no name, declare it as a constant. [magic-number]",
+                "The number 2 has no name, declare it as a constant. [magic-number]",
+                "The number 2 has no name, declare it as a constant. [magic-number]",
+                "The number 2 has no name, declare it as a constant. [magic-number]",
+                "The number 2.5 has no name, declare it as a constant. [magic-number]",
+                "The number 2.0 has no name, declare it as a constant. [magic-number]",
+                "The number 0.5 has no name, declare it as a constant. [magic-number]",
+                "The number 3 has no name, declare it as a constant. [magic-number]",
+                "The number 0.5 has no name, declare it as a constant. [magic-number]",
+                "The number 5 has no name, declare it as a constant. [magic-number]",
+            ]
+        );
+    }
+}
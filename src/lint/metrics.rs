@@ -0,0 +1,581 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    formats::json::Json,
+    parsing::{
+        ast::{Block, Identifier, Pou, Span, Statement, Statements, VariableBlock, VariableKind},
+        lexer::Lexer,
+        token::Token,
+    },
+    project::Project,
+    semantic::{
+        flow::{self, Graph},
+        symbols::SymbolId,
+        types::TypeEnv,
+    },
+};
+
+/// Halstead's counts of the distinct and total operators and operands of some code.
+///
+/// Identifiers and literals are operands, every other token is an operator.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Halstead {
+    pub operators: usize,
+    pub operands: usize,
+    pub total_operators: usize,
+    pub total_operands: usize,
+}
+
+impl Halstead {
+    pub fn vocabulary(&self) -> usize {
+        self.operators + self.operands
+    }
+
+    pub fn length(&self) -> usize {
+        self.total_operators + self.total_operands
+    }
+
+    pub fn volume(&self) -> f64 {
+        match self.vocabulary() {
+            0 => 0.0,
+            n => self.length() as f64 * (n as f64).log2(),
+        }
+    }
+
+    pub fn difficulty(&self) -> f64 {
+        match self.operands {
+            0 => 0.0,
+            n => self.operators as f64 / 2.0 * self.total_operands as f64 / n as f64,
+        }
+    }
+
+    pub fn effort(&self) -> f64 {
+        self.difficulty() * self.volume()
+    }
+}
+
+/// Metrics of a POU, method or action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metrics {
+    pub name: String,
+    /// PROGRAM, FUNCTION, FUNCTION_BLOCK, METHOD or ACTION
+    pub kind: &'static str,
+    /// One more than the number of conditions of IF and ELSIF, branches of CASE, and loops
+    pub complexity: usize,
+    /// Lines with code, not counting blank lines, comments and methods and actions within
+    pub lines: usize,
+    pub statements: usize,
+    /// Deepest nesting of control statements
+    pub nesting: usize,
+    /// Inputs and in-out variables
+    pub inputs: usize,
+    /// Outputs and in-out variables
+    pub outputs: usize,
+    pub halstead: Halstead,
+    /// Number of POUs, methods and actions calling this one
+    pub fan_in: usize,
+    /// Number of POUs, methods and actions this one calls
+    pub fan_out: usize,
+}
+
+/// Measures every POU, method and action of a project, in the order of the sources.
+pub fn measure(project: &Project) -> Vec<Metrics> {
+    let env = TypeEnv::new(&project.table);
+    let mut bodies = Vec::new();
+    collect(&project.ast.blocks, &mut bodies);
+
+    // Tokens of the code selected by the conditional compilation pragmas, by their positions
+    let mut tokens = Vec::new();
+    for (id, file) in project.sources.files() {
+        // The project only analyzes if the pragmas of every file apply
+        let Ok(text) = project.sources.active_text(id) else {
+            continue;
+        };
+        for marked in Lexer::create_at(&file.name, &text, file.origin.0, file.origin.1) {
+            let span = marked.span();
+            let (key, operand) = match marked.token {
+                Token::Comment(_) | Token::Illegal => continue,
+                Token::Identifier(name) => (name.to_ascii_lowercase(), true),
+                Token::Number(text, _)
+                | Token::String(text)
+                | Token::Time(text, _)
+                | Token::Date(text, _)
+                | Token::DirectAddress(text) => (text.to_string(), true),
+                Token::True | Token::False => (format!("{:?}", marked.token), true),
+                token => (format!("{token:?}"), false),
+            };
+            tokens.push((span, key, operand));
+        }
+    }
+
+    let ids = bodies
+        .iter()
+        .map(|b| env.binding(b.name.span))
+        .collect::<Vec<_>>();
+    let mut callees = HashMap::<SymbolId, HashSet<SymbolId>>::new();
+    for (body, id) in bodies.iter().zip(&ids) {
+        let Some(id) = *id else {
+            continue;
+        };
+        let mut called = HashSet::new();
+        for node in &Graph::new(body.statements).nodes {
+            for expression in node.expressions() {
+                flow::visit_calls(expression, &mut |call| {
+                    if let Some((callee, _)) = flow::callee(&env, call) {
+                        called.insert(callee);
+                    }
+                });
+            }
+        }
+        called.remove(&id);
+        callees.insert(id, called);
+    }
+
+    bodies
+        .iter()
+        .zip(&ids)
+        .map(|(body, id)| {
+            let within = |span: &Span| {
+                body.span.pos <= span.pos
+                    && span.pos < body.span.end()
+                    && !body
+                        .excluded
+                        .iter()
+                        .any(|s| s.pos <= span.pos && span.pos < s.end())
+            };
+            let mut lines = HashSet::new();
+            let mut operators = HashSet::new();
+            let mut operands = HashSet::new();
+            let mut halstead = Halstead::default();
+            for (span, key, operand) in tokens.iter().filter(|(span, ..)| within(span)) {
+                lines.insert(span.line);
+                match operand {
+                    true => {
+                        operands.insert(key);
+                        halstead.total_operands += 1;
+                    }
+                    false => {
+                        operators.insert(key);
+                        halstead.total_operators += 1;
+                    }
+                }
+            }
+            halstead.operators = operators.len();
+            halstead.operands = operands.len();
+
+            let count = |kinds: &[VariableKind]| {
+                body.variables
+                    .iter()
+                    .filter(|v| kinds.contains(&v.kind))
+                    .flat_map(|v| &v.declarations)
+                    .map(|d| d.names.len())
+                    .sum()
+            };
+            let (fan_in, fan_out) = match id {
+                Some(id) => (
+                    callees.values().filter(|c| c.contains(id)).count(),
+                    callees.get(id).map_or(0, HashSet::len),
+                ),
+                None => (0, 0),
+            };
+            Metrics {
+                name: id.map_or(body.name.name.clone(), |id| {
+                    project.table.qualified_name(id)
+                }),
+                kind: body.kind,
+                complexity: 1 + decisions(body.statements),
+                lines: lines.len(),
+                statements: statements(body.statements),
+                nesting: nesting(body.statements),
+                inputs: count(&[VariableKind::Input, VariableKind::InOut]),
+                outputs: count(&[VariableKind::Output, VariableKind::InOut]),
+                halstead,
+                fan_in,
+                fan_out,
+            }
+        })
+        .collect()
+}
+
+/// The metrics as a table with a row per POU, method and action.
+pub fn table(metrics: &[Metrics]) -> String {
+    let header = [
+        "POU",
+        "CC",
+        "LOC",
+        "Statements",
+        "Nesting",
+        "Inputs",
+        "Outputs",
+        "Volume",
+        "Difficulty",
+        "Effort",
+        "Fan-in",
+        "Fan-out",
+    ];
+    let rows = metrics.iter().map(|m| {
+        [
+            m.name.clone(),
+            m.complexity.to_string(),
+            m.lines.to_string(),
+            m.statements.to_string(),
+            m.nesting.to_string(),
+            m.inputs.to_string(),
+            m.outputs.to_string(),
+            format!("{:.1}", m.halstead.volume()),
+            format!("{:.1}", m.halstead.difficulty()),
+            format!("{:.0}", m.halstead.effort()),
+            m.fan_in.to_string(),
+            m.fan_out.to_string(),
+        ]
+    });
+    let rows = std::iter::once(header.map(String::from))
+        .chain(rows)
+        .collect::<Vec<_>>();
+    let widths = (0..header.len())
+        .map(|i| rows.iter().map(|r| r[i].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+    for row in rows {
+        let cells = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                // Names align left, numbers right
+                match i {
+                    0 => format!("{cell:<width$}"),
+                    _ => format!("{cell:>width$}"),
+                }
+            });
+        table += cells.collect::<Vec<_>>().join("  ").trim_end();
+        table.push('\n');
+    }
+    table
+}
+
+/// The metrics as a JSON array with an object per POU, method and action.
+pub fn to_json(metrics: &[Metrics]) -> Json {
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    let objects = metrics.iter().map(|m| {
+        let h = &m.halstead;
+        Json::object([
+            ("name", m.name.clone().into()),
+            ("kind", m.kind.into()),
+            ("complexity", m.complexity.into()),
+            ("lines", m.lines.into()),
+            ("statements", m.statements.into()),
+            ("nesting", m.nesting.into()),
+            ("inputs", m.inputs.into()),
+            ("outputs", m.outputs.into()),
+            (
+                "halstead",
+                Json::object([
+                    ("operators", h.operators.into()),
+                    ("operands", h.operands.into()),
+                    ("total_operators", h.total_operators.into()),
+                    ("total_operands", h.total_operands.into()),
+                    ("volume", round(h.volume()).into()),
+                    ("difficulty", round(h.difficulty()).into()),
+                    ("effort", round(h.effort()).into()),
+                ]),
+            ),
+            ("fan_in", m.fan_in.into()),
+            ("fan_out", m.fan_out.into()),
+        ])
+    });
+    Json::Array(objects.collect())
+}
+
+/// Code of a POU, method or action to measure.
+struct Body<'a> {
+    name: &'a Identifier,
+    kind: &'static str,
+    span: Span,
+    /// Methods and actions within the span, which are measured of their own
+    excluded: Vec<Span>,
+    variables: &'a [VariableBlock],
+    statements: &'a Statements,
+}
+
+fn collect<'a>(blocks: &'a [Block], bodies: &mut Vec<Body<'a>>) {
+    for block in blocks {
+        let (pou, kind) = match block {
+            Block::Program(pou) => (pou, "PROGRAM"),
+            Block::Function(pou) => (pou, "FUNCTION"),
+            Block::FunctionBlock(pou) => (pou, "FUNCTION_BLOCK"),
+            Block::Action(action) => {
+                bodies.push(Body {
+                    name: &action.name,
+                    kind: "ACTION",
+                    span: action.span,
+                    excluded: Vec::new(),
+                    variables: &[],
+                    statements: &action.statements,
+                });
+                continue;
+            }
+            Block::Namespace(namespace) => {
+                collect(&namespace.blocks, bodies);
+                continue;
+            }
+            Block::Type(_) | Block::GlobalVariables(_) | Block::Configuration(_) => continue,
+        };
+        collect_pou(pou, kind, bodies);
+    }
+}
+
+fn collect_pou<'a>(pou: &'a Pou, kind: &'static str, bodies: &mut Vec<Body<'a>>) {
+    let excluded = pou
+        .methods
+        .iter()
+        .map(|m| m.span)
+        .chain(pou.actions.iter().map(|a| a.span))
+        .collect();
+    bodies.push(Body {
+        name: &pou.name,
+        kind,
+        span: pou.span,
+        excluded,
+        variables: &pou.variables,
+        statements: &pou.statements,
+    });
+    for method in &pou.methods {
+        collect_pou(method, "METHOD", bodies);
+    }
+    for action in &pou.actions {
+        bodies.push(Body {
+            name: &action.name,
+            kind: "ACTION",
+            span: action.span,
+            excluded: Vec::new(),
+            variables: &[],
+            statements: &action.statements,
+        });
+    }
+}
+
+/// Number of decisions: conditions of IF and ELSIF, branches of CASE, and loops.
+fn decisions(statements: &Statements) -> usize {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::If(x) => {
+                std::iter::once(&x.branch)
+                    .chain(&x.alt_branches)
+                    .map(|b| 1 + decisions(&b.statements))
+                    .sum::<usize>()
+                    + x.fallback.as_ref().map_or(0, decisions)
+            }
+            Statement::Case(x) => {
+                x.branches
+                    .iter()
+                    .map(|b| 1 + decisions(&b.statements))
+                    .sum::<usize>()
+                    + x.fallback.as_ref().map_or(0, decisions)
+            }
+            Statement::For(x) => 1 + decisions(&x.statements),
+            Statement::While(x) => 1 + decisions(&x.statements),
+            Statement::Repeat(x) => 1 + decisions(&x.statements),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Number of statements, counting control statements and the statements within them.
+fn statements(statements: &Statements) -> usize {
+    nested(statements)
+        .iter()
+        .map(|(_, inner)| 1 + inner.iter().map(|s| self::statements(s)).sum::<usize>())
+        .sum()
+}
+
+/// Deepest nesting of control statements.
+fn nesting(statements: &Statements) -> usize {
+    nested(statements)
+        .iter()
+        .filter(|(control, _)| *control)
+        .map(|(_, inner)| 1 + inner.iter().map(|s| nesting(s)).max().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether each statement that is not empty controls others, and the statement lists in it.
+fn nested(statements: &Statements) -> Vec<(bool, Vec<&Statements>)> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Empty(_) => None,
+            Statement::If(x) => {
+                let branches = std::iter::once(&x.branch).chain(&x.alt_branches);
+                Some((
+                    true,
+                    branches
+                        .map(|b| &b.statements)
+                        .chain(x.fallback.as_ref())
+                        .collect(),
+                ))
+            }
+            Statement::Case(x) => Some((
+                true,
+                x.branches
+                    .iter()
+                    .map(|b| &b.statements)
+                    .chain(x.fallback.as_ref())
+                    .collect(),
+            )),
+            Statement::For(x) => Some((true, vec![&x.statements])),
+            Statement::While(x) => Some((true, vec![&x.statements])),
+            Statement::Repeat(x) => Some((true, vec![&x.statements])),
+            _ => Some((false, Vec::new())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::SourceDb;
+
+    const SRC: &str = "FUNCTION Clamp : INT
+VAR_INPUT x, limit : INT; END_VAR
+(* Limits x *)
+IF x > limit THEN
+    Clamp := limit;
+ELSIF x < -limit THEN
+    Clamp := -limit;
+ELSE
+    Clamp := x;
+END_IF;
+END_FUNCTION
+FUNCTION_BLOCK Motor
+VAR_INPUT speed : INT; END_VAR
+VAR_OUTPUT done : BOOL; END_VAR
+METHOD Run
+FOR speed := 0 TO 3 DO
+    CASE speed OF 1: done := TRUE; 2: Stop(); END_CASE;
+END_FOR;
+END_METHOD
+ACTION Stop done := FALSE; END_ACTION
+speed := Clamp(speed, 10);
+END_FUNCTION_BLOCK
+PROGRAM Main
+VAR m : Motor; END_VAR
+m.Run();
+m.speed := Clamp(m.speed, 5);
+END_PROGRAM
+";
+
+    fn measured() -> Vec<Metrics> {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", SRC);
+        let project = Project::analyze(sources).unwrap();
+        assert!(!project.has_errors(), "{:?}", project.diagnostics);
+        measure(&project)
+    }
+
+    #[test]
+    fn test_measures_structure_and_calls() {
+        let metrics = measured();
+        let row = |m: &Metrics| {
+            (
+                m.name.clone(),
+                m.kind,
+                m.complexity,
+                m.lines,
+                m.statements,
+                m.nesting,
+                m.inputs,
+                m.outputs,
+                m.fan_in,
+                m.fan_out,
+            )
+        };
+        assert_eq!(
+            metrics.iter().map(row).collect::<Vec<_>>(),
+            [
+                ("Clamp".to_string(), "FUNCTION", 3, 10, 4, 1, 2, 0, 2, 0),
+                (
+                    "Motor".to_string(),
+                    "FUNCTION_BLOCK",
+                    1,
+                    5,
+                    1,
+                    0,
+                    1,
+                    1,
+                    0,
+                    1
+                ),
+                ("Motor.Run".to_string(), "METHOD", 4, 5, 4, 2, 0, 0, 1, 1),
+                ("Motor.Stop".to_string(), "ACTION", 1, 1, 1, 0, 0, 0, 1, 0),
+                ("Main".to_string(), "PROGRAM", 1, 5, 2, 0, 0, 0, 0, 2),
+            ]
+        );
+        let clamp = metrics[0].halstead;
+        assert_eq!((clamp.operands, clamp.total_operands), (4, 15));
+        assert!(clamp.volume() > 0.0 && clamp.effort() > clamp.volume());
+    }
+
+    #[test]
+    fn test_counts_only_active_code() {
+        let measure_src = |src: &str| {
+            let mut sources = SourceDb::new();
+            sources.add("main.st", src);
+            let project = Project::analyze(sources).unwrap();
+            assert!(!project.has_errors(), "{:?}", project.diagnostics);
+            let main = measure(&project).remove(0);
+            (main.lines, main.halstead)
+        };
+        let conditional = measure_src(
+            "PROGRAM Main
+VAR x : INT; END_VAR
+{IF defined(SIM)}
+x := x * 2 + 1;
+{ELSE}
+x := 0;
+{END_IF}
+END_PROGRAM",
+        );
+        let active = measure_src(
+            "PROGRAM Main
+VAR x : INT; END_VAR
+
+x := 0;
+
+END_PROGRAM",
+        );
+        assert_eq!(conditional, active);
+    }
+
+    #[test]
+    fn test_formats_table_and_json() {
+        let metrics = measured();
+        let table = table(&metrics);
+        let mut lines = table.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "POU         CC  LOC  Statements  Nesting  Inputs  Outputs  Volume  Difficulty  Effort  Fan-in  Fan-out"
+            )
+        );
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("Clamp        3   10           4        1       2        0")
+        );
+        let json = to_json(&metrics);
+        let first = &json.as_array().unwrap()[0];
+        assert_eq!(first.get("name").and_then(Json::as_str), Some("Clamp"));
+        assert_eq!(
+            first
+                .get("halstead")
+                .and_then(|h| h.get("operands"))
+                .and_then(Json::as_i64),
+            Some(4)
+        );
+        assert_eq!(Json::parse(&json.to_string()), Ok(json));
+    }
+}
@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    lint::findings,
+    parsing::{
+        ast::{
+            Argument, Block, DataType, Expression, Identifier, Pou, Span, Statement, Statements,
+            VariableBlock, VariableKind,
+        },
+        token::Token,
+    },
+    project::{Project, manifest::Guidelines},
+    semantic::{
+        symbols::{SymbolId, SymbolKind},
+        types::TypeEnv,
+    },
+};
+
+/// A rule of the PLCopen Coding Guidelines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Guideline {
+    pub id: &'static str,
+    pub title: &'static str,
+}
+
+/// The guidelines that are checked, by the ids of the PLCopen Coding Guidelines 1.0.
+pub const GUIDELINES: &[Guideline] = &[
+    Guideline {
+        id: "N1",
+        title: "Names have the prefix of their kind of element.",
+    },
+    Guideline {
+        id: "CP8",
+        title: "Real numbers are not compared for equality or inequality.",
+    },
+    Guideline {
+        id: "CP9",
+        title: "The code of a POU is limited in size.",
+    },
+    Guideline {
+        id: "CP12",
+        title: "An output is written at one place only.",
+    },
+    Guideline {
+        id: "CP13",
+        title: "POUs do not call themselves, directly or indirectly.",
+    },
+    Guideline {
+        id: "CP20",
+        title: "The control flow does not jump out of loops and POUs.",
+    },
+    Guideline {
+        id: "C3",
+        title: "Every input of a POU is documented by a comment.",
+    },
+];
+
+/// Lints whose findings violate a guideline.
+const LINT_GUIDELINES: [(&str, &str); 2] = [("float-equality", "CP8"), ("recursion", "CP13")];
+
+/// Kinds of elements whose names have a prefix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Element {
+    Program,
+    Function,
+    FunctionBlock,
+    Method,
+    Struct,
+    Enum,
+    Global,
+    Input,
+    Output,
+    InOut,
+}
+
+impl Element {
+    const ALL: [Element; 10] = [
+        Element::Program,
+        Element::Function,
+        Element::FunctionBlock,
+        Element::Method,
+        Element::Struct,
+        Element::Enum,
+        Element::Global,
+        Element::Input,
+        Element::Output,
+        Element::InOut,
+    ];
+
+    /// Name of the kind in the `[plcopen]` table of a manifest.
+    pub fn name(self) -> &'static str {
+        match self {
+            Element::Program => "program",
+            Element::Function => "function",
+            Element::FunctionBlock => "function-block",
+            Element::Method => "method",
+            Element::Struct => "struct",
+            Element::Enum => "enum",
+            Element::Global => "global",
+            Element::Input => "input",
+            Element::Output => "output",
+            Element::InOut => "in-out",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Element::Program => "program",
+            Element::Function => "function",
+            Element::FunctionBlock => "function block",
+            Element::Method => "method",
+            Element::Struct => "structure",
+            Element::Enum => "enumeration",
+            Element::Global => "global variable",
+            Element::Input => "input",
+            Element::Output => "output",
+            Element::InOut => "in-out variable",
+        }
+    }
+}
+
+/// Prefixes and limits the guidelines leave to each project.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub prefixes: Vec<(Element, String)>,
+    pub max_statements: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let prefixes = [
+            (Element::FunctionBlock, "FB_"),
+            (Element::Function, "F_"),
+            (Element::Struct, "ST_"),
+            (Element::Enum, "E_"),
+            (Element::Global, "g"),
+            (Element::Input, "i"),
+            (Element::Output, "q"),
+            (Element::InOut, "iq"),
+        ];
+        Self {
+            prefixes: prefixes
+                .into_iter()
+                .map(|(e, p)| (e, p.to_string()))
+                .collect(),
+            max_statements: 100,
+        }
+    }
+}
+
+impl Settings {
+    /// The defaults as changed by the `[plcopen]` table of a manifest, where an empty prefix
+    /// requires none.
+    pub fn new(guidelines: &Guidelines) -> Result<Self, String> {
+        let mut settings = Self::default();
+        for (name, prefix) in &guidelines.prefixes {
+            let element = Element::ALL
+                .into_iter()
+                .find(|e| e.name() == name)
+                .ok_or_else(|| format!("There is no kind of element '{name}' to prefix."))?;
+            settings.prefixes.retain(|(e, _)| *e != element);
+            if !prefix.is_empty() {
+                settings.prefixes.push((element, prefix.clone()));
+            }
+        }
+        if let Some(max) = guidelines.max_statements {
+            settings.max_statements = max;
+        }
+        Ok(settings)
+    }
+
+    fn prefix(&self, element: Element) -> Option<&str> {
+        self.prefixes
+            .iter()
+            .find(|(e, _)| *e == element)
+            .map(|(_, p)| p.as_str())
+    }
+}
+
+/// The violations of the guidelines in a project.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// Warnings by the ids of the guidelines they violate, in the order of the sources
+    pub violations: Vec<(&'static str, Diagnostic)>,
+}
+
+impl Report {
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// A table of the guidelines with the number of their violations, and a verdict.
+    pub fn summary(&self) -> String {
+        let mut summary = "Guideline  Violations  Title\n".to_string();
+        let mut kept = 0;
+        for guideline in GUIDELINES {
+            let count = self
+                .violations
+                .iter()
+                .filter(|(id, _)| *id == guideline.id)
+                .count();
+            if count == 0 {
+                kept += 1;
+            }
+            summary += &format!("{:<10} {count:>10}  {}\n", guideline.id, guideline.title);
+        }
+        summary += &match self.is_compliant() {
+            true => "The project complies with all guidelines.".to_string(),
+            false => format!(
+                "The project complies with {kept} of {} guidelines.",
+                GUIDELINES.len()
+            ),
+        };
+        summary
+    }
+}
+
+/// Checks a project against the guidelines, each violation a warning ending with the id of its
+/// guideline.
+pub fn check(project: &Project, settings: &Settings) -> Report {
+    let mut checker = Checker {
+        project,
+        settings,
+        env: TypeEnv::new(&project.table),
+        violations: Vec::new(),
+    };
+    checker.check_blocks(&project.ast.blocks);
+    checker.check_located_outputs();
+    checker.check_documentation();
+    for (lint, diagnostic) in findings(project) {
+        if let Some((_, id)) = LINT_GUIDELINES.iter().find(|(l, _)| *l == lint) {
+            checker.violations.push((id, diagnostic));
+        }
+    }
+
+    let mut violations = checker
+        .violations
+        .into_iter()
+        .map(|(id, diagnostic)| {
+            let message = format!("{} [{id}]", diagnostic.message);
+            (
+                id,
+                Diagnostic {
+                    message,
+                    ..diagnostic
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    violations.sort_by_key(|(_, d)| d.span.pos);
+    Report { violations }
+}
+
+struct Checker<'a> {
+    project: &'a Project,
+    settings: &'a Settings,
+    env: TypeEnv<'a>,
+    violations: Vec<(&'static str, Diagnostic)>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, id: &'static str, diagnostic: Diagnostic) {
+        self.violations.push((id, diagnostic));
+    }
+
+    fn check_blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) => self.check_pou(pou, Element::Program),
+                Block::Function(pou) => self.check_pou(pou, Element::Function),
+                Block::FunctionBlock(pou) => self.check_pou(pou, Element::FunctionBlock),
+                Block::Action(action) => self.check_body(&action.name, &action.statements),
+                Block::Type(types) => {
+                    for declaration in types {
+                        match declaration.data_type {
+                            DataType::Struct(..) => {
+                                self.check_prefix(&declaration.name, Element::Struct)
+                            }
+                            DataType::Enum { .. } => {
+                                self.check_prefix(&declaration.name, Element::Enum)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Block::GlobalVariables(variables) => self.check_variables(variables),
+                Block::Namespace(namespace) => self.check_blocks(&namespace.blocks),
+                Block::Configuration(_) => {}
+            }
+        }
+    }
+
+    fn check_pou(&mut self, pou: &Pou, element: Element) {
+        self.check_prefix(&pou.name, element);
+        for variables in &pou.variables {
+            self.check_variables(variables);
+        }
+        for method in &pou.methods {
+            self.check_pou(method, Element::Method);
+        }
+        for action in &pou.actions {
+            self.check_body(&action.name, &action.statements);
+        }
+        self.check_body(&pou.name, &pou.statements);
+
+        // Actions run as part of their POU and write the same outputs
+        let bodies =
+            std::iter::once(&pou.statements).chain(pou.actions.iter().map(|a| &a.statements));
+        let mut writes = Vec::new();
+        for statements in bodies {
+            visit(statements, &mut |statement| {
+                collect_writes(statement, &mut writes)
+            });
+        }
+        let mut first = HashMap::<SymbolId, Span>::new();
+        for name in writes {
+            let Some(id) = self.env.binding(name.span) else {
+                continue;
+            };
+            let SymbolKind::Variable {
+                kind: VariableKind::Output,
+                ..
+            } = self.project.table.symbol(id).kind
+            else {
+                continue;
+            };
+            match first.get(&id) {
+                None => {
+                    first.insert(id, name.span);
+                }
+                Some(span) => self.report(
+                    "CP12",
+                    Diagnostic::warning(
+                        name.span,
+                        format!("The output '{}' is written at a second place.", name.name),
+                    )
+                    .with_note(*span, "First written here."),
+                ),
+            }
+        }
+    }
+
+    fn check_variables(&mut self, variables: &VariableBlock) {
+        let element = match variables.kind {
+            VariableKind::Global => Element::Global,
+            VariableKind::Input => Element::Input,
+            VariableKind::Output => Element::Output,
+            VariableKind::InOut => Element::InOut,
+            _ => return,
+        };
+        for declaration in &variables.declarations {
+            for name in &declaration.names {
+                self.check_prefix(name, element);
+            }
+        }
+    }
+
+    fn check_prefix(&mut self, name: &Identifier, element: Element) {
+        if let Some(prefix) = self.settings.prefix(element)
+            && !name.name.starts_with(prefix)
+        {
+            let message = format!(
+                "The {} '{}' does not start with '{prefix}'.",
+                element.describe(),
+                name.name
+            );
+            self.report("N1", Diagnostic::warning(name.span, message));
+        }
+    }
+
+    /// Checks the size of the statements of a POU, method or action, and their jumps.
+    fn check_body(&mut self, name: &Identifier, statements: &Statements) {
+        let mut count = 0;
+        let mut jumps = Vec::new();
+        visit(statements, &mut |statement| {
+            match statement {
+                Statement::Empty(_) => return,
+                Statement::Exit(span) => jumps.push((
+                    *span,
+                    "EXIT leaves a loop early, make it part of the loop condition.",
+                )),
+                Statement::Continue(span) => jumps.push((
+                    *span,
+                    "CONTINUE skips the rest of a loop, use an IF around it instead.",
+                )),
+                _ => {}
+            }
+            count += 1;
+        });
+        // A RETURN at the end leaves the POU where it ends anyway
+        let last = statements
+            .iter()
+            .rev()
+            .find(|s| !matches!(s, Statement::Empty(_)));
+        visit(statements, &mut |statement| {
+            if let Statement::Return(span) = statement
+                && !matches!(last, Some(Statement::Return(s)) if s.pos == span.pos)
+            {
+                jumps.push((
+                    *span,
+                    "RETURN leaves the POU early, use an IF around the rest instead.",
+                ));
+            }
+        });
+        for (span, message) in jumps {
+            self.report("CP20", Diagnostic::warning(span, message));
+        }
+
+        if count > self.settings.max_statements {
+            let message = format!(
+                "'{}' has {count} statements, more than {}.",
+                name.name, self.settings.max_statements
+            );
+            self.report("CP9", Diagnostic::warning(name.span, message));
+        }
+    }
+
+    /// Reports physical outputs, variables declared AT a %Q location, written at several places
+    /// of the project.
+    fn check_located_outputs(&mut self) {
+        let mut outputs = Vec::new();
+        located_outputs(&self.project.ast.blocks, &mut outputs);
+        let mut writes = Vec::new();
+        visit_bodies(&self.project.ast.blocks, &mut |statement| {
+            collect_writes(statement, &mut writes)
+        });
+        let mut first = HashMap::<SymbolId, Span>::new();
+        for name in writes {
+            let Some(id) = self.env.binding(name.span) else {
+                continue;
+            };
+            // Within a POU a VAR_EXTERNAL declaration stands for the global
+            let global = self
+                .project
+                .table
+                .references
+                .iter()
+                .find(|r| r.span.pos == self.project.table.symbol(id).span.pos)
+                .map_or(id, |r| r.symbol);
+            if !outputs.contains(&self.project.table.symbol(global).span.pos) {
+                continue;
+            }
+            match first.get(&global) {
+                None => {
+                    first.insert(global, name.span);
+                }
+                Some(span) if span.pos == name.span.pos => {}
+                Some(span) => self.report(
+                    "CP12",
+                    Diagnostic::warning(
+                        name.span,
+                        format!(
+                            "The physical output '{}' is written at a second place.",
+                            name.name
+                        ),
+                    )
+                    .with_note(*span, "First written here."),
+                ),
+            }
+        }
+    }
+
+    /// Reports inputs without a comment on their line or alone on the line before.
+    fn check_documentation(&mut self) {
+        let sources = &self.project.sources;
+        // Lines with a comment after the code on them, and lines of comments alone
+        let mut trailing = HashMap::<usize, usize>::new();
+        let mut alone = Vec::new();
+        for (id, _) in sources.files() {
+            let mut previous_line = None;
+            for marked in sources.lexer(id) {
+                let span = marked.span();
+                if let Token::Comment(text) = marked.token {
+                    let end = span.line + text.matches('\n').count();
+                    match previous_line == Some(span.line) {
+                        true => {
+                            trailing.entry(span.line).or_insert(span.pos);
+                        }
+                        false => alone.push(end),
+                    }
+                    previous_line = Some(end);
+                } else {
+                    previous_line = Some(span.line);
+                }
+            }
+        }
+
+        let mut inputs = Vec::new();
+        pou_inputs(&self.project.ast.blocks, &mut inputs);
+        for name in inputs {
+            let documented = trailing
+                .get(&name.span.line)
+                .is_some_and(|pos| *pos > name.span.pos)
+                || alone.contains(&(name.span.line.wrapping_sub(1)));
+            if !documented {
+                let message = format!("The input '{}' has no comment describing it.", name.name);
+                self.report("C3", Diagnostic::warning(name.span, message));
+            }
+        }
+    }
+}
+
+/// Calls the function with every statement, each before the statements within it.
+fn visit<'a>(statements: &'a Statements, f: &mut impl FnMut(&'a Statement)) {
+    for statement in statements {
+        f(statement);
+        match statement {
+            Statement::If(x) => {
+                for branch in std::iter::once(&x.branch).chain(&x.alt_branches) {
+                    visit(&branch.statements, f);
+                }
+                if let Some(fallback) = &x.fallback {
+                    visit(fallback, f);
+                }
+            }
+            Statement::Case(x) => {
+                for branch in &x.branches {
+                    visit(&branch.statements, f);
+                }
+                if let Some(fallback) = &x.fallback {
+                    visit(fallback, f);
+                }
+            }
+            Statement::For(x) => visit(&x.statements, f),
+            Statement::While(x) => visit(&x.statements, f),
+            Statement::Repeat(x) => visit(&x.statements, f),
+            _ => {}
+        }
+    }
+}
+
+/// Calls the function with every statement of the POUs, methods and actions of the blocks.
+fn visit_bodies<'a>(blocks: &'a [Block], f: &mut impl FnMut(&'a Statement)) {
+    fn pou<'a>(p: &'a Pou, f: &mut impl FnMut(&'a Statement)) {
+        visit(&p.statements, f);
+        for method in &p.methods {
+            pou(method, f);
+        }
+        for action in &p.actions {
+            visit(&action.statements, f);
+        }
+    }
+
+    for block in blocks {
+        match block {
+            Block::Program(p) | Block::Function(p) | Block::FunctionBlock(p) => pou(p, f),
+            Block::Action(action) => visit(&action.statements, f),
+            Block::Namespace(namespace) => visit_bodies(&namespace.blocks, f),
+            _ => {}
+        }
+    }
+}
+
+/// The names of the variables a statement writes: the first name of an assignment target and
+/// of the variables connected to outputs of a call, like `q` in `timer(Q => q)`.
+fn collect_writes<'a>(statement: &'a Statement, writes: &mut Vec<&'a Identifier>) {
+    fn root(target: &Expression) -> Option<&Identifier> {
+        match target {
+            Expression::Identifier(name) => Some(name),
+            Expression::Member(x) => root(&x.target),
+            Expression::Index(x) => root(&x.target),
+            _ => None,
+        }
+    }
+
+    fn calls<'a>(expression: &'a Expression, writes: &mut Vec<&'a Identifier>) {
+        if let Expression::Call(call) = expression {
+            for argument in &call.arguments {
+                if let Argument::Output(_, target) = argument {
+                    writes.extend(root(target));
+                }
+                calls(argument.value(), writes);
+            }
+        }
+    }
+
+    match statement {
+        Statement::Assignment(assignment) => {
+            writes.extend(root(&assignment.target));
+            calls(&assignment.value, writes);
+        }
+        Statement::Expression(expression) => calls(expression, writes),
+        _ => {}
+    }
+}
+
+/// Positions of the names of the variables declared AT a %Q location.
+fn located_outputs(blocks: &[Block], outputs: &mut Vec<usize>) {
+    fn add(variables: &VariableBlock, outputs: &mut Vec<usize>) {
+        let located = variables.declarations.iter().filter(|d| {
+            d.location
+                .as_ref()
+                .is_some_and(|l| l.name.to_ascii_uppercase().starts_with("%Q"))
+        });
+        outputs.extend(located.flat_map(|d| &d.names).map(|n| n.span.pos));
+    }
+
+    for block in blocks {
+        match block {
+            Block::Program(p) | Block::Function(p) | Block::FunctionBlock(p) => {
+                for variables in &p.variables {
+                    add(variables, outputs);
+                }
+            }
+            Block::GlobalVariables(variables) => add(variables, outputs),
+            Block::Namespace(namespace) => located_outputs(&namespace.blocks, outputs),
+            _ => {}
+        }
+    }
+}
+
+/// The names of the inputs of the POUs and methods of the blocks.
+fn pou_inputs<'a>(blocks: &'a [Block], inputs: &mut Vec<&'a Identifier>) {
+    fn pou<'a>(p: &'a Pou, inputs: &mut Vec<&'a Identifier>) {
+        let declarations = p
+            .variables
+            .iter()
+            .filter(|v| v.kind == VariableKind::Input)
+            .flat_map(|v| &v.declarations);
+        inputs.extend(declarations.flat_map(|d| &d.names));
+        for method in &p.methods {
+            pou(method, inputs);
+        }
+    }
+
+    for block in blocks {
+        match block {
+            Block::Program(p) | Block::Function(p) | Block::FunctionBlock(p) => pou(p, inputs),
+            Block::Namespace(namespace) => pou_inputs(&namespace.blocks, inputs),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::SourceDb;
+
+    fn checked(src: &str, settings: &Settings) -> Report {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", src);
+        let project = Project::analyze(sources).unwrap();
+        assert!(!project.has_errors(), "{:?}", project.diagnostics);
+        check(&project, settings)
+    }
+
+    fn messages(report: &Report) -> Vec<&str> {
+        report
+            .violations
+            .iter()
+            .map(|(_, d)| d.message.as_str())
+            .collect()
+    }
+
+    const SRC: &str = "TYPE ST_Pair : STRUCT a, b : INT; END_STRUCT END_TYPE
+TYPE Mode : (Off, Run); END_TYPE
+VAR_GLOBAL gLamp AT %QX0.0 : BOOL; END_VAR
+FUNCTION_BLOCK FB_Motor
+VAR_INPUT
+    iSpeed : INT; // set point
+    (* enables the motor *)
+    iOn : BOOL;
+    limit : INT;
+END_VAR
+VAR_OUTPUT qDone : BOOL; END_VAR
+VAR_EXTERNAL gLamp : BOOL; END_VAR
+VAR i : INT; END_VAR
+qDone := FALSE;
+FOR i := 0 TO iSpeed DO
+    IF i = limit THEN EXIT; END_IF;
+END_FOR;
+IF iOn THEN qDone := TRUE; RETURN; END_IF;
+gLamp := iOn;
+RETURN;
+END_FUNCTION_BLOCK
+PROGRAM Main
+VAR m : FB_Motor; END_VAR
+gLamp := FALSE;
+m(iSpeed := 1);
+END_PROGRAM
+";
+
+    #[test]
+    fn test_reports_violations_by_guideline() {
+        let report = checked(SRC, &Settings::default());
+        assert_eq!(
+            messages(&report),
+            [
+                "The enumeration 'Mode' does not start with 'E_'. [N1]",
+                "The input 'limit' does not start with 'i'. [N1]",
+                "The input 'limit' has no comment describing it. [C3]",
+                "EXIT leaves a loop early, make it part of the loop condition. [CP20]",
+                "The output 'qDone' is written at a second place. [CP12]",
+                "RETURN leaves the POU early, use an IF around the rest instead. [CP20]",
+                "The physical output 'gLamp' is written at a second place. [CP12]",
+            ]
+        );
+        let summary = report.summary();
+        assert!(
+            summary.contains("\nCP12                2  An output is written at one place only.\n"),
+            "{summary}"
+        );
+        assert!(summary.ends_with("The project complies with 3 of 7 guidelines."));
+    }
+
+    #[test]
+    fn test_applies_project_settings() {
+        let guidelines = Guidelines {
+            prefixes: vec![
+                ("enum".to_string(), String::new()),
+                ("input".to_string(), "in".to_string()),
+            ],
+            max_statements: Some(3),
+        };
+        let report = checked(SRC, &Settings::new(&guidelines).unwrap());
+        let messages = messages(&report);
+        assert!(messages.contains(&"The input 'iSpeed' does not start with 'in'. [N1]"));
+        assert!(messages.contains(&"'FB_Motor' has 9 statements, more than 3. [CP9]"));
+        assert!(!messages.iter().any(|m| m.contains("'Mode'")));
+
+        let unknown = Guidelines {
+            prefixes: vec![("variable".to_string(), "v".to_string())],
+            max_statements: None,
+        };
+        assert_eq!(
+            Settings::new(&unknown),
+            Err("There is no kind of element 'variable' to prefix.".to_string())
+        );
+        assert!(checked("PROGRAM Main END_PROGRAM", &Settings::default()).is_compliant());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    lint::{Linter, keyword},
+    parsing::ast::{Expression, ForLoop, IfCondition, Statement, Statements},
+    semantic::{
+        consteval::{self, ConstValue},
+        flow::{Graph, Node},
+    },
+};
+
+impl Linter<'_> {
+    /// Reports the first statement of every run of statements no path from the start of a body
+    /// leads to, like those following a RETURN.
+    pub(super) fn check_reachability(&mut self, statements: &Statements) {
+        let graph = Graph::new(statements);
+        let reachable = graph.reachable();
+        let predecessors = graph.predecessors();
+        for (node, kind) in graph.nodes.iter().enumerate() {
+            // Edges from later nodes lead back to the start of loops
+            if reachable[node] || predecessors[node].iter().any(|p| *p < node) {
+                continue;
+            }
+            let span = match kind {
+                Node::Entry | Node::Exit => continue,
+                Node::Statement(statement) => statement.span(),
+                Node::Condition(condition) => condition.span(),
+                Node::ForStart(x) | Node::ForStep(x) => keyword(x.span, "FOR"),
+            };
+            self.report(
+                "unreachable-code",
+                span,
+                "The code from here on is unreachable.".to_string(),
+            );
+        }
+    }
+
+    /// Reports branches of an IF whose conditions are always FALSE, or that follow one that is
+    /// always TRUE.
+    pub(super) fn check_constant_conditions(&mut self, condition: &IfCondition) {
+        let branches = std::iter::once(&condition.branch)
+            .chain(&condition.alt_branches)
+            .collect::<Vec<_>>();
+        for (index, branch) in branches.iter().enumerate() {
+            let span = branch.condition.span();
+            match self.constant(&branch.condition) {
+                Some(false) => self.report(
+                    "dead-branch",
+                    span,
+                    "The condition is always FALSE, so the branch never runs.".to_string(),
+                ),
+                Some(true) if index + 1 < branches.len() || condition.fallback.is_some() => {
+                    self.report(
+                        "dead-branch",
+                        span,
+                        "The condition is always TRUE, so the branches after it never run."
+                            .to_string(),
+                    );
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reports a WHILE loop that never runs, or never ends for lack of an EXIT or RETURN.
+    pub(super) fn check_while(&mut self, condition: &Expression, statements: &Statements) {
+        match self.constant(condition) {
+            Some(false) => self.report(
+                "dead-branch",
+                condition.span(),
+                "The condition is always FALSE, so the loop never runs.".to_string(),
+            ),
+            Some(true) if !leaves(statements) => self.report(
+                "infinite-loop",
+                condition.span(),
+                "The loop never ends, which hangs the scan cycle.".to_string(),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Reports a REPEAT loop that never ends for lack of an EXIT or RETURN.
+    pub(super) fn check_repeat(&mut self, condition: &Expression, statements: &Statements) {
+        if self.constant(condition) == Some(false) && !leaves(statements) {
+            self.report(
+                "infinite-loop",
+                condition.span(),
+                "The loop never ends, which hangs the scan cycle.".to_string(),
+            );
+        }
+    }
+
+    /// Reports a FOR loop whose constant bounds make it never run, or whose step is 0.
+    pub(super) fn check_for_bounds(&mut self, x: &ForLoop) {
+        let env = self.typer.env();
+        let step = match &x.step {
+            Some(step) => env.integer_value(step),
+            None => Some(1),
+        };
+        let span = keyword(x.span, "FOR");
+        if step == Some(0) && !leaves(&x.statements) {
+            self.report(
+                "infinite-loop",
+                span,
+                "The loop never ends, because its step is 0.".to_string(),
+            );
+            return;
+        }
+        let (Some(start), Some(end), Some(step)) =
+            (env.integer_value(&x.start), env.integer_value(&x.end), step)
+        else {
+            return;
+        };
+        if step > 0 && start > end || step < 0 && start < end {
+            self.report(
+                "dead-branch",
+                span,
+                format!("The loop never runs, because it counts from {start} away from {end}."),
+            );
+        }
+    }
+
+    /// The value of a condition known at compile time.
+    fn constant(&self, condition: &Expression) -> Option<bool> {
+        match consteval::evaluate(self.typer.env(), condition) {
+            Ok(ConstValue::Bool(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Whether statements of a loop body can leave it, by an EXIT of this loop or a RETURN.
+fn leaves(statements: &Statements) -> bool {
+    fn within(statements: &Statements, nested: bool) -> bool {
+        statements.iter().any(|statement| match statement {
+            Statement::Return(_) => true,
+            Statement::Exit(_) => !nested,
+            Statement::If(x) => {
+                std::iter::once(&x.branch)
+                    .chain(&x.alt_branches)
+                    .any(|b| within(&b.statements, nested))
+                    || x.fallback.as_ref().is_some_and(|f| within(f, nested))
+            }
+            Statement::Case(x) => {
+                x.branches.iter().any(|b| within(&b.statements, nested))
+                    || x.fallback.as_ref().is_some_and(|f| within(f, nested))
+            }
+            Statement::For(x) => within(&x.statements, true),
+            Statement::While(x) => within(&x.statements, true),
+            Statement::Repeat(x) => within(&x.statements, true),
+            _ => false,
+        })
+    }
+
+    within(statements, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    #[test]
+    fn test_reports_unreachable_code() {
+        let src = "PROGRAM Main
+VAR n : INT; END_VAR
+FOR n := 0 TO 9 DO
+    IF n > 5 THEN EXIT; n := 0; END_IF;
+    CONTINUE;
+    n := 1; n := 2;
+END_FOR;
+IF n > 0 THEN RETURN; ELSE RETURN; END_IF;
+WHILE n > 0 DO n := n - 1; END_WHILE;
+END_PROGRAM";
+        assert_eq!(
+            linted(src, "unreachable-code"),
+            [
+                "The code from here on is unreachable. [unreachable-code]",
+                "The code from here on is unreachable. [unreachable-code]",
+                "The code from here on is unreachable. [unreachable-code]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_constant_conditions_and_loops() {
+        let src = "PROGRAM Main
+VAR CONSTANT debug : BOOL := FALSE; last : INT := 3; END_VAR
+VAR n : INT; END_VAR
+IF debug THEN n := 1; ELSIF TRUE THEN n := 2; ELSE n := 3; END_IF;
+WHILE TRUE DO n := n + 1; END_WHILE;
+WHILE TRUE DO IF n > 9 THEN EXIT; END_IF; END_WHILE;
+WHILE NOT TRUE DO n := 0; END_WHILE;
+REPEAT FOR n := 0 TO 1 DO EXIT; END_FOR; UNTIL FALSE END_REPEAT;
+FOR n := last TO 0 DO n := 0; END_FOR;
+FOR n := 0 TO last BY -1 DO n := 0; END_FOR;
+FOR n := 0 TO last BY 0 DO n := 0; END_FOR;
+END_PROGRAM";
+        assert_eq!(
+            linted(src, "dead-branch"),
+            [
+                "The condition is always FALSE, so the branch never runs. [dead-branch]",
+                "The condition is always TRUE, so the branches after it never run. [dead-branch]",
+                "The condition is always FALSE, so the loop never runs. [dead-branch]",
+                "The loop never runs, because it counts from 3 away from 0. [dead-branch]",
+                "The loop never runs, because it counts from 0 away from 3. [dead-branch]",
+            ]
+        );
+        assert_eq!(
+            linted(src, "infinite-loop"),
+            [
+                "The loop never ends, which hangs the scan cycle. [infinite-loop]",
+                "The loop never ends, which hangs the scan cycle. [infinite-loop]",
+                "The loop never ends, because its step is 0. [infinite-loop]",
+            ]
+        );
+    }
+}
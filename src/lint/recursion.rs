@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{lint::Linter, parsing::ast::CallExpression, semantic::flow};
+
+impl Linter<'_> {
+    /// Records which POU, method or action a call in the current body runs.
+    pub(super) fn record_call(&mut self, call: &CallExpression) {
+        let Some(caller) = self.body else {
+            return;
+        };
+        let Some((callee, _)) = flow::callee(self.typer.env(), call) else {
+            return;
+        };
+        self.calls.push((caller, callee, call.callee.span()));
+    }
+
+    /// Reports every POU that calls itself, at its first call leading back to it.
+    pub(super) fn lint_recursion(&mut self) {
+        let mut callers = self
+            .calls
+            .iter()
+            .map(|(caller, ..)| *caller)
+            .collect::<Vec<_>>();
+        callers.sort();
+        callers.dedup();
+        for caller in callers {
+            // The shortest way back, found breadth first
+            let mut previous = HashMap::new();
+            let mut queue = VecDeque::from([caller]);
+            while let Some(current) = queue.pop_front() {
+                let callees = self.calls.iter().filter(|(c, ..)| *c == current);
+                for (_, callee, _) in callees {
+                    if !previous.contains_key(callee) {
+                        previous.insert(*callee, current);
+                        queue.push_back(*callee);
+                    }
+                }
+                if previous.contains_key(&caller) {
+                    break;
+                }
+            }
+            let Some(mut current) = previous.get(&caller).copied() else {
+                continue;
+            };
+            let mut path = Vec::new();
+            while current != caller {
+                path.push(current);
+                current = previous[&current];
+            }
+            path.reverse();
+
+            let first = path.first().copied().unwrap_or(caller);
+            let (.., span) = self
+                .calls
+                .iter()
+                .find(|(c, callee, _)| *c == caller && *callee == first)
+                .copied()
+                .unwrap();
+            let path = path
+                .into_iter()
+                .map(|id| self.table.qualified_name(id))
+                .collect::<Vec<_>>();
+            let name = self.table.qualified_name(caller);
+            let message = match path.is_empty() {
+                true => format!("'{name}' calls itself."),
+                false => format!("'{name}' calls itself through '{}'.", path.join("', '")),
+            };
+            self.report("recursion", span, message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    #[test]
+    fn test_reports_calls_back_to_the_caller() {
+        let src = "FUNCTION Fact : INT
+VAR_INPUT n : INT; END_VAR
+IF n > 1 THEN Fact := n * Fact(n - 1); ELSE Fact := 1; END_IF;
+END_FUNCTION
+FUNCTION_BLOCK Motor
+METHOD Start Step(); END_METHOD
+METHOD Step Start(); END_METHOD
+END_FUNCTION_BLOCK
+FUNCTION Twice : INT VAR_INPUT n : INT; END_VAR Twice := Fact(n) * 2; END_FUNCTION";
+        assert_eq!(
+            linted(src, "recursion"),
+            [
+                "'Fact' calls itself. [recursion]",
+                "'Motor.Start' calls itself through 'Motor.Step'. [recursion]",
+                "'Motor.Step' calls itself through 'Motor.Start'. [recursion]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follows_function_block_instances() {
+        let src = "FUNCTION_BLOCK Outer
+VAR next : Inner; END_VAR
+next();
+END_FUNCTION_BLOCK
+FUNCTION_BLOCK Inner
+METHOD Run
+VAR back : Outer; END_VAR
+back();
+END_METHOD
+Run();
+END_FUNCTION_BLOCK";
+        assert_eq!(
+            linted(src, "recursion"),
+            [
+                "'Outer' calls itself through 'Inner', 'Inner.Run'. [recursion]",
+                "'Inner.Run' calls itself through 'Outer', 'Inner'. [recursion]",
+                "'Inner' calls itself through 'Inner.Run', 'Outer'. [recursion]"
+            ]
+        );
+        let src = src.replace("back();", "");
+        assert_eq!(linted(&src, "recursion"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    lint::{Linter, MAX_NESTING},
+    parsing::ast::{CaseLabel, CaseStatement, Expression, IfCondition, Span, Statement},
+    semantic::{symbols::SymbolKind, types::Type},
+};
+
+impl Linter<'_> {
+    /// Reports a control statement within `depth` others that is nested too deeply, but none
+    /// within it again.
+    pub(super) fn check_nesting(&mut self, span: Span, depth: usize) {
+        if depth == MAX_NESTING {
+            self.report(
+                "deep-nesting",
+                span,
+                format!(
+                    "The statement is nested {} levels deep, move parts of it into an action or method.",
+                    depth + 1
+                ),
+            );
+        }
+    }
+
+    /// Reports branches of an IF without statements, or only empty ones like a lone `;`.
+    pub(super) fn check_empty_branches(&mut self, condition: &IfCondition) {
+        let empty =
+            |statements: &[Statement]| statements.iter().all(|s| matches!(s, Statement::Empty(_)));
+        for branch in std::iter::once(&condition.branch).chain(&condition.alt_branches) {
+            if empty(&branch.statements) {
+                self.report(
+                    "empty-branch",
+                    branch.condition.span(),
+                    "Nothing happens if the condition holds.".to_string(),
+                );
+            }
+        }
+        if condition.fallback.as_deref().is_some_and(empty) {
+            self.report(
+                "empty-branch",
+                condition.span,
+                "The ELSE branch of the IF does nothing.".to_string(),
+            );
+        }
+    }
+
+    /// Reports a CASE over an enum without ELSE that has no branch for some values.
+    pub(super) fn check_case_values(&mut self, case: &CaseStatement) {
+        if case.fallback.is_some() {
+            return;
+        }
+        let selector = self.typer.type_of(&case.selector);
+        let Type::Enum(values) = selector.dereferenced() else {
+            return;
+        };
+        let env = self.typer.env();
+        let handled = case
+            .branches
+            .iter()
+            .flat_map(|b| &b.labels)
+            .filter_map(|label| match label {
+                CaseLabel::Value(value) => {
+                    let name = match value {
+                        Expression::Identifier(name) => name,
+                        Expression::Member(x) => &x.member,
+                        Expression::TypedLiteral(x) => match x.value.as_ref() {
+                            Expression::Identifier(name) => name,
+                            _ => return None,
+                        },
+                        _ => return None,
+                    };
+                    env.binding(name.span)
+                }
+                CaseLabel::Range(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let missing = self
+            .table
+            .symbols_in(*values)
+            .into_iter()
+            .filter(|v| self.table.symbol(*v).kind == SymbolKind::EnumValue)
+            .filter(|v| !handled.contains(v))
+            .map(|v| self.table.symbol(v).name.clone())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let message = format!(
+                "The CASE over {} has no ELSE and no branch for {}.",
+                selector.display(self.table),
+                missing.join(", ")
+            );
+            self.report("case-without-else", case.selector.span(), message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    #[test]
+    fn test_reports_empty_branches_and_missing_values() {
+        let src = "TYPE Mode : (Off, Run, Fault); END_TYPE
+PROGRAM Main
+VAR m : Mode; n : INT; END_VAR
+IF n > 0 THEN ; ELSIF n < 0 THEN n := 0; ELSE END_IF;
+CASE m OF Off: n := 0; Mode#Run: n := 1; END_CASE;
+CASE m OF Off, Run, Fault: n := 0; END_CASE;
+CASE m OF Off: n := 0; ELSE n := 1; END_CASE;
+END_PROGRAM";
+        assert_eq!(
+            linted(src, "empty-branch"),
+            [
+                "The ELSE branch of the IF does nothing. [empty-branch]",
+                "Nothing happens if the condition holds. [empty-branch]"
+            ]
+        );
+        assert_eq!(
+            linted(src, "case-without-else"),
+            ["The CASE over Mode has no ELSE and no branch for Fault. [case-without-else]"]
+        );
+    }
+
+    #[test]
+    fn test_reports_deep_nesting_once() {
+        let src = "PROGRAM Main
+VAR n : INT; END_VAR
+IF n > 0 THEN
+    FOR n := 0 TO 1 DO
+        WHILE n > 0 DO
+            REPEAT
+                IF n = 0 THEN
+                    IF n = 1 THEN n := 0; END_IF;
+                END_IF;
+            UNTIL TRUE END_REPEAT;
+        END_WHILE;
+    END_FOR;
+END_IF;
+END_PROGRAM";
+        assert_eq!(
+            linted(src, "deep-nesting"),
+            [
+                "The statement is nested 5 levels deep, move parts of it into an action or method. [deep-nesting]"
+            ]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{Block, Pou, Span, Statements, VariableBlock, VariableKind},
+    project::Project,
+    runtime::scheduler::Configuration,
+    semantic::{
+        flow::{self, AccessKind, Graph},
+        symbols::{SymbolId, SymbolKind},
+        types::{Elementary, Type, TypeEnv},
+    },
+};
+
+/// Memory that programs of several tasks can access: a global variable or an I/O address,
+/// which variables declared AT the same location share.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Shared {
+    Global(SymbolId),
+    Address(String),
+}
+
+/// The shared variables and I/O the programs of a task read and write, directly or through the
+/// POUs, methods and actions they call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskAccesses {
+    pub task: String,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+/// The accesses of every task and the conflicts between them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskAnalysis {
+    pub tasks: Vec<TaskAccesses>,
+    /// Warnings about variables written by several tasks, or read by one while another writes
+    /// them in more than one step
+    pub conflicts: Vec<Diagnostic>,
+}
+
+impl TaskAnalysis {
+    /// The read and write sets of the tasks, one task after another.
+    pub fn report(&self) -> String {
+        let list = |names: &[String]| match names.is_empty() {
+            true => "-".to_string(),
+            false => names.join(", "),
+        };
+        self.tasks
+            .iter()
+            .map(|t| {
+                format!(
+                    "Task {}\n    reads   {}\n    writes  {}\n",
+                    t.task,
+                    list(&t.reads),
+                    list(&t.writes)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Analyses which tasks of a configuration access the same global variables and I/O.
+pub fn analyze(project: &Project, config: &Configuration) -> Result<TaskAnalysis, String> {
+    let table = &project.table;
+    let env = TypeEnv::new(table);
+    let mut analyzer = Analyzer {
+        env: &env,
+        locations: HashMap::new(),
+        bodies: HashMap::new(),
+        accesses: HashMap::new(),
+        calls: HashMap::new(),
+    };
+    analyzer.collect(&project.ast.blocks);
+    let bodies = analyzer.bodies.clone();
+    for (id, statements) in bodies {
+        analyzer.analyze_body(id, statements);
+    }
+
+    // The first read and write of every shared variable by each task
+    let mut by_task = Vec::new();
+    for task in &config.tasks {
+        let mut queue = VecDeque::new();
+        for program in &task.programs {
+            let id = (0..table.symbols.len())
+                .find(|id| {
+                    table.symbol(*id).kind == SymbolKind::Program
+                        && table.qualified_name(*id).eq_ignore_ascii_case(program)
+                })
+                .ok_or_else(|| {
+                    format!("There is no program '{program}' for task '{}'.", task.name)
+                })?;
+            queue.push_back(id);
+        }
+        let mut visited = Vec::new();
+        let mut reads = BTreeMap::<Shared, Span>::new();
+        let mut writes = BTreeMap::<Shared, Span>::new();
+        while let Some(body) = queue.pop_front() {
+            if visited.contains(&body) {
+                continue;
+            }
+            visited.push(body);
+            for (shared, kind, span) in analyzer.accesses.get(&body).into_iter().flatten() {
+                let set = match kind {
+                    AccessKind::Read => &mut reads,
+                    _ => &mut writes,
+                };
+                set.entry(shared.clone()).or_insert(*span);
+            }
+            queue.extend(analyzer.calls.get(&body).into_iter().flatten());
+        }
+        by_task.push((task.name.as_str(), reads, writes));
+    }
+
+    let name = |shared: &Shared| match shared {
+        Shared::Global(id) => table.qualified_name(*id),
+        Shared::Address(address) => address.clone(),
+    };
+    let tasks = by_task
+        .iter()
+        .map(|(task, reads, writes)| TaskAccesses {
+            task: task.to_string(),
+            reads: reads.keys().map(name).collect(),
+            writes: writes.keys().map(name).collect(),
+        })
+        .collect();
+
+    let mut shared = by_task
+        .iter()
+        .flat_map(|(_, reads, writes)| reads.keys().chain(writes.keys()))
+        .collect::<Vec<_>>();
+    shared.sort();
+    shared.dedup();
+    let mut conflicts = Vec::new();
+    for variable in shared {
+        let writers = by_task
+            .iter()
+            .filter_map(|(task, _, writes)| writes.get(variable).map(|span| (*task, *span)))
+            .collect::<Vec<_>>();
+        let readers = by_task
+            .iter()
+            .filter(|(task, reads, _)| reads.contains_key(variable) && writers[0].0 != *task)
+            .map(|(task, ..)| *task);
+        match writers.as_slice() {
+            [] => {}
+            [(_, span), others @ ..] if !others.is_empty() => {
+                let tasks = writers
+                    .iter()
+                    .map(|(task, _)| format!("'{task}'"))
+                    .collect::<Vec<_>>();
+                let message = format!(
+                    "'{}' is written by the tasks {}, which may interrupt each other.",
+                    name(variable),
+                    join(&tasks)
+                );
+                let mut diagnostic = Diagnostic::warning(*span, message);
+                for (task, span) in others {
+                    diagnostic =
+                        diagnostic.with_note(*span, format!("Task '{task}' writes it here."));
+                }
+                conflicts.push(diagnostic);
+            }
+            [(writer, span)] => {
+                let ty = analyzer.type_of(variable);
+                let readers = readers.map(|t| format!("'{t}'")).collect::<Vec<_>>();
+                if readers.is_empty() || is_atomic(&ty) {
+                    continue;
+                }
+                let message = format!(
+                    "'{}' is written by task '{writer}' and read by {} {}, which may see a half-written {}.",
+                    name(variable),
+                    match readers.len() {
+                        1 => "task",
+                        _ => "the tasks",
+                    },
+                    join(&readers),
+                    ty.display(table)
+                );
+                conflicts.push(Diagnostic::warning(*span, message));
+            }
+            _ => {}
+        }
+    }
+    Ok(TaskAnalysis { tasks, conflicts })
+}
+
+/// Joins names like "'A', 'B' and 'C'".
+fn join(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
+/// Whether a value of a type is read and written in one step on a 32-bit target.
+fn is_atomic(ty: &Type) -> bool {
+    let elementary = match ty {
+        Type::Elementary(e) | Type::Subrange { base: e, .. } => *e,
+        Type::Enum(_) | Type::Pointer(_) | Type::Reference(_) => return true,
+        _ => return false,
+    };
+    match elementary {
+        Elementary::Real
+        | Elementary::Time
+        | Elementary::Date
+        | Elementary::TimeOfDay
+        | Elementary::Char
+        | Elementary::WChar => true,
+        e => e.bit_width().is_some_and(|bits| bits <= 32),
+    }
+}
+
+struct Analyzer<'a> {
+    env: &'a TypeEnv<'a>,
+    /// Locations of the variables declared AT one, by the positions of their names
+    locations: HashMap<usize, String>,
+    /// Statements of the POUs, methods and actions
+    bodies: HashMap<SymbolId, &'a Statements>,
+    /// Accesses of shared variables by each body itself
+    accesses: HashMap<SymbolId, Vec<(Shared, AccessKind, Span)>>,
+    /// POUs, methods and actions each body calls
+    calls: HashMap<SymbolId, Vec<SymbolId>>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn collect(&mut self, blocks: &'a [Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                    self.collect_pou(pou)
+                }
+                Block::Action(action) => self.add_body(action.name.span, &action.statements),
+                Block::GlobalVariables(variables) => self.collect_locations(variables),
+                Block::Namespace(namespace) => self.collect(&namespace.blocks),
+                Block::Type(_) | Block::Configuration(_) => {}
+            }
+        }
+    }
+
+    fn collect_pou(&mut self, pou: &'a Pou) {
+        for variables in &pou.variables {
+            self.collect_locations(variables);
+        }
+        for method in &pou.methods {
+            self.collect_pou(method);
+        }
+        for action in &pou.actions {
+            self.add_body(action.name.span, &action.statements);
+        }
+        self.add_body(pou.name.span, &pou.statements);
+    }
+
+    fn add_body(&mut self, name: Span, statements: &'a Statements) {
+        if let Some(id) = self.env.binding(name) {
+            self.bodies.insert(id, statements);
+        }
+    }
+
+    fn collect_locations(&mut self, variables: &VariableBlock) {
+        for declaration in &variables.declarations {
+            if let Some(location) = &declaration.location {
+                for name in &declaration.names {
+                    let address = location.name.to_ascii_uppercase();
+                    self.locations.insert(name.span.pos, address);
+                }
+            }
+        }
+    }
+
+    fn analyze_body(&mut self, id: SymbolId, statements: &'a Statements) {
+        let graph = Graph::new(statements);
+        let mut accesses = Vec::new();
+        let mut calls = Vec::new();
+        for node in &graph.nodes {
+            for access in flow::accesses(node, self.env) {
+                if let Some(shared) = self.shared(access.symbol) {
+                    accesses.push((shared, access.kind, access.span));
+                }
+            }
+            for expression in node.expressions() {
+                flow::visit_calls(expression, &mut |call| {
+                    if let Some((callee, instance)) = flow::callee(self.env, call) {
+                        calls.push(callee);
+                        // Calls change the state of the instance they run on
+                        if let Some(shared) = instance.and_then(|i| self.shared(i)) {
+                            accesses.push((shared, AccessKind::PartialWrite, call.callee.span()));
+                        }
+                    }
+                });
+            }
+        }
+        self.accesses.insert(id, accesses);
+        self.calls.insert(id, calls);
+    }
+
+    /// The shared memory a variable denotes, if any.
+    fn shared(&self, id: SymbolId) -> Option<Shared> {
+        let table = self.env.table;
+        let symbol = table.symbol(id);
+        let SymbolKind::Variable { kind, .. } = symbol.kind else {
+            return None;
+        };
+        // A VAR_EXTERNAL declaration refers to its global
+        let id = match kind {
+            VariableKind::External => table
+                .references
+                .iter()
+                .find(|r| r.span.pos == symbol.span.pos)
+                .map_or(id, |r| r.symbol),
+            _ => id,
+        };
+        let symbol = table.symbol(id);
+        if let Some(address) = self.locations.get(&symbol.span.pos) {
+            return Some(Shared::Address(address.clone()));
+        }
+        match symbol.kind {
+            SymbolKind::Variable {
+                kind: VariableKind::Global,
+                ..
+            } => Some(Shared::Global(id)),
+            _ => None,
+        }
+    }
+
+    fn type_of(&self, shared: &Shared) -> Type {
+        let id = match shared {
+            Shared::Global(id) => Some(*id),
+            Shared::Address(address) => self
+                .locations
+                .iter()
+                .find(|(_, a)| *a == address)
+                .and_then(|(pos, _)| {
+                    self.env
+                        .table
+                        .symbols
+                        .iter()
+                        .position(|s| s.span.pos == *pos)
+                }),
+        };
+        id.map_or(Type::Unknown, |id| {
+            self.env.type_of_symbol(id).dereferenced().clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::SourceDb, runtime::scheduler::TaskConfig};
+
+    const SRC: &str = "VAR_GLOBAL
+    gCount : INT;
+    gPosition : LREAL;
+    gMode : INT;
+    gLamp AT %QX0.0 : BOOL;
+    gTimer : TON;
+END_VAR
+FUNCTION_BLOCK Axis
+VAR_EXTERNAL gPosition : LREAL; END_VAR
+METHOD Move gPosition := gPosition + 1.0; END_METHOD
+END_FUNCTION_BLOCK
+PROGRAM Fast
+VAR drive : Axis; lamp AT %QX0.0 : BOOL; END_VAR
+gCount := gCount + 1;
+drive.Move();
+Blink();
+END_PROGRAM
+ACTION Fast.Blink
+lamp := NOT lamp;
+gTimer(IN := TRUE, PT := T#1s);
+END_ACTION
+PROGRAM Slow
+VAR position : LREAL; count : INT; END_VAR
+position := gPosition;
+count := gCount;
+gCount := 0;
+gLamp := gMode > 0;
+gTimer(IN := FALSE);
+END_PROGRAM
+";
+
+    fn analyzed(programs: &[(&str, &str)]) -> Result<(Project, TaskAnalysis), String> {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", SRC);
+        let project = Project::analyze(sources).unwrap();
+        assert!(!project.has_errors(), "{:?}", project.diagnostics);
+        let config = Configuration {
+            tasks: programs
+                .iter()
+                .map(|(task, program)| TaskConfig {
+                    name: task.to_string(),
+                    interval: 1_000_000,
+                    priority: 0,
+                    programs: vec![program.to_string()],
+                })
+                .collect(),
+            ..Configuration::default()
+        };
+        let analysis = analyze(&project, &config)?;
+        Ok((project, analysis))
+    }
+
+    #[test]
+    fn test_collects_accesses_through_calls_and_actions() {
+        let (_, analysis) = analyzed(&[("Cyclic", "Fast"), ("Background", "Slow")]).unwrap();
+        assert_eq!(
+            analysis.report(),
+            "Task Cyclic
+    reads   gCount, gPosition, %QX0.0
+    writes  gCount, gPosition, gTimer, %QX0.0
+Task Background
+    reads   gCount, gPosition, gMode
+    writes  gCount, gTimer, %QX0.0
+"
+        );
+        assert_eq!(
+            analyzed(&[("Cyclic", "Main")]).err().unwrap(),
+            "There is no program 'Main' for task 'Cyclic'."
+        );
+    }
+
+    #[test]
+    fn test_reports_writers_of_several_tasks_and_torn_reads() {
+        let (project, analysis) = analyzed(&[("Cyclic", "Fast"), ("Background", "Slow")]).unwrap();
+        let conflicts = analysis
+            .conflicts
+            .iter()
+            .map(|d| {
+                let (_, local) = project.sources.local(d.span);
+                format!("{}: {}", local.line + 1, d.message)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            conflicts,
+            [
+                "14: 'gCount' is written by the tasks 'Cyclic' and 'Background', which may interrupt each other.",
+                "10: 'gPosition' is written by task 'Cyclic' and read by task 'Background', which may see a half-written LREAL.",
+                "20: 'gTimer' is written by the tasks 'Cyclic' and 'Background', which may interrupt each other.",
+                "19: '%QX0.0' is written by the tasks 'Cyclic' and 'Background', which may interrupt each other.",
+            ]
+        );
+        let (_, alone) = analyzed(&[("Cyclic", "Fast")]).unwrap();
+        assert!(alone.conflicts.is_empty());
+    }
+}
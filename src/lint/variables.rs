@@ -0,0 +1,186 @@
+use crate::{
+    diagnostic::Diagnostic,
+    lint::Linter,
+    parsing::ast::{Expression, Identifier, VariableKind},
+    semantic::symbols::{ScopeKind, SymbolKind},
+};
+
+impl Linter<'_> {
+    /// Records the variable an assignment target writes to, and reports writes to inputs.
+    ///
+    /// That is the first name of the target that is a variable, like `motor` in
+    /// `motor.speed := 1` and `level` in `Main.level := 1`, but no variable a pointer points to.
+    pub(super) fn write(&mut self, target: &Expression) {
+        let mut names = Vec::new();
+        collect_names(target, &mut names);
+        let variable = names.iter().position(|name| {
+            let binding = self.typer.env().binding(name.span);
+            binding
+                .is_some_and(|id| matches!(self.table.symbol(id).kind, SymbolKind::Variable { .. }))
+        });
+        if let Some(index) = variable {
+            self.writes.insert(names[index].span.pos);
+            // Only a name of its own, inputs of other POUs are set from outside
+            if index == 0 {
+                self.check_input_write(names[0]);
+            }
+        }
+    }
+
+    pub(super) fn check_input_write(&mut self, name: &Identifier) {
+        let Some(id) = self.typer.env().binding(name.span) else {
+            return;
+        };
+        if let SymbolKind::Variable {
+            kind: VariableKind::Input,
+            ..
+        } = self.table.symbol(id).kind
+        {
+            self.report(
+                "input-assignment",
+                name.span,
+                format!(
+                    "The input '{}' is assigned, which hides the value the caller passed.",
+                    name.name
+                ),
+            );
+        }
+    }
+
+    /// Reports variables of POUs that are never read, or that hide others.
+    pub(super) fn lint_variables(&mut self) {
+        let table = self.table;
+        for (id, symbol) in table.symbols.iter().enumerate() {
+            // Variables of the standard function blocks have no place in the sources
+            let SymbolKind::Variable { kind, .. } = symbol.kind else {
+                continue;
+            };
+            if symbol.span.len == 0 {
+                continue;
+            }
+            let scope = table.scope(symbol.scope);
+            if !matches!(scope.kind, ScopeKind::Pou | ScopeKind::Method) {
+                continue;
+            }
+
+            if kind != VariableKind::External
+                && let Some(parent) = scope.parent
+                && let Some(shadowed) = table.lookup(parent, &symbol.name)
+            {
+                let diagnostic = Diagnostic::warning(
+                    symbol.span,
+                    format!("'{}' shadows a declaration of an outer scope.", symbol.name),
+                )
+                .with_note(
+                    table.symbol(shadowed).span,
+                    format!("Shadowed declaration of '{}'.", symbol.name),
+                );
+                self.findings.push(("shadowing", diagnostic));
+            }
+
+            if !matches!(kind, VariableKind::Local | VariableKind::Temp) {
+                continue;
+            }
+            let mut uses = table.references_to(id).peekable();
+            if uses.peek().is_none() {
+                self.report(
+                    "unused-variable",
+                    symbol.span,
+                    format!("'{}' is declared, but never used.", symbol.name),
+                );
+            } else if uses.all(|r| self.writes.contains(&r.span.pos))
+                && !self.located.contains(&symbol.span.pos)
+            {
+                self.report(
+                    "write-only-variable",
+                    symbol.span,
+                    format!("'{}' is assigned, but never read.", symbol.name),
+                );
+            }
+        }
+    }
+}
+
+/// The names a target is accessed through, from the outermost.
+fn collect_names<'a>(target: &'a Expression, names: &mut Vec<&'a Identifier>) {
+    match target {
+        Expression::Identifier(name) => names.push(name),
+        Expression::Member(x) => {
+            collect_names(&x.target, names);
+            names.push(&x.member);
+        }
+        Expression::Index(x) => collect_names(&x.target, names),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::linted;
+
+    const SRC: &str = "TYPE Pair : STRUCT a, b : INT; END_STRUCT END_TYPE
+VAR_GLOBAL limit : INT; END_VAR
+FUNCTION_BLOCK Motor
+VAR_INPUT speed : INT; END_VAR
+VAR_OUTPUT done : BOOL; END_VAR
+VAR idle : INT; last : INT; both : Pair; lamp AT %QX0.0 : BOOL; END_VAR
+VAR_TEMP i : INT; END_VAR
+last := speed;
+both.a := 1;
+speed := speed + 1;
+lamp := TRUE;
+FOR i := 0 TO 3 DO done := TRUE; END_FOR;
+END_FUNCTION_BLOCK
+PROGRAM Main
+VAR m : Motor; limit : INT; END_VAR
+m.speed := limit;
+m();
+END_PROGRAM
+";
+
+    #[test]
+    fn test_reports_variables_never_read() {
+        assert_eq!(
+            linted(SRC, "unused-variable"),
+            ["'idle' is declared, but never used. [unused-variable]"]
+        );
+        assert_eq!(
+            linted(SRC, "write-only-variable"),
+            [
+                "'last' is assigned, but never read. [write-only-variable]",
+                "'both' is assigned, but never read. [write-only-variable]"
+            ]
+        );
+        assert_eq!(
+            linted(SRC, "input-assignment"),
+            [
+                "The input 'speed' is assigned, which hides the value the caller passed. [input-assignment]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_shadowing() {
+        let src = "VAR_GLOBAL speed : REAL; END_VAR
+FUNCTION_BLOCK Motor
+    VAR speed : REAL; END_VAR
+    VAR_EXTERNAL speed2 : REAL; END_VAR
+    METHOD Stop
+        VAR speed : REAL; END_VAR
+        speed := 0.0;
+    END_METHOD
+END_FUNCTION_BLOCK
+VAR_GLOBAL speed2 : REAL; END_VAR";
+        assert_eq!(
+            linted(src, "shadowing"),
+            [
+                "'speed' shadows a declaration of an outer scope. [shadowing]",
+                "'speed' shadows a declaration of an outer scope. [shadowing]"
+            ]
+        );
+        assert_eq!(
+            linted(SRC, "shadowing"),
+            ["'limit' shadows a declaration of an outer scope. [shadowing]"]
+        );
+    }
+}
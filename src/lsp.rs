@@ -0,0 +1,509 @@
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+    path::PathBuf,
+};
+
+use crate::{diagnostic::Severity, formats::json::Json};
+
+mod features;
+mod transport;
+mod workspace;
+
+use features::{Completion, DocumentSymbol, kinds};
+use workspace::{Location, Position, Problem, Workspace, path_of};
+
+/// Error code of requests for methods the server does not know.
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Error code of requests that are valid but cannot be carried out, like a rename to a taken name.
+const REQUEST_FAILED: i64 = -32803;
+
+/// Serves the Language Server Protocol over a stream until the client sends `exit`.
+///
+/// Documents are synchronized by the ranges that change. Every change analyzes the workspace again and publishes
+/// the diagnostics of all documents, as declarations of one document are visible in all others.
+/// Returns whether the client asked the server to shut down before it exits.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> Result<bool, String> {
+    let mut server = Server {
+        workspace: Workspace::new(),
+        shutdown: false,
+        published: Vec::new(),
+    };
+    while let Some(message) = transport::read(&mut input)? {
+        let method = message
+            .get("method")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").unwrap_or(&Json::Null);
+        match message.get("id") {
+            _ if method == "exit" => return Ok(server.shutdown),
+            Some(id) if !method.is_empty() => {
+                let response = match server.request(method, params) {
+                    Ok(result) => Json::object([
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, message)) => Json::object([
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        (
+                            "error",
+                            Json::object([("code", code.into()), ("message", message.into())]),
+                        ),
+                    ]),
+                };
+                transport::write(&mut output, &response)?;
+            }
+            // The server sends no requests, so there are no responses to handle
+            Some(_) => (),
+            None => {
+                for notification in server.notify(method, params) {
+                    transport::write(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+struct Server {
+    workspace: Workspace,
+    shutdown: bool,
+    /// Documents with diagnostics published, which are cleared once they have none
+    published: Vec<String>,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|d| d.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let position = || {
+            params
+                .get("position")
+                .and_then(position_from)
+                .ok_or((INVALID_PARAMS, "A position is expected.".to_string()))
+        };
+        let result = match method {
+            "initialize" => {
+                let root = params
+                    .get("rootUri")
+                    .and_then(Json::as_str)
+                    .and_then(path_of)
+                    .or_else(|| {
+                        let path = params.get("rootPath").and_then(Json::as_str)?;
+                        Some(PathBuf::from(path))
+                    });
+                if let Some(root) = root
+                    && let Err(e) = self.workspace.load(&root)
+                {
+                    eprintln!("{e}");
+                }
+                capabilities()
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            }
+            "textDocument/hover" => self
+                .workspace
+                .hover(uri, position()?)
+                .map(|hover| {
+                    let contents =
+                        Json::object([("kind", "markdown".into()), ("value", hover.into())]);
+                    Json::object([("contents", contents)])
+                })
+                .into(),
+            "textDocument/definition" => self
+                .workspace
+                .definition(uri, position()?)
+                .map(location_json)
+                .into(),
+            "textDocument/references" => {
+                let declaration = params
+                    .get("context")
+                    .and_then(|c| c.get("includeDeclaration"))
+                    .and_then(Json::as_bool)
+                    .unwrap_or_default();
+                let references = self.workspace.references(uri, position()?, declaration);
+                Json::Array(references.into_iter().map(location_json).collect())
+            }
+            "textDocument/rename" => {
+                let name = params
+                    .get("newName")
+                    .and_then(Json::as_str)
+                    .ok_or((INVALID_PARAMS, "A new name is expected.".to_string()))?;
+                let changes = self
+                    .workspace
+                    .rename(uri, position()?, name)
+                    .map_err(|e| (REQUEST_FAILED, e))?;
+                let changes = changes
+                    .into_iter()
+                    .map(|document| {
+                        let edits = document
+                            .edits
+                            .iter()
+                            .map(|(range, text)| {
+                                Json::object([
+                                    ("range", range_json(range)),
+                                    ("newText", text.as_str().into()),
+                                ])
+                            })
+                            .collect();
+                        (document.uri, Json::Array(edits))
+                    })
+                    .collect();
+                Json::object([("changes", Json::Object(changes))])
+            }
+            "textDocument/documentSymbol" => Json::Array(
+                self.workspace
+                    .symbols(uri)
+                    .iter()
+                    .map(symbol_json)
+                    .collect(),
+            ),
+            "textDocument/semanticTokens/full" => {
+                let data = self.workspace.semantic_tokens(uri);
+                let data = data.into_iter().map(|n| (n as usize).into()).collect();
+                Json::object([("data", Json::Array(data))])
+            }
+            "textDocument/completion" => {
+                let completions = self.workspace.completions(uri, position()?);
+                Json::Array(completions.into_iter().map(completion_json).collect())
+            }
+            method => return Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'."))),
+        };
+        Ok(result)
+    }
+
+    /// Handles a notification, giving the notifications to send back.
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let document = params.get("textDocument");
+        let uri = document
+            .and_then(|d| d.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        match method {
+            "initialized" => (),
+            "textDocument/didOpen" => {
+                let text = document.and_then(|d| d.get("text")).and_then(Json::as_str);
+                self.workspace
+                    .update(uri, text.unwrap_or_default().to_string());
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                for change in changes.unwrap_or_default() {
+                    let text = change
+                        .get("text")
+                        .and_then(Json::as_str)
+                        .unwrap_or_default();
+                    let range = change.get("range").and_then(|r| {
+                        Some(position_from(r.get("start")?)?..position_from(r.get("end")?)?)
+                    });
+                    match range {
+                        Some(range) => self.workspace.edit(uri, range, text.to_string()),
+                        None => self.workspace.update(uri, text.to_string()),
+                    }
+                }
+            }
+            "textDocument/didClose" => self.workspace.close(uri),
+            _ => return Vec::new(),
+        }
+        self.workspace.analyze();
+        self.publish()
+    }
+
+    /// Diagnostics of the documents which have some or had some before.
+    fn publish(&mut self) -> Vec<Json> {
+        let notification = |uri: &str, problems: &[Problem]| {
+            let params = Json::object([
+                ("uri", uri.into()),
+                (
+                    "diagnostics",
+                    Json::Array(problems.iter().map(problem_json).collect()),
+                ),
+            ]);
+            Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("method", "textDocument/publishDiagnostics".into()),
+                ("params", params),
+            ])
+        };
+
+        let mut published = Vec::new();
+        let mut notifications = Vec::new();
+        for (uri, problems) in self.workspace.problems() {
+            if !problems.is_empty() || self.published.iter().any(|p| p == uri) {
+                notifications.push(notification(uri, problems));
+            }
+            if !problems.is_empty() {
+                published.push(uri.to_string());
+            }
+        }
+        // Documents closed since
+        for uri in &self.published {
+            if self.workspace.problems().all(|(u, _)| u != uri) {
+                notifications.push(notification(uri, &[]));
+            }
+        }
+        self.published = published;
+        notifications
+    }
+}
+
+fn capabilities() -> Json {
+    let names = |names: &[&str]| Json::Array(names.iter().map(|&n| n.into()).collect());
+    let legend = Json::object([
+        ("tokenTypes", names(&kinds::TOKEN_TYPES)),
+        ("tokenModifiers", names(&kinds::TOKEN_MODIFIERS)),
+    ]);
+    let capabilities = Json::object([
+        ("textDocumentSync", 2usize.into()),
+        ("hoverProvider", true.into()),
+        ("definitionProvider", true.into()),
+        ("referencesProvider", true.into()),
+        ("renameProvider", true.into()),
+        ("documentSymbolProvider", true.into()),
+        (
+            "completionProvider",
+            Json::object([("triggerCharacters", Json::Array(vec![".".into()]))]),
+        ),
+        (
+            "semanticTokensProvider",
+            Json::object([("legend", legend), ("full", true.into())]),
+        ),
+    ]);
+    let info = Json::object([
+        ("name", "strooct".into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+    ]);
+    Json::object([("capabilities", capabilities), ("serverInfo", info)])
+}
+
+fn position_from(json: &Json) -> Option<Position> {
+    let number = |key: &str| {
+        json.get(key)?
+            .as_i64()
+            .and_then(|n| usize::try_from(n).ok())
+    };
+    Some(Position {
+        line: number("line")?,
+        character: number("character")?,
+    })
+}
+
+fn range_json(range: &Range<Position>) -> Json {
+    let position =
+        |p: Position| Json::object([("line", p.line.into()), ("character", p.character.into())]);
+    Json::object([
+        ("start", position(range.start)),
+        ("end", position(range.end)),
+    ])
+}
+
+fn location_json(location: Location) -> Json {
+    Json::object([
+        ("uri", location.uri.into()),
+        ("range", range_json(&location.range)),
+    ])
+}
+
+fn problem_json(problem: &Problem) -> Json {
+    let severity: usize = match problem.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Info => 3,
+    };
+    let related = problem
+        .related
+        .iter()
+        .map(|(location, message)| {
+            Json::object([
+                ("location", location_json(location.clone())),
+                ("message", message.as_str().into()),
+            ])
+        })
+        .collect();
+    Json::object([
+        ("range", range_json(&problem.range)),
+        ("severity", severity.into()),
+        ("source", "strooct".into()),
+        ("message", problem.message.as_str().into()),
+        ("relatedInformation", Json::Array(related)),
+    ])
+}
+
+fn symbol_json(symbol: &DocumentSymbol) -> Json {
+    Json::object([
+        ("name", symbol.name.as_str().into()),
+        ("kind", (symbol.kind as usize).into()),
+        ("range", range_json(&symbol.range)),
+        ("selectionRange", range_json(&symbol.selection)),
+        (
+            "children",
+            Json::Array(symbol.children.iter().map(symbol_json).collect()),
+        ),
+    ])
+}
+
+fn completion_json(completion: Completion) -> Json {
+    Json::object([
+        ("label", completion.label.into()),
+        ("kind", (completion.kind as usize).into()),
+        ("detail", completion.detail.into()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: usize, method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    #[test]
+    fn test_serves_a_session() {
+        let uri = "file:///main.st";
+        let document = |text: &str| Json::object([("uri", uri.into()), ("text", text.into())]);
+        let position = |line: usize, character: usize| {
+            Json::object([("line", line.into()), ("character", character.into())])
+        };
+        let range = |line: usize, start: usize, end: usize| {
+            Json::object([
+                ("start", position(line, start)),
+                ("end", position(line, end)),
+            ])
+        };
+        let at = |line: usize, character: usize| {
+            Json::object([
+                ("textDocument", Json::object([("uri", uri.into())])),
+                ("position", position(line, character)),
+            ])
+        };
+        let messages = [
+            request(1, "initialize", Json::object([])),
+            notification("initialized", Json::object([])),
+            notification(
+                "textDocument/didOpen",
+                Json::object([(
+                    "textDocument",
+                    document("PROGRAM Main\nVAR n : INT; END_VAR\nn := m;\nEND_PROGRAM"),
+                )]),
+            ),
+            notification(
+                "textDocument/didChange",
+                Json::object([
+                    ("textDocument", Json::object([("uri", uri.into())])),
+                    (
+                        "contentChanges",
+                        Json::Array(vec![Json::object([
+                            ("range", range(2, 5, 6)),
+                            ("text", "n".into()),
+                        ])]),
+                    ),
+                ]),
+            ),
+            request(2, "textDocument/hover", at(2, 5)),
+            request(3, "textDocument/definition", at(2, 0)),
+            request(4, "textDocument/formatting", at(0, 0)),
+            request(
+                5,
+                "textDocument/rename",
+                Json::object([
+                    ("textDocument", Json::object([("uri", uri.into())])),
+                    ("position", position(2, 0)),
+                    ("newName", "count".into()),
+                ]),
+            ),
+            request(6, "shutdown", Json::Null),
+            notification("exit", Json::Null),
+        ];
+        let mut input = Vec::new();
+        for message in &messages {
+            transport::write(&mut input, message).unwrap();
+        }
+        let mut output = Vec::new();
+        assert_eq!(serve(&input[..], &mut output), Ok(true));
+
+        let mut output = &output[..];
+        let mut responses = Vec::new();
+        while let Some(response) = transport::read(&mut output).unwrap() {
+            responses.push(response);
+        }
+        let [
+            initialized,
+            diagnostics,
+            cleared,
+            hover,
+            definition,
+            unknown,
+            renamed,
+            shutdown,
+        ] = &responses[..]
+        else {
+            panic!("{responses:?}");
+        };
+        let capabilities = initialized
+            .get("result")
+            .and_then(|r| r.get("capabilities"));
+        assert_eq!(
+            capabilities.and_then(|c| c.get("textDocumentSync")),
+            Some(&Json::from(2usize))
+        );
+
+        let published = |n: &Json| {
+            n.get("params")?
+                .get("diagnostics")?
+                .as_array()
+                .map(<[_]>::len)
+        };
+        assert_eq!(published(diagnostics), Some(1));
+        assert_eq!(published(cleared), Some(0));
+
+        let hover = hover
+            .get("result")
+            .and_then(|r| r.get("contents")?.get("value")?.as_str());
+        assert_eq!(hover, Some("```st\nVAR n : INT\n```"));
+        let definition = definition.get("result").map(Json::to_string);
+        assert_eq!(
+            definition.as_deref(),
+            Some(
+                r#"{"uri":"file:///main.st","range":{"start":{"line":1,"character":4},"end":{"line":1,"character":5}}}"#
+            )
+        );
+        assert_eq!(
+            unknown.get("error").and_then(|e| e.get("code")),
+            Some(&Json::from(METHOD_NOT_FOUND))
+        );
+        let renamed = renamed
+            .get("result")
+            .and_then(|r| r.get("changes")?.get(uri)?.as_array())
+            .map(|edits| {
+                edits
+                    .iter()
+                    .map(|e| e.get("range").map(Json::to_string))
+                    .collect::<Vec<_>>()
+            });
+        let range = |line: usize, start: usize| Some(range(line, start, start + 1).to_string());
+        assert_eq!(renamed, Some(vec![range(1, 4), range(2, 0), range(2, 5)]));
+        assert_eq!(shutdown.get("result"), Some(&Json::Null));
+    }
+}
@@ -0,0 +1,661 @@
+use std::ops::Range;
+
+use crate::{
+    lsp::workspace::{Location, Position, Workspace, location, offset, position, range},
+    parsing::{
+        ast::VariableKind,
+        lexer::Lexer,
+        syntax::{SyntaxElement, SyntaxKind, SyntaxNode},
+        token::KEYWORDS,
+    },
+    refactor::rename,
+    semantic::{
+        highlight::{Highlight, highlight},
+        symbols::{ScopeId, SymbolId, SymbolKind, SymbolTable},
+        types::{Type, TypeEnv},
+    },
+};
+
+/// Kinds of completion items and document symbols, numbered as in LSP.
+pub mod kinds {
+    pub const COMPLETION_METHOD: u32 = 2;
+    pub const COMPLETION_FUNCTION: u32 = 3;
+    pub const COMPLETION_FIELD: u32 = 5;
+    pub const COMPLETION_VARIABLE: u32 = 6;
+    pub const COMPLETION_CLASS: u32 = 7;
+    pub const COMPLETION_MODULE: u32 = 9;
+    pub const COMPLETION_KEYWORD: u32 = 14;
+    pub const COMPLETION_STRUCT: u32 = 22;
+
+    pub const SYMBOL_MODULE: u32 = 2;
+    pub const SYMBOL_NAMESPACE: u32 = 3;
+    pub const SYMBOL_CLASS: u32 = 5;
+    pub const SYMBOL_METHOD: u32 = 6;
+    pub const SYMBOL_ENUM: u32 = 10;
+    pub const SYMBOL_FUNCTION: u32 = 12;
+    pub const SYMBOL_STRUCT: u32 = 23;
+
+    /// Legend of the semantic tokens, indexed by their token types and modifier bits.
+    pub const TOKEN_TYPES: [&str; 10] = [
+        "keyword",
+        "type",
+        "variable",
+        "parameter",
+        "enumMember",
+        "comment",
+        "function",
+        "namespace",
+        "string",
+        "number",
+    ];
+    pub const TOKEN_MODIFIERS: [&str; 2] = ["readonly", "instance"];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: u32,
+    /// The whole declaration
+    pub range: Range<Position>,
+    /// The name of the declaration
+    pub selection: Range<Position>,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: u32,
+    pub detail: Option<String>,
+}
+
+/// Changes of a document, each a range of its text replaced by another text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentEdit {
+    pub uri: String,
+    pub edits: Vec<(Range<Position>, String)>,
+}
+
+impl Workspace {
+    /// The symbol declared or referenced at a position of a document.
+    fn symbol_at(&self, uri: &str, position: Position) -> Option<SymbolId> {
+        let project = self.project.as_ref()?;
+        let file = project.sources.file(project.sources.find(uri)?);
+        let pos = file.origin.0 + offset(&file.text, position);
+        project.table.symbol_at(pos)
+    }
+
+    /// The declaration of the symbol at a position, as Markdown.
+    pub fn hover(&self, uri: &str, position: Position) -> Option<String> {
+        let table = &self.project.as_ref()?.table;
+        let id = self.symbol_at(uri, position)?;
+        Some(format!("```st\n{}\n```", describe(table, id)))
+    }
+
+    pub fn definition(&self, uri: &str, position: Position) -> Option<Location> {
+        let project = self.project.as_ref()?;
+        let id = self.symbol_at(uri, position)?;
+        Some(location(&project.sources, project.table.symbol(id).span))
+    }
+
+    /// Uses of the symbol at a position, after its declaration if that is asked for.
+    pub fn references(&self, uri: &str, position: Position, declaration: bool) -> Vec<Location> {
+        let (Some(project), Some(id)) = (self.project.as_ref(), self.symbol_at(uri, position))
+        else {
+            return Vec::new();
+        };
+        let table = &project.table;
+        let declared = Some(table.symbol(id).span).filter(|_| declaration);
+        declared
+            .into_iter()
+            .chain(table.references_to(id).map(|r| r.span))
+            .map(|span| location(&project.sources, span))
+            .collect()
+    }
+
+    /// Edits of the documents renaming the symbol at a position, by URI.
+    ///
+    /// Only documents analyzed by their current text can be renamed in, so every document the
+    /// rename touches must be free of syntax errors.
+    pub fn rename(
+        &self,
+        uri: &str,
+        position: Position,
+        name: &str,
+    ) -> Result<Vec<DocumentEdit>, String> {
+        let project = self
+            .project
+            .as_ref()
+            .ok_or("The workspace is not analyzed.")?;
+        let sources = &project.sources;
+        let current = |uri: &str| {
+            let file = sources.find(uri).map(|id| sources.file(id));
+            file.filter(|f| self.text(uri) == Some(f.text.as_str()))
+                .ok_or(format!("Fix the syntax errors of {uri} before renaming."))
+        };
+        let file = current(uri)?;
+        let edits = rename::rename(project, file.origin.0 + offset(&file.text, position), name)?;
+
+        let mut changes = Vec::<DocumentEdit>::new();
+        for (id, edit) in edits {
+            let file = current(&sources.file(id).name)?;
+            let change = (range(&file.text, edit.range), edit.text);
+            match changes.iter_mut().find(|d| d.uri == file.name) {
+                Some(document) => document.edits.push(change),
+                None => changes.push(DocumentEdit {
+                    uri: file.name.clone(),
+                    edits: vec![change],
+                }),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// The POUs, actions, types and namespaces declared in a document, nested as in the source.
+    pub fn symbols(&self, uri: &str) -> Vec<DocumentSymbol> {
+        let Some(project) = self.project.as_ref() else {
+            return Vec::new();
+        };
+        let Some(id) = project.sources.find(uri) else {
+            return Vec::new();
+        };
+        let table = &project.table;
+        let file = project.sources.file(id);
+        let tree = SyntaxNode::parse(&file.text);
+        let outline = |id: SymbolId| -> Option<u32> {
+            let kind = match table.symbol(id).kind {
+                SymbolKind::Program => kinds::SYMBOL_MODULE,
+                SymbolKind::FunctionBlock => kinds::SYMBOL_CLASS,
+                SymbolKind::Function => kinds::SYMBOL_FUNCTION,
+                SymbolKind::Method | SymbolKind::Action => kinds::SYMBOL_METHOD,
+                SymbolKind::Namespace | SymbolKind::Configuration | SymbolKind::Resource => {
+                    kinds::SYMBOL_NAMESPACE
+                }
+                SymbolKind::Type => match TypeEnv::new(table).type_of_symbol(id) {
+                    Type::Enum(_) => kinds::SYMBOL_ENUM,
+                    _ => kinds::SYMBOL_STRUCT,
+                },
+                SymbolKind::Variable { .. }
+                | SymbolKind::EnumValue
+                | SymbolKind::Task
+                | SymbolKind::ProgramInstance => return None,
+            };
+            Some(kind)
+        };
+        // The nearest symbol of the outline a symbol is declared within
+        let parent = |id: SymbolId| {
+            let mut scope = Some(table.symbol(id).scope);
+            while let Some(s) = scope {
+                if let Some(owner) = table.scope(s).owner.filter(|o| outline(*o).is_some()) {
+                    return Some(owner);
+                }
+                scope = table.scope(s).parent;
+            }
+            None
+        };
+        let declared = (0..table.symbols.len())
+            .filter(|i| {
+                let span = table.symbol(*i).span;
+                outline(*i).is_some() && project.sources.file_of(span) == id && span.len > 0
+            })
+            .collect::<Vec<_>>();
+
+        fn nest(
+            within: Option<SymbolId>,
+            declared: &[SymbolId],
+            node: &dyn Fn(SymbolId) -> DocumentSymbol,
+            parent: &dyn Fn(SymbolId) -> Option<SymbolId>,
+        ) -> Vec<DocumentSymbol> {
+            declared
+                .iter()
+                .filter(|s| parent(**s) == within)
+                .map(|s| DocumentSymbol {
+                    children: nest(Some(*s), declared, node, parent),
+                    ..node(*s)
+                })
+                .collect()
+        }
+        let node = |id: SymbolId| {
+            let symbol = table.symbol(id);
+            let name = symbol.span.pos - file.origin.0..symbol.span.end() - file.origin.0;
+            let declaration = tree
+                .token_at(name.start)
+                .and_then(|t| {
+                    t.parent().ancestors().find(|n| {
+                        matches!(
+                            n.kind(),
+                            SyntaxKind::Pou
+                                | SyntaxKind::Namespace
+                                | SyntaxKind::Configuration
+                                | SyntaxKind::Resource
+                                | SyntaxKind::Declaration
+                        )
+                    })
+                })
+                .map_or(name.clone(), |n| n.range());
+            DocumentSymbol {
+                name: symbol.name.clone(),
+                kind: outline(id).unwrap_or_default(),
+                range: range(&file.text, declaration),
+                selection: range(&file.text, name),
+                children: Vec::new(),
+            }
+        };
+        nest(None, &declared, &node, &parent)
+    }
+
+    /// Variables, POUs and keywords visible at a position, or the members of the variable before
+    /// a dot.
+    pub fn completions(&self, uri: &str, position: Position) -> Vec<Completion> {
+        let (Some(project), Some(text)) = (self.project.as_ref(), self.text(uri)) else {
+            return Vec::new();
+        };
+        let table = &project.table;
+        let env = TypeEnv::new(table);
+        let cursor = offset(text, position);
+        let before = &text[..cursor];
+        let word = |s: &str| {
+            s.len()
+                - s.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                    .len()
+        };
+        let prefix = &before[before.len() - word(before)..];
+        let Some(tree) = self.tree(uri) else {
+            return Vec::new();
+        };
+        let scope = scope_at(table, tree, cursor);
+        let item = |id: SymbolId, member: bool| {
+            let symbol = table.symbol(id);
+            let kind = match symbol.kind {
+                SymbolKind::Variable { .. } if member => kinds::COMPLETION_FIELD,
+                SymbolKind::Variable { .. }
+                | SymbolKind::EnumValue
+                | SymbolKind::Task
+                | SymbolKind::ProgramInstance => kinds::COMPLETION_VARIABLE,
+                SymbolKind::Program | SymbolKind::FunctionBlock => kinds::COMPLETION_CLASS,
+                SymbolKind::Function => kinds::COMPLETION_FUNCTION,
+                SymbolKind::Method | SymbolKind::Action => kinds::COMPLETION_METHOD,
+                SymbolKind::Type => kinds::COMPLETION_STRUCT,
+                SymbolKind::Namespace | SymbolKind::Configuration | SymbolKind::Resource => {
+                    kinds::COMPLETION_MODULE
+                }
+            };
+            Completion {
+                label: symbol.name.clone(),
+                kind,
+                detail: Some(describe(table, id)),
+            }
+        };
+        let matches = |name: &str| {
+            name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+        };
+
+        // Members of a chain of names before the dot, like `motor.speed.`
+        let mut rest = &before[..before.len() - prefix.len()];
+        if rest.ends_with('.') {
+            let mut chain = Vec::new();
+            while let Some(stripped) = rest.strip_suffix('.') {
+                let name = &stripped[stripped.len() - word(stripped)..];
+                if name.is_empty() {
+                    return Vec::new();
+                }
+                chain.push(name);
+                rest = &stripped[..stripped.len() - name.len()];
+            }
+            let mut names = chain.iter().rev();
+            let first = names.next().and_then(|n| table.lookup(scope, n));
+            let target = names.try_fold(first, |id, name| {
+                let members = members_of(table, &env, id?)?;
+                Some(table.lookup_local(members, name))
+            });
+            let Some(members) = target.flatten().and_then(|id| members_of(table, &env, id)) else {
+                return Vec::new();
+            };
+            // Only the interface of function blocks and programs is visible from the outside
+            let interface = table.scope(members).owner.is_some_and(|o| {
+                matches!(
+                    table.symbol(o).kind,
+                    SymbolKind::FunctionBlock | SymbolKind::Program
+                )
+            });
+            return table
+                .symbols_in(members)
+                .into_iter()
+                .filter(|id| {
+                    !interface
+                        || matches!(
+                            table.symbol(*id).kind,
+                            SymbolKind::Variable {
+                                kind: VariableKind::Input
+                                    | VariableKind::Output
+                                    | VariableKind::InOut,
+                                ..
+                            } | SymbolKind::Method
+                                | SymbolKind::Action
+                        )
+                })
+                .filter(|id| matches(&table.symbol(*id).name))
+                .map(|id| item(id, true))
+                .collect();
+        }
+
+        let mut items: Vec<Completion> = Vec::new();
+        let mut cur = Some(scope);
+        while let Some(s) = cur {
+            for id in table.symbols_in(s) {
+                let name = &table.symbol(id).name;
+                if matches(name) && !items.iter().any(|i| i.label.eq_ignore_ascii_case(name)) {
+                    items.push(item(id, false));
+                }
+            }
+            cur = table.scope(s).parent;
+        }
+        items.extend(
+            KEYWORDS
+                .iter()
+                .filter(|(k, _)| matches(k))
+                .map(|(k, _)| Completion {
+                    label: k.to_string(),
+                    kind: kinds::COMPLETION_KEYWORD,
+                    detail: None,
+                }),
+        );
+        items
+    }
+
+    /// The highlights of a document in the relative encoding of LSP semantic tokens.
+    ///
+    /// Names are only resolved while the document is analyzed by its current text, tokens
+    /// spanning several lines are split into one token per line.
+    pub fn semantic_tokens(&self, uri: &str) -> Vec<u32> {
+        let Some(text) = self.text(uri) else {
+            return Vec::new();
+        };
+        let analyzed = self.project.as_ref().and_then(|project| {
+            let id = project.sources.find(uri)?;
+            (project.sources.file(id).text == text).then_some((project, id))
+        });
+        let highlights = match analyzed {
+            Some((project, id)) => {
+                let origin = project.sources.file(id).origin.0;
+                let env = TypeEnv::new(&project.table);
+                highlight(project.sources.lexer(id), Some(&env))
+                    .into_iter()
+                    .map(|(span, h)| (span.pos - origin..span.end() - origin, h))
+                    .collect::<Vec<_>>()
+            }
+            None => highlight(Lexer::create(uri, text), None)
+                .into_iter()
+                .map(|(span, h)| (span.pos..span.end(), h))
+                .collect(),
+        };
+
+        let mut data = Vec::new();
+        let mut previous = Position::default();
+        for (range, h) in highlights {
+            let (token_type, modifiers) = match h {
+                Highlight::Keyword => (0, 0),
+                Highlight::Type => (1, 0),
+                Highlight::Variable => (2, 0),
+                Highlight::Constant => (2, 1),
+                Highlight::Instance => (2, 2),
+                Highlight::Parameter => (3, 0),
+                Highlight::EnumMember => (4, 0),
+                Highlight::Comment => (5, 0),
+                Highlight::Function => (6, 0),
+                Highlight::Namespace => (7, 0),
+                Highlight::String => (8, 0),
+                Highlight::Number => (9, 0),
+            };
+            let mut start = range.start;
+            for line in text[range].split('\n') {
+                let at = position(text, start);
+                let length = line.trim_end_matches('\r').encode_utf16().count();
+                start += line.len() + 1;
+                if length == 0 {
+                    continue;
+                }
+                let delta_start = match at.line == previous.line {
+                    true => at.character - previous.character,
+                    false => at.character,
+                };
+                data.extend([
+                    (at.line - previous.line) as u32,
+                    delta_start as u32,
+                    length as u32,
+                    token_type,
+                    modifiers,
+                ]);
+                previous = at;
+            }
+        }
+        data
+    }
+}
+
+/// The scope of the innermost POU or namespace around a position, found by their names.
+fn scope_at(table: &SymbolTable, tree: &SyntaxNode, offset: usize) -> ScopeId {
+    let Some(token) = tree.token_at(offset.saturating_sub(1)) else {
+        return SymbolTable::GLOBAL;
+    };
+    let mut names = token
+        .parent()
+        .ancestors()
+        .filter(|n| matches!(n.kind(), SyntaxKind::Pou | SyntaxKind::Namespace))
+        .map(|n| {
+            n.children()
+                .into_iter()
+                .filter_map(|c| match c {
+                    SyntaxElement::Token(t) if t.kind() == SyntaxKind::Identifier => {
+                        Some(t.text().to_string())
+                    }
+                    _ => None,
+                })
+                .next()
+        })
+        .collect::<Vec<_>>();
+    names.reverse();
+
+    let mut scope = SymbolTable::GLOBAL;
+    for name in names.into_iter().flatten() {
+        match table
+            .lookup_local(scope, &name)
+            .and_then(|id| table.symbol(id).members)
+        {
+            Some(members) => scope = members,
+            None => break,
+        }
+    }
+    scope
+}
+
+/// The scope of the members of a namespace or of the value of a symbol.
+fn members_of(table: &SymbolTable, env: &TypeEnv, id: SymbolId) -> Option<ScopeId> {
+    if table.symbol(id).kind == SymbolKind::Namespace {
+        return table.symbol(id).members;
+    }
+    match env.type_of_symbol(id).dereferenced() {
+        Type::FunctionBlock(t) | Type::Program(t) | Type::Struct(t) => table.symbol(*t).members,
+        _ => None,
+    }
+}
+
+/// A symbol the way it is declared, like `VAR_INPUT start : BOOL`.
+fn describe(table: &SymbolTable, id: SymbolId) -> String {
+    let env = TypeEnv::new(table);
+    let symbol = table.symbol(id);
+    let name = table.qualified_name(id);
+    let ty = env.type_of_symbol(id);
+    let typed = |keyword: &str| match &ty {
+        Type::Void => format!("{keyword} {name}"),
+        ty => format!("{keyword} {name} : {}", ty.display(table)),
+    };
+    match symbol.kind {
+        SymbolKind::Variable { kind, constant } => {
+            let block = match kind {
+                VariableKind::Local => "VAR",
+                VariableKind::Input => "VAR_INPUT",
+                VariableKind::Output => "VAR_OUTPUT",
+                VariableKind::InOut => "VAR_IN_OUT",
+                VariableKind::Temp => "VAR_TEMP",
+                VariableKind::Global => "VAR_GLOBAL",
+                VariableKind::External => "VAR_EXTERNAL",
+            };
+            let constant = if constant { " CONSTANT" } else { "" };
+            format!("{block}{constant} {} : {}", symbol.name, ty.display(table))
+        }
+        SymbolKind::Program => format!("PROGRAM {name}"),
+        SymbolKind::FunctionBlock => format!("FUNCTION_BLOCK {name}"),
+        SymbolKind::Function => typed("FUNCTION"),
+        SymbolKind::Method => typed("METHOD"),
+        SymbolKind::Action => format!("ACTION {name}"),
+        SymbolKind::Namespace => format!("NAMESPACE {name}"),
+        SymbolKind::Configuration => format!("CONFIGURATION {name}"),
+        SymbolKind::Resource => format!("RESOURCE {name}"),
+        SymbolKind::Task => format!("TASK {name}"),
+        SymbolKind::ProgramInstance => format!("PROGRAM {name}"),
+        SymbolKind::Type => match ty {
+            Type::Struct(_) => format!("TYPE {name} : STRUCT"),
+            Type::Enum(values) => {
+                let values = table
+                    .symbols_in(values)
+                    .into_iter()
+                    .map(|v| table.symbol(v).name.clone())
+                    .collect::<Vec<_>>();
+                format!("TYPE {name} : ({})", values.join(", "))
+            }
+            ty => format!("TYPE {name} : {}", ty.display(table)),
+        },
+        SymbolKind::EnumValue => format!("{} : {}", symbol.name, ty.display(table)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOTOR: &str = "TYPE Mode : (Off, Run); END_TYPE
+FUNCTION_BLOCK Motor
+    VAR_INPUT speed : INT; END_VAR
+    VAR_OUTPUT running : BOOL; END_VAR
+    VAR ticks : DINT; END_VAR
+    METHOD Stop
+    END_METHOD
+END_FUNCTION_BLOCK
+";
+    const MAIN: &str = "PROGRAM Main
+    VAR motor1 : Motor; state : Mode; END_VAR
+    motor1(speed := 10);
+    state := Run;
+END_PROGRAM
+";
+
+    fn workspace() -> Workspace {
+        let mut workspace = Workspace::new();
+        workspace.update("file:///motor.st", MOTOR.to_string());
+        workspace.update("file:///main.st", MAIN.to_string());
+        workspace.analyze();
+        workspace
+    }
+
+    fn at(text: &str, needle: &str) -> Position {
+        position(text, text.find(needle).unwrap())
+    }
+
+    #[test]
+    fn test_hovers_and_navigates() {
+        let workspace = workspace();
+        let hover = workspace.hover("file:///main.st", at(MAIN, "speed :="));
+        assert_eq!(hover.as_deref(), Some("```st\nVAR_INPUT speed : INT\n```"));
+        let hover = workspace.hover("file:///main.st", at(MAIN, "state :="));
+        assert_eq!(hover.as_deref(), Some("```st\nVAR state : Mode\n```"));
+
+        let definition = workspace
+            .definition("file:///main.st", at(MAIN, "Motor;"))
+            .unwrap();
+        assert_eq!(definition.uri, "file:///motor.st");
+        assert_eq!(definition.range.start, at(MOTOR, "Motor"));
+
+        let references = workspace.references("file:///motor.st", at(MOTOR, "Run"), true);
+        let uris = references
+            .iter()
+            .map(|l| l.uri.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(uris, ["file:///motor.st", "file:///main.st"]);
+        assert_eq!(references[1].range.start, at(MAIN, "Run"));
+    }
+
+    #[test]
+    fn test_lists_document_symbols() {
+        let symbols = workspace().symbols("file:///motor.st");
+        let outline = |s: &[DocumentSymbol]| {
+            s.iter()
+                .map(|s| (s.name.clone(), s.kind, s.children.len()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            outline(&symbols),
+            [
+                ("Mode".to_string(), kinds::SYMBOL_ENUM, 0),
+                ("Motor".to_string(), kinds::SYMBOL_CLASS, 1)
+            ]
+        );
+        assert_eq!(
+            outline(&symbols[1].children),
+            [("Stop".to_string(), kinds::SYMBOL_METHOD, 0)]
+        );
+        assert_eq!(symbols[1].range.start.line, 1);
+        assert_eq!(symbols[1].range.end.line, 7);
+    }
+
+    #[test]
+    fn test_completes_variables_members_and_keywords() {
+        let mut workspace = workspace();
+        let typing = MAIN.replace("state := Run;", "state := Run;\n    motor1.");
+        workspace.update("file:///main.st", typing.clone());
+        workspace.analyze();
+        let end = at(&typing, "motor1.\n");
+        let members = workspace.completions(
+            "file:///main.st",
+            Position {
+                character: end.character + 7,
+                ..end
+            },
+        );
+        let labels = members.iter().map(|c| c.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["speed", "running", "Stop"]);
+        assert_eq!(members[0].kind, kinds::COMPLETION_FIELD);
+
+        let typing = MAIN.replace("state := Run;", "mo");
+        workspace.update("file:///main.st", typing.clone());
+        let end = at(&typing, "mo\n");
+        let items = workspace.completions(
+            "file:///main.st",
+            Position {
+                character: end.character + 2,
+                ..end
+            },
+        );
+        let labels = items.iter().map(|c| c.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, ["motor1", "Mode", "Motor", "MOD"]);
+        assert_eq!(items[0].detail.as_deref(), Some("VAR motor1 : Motor"));
+    }
+
+    #[test]
+    fn test_encodes_semantic_tokens() {
+        let mut workspace = workspace();
+        let tokens = workspace.semantic_tokens("file:///main.st");
+        assert_eq!(tokens[..5], [0, 0, 7, 0, 0]);
+        assert_eq!(tokens[5..10], [0, 8, 4, 6, 0]);
+        assert_eq!(tokens[10..15], [1, 4, 3, 0, 0]);
+        assert_eq!(tokens[15..20], [0, 4, 6, 2, 2]);
+
+        // A comment over two lines, while the text with a syntax error is not analyzed
+        let broken = format!("(* a\n b *) x := ;\n{MAIN}");
+        workspace.update("file:///main.st", broken);
+        workspace.analyze();
+        let tokens = workspace.semantic_tokens("file:///main.st");
+        assert_eq!(tokens[..10], [0, 0, 4, 5, 0, 1, 0, 5, 5, 0]);
+        assert_eq!(tokens[10..15], [1, 0, 7, 0, 0]);
+    }
+}
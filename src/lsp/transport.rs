@@ -0,0 +1,69 @@
+use std::io::{BufRead, Write};
+
+use crate::formats::json::Json;
+
+/// Reads a message framed by a `Content-Length` header, none at the end of the input.
+pub fn read(input: &mut impl BufRead) -> Result<Option<Json>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        let read = input
+            .read_line(&mut header)
+            .map_err(|e| format!("Cannot read a message header: {e}"))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            let value = value.trim();
+            length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid content length '{value}'."))?,
+            );
+        }
+    }
+
+    let length = length.ok_or("A message has no Content-Length header.")?;
+    let mut content = vec![0; length];
+    input
+        .read_exact(&mut content)
+        .map_err(|e| format!("Cannot read a message: {e}"))?;
+    let content =
+        String::from_utf8(content).map_err(|_| "A message is not valid UTF-8.".to_string())?;
+    Json::parse(&content).map(Some)
+}
+
+pub fn write(output: &mut impl Write, message: &Json) -> Result<(), String> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())
+        .and_then(|_| output.flush())
+        .map_err(|e| format!("Cannot write a message: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_messages() {
+        let message = Json::object([("id", 1usize.into()), ("text", "é\n".into())]);
+        let mut framed = Vec::new();
+        write(&mut framed, &message).unwrap();
+        write(&mut framed, &Json::Null).unwrap();
+        assert!(framed.starts_with(b"Content-Length: 22\r\n\r\n{\"id\":1"));
+
+        let mut input = &framed[..];
+        assert_eq!(read(&mut input).unwrap(), Some(message));
+        assert_eq!(read(&mut input).unwrap(), Some(Json::Null));
+        assert_eq!(read(&mut input).unwrap(), None);
+
+        let mut missing = &b"Content-Type: x\r\n\r\n{}"[..];
+        assert!(read(&mut missing).is_err());
+    }
+}
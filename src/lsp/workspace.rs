@@ -0,0 +1,566 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    diagnostic::Severity,
+    lint::{LintLevels, lint},
+    parsing::{
+        ast::{Ast, Span, VisitSpans},
+        syntax::{GreenNode, SyntaxNode, incremental::TextEdit},
+    },
+    project::{FileId, Project, SourceDb, manifest::Manifest},
+};
+
+/// A position in a text as LSP counts it: lines, and UTF-16 code units within the line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range<Position>,
+}
+
+/// A diagnostic of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub range: Range<Position>,
+    pub severity: Severity,
+    pub message: String,
+    /// Notes pointing to other places, like the first declaration of a duplicate
+    pub related: Vec<(Location, String)>,
+}
+
+/// A source file of the workspace, open in the editor or read from the disk.
+struct Document {
+    uri: String,
+    path: Option<PathBuf>,
+    text: String,
+    tree: SyntaxNode,
+    open: bool,
+    /// Whether the file belongs to the workspace on the disk, rather than only to the editor
+    on_disk: bool,
+    /// The tree the syntax errors and `parsed` are of, to parse only the documents that changed
+    derived: Option<Rc<GreenNode>>,
+    /// The last text of the document without syntax errors and its syntax tree as if the file
+    /// started the project, analyzed while the document has some
+    parsed: Option<(String, Ast)>,
+    syntax_problems: Vec<Problem>,
+    problems: Vec<Problem>,
+}
+
+/// The documents of the editor and the source files of the workspace, analyzed as one project.
+#[derive(Default)]
+pub struct Workspace {
+    documents: Vec<Document>,
+    defines: Vec<String>,
+    lints: LintLevels,
+    /// Analysis of the documents, named by their URIs
+    pub(super) project: Option<Project>,
+    /// Number of times a document was parsed
+    parses: usize,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the project of the manifest in the root or else the source files below it.
+    pub fn load(&mut self, root: &Path) -> Result<(), String> {
+        let manifest = root.join(Manifest::FILE_NAME);
+        let sources = match manifest.is_file() {
+            true => {
+                let manifest = Manifest::load(&manifest)?;
+                self.defines = manifest.defines.clone();
+                // The defines select other code of the documents open already
+                for document in &mut self.documents {
+                    document.derived = None;
+                }
+                self.lints = LintLevels::new(&manifest.lints)?;
+                manifest.sources()?
+            }
+            false => SourceDb::load(&[root])?,
+        };
+        for (_, file) in sources.files() {
+            let path = absolute(Path::new(&file.name));
+            self.documents.push(Document {
+                uri: uri_of(&path),
+                path: Some(path),
+                tree: SyntaxNode::parse(&file.text),
+                text: file.text.clone(),
+                open: false,
+                on_disk: true,
+                derived: None,
+                parsed: None,
+                syntax_problems: Vec::new(),
+                problems: Vec::new(),
+            });
+        }
+        Ok(())
+    }
+
+    fn position_of(&self, uri: &str) -> Option<usize> {
+        let path = path_of(uri).map(|p| absolute(&p));
+        self.documents
+            .iter()
+            .position(|d| d.uri == uri || path.is_some() && d.path == path)
+    }
+
+    /// Opens a document or changes its text.
+    pub fn update(&mut self, uri: &str, text: String) {
+        match self.position_of(uri) {
+            Some(index) => {
+                let document = &mut self.documents[index];
+                document.uri = uri.to_string();
+                document.tree = SyntaxNode::parse(&text);
+                document.text = text;
+                document.open = true;
+            }
+            None => self.documents.push(Document {
+                uri: uri.to_string(),
+                path: path_of(uri).map(|p| absolute(&p)),
+                tree: SyntaxNode::parse(&text),
+                text,
+                open: true,
+                on_disk: false,
+                derived: None,
+                parsed: None,
+                syntax_problems: Vec::new(),
+                problems: Vec::new(),
+            }),
+        }
+    }
+
+    /// Closes a document, files of the workspace go back to their text on the disk.
+    pub fn close(&mut self, uri: &str) {
+        let Some(index) = self.position_of(uri) else {
+            return;
+        };
+        let document = &mut self.documents[index];
+        let saved = document
+            .path
+            .as_ref()
+            .filter(|_| document.on_disk)
+            .and_then(|p| std::fs::read_to_string(p).ok());
+        match saved {
+            Some(text) => {
+                document.tree = SyntaxNode::parse(&text);
+                document.text = text;
+                document.open = false;
+            }
+            None => {
+                self.documents.remove(index);
+            }
+        }
+    }
+
+    /// Replaces a range of the text of an open document, building its tree again only around
+    /// the range.
+    pub fn edit(&mut self, uri: &str, range: Range<Position>, text: String) {
+        let Some(index) = self.position_of(uri) else {
+            return;
+        };
+        let document = &mut self.documents[index];
+        let start = offset(&document.text, range.start);
+        let edit = TextEdit {
+            range: start..offset(&document.text, range.end).max(start),
+            text,
+        };
+        document.tree = document.tree.reparse(&edit);
+        document.text = edit.apply(&document.text);
+    }
+
+    /// The syntax tree of the current text of a document.
+    pub fn tree(&self, uri: &str) -> Option<&SyntaxNode> {
+        self.position_of(uri).map(|i| &self.documents[i].tree)
+    }
+
+    /// The current text of a document.
+    pub fn text(&self, uri: &str) -> Option<&str> {
+        self.position_of(uri)
+            .map(|i| self.documents[i].text.as_str())
+    }
+
+    /// Checks the syntax of every document and analyzes and lints those without syntax errors together,
+    /// documents with syntax errors by their last text that parsed.
+    ///
+    /// Only documents whose tree changed since the last analysis are parsed again, by deriving
+    /// their syntax tree from the lossless one that edits keep up to date.
+    pub fn analyze(&mut self) {
+        let mut sources = SourceDb::new();
+        sources.set_defines(self.defines.clone());
+        let mut ast = Ast::new();
+        let mut current = Vec::new();
+        for document in &mut self.documents {
+            let green = document.tree.green();
+            if !document
+                .derived
+                .as_ref()
+                .is_some_and(|d| Rc::ptr_eq(d, green))
+            {
+                self.parses += 1;
+                document.derived = Some(green.clone());
+                let mut single = SourceDb::new();
+                single.set_defines(self.defines.clone());
+                single.add(document.uri.clone(), document.text.clone());
+                document.syntax_problems = match single.parse_tree(FileId(0), &document.tree) {
+                    Ok(parsed) => {
+                        document.parsed = Some((document.text.clone(), parsed));
+                        Vec::new()
+                    }
+                    Err(errors) => errors
+                        .into_iter()
+                        .map(|e| {
+                            let start = e.span.map_or(document.text.len(), |s| s.pos);
+                            let end = e.span.map_or(start, |s| s.end());
+                            Problem {
+                                range: range(&document.text, start..end),
+                                severity: Severity::Error,
+                                message: e.message,
+                                related: Vec::new(),
+                            }
+                        })
+                        .collect(),
+                };
+            }
+            document.problems = document.syntax_problems.clone();
+            if let Some((text, parsed)) = &document.parsed {
+                current.push(*text == document.text);
+                let id = sources.add(document.uri.clone(), text.clone());
+                let (pos, line) = sources.file(id).origin;
+                let mut parsed = parsed.clone();
+                parsed.visit_spans(&mut |span| {
+                    span.pos += pos;
+                    span.line += line;
+                });
+                ast.blocks.extend(parsed.blocks);
+            }
+        }
+
+        let project = Project::from_ast(sources, ast);
+        // Like `strooct check`, only code without errors is linted
+        let lints = match project.has_errors() {
+            true => Vec::new(),
+            false => lint(&project, &self.lints),
+        };
+        for diagnostic in project.diagnostics.iter().chain(&lints) {
+            let (id, local) = project.sources.local(diagnostic.span);
+            if !current.get(id.0).copied().unwrap_or_default() {
+                continue;
+            }
+            let file = project.sources.file(id);
+            let problem = Problem {
+                range: range(&file.text, local.pos..local.end()),
+                severity: diagnostic.severity,
+                message: diagnostic.message.clone(),
+                related: diagnostic
+                    .notes
+                    .iter()
+                    .map(|(span, note)| (location(&project.sources, *span), note.clone()))
+                    .collect(),
+            };
+            if let Some(document) = self.documents.iter_mut().find(|d| d.uri == file.name) {
+                document.problems.push(problem);
+            }
+        }
+        self.project = Some(project);
+    }
+
+    /// The problems of every document, by URI.
+    pub fn problems(&self) -> impl Iterator<Item = (&str, &[Problem])> {
+        self.documents
+            .iter()
+            .map(|d| (d.uri.as_str(), d.problems.as_slice()))
+    }
+}
+
+/// Location of a span of a project whose files are named by their URIs.
+pub fn location(sources: &SourceDb, span: Span) -> Location {
+    let (id, local) = sources.local(span);
+    let file = sources.file(id);
+    Location {
+        uri: file.name.clone(),
+        range: range(&file.text, local.pos..local.end()),
+    }
+}
+
+pub fn range(text: &str, range: Range<usize>) -> Range<Position> {
+    position(text, range.start)..position(text, range.end)
+}
+
+/// Position of a byte offset in a text.
+pub fn position(text: &str, offset: usize) -> Position {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count(),
+        character: before[start..].encode_utf16().count(),
+    }
+}
+
+/// Byte offset of a position in a text, the end of the line for positions past it.
+pub fn offset(text: &str, position: Position) -> usize {
+    let start = match position.line {
+        0 => 0,
+        line => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        },
+    };
+    let line = text[start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    start + line.len()
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The `file` URI of a path.
+pub fn uri_of(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// The path of a `file` URI.
+pub fn path_of(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = encoded
+            .get(i + 1..i + 3)
+            .filter(|_| encoded[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_converts_positions_and_uris() {
+        let text = "a\n𝄞x := 1;\nend";
+        let x = text.find('x').unwrap();
+        let at = Position {
+            line: 1,
+            character: 2,
+        };
+        assert_eq!(position(text, x), at);
+        assert_eq!(offset(text, at), x);
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 1,
+                    character: 99
+                }
+            ),
+            text.find(";\n").unwrap() + 1
+        );
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 9,
+                    character: 0
+                }
+            ),
+            text.len()
+        );
+
+        let path = Path::new("/tmp/my project/ä.st");
+        let uri = uri_of(path);
+        assert_eq!(uri, "file:///tmp/my%20project/%C3%A4.st");
+        assert_eq!(path_of(&uri).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn test_reports_problems_of_documents() {
+        let mut workspace = Workspace::new();
+        workspace.update(
+            "file:///a.st",
+            "PROGRAM A VAR x : INT; END_VAR x := y; END_PROGRAM".into(),
+        );
+        workspace.update("file:///b.st", "PROGRAM B\n  x := ;\nEND_PROGRAM".into());
+        workspace.analyze();
+        let problems = workspace
+            .problems()
+            .map(|(uri, p)| (uri.to_string(), p.to_vec()))
+            .collect::<Vec<_>>();
+        let [(a, a_problems), (b, b_problems)] = &problems[..] else {
+            panic!("{problems:?}");
+        };
+        assert_eq!((a.as_str(), b.as_str()), ("file:///a.st", "file:///b.st"));
+        assert_eq!(a_problems.len(), 1);
+        assert_eq!(a_problems[0].range.start.character, 36);
+        assert!(a_problems[0].message.contains('y'), "{:?}", a_problems[0]);
+        assert_eq!(b_problems.len(), 1);
+        assert_eq!(
+            b_problems[0].range.start,
+            Position {
+                line: 1,
+                character: 7
+            }
+        );
+
+        // A document with syntax errors is analyzed by the text that parsed last
+        workspace.update(
+            "file:///a.st",
+            "PROGRAM A VAR x : INT; END_VAR x := ; END_PROGRAM".into(),
+        );
+        workspace.analyze();
+        let project = workspace.project.as_ref().unwrap();
+        assert!(project.sources.file(FileId(0)).text.contains("x := y"));
+        workspace.close("file:///a.st");
+        assert_eq!(workspace.text("file:///a.st"), None);
+
+        let start = Position {
+            line: 1,
+            character: 7,
+        };
+        workspace.edit("file:///b.st", start..start, "x + 1".into());
+        let edited = "PROGRAM B\n  x := x + 1;\nEND_PROGRAM";
+        assert_eq!(workspace.text("file:///b.st"), Some(edited));
+        let tree = workspace.tree("file:///b.st").unwrap();
+        assert_eq!(tree.green(), SyntaxNode::parse(edited).green());
+
+        // Without errors the lints are reported too
+        workspace.update(
+            "file:///b.st",
+            "PROGRAM B VAR x : INT; END_VAR x := 1; END_PROGRAM".into(),
+        );
+        workspace.analyze();
+        let (_, problems) = workspace.problems().last().unwrap();
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| (p.severity, p.message.as_str()))
+                .collect::<Vec<_>>(),
+            [(
+                Severity::Warning,
+                "'x' is assigned, but never read. [write-only-variable]"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reports_instances_the_runtime_cannot_run() {
+        let mut workspace = Workspace::new();
+        workspace.update(
+            "file:///main.st",
+            "PROGRAM Main VAR_INPUT x : INT; END_VAR END_PROGRAM".into(),
+        );
+        workspace.update(
+            "file:///plant.st",
+            "CONFIGURATION Plant RESOURCE Cpu ON PLC
+    PROGRAM First : Main(x := 1);
+    PROGRAM Second : Main;
+END_RESOURCE END_CONFIGURATION"
+                .into(),
+        );
+        workspace.analyze();
+        let (uri, problems) = workspace.problems().last().unwrap();
+        assert_eq!(uri, "file:///plant.st");
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| (p.severity, p.range.start.line, p.message.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    Severity::Error,
+                    1,
+                    "Program instance 'First' cannot take arguments."
+                ),
+                (
+                    Severity::Error,
+                    2,
+                    "Program 'Main' is instantiated more than once."
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_only_changed_documents() {
+        let mut workspace = Workspace::new();
+        workspace.update(
+            "file:///a.st",
+            "PROGRAM A VAR x : INT; END_VAR x := 1; END_PROGRAM".into(),
+        );
+        workspace.update("file:///b.st", "PROGRAM B\n  y := 2;\nEND_PROGRAM".into());
+        workspace.analyze();
+        assert_eq!(workspace.parses, 2);
+        workspace.analyze();
+        assert_eq!(workspace.parses, 2);
+
+        // An edit of the first document moves the second within the project without parsing it
+        let start = Position {
+            line: 0,
+            character: 36,
+        };
+        let end = Position {
+            line: 0,
+            character: 37,
+        };
+        workspace.edit("file:///a.st", start..end, "x + 10".into());
+        workspace.analyze();
+        assert_eq!(workspace.parses, 3);
+        let project = workspace.project.as_ref().unwrap();
+        assert!(
+            project
+                .sources
+                .file(FileId(0))
+                .text
+                .contains("x := x + 10;")
+        );
+        let (uri, problems) = workspace.problems().last().unwrap();
+        assert_eq!(uri, "file:///b.st");
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert_eq!(
+            problems[0].range.start,
+            Position {
+                line: 1,
+                character: 2
+            }
+        );
+    }
+}
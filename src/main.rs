@@ -1,3 +1,641 @@
-fn main() {
-    println!("Hello, world!");
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use strooct::{
+    diagnostic::{Diagnostic, Severity},
+    lint::{LintLevels, lint, metrics, plcopen, tasks},
+    parsing::{formatter, lexer::Lexer, textmate},
+    project::{Project, SourceDb, manifest::Manifest},
+    runtime::{
+        Engine, EngineKind,
+        interpreter::Interpreter,
+        scheduler::{self, Configuration, Scheduler},
+        testing,
+        vm::Vm,
+    },
+    semantic::types::TypeEnv,
+};
+
+const USAGE: &str = "Usage: strooct <command> [files, directories or strooct.toml...] [options]
+
+Commands:
+    lex      Print the tokens of each file
+    parse    Print the syntax tree of each file
+    check    Report the diagnostics and lints of all files as one project
+    plcopen  Check the PLCopen coding guidelines and print a compliance report
+    tasks    Print the globals and I/O each task accesses, reporting conflicts between tasks
+             --tasks <tasks.toml>  the tasks of a configuration file instead of the project's
+    metrics  Print the complexity, size, Halstead and call metrics of every POU, method and action
+             --json                as JSON instead of a table
+    run      Run the programs of all files
+             --cycles <n>          scan cycles of every program, 1 by default
+             --duration <time>     run the tasks of the CONFIGURATION for a virtual time
+             --tasks <tasks.toml>  run the tasks of a configuration file, for 1s or --duration
+    test     Run the unit tests of all files
+             --junit <report.xml>  also write the results as JUnit XML
+    fmt      Format the files in place
+             --check               only list the files that are not formatted, failing if any are
+    lsp      Serve the Language Server Protocol over stdio for the project of the editor
+    grammar  Print a TextMate grammar for editors, generated from the keywords of the lexer
+
+Without paths the strooct.toml in the working directory describes the project. Its tasks run
+unless --cycles is given.";
+
+/// Exit code of invalid command lines and unreadable files, sources with errors exit with 1.
+const USAGE_ERROR: u8 = 2;
+
+/// Native stack of the thread running a command.
+const STACK_SIZE: usize = 64 << 20;
+
+/// Command line interface of the toolchain.
+///
+/// Every command takes source files and directories, which are searched for `.st`, `.typ` and
+/// `.var` files, or a project manifest. The files of a command form one project, so declarations
+/// in one file are visible in all others. Without paths the `strooct.toml` in the working
+/// directory describes the project.
+fn main() -> ExitCode {
+    // Interpreted calls nest on the native stack, as deep as the interpreter allows
+    match std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(command)
+    {
+        // A panic was already printed
+        Ok(worker) => worker.join().unwrap_or(ExitCode::FAILURE),
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn command() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(USAGE_ERROR);
+    };
+    let allowed: &[&str] = match command.as_str() {
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        "lsp" if args.is_empty() => {
+            return match strooct::lsp::serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+                Ok(true) => ExitCode::SUCCESS,
+                Ok(false) => ExitCode::FAILURE,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::from(USAGE_ERROR)
+                }
+            };
+        }
+        "grammar" if args.is_empty() => {
+            println!("{}", textmate::grammar());
+            return ExitCode::SUCCESS;
+        }
+        "lsp" | "grammar" => {
+            return usage_error(&format!("The '{command}' command takes no arguments."));
+        }
+        "run" => &["cycles", "duration", "tasks"],
+        "tasks" => &["tasks"],
+        "metrics" => &["json"],
+        "test" => &["junit"],
+        "fmt" => &["check"],
+        "lex" | "parse" | "check" | "plcopen" => &[],
+        command => return usage_error(&format!("Unknown command '{command}'.")),
+    };
+    let (paths, options) = match split_options(args) {
+        Ok(split) => split,
+        Err(e) => return usage_error(&e),
+    };
+    if let Some((option, _)) = options.iter().find(|(o, _)| !allowed.contains(o)) {
+        return usage_error(&format!("Unknown option '--{option}' of '{command}'."));
+    }
+    let (sources, manifest) = match load(&paths) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+    };
+
+    match command.as_str() {
+        "lex" => lex(&sources),
+        "parse" => dump_syntax_trees(&sources),
+        "check" => check(sources, manifest.as_ref()),
+        "plcopen" => check_guidelines(sources, manifest.as_ref()),
+        "tasks" => check_tasks(sources, manifest.as_ref(), &options),
+        "metrics" => print_metrics(sources, option(&options, "json").is_some()),
+        "run" => run(sources, manifest.as_ref(), &options),
+        "fmt" => format_files(
+            &sources,
+            manifest.as_ref(),
+            option(&options, "check").is_some(),
+        ),
+        _ => run_tests(sources, manifest.as_ref(), &options),
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{message}\n\n{USAGE}");
+    ExitCode::from(USAGE_ERROR)
+}
+
+/// Options like `--cycles 10` by their name, flags like `--check` have an empty value.
+type Options<'a> = Vec<(&'a str, String)>;
+
+/// Options which take no value.
+const FLAGS: [&str; 2] = ["check", "json"];
+
+/// Separates paths from options.
+fn split_options(args: &[String]) -> Result<(Vec<String>, Options<'_>), String> {
+    let mut paths = Vec::new();
+    let mut options = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(option) if FLAGS.contains(&option) => options.push((option, String::new())),
+            Some(option) => match args.next() {
+                Some(value) => options.push((option, value.clone())),
+                None => return Err(format!("Option '{arg}' needs a value.")),
+            },
+            None => paths.push(arg.clone()),
+        }
+    }
+    Ok((paths, options))
+}
+
+fn option<'a>(options: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|(o, _)| *o == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Loads the sources of the paths, which may name a manifest or a directory holding one.
+///
+/// Without paths the manifest in the working directory describes the project.
+fn load(paths: &[String]) -> Result<(SourceDb, Option<Manifest>), String> {
+    let is_manifest = |path: &String| {
+        let path = Path::new(path);
+        path.extension().is_some_and(|e| e == "toml") || path.join(Manifest::FILE_NAME).is_file()
+    };
+    let manifest = match paths.iter().position(is_manifest) {
+        Some(index) => Some((index, Manifest::load(Path::new(&paths[index]))?)),
+        None if paths.is_empty() && Path::new(Manifest::FILE_NAME).is_file() => {
+            Some((0, Manifest::load(Path::new(Manifest::FILE_NAME))?))
+        }
+        None if paths.is_empty() => {
+            return Err(format!(
+                "No source files are given and there is no {}.",
+                Manifest::FILE_NAME
+            ));
+        }
+        None => None,
+    };
+
+    let Some((index, manifest)) = manifest else {
+        return Ok((SourceDb::load(paths)?, None));
+    };
+    let mut sources = manifest.sources()?;
+    let others = paths
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, p)| p)
+        .collect::<Vec<_>>();
+    for (_, file) in SourceDb::load(&others)?.files() {
+        sources.add(file.name.clone(), file.text.clone());
+    }
+    Ok((sources, Some(manifest)))
+}
+
+/// Parses, resolves and checks all files, printing their errors and diagnostics, unless they
+/// have errors.
+fn analyze(sources: SourceDb) -> Option<Project> {
+    let project = match Project::analyze(sources) {
+        Ok(project) => project,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{error}");
+            }
+            return None;
+        }
+    };
+    for diagnostic in &project.diagnostics {
+        eprintln!("{}", project.sources.format(diagnostic));
+    }
+    match project.has_errors() {
+        true => None,
+        false => Some(project),
+    }
+}
+
+/// The tasks of the CONFIGURATION declarations of a project, reporting why they cannot be scheduled.
+fn source_tasks(project: &Project) -> Option<Configuration> {
+    match Configuration::from_source(&project.ast, &project.table) {
+        Ok(config) if config.tasks.is_empty() => {
+            eprintln!("The source declares no tasks.");
+            None
+        }
+        Ok(config) => Some(config),
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", project.sources.format(diagnostic));
+            }
+            None
+        }
+    }
+}
+
+/// Analyzes all files and lints them at the levels of the manifest, failing on errors and on
+/// denied lints.
+fn check(sources: SourceDb, manifest: Option<&Manifest>) -> ExitCode {
+    let levels = match LintLevels::new(manifest.map_or(&[], |m| m.lints.as_slice())) {
+        Ok(levels) => levels,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+    };
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+    let lints = lint(&project, &levels);
+    for diagnostic in &lints {
+        eprintln!("{}", project.sources.format(diagnostic));
+    }
+    match lints.iter().any(Diagnostic::is_error) {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
+
+fn check_guidelines(sources: SourceDb, manifest: Option<&Manifest>) -> ExitCode {
+    let guidelines = manifest.map(|m| m.guidelines.clone()).unwrap_or_default();
+    let settings = match plcopen::Settings::new(&guidelines) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+    };
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+    let report = plcopen::check(&project, &settings);
+    for (_, diagnostic) in &report.violations {
+        eprintln!("{}", project.sources.format(diagnostic));
+    }
+    println!("{}", report.summary());
+    match report.is_compliant() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// Analyses the tasks of a configuration file, the manifest or the CONFIGURATION in the source.
+fn check_tasks(
+    sources: SourceDb,
+    manifest: Option<&Manifest>,
+    options: &[(&str, String)],
+) -> ExitCode {
+    let config = match option(options, "tasks").map(Configuration::load) {
+        Some(Ok(config)) => Some(config),
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+        None => manifest.and_then(|m| m.tasks.clone()),
+    };
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+    let Some(config) = config.or_else(|| source_tasks(&project)) else {
+        return ExitCode::FAILURE;
+    };
+    let analysis = match tasks::analyze(&project, &config) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    for diagnostic in &analysis.conflicts {
+        eprintln!("{}", project.sources.format(diagnostic));
+    }
+    print!("{}", analysis.report());
+    match analysis.conflicts.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+fn print_metrics(sources: SourceDb, json: bool) -> ExitCode {
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+    let measured = metrics::measure(&project);
+    match json {
+        true => println!("{}", metrics::to_json(&measured)),
+        false => print!("{}", metrics::table(&measured)),
+    }
+    ExitCode::SUCCESS
+}
+
+fn lex(sources: &SourceDb) -> ExitCode {
+    // Writing stops quietly when the output is closed, like when piped into `head`
+    let mut out = std::io::stdout().lock();
+    for (_, file) in sources.files() {
+        let name = &file.name;
+        for token in Lexer::create(name, &file.text) {
+            let span = token.span();
+            if writeln!(
+                out,
+                "{name}:{}:{}\t{:?}",
+                span.line + 1,
+                span.col,
+                token.token
+            )
+            .is_err()
+            {
+                return ExitCode::SUCCESS;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn dump_syntax_trees(sources: &SourceDb) -> ExitCode {
+    let mut out = std::io::stdout().lock();
+    let mut valid = true;
+    for (id, file) in sources.files() {
+        let name = &file.name;
+        match sources.parse_file(id) {
+            Ok(ast) => {
+                if writeln!(out, "{name}\n{ast:#?}").is_err() {
+                    break;
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", error.printable);
+                }
+                valid = false;
+            }
+        }
+    }
+    match valid {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// Formats the files in place, or with `--check` lists those that are not formatted.
+///
+/// The files of the libraries a manifest depends on are left as they are.
+fn format_files(sources: &SourceDb, manifest: Option<&Manifest>, check: bool) -> ExitCode {
+    // Libraries are loaded before the files of the project
+    let libraries = match manifest.map(|m| (m.sources(), m.source_files())) {
+        Some((Ok(all), Ok(own))) => all.len() - own.len(),
+        Some((Err(e), _) | (_, Err(e))) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+        None => 0,
+    };
+
+    let mut valid = true;
+    for (_, file) in sources.files().skip(libraries) {
+        let formatted = match formatter::format(&file.name, &file.text) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{e}");
+                valid = false;
+                continue;
+            }
+        };
+        if formatted == file.text {
+            continue;
+        }
+        if check {
+            println!("{}", file.name);
+            valid = false;
+        } else if let Err(e) = std::fs::write(&file.name, formatted) {
+            eprintln!("Cannot write {}: {e}", file.name);
+            return ExitCode::from(USAGE_ERROR);
+        }
+    }
+    match valid {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// Runs the programs of the sources and prints their variables.
+///
+/// Without tasks every PROGRAM runs for a number of scan cycles. Given a task configuration file,
+/// or a duration for the tasks of the CONFIGURATION in the source, the scheduler runs the tasks
+/// on a virtual clock and prints their timing statistics.
+fn run(sources: SourceDb, manifest: Option<&Manifest>, options: &[(&str, String)]) -> ExitCode {
+    let duration = match option(options, "duration").map(scheduler::parse_duration) {
+        Some(Some(duration)) => Some(duration),
+        Some(None) => return usage_error("The duration must look like 100ms or T#1s."),
+        None => None,
+    };
+    let cycles = match option(options, "cycles").map(|c| c.parse::<usize>()) {
+        Some(Ok(cycles)) if cycles > 0 => Some(cycles),
+        Some(_) => return usage_error("The number of cycles must be a positive integer."),
+        None => None,
+    };
+    // The tasks of the manifest run unless cycles are asked for
+    let config = match option(options, "tasks").map(Configuration::load) {
+        Some(Ok(config)) => Some(config),
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return ExitCode::from(USAGE_ERROR);
+        }
+        None => manifest
+            .and_then(|m| m.tasks.clone())
+            .filter(|_| cycles.is_none()),
+    };
+    let cycles = cycles.unwrap_or(1);
+    let engine = manifest.map_or(EngineKind::default(), |m| m.engine);
+
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+    let (ast, table) = (&project.ast, &project.table);
+    match (config, duration) {
+        (Some(config), duration) => {
+            let duration = duration.unwrap_or(1_000_000_000);
+            return run_tasks(&project, &config, duration);
+        }
+        (None, Some(duration)) => {
+            return match source_tasks(&project) {
+                Some(config) => run_tasks(&project, &config, duration),
+                None => ExitCode::FAILURE,
+            };
+        }
+        (None, None) => {}
+    }
+
+    // Programs the bytecode compiler cannot handle yet still run on the interpreter
+    let vm = match engine {
+        EngineKind::Vm => match Vm::new(ast, table) {
+            Ok(vm) => Some(vm),
+            Err(e) => {
+                let note = Diagnostic {
+                    severity: Severity::Info,
+                    message: format!("{} The programs run on the interpreter instead.", e.message),
+                    ..e
+                };
+                eprintln!("{}", project.sources.format(&note));
+                None
+            }
+        },
+        EngineKind::Interpreter => None,
+    };
+    let engine: Result<Box<dyn Engine>, _> = match vm {
+        Some(vm) => Ok(Box::new(vm)),
+        None => Interpreter::new(ast, table).map(|i| Box::new(i) as Box<dyn Engine>),
+    };
+    // Unit tests only run by `strooct test`
+    let tests = testing::discover(ast).unwrap_or_default();
+    let result = engine.and_then(|mut engine| {
+        let programs = engine
+            .programs()
+            .into_iter()
+            .filter(|id| {
+                let name = table.qualified_name(*id);
+                !tests.iter().any(|t| t.program.eq_ignore_ascii_case(&name))
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..cycles {
+            for program in &programs {
+                engine.run_cycle(*program)?;
+            }
+        }
+        let env = TypeEnv::new(table);
+        for program in programs {
+            let name = table.qualified_name(program);
+            if let Some(value) = engine.read(&name) {
+                println!("{name} = {}", value.display(&env));
+            }
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", project.sources.format(&e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the configured tasks on the VM, whose cost model provides the execution times.
+fn run_tasks(project: &Project, config: &Configuration, duration: u64) -> ExitCode {
+    let (table, sources) = (&project.table, &project.sources);
+    let vm = match Vm::new(&project.ast, table) {
+        Ok(vm) => vm,
+        Err(e) => {
+            eprintln!("{}", sources.format(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut scheduler = match Scheduler::new(vm, config) {
+        Ok(scheduler) => scheduler,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = scheduler.run_for(duration) {
+        eprintln!("{}", sources.format(&e));
+        return ExitCode::FAILURE;
+    }
+
+    print!("{}", scheduler.report());
+    for overrun in scheduler.overruns() {
+        println!(
+            "Overrun of task {} released at {} finished at {}",
+            overrun.task,
+            scheduler::format_duration(overrun.release),
+            scheduler::format_duration(overrun.finish)
+        );
+    }
+    let tests = testing::discover(&project.ast).unwrap_or_default();
+    let env = TypeEnv::new(table);
+    for program in scheduler.vm().programs() {
+        let name = table.qualified_name(program);
+        if tests.iter().any(|t| t.program.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+        if let Some(value) = scheduler.vm().read(&name) {
+            println!("{name} = {}", value.display(&env));
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Runs the unit tests of the sources, optionally writing a JUnit XML report.
+fn run_tests(
+    mut sources: SourceDb,
+    manifest: Option<&Manifest>,
+    options: &[(&str, String)],
+) -> ExitCode {
+    let tests = match sources.parse() {
+        Ok(ast) => testing::discover(&ast),
+        Err(errors) => Err(errors.join("\n")),
+    };
+    let tests = match tests {
+        Ok(tests) => tests,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let suite = match manifest {
+        Some(manifest) => manifest.name.clone(),
+        None => sources
+            .files()
+            .next()
+            .map_or(String::new(), |(_, f)| f.name.clone()),
+    };
+
+    // Function block tests run in generated programs, added as another file
+    sources.add("<test harness>", testing::harness(&tests));
+    let Some(project) = analyze(sources) else {
+        return ExitCode::FAILURE;
+    };
+
+    let engine = manifest.map_or(EngineKind::default(), |m| m.engine);
+    let results = testing::run(&project.ast, &project.table, &tests, engine);
+    let describe = |d: &Diagnostic| project.sources.format(d);
+    print!("{}", testing::report(&results, &describe));
+    let report = match option(options, "junit") {
+        Some(report) => Some(PathBuf::from(report)),
+        None => manifest.and_then(|m| m.outputs.junit.clone()),
+    };
+    if let Some(report) = report {
+        let xml = testing::junit(&suite, &results, &describe);
+        let written = match report.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir),
+            _ => Ok(()),
+        }
+        .and_then(|_| std::fs::write(&report, xml));
+        if let Err(e) = written {
+            eprintln!("Cannot write {}: {e}", report.display());
+            return ExitCode::from(USAGE_ERROR);
+        }
+    }
+    match results.iter().all(|r| r.passed()) {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
 }
@@ -1,4 +1,7 @@
 pub mod ast;
+pub mod formatter;
 pub mod lexer;
 pub mod parser;
+pub mod syntax;
+pub mod textmate;
 pub mod token;
@@ -1,5 +1,54 @@
-use crate::parsing::token::NumberValue;
+use crate::parsing::token::{DateValue, NumberValue, TimeValue};
 
+mod spans;
+
+pub use spans::VisitSpans;
+
+/// Location of a syntax element inside its source file.
+///
+/// Tests comparing trees regardless of where they were parsed use `assert_same_tree!`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub pos: usize,
+    pub len: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Like `assert_eq!`, but ignores the locations of syntax elements.
+#[cfg(test)]
+macro_rules! assert_same_tree {
+    ($left:expr, $right:expr $(,)?) => {
+        assert_eq!(
+            $crate::parsing::ast::VisitSpans::without_spans(&$left),
+            $crate::parsing::ast::VisitSpans::without_spans(&$right),
+            "the trees differ"
+        )
+    };
+}
+
+#[cfg(test)]
+pub(crate) use assert_same_tree;
+
+impl Span {
+    pub fn end(&self) -> usize {
+        self.pos + self.len
+    }
+
+    /// Span reaching from the start of `self` to the end of `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            len: other.end().saturating_sub(self.pos),
+            ..*self
+        }
+    }
+
+    pub fn contains(&self, pos: usize) -> bool {
+        self.pos <= pos && pos <= self.end()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Ast {
     pub blocks: Vec<Block>,
 }
@@ -10,60 +59,497 @@ impl Ast {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Block {
-    Program(String, Statements),
+    Program(Pou),
+    Function(Pou),
+    FunctionBlock(Pou),
+    Action(Action),
+    Type(Vec<TypeDeclaration>),
+    GlobalVariables(VariableBlock),
+    Namespace(Namespace),
+    Configuration(Configuration),
+}
+
+/// Program organization unit: a PROGRAM, FUNCTION, FUNCTION_BLOCK or METHOD.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pou {
+    pub name: Identifier,
+    pub return_type: Option<DataType>,
+    pub variables: Vec<VariableBlock>,
+    pub statements: Statements,
+    pub methods: Vec<Pou>,
+    pub actions: Vec<Action>,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+}
+
+impl Pou {
+    pub fn new(name: Identifier) -> Self {
+        let span = name.span;
+        Self {
+            name,
+            return_type: None,
+            variables: Vec::new(),
+            statements: Vec::new(),
+            methods: Vec::new(),
+            actions: Vec::new(),
+            attributes: Vec::new(),
+            span,
+        }
+    }
+
+    /// Finds an attribute by its case-insensitive name.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A pragma like `{attribute 'name'}` or `{attribute 'name' := 'value'}` in front of a POU.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub value: Option<String>,
+    pub span: Span,
+}
+
+/// An ACTION either nested inside its POU or declared on top level as `Owner.Name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action {
+    pub owner: Option<Identifier>,
+    pub name: Identifier,
+    pub statements: Statements,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Namespace {
+    pub name: QualifiedName,
+    pub blocks: Vec<Block>,
+    pub span: Span,
+}
+
+/// A CONFIGURATION describing on which resources and in which tasks programs execute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Configuration {
+    pub name: Identifier,
+    pub variables: Vec<VariableBlock>,
+    pub resources: Vec<Resource>,
+    pub span: Span,
+}
+
+/// A RESOURCE of a configuration, running on a processor named after `ON`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resource {
+    pub name: Identifier,
+    pub processor: Identifier,
+    pub variables: Vec<VariableBlock>,
+    pub tasks: Vec<Task>,
+    pub programs: Vec<ProgramInstance>,
+    pub span: Span,
+}
+
+/// A TASK like `Fast(INTERVAL := T#10ms, PRIORITY := 1)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    pub name: Identifier,
+    /// Cycle time of a cyclic task
+    pub interval: Option<Expression>,
+    /// Trigger of an event task, which runs on its rising edge
+    pub single: Option<Expression>,
+    pub priority: Option<Expression>,
+    pub span: Span,
+}
+
+/// A program instance like `PROGRAM Main WITH Fast : MainProgram(input := 1);`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramInstance {
+    pub name: Identifier,
+    pub task: Option<Identifier>,
+    pub program: QualifiedName,
+    pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Identifier {
+    pub fn new(name: &str, span: Span) -> Self {
+        Self {
+            name: name.to_string(),
+            span,
+        }
+    }
+
+    /// Identifiers are case-insensitive, this is the canonical spelling to compare them by.
+    pub fn key(&self) -> String {
+        self.name.to_ascii_uppercase()
+    }
+}
+
+/// A dotted name like `Ns.Sub.Item`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualifiedName {
+    pub parts: Vec<Identifier>,
+}
+
+impl QualifiedName {
+    pub fn last(&self) -> &Identifier {
+        self.parts.last().expect("Qualified names are never empty")
+    }
+
+    pub fn span(&self) -> Span {
+        self.parts[0].span.to(self.last().span)
+    }
+
+    pub fn to_printable(&self) -> String {
+        self.parts
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VariableKind {
+    Local,
+    Input,
+    Output,
+    InOut,
+    Temp,
+    Global,
+    External,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableBlock {
+    pub kind: VariableKind,
+    pub constant: bool,
+    pub retain: bool,
+    pub declarations: Vec<VariableDeclaration>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableDeclaration {
+    pub names: Vec<Identifier>,
+    pub location: Option<Identifier>,
+    pub data_type: DataType,
+    pub initializer: Option<Expression>,
 }
 
-type Statements = Vec<Statement>;
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataType {
+    Named(QualifiedName),
+    String {
+        wide: bool,
+        length: Option<Box<Expression>>,
+        span: Span,
+    },
+    Array {
+        ranges: Vec<Range>,
+        element: Box<DataType>,
+        span: Span,
+    },
+    Pointer(Box<DataType>, Span),
+    Reference(Box<DataType>, Span),
+    Subrange {
+        base: QualifiedName,
+        range: Range,
+    },
+    Enum {
+        base: Option<QualifiedName>,
+        values: Vec<EnumValue>,
+        span: Span,
+    },
+    Struct(Vec<VariableDeclaration>, Span),
+    Union(Vec<VariableDeclaration>, Span),
+}
+
+impl DataType {
+    pub fn span(&self) -> Span {
+        match self {
+            DataType::Named(n) => n.span(),
+            DataType::Subrange { base, range } => base.span().to(range.upper.span()),
+            DataType::String { span, .. }
+            | DataType::Array { span, .. }
+            | DataType::Pointer(_, span)
+            | DataType::Reference(_, span)
+            | DataType::Enum { span, .. }
+            | DataType::Struct(_, span)
+            | DataType::Union(_, span) => *span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    pub lower: Expression,
+    pub upper: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumValue {
+    pub name: Identifier,
+    pub value: Option<Expression>,
+}
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeDeclaration {
+    pub name: Identifier,
+    pub data_type: DataType,
+    pub initializer: Option<Expression>,
+}
+
+pub type Statements = Vec<Statement>;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Statement {
-    Empty,
+    Empty(Span),
     Expression(Expression),
-    Return,
-    Exit,
+    Assignment(Assignment),
+    Return(Span),
+    Exit(Span),
+    Continue(Span),
     If(IfCondition),
+    Case(CaseStatement),
+    For(ForLoop),
+    While(WhileLoop),
+    Repeat(RepeatLoop),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Empty(span)
+            | Statement::Return(span)
+            | Statement::Exit(span)
+            | Statement::Continue(span) => *span,
+            Statement::Expression(e) => e.span(),
+            Statement::Assignment(a) => a.target.span().to(a.value.span()),
+            Statement::If(x) => x.span,
+            Statement::Case(x) => x.span,
+            Statement::For(x) => x.span,
+            Statement::While(x) => x.span,
+            Statement::Repeat(x) => x.span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub target: Expression,
+    pub value: Expression,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
-    Literal(LiteralExpression),
+    Literal(LiteralExpression, Span),
+    Identifier(Identifier),
+    Prefix(PrefixExpression),
     Infix(InfixExpression),
+    Member(MemberExpression),
+    Index(IndexExpression),
+    Call(CallExpression),
+    Deref(Box<Expression>, Span),
+    TypedLiteral(TypedLiteral),
+    Array(Vec<Expression>, Span),
+    Struct(Vec<(Identifier, Expression)>, Span),
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal(_, span)
+            | Expression::Deref(_, span)
+            | Expression::Array(_, span)
+            | Expression::Struct(_, span) => *span,
+            Expression::Identifier(i) => i.span,
+            Expression::Prefix(x) => x.span.to(x.operand.span()),
+            Expression::Infix(x) => x.left.span().to(x.right.span()),
+            Expression::Member(x) => x.target.span().to(x.member.span),
+            Expression::Index(x) => x.target.span().to(x.span),
+            Expression::Call(x) => x.callee.span().to(x.span),
+            Expression::TypedLiteral(x) => x.type_name.span().to(x.value.span()),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum LiteralExpression {
     Number(NumberValue),
     True,
     False,
+    String(String, bool),
+    Time(Box<TimeValue>),
+    Date(Box<DateValue>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrefixExpression {
+    pub op: PrefixOperator,
+    pub operand: Box<Expression>,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrefixOperator {
+    Negation,
+    Not,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct InfixExpression {
     pub left: Box<Expression>,
     pub right: Box<Expression>,
     pub op: InfixOperator,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InfixOperator {
     Addition,
     Subtraction,
     Multiplication,
     Division,
     Modulo,
+    Power,
+    Equals,
+    NotEquals,
+    GreaterThan,
+    GreaterThanOrEquals,
+    LessThan,
+    LessThanOrEquals,
+    And,
+    Or,
+    Xor,
+}
+
+impl InfixOperator {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            InfixOperator::Equals
+                | InfixOperator::NotEquals
+                | InfixOperator::GreaterThan
+                | InfixOperator::GreaterThanOrEquals
+                | InfixOperator::LessThan
+                | InfixOperator::LessThanOrEquals
+        )
+    }
+
+    pub fn is_logical(&self) -> bool {
+        matches!(
+            self,
+            InfixOperator::And | InfixOperator::Or | InfixOperator::Xor
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberExpression {
+    pub target: Box<Expression>,
+    pub member: Identifier,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexExpression {
+    pub target: Box<Expression>,
+    pub indices: Vec<Expression>,
+    /// Span of the closing bracket
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    pub arguments: Vec<Argument>,
+    /// Span of the closing parenthesis
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    Positional(Expression),
+    Named(Identifier, Expression),
+    Output(Identifier, Expression),
+}
+
+impl Argument {
+    pub fn value(&self) -> &Expression {
+        match self {
+            Argument::Positional(e) | Argument::Named(_, e) | Argument::Output(_, e) => e,
+        }
+    }
+}
+
+/// A literal with an explicit type like `INT#5` or an enum value like `Color#Red`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedLiteral {
+    pub type_name: QualifiedName,
+    pub value: Box<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct IfCondition {
-    pub branch : IfConditionalBranch,
+    pub branch: IfConditionalBranch,
     pub alt_branches: Vec<IfConditionalBranch>,
     pub fallback: Option<Statements>,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct IfConditionalBranch {
     pub condition: Expression,
     pub statements: Statements,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseStatement {
+    pub selector: Expression,
+    pub branches: Vec<CaseBranch>,
+    pub fallback: Option<Statements>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseBranch {
+    pub labels: Vec<CaseLabel>,
+    pub statements: Statements,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaseLabel {
+    Value(Expression),
+    Range(Range),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForLoop {
+    pub variable: Identifier,
+    pub start: Expression,
+    pub end: Expression,
+    pub step: Option<Expression>,
+    pub statements: Statements,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhileLoop {
+    pub condition: Expression,
+    pub statements: Statements,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RepeatLoop {
+    pub statements: Statements,
+    pub condition: Expression,
+    pub span: Span,
+}
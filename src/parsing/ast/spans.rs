@@ -0,0 +1,226 @@
+use crate::parsing::ast::{
+    Action, Argument, Assignment, Ast, Attribute, Block, CallExpression, CaseBranch, CaseLabel,
+    CaseStatement, Configuration, DataType, EnumValue, Expression, ForLoop, Identifier,
+    IfCondition, IfConditionalBranch, IndexExpression, InfixExpression, MemberExpression,
+    Namespace, Pou, PrefixExpression, ProgramInstance, QualifiedName, Range, RepeatLoop, Resource,
+    Span, Statement, Task, TypeDeclaration, TypedLiteral, VariableBlock, VariableDeclaration,
+    WhileLoop,
+};
+
+/// Syntax elements holding spans, which can be changed all at once, like to move the tree of a
+/// file to where the file starts in its project.
+pub trait VisitSpans {
+    /// Calls the function with every span of the element and of the elements within it.
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span));
+
+    /// A copy of the element with all spans empty, to compare elements regardless of where they
+    /// were parsed.
+    fn without_spans(&self) -> Self
+    where
+        Self: Clone,
+    {
+        let mut element = self.clone();
+        element.visit_spans(&mut |span| *span = Span::default());
+        element
+    }
+}
+
+impl VisitSpans for Span {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        f(self)
+    }
+}
+
+impl<T: VisitSpans> VisitSpans for Box<T> {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        (**self).visit_spans(f)
+    }
+}
+
+impl<T: VisitSpans> VisitSpans for Option<T> {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        if let Some(element) = self {
+            element.visit_spans(f)
+        }
+    }
+}
+
+impl<T: VisitSpans> VisitSpans for Vec<T> {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        for element in self {
+            element.visit_spans(f)
+        }
+    }
+}
+
+impl<A: VisitSpans, B: VisitSpans> VisitSpans for (A, B) {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        self.0.visit_spans(f);
+        self.1.visit_spans(f);
+    }
+}
+
+/// Visits the spans of the fields of structs, which hold no other spans.
+macro_rules! visit_fields {
+    ($($name:ident { $($field:ident),* $(,)? })*) => {
+        $(
+            impl VisitSpans for $name {
+                fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+                    $(self.$field.visit_spans(f);)*
+                }
+            }
+        )*
+    };
+}
+
+visit_fields! {
+    Ast { blocks }
+    Pou { name, return_type, variables, statements, methods, actions, attributes, span }
+    Attribute { span }
+    Action { owner, name, statements, span }
+    Namespace { name, blocks, span }
+    Configuration { name, variables, resources, span }
+    Resource { name, processor, variables, tasks, programs, span }
+    Task { name, interval, single, priority, span }
+    ProgramInstance { name, task, program, arguments, span }
+    Identifier { span }
+    QualifiedName { parts }
+    VariableBlock { declarations, span }
+    VariableDeclaration { names, location, data_type, initializer }
+    Range { lower, upper }
+    EnumValue { name, value }
+    TypeDeclaration { name, data_type, initializer }
+    Assignment { target, value }
+    PrefixExpression { operand, span }
+    InfixExpression { left, right }
+    MemberExpression { target, member }
+    IndexExpression { target, indices, span }
+    CallExpression { callee, arguments, span }
+    TypedLiteral { type_name, value }
+    IfCondition { branch, alt_branches, fallback, span }
+    IfConditionalBranch { condition, statements }
+    CaseStatement { selector, branches, fallback, span }
+    CaseBranch { labels, statements }
+    ForLoop { variable, start, end, step, statements, span }
+    WhileLoop { condition, statements, span }
+    RepeatLoop { statements, condition, span }
+}
+
+impl VisitSpans for Block {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                pou.visit_spans(f)
+            }
+            Block::Action(action) => action.visit_spans(f),
+            Block::Type(declarations) => declarations.visit_spans(f),
+            Block::GlobalVariables(variables) => variables.visit_spans(f),
+            Block::Namespace(namespace) => namespace.visit_spans(f),
+            Block::Configuration(configuration) => configuration.visit_spans(f),
+        }
+    }
+}
+
+impl VisitSpans for DataType {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            DataType::Named(name) => name.visit_spans(f),
+            DataType::String { length, span, .. } => {
+                length.visit_spans(f);
+                span.visit_spans(f);
+            }
+            DataType::Array {
+                ranges,
+                element,
+                span,
+            } => {
+                ranges.visit_spans(f);
+                element.visit_spans(f);
+                span.visit_spans(f);
+            }
+            DataType::Pointer(target, span) | DataType::Reference(target, span) => {
+                target.visit_spans(f);
+                span.visit_spans(f);
+            }
+            DataType::Subrange { base, range } => {
+                base.visit_spans(f);
+                range.visit_spans(f);
+            }
+            DataType::Enum { base, values, span } => {
+                base.visit_spans(f);
+                values.visit_spans(f);
+                span.visit_spans(f);
+            }
+            DataType::Struct(members, span) | DataType::Union(members, span) => {
+                members.visit_spans(f);
+                span.visit_spans(f);
+            }
+        }
+    }
+}
+
+impl VisitSpans for Statement {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Statement::Empty(span)
+            | Statement::Return(span)
+            | Statement::Exit(span)
+            | Statement::Continue(span) => span.visit_spans(f),
+            Statement::Expression(expression) => expression.visit_spans(f),
+            Statement::Assignment(assignment) => assignment.visit_spans(f),
+            Statement::If(condition) => condition.visit_spans(f),
+            Statement::Case(case) => case.visit_spans(f),
+            Statement::For(for_loop) => for_loop.visit_spans(f),
+            Statement::While(while_loop) => while_loop.visit_spans(f),
+            Statement::Repeat(repeat_loop) => repeat_loop.visit_spans(f),
+        }
+    }
+}
+
+impl VisitSpans for Expression {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Expression::Literal(_, span) => span.visit_spans(f),
+            Expression::Identifier(identifier) => identifier.visit_spans(f),
+            Expression::Prefix(prefix) => prefix.visit_spans(f),
+            Expression::Infix(infix) => infix.visit_spans(f),
+            Expression::Member(member) => member.visit_spans(f),
+            Expression::Index(index) => index.visit_spans(f),
+            Expression::Call(call) => call.visit_spans(f),
+            Expression::Deref(target, span) => {
+                target.visit_spans(f);
+                span.visit_spans(f);
+            }
+            Expression::TypedLiteral(literal) => literal.visit_spans(f),
+            Expression::Array(elements, span) => {
+                elements.visit_spans(f);
+                span.visit_spans(f);
+            }
+            Expression::Struct(members, span) => {
+                members.visit_spans(f);
+                span.visit_spans(f);
+            }
+        }
+    }
+}
+
+impl VisitSpans for Argument {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Argument::Positional(value) => value.visit_spans(f),
+            Argument::Named(name, value) | Argument::Output(name, value) => {
+                name.visit_spans(f);
+                value.visit_spans(f);
+            }
+        }
+    }
+}
+
+impl VisitSpans for CaseLabel {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            CaseLabel::Value(value) => value.visit_spans(f),
+            CaseLabel::Range(range) => range.visit_spans(f),
+        }
+    }
+}
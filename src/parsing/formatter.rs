@@ -0,0 +1,809 @@
+use crate::{
+    parsing::{
+        lexer::Lexer,
+        parser::parse,
+        token::{MarkedToken, Marker, Token},
+    },
+    project::conditional,
+    semantic::symbols::ELEMENTARY_TYPES,
+};
+
+/// Spaces per level of indentation.
+pub const INDENT: usize = 4;
+
+/// A token as it is written, or a whole `{...}` pragma, which is copied as it is.
+struct Word<'a> {
+    token: Token<'a>,
+    text: &'a str,
+    line: usize,
+    end_line: usize,
+    /// Whether a `+` or `-` is a sign rather than a binary operator
+    unary: bool,
+}
+
+/// The lines of the source, each with the words starting on it.
+struct Line<'a> {
+    words: Vec<Word<'a>>,
+    blank_before: bool,
+}
+
+/// A block of the source, which indents the lines within it.
+#[derive(Clone)]
+enum Scope<'a> {
+    Block(Token<'a>),
+    /// Labels of a CASE are indented once, their statements twice, the ELSE branch once.
+    Case {
+        otherwise: bool,
+    },
+}
+
+/// Scopes at a conditional compilation `{IF}` and at the end of its first branch.
+///
+/// Every branch starts from the scopes at the `{IF}`, and the code after the `{END_IF}` continues
+/// from the end of the first branch, so that blocks opened in several branches count once.
+struct Conditional<'a> {
+    start: Vec<Scope<'a>>,
+    first: Option<Vec<Scope<'a>>>,
+}
+
+/// A formatted line, with its declaration colon separated for alignment.
+struct Formatted {
+    indent: usize,
+    head: String,
+    colon: Option<String>,
+    blank_before: bool,
+}
+
+/// Formats a source.
+///
+/// The line breaks and comments of the source are kept, and lines holding several statements are
+/// split so that every statement starts a line. Keywords and elementary types are written in upper
+/// case, lines are indented by the blocks they are in, operators are separated by single spaces and
+/// the colons of consecutive declarations are aligned. At most one empty line separates two lines.
+/// Formatting a formatted source returns it unchanged.
+///
+/// The tokens of the result are those of the source, sources with illegal characters or which do
+/// not parse without defines are not formatted.
+pub fn format(src_file: &str, src: &str) -> Result<String, String> {
+    let tokens = Lexer::create(src_file, src).collect::<Vec<_>>();
+    if let Some(illegal) = tokens.iter().find(|t| t.token == Token::Illegal) {
+        return Err(format!(
+            "[ERR] in {} Illegal character, the source is not formatted.",
+            illegal.marker.format_as_printable()
+        ));
+    }
+    let active = conditional::apply(src, &[]).map_err(|(at, message)| {
+        let before = &src[..at];
+        let mut marker = Marker::create_at(src_file, src, 0, 0);
+        marker.set(
+            at,
+            before.matches('\n').count(),
+            before.rsplit('\n').next().map_or(0, |l| l.chars().count()),
+        );
+        format!(
+            "[ERR] in {} {message} The source is not formatted.",
+            marker.format_as_printable()
+        )
+    })?;
+    if let Err(errors) = parse(Lexer::create(src_file, &active)) {
+        return Err(format!(
+            "{}\n[ERR] {src_file} does not parse, it is not formatted.",
+            errors.join("\n")
+        ));
+    }
+
+    let lines = lines(words(src, tokens));
+    let formatted = indent(lines);
+    let result = render(formatted);
+
+    match significant(src_file, src) == significant(src_file, &result) {
+        true => Ok(result),
+        false => Err(format!(
+            "[ERR] Formatting {src_file} would change its tokens, it is not formatted."
+        )),
+    }
+}
+
+/// The tokens of a source, apart from trailing whitespace in comments and the case of elementary
+/// types.
+fn significant<'a>(src_file: &'a str, src: &'a str) -> Vec<Token<'a>> {
+    Lexer::create(src_file, src)
+        .map(|t| match t.token {
+            Token::Comment(c) => Token::Comment(c.trim_end()),
+            Token::Identifier(name) => Token::Identifier(elementary_type(name).unwrap_or(name)),
+            token => token,
+        })
+        .collect()
+}
+
+/// The upper case name of an elementary type.
+fn elementary_type(name: &str) -> Option<&'static str> {
+    ELEMENTARY_TYPES
+        .iter()
+        .find(|t| t.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+fn words<'a>(src: &'a str, tokens: Vec<MarkedToken<'a>>) -> Vec<Word<'a>> {
+    let mut words: Vec<Word> = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(marked) = tokens.next() {
+        let span = marked.span();
+        let mut end = span.pos + span.len;
+        // Braces hold pragmas like `{IF defined(SIM)}`, which are no code
+        if marked.token == Token::LeftBrace {
+            let rest = &src[span.pos..];
+            let closing = rest.find('}').filter(|i| !rest[..*i].contains('\n'));
+            if let Some(closing) = closing {
+                end = span.pos + closing + 1;
+                while tokens.next_if(|t| t.span().pos < end).is_some() {}
+            }
+        }
+        let text = match marked.token.keyword_text() {
+            Some(keyword) => keyword,
+            None => match &marked.token {
+                Token::Comment(c) => c.trim_end(),
+                Token::Identifier(name) => elementary_type(name).unwrap_or(name),
+                _ => &src[span.pos..end],
+            },
+        };
+        let operand = words
+            .iter()
+            .rev()
+            .find(|w| !matches!(w.token, Token::Comment(_)))
+            .is_some_and(|w| ends_operand(&w.token));
+        words.push(Word {
+            unary: matches!(marked.token, Token::Plus | Token::Minus) && !operand,
+            token: marked.token,
+            text,
+            line: span.line,
+            end_line: span.line + src[span.pos..end].matches('\n').count(),
+        });
+    }
+    words
+}
+
+fn lines<'a>(words: Vec<Word<'a>>) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.words.last().is_some_and(|w| w.end_line >= word.line) => {
+                line.words.push(word)
+            }
+            last => {
+                let blank_before = last
+                    .and_then(|l| l.words.last())
+                    .is_some_and(|w| word.line > w.end_line + 1);
+                lines.push(Line {
+                    words: vec![word],
+                    blank_before,
+                });
+            }
+        }
+    }
+    lines
+}
+
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::Number(..)
+            | Token::String(_)
+            | Token::Time(..)
+            | Token::Date(..)
+            | Token::DirectAddress(_)
+            | Token::RightParenthesis
+            | Token::RightBracket
+            | Token::Caret
+            | Token::True
+            | Token::False
+    )
+}
+
+/// Whether a line ending in the token continues on the next one.
+fn continues(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Plus
+            | Token::Minus
+            | Token::Asterisk
+            | Token::Slash
+            | Token::Percent
+            | Token::Power
+            | Token::Ampersand
+            | Token::Assign
+            | Token::Arrow
+            | Token::Equals
+            | Token::NotEquals
+            | Token::GreaterThan
+            | Token::GreaterThanOrEquals
+            | Token::LessThan
+            | Token::LessThanOrEquals
+            | Token::Comma
+            | Token::LeftParenthesis
+            | Token::LeftBracket
+            | Token::Not
+            | Token::And
+            | Token::Or
+            | Token::Xor
+            | Token::Mod
+    )
+}
+
+fn is_end(token: &Token) -> bool {
+    token.keyword_text().is_some_and(|k| k.starts_with("END_"))
+}
+
+fn opens_block(token: &Token, scopes: &[Scope]) -> bool {
+    match token {
+        // Within a configuration PROGRAM declares an instance
+        Token::Program => !matches!(
+            scopes.last(),
+            Some(Scope::Block(Token::Configuration | Token::Resource))
+        ),
+        Token::Function
+        | Token::FunctionBlock
+        | Token::Method
+        | Token::Action
+        | Token::Namespace
+        | Token::Configuration
+        | Token::Resource
+        | Token::Var
+        | Token::VarInput
+        | Token::VarOutput
+        | Token::VarInOut
+        | Token::VarTemp
+        | Token::VarGlobal
+        | Token::VarExternal
+        | Token::Type
+        | Token::Struct
+        | Token::Union
+        | Token::If
+        | Token::For
+        | Token::While
+        | Token::Repeat => true,
+        _ => false,
+    }
+}
+
+fn depth(scopes: &[Scope]) -> usize {
+    scopes
+        .iter()
+        .map(|s| match s {
+            Scope::Case { otherwise: false } => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Whether a statement starts at a word, after the words before it on its line.
+fn starts_statement(before: &Word, word: &Word, scopes: &[Scope]) -> bool {
+    let closes =
+        is_end(&word.token) || matches!(word.token, Token::Else | Token::Elsif | Token::Until);
+    closes
+        || match before.token {
+            Token::SemiColon | Token::Then | Token::Do | Token::Else | Token::Repeat => true,
+            Token::Of => matches!(scopes.last(), Some(Scope::Case { .. })),
+            _ => false,
+        }
+}
+
+/// Follows the conditional compilation pragmas, which leave the indentation as it is.
+///
+/// Gives the depth of the `{IF}` for the pragmas which continue or end it.
+fn branch<'a>(
+    word: &Word,
+    scopes: &mut Vec<Scope<'a>>,
+    conditionals: &mut Vec<Conditional<'a>>,
+) -> Option<usize> {
+    if word.token != Token::LeftBrace {
+        return None;
+    }
+    let inner = word.text.trim_start_matches('{').trim_end_matches('}');
+    match conditional::directive(inner)?.0.as_str() {
+        "IF" => {
+            conditionals.push(Conditional {
+                start: scopes.clone(),
+                first: None,
+            });
+            None
+        }
+        "ELSIF" | "ELSE" => {
+            let conditional = conditionals.last_mut()?;
+            conditional.first.get_or_insert_with(|| scopes.clone());
+            *scopes = conditional.start.clone();
+            Some(depth(scopes))
+        }
+        _ => {
+            let conditional = conditionals.pop()?;
+            let start = depth(&conditional.start);
+            *scopes = conditional.first.unwrap_or(std::mem::take(scopes));
+            Some(start)
+        }
+    }
+}
+
+/// Whether the innermost block holds declarations rather than statements.
+fn declares(scopes: &[Scope]) -> bool {
+    matches!(
+        scopes.last(),
+        Some(Scope::Block(
+            Token::Var
+                | Token::VarInput
+                | Token::VarOutput
+                | Token::VarInOut
+                | Token::VarTemp
+                | Token::VarGlobal
+                | Token::VarExternal
+                | Token::Type
+                | Token::Struct
+                | Token::Union
+        ))
+    )
+}
+
+/// Indents the lines and spaces their words, starting a new line at every statement.
+fn indent(lines: Vec<Line>) -> Vec<Formatted> {
+    let mut scopes: Vec<Scope> = Vec::new();
+    let mut conditionals = Vec::new();
+    let mut parentheses = 0usize;
+    let mut pending = false;
+    // Whether the header of an IF, ELSIF, CASE, FOR or WHILE lacks its THEN, OF or DO yet
+    let mut header = false;
+    // The indentation of the line the current statement starts on
+    let mut statement = 0;
+    let mut formatted = Vec::with_capacity(lines.len());
+    let mut queue = lines;
+    queue.reverse();
+    while let Some(mut line) = queue.pop() {
+        let pragma = branch(&line.words[0], &mut scopes, &mut conditionals);
+        let first = &line.words[0].token;
+        let continued = pending
+            || header
+            || parentheses > 0 && !matches!(first, Token::RightParenthesis | Token::RightBracket);
+        // The position of a colon after a label at the start of the line
+        let label = match scopes.last() {
+            Some(Scope::Case { otherwise: false }) if !continued => {
+                let mut nesting = 0isize;
+                line.words
+                    .iter()
+                    .take_while(|w| {
+                        !matches!(w.token, Token::Assign | Token::SemiColon | Token::Case)
+                    })
+                    .position(|w| {
+                        match w.token {
+                            Token::LeftParenthesis | Token::LeftBracket => nesting += 1,
+                            Token::RightParenthesis | Token::RightBracket => nesting -= 1,
+                            _ => (),
+                        }
+                        nesting == 0 && w.token == Token::Colon
+                    })
+            }
+            _ => None,
+        };
+
+        let mut skip = 0;
+        let indent = match (pragma, first) {
+            (Some(depth), _) => depth,
+            (None, token) if is_end(token) => {
+                scopes.pop();
+                skip = 1;
+                depth(&scopes)
+            }
+            (None, Token::Else | Token::Elsif) => {
+                if let (Token::Else, Some(Scope::Case { otherwise })) = (first, scopes.last_mut()) {
+                    *otherwise = true;
+                }
+                depth(&scopes).saturating_sub(1)
+            }
+            (None, Token::Until) => depth(&scopes).saturating_sub(1),
+            _ if label.is_some() => depth(&scopes) - 1,
+            _ if continued => statement + 1,
+            _ => depth(&scopes),
+        };
+        if !continued && pragma.is_none() {
+            statement = indent;
+        }
+        let declaration = !continued && matches!(first, Token::Identifier(_)) && declares(&scopes);
+
+        let mut head = String::new();
+        let mut colon: Option<String> = None;
+        // The last word which is no comment, after which the next statement may start
+        let mut code: Option<usize> = None;
+        let mut split = None;
+        for (i, word) in line.words.iter().enumerate() {
+            let comment = matches!(word.token, Token::Comment(_));
+            if let Some(before) = code
+                && !comment
+                && parentheses == 0
+                && !declares(&scopes)
+                && (label == Some(before) || starts_statement(&line.words[before], word, &scopes))
+            {
+                split = Some(i);
+                break;
+            }
+            if i > 0 {
+                branch(word, &mut scopes, &mut conditionals);
+            }
+            if !comment {
+                code = Some(i);
+            }
+
+            if i > 0 && spaced(&line.words[i - 1], word, label == Some(i)) {
+                match &mut colon {
+                    Some(colon) => colon.push(' '),
+                    None => head.push(' '),
+                }
+            }
+            match &mut colon {
+                Some(colon) => colon.push_str(word.text),
+                None if declaration && parentheses == 0 && word.token == Token::Colon => {
+                    head.pop();
+                    colon = Some(word.text.to_string());
+                }
+                None => head.push_str(word.text),
+            }
+
+            match &word.token {
+                Token::LeftParenthesis | Token::LeftBracket => parentheses += 1,
+                Token::RightParenthesis | Token::RightBracket => {
+                    parentheses = parentheses.saturating_sub(1)
+                }
+                Token::If | Token::Elsif | Token::For | Token::While => header = true,
+                Token::Then | Token::Do | Token::Of => header = false,
+                _ => (),
+            }
+            match &word.token {
+                Token::Case => {
+                    header = true;
+                    scopes.push(Scope::Case { otherwise: false })
+                }
+                token if i >= skip && is_end(token) => {
+                    scopes.pop();
+                }
+                token if opens_block(token, &scopes) => scopes.push(Scope::Block(token.clone())),
+                _ => (),
+            }
+        }
+        if let Some(split) = split {
+            let rest = line.words.split_off(split);
+            queue.push(Line {
+                words: rest,
+                blank_before: false,
+            });
+        }
+        let code = line
+            .words
+            .iter()
+            .rev()
+            .find(|w| !matches!(w.token, Token::Comment(_) | Token::LeftBrace));
+        if let Some(last) = code {
+            pending = continues(&last.token);
+        }
+
+        formatted.push(Formatted {
+            indent,
+            head,
+            colon,
+            blank_before: line.blank_before,
+        });
+    }
+    formatted
+}
+
+/// Whether a space separates two words of a line.
+fn spaced(before: &Word, word: &Word, label: bool) -> bool {
+    if matches!(word.token, Token::Comment(_)) || matches!(before.token, Token::Comment(_)) {
+        return true;
+    }
+    match (&before.token, &word.token) {
+        (_, Token::Colon) => !label,
+        (Token::LiteralPrefix(_), _) => false,
+        (
+            _,
+            Token::Comma
+            | Token::SemiColon
+            | Token::RightParenthesis
+            | Token::RightBracket
+            | Token::Dot
+            | Token::Range
+            | Token::Caret,
+        ) => false,
+        (Token::LeftParenthesis | Token::LeftBracket | Token::Dot | Token::Range, _) => false,
+        (
+            Token::Identifier(_) | Token::Caret | Token::RightBracket | Token::Array,
+            Token::LeftParenthesis | Token::LeftBracket,
+        ) => false,
+        (Token::Plus | Token::Minus, _) => !before.unary,
+        _ => true,
+    }
+}
+
+/// Writes the lines, aligning the colons of consecutive declarations at the same indentation.
+fn render(lines: Vec<Formatted>) -> String {
+    let mut out = String::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let group = |l: &Formatted| l.colon.is_some() && l.indent == lines[start].indent;
+        let mut end = start + 1;
+        if group(&lines[start]) {
+            while end < lines.len() && group(&lines[end]) && !lines[end].blank_before {
+                end += 1;
+            }
+        }
+        let width = lines[start..end]
+            .iter()
+            .map(|l| l.head.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        for line in &lines[start..end] {
+            if line.blank_before && !out.is_empty() {
+                out.push('\n');
+            }
+            out.extend(std::iter::repeat_n(' ', line.indent * INDENT));
+            out.push_str(&line.head);
+            if let Some(colon) = &line.colon {
+                let padding = width - line.head.chars().count() + 1;
+                out.extend(std::iter::repeat_n(' ', padding));
+                out.push_str(colon);
+            }
+            out.push('\n');
+        }
+        start = end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(src: &str) -> String {
+        let once = format("test.st", src).unwrap();
+        assert_eq!(format("test.st", &once).unwrap(), once, "not idempotent");
+        once
+    }
+
+    const SPACING: &str = "program Main
+var
+  count: int := 0; // cycles
+     limit   : INT:=10;
+  values : array[0..9] OF dint;
+end_var
+(* count
+   up *)
+if count<limit then count:=count+1;
+elsif count = -limit THEN
+count := - 1;
+else
+    values[ 0 ] := ABS( -count ) * 2;
+END_IF;
+
+
+WHILE NOT done DO
+x := a +
+b;
+end_while;
+END_PROGRAM
+";
+
+    #[test]
+    fn test_normalizes_case_indentation_and_spacing() {
+        assert_eq!(
+            formatted(SPACING),
+            "PROGRAM Main
+    VAR
+        count  : INT := 0; // cycles
+        limit  : INT := 10;
+        values : ARRAY[0..9] OF DINT;
+    END_VAR
+    (* count
+   up *)
+    IF count < limit THEN
+        count := count + 1;
+    ELSIF count = -limit THEN
+        count := -1;
+    ELSE
+        values[0] := ABS(-count) * 2;
+    END_IF;
+
+    WHILE NOT done DO
+        x := a +
+            b;
+    END_WHILE;
+END_PROGRAM
+"
+        );
+    }
+
+    const NESTING: &str = "TYPE
+Point : STRUCT
+x : REAL;
+y : REAL;
+END_STRUCT;
+Color : (Red, Green);
+END_TYPE
+FUNCTION_BLOCK Fb
+CASE state OF
+1, 2:
+state := 3;
+Color#Red: state := INT#1;
+ELSE
+p^.x := 0.0;
+END_CASE;
+REPEAT
+i := i + 1;
+UNTIL i > 10
+END_REPEAT;
+END_FUNCTION_BLOCK
+CONFIGURATION Plant
+RESOURCE Cpu ON PLC
+TASK Fast(INTERVAL := T#10ms, PRIORITY := 1);
+PROGRAM main WITH Fast : Main;
+END_RESOURCE
+END_CONFIGURATION
+";
+
+    #[test]
+    fn test_indents_case_labels_types_and_configurations() {
+        assert_eq!(
+            formatted(NESTING),
+            "TYPE
+    Point : STRUCT
+        x : REAL;
+        y : REAL;
+    END_STRUCT;
+    Color : (Red, Green);
+END_TYPE
+FUNCTION_BLOCK Fb
+    CASE state OF
+        1, 2:
+            state := 3;
+        Color#Red:
+            state := INT#1;
+    ELSE
+        p^.x := 0.0;
+    END_CASE;
+    REPEAT
+        i := i + 1;
+    UNTIL i > 10
+    END_REPEAT;
+END_FUNCTION_BLOCK
+CONFIGURATION Plant
+    RESOURCE Cpu ON PLC
+        TASK Fast(INTERVAL := T#10ms, PRIORITY := 1);
+        PROGRAM main WITH Fast : Main;
+    END_RESOURCE
+END_CONFIGURATION
+"
+        );
+    }
+
+    const STATEMENTS: &str = "PROGRAM Main
+VAR x : int; END_VAR
+IF x > 1 THEN x := x + 1; // inc
+ELSIF x = 0 THEN ;
+ELSE x := -x; END_IF;
+CASE x OF 1: x := 2; x := 3; (* twice *) 2, 3: ; ELSE x := 0; END_CASE;
+REPEAT x := x - 1; UNTIL x < 0 END_REPEAT;
+END_PROGRAM
+";
+
+    #[test]
+    fn test_puts_every_statement_on_its_own_line() {
+        assert_eq!(
+            formatted(STATEMENTS),
+            "PROGRAM Main
+    VAR x : INT; END_VAR
+    IF x > 1 THEN
+        x := x + 1; // inc
+    ELSIF x = 0 THEN
+        ;
+    ELSE
+        x := -x;
+    END_IF;
+    CASE x OF
+        1:
+            x := 2;
+            x := 3; (* twice *)
+        2, 3:
+            ;
+    ELSE
+        x := 0;
+    END_CASE;
+    REPEAT
+        x := x - 1;
+    UNTIL x < 0
+    END_REPEAT;
+END_PROGRAM
+"
+        );
+    }
+
+    const CONDITIONAL: &str = "PROGRAM Main
+{IF defined(SIM)}
+IF simulated THEN
+{ELSE}
+IF x > 0 THEN
+{END_IF}
+x := 0;
+END_IF;
+END_PROGRAM
+";
+
+    #[test]
+    fn test_indents_conditional_branches_alike() {
+        assert_eq!(
+            formatted(CONDITIONAL),
+            "PROGRAM Main
+    {IF defined(SIM)}
+    IF simulated THEN
+    {ELSE}
+    IF x > 0 THEN
+    {END_IF}
+        x := 0;
+    END_IF;
+END_PROGRAM
+"
+        );
+    }
+
+    const PRAGMAS: &str =
+        "{attribute 'test'}\nprogram T\n{IF defined(SIM)}\nx:=1;\n{END_IF}\nend_program";
+
+    #[test]
+    fn test_keeps_pragmas_and_rejects_illegal_characters() {
+        assert_eq!(
+            formatted(PRAGMAS),
+            "{attribute 'test'}\nPROGRAM T\n    {IF defined(SIM)}\n    x := 1;\n    {END_IF}\nEND_PROGRAM\n"
+        );
+
+        let error = format("bad.st", "x := 1 $ 2;").unwrap_err();
+        assert!(error.starts_with("[ERR] in bad.st:1:7"), "{error}");
+    }
+
+    #[test]
+    fn test_indents_continued_headers_from_their_statement() {
+        let src = "PROGRAM Main\nCASE x\nOF 1: x := 3;\nEND_CASE;\nIF x > 1 AND\nx < 5\nTHEN x := 0;\nEND_IF;\nEND_PROGRAM\n";
+        assert_eq!(
+            formatted(src),
+            "PROGRAM Main
+    CASE x
+        OF
+        1:
+            x := 3;
+    END_CASE;
+    IF x > 1 AND
+        x < 5
+        THEN
+        x := 0;
+    END_IF;
+END_PROGRAM
+"
+        );
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let headers = "PROGRAM Main\nCASE x\nOF 1: x := 3;\nEND_CASE;\nWHILE x <\n10 DO x := x + 1; END_WHILE;\nEND_PROGRAM";
+        for src in [SPACING, NESTING, STATEMENTS, CONDITIONAL, PRAGMAS, headers] {
+            let once = format("test.st", src).unwrap();
+            assert_eq!(format("test.st", &once).unwrap(), once);
+        }
+    }
+
+    #[test]
+    fn test_rejects_sources_which_do_not_parse() {
+        let error = format("bad.st", "PROGRAM Main\nx := ;\nEND_PROGRAM\n").unwrap_err();
+        assert!(error.starts_with("[ERR] in bad.st:2"), "{error}");
+        assert!(
+            error.ends_with("bad.st does not parse, it is not formatted."),
+            "{error}"
+        );
+
+        let error = format("bad.st", "{IF defined(SIM)}\nPROGRAM Main\nEND_PROGRAM\n").unwrap_err();
+        assert!(error.contains("IF is missing its END_IF."), "{error}");
+    }
+}
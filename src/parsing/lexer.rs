@@ -1,8 +1,23 @@
-use crate::parsing::token::{MarkedToken, Marker, NumberValue, TimeValue, Token};
+use crate::parsing::token::{
+    DATE_PREFIXES, DateKind, DateValue, MarkedToken, Marker, NumberValue, TIME_PREFIXES,
+    TIME_UNITS, TimeValue, Token,
+};
 use nom::number::complete::double;
 
 fn numeric_len(s: &str) -> Option<usize> {
-    double::<_, ()>(s).map(|(r, _)| s.len() - r.len()).ok()
+    let n = double::<_, ()>(s).map(|(r, _)| s.len() - r.len()).ok()?;
+
+    // A range like `0..10` must not swallow its first dot as a fraction
+    match s[..n].find('.') {
+        Some(i) if s[i + 1..].starts_with('.') => Some(i),
+        _ => Some(n),
+    }
+}
+
+fn word_len(s: &str) -> usize {
+    s.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .count()
 }
 
 pub struct Lexer<'a> {
@@ -14,27 +29,35 @@ type TokenResult<'a> = Option<(Token<'a>, usize)>;
 
 impl<'a> Lexer<'a> {
     pub fn create(src_file: &'a str, src: &'a str) -> Self {
+        Self::create_at(src_file, src, 0, 0)
+    }
+
+    /// Lexer of a source starting at a position and line of a project, so that the spans of its
+    /// tokens do not overlap those of other files.
+    pub fn create_at(src_file: &'a str, src: &'a str, pos: usize, line: usize) -> Self {
         Self {
             src,
-            marker: Marker::create(src_file, src),
+            marker: Marker::create_at(src_file, src, pos, line),
         }
     }
 
     fn is_exhausted(&self) -> bool {
-        self.src.len() == 0
+        self.src.is_empty()
     }
 
     fn advance(&mut self, n: usize) {
-        for _ in 0..n {
+        let mut consumed = 0;
+        while consumed < n {
             if let Some(c) = self.src.chars().next() {
                 // Advance source
-                self.src = &self.src[1..];
+                self.src = &self.src[c.len_utf8()..];
+                consumed += c.len_utf8();
 
                 // Advance marker with line breaks
                 if c == '\n' {
                     self.marker.advance_new_line();
                 } else {
-                    self.marker.advance();
+                    self.marker.advance(c.len_utf8());
                 }
             } else {
                 // Stop when the end of the source is reached
@@ -55,70 +78,29 @@ impl<'a> Lexer<'a> {
             .or_else(|| self.get_line_comment_token())
             .or_else(|| self.get_block_comment_token())
             .or_else(|| self.get_pragma_token())
+            .or_else(|| self.get_attribute_token())
             .or_else(|| self.get_time_token())
+            .or_else(|| self.get_date_token())
+            .or_else(|| self.get_literal_prefix_token())
             .or_else(|| self.get_identifier_token())
             .or_else(|| self.get_string_token())
+            .or_else(|| self.get_direct_address_token())
             .or_else(|| self.get_operator_token())
             .or_else(|| self.get_number_token())
             .or_else(|| self.get_delimiter_token())
-            .unwrap_or((Token::Illegal, self.src.len()))
+            .unwrap_or_else(|| {
+                let len = self.src.chars().next().map_or(0, char::len_utf8);
+                (Token::Illegal, len)
+            })
     }
 
     fn get_keyword_token(&self) -> TokenResult<'a> {
-        match self.src {
-            // Logic
-            s if s.starts_with("NOT") => Some((Token::Not, 3)),
-            s if s.starts_with("TRUE") => Some((Token::True, 4)),
-            s if s.starts_with("FALSE") => Some((Token::False, 5)),
-            s if s.starts_with("AND") => Some((Token::And, 3)),
-            s if s.starts_with("OR") => Some((Token::Or, 2)),
-            s if s.starts_with("XOR") => Some((Token::Xor, 3)),
-
-            // Control flow
-            s if s.starts_with("IF") => Some((Token::If, 2)),
-            s if s.starts_with("THEN") => Some((Token::Then, 4)),
-            s if s.starts_with("ELSIF") => Some((Token::Elsif, 5)),
-            s if s.starts_with("ELSE") => Some((Token::Else, 4)),
-            s if s.starts_with("END_IF") => Some((Token::EndIf, 6)),
-            s if s.starts_with("CASE") => Some((Token::Case, 4)),
-            s if s.starts_with("OF") => Some((Token::Of, 2)),
-            s if s.starts_with("END_CASE") => Some((Token::EndCase, 8)),
-            s if s.starts_with("FOR") => Some((Token::For, 3)),
-            s if s.starts_with("TO") => Some((Token::To, 2)),
-            s if s.starts_with("BY") => Some((Token::By, 2)),
-            s if s.starts_with("DO") => Some((Token::Do, 2)),
-            s if s.starts_with("END_FOR") => Some((Token::EndFor, 7)),
-            s if s.starts_with("WHILE") => Some((Token::While, 5)),
-            s if s.starts_with("END_WHILE") => Some((Token::EndWhile, 9)),
-
-            // Program / Action / Function / Function block
-            s if s.starts_with("PROGRAM") => Some((Token::Program, 7)),
-            s if s.starts_with("END_PROGRAM") => Some((Token::EndProgram, 11)),
-            s if s.starts_with("EXIT") => Some((Token::Exit, 4)),
-            s if s.starts_with("ACTION") => Some((Token::Action, 6)),
-            s if s.starts_with("END_ACTION") => Some((Token::EndAction, 10)),
-            s if s.starts_with("FUNCTION_BLOCK") => Some((Token::FunctionBlock, 14)),
-            s if s.starts_with("END_FUNCTION_BLOCK") => Some((Token::EndFunctionBlock, 18)),
-            s if s.starts_with("FUNCTION") => Some((Token::Function, 8)),
-            s if s.starts_with("END_FUNCTION") => Some((Token::EndFunction, 12)),
-            s if s.starts_with("RETURN") => Some((Token::Return, 6)),
-
-            // Variable declarations
-            s if s.starts_with("VAR_INPUT") => Some((Token::VarInput, 9)),
-            s if s.starts_with("VAR_OUTPUT") => Some((Token::VarOutput, 10)),
-            s if s.starts_with("VAR") => Some((Token::Var, 3)),
-            s if s.starts_with("CONSTANT") => Some((Token::Constant, 8)),
-            s if s.starts_with("END_VAR") => Some((Token::EndVar, 7)),
-
-            // Type declarations
-            s if s.starts_with("TYPE") => Some((Token::Type, 4)),
-            s if s.starts_with("END_TYPE") => Some((Token::EndType, 8)),
-            s if s.starts_with("STRUCT") => Some((Token::Struct, 6)),
-            s if s.starts_with("END_STRUCT") => Some((Token::EndStruct, 10)),
-            s if s.starts_with("UNION") => Some((Token::Union, 5)),
-            s if s.starts_with("END_UNION") => Some((Token::EndUnion, 9)),
-            _ => None,
+        let len = word_len(self.src);
+        if len == 0 || self.src[len..].starts_with('#') {
+            return None;
         }
+
+        Token::keyword(&self.src[..len]).map(|t| (t, len))
     }
 
     fn get_line_comment_token(&self) -> TokenResult<'a> {
@@ -126,9 +108,9 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
-        let n = self.src.chars().take_while(|c| *c != '\n').count();
+        let n = self.src.find('\n').unwrap_or(self.src.len());
 
-        return Some((Token::Comment(&self.src[..n]), n));
+        Some((Token::Comment(&self.src[..n]), n))
     }
 
     fn get_block_comment_token(&self) -> TokenResult<'a> {
@@ -136,16 +118,8 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
-        let mut peak = &self.src[2..];
-        while peak.len() > 0 {
-            if peak.starts_with("*)") {
-                let n = self.src.len() - peak.len() + 2;
-                return Some((Token::Comment(&self.src[..n]), n));
-            }
-
-            peak = &peak[1..];
-        }
-        return None;
+        let n = self.src[2..].find("*)")? + 4;
+        Some((Token::Comment(&self.src[..n]), n))
     }
 
     fn get_pragma_token(&self) -> Option<(Token<'a>, usize)> {
@@ -155,30 +129,46 @@ impl<'a> Lexer<'a> {
 
         let n = self
             .src
-            .chars()
-            .take_while(|c| !c.is_ascii_whitespace())
-            .count();
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(self.src.len());
 
-        return Some((Token::Pragma(&self.src[..n]), n));
+        Some((Token::Pragma(&self.src[..n]), n))
     }
 
-    fn get_time_token(&self) -> Option<(Token<'a>, usize)> {
-        // Must start with "T#"
-        if !self.src.starts_with("T#") {
+    fn get_attribute_token(&self) -> Option<(Token<'a>, usize)> {
+        let keyword = self.src.get(..10)?;
+        let separated = self.src[10..].starts_with(|c: char| c.is_ascii_whitespace());
+        if !keyword.eq_ignore_ascii_case("{attribute") || !separated {
             return None;
         }
 
+        let n = self.src.find('}')? + 1;
+        Some((Token::Attribute(&self.src[..n]), n))
+    }
+
+    fn get_time_token(&self) -> Option<(Token<'a>, usize)> {
+        let prefix_len = TIME_PREFIXES
+            .iter()
+            .find(|p| {
+                self.src
+                    .get(..p.len())
+                    .is_some_and(|s| s.eq_ignore_ascii_case(p))
+            })?
+            .len();
+
         let mut data = TimeValue {
             days: 0,
             hours: 0,
             minutes: 0,
             seconds: 0,
             milli_seconds: 0,
+            micro_seconds: 0,
+            nano_seconds: 0,
         };
-        let mut cur_value: usize;
-        let mut peak = &self.src[2..];
+        let mut cur_value: u64;
+        let mut peak = &self.src[prefix_len..];
         if let Some(n) = numeric_len(peak)
-            && let Ok(value) = str::parse::<usize>(&peak[..n])
+            && let Ok(value) = str::parse::<u64>(&peak[..n])
         {
             cur_value = value;
             peak = &peak[n..];
@@ -186,23 +176,30 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
-        for (lower, upper) in [("d", "D"), ("h", "H"), ("m", "M"), ("s", "S"), ("ms", "MS")] {
-            if !peak.starts_with(lower) && !peak.starts_with(upper) {
+        for unit in TIME_UNITS {
+            let matches_unit = peak
+                .get(..unit.len())
+                .is_some_and(|s| s.eq_ignore_ascii_case(unit));
+            // "m" must not eat the start of "ms"
+            let is_ms = unit == "m" && peak.get(..2).is_some_and(|s| s.eq_ignore_ascii_case("ms"));
+            if !matches_unit || is_ms {
                 continue;
             }
 
-            match lower {
+            match unit {
                 "d" => data.days = cur_value,
-                "h" => data.hours = cur_value as u8,
-                "m" => data.minutes = cur_value as u8,
-                "s" => data.seconds = cur_value as u8,
-                "ms" => data.milli_seconds = cur_value as u16,
-                _ => unreachable!("Cannot pass check for d, h, m, s, ms and not be one of them"),
+                "h" => data.hours = cur_value,
+                "m" => data.minutes = cur_value,
+                "s" => data.seconds = cur_value,
+                "ms" => data.milli_seconds = cur_value,
+                "us" => data.micro_seconds = cur_value,
+                "ns" => data.nano_seconds = cur_value,
+                _ => unreachable!("Cannot pass check for a unit and not be one of them"),
             }
 
-            peak = &peak[lower.len()..];
+            peak = &peak[unit.len()..];
             if let Some(n) = numeric_len(peak)
-                && let Ok(value) = str::parse::<usize>(&peak[..n])
+                && let Ok(value) = str::parse::<u64>(&peak[..n])
             {
                 // Consume the number
                 cur_value = value;
@@ -215,7 +212,68 @@ impl<'a> Lexer<'a> {
         }
 
         // Invalid time when loop runs through
-        return None;
+        None
+    }
+
+    fn get_date_token(&self) -> TokenResult<'a> {
+        let (prefix, kind) = DATE_PREFIXES.iter().find(|(p, _)| {
+            self.src
+                .get(..p.len())
+                .is_some_and(|s| s.eq_ignore_ascii_case(p))
+        })?;
+
+        let mut value = DateValue {
+            kind: *kind,
+            year: 1970,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            nanosecond: 0,
+        };
+        let mut rest = &self.src[prefix.len()..];
+        // Takes a number of at most the given digits, after the separator unless it is empty
+        let mut number = |separator: &str, max: usize| {
+            let digits = rest.strip_prefix(separator)?;
+            let n = digits.bytes().take_while(u8::is_ascii_digit).count();
+            if n == 0 || n > max {
+                return None;
+            }
+            rest = &digits[n..];
+            Some((digits[..n].parse::<u32>().ok()?, n))
+        };
+
+        if *kind != DateKind::TimeOfDay {
+            value.year = number("", 4)?.0 as i64;
+            value.month = number("-", 2)?.0;
+            value.day = number("-", 2)?.0;
+        }
+        if *kind != DateKind::Date {
+            let separator = if *kind == DateKind::DateAndTime {
+                "-"
+            } else {
+                ""
+            };
+            value.hour = number(separator, 2)?.0;
+            value.minute = number(":", 2)?.0;
+            value.second = number(":", 2)?.0;
+            if let Some((fraction, n)) = number(".", 9) {
+                value.nanosecond = fraction * 10u32.pow(9 - n as u32);
+            }
+        }
+
+        let len = self.src.len() - rest.len();
+        Some((Token::Date(&self.src[..len], value), len))
+    }
+
+    fn get_literal_prefix_token(&self) -> Option<(Token<'a>, usize)> {
+        let len = word_len(self.src);
+        if len == 0 || !self.src[len..].starts_with('#') || self.src.starts_with(char::is_numeric) {
+            return None;
+        }
+
+        Some((Token::LiteralPrefix(&self.src[..len + 1]), len + 1))
     }
 
     fn get_identifier_token(&self) -> Option<(Token<'a>, usize)> {
@@ -224,12 +282,8 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
-        let len = self
-            .src
-            .chars()
-            .take_while(|c| c.is_alphanumeric() || *c == '_')
-            .count();
-        return Some((Token::Identifier(&self.src[..len]), len));
+        let len = word_len(self.src);
+        Some((Token::Identifier(&self.src[..len]), len))
     }
 
     fn get_string_token(&self) -> Option<(Token<'a>, usize)> {
@@ -239,37 +293,53 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
-        let len = self
-            .src
-            .chars()
-            .skip(1)
-            .take_while(|c| *c != start_quote)
-            .count()
-            + 2;
+        let len = self.src[1..].find(start_quote)? + 2;
+        Some((Token::String(&self.src[..len]), len))
+    }
 
-        if len <= self.src.len() {
-            return Some((Token::String(&self.src[..len]), len));
-        } else {
+    fn get_direct_address_token(&self) -> Option<(Token<'a>, usize)> {
+        let mut chars = self.src.chars();
+        if chars.next()? != '%' || !matches!(chars.next()?, 'I' | 'Q' | 'M') {
             return None;
         }
+
+        let len = self
+            .src
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '%' && c != '.' && c != '*')
+            .unwrap_or(self.src.len());
+        Some((Token::DirectAddress(&self.src[..len]), len))
     }
 
     fn get_number_token(&self) -> Option<(Token<'a>, usize)> {
-        match numeric_len(self.src) {
-            None => None,
-            Some(n) => match str::parse::<usize>(&self.src[..n]) {
-                Ok(int) => Some((Token::Number(&self.src[..n], NumberValue::Int(int)), n)),
-                _ => Some((
-                    Token::Number(
-                        &self.src[..n],
-                        NumberValue::Float(
-                            str::parse(&self.src[..n])
-                                .expect("Must be a float when numeric and not integer"),
-                        ),
+        let n = numeric_len(self.src)?;
+
+        // Based integers like 16#FF or 2#1010_1010
+        if self.src[n..].starts_with('#')
+            && let Ok(base) = str::parse::<u32>(&self.src[..n])
+            && matches!(base, 2 | 8 | 16)
+        {
+            let digits_len = word_len(&self.src[n + 1..]);
+            let digits = self.src[n + 1..n + 1 + digits_len].replace('_', "");
+            let value = usize::from_str_radix(&digits, base).ok()?;
+            let len = n + 1 + digits_len;
+            return Some((
+                Token::Number(&self.src[..len], NumberValue::Int(value)),
+                len,
+            ));
+        }
+
+        match str::parse::<usize>(&self.src[..n]) {
+            Ok(int) => Some((Token::Number(&self.src[..n], NumberValue::Int(int)), n)),
+            _ => Some((
+                Token::Number(
+                    &self.src[..n],
+                    NumberValue::Float(
+                        str::parse(&self.src[..n])
+                            .expect("Must be a float when numeric and not integer"),
                     ),
-                    n,
-                )),
-            },
+                ),
+                n,
+            )),
         }
     }
 
@@ -277,16 +347,21 @@ impl<'a> Lexer<'a> {
         match self.src {
             s if s.starts_with("+") => Some((Token::Plus, 1)),
             s if s.starts_with("-") => Some((Token::Minus, 1)),
+            s if s.starts_with("**") => Some((Token::Power, 2)),
             s if s.starts_with("*") => Some((Token::Asterisk, 1)),
             s if s.starts_with("/") => Some((Token::Slash, 1)),
             s if s.starts_with("%") => Some((Token::Percent, 1)),
+            s if s.starts_with("^") => Some((Token::Caret, 1)),
+            s if s.starts_with("&") => Some((Token::Ampersand, 1)),
             s if s.starts_with(":=") => Some((Token::Assign, 2)),
+            s if s.starts_with("=>") => Some((Token::Arrow, 2)),
             s if s.starts_with("=") => Some((Token::Equals, 1)),
             s if s.starts_with("<>") => Some((Token::NotEquals, 2)),
             s if s.starts_with(">=") => Some((Token::GreaterThanOrEquals, 2)),
             s if s.starts_with(">") => Some((Token::GreaterThan, 1)),
             s if s.starts_with("<=") => Some((Token::LessThanOrEquals, 2)),
             s if s.starts_with("<") => Some((Token::LessThan, 1)),
+            s if s.starts_with("..") => Some((Token::Range, 2)),
             _ => None,
         }
     }
@@ -308,7 +383,7 @@ impl<'a> Lexer<'a> {
             _ => None,
         }?;
 
-        return Some((data, 1));
+        Some((data, 1))
     }
 }
 
@@ -327,7 +402,7 @@ impl<'a> Iterator for Lexer<'a> {
         let marked_token = token.mark(self.marker.clone());
         self.advance(token_len);
 
-        return Some(marked_token);
+        Some(marked_token)
     }
 }
 
@@ -665,7 +740,9 @@ T#1m1ms"#;
                         hours: 0,
                         minutes: 0,
                         seconds: 1,
-                        milli_seconds: 0
+                        milli_seconds: 0,
+                        micro_seconds: 0,
+                        nano_seconds: 0
                     }
                 ),
                 src_file,
@@ -685,7 +762,9 @@ T#1m1ms"#;
                         hours: 1,
                         minutes: 1,
                         seconds: 1,
-                        milli_seconds: 1
+                        milli_seconds: 1,
+                        micro_seconds: 0,
+                        nano_seconds: 0
                     }
                 ),
                 src_file,
@@ -705,7 +784,9 @@ T#1m1ms"#;
                         hours: 1,
                         minutes: 1,
                         seconds: 1,
-                        milli_seconds: 1
+                        milli_seconds: 1,
+                        micro_seconds: 0,
+                        nano_seconds: 0
                     }
                 ),
                 src_file,
@@ -725,7 +806,9 @@ T#1m1ms"#;
                         hours: 0,
                         minutes: 1,
                         seconds: 0,
-                        milli_seconds: 1
+                        milli_seconds: 1,
+                        micro_seconds: 0,
+                        nano_seconds: 0
                     }
                 ),
                 src_file,
@@ -739,6 +822,39 @@ T#1m1ms"#;
         assert_eq!(l.next(), None);
     }
 
+    #[test]
+    fn test_attribute() {
+        let src_file = "Some file.st";
+        let src = "{attribute 'test'} {ATTRIBUTE 'cycles' := '3'}{ }";
+        let mut l = Lexer::create(src_file, src);
+
+        assert_eq!(
+            l.next(),
+            exp(
+                Token::Attribute("{attribute 'test'}"),
+                src_file,
+                src,
+                0,
+                0,
+                0
+            )
+        );
+        assert_eq!(
+            l.next(),
+            exp(
+                Token::Attribute("{ATTRIBUTE 'cycles' := '3'}"),
+                src_file,
+                src,
+                19,
+                0,
+                19
+            )
+        );
+        assert_eq!(l.next(), exp(Token::LeftBrace, src_file, src, 46, 0, 46));
+        assert_eq!(l.next(), exp(Token::RightBrace, src_file, src, 48, 0, 48));
+        assert_eq!(l.next(), None);
+    }
+
     #[test]
     fn test_pragma() {
         let src_file = "Some file.st";
@@ -790,4 +906,165 @@ T#1m1ms"#;
 
         assert_eq!(l.next(), None);
     }
+
+    #[test]
+    fn test_keywords_are_case_insensitive_and_whole_words() {
+        let src = "end_if Or ORDER IFX";
+        let tokens: Vec<_> = Lexer::create("Some file.st", src)
+            .map(|t| t.token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::EndIf,
+                Token::Or,
+                Token::Identifier("ORDER"),
+                Token::Identifier("IFX"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structured_text_extensions() {
+        let src = "a ** b^ => 0..10 16#FF INT#5 TIME#1ms %IX0.1 & ä";
+        let tokens: Vec<_> = Lexer::create("Some file.st", src)
+            .map(|t| t.token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a"),
+                Token::Power,
+                Token::Identifier("b"),
+                Token::Caret,
+                Token::Arrow,
+                Token::Number("0", NumberValue::Int(0)),
+                Token::Range,
+                Token::Number("10", NumberValue::Int(10)),
+                Token::Number("16#FF", NumberValue::Int(255)),
+                Token::LiteralPrefix("INT#"),
+                Token::Number("5", NumberValue::Int(5)),
+                Token::Time(
+                    "TIME#1ms",
+                    TimeValue {
+                        days: 0,
+                        hours: 0,
+                        minutes: 0,
+                        seconds: 0,
+                        milli_seconds: 1,
+                        micro_seconds: 0,
+                        nano_seconds: 0
+                    }
+                ),
+                Token::DirectAddress("%IX0.1"),
+                Token::Ampersand,
+                Token::Illegal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sub_millisecond_times() {
+        let src = "T#1ms500us T#250ns t#2us";
+        let tokens: Vec<_> = Lexer::create("Some file.st", src)
+            .map(|t| t.token)
+            .collect();
+
+        let time = |milli_seconds, micro_seconds, nano_seconds| TimeValue {
+            days: 0,
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+            milli_seconds,
+            micro_seconds,
+            nano_seconds,
+        };
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Time("T#1ms500us", time(1, 500, 0)),
+                Token::Time("T#250ns", time(0, 0, 250)),
+                Token::Time("t#2us", time(0, 2, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_times_keep_units_beyond_their_carry() {
+        let src = "T#100000us T#70000ms T#18446744073709551615ns";
+        let tokens: Vec<_> = Lexer::create("Some file.st", src)
+            .map(|t| t.token)
+            .collect();
+
+        let Token::Time(_, micro) = &tokens[0] else {
+            panic!("{tokens:?}")
+        };
+        let Token::Time(_, milli) = &tokens[1] else {
+            panic!("{tokens:?}")
+        };
+        let Token::Time(_, huge) = &tokens[2] else {
+            panic!("{tokens:?}")
+        };
+        assert_eq!(micro.nanoseconds(), Some(100_000_000));
+        assert_eq!(milli.nanoseconds(), Some(70_000_000_000));
+        assert_eq!(huge.nanoseconds(), None);
+    }
+
+    #[test]
+    fn test_dates_and_times_of_day() {
+        let src = "D#2024-01-15 tod#12:00:00.5 DATE_AND_TIME#1969-12-31-23:59:59 DATE#3";
+        let tokens: Vec<_> = Lexer::create("Some file.st", src)
+            .map(|t| t.token)
+            .collect();
+
+        let Token::Date("D#2024-01-15", date) = &tokens[0] else {
+            panic!("{tokens:?}")
+        };
+        let Token::Date("tod#12:00:00.5", time) = &tokens[1] else {
+            panic!("{tokens:?}")
+        };
+        let Token::Date("DATE_AND_TIME#1969-12-31-23:59:59", both) = &tokens[2] else {
+            panic!("{tokens:?}")
+        };
+        assert_eq!(date.nanoseconds(), Some(19_737 * 86_400_000_000_000));
+        assert_eq!(time.nanoseconds(), Some(43_200_500_000_000));
+        assert_eq!(both.nanoseconds(), Some(-1_000_000_000));
+        assert_eq!(date.to_string(), "D#2024-01-15");
+        assert_eq!(time.to_string(), "TOD#12:00:00.5");
+        assert_eq!(both.to_string(), "DT#1969-12-31-23:59:59");
+        assert_eq!(
+            &DateValue::from_nanoseconds(DateKind::DateAndTime, -1_000_000_000),
+            both
+        );
+        // Without a date it is a typed literal
+        assert_eq!(
+            tokens[3..],
+            [
+                Token::LiteralPrefix("DATE#"),
+                Token::Number("3", NumberValue::Int(3))
+            ]
+        );
+
+        let invalid = Lexer::create("Some file.st", "D#2023-02-29")
+            .next()
+            .unwrap();
+        let Token::Date(_, invalid) = invalid.token else {
+            panic!("{invalid:?}")
+        };
+        assert_eq!(invalid.nanoseconds(), None);
+    }
+
+    #[test]
+    fn test_multi_byte_characters_in_comments() {
+        let src = "// Größe\nx";
+        let mut l = Lexer::create("Some file.st", src);
+
+        assert_eq!(l.next().map(|t| t.token), Some(Token::Comment("// Größe")));
+        assert_eq!(
+            l.next(),
+            exp(Token::Identifier("x"), "Some file.st", src, 11, 1, 0)
+        );
+    }
 }
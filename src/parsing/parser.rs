@@ -1,5 +1,13 @@
 use crate::parsing::{
-    ast::{Ast, Block, Expression, IfCondition, IfConditionalBranch, LiteralExpression, Statement},
+    ast::{
+        Action, Argument, Assignment, Ast, Attribute, Block, CallExpression, CaseBranch, CaseLabel,
+        CaseStatement, Configuration, DataType, EnumValue, Expression, ForLoop, Identifier,
+        IfCondition, IfConditionalBranch, IndexExpression, InfixExpression, InfixOperator,
+        LiteralExpression, MemberExpression, Namespace, Pou, PrefixExpression, PrefixOperator,
+        ProgramInstance, QualifiedName, Range, RepeatLoop, Resource, Span, Statement, Statements,
+        Task, TypeDeclaration, TypedLiteral, VariableBlock, VariableDeclaration, VariableKind,
+        WhileLoop,
+    },
     lexer::Lexer,
     token::{MarkedToken, Token},
 };
@@ -8,11 +16,43 @@ type Error = String;
 type Errors = Vec<Error>;
 type Result = std::result::Result<Ast, Errors>;
 
+/// A syntax error with its location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError {
+    /// Span of the token the error is found at, none at the end of the source
+    pub span: Option<Span>,
+    pub message: String,
+    /// The error as it is printed, with the file, line and an excerpt of the source
+    pub printable: String,
+}
+
 pub fn parse(lexer: Lexer) -> Result {
-    let stream = lexer.filter(|x| !matches!(x.token, Token::Comment { .. }));
+    parse_tokens(lexer.filter(|x| !matches!(x.token, Token::Comment { .. })))
+}
+
+/// Parses a stream of tokens without comments, like those of a syntax tree.
+pub fn parse_tokens<'a>(stream: impl Iterator<Item = MarkedToken<'a>>) -> Result {
+    parse_located(stream).map_err(|errors| errors.into_iter().map(|e| e.printable).collect())
+}
+
+/// Parses a stream of tokens without comments, giving the location of each error.
+pub fn parse_located<'a>(
+    stream: impl Iterator<Item = MarkedToken<'a>>,
+) -> std::result::Result<Ast, Vec<SyntaxError>> {
     let mut parser = Parser::create(stream);
     parser.parse();
-    return parser.into_result();
+    match parser.errors[..] {
+        [] => Ok(parser.ast),
+        _ => Err(parser.errors),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PouKind {
+    Program,
+    Function,
+    FunctionBlock,
+    Method,
 }
 
 struct Parser<'a, S>
@@ -21,9 +61,11 @@ where
 {
     stream: S,
     ast: Ast,
-    errors: Errors,
+    errors: Vec<SyntaxError>,
     cur: Option<MarkedToken<'a>>,
     peek: Option<MarkedToken<'a>>,
+    /// Source file of the tokens, for errors at its end
+    src_file: &'a str,
 }
 
 impl<'a, S> Parser<'a, S>
@@ -34,32 +76,30 @@ where
         let first_token = stream.next();
         let second_token = stream.next();
 
+        let src_file = first_token.as_ref().map_or("", |t| t.marker.src_file());
         Self {
             stream,
             ast: Ast::new(),
-            errors: Errors::new(),
+            errors: Vec::new(),
             cur: first_token,
             peek: second_token,
-        }
-    }
-
-    fn into_result(self) -> Result {
-        match self.errors[..] {
-            [] => Ok(self.ast),
-            _ => Err(self.errors),
+            src_file,
         }
     }
 
     fn error_out<T>(&mut self, msg: &str) -> Option<T> {
-        if let Some(cur) = &self.cur {
-            self.errors.push(format!(
-                "[ERR] in {} {}",
-                cur.marker.format_as_printable(),
-                msg
-            ));
+        let printable = if let Some(cur) = &self.cur {
+            format!("[ERR] in {} {}", cur.marker.format_as_printable(), msg)
+        } else if !self.src_file.is_empty() {
+            format!("[ERR] at the end of {} {msg}", self.src_file)
         } else {
-            self.errors.push(format!("[ERR] {}", msg));
-        }
+            format!("[ERR] {}", msg)
+        };
+        self.errors.push(SyntaxError {
+            span: self.cur.as_ref().map(MarkedToken::span),
+            message: msg.to_string(),
+            printable,
+        });
 
         None
     }
@@ -76,202 +116,1442 @@ where
         }
     }
 
-    fn advance(&mut self) {
-        self.cur = self.peek.take();
-        self.peek = self.stream.next();
+    fn advance(&mut self) {
+        self.cur = self.peek.take();
+        self.peek = self.stream.next();
+    }
+
+    fn cur_is(&self, token: &Token) -> bool {
+        self.cur.as_ref().is_some_and(|x| x.token == *token)
+    }
+
+    fn peek_is(&self, token: &Token) -> bool {
+        self.peek.as_ref().is_some_and(|x| x.token == *token)
+    }
+
+    fn cur_span(&self) -> Span {
+        self.cur.as_ref().map_or(Span::default(), |x| x.span())
+    }
+
+    /// Moves onto the peeked token if it is the expected one and errors out otherwise.
+    fn expect_peek(&mut self, token: Token, msg: &str) -> Option<()> {
+        let matches = self.peek_is(&token);
+        self.advance();
+        match matches {
+            true => Some(()),
+            false => self.error_out(msg),
+        }
+    }
+
+    /// Skips tokens until a statement boundary to resume parsing after an error.
+    fn synchronize(&mut self, terminators: &[Token]) {
+        while let Some(cur) = &self.cur {
+            if cur.token == Token::SemiColon || terminators.contains(&cur.token) {
+                return;
+            }
+            self.advance();
+        }
+    }
+
+    fn parse_identifier(&mut self, msg: &str) -> Option<Identifier> {
+        match &self.cur {
+            Some(MarkedToken {
+                token: Token::Identifier(i),
+                ..
+            }) => Some(Identifier::new(i, self.cur_span())),
+            _ => self.error_out(msg),
+        }
+    }
+
+    fn parse_qualified_name(&mut self, msg: &str) -> Option<QualifiedName> {
+        let mut parts = vec![self.parse_identifier(msg)?];
+        while self.peek_is(&Token::Dot) {
+            self.advance();
+            self.advance();
+            parts.push(self.parse_identifier(msg)?);
+        }
+
+        Some(QualifiedName { parts })
+    }
+
+    fn parse_block(&mut self) -> Option<Block> {
+        match &self.cur {
+            Some(cur) => match cur.token {
+                Token::Program => self.parse_pou(PouKind::Program).map(Block::Program),
+                Token::Action => self.parse_action().map(Block::Action),
+                Token::Function => self.parse_pou(PouKind::Function).map(Block::Function),
+                Token::FunctionBlock => self
+                    .parse_pou(PouKind::FunctionBlock)
+                    .map(Block::FunctionBlock),
+                Token::VarGlobal => self.parse_variable_block().map(Block::GlobalVariables),
+                Token::Type => self.parse_type_block().map(Block::Type),
+                Token::Namespace => self.parse_namespace().map(Block::Namespace),
+                Token::Configuration => self.parse_configuration().map(Block::Configuration),
+                Token::Attribute(_) => self.parse_attributed_block(),
+                _ => self.error_out("Expected a block opening token."),
+            },
+            None => self.error_out("Expected a block opening token."),
+        }
+    }
+
+    /// Attributes in front of a POU, like `{attribute 'test'}`.
+    fn parse_attributed_block(&mut self) -> Option<Block> {
+        let mut attributes = Vec::new();
+        while let Some(MarkedToken {
+            token: Token::Attribute(text),
+            ..
+        }) = self.cur
+        {
+            attributes.push(self.parse_attribute(text)?);
+            self.advance();
+        }
+
+        let mut block = self.parse_block()?;
+        match &mut block {
+            Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                pou.attributes = attributes;
+                Some(block)
+            }
+            _ => self.error_out(
+                "Attributes can only be given to a PROGRAM, FUNCTION or FUNCTION_BLOCK.",
+            ),
+        }
+    }
+
+    fn parse_attribute(&mut self, text: &str) -> Option<Attribute> {
+        let inner = text["{attribute".len()..text.len() - 1].trim();
+        let quoted = |s: &str| {
+            s.strip_prefix('\'')
+                .and_then(|s| s.split_once('\''))
+                .map(|(quoted, rest)| (quoted.to_string(), rest.trim().to_string()))
+        };
+        let parsed = quoted(inner).and_then(|(name, rest)| match rest.as_str() {
+            "" => Some((name, None)),
+            rest => rest
+                .strip_prefix(":=")
+                .and_then(|value| quoted(value.trim()))
+                .filter(|(_, rest)| rest.is_empty())
+                .map(|(value, _)| (name, Some(value))),
+        });
+
+        match parsed {
+            Some((name, value)) => Some(Attribute {
+                name,
+                value,
+                span: self.cur_span(),
+            }),
+            None => self.error_out(
+                "Expected an attribute like {attribute 'name'} or {attribute 'name' := 'value'}.",
+            ),
+        }
+    }
+
+    fn parse_namespace(&mut self) -> Option<Namespace> {
+        let start = self.cur_span();
+        self.advance();
+
+        let name = self.parse_qualified_name("Expected a name after the NAMESPACE keyword.")?;
+        self.advance();
+
+        let mut blocks = Vec::new();
+        while let Some(cur) = &self.cur {
+            if cur.token == Token::EndNamespace {
+                return Some(Namespace {
+                    name,
+                    blocks,
+                    span: start.to(self.cur_span()),
+                });
+            }
+
+            if let Some(block) = self.parse_block() {
+                blocks.push(block);
+            }
+            self.advance();
+        }
+
+        self.error_out(&format!(
+            "Namespace {} is not properly closed. Try adding a END_NAMESPACE to the end.",
+            name.to_printable()
+        ))
+    }
+
+    fn parse_configuration(&mut self) -> Option<Configuration> {
+        let start = self.cur_span();
+        self.advance();
+
+        let name = self.parse_identifier("Expected a name after the CONFIGURATION keyword.")?;
+        self.advance();
+
+        let mut configuration = Configuration {
+            name,
+            variables: Vec::new(),
+            resources: Vec::new(),
+            span: start,
+        };
+        while let Some(cur) = &self.cur {
+            match cur.token {
+                Token::EndConfiguration => {
+                    configuration.span = start.to(self.cur_span());
+                    return Some(configuration);
+                }
+                Token::VarGlobal => {
+                    if let Some(block) = self.parse_variable_block() {
+                        configuration.variables.push(block);
+                    }
+                }
+                Token::Resource => {
+                    if let Some(resource) = self.parse_resource() {
+                        configuration.resources.push(resource);
+                    }
+                }
+                _ => {
+                    self.error_out::<()>("Expected a RESOURCE or VAR_GLOBAL in the configuration.");
+                }
+            }
+            self.advance();
+        }
+
+        self.error_out(&format!(
+            "Configuration {} is not properly closed. Try adding a END_CONFIGURATION to the end.",
+            configuration.name.name
+        ))
+    }
+
+    fn parse_resource(&mut self) -> Option<Resource> {
+        let start = self.cur_span();
+        self.advance();
+
+        let name = self.parse_identifier("Expected a name after the RESOURCE keyword.")?;
+        self.expect_peek(
+            Token::On,
+            "Expected ON and the processor the resource runs on.",
+        )?;
+        self.advance();
+        let processor = self.parse_identifier("Expected the processor after ON.")?;
+        self.advance();
+
+        let mut resource = Resource {
+            name,
+            processor,
+            variables: Vec::new(),
+            tasks: Vec::new(),
+            programs: Vec::new(),
+            span: start,
+        };
+        while let Some(cur) = &self.cur {
+            match cur.token {
+                Token::EndResource => {
+                    resource.span = start.to(self.cur_span());
+                    return Some(resource);
+                }
+                Token::VarGlobal => {
+                    if let Some(block) = self.parse_variable_block() {
+                        resource.variables.push(block);
+                    }
+                }
+                Token::Task => match self.parse_task() {
+                    Some(task) => resource.tasks.push(task),
+                    None => self.synchronize(&[Token::EndResource]),
+                },
+                Token::Program => match self.parse_program_instance() {
+                    Some(program) => resource.programs.push(program),
+                    None => self.synchronize(&[Token::EndResource]),
+                },
+                _ => {
+                    self.error_out::<()>("Expected a TASK, PROGRAM or VAR_GLOBAL in the resource.");
+                }
+            }
+            if !self.cur_is(&Token::EndResource) {
+                self.advance();
+            }
+        }
+
+        self.error_out(&format!(
+            "Resource {} is not properly closed. Try adding a END_RESOURCE to the end.",
+            resource.name.name
+        ))
+    }
+
+    fn parse_task(&mut self) -> Option<Task> {
+        let start = self.cur_span();
+        self.advance();
+
+        let name = self.parse_identifier("Expected a name after the TASK keyword.")?;
+        self.expect_peek(
+            Token::LeftParenthesis,
+            "Expected the task properties in parentheses after the task name.",
+        )?;
+
+        let mut task = Task {
+            name,
+            interval: None,
+            single: None,
+            priority: None,
+            span: start,
+        };
+        while !self.peek_is(&Token::RightParenthesis) {
+            self.advance();
+            let property = self.parse_identifier("Expected INTERVAL, SINGLE or PRIORITY.")?;
+            self.expect_peek(Token::Assign, "Expected ':=' after the task property.")?;
+            self.advance();
+            let value = Some(self.parse_expression()?);
+            match property.key().as_str() {
+                "INTERVAL" => task.interval = value,
+                "SINGLE" => task.single = value,
+                "PRIORITY" => task.priority = value,
+                _ => {
+                    return self.error_out(&format!(
+                        "Unknown task property {}. Expected INTERVAL, SINGLE or PRIORITY.",
+                        property.name
+                    ));
+                }
+            }
+            if !self.peek_is(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_peek(
+            Token::RightParenthesis,
+            "Expected a closing parenthesis after the task properties.",
+        )?;
+        self.expect_peek(
+            Token::SemiColon,
+            "Expected a semi colon at the end of the task declaration.",
+        )?;
+
+        task.span = start.to(self.cur_span());
+        Some(task)
+    }
+
+    fn parse_program_instance(&mut self) -> Option<ProgramInstance> {
+        let start = self.cur_span();
+        self.advance();
+
+        let name = self.parse_identifier("Expected the name of the program instance.")?;
+        let mut task = None;
+        if self.peek_is(&Token::With) {
+            self.advance();
+            self.advance();
+            task = Some(self.parse_identifier("Expected the name of a task after WITH.")?);
+        }
+        self.expect_peek(
+            Token::Colon,
+            "Expected a colon and the program type after the instance name.",
+        )?;
+        self.advance();
+        let program = self.parse_qualified_name("Expected the program type of the instance.")?;
+
+        let mut arguments = Vec::new();
+        if self.peek_is(&Token::LeftParenthesis) {
+            self.advance();
+            arguments = self.parse_arguments()?;
+        }
+        self.expect_peek(
+            Token::SemiColon,
+            "Expected a semi colon at the end of the program instance.",
+        )?;
+
+        Some(ProgramInstance {
+            name,
+            task,
+            program,
+            arguments,
+            span: start.to(self.cur_span()),
+        })
+    }
+
+    fn parse_pou(&mut self, kind: PouKind) -> Option<Pou> {
+        let (keyword, end_token, end_keyword) = match kind {
+            PouKind::Program => ("Program", Token::EndProgram, "END_PROGRAM"),
+            PouKind::Function => ("Function", Token::EndFunction, "END_FUNCTION"),
+            PouKind::FunctionBlock => (
+                "Function block",
+                Token::EndFunctionBlock,
+                "END_FUNCTION_BLOCK",
+            ),
+            PouKind::Method => ("Method", Token::EndMethod, "END_METHOD"),
+        };
+        let start = self.cur_span();
+        self.advance();
+
+        let identifier = self.parse_identifier(&format!(
+            "Expected an identifier token after the {} declaration.",
+            keyword.to_uppercase()
+        ))?;
+        let mut pou = Pou::new(identifier);
+
+        if matches!(kind, PouKind::Function | PouKind::Method) && self.peek_is(&Token::Colon) {
+            self.advance();
+            self.advance();
+            pou.return_type = Some(self.parse_data_type()?);
+        }
+        self.advance();
+
+        while let Some(cur) = &self.cur {
+            // Correct ending of a pou
+            if cur.token == end_token {
+                pou.span = start.to(self.cur_span());
+                return Some(pou);
+            }
+
+            match cur.token {
+                Token::Var
+                | Token::VarInput
+                | Token::VarOutput
+                | Token::VarInOut
+                | Token::VarTemp
+                | Token::VarExternal => {
+                    if let Some(block) = self.parse_variable_block() {
+                        pou.variables.push(block);
+                    }
+                }
+                Token::Method if kind != PouKind::Method => {
+                    if let Some(method) = self.parse_pou(PouKind::Method) {
+                        pou.methods.push(method);
+                    }
+                }
+                Token::Action if kind != PouKind::Method => {
+                    if let Some(action) = self.parse_action() {
+                        pou.actions.push(action);
+                    }
+                }
+                _ => match self.parse_statement() {
+                    Some(statement) => pou.statements.push(statement),
+                    None => self.synchronize(std::slice::from_ref(&end_token)),
+                },
+            }
+
+            if self.cur_is(&end_token) {
+                continue;
+            }
+            self.advance();
+        }
+
+        self.error_out(&format!(
+            "{keyword} {} is not properly closed. Try adding a {end_keyword} to the end.",
+            pou.name.name
+        ))
+    }
+
+    fn parse_action(&mut self) -> Option<Action> {
+        let start = self.cur_span();
+        self.advance();
+
+        let mut name = self.parse_identifier("Expected an identifier token after ACTION.")?;
+        let mut owner = None;
+        if self.peek_is(&Token::Dot) {
+            self.advance();
+            self.advance();
+            owner = Some(name);
+            name = self.parse_identifier("Expected the action name after the owning POU.")?;
+        }
+        if self.peek_is(&Token::Colon) {
+            self.advance();
+        }
+        self.advance();
+
+        let statements = self.parse_statements_until(&[Token::EndAction]);
+        if !self.cur_is(&Token::EndAction) {
+            return self.error_out(&format!(
+                "Action {} is not properly closed. Try adding a END_ACTION to the end.",
+                name.name
+            ));
+        }
+
+        Some(Action {
+            owner,
+            name,
+            statements,
+            span: start.to(self.cur_span()),
+        })
+    }
+
+    fn parse_variable_block(&mut self) -> Option<VariableBlock> {
+        let start = self.cur_span();
+        let kind = match self.cur.as_ref()?.token {
+            Token::VarInput => VariableKind::Input,
+            Token::VarOutput => VariableKind::Output,
+            Token::VarInOut => VariableKind::InOut,
+            Token::VarTemp => VariableKind::Temp,
+            Token::VarGlobal => VariableKind::Global,
+            Token::VarExternal => VariableKind::External,
+            _ => VariableKind::Local,
+        };
+        self.advance();
+
+        let mut block = VariableBlock {
+            kind,
+            constant: false,
+            retain: false,
+            declarations: Vec::new(),
+            span: start,
+        };
+
+        loop {
+            if self.cur_is(&Token::Constant) {
+                block.constant = true;
+            } else if self.cur_is(&Token::Retain) || self.cur_is(&Token::Persistent) {
+                block.retain = true;
+            } else {
+                break;
+            }
+            self.advance();
+        }
+
+        while let Some(cur) = &self.cur {
+            if cur.token == Token::EndVar {
+                block.span = start.to(self.cur_span());
+                return Some(block);
+            }
+
+            match self.parse_variable_declaration() {
+                Some(declaration) => block.declarations.push(declaration),
+                None => self.synchronize(&[Token::EndVar]),
+            }
+
+            if !self.cur_is(&Token::EndVar) {
+                self.advance();
+            }
+        }
+
+        self.error_out("Variable block is not properly closed. Try adding a END_VAR to the end.")
+    }
+
+    fn parse_variable_declaration(&mut self) -> Option<VariableDeclaration> {
+        let mut names = vec![self.parse_identifier("Expected the name of a variable.")?];
+        while self.peek_is(&Token::Comma) {
+            self.advance();
+            self.advance();
+            names.push(self.parse_identifier("Expected the name of a variable after a comma.")?);
+        }
+
+        let mut location = None;
+        if self.peek_is(&Token::At) {
+            self.advance();
+            self.advance();
+            location = match &self.cur {
+                Some(MarkedToken {
+                    token: Token::DirectAddress(a),
+                    ..
+                }) => Some(Identifier::new(a, self.cur_span())),
+                _ => return self.error_out("Expected a direct address like %IX0.0 after AT."),
+            };
+        }
+
+        self.expect_peek(
+            Token::Colon,
+            "Expected a colon between the variable name and its type.",
+        )?;
+        self.advance();
+        let data_type = self.parse_data_type()?;
+
+        let mut initializer = None;
+        if self.peek_is(&Token::Assign) {
+            self.advance();
+            self.advance();
+            initializer = Some(self.parse_expression()?);
+        }
+
+        self.expect_peek(
+            Token::SemiColon,
+            "Expected a semi colon at the end of the declaration.",
+        )?;
+
+        Some(VariableDeclaration {
+            names,
+            location,
+            data_type,
+            initializer,
+        })
+    }
+
+    fn parse_type_block(&mut self) -> Option<Vec<TypeDeclaration>> {
+        self.advance();
+
+        let mut declarations = Vec::new();
+        while let Some(cur) = &self.cur {
+            if cur.token == Token::EndType {
+                return Some(declarations);
+            }
+
+            match self.parse_type_declaration() {
+                Some(declaration) => declarations.push(declaration),
+                None => self.synchronize(&[Token::EndType]),
+            }
+
+            if !self.cur_is(&Token::EndType) {
+                self.advance();
+            }
+        }
+
+        self.error_out("Type block is not properly closed. Try adding a END_TYPE to the end.")
+    }
+
+    fn parse_type_declaration(&mut self) -> Option<TypeDeclaration> {
+        let name = self.parse_identifier("Expected the name of a type.")?;
+        self.expect_peek(
+            Token::Colon,
+            "Expected a colon between the type name and its definition.",
+        )?;
+        self.advance();
+        let data_type = self.parse_data_type()?;
+
+        let mut initializer = None;
+        if self.peek_is(&Token::Assign) {
+            self.advance();
+            self.advance();
+            initializer = Some(self.parse_expression()?);
+        }
+
+        // Structured types may omit the trailing semi colon
+        let is_structured = matches!(data_type, DataType::Struct(..) | DataType::Union(..));
+        if !is_structured || self.peek_is(&Token::SemiColon) {
+            self.expect_peek(
+                Token::SemiColon,
+                "Expected a semi colon at the end of the type declaration.",
+            )?;
+        }
+
+        Some(TypeDeclaration {
+            name,
+            data_type,
+            initializer,
+        })
+    }
+
+    fn parse_data_type(&mut self) -> Option<DataType> {
+        let start = self.cur_span();
+        match &self.cur {
+            Some(cur) => match cur.token {
+                Token::Identifier(_) => self.parse_named_data_type(),
+                Token::Array => {
+                    self.expect_peek(
+                        Token::LeftBracket,
+                        "Expected the dimensions of the array in brackets.",
+                    )?;
+                    let mut ranges = Vec::new();
+                    loop {
+                        self.advance();
+                        ranges.push(self.parse_range()?);
+                        if !self.peek_is(&Token::Comma) {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    self.expect_peek(
+                        Token::RightBracket,
+                        "Expected a closing bracket after the array dimensions.",
+                    )?;
+                    self.expect_peek(Token::Of, "Expected OF after the array dimensions.")?;
+                    self.advance();
+                    let element = self.parse_data_type()?;
+                    Some(DataType::Array {
+                        ranges,
+                        span: start.to(element.span()),
+                        element: Box::new(element),
+                    })
+                }
+                Token::Pointer | Token::Reference => {
+                    let is_pointer = cur.token == Token::Pointer;
+                    self.expect_peek(Token::To, "Expected TO after POINTER or REFERENCE.")?;
+                    self.advance();
+                    let target = self.parse_data_type()?;
+                    let span = start.to(target.span());
+                    match is_pointer {
+                        true => Some(DataType::Pointer(Box::new(target), span)),
+                        false => Some(DataType::Reference(Box::new(target), span)),
+                    }
+                }
+                Token::LeftParenthesis => self.parse_enum_data_type(),
+                Token::Struct | Token::Union => {
+                    let is_struct = cur.token == Token::Struct;
+                    let end_token = match is_struct {
+                        true => Token::EndStruct,
+                        false => Token::EndUnion,
+                    };
+                    self.advance();
+
+                    let mut members = Vec::new();
+                    while let Some(cur) = &self.cur {
+                        if cur.token == end_token {
+                            let span = start.to(self.cur_span());
+                            return match is_struct {
+                                true => Some(DataType::Struct(members, span)),
+                                false => Some(DataType::Union(members, span)),
+                            };
+                        }
+
+                        match self.parse_variable_declaration() {
+                            Some(member) => members.push(member),
+                            None => self.synchronize(std::slice::from_ref(&end_token)),
+                        }
+
+                        if !self.cur_is(&end_token) {
+                            self.advance();
+                        }
+                    }
+                    self.error_out("Structured type is not properly closed.")
+                }
+                _ => self.error_out("Expected a data type."),
+            },
+            None => self.error_out("Expected a data type but no more tokens left."),
+        }
+    }
+
+    fn parse_named_data_type(&mut self) -> Option<DataType> {
+        let name = self.parse_qualified_name("Expected the name of a data type.")?;
+        let key = name.last().key();
+
+        if name.parts.len() == 1 && (key == "STRING" || key == "WSTRING") {
+            let wide = key == "WSTRING";
+            let mut span = name.span();
+            let mut length = None;
+            if self.peek_is(&Token::LeftParenthesis) || self.peek_is(&Token::LeftBracket) {
+                let closing = match self.peek_is(&Token::LeftParenthesis) {
+                    true => Token::RightParenthesis,
+                    false => Token::RightBracket,
+                };
+                self.advance();
+                self.advance();
+                length = Some(Box::new(self.parse_expression()?));
+                self.expect_peek(closing, "Expected the string length to be closed.")?;
+                span = span.to(self.cur_span());
+            }
+            return Some(DataType::String { wide, length, span });
+        }
+
+        if self.peek_is(&Token::LeftParenthesis) {
+            self.advance();
+            self.advance();
+            let range = self.parse_range()?;
+            self.expect_peek(
+                Token::RightParenthesis,
+                "Expected a closing parenthesis after the subrange.",
+            )?;
+            return Some(DataType::Subrange { base: name, range });
+        }
+
+        Some(DataType::Named(name))
+    }
+
+    fn parse_enum_data_type(&mut self) -> Option<DataType> {
+        let start = self.cur_span();
+        let mut values = Vec::new();
+        loop {
+            self.advance();
+            let name = self.parse_identifier("Expected the name of an enum value.")?;
+            let mut value = None;
+            if self.peek_is(&Token::Assign) {
+                self.advance();
+                self.advance();
+                value = Some(self.parse_expression()?);
+            }
+            values.push(EnumValue { name, value });
+
+            if !self.peek_is(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_peek(
+            Token::RightParenthesis,
+            "Expected a closing parenthesis after the enum values.",
+        )?;
+
+        let mut base = None;
+        if let Some(MarkedToken {
+            token: Token::Identifier(_),
+            ..
+        }) = &self.peek
+        {
+            self.advance();
+            base = Some(self.parse_qualified_name("Expected the base type of the enum.")?);
+        }
+
+        Some(DataType::Enum {
+            base,
+            values,
+            span: start.to(self.cur_span()),
+        })
+    }
+
+    fn parse_range(&mut self) -> Option<Range> {
+        let lower = self.parse_expression()?;
+        self.expect_peek(
+            Token::Range,
+            "Expected two dots between the bounds of a range.",
+        )?;
+        self.advance();
+        let upper = self.parse_expression()?;
+
+        Some(Range { lower, upper })
+    }
+
+    /// Parses statements until one of the terminators is the current token.
+    fn parse_statements_until(&mut self, terminators: &[Token]) -> Statements {
+        let mut statements = Vec::new();
+        while let Some(cur) = &self.cur {
+            if terminators.contains(&cur.token) {
+                break;
+            }
+
+            match self.parse_statement() {
+                Some(statement) => statements.push(statement),
+                None => self.synchronize(terminators),
+            }
+
+            if self
+                .cur
+                .as_ref()
+                .is_some_and(|c| terminators.contains(&c.token))
+            {
+                break;
+            }
+            self.advance();
+        }
+
+        statements
+    }
+
+    fn parse_statement(&mut self) -> Option<Statement> {
+        match &self.cur {
+            Some(cur) => match cur.token {
+                Token::SemiColon => Some(Statement::Empty(self.cur_span())),
+                Token::Exit => {
+                    let span = self.cur_span();
+                    self.expect_peek(Token::SemiColon, "Expected a semi colon after EXIT.")?;
+                    Some(Statement::Exit(span))
+                }
+                Token::Return => {
+                    let span = self.cur_span();
+                    self.expect_peek(Token::SemiColon, "Expected a semi colon after RETURN.")?;
+                    Some(Statement::Return(span))
+                }
+                Token::Continue => {
+                    let span = self.cur_span();
+                    self.expect_peek(Token::SemiColon, "Expected a semi colon after CONTINUE.")?;
+                    Some(Statement::Continue(span))
+                }
+                Token::If => self.parse_if_condition(),
+                Token::Case => self.parse_case_statement(),
+                Token::For => self.parse_for_loop(),
+                Token::While => self.parse_while_loop(),
+                Token::Repeat => self.parse_repeat_loop(),
+                Token::Pragma(_) => self.error_out("Pragmas are currently not supported"),
+                _ => self.parse_expression_statement(),
+            },
+            None => self.error_out("Expected a statement but no more tokens left."),
+        }
+    }
+
+    /// Expects the closing keyword of a compound statement followed by a semi colon.
+    fn expect_statement_end(&mut self, end_token: Token, end_keyword: &str) -> Option<Span> {
+        if !self.cur_is(&end_token) {
+            return self.error_out(&format!("Expected {end_keyword} to close the statement."));
+        }
+        let span = self.cur_span();
+        self.expect_peek(
+            Token::SemiColon,
+            &format!("Expected a semi colon after {end_keyword}."),
+        )?;
+
+        Some(span)
+    }
+
+    fn parse_if_condition(&mut self) -> Option<Statement> {
+        let start = self.cur_span();
+        self.advance();
+
+        let condition = match self.parse_expression() {
+            Some(c) => c,
+            None => {
+                return self.error_out("Expected an expression as a condition of an if statement.");
+            }
+        };
+        self.expect_peek(
+            Token::Then,
+            "Expected the THEN keyword to separate the condition and the conditional code.",
+        )?;
+        self.advance();
+
+        let terminators = [Token::Elsif, Token::Else, Token::EndIf];
+        let mut if_condition = IfCondition {
+            branch: IfConditionalBranch {
+                condition,
+                statements: self.parse_statements_until(&terminators),
+            },
+            alt_branches: Vec::new(),
+            fallback: None,
+            span: start,
+        };
+
+        while self.cur_is(&Token::Elsif) {
+            self.advance();
+            let condition = match self.parse_expression() {
+                Some(c) => c,
+                None => {
+                    return self
+                        .error_out("Expected an expression as a condition of an else if branch.");
+                }
+            };
+            self.expect_peek(
+                Token::Then,
+                "Expected the THEN keyword to separate the condition and the conditional code.",
+            )?;
+            self.advance();
+
+            if_condition.alt_branches.push(IfConditionalBranch {
+                condition,
+                statements: self.parse_statements_until(&terminators),
+            });
+        }
+
+        if self.cur_is(&Token::Else) {
+            self.advance();
+            if_condition.fallback = Some(self.parse_statements_until(&[Token::EndIf]));
+        }
+
+        let end = self.expect_statement_end(Token::EndIf, "END_IF")?;
+        if_condition.span = start.to(end);
+        Some(Statement::If(if_condition))
+    }
+
+    fn parse_case_statement(&mut self) -> Option<Statement> {
+        let start = self.cur_span();
+        self.advance();
+
+        let selector = self.parse_expression()?;
+        self.expect_peek(Token::Of, "Expected OF after the CASE selector.")?;
+        self.advance();
+
+        let mut case = CaseStatement {
+            selector,
+            branches: Vec::new(),
+            fallback: None,
+            span: start,
+        };
+
+        while let Some(cur) = &self.cur {
+            match cur.token {
+                Token::EndCase => break,
+                Token::Else => {
+                    self.advance();
+                    case.fallback = Some(self.parse_statements_until(&[Token::EndCase]));
+                    break;
+                }
+                Token::Number(..) | Token::LiteralPrefix(_) | Token::Minus => {
+                    let first = self.parse_expression()?;
+                    let labels = self.parse_case_labels_from(first)?;
+                    case.branches.push(CaseBranch {
+                        labels,
+                        statements: Vec::new(),
+                    });
+                }
+                Token::Identifier(_) => {
+                    // Either the label of the next branch or a statement of the current one
+                    let expression = self.parse_expression()?;
+                    if self.peek_is(&Token::Colon)
+                        || self.peek_is(&Token::Comma)
+                        || self.peek_is(&Token::Range)
+                    {
+                        let labels = self.parse_case_labels_from(expression)?;
+                        case.branches.push(CaseBranch {
+                            labels,
+                            statements: Vec::new(),
+                        });
+                    } else {
+                        let statement = self.parse_expression_statement_from(expression);
+                        self.push_case_statement(&mut case, statement);
+                    }
+                }
+                _ => {
+                    let statement = self.parse_statement();
+                    self.push_case_statement(&mut case, statement);
+                }
+            }
+
+            if !self.cur_is(&Token::EndCase) {
+                self.advance();
+            }
+        }
+
+        let end = self.expect_statement_end(Token::EndCase, "END_CASE")?;
+        case.span = start.to(end);
+        Some(Statement::Case(case))
+    }
+
+    fn push_case_statement(&mut self, case: &mut CaseStatement, statement: Option<Statement>) {
+        match (statement, case.branches.last_mut()) {
+            (Some(statement), Some(branch)) => branch.statements.push(statement),
+            (Some(_), None) => {
+                self.error_out::<()>("Expected a CASE label before the first statement.");
+            }
+            (None, _) => self.synchronize(&[Token::EndCase]),
+        }
+    }
+
+    /// Parses the remaining `, label, low..high` after the first label and leaves the colon as
+    /// current token.
+    fn parse_case_labels_from(&mut self, first: Expression) -> Option<Vec<CaseLabel>> {
+        let mut labels = vec![self.parse_case_label_from(first)?];
+        while self.peek_is(&Token::Comma) {
+            self.advance();
+            self.advance();
+            let value = self.parse_expression()?;
+            labels.push(self.parse_case_label_from(value)?);
+        }
+        self.expect_peek(Token::Colon, "Expected a colon after the CASE labels.")?;
+
+        Some(labels)
+    }
+
+    fn parse_case_label_from(&mut self, value: Expression) -> Option<CaseLabel> {
+        if !self.peek_is(&Token::Range) {
+            return Some(CaseLabel::Value(value));
+        }
+
+        self.advance();
+        self.advance();
+        let upper = self.parse_expression()?;
+        Some(CaseLabel::Range(Range {
+            lower: value,
+            upper,
+        }))
+    }
+
+    fn parse_for_loop(&mut self) -> Option<Statement> {
+        let start = self.cur_span();
+        self.advance();
+
+        let variable = self.parse_identifier("Expected the loop variable after FOR.")?;
+        self.expect_peek(
+            Token::Assign,
+            "Expected an assignment of the loop variable's start value.",
+        )?;
+        self.advance();
+        let from = self.parse_expression()?;
+        self.expect_peek(Token::To, "Expected TO after the start value of the loop.")?;
+        self.advance();
+        let to = self.parse_expression()?;
+
+        let mut step = None;
+        if self.peek_is(&Token::By) {
+            self.advance();
+            self.advance();
+            step = Some(self.parse_expression()?);
+        }
+        self.expect_peek(Token::Do, "Expected DO after the loop header.")?;
+        self.advance();
+
+        let statements = self.parse_statements_until(&[Token::EndFor]);
+        let end = self.expect_statement_end(Token::EndFor, "END_FOR")?;
+
+        Some(Statement::For(ForLoop {
+            variable,
+            start: from,
+            end: to,
+            step,
+            statements,
+            span: start.to(end),
+        }))
+    }
+
+    fn parse_while_loop(&mut self) -> Option<Statement> {
+        let start = self.cur_span();
+        self.advance();
+
+        let condition = self.parse_expression()?;
+        self.expect_peek(Token::Do, "Expected DO after the loop condition.")?;
+        self.advance();
+
+        let statements = self.parse_statements_until(&[Token::EndWhile]);
+        let end = self.expect_statement_end(Token::EndWhile, "END_WHILE")?;
+
+        Some(Statement::While(WhileLoop {
+            condition,
+            statements,
+            span: start.to(end),
+        }))
+    }
+
+    fn parse_repeat_loop(&mut self) -> Option<Statement> {
+        let start = self.cur_span();
+        self.advance();
+
+        let statements = self.parse_statements_until(&[Token::Until, Token::EndRepeat]);
+        if !self.cur_is(&Token::Until) {
+            return self.error_out("Expected UNTIL followed by the loop condition.");
+        }
+        self.advance();
+        let condition = self.parse_expression()?;
+        self.advance();
+
+        let end = self.expect_statement_end(Token::EndRepeat, "END_REPEAT")?;
+
+        Some(Statement::Repeat(RepeatLoop {
+            statements,
+            condition,
+            span: start.to(end),
+        }))
+    }
+
+    fn parse_expression_statement(&mut self) -> Option<Statement> {
+        let expr = self.parse_expression()?;
+        self.parse_expression_statement_from(expr)
     }
 
-    fn parse_block(&mut self) -> Option<Block> {
+    fn parse_expression_statement_from(&mut self, expr: Expression) -> Option<Statement> {
+        let mut statement = Statement::Expression(expr);
+        if self.peek_is(&Token::Assign) {
+            self.advance();
+            self.advance();
+            let value = self.parse_expression()?;
+            let Statement::Expression(target) = statement else {
+                unreachable!("Statement was constructed as an expression above");
+            };
+            statement = Statement::Assignment(Assignment { target, value });
+        }
+        self.advance();
+
         match &self.cur {
             Some(cur) => match cur.token {
-                Token::Program => self.parse_program(),
-                Token::Action => todo!(),
-                Token::Function => todo!(),
-                Token::FunctionBlock => todo!(),
-                Token::Var => todo!(),
-                Token::Type => todo!(),
-                _ => self.error_out("Expected a block opening token."),
+                Token::SemiColon => Some(statement),
+                _ => self.error_out("Expected a semi colon at the end of the statement."),
             },
-            None => self.error_out("Expected a block opening token."),
+            None => self.error_out("Statement at the end of the file is missing a semi colon."),
         }
     }
 
-    fn parse_program(&mut self) -> Option<Block> {
-        self.advance();
-
-        let identifier = match &self.cur {
-            Some(x) => match x.token {
-                Token::Identifier(i) => Some(i.to_string()),
-                _ => self.error_out("Expected an identifier token after the PROGRAM declaration."),
-            },
-            None => self.error_out("Expected an identifier token after the PROGRAM declaration."),
-        }?;
-        self.advance();
+    fn parse_expression(&mut self) -> Option<Expression> {
+        self.parse_expression_with_precedence(LOWEST_PRECEDENCE)
+    }
 
-        let mut statements = Vec::new();
-        while let Some(cur) = &self.cur {
-            // Correct ending of a program
-            if cur.token == Token::EndProgram {
-                return Some(Block::Program(identifier, statements));
-            }
+    fn parse_expression_with_precedence(&mut self, precedence: u8) -> Option<Expression> {
+        let mut left = self.parse_prefix_expression()?;
 
-            if let Some(statement) = self.parse_statement() {
-                statements.push(statement);
-            }
+        while let Some(peek) = &self.peek
+            && precedence < get_precedence(&peek.token)
+        {
             self.advance();
+            left = self.parse_infix_expression(left)?;
         }
 
-        self.error_out(&format!(
-            "Program {identifier} is not properly closed. Try adding a END_PROGRAM to the end."
-        ))
+        Some(left)
     }
 
-    fn parse_statement(&mut self) -> Option<Statement> {
+    fn parse_prefix_expression(&mut self) -> Option<Expression> {
+        let span = self.cur_span();
         match &self.cur {
-            Some(cur) => match cur.token {
-                Token::SemiColon => Some(Statement::Empty),
-                Token::Exit => {
+            Some(cur) => match &cur.token {
+                Token::Identifier(i) => Some(Expression::Identifier(Identifier::new(i, span))),
+                Token::Number(_, x) => Some(Expression::Literal(
+                    LiteralExpression::Number(x.clone()),
+                    span,
+                )),
+                Token::String(s) => Some(Expression::Literal(
+                    LiteralExpression::String(s[1..s.len() - 1].to_string(), s.starts_with('"')),
+                    span,
+                )),
+                Token::Time(_, time_value) => match time_value.nanoseconds() {
+                    Some(_) => Some(Expression::Literal(
+                        LiteralExpression::Time(Box::new(time_value.clone())),
+                        span,
+                    )),
+                    None => self.error_out("The duration does not fit into TIME."),
+                },
+                Token::Date(_, date_value) => match date_value.nanoseconds() {
+                    Some(_) => Some(Expression::Literal(
+                        LiteralExpression::Date(Box::new(date_value.clone())),
+                        span,
+                    )),
+                    None => self.error_out("The date or time of day does not exist."),
+                },
+                Token::LiteralPrefix(prefix) => {
+                    let type_name = QualifiedName {
+                        parts: vec![Identifier::new(
+                            &prefix[..prefix.len() - 1],
+                            Span {
+                                len: span.len - 1,
+                                ..span
+                            },
+                        )],
+                    };
+                    self.parse_typed_literal(type_name)
+                }
+                Token::Plus => {
                     self.advance();
-                    Some(Statement::Exit)
+                    self.parse_expression_with_precedence(PREFIX_PRECEDENCE)
                 }
-                Token::Return => {
+                Token::Minus | Token::Not => {
+                    let op = match cur.token {
+                        Token::Minus => PrefixOperator::Negation,
+                        _ => PrefixOperator::Not,
+                    };
                     self.advance();
-                    Some(Statement::Return)
+                    let operand = self.parse_expression_with_precedence(PREFIX_PRECEDENCE)?;
+                    Some(Expression::Prefix(PrefixExpression {
+                        op,
+                        operand: Box::new(operand),
+                        span,
+                    }))
                 }
-                Token::If => self.parse_if_condition(),
-                Token::Case => todo!(),
-                Token::For => todo!(),
-                Token::While => todo!(),
-                Token::Pragma(_) => self.error_out("Pragmas are currently not supported"),
-                _ => self.parse_expression_statement(),
+                Token::LeftParenthesis => self.parse_grouped_expression(),
+                Token::LeftBracket => {
+                    let mut items = Vec::new();
+                    if !self.peek_is(&Token::RightBracket) {
+                        loop {
+                            self.advance();
+                            items.push(self.parse_expression()?);
+                            if !self.peek_is(&Token::Comma) {
+                                break;
+                            }
+                            self.advance();
+                        }
+                    }
+                    self.expect_peek(
+                        Token::RightBracket,
+                        "Expected a closing bracket after the array values.",
+                    )?;
+                    Some(Expression::Array(items, span.to(self.cur_span())))
+                }
+                Token::True => Some(Expression::Literal(LiteralExpression::True, span)),
+                Token::False => Some(Expression::Literal(LiteralExpression::False, span)),
+                _ => self.error_out("Invalid token kind for an expression."),
             },
-            None => self.error_out("Expected a statement but no more tokens left."),
+            None => self.error_out("No more tokens left but expected an expression."),
         }
     }
 
-    fn parse_if_condition(&mut self) -> Option<Statement> {
+    fn parse_typed_literal(&mut self, type_name: QualifiedName) -> Option<Expression> {
         self.advance();
+        let value = self.parse_expression_with_precedence(PREFIX_PRECEDENCE)?;
 
-        let mut condition = IfCondition {
-            branch: IfConditionalBranch {
-                condition: Expression::Literal(LiteralExpression::True),
-                statements: Vec::new(),
-            },
-            alt_branches: Vec::new(),
-            fallback: None,
+        Some(Expression::TypedLiteral(TypedLiteral {
+            type_name,
+            value: Box::new(value),
+        }))
+    }
+
+    /// Parses `(expr)` as well as structure initializers like `(x := 1, y := 2)`.
+    fn parse_grouped_expression(&mut self) -> Option<Expression> {
+        let start = self.cur_span();
+        self.advance();
+        let first = self.parse_expression()?;
+
+        let Expression::Identifier(first_member) = &first else {
+            self.expect_peek(Token::RightParenthesis, "Expected a closing parenthesis.")?;
+            return Some(first);
         };
+        if !self.peek_is(&Token::Assign) {
+            self.expect_peek(Token::RightParenthesis, "Expected a closing parenthesis.")?;
+            return Some(first);
+        }
 
-        if let Some(c) = self.parse_expression() {
-            condition.branch.condition = c;
+        let mut member = first_member.clone();
+        let mut members = Vec::new();
+        loop {
+            self.expect_peek(Token::Assign, "Expected := after the member name.")?;
             self.advance();
-        } else {
-            return self.error_out("Expected an expression as a condition of an if statement.");
+            members.push((member, self.parse_expression()?));
+
+            if !self.peek_is(&Token::Comma) {
+                break;
+            }
+            self.advance();
+            self.advance();
+            member = self.parse_identifier("Expected the name of a structure member.")?;
         }
+        self.expect_peek(
+            Token::RightParenthesis,
+            "Expected a closing parenthesis after the structure initializer.",
+        )?;
+
+        Some(Expression::Struct(members, start.to(self.cur_span())))
+    }
+
+    fn parse_infix_expression(&mut self, left: Expression) -> Option<Expression> {
+        let cur = self.cur.as_ref()?;
+        let op = match cur.token {
+            Token::Plus => InfixOperator::Addition,
+            Token::Minus => InfixOperator::Subtraction,
+            Token::Asterisk => InfixOperator::Multiplication,
+            Token::Slash => InfixOperator::Division,
+            Token::Percent | Token::Mod => InfixOperator::Modulo,
+            Token::Power => InfixOperator::Power,
+            Token::Equals => InfixOperator::Equals,
+            Token::NotEquals => InfixOperator::NotEquals,
+            Token::GreaterThan => InfixOperator::GreaterThan,
+            Token::GreaterThanOrEquals => InfixOperator::GreaterThanOrEquals,
+            Token::LessThan => InfixOperator::LessThan,
+            Token::LessThanOrEquals => InfixOperator::LessThanOrEquals,
+            Token::And | Token::Ampersand => InfixOperator::And,
+            Token::Or => InfixOperator::Or,
+            Token::Xor => InfixOperator::Xor,
+            Token::Dot => return self.parse_member_expression(left),
+            Token::LeftBracket => return self.parse_index_expression(left),
+            Token::LeftParenthesis => return self.parse_call_expression(left),
+            Token::Caret => return Some(Expression::Deref(Box::new(left), self.cur_span())),
+            _ => return self.error_out("Invalid operator in an expression."),
+        };
+
+        let precedence = get_precedence(&cur.token);
+        self.advance();
+        let right = self.parse_expression_with_precedence(precedence)?;
 
-        if let Some(cur) = &self.cur
-            && cur.token == Token::Then
+        Some(Expression::Infix(InfixExpression {
+            left: Box::new(left),
+            right: Box::new(right),
+            op,
+        }))
+    }
+
+    fn parse_member_expression(&mut self, target: Expression) -> Option<Expression> {
+        self.advance();
+
+        // Qualified typed literals like `Ns.Color#Red`
+        if let Some(MarkedToken {
+            token: Token::LiteralPrefix(prefix),
+            ..
+        }) = &self.cur
         {
-            self.advance();
-        } else {
-            return self.error_out(
-                "Expected the THEN keyword to separate the condition and the conditional code.",
-            );
+            let span = self.cur_span();
+            let Some(mut type_name) = qualified_name_of(&target) else {
+                return self.error_out("Expected a qualified type name before the #.");
+            };
+            type_name.parts.push(Identifier::new(
+                &prefix[..prefix.len() - 1],
+                Span {
+                    len: span.len - 1,
+                    ..span
+                },
+            ));
+            return self.parse_typed_literal(type_name);
         }
 
-        let mut statements = &mut condition.branch.statements;
+        let member = self.parse_identifier("Expected the name of a member after the dot.")?;
+        Some(Expression::Member(MemberExpression {
+            target: Box::new(target),
+            member,
+        }))
+    }
 
-        while let Some(cur) = &self.cur {
-            if cur.token == Token::EndIf {
-                self.advance();
-                return Some(Statement::If(condition));
+    fn parse_index_expression(&mut self, target: Expression) -> Option<Expression> {
+        let mut indices = Vec::new();
+        loop {
+            self.advance();
+            indices.push(self.parse_expression()?);
+            if !self.peek_is(&Token::Comma) {
+                break;
             }
+            self.advance();
+        }
+        self.expect_peek(
+            Token::RightBracket,
+            "Expected a closing bracket after the index.",
+        )?;
 
-            if cur.token == Token::Else {
-                condition.fallback = Some(Vec::new());
-                statements = condition.fallback.as_mut().unwrap();
-            } else if cur.token == Token::Elsif {
-                self.advance();
-                if let Some(c) = self.parse_expression() {
-                    condition.alt_branches.push(IfConditionalBranch {
-                        condition: c,
-                        statements: Vec::new(),
-                    });
-                    statements = &mut condition.alt_branches.last_mut().unwrap().statements;
-                    self.advance();
-                } else {
-                    return self
-                        .error_out("Expected an expression as a condition of an else if branch.");
-                }
+        Some(Expression::Index(IndexExpression {
+            target: Box::new(target),
+            indices,
+            span: self.cur_span(),
+        }))
+    }
+
+    fn parse_call_expression(&mut self, callee: Expression) -> Option<Expression> {
+        let arguments = self.parse_arguments()?;
+
+        Some(Expression::Call(CallExpression {
+            callee: Box::new(callee),
+            arguments,
+            span: self.cur_span(),
+        }))
+    }
 
-                if let Some(cur) = &self.cur
-                    && cur.token == Token::Then
-                {
-                } else {
-                    return self.error_out("Expected the THEN keyword to separate the condition and the conditional code.");
+    /// Parses the arguments after an opening parenthesis up to the closing one.
+    fn parse_arguments(&mut self) -> Option<Vec<Argument>> {
+        let mut arguments = Vec::new();
+        if !self.peek_is(&Token::RightParenthesis) {
+            loop {
+                self.advance();
+                arguments.push(self.parse_argument()?);
+                if !self.peek_is(&Token::Comma) {
+                    break;
                 }
-            } else if let Some(s) = self.parse_statement() {
-                statements.push(s);
-            } else {
-                self.error_out::<u8>("Expected statement inside code blocks of an if statement.");
+                self.advance();
             }
-
-            self.advance();
         }
+        self.expect_peek(
+            Token::RightParenthesis,
+            "Expected a closing parenthesis after the arguments.",
+        )?;
 
-        None
+        Some(arguments)
     }
 
-    fn parse_expression_statement(&mut self) -> Option<Statement> {
-        let expr = self.parse_expression()?;
-        self.advance();
-
-        match &self.cur {
-            Some(cur) => match cur.token {
-                Token::SemiColon => Some(Statement::Expression(expr)),
-                _ => self.error_out("Expected a semi colon at the end of the statement."),
-            },
-            None => self.error_out("Statement at the end of the file is missing a semi colon."),
+    fn parse_argument(&mut self) -> Option<Argument> {
+        if let Some(MarkedToken {
+            token: Token::Identifier(i),
+            ..
+        }) = &self.cur
+            && (self.peek_is(&Token::Assign) || self.peek_is(&Token::Arrow))
+        {
+            let name = Identifier::new(i, self.cur_span());
+            let is_output = self.peek_is(&Token::Arrow);
+            self.advance();
+            self.advance();
+            let value = self.parse_expression()?;
+            return match is_output {
+                true => Some(Argument::Output(name, value)),
+                false => Some(Argument::Named(name, value)),
+            };
         }
+
+        self.parse_expression().map(Argument::Positional)
     }
+}
 
-    fn parse_expression(&mut self) -> Option<Expression> {
-        match &self.cur {
-            Some(cur) => match &cur.token {
-                Token::Identifier(_) => todo!(),
-                Token::Number(_, x) => {
-                    Some(Expression::Literal(LiteralExpression::Number(x.clone())))
-                }
-                Token::String(_) => todo!(),
-                Token::Time(_, time_value) => todo!(),
-                Token::Plus => todo!(),
-                Token::Minus => todo!(),
-                Token::LeftParenthesis => todo!(),
-                Token::Not => todo!(),
-                Token::True => Some(Expression::Literal(LiteralExpression::True)),
-                Token::False => Some(Expression::Literal(LiteralExpression::False)),
-                _ => self.error_out("Invalid token kind for an expression."),
-            },
-            None => self.error_out("No more tokens left but expected an expression."),
+/// Turns `a.b.c` back into a qualified name.
+fn qualified_name_of(expression: &Expression) -> Option<QualifiedName> {
+    match expression {
+        Expression::Identifier(i) => Some(QualifiedName {
+            parts: vec![i.clone()],
+        }),
+        Expression::Member(m) => {
+            let mut name = qualified_name_of(&m.target)?;
+            name.parts.push(m.member.clone());
+            Some(name)
         }
+        _ => None,
     }
 }
 
 const LOWEST_PRECEDENCE: u8 = 1;
-const EQUALS_PRECEDENCE: u8 = 2;
-const LESS_GREATER_PRECEDENCE: u8 = 3;
-const SUM_PRECEDENCE: u8 = 4;
-const PRODUCT_PRECEDENCE: u8 = 5;
-const PREFIX_PRECEDENCE: u8 = 6;
-const CALL_PRECEDENCE: u8 = 7;
-const INDEX_PRECEDENCE: u8 = 8;
+const OR_PRECEDENCE: u8 = 2;
+const XOR_PRECEDENCE: u8 = 3;
+const AND_PRECEDENCE: u8 = 4;
+const EQUALS_PRECEDENCE: u8 = 5;
+const LESS_GREATER_PRECEDENCE: u8 = 6;
+const SUM_PRECEDENCE: u8 = 7;
+const PRODUCT_PRECEDENCE: u8 = 8;
+const POWER_PRECEDENCE: u8 = 9;
+const PREFIX_PRECEDENCE: u8 = 10;
+const CALL_PRECEDENCE: u8 = 11;
+const INDEX_PRECEDENCE: u8 = 12;
 
 fn get_precedence(t: &Token) -> u8 {
     match t {
+        Token::Or => OR_PRECEDENCE,
+        Token::Xor => XOR_PRECEDENCE,
+        Token::And => AND_PRECEDENCE,
+        Token::Ampersand => AND_PRECEDENCE,
         Token::Plus => SUM_PRECEDENCE,
         Token::Minus => SUM_PRECEDENCE,
         Token::Asterisk => PRODUCT_PRECEDENCE,
         Token::Slash => PRODUCT_PRECEDENCE,
         Token::Percent => PRODUCT_PRECEDENCE,
+        Token::Mod => PRODUCT_PRECEDENCE,
+        Token::Power => POWER_PRECEDENCE,
         Token::Equals => EQUALS_PRECEDENCE,
         Token::NotEquals => EQUALS_PRECEDENCE,
         Token::GreaterThan => LESS_GREATER_PRECEDENCE,
@@ -281,8 +1561,8 @@ fn get_precedence(t: &Token) -> u8 {
         Token::Dot => INDEX_PRECEDENCE,
         Token::LeftParenthesis => CALL_PRECEDENCE,
         Token::LeftBracket => INDEX_PRECEDENCE,
-        Token::Not => PREFIX_PRECEDENCE,
-        _ => 1,
+        Token::Caret => INDEX_PRECEDENCE,
+        _ => LOWEST_PRECEDENCE,
     }
 }
 
@@ -294,18 +1574,53 @@ mod tests {
     };
 
     use super::*;
+    use crate::parsing::ast::assert_same_tree;
 
     fn parse_src(src: &str) -> Ast {
         parse(Lexer::create("main.st", src)).unwrap()
     }
 
+    fn program(ast: &Ast) -> &Pou {
+        match &ast.blocks[0] {
+            Block::Program(p) => p,
+            b => panic!("Expected a program but got {b:?}"),
+        }
+    }
+
+    fn int(value: usize) -> Expression {
+        Expression::Literal(
+            LiteralExpression::Number(NumberValue::Int(value)),
+            Span::default(),
+        )
+    }
+
+    fn ident(name: &str) -> Expression {
+        Expression::Identifier(Identifier::new(name, Span::default()))
+    }
+
+    fn infix(left: Expression, op: InfixOperator, right: Expression) -> Expression {
+        Expression::Infix(InfixExpression {
+            left: Box::new(left),
+            right: Box::new(right),
+            op,
+        })
+    }
+
+    fn parse_expression_src(src: &str) -> Expression {
+        let ast = parse_src(&format!("PROGRAM P {src}; END_PROGRAM"));
+        match &program(&ast).statements[0] {
+            Statement::Expression(e) => e.clone(),
+            s => panic!("Expected an expression statement but got {s:?}"),
+        }
+    }
+
     #[test]
     fn test_empty_program_block() {
         let ast = parse_src("PROGRAM MyProgram END_PROGRAM\n");
 
-        assert_eq!(
+        assert_same_tree!(
             ast.blocks[0],
-            Block::Program("MyProgram".to_string(), vec![])
+            Block::Program(Pou::new(Identifier::new("MyProgram", Span::default())))
         );
     }
 
@@ -318,23 +1633,20 @@ mod tests {
             1;
             EXIT;
             RETURN;
+            CONTINUE;
         END_PROGRAM
         "#,
         );
 
-        assert_eq!(
-            ast.blocks[0],
-            Block::Program(
-                "MyProgram".to_string(),
-                vec![
-                    Statement::Empty,
-                    Statement::Expression(Expression::Literal(LiteralExpression::Number(
-                        NumberValue::Int(1)
-                    ))),
-                    Statement::Exit,
-                    Statement::Return
-                ]
-            )
+        assert_same_tree!(
+            program(&ast).statements,
+            vec![
+                Statement::Empty(Span::default()),
+                Statement::Expression(int(1)),
+                Statement::Exit(Span::default()),
+                Statement::Return(Span::default()),
+                Statement::Continue(Span::default()),
+            ]
         );
     }
 
@@ -359,46 +1671,475 @@ mod tests {
         "#,
         );
 
-        assert_eq!(
-            ast.blocks[0],
-            Block::Program(
-                "MyProgram".to_string(),
-                vec![
-                    Statement::If(IfCondition {
-                        branch: IfConditionalBranch {
-                            condition: Expression::Literal(LiteralExpression::True),
-                            statements: vec![Statement::Empty],
+        assert_same_tree!(
+            program(&ast).statements,
+            vec![
+                Statement::If(IfCondition {
+                    branch: IfConditionalBranch {
+                        condition: Expression::Literal(LiteralExpression::True, Span::default()),
+                        statements: vec![Statement::Empty(Span::default())],
+                    },
+                    alt_branches: vec![],
+                    fallback: None,
+                    span: Span::default(),
+                }),
+                Statement::If(IfCondition {
+                    branch: IfConditionalBranch {
+                        condition: Expression::Literal(LiteralExpression::False, Span::default()),
+                        statements: vec![Statement::Expression(int(1))]
+                    },
+                    alt_branches: vec![
+                        IfConditionalBranch {
+                            condition: Expression::Literal(
+                                LiteralExpression::True,
+                                Span::default()
+                            ),
+                            statements: vec![Statement::Expression(int(2))]
                         },
-                        alt_branches: vec![],
-                        fallback: None,
-                    }),
-                    Statement::If(IfCondition {
-                        branch: IfConditionalBranch {
-                            condition: Expression::Literal(LiteralExpression::False),
-                            statements: vec![Statement::Expression(Expression::Literal(
-                                LiteralExpression::Number(NumberValue::Int(1))
-                            ))]
+                        IfConditionalBranch {
+                            condition: Expression::Literal(
+                                LiteralExpression::False,
+                                Span::default()
+                            ),
+                            statements: vec![Statement::Expression(int(3))]
                         },
-                        alt_branches: vec![
-                            IfConditionalBranch {
-                                condition: Expression::Literal(LiteralExpression::True),
-                                statements: vec![Statement::Expression(Expression::Literal(
-                                    LiteralExpression::Number(NumberValue::Int(2))
-                                ))]
-                            },
-                            IfConditionalBranch {
-                                condition: Expression::Literal(LiteralExpression::False),
-                                statements: vec![Statement::Expression(Expression::Literal(
-                                    LiteralExpression::Number(NumberValue::Int(3))
-                                ))]
-                            },
-                        ],
-                        fallback: Some(vec![Statement::Expression(Expression::Literal(
-                            LiteralExpression::Number(NumberValue::Int(4))
-                        ))]),
-                    })
-                ]
+                    ],
+                    fallback: Some(vec![Statement::Expression(int(4))]),
+                    span: Span::default(),
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert_same_tree!(
+            parse_expression_src("1 + 2 * 3"),
+            infix(
+                int(1),
+                InfixOperator::Addition,
+                infix(int(2), InfixOperator::Multiplication, int(3))
+            )
+        );
+        assert_same_tree!(
+            parse_expression_src("a OR b AND c = 1"),
+            infix(
+                ident("a"),
+                InfixOperator::Or,
+                infix(
+                    ident("b"),
+                    InfixOperator::And,
+                    infix(ident("c"), InfixOperator::Equals, int(1))
+                )
+            )
+        );
+        assert_same_tree!(
+            parse_expression_src("(1 + 2) MOD 3"),
+            infix(
+                infix(int(1), InfixOperator::Addition, int(2)),
+                InfixOperator::Modulo,
+                int(3)
+            )
+        );
+        assert_same_tree!(
+            parse_expression_src("-a ** 2"),
+            infix(
+                Expression::Prefix(PrefixExpression {
+                    op: PrefixOperator::Negation,
+                    operand: Box::new(ident("a")),
+                    span: Span::default(),
+                }),
+                InfixOperator::Power,
+                int(2)
+            )
+        );
+    }
+
+    #[test]
+    fn test_postfix_expressions() {
+        assert_same_tree!(
+            parse_expression_src("fb.out[1, i]^"),
+            Expression::Deref(
+                Box::new(Expression::Index(IndexExpression {
+                    target: Box::new(Expression::Member(MemberExpression {
+                        target: Box::new(ident("fb")),
+                        member: Identifier::new("out", Span::default()),
+                    })),
+                    indices: vec![int(1), ident("i")],
+                    span: Span::default(),
+                })),
+                Span::default()
             )
         );
+        assert_same_tree!(
+            parse_expression_src("Func(1, IN := x, Q => y)"),
+            Expression::Call(CallExpression {
+                callee: Box::new(ident("Func")),
+                arguments: vec![
+                    Argument::Positional(int(1)),
+                    Argument::Named(Identifier::new("IN", Span::default()), ident("x")),
+                    Argument::Output(Identifier::new("Q", Span::default()), ident("y")),
+                ],
+                span: Span::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_typed_literals() {
+        let typed = |parts: &[&str], value: Expression| {
+            Expression::TypedLiteral(TypedLiteral {
+                type_name: QualifiedName {
+                    parts: parts
+                        .iter()
+                        .map(|p| Identifier::new(p, Span::default()))
+                        .collect(),
+                },
+                value: Box::new(value),
+            })
+        };
+
+        assert_same_tree!(parse_expression_src("INT#5"), typed(&["INT"], int(5)));
+        assert_same_tree!(
+            parse_expression_src("Color#Red"),
+            typed(&["Color"], ident("Red"))
+        );
+        assert_same_tree!(
+            parse_expression_src("Ns.Color#Red"),
+            typed(&["Ns", "Color"], ident("Red"))
+        );
+        assert_same_tree!(parse_expression_src("16#FF"), int(255));
+    }
+
+    #[test]
+    fn test_assignment_and_loops() {
+        let ast = parse_src(
+            r#"
+        PROGRAM MyProgram
+            x := 1;
+            FOR i := 0 TO 10 BY 2 DO
+                x := x + i;
+            END_FOR;
+            WHILE x > 0 DO
+                x := x - 1;
+            END_WHILE;
+            REPEAT
+                EXIT;
+            UNTIL TRUE
+            END_REPEAT;
+        END_PROGRAM
+        "#,
+        );
+        let statements = &program(&ast).statements;
+
+        assert_same_tree!(
+            statements[0],
+            Statement::Assignment(Assignment {
+                target: ident("x"),
+                value: int(1),
+            })
+        );
+        assert_same_tree!(
+            statements[1],
+            Statement::For(ForLoop {
+                variable: Identifier::new("i", Span::default()),
+                start: int(0),
+                end: int(10),
+                step: Some(int(2)),
+                statements: vec![Statement::Assignment(Assignment {
+                    target: ident("x"),
+                    value: infix(ident("x"), InfixOperator::Addition, ident("i")),
+                })],
+                span: Span::default(),
+            })
+        );
+        assert!(matches!(statements[2], Statement::While(_)));
+        assert_same_tree!(
+            statements[3],
+            Statement::Repeat(RepeatLoop {
+                statements: vec![Statement::Exit(Span::default())],
+                condition: Expression::Literal(LiteralExpression::True, Span::default()),
+                span: Span::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_case_statement() {
+        let ast = parse_src(
+            r#"
+        PROGRAM MyProgram
+            CASE state OF
+                1, 2:
+                    x := 1;
+                3..5:
+                    ;
+                Idle:
+                    x := 2;
+                    y := 3;
+            ELSE
+                x := 0;
+            END_CASE;
+        END_PROGRAM
+        "#,
+        );
+
+        let Statement::Case(case) = &program(&ast).statements[0] else {
+            panic!("Expected a case statement");
+        };
+        assert_same_tree!(case.selector, ident("state"));
+        assert_same_tree!(
+            case.branches
+                .iter()
+                .map(|b| b.labels.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![CaseLabel::Value(int(1)), CaseLabel::Value(int(2))],
+                vec![CaseLabel::Range(Range {
+                    lower: int(3),
+                    upper: int(5)
+                })],
+                vec![CaseLabel::Value(ident("Idle"))],
+            ]
+        );
+        assert_eq!(
+            case.branches
+                .iter()
+                .map(|b| b.statements.len())
+                .collect::<Vec<_>>(),
+            vec![1, 1, 2]
+        );
+        assert_eq!(case.fallback.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_function_block_with_declarations() {
+        let ast = parse_src(
+            r#"
+        FUNCTION_BLOCK Motor
+            VAR_INPUT
+                enable AT %IX0.0 : BOOL;
+                speed, accel : REAL := 1.5;
+            END_VAR
+            VAR CONSTANT
+                MAX : INT := 10;
+            END_VAR
+            VAR
+                buffer : ARRAY[0..MAX - 1] OF STRING(80);
+                next : POINTER TO Motor;
+            END_VAR
+
+            METHOD Start : BOOL
+                VAR_INPUT force : BOOL; END_VAR
+                Start := TRUE;
+            END_METHOD
+
+            ACTION Reset:
+                speed := 0;
+            END_ACTION
+        END_FUNCTION_BLOCK
+        "#,
+        );
+
+        let Block::FunctionBlock(fb) = &ast.blocks[0] else {
+            panic!("Expected a function block");
+        };
+        assert_eq!(fb.name.name, "Motor");
+        assert_eq!(fb.variables.len(), 3);
+        assert_eq!(fb.variables[0].kind, VariableKind::Input);
+        assert_same_tree!(
+            fb.variables[0].declarations[0].location,
+            Some(Identifier::new("%IX0.0", Span::default()))
+        );
+        assert_eq!(fb.variables[0].declarations[1].names.len(), 2);
+        assert!(fb.variables[1].constant);
+        assert!(matches!(
+            fb.variables[2].declarations[0].data_type,
+            DataType::Array { .. }
+        ));
+        assert!(matches!(
+            fb.variables[2].declarations[1].data_type,
+            DataType::Pointer(..)
+        ));
+        assert_eq!(fb.methods[0].name.name, "Start");
+        assert!(fb.methods[0].return_type.is_some());
+        assert_eq!(fb.actions[0].name.name, "Reset");
+        assert_eq!(fb.actions[0].statements.len(), 1);
+    }
+
+    #[test]
+    fn test_type_and_global_blocks() {
+        let ast = parse_src(
+            r#"
+        TYPE
+            Color : (Red, Green := 5, Blue) INT;
+            Point : STRUCT
+                x, y : REAL;
+            END_STRUCT
+            Percent : INT(0..100);
+        END_TYPE
+
+        VAR_GLOBAL RETAIN
+            origin : Point := (x := 0.0, y := 0.0);
+        END_VAR
+
+        NAMESPACE Lib.Util
+            FUNCTION Twice : INT
+                VAR_INPUT x : INT; END_VAR
+                Twice := x * 2;
+            END_FUNCTION
+        END_NAMESPACE
+        "#,
+        );
+
+        let Block::Type(types) = &ast.blocks[0] else {
+            panic!("Expected a type block");
+        };
+        assert_eq!(types.len(), 3);
+        let DataType::Enum { base, values, .. } = &types[0].data_type else {
+            panic!("Expected an enum type");
+        };
+        assert_eq!(base.as_ref().map(|b| b.to_printable()), Some("INT".into()));
+        assert_eq!(values.len(), 3);
+        assert_same_tree!(values[1].value, Some(int(5)));
+        assert!(matches!(types[1].data_type, DataType::Struct(..)));
+        assert!(matches!(types[2].data_type, DataType::Subrange { .. }));
+
+        let Block::GlobalVariables(globals) = &ast.blocks[1] else {
+            panic!("Expected a global variable block");
+        };
+        assert!(globals.retain);
+        assert!(matches!(
+            globals.declarations[0].initializer,
+            Some(Expression::Struct(..))
+        ));
+
+        let Block::Namespace(namespace) = &ast.blocks[2] else {
+            panic!("Expected a namespace");
+        };
+        assert_eq!(namespace.name.to_printable(), "Lib.Util");
+        assert!(matches!(namespace.blocks[0], Block::Function(_)));
+    }
+
+    #[test]
+    fn test_configuration() {
+        let ast = parse_src(
+            r#"
+        CONFIGURATION Plant
+            VAR_GLOBAL
+                mode : INT;
+            END_VAR
+            RESOURCE Cpu ON PLC_1
+                TASK Fast(INTERVAL := T#10ms, PRIORITY := 1);
+                TASK OnAlarm(SINGLE := alarm, PRIORITY := 0);
+                PROGRAM Main WITH Fast : Lib.Control(enable := TRUE, state => mode);
+                PROGRAM Idle : Background;
+            END_RESOURCE
+        END_CONFIGURATION
+        "#,
+        );
+
+        let Block::Configuration(configuration) = &ast.blocks[0] else {
+            panic!("Expected a configuration");
+        };
+        assert_eq!(configuration.name.name, "Plant");
+        assert_eq!(configuration.variables.len(), 1);
+        let resource = &configuration.resources[0];
+        assert_eq!(resource.processor.name, "PLC_1");
+
+        assert_eq!(resource.tasks.len(), 2);
+        let fast = &resource.tasks[0];
+        assert!(matches!(
+            fast.interval,
+            Some(Expression::Literal(LiteralExpression::Time(_), _))
+        ));
+        assert_same_tree!(fast.priority, Some(int(1)));
+        assert_same_tree!(resource.tasks[1].single, Some(ident("alarm")));
+        assert_same_tree!(resource.tasks[1].interval, None);
+
+        let main = &resource.programs[0];
+        assert_eq!(main.task.as_ref().map(|t| t.name.as_str()), Some("Fast"));
+        assert_eq!(main.program.to_printable(), "Lib.Control");
+        assert_eq!(main.arguments.len(), 2);
+        assert!(matches!(main.arguments[1], Argument::Output(..)));
+        assert_same_tree!(resource.programs[1].task, None);
+
+        let errors = parse(Lexer::create(
+            "main.st",
+            "CONFIGURATION C RESOURCE R ON P
+ TASK T(CYCLE := T#1s);
+END_RESOURCE END_CONFIGURATION",
+        ))
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Unknown task property CYCLE."));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let ast = parse_src(
+            r#"
+        {attribute 'test'}
+        {attribute 'cycle_time' := 'T#10ms'}
+        PROGRAM TestTimer END_PROGRAM
+        "#,
+        );
+
+        let pou = program(&ast);
+        assert_eq!(pou.attributes.len(), 2);
+        assert_eq!(pou.attribute("TEST").map(|a| a.value.clone()), Some(None));
+        assert_eq!(
+            pou.attribute("cycle_time").and_then(|a| a.value.as_deref()),
+            Some("T#10ms")
+        );
+
+        let errors = parse(Lexer::create(
+            "main.st",
+            "{attribute test}\nPROGRAM P END_PROGRAM\n{attribute 'x'}\nTYPE T : INT; END_TYPE",
+        ))
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("Expected an attribute like {attribute 'name'}"));
+        assert!(errors[1].contains("Attributes can only be given to a PROGRAM"));
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let errors = parse(Lexer::create(
+            "main.st",
+            "PROGRAM P\n x := 1\n y := 2;\nEND_PROGRAM",
+        ))
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("main.st:3:1"));
+        assert!(errors[0].contains("Expected a semi colon at the end of the statement."));
+    }
+
+    #[test]
+    fn test_rejects_durations_beyond_time() {
+        let errors = parse(Lexer::create(
+            "main.st",
+            "PROGRAM P\n x := T#200000d;\n y := T#106751d;\nEND_PROGRAM",
+        ))
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("main.st:2:6"), "{}", errors[0]);
+        assert!(errors[0].contains("The duration does not fit into TIME."));
+    }
+
+    #[test]
+    fn test_rejects_dates_which_do_not_exist() {
+        let errors = parse(Lexer::create(
+            "main.st",
+            "PROGRAM P\n x := D#2023-02-29;\n y := TOD#24:00:00;\n z := D#2024-02-29;\nEND_PROGRAM",
+        ))
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("main.st:2:6"), "{}", errors[0]);
+        assert!(errors[1].contains("main.st:3:6"), "{}", errors[1]);
+        assert!(errors[1].contains("The date or time of day does not exist."));
     }
 }
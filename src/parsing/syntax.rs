@@ -0,0 +1,513 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use crate::parsing::{
+    ast::Ast,
+    lexer::Lexer,
+    parser::{SyntaxError, parse_located, parse_tokens},
+    token::{MarkedToken, Marker, Token},
+};
+
+mod builder;
+pub mod incremental;
+
+/// Kinds of the nodes and tokens of a syntax tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Tokens
+    Whitespace,
+    Comment,
+    /// A pragma in braces, like `{IF defined(SIM)}`
+    Pragma,
+    /// An attribute of a POU, like `{attribute 'test'}`
+    Attribute,
+    Keyword,
+    Identifier,
+    /// Numbers, strings, times, direct addresses, typed literal prefixes, TRUE and FALSE
+    Literal,
+    /// Operators and delimiters
+    Operator,
+    /// Text the language does not accept, like illegal characters and `#` pragmas
+    Error,
+
+    // Nodes
+    Root,
+    /// PROGRAM, FUNCTION, FUNCTION_BLOCK, METHOD or ACTION
+    Pou,
+    Namespace,
+    TypeBlock,
+    VarBlock,
+    /// STRUCT or UNION
+    Struct,
+    Configuration,
+    Resource,
+    If,
+    Case,
+    For,
+    While,
+    Repeat,
+    /// A declaration of a variable or type, up to its semicolon
+    Declaration,
+    /// A statement up to its semicolon, holding a control statement if it is one
+    Statement,
+}
+
+impl SyntaxKind {
+    /// Whether tokens of the kind carry no meaning for the program.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment | Self::Pragma)
+    }
+}
+
+/// A token of a green tree, without a position.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: Box<str>,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: &str) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A node of a green tree, which knows the length but not the position of its text.
+///
+/// Green nodes are immutable and may be shared by several trees, so an edit rebuilds only the
+/// nodes on the way from the changed token to the root.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            len: children.iter().map(GreenElement::len).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// Length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(out),
+                GreenElement::Token(token) => out.push_str(&token.text),
+            }
+        }
+    }
+}
+
+/// A node of a syntax tree, which is a green node at a position with a parent.
+///
+/// The tree is lossless: its text is the source it was built from, byte for byte, including
+/// whitespace, comments and pragmas.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Position of the text in the source of the root
+    offset: usize,
+}
+
+/// A token of a syntax tree.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Builds the syntax tree of a source.
+    ///
+    /// Every source has a tree, code that does not parse ends up in the nodes closest to what it
+    /// looks like.
+    pub fn parse(src: &str) -> Self {
+        Self::new_root(Rc::new(builder::build(src)))
+    }
+
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    /// Byte range of the text in the source of the root.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// The node and the nodes it is within, innermost first.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    /// Child nodes and tokens in the order of the source.
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let start = offset;
+                offset += child.len();
+                match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            offset: start,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        offset: start,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children().into_iter().filter_map(|c| match c {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// All tokens below the node in the order of the source.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The token covering a position of the source, the one starting there at a boundary.
+    pub fn token_at(&self, offset: usize) -> Option<SyntaxToken> {
+        let mut node = self.clone();
+        loop {
+            let child = node
+                .children()
+                .into_iter()
+                .find(|c| c.range().contains(&offset))?;
+            match child {
+                SyntaxElement::Node(child) => node = child,
+                SyntaxElement::Token(token) => return Some(token),
+            }
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.0.green.len);
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    /// The syntax tree of the language tokens of the node, which leaves out all trivia.
+    ///
+    /// Errors are those of [`parse`](crate::parsing::parser::parse) on the text of the node.
+    pub fn ast(&self, src_file: &str) -> Result<Ast, Vec<String>> {
+        let text = self.text();
+        let tokens = self.tokens();
+        let marker = Marker::create(src_file, &text);
+        parse_tokens(self.marked(&tokens, marker, &text).into_iter())
+    }
+
+    /// Like [`ast`](Self::ast), but only of the tokens left in `active`, the text of the node
+    /// after conditional compilation, with the spans of a file starting at a position and line
+    /// of a project.
+    pub fn ast_located(
+        &self,
+        src_file: &str,
+        origin: (usize, usize),
+        active: &str,
+    ) -> Result<Ast, Vec<SyntaxError>> {
+        let text = self.text();
+        let tokens = self.tokens();
+        let marker = Marker::create_at(src_file, &text, origin.0, origin.1);
+        parse_located(self.marked(&tokens, marker, active).into_iter())
+    }
+
+    /// The language tokens of the node with their positions, those `active` blanks out left out.
+    fn marked<'a>(
+        &self,
+        tokens: &'a [SyntaxToken],
+        mut marker: Marker<'a>,
+        active: &str,
+    ) -> Vec<MarkedToken<'a>> {
+        let (mut line, mut col) = (0, 0);
+        let mut stream = Vec::new();
+        for token in tokens {
+            let offset = token.offset - self.0.offset;
+            let kept = active.get(offset..offset + token.text().len()) == Some(token.text());
+            if !token.kind().is_trivia()
+                && kept
+                && let Some(lexed) = token.token()
+            {
+                marker.set(offset, line, col);
+                stream.push(lexed.mark(marker.clone()));
+            }
+            for c in token.text().chars() {
+                match c {
+                    '\n' => (line, col) = (line + 1, 0),
+                    _ => col += 1,
+                }
+            }
+        }
+        stream
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// The language token of the text, none for whitespace and comments.
+    pub fn token(&self) -> Option<Token<'_>> {
+        match self.kind() {
+            SyntaxKind::Whitespace | SyntaxKind::Comment => None,
+            _ => Lexer::create("", &self.green.text).next().map(|t| t.token),
+        }
+    }
+}
+
+impl SyntaxElement {
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.range(),
+            SyntaxElement::Token(token) => token.range(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    /// Writes the tree with a line per node and token, indented by depth.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write(node: &SyntaxNode, depth: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let Range { start, end } = node.range();
+            writeln!(f, "{:depth$}{:?}@{start}..{end}", "", node.kind())?;
+            for child in node.children() {
+                match child {
+                    SyntaxElement::Node(child) => write(&child, depth + 2, f)?,
+                    SyntaxElement::Token(token) => {
+                        writeln!(f, "{:w$}{token:?}", "", w = depth + 2)?
+                    }
+                }
+            }
+            Ok(())
+        }
+        write(self, 0, f)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Range { start, end } = self.range();
+        write!(f, "{:?}@{start}..{end} {:?}", self.kind(), self.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::parser;
+
+    const SRC: &str = "{attribute 'test'}\r\nprogram Main  (* counts *)\n\tVAR x : INT; END_VAR\n{IF defined(SIM)}\nIF x<10 THEN x:=x+1; // up\nELSE x := 0; END_IF;\n{END_IF}\nEND_PROGRAM\n";
+
+    #[test]
+    fn test_round_trips_sources() {
+        for src in [
+            SRC,
+            "",
+            "  \n",
+            "x $ (* open",
+            "END_IF PROGRAM P ELSE ;; END_VAR",
+        ] {
+            assert_eq!(SyntaxNode::parse(src).text(), src);
+        }
+        let tree = SyntaxNode::parse(SRC);
+        let token = tree.token_at(SRC.find("x+1").unwrap()).unwrap();
+        assert_eq!((token.kind(), token.text()), (SyntaxKind::Identifier, "x"));
+        let kinds = token
+            .parent()
+            .ancestors()
+            .map(|n| n.kind())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                SyntaxKind::Statement,
+                SyntaxKind::If,
+                SyntaxKind::Statement,
+                SyntaxKind::Pou,
+                SyntaxKind::Root
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structures_blocks_and_keeps_trivia() {
+        let tree = SyntaxNode::parse("PROGRAM P\nVAR a : INT; END_VAR\n// c\na := 1;\nEND_PROGRAM");
+        assert_eq!(
+            format!("{tree:?}"),
+            r#"Root@0..55
+  Pou@0..55
+    Keyword@0..7 "PROGRAM"
+    Whitespace@7..8 " "
+    Identifier@8..9 "P"
+    Whitespace@9..10 "\n"
+    VarBlock@10..30
+      Keyword@10..13 "VAR"
+      Whitespace@13..14 " "
+      Declaration@14..22
+        Identifier@14..15 "a"
+        Whitespace@15..16 " "
+        Operator@16..17 ":"
+        Whitespace@17..18 " "
+        Identifier@18..21 "INT"
+        Operator@21..22 ";"
+      Whitespace@22..23 " "
+      Keyword@23..30 "END_VAR"
+    Whitespace@30..31 "\n"
+    Comment@31..35 "// c"
+    Whitespace@35..36 "\n"
+    Statement@36..43
+      Identifier@36..37 "a"
+      Whitespace@37..38 " "
+      Operator@38..40 ":="
+      Whitespace@40..41 " "
+      Literal@41..42 "1"
+      Operator@42..43 ";"
+    Whitespace@43..44 "\n"
+    Keyword@44..55 "END_PROGRAM"
+"#
+        );
+    }
+
+    #[test]
+    fn test_derives_the_ast() {
+        let src = SRC.replace("{IF defined(SIM)}", "").replace("{END_IF}", "");
+        let tree = SyntaxNode::parse(&src);
+        let parsed = parser::parse(Lexer::create("main.st", &src)).unwrap();
+        assert_eq!(
+            format!("{:?}", tree.ast("main.st").unwrap()),
+            format!("{parsed:?}")
+        );
+
+        let broken = "PROGRAM P\n  x := ;\nEND_PROGRAM";
+        assert_eq!(
+            SyntaxNode::parse(broken).ast("b.st").unwrap_err(),
+            parser::parse(Lexer::create("b.st", broken)).unwrap_err()
+        );
+    }
+}
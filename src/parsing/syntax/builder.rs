@@ -0,0 +1,471 @@
+use std::rc::Rc;
+
+use crate::parsing::{
+    lexer::Lexer,
+    syntax::{GreenElement, GreenNode, GreenToken, SyntaxKind},
+    token::Token,
+};
+
+/// A token of the source with the whitespace before it split off.
+pub struct Leaf<'a> {
+    kind: SyntaxKind,
+    text: &'a str,
+    token: Token<'a>,
+}
+
+impl<'a> Leaf<'a> {
+    /// A leaf of a token lexed before, like one of an existing tree.
+    pub fn lexed(kind: SyntaxKind, text: &'a str) -> Self {
+        let token = match kind.is_trivia() {
+            true => Token::Illegal,
+            false => Lexer::create("", text)
+                .next()
+                .map_or(Token::Illegal, |t| t.token),
+        };
+        Self { kind, text, token }
+    }
+}
+
+struct Builder<'a> {
+    leaves: Vec<Leaf<'a>>,
+    pos: usize,
+    /// Nodes being built, innermost last
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+}
+
+/// Builds the green tree of a source.
+///
+/// The structure follows the blocks of the source: POUs, variable and type blocks and control
+/// statements hold their parts, declarations and statements their tokens up to the semicolon.
+/// Trivia belongs to the innermost node open where it is found, so trivia after a semicolon
+/// belongs to the enclosing block.
+pub fn build(src: &str) -> GreenNode {
+    build_leaves(leaves_until(src, |_| false))
+}
+
+pub fn build_leaves(leaves: Vec<Leaf>) -> GreenNode {
+    let mut builder = Builder {
+        leaves,
+        pos: 0,
+        stack: vec![(SyntaxKind::Root, Vec::new())],
+    };
+    while builder.peek().is_some() {
+        builder.item();
+    }
+    builder.trivia();
+    let (kind, children) = builder
+        .stack
+        .pop()
+        .unwrap_or((SyntaxKind::Root, Vec::new()));
+    GreenNode::new(kind, children)
+}
+
+/// Builds the node of a kind from leaves within a parent of a kind, as the tree of a whole source
+/// would have it.
+///
+/// There is none unless the leaves make up exactly one node of the kind which ends on its own,
+/// with the semicolon of a statement or the END keyword of a block, so that it does not depend on
+/// what follows it.
+pub fn rebuild(leaves: Vec<Leaf>, parent: SyntaxKind, kind: SyntaxKind) -> Option<GreenNode> {
+    let mut builder = Builder {
+        leaves,
+        pos: 0,
+        stack: vec![(parent, Vec::new())],
+    };
+    match kind {
+        SyntaxKind::If
+        | SyntaxKind::Case
+        | SyntaxKind::For
+        | SyntaxKind::While
+        | SyntaxKind::Repeat
+        | SyntaxKind::Struct => builder.block(kind),
+        _ => builder.item(),
+    }
+    if builder.pos < builder.leaves.len() {
+        return None;
+    }
+    let (_, mut children) = builder.stack.pop()?;
+    let Some(GreenElement::Node(node)) = children.pop() else {
+        return None;
+    };
+    let last = node.children().last();
+    let ended = match last {
+        Some(GreenElement::Token(token)) => match kind {
+            SyntaxKind::Statement | SyntaxKind::Declaration => token.text() == ";",
+            _ => token.kind() == SyntaxKind::Keyword && token.text().starts_with("END_"),
+        },
+        _ => false,
+    };
+    match children.is_empty() && node.kind() == kind && ended {
+        true => Rc::into_inner(node),
+        false => None,
+    }
+}
+
+/// The leaves of a source, up to the first one starting at an offset where `stop` tells to.
+pub fn leaves_until(src: &str, mut stop: impl FnMut(usize) -> bool) -> Vec<Leaf<'_>> {
+    let mut leaves = Vec::new();
+    let mut end = 0;
+    let mut tokens = Lexer::create("", src).peekable();
+    while let Some(marked) = tokens.next() {
+        let start = marked.span().pos;
+        if start > end && stop(end) {
+            return leaves;
+        }
+        if start > end {
+            leaves.push(Leaf {
+                kind: SyntaxKind::Whitespace,
+                text: &src[end..start],
+                token: Token::Illegal,
+            });
+        }
+        if stop(start) {
+            return leaves;
+        }
+        end = match marked.token {
+            Token::Illegal => start + src[start..].chars().next().map_or(1, char::len_utf8),
+            ref token => start + token.text_len(),
+        };
+        let kind = match &marked.token {
+            // Braces on one line hold a pragma, like a conditional compilation directive
+            Token::LeftBrace => match src[start..].find('}') {
+                Some(i) if !src[start..start + i].contains('\n') => {
+                    end = start + i + 1;
+                    // Tokens within may reach past the brace, like a quote opening a string
+                    tokens = Lexer::create_at("", &src[end..], end, 0).peekable();
+                    SyntaxKind::Pragma
+                }
+                _ => SyntaxKind::Operator,
+            },
+            Token::Comment(_) => SyntaxKind::Comment,
+            Token::Attribute(_) => SyntaxKind::Attribute,
+            Token::Identifier(_) => SyntaxKind::Identifier,
+            Token::Number(..)
+            | Token::String(_)
+            | Token::Time(..)
+            | Token::Date(..)
+            | Token::DirectAddress(_)
+            | Token::LiteralPrefix(_)
+            | Token::True
+            | Token::False => SyntaxKind::Literal,
+            Token::Illegal | Token::Pragma(_) => SyntaxKind::Error,
+            token if token.keyword_text().is_some() => SyntaxKind::Keyword,
+            _ => SyntaxKind::Operator,
+        };
+        leaves.push(Leaf {
+            kind,
+            text: &src[start..end],
+            token: marked.token,
+        });
+    }
+    if end < src.len() && !stop(end) {
+        leaves.push(Leaf {
+            kind: SyntaxKind::Whitespace,
+            text: &src[end..],
+            token: Token::Illegal,
+        });
+    }
+    leaves
+}
+
+/// The END keyword closing a block opened by a keyword.
+fn end_of(opener: &Token) -> Option<Token<'static>> {
+    let end = match opener {
+        Token::Program => Token::EndProgram,
+        Token::Function => Token::EndFunction,
+        Token::FunctionBlock => Token::EndFunctionBlock,
+        Token::Method => Token::EndMethod,
+        Token::Action => Token::EndAction,
+        Token::Namespace => Token::EndNamespace,
+        Token::Configuration => Token::EndConfiguration,
+        Token::Resource => Token::EndResource,
+        Token::Var
+        | Token::VarInput
+        | Token::VarOutput
+        | Token::VarInOut
+        | Token::VarTemp
+        | Token::VarGlobal
+        | Token::VarExternal => Token::EndVar,
+        Token::Type => Token::EndType,
+        Token::Struct => Token::EndStruct,
+        Token::Union => Token::EndUnion,
+        Token::If => Token::EndIf,
+        Token::Case => Token::EndCase,
+        Token::For => Token::EndFor,
+        Token::While => Token::EndWhile,
+        Token::Repeat => Token::EndRepeat,
+        _ => return None,
+    };
+    Some(end)
+}
+
+fn is_end(token: &Token) -> bool {
+    token.keyword_text().is_some_and(|k| k.starts_with("END_"))
+}
+
+impl<'a> Builder<'a> {
+    /// The next language token, after any trivia.
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.leaves[self.pos..]
+            .iter()
+            .find(|l| !l.kind.is_trivia())
+            .map(|l| &l.token)
+    }
+
+    fn peek_is(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    fn kind(&self) -> SyntaxKind {
+        self.stack
+            .last()
+            .map_or(SyntaxKind::Root, |(kind, _)| *kind)
+    }
+
+    fn push(&mut self, element: GreenElement) {
+        if let Some((_, children)) = self.stack.last_mut() {
+            children.push(element);
+        }
+    }
+
+    /// Adds the trivia before the next language token to the current node.
+    fn trivia(&mut self) {
+        while let Some(leaf) = self.leaves.get(self.pos).filter(|l| l.kind.is_trivia()) {
+            let token = GreenToken::new(leaf.kind, leaf.text);
+            self.push(GreenElement::Token(Rc::new(token)));
+            self.pos += 1;
+        }
+    }
+
+    /// Adds the next language token and the trivia before it to the current node.
+    fn bump(&mut self) {
+        self.trivia();
+        if let Some(leaf) = self.leaves.get(self.pos) {
+            let token = GreenToken::new(leaf.kind, leaf.text);
+            self.push(GreenElement::Token(Rc::new(token)));
+            self.pos += 1;
+        }
+    }
+
+    fn start(&mut self, kind: SyntaxKind) {
+        self.trivia();
+        self.stack.push((kind, Vec::new()));
+    }
+
+    fn finish(&mut self) {
+        if let Some((kind, children)) = self.stack.pop() {
+            self.push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+        }
+    }
+
+    /// A block, a declaration or a statement.
+    fn item(&mut self) {
+        let Some(token) = self.peek() else {
+            return;
+        };
+        let in_configuration = matches!(
+            self.kind(),
+            SyntaxKind::Configuration | SyntaxKind::Resource
+        );
+        match token {
+            // Within a configuration PROGRAM declares an instance
+            Token::Program if in_configuration => self.statement(SyntaxKind::Statement),
+            Token::Program
+            | Token::Function
+            | Token::FunctionBlock
+            | Token::Method
+            | Token::Action => self.block(SyntaxKind::Pou),
+            Token::Namespace => self.block(SyntaxKind::Namespace),
+            Token::Configuration => self.block(SyntaxKind::Configuration),
+            Token::Resource => self.block(SyntaxKind::Resource),
+            Token::Type => self.block(SyntaxKind::TypeBlock),
+            Token::Var
+            | Token::VarInput
+            | Token::VarOutput
+            | Token::VarInOut
+            | Token::VarTemp
+            | Token::VarGlobal
+            | Token::VarExternal => self.block(SyntaxKind::VarBlock),
+            Token::Attribute(_) => self.bump(),
+            _ => match self.kind() {
+                SyntaxKind::VarBlock | SyntaxKind::TypeBlock | SyntaxKind::Struct => {
+                    self.statement(SyntaxKind::Declaration)
+                }
+                _ => self.statement(SyntaxKind::Statement),
+            },
+        }
+    }
+
+    /// A block from its opening keyword to its END keyword.
+    fn block(&mut self, kind: SyntaxKind) {
+        self.start(kind);
+        let Some(opener) = self.peek().cloned() else {
+            return self.finish();
+        };
+        let end = end_of(&opener);
+        self.bump();
+        match kind {
+            SyntaxKind::Pou | SyntaxKind::Namespace | SyntaxKind::Configuration => {
+                self.name();
+                if self.peek_is(&Token::Colon) {
+                    self.bump();
+                    if opener != Token::Action {
+                        self.data_type();
+                    }
+                }
+            }
+            SyntaxKind::Resource => {
+                self.name();
+                if self.peek_is(&Token::On) {
+                    self.bump();
+                    self.name();
+                }
+            }
+            SyntaxKind::VarBlock => {
+                while matches!(
+                    self.peek(),
+                    Some(Token::Constant | Token::Retain | Token::Persistent)
+                ) {
+                    self.bump();
+                }
+            }
+            SyntaxKind::If => self.until(&Token::Then),
+            SyntaxKind::Case => self.until(&Token::Of),
+            SyntaxKind::For | SyntaxKind::While => self.until(&Token::Do),
+            _ => (),
+        }
+
+        while let Some(token) = self.peek() {
+            match token {
+                token if is_end(token) => {
+                    if Some(token) == end.as_ref() {
+                        self.bump();
+                    }
+                    break;
+                }
+                Token::Elsif if kind == SyntaxKind::If => {
+                    self.bump();
+                    self.until(&Token::Then);
+                }
+                Token::Else if matches!(kind, SyntaxKind::If | SyntaxKind::Case) => self.bump(),
+                Token::Until if kind == SyntaxKind::Repeat => {
+                    self.bump();
+                    while self.peek().is_some_and(|t| !is_end(t)) {
+                        self.bump();
+                    }
+                }
+                _ => self.item(),
+            }
+        }
+        self.finish();
+    }
+
+    /// Tokens up to and including the keyword ending the head of a control statement.
+    fn until(&mut self, keyword: &Token) {
+        while let Some(token) = self.peek() {
+            if is_end(token) {
+                return;
+            }
+            let found = token == keyword;
+            self.bump();
+            if found {
+                return;
+            }
+        }
+    }
+
+    /// A declaration or statement up to its semicolon, or up to a keyword that cannot be part of it.
+    fn statement(&mut self, kind: SyntaxKind) {
+        self.start(kind);
+        let mut first = true;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::SemiColon => {
+                    self.bump();
+                    break;
+                }
+                Token::If => self.block(SyntaxKind::If),
+                Token::Case => self.block(SyntaxKind::Case),
+                Token::For => self.block(SyntaxKind::For),
+                Token::While => self.block(SyntaxKind::While),
+                Token::Repeat => self.block(SyntaxKind::Repeat),
+                Token::Struct | Token::Union => self.block(SyntaxKind::Struct),
+                token
+                    if !first
+                        && (is_end(token)
+                            || matches!(token, Token::Else | Token::Elsif | Token::Until)
+                            || end_of(token).is_some()
+                            || matches!(token, Token::Attribute(_))) =>
+                {
+                    break;
+                }
+                _ => self.bump(),
+            }
+            first = false;
+        }
+        self.finish();
+    }
+
+    /// A name, qualified or not.
+    fn name(&mut self) {
+        if !matches!(self.peek(), Some(Token::Identifier(_))) {
+            return;
+        }
+        self.bump();
+        while self.peek_is(&Token::Dot) {
+            self.bump();
+            if matches!(self.peek(), Some(Token::Identifier(_))) {
+                self.bump();
+            }
+        }
+    }
+
+    /// A data type like `INT`, `STRING[20]` or `ARRAY[0..9] OF POINTER TO Point`.
+    fn data_type(&mut self) {
+        match self.peek() {
+            Some(Token::Array) => {
+                self.bump();
+                if self.peek_is(&Token::LeftBracket) {
+                    self.group();
+                }
+                if self.peek_is(&Token::Of) {
+                    self.bump();
+                    self.data_type();
+                }
+            }
+            Some(Token::Pointer | Token::Reference) => {
+                self.bump();
+                if self.peek_is(&Token::To) {
+                    self.bump();
+                    self.data_type();
+                }
+            }
+            Some(Token::Identifier(_)) => {
+                self.name();
+                if matches!(
+                    self.peek(),
+                    Some(Token::LeftBracket | Token::LeftParenthesis)
+                ) {
+                    self.group();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Tokens from an opening bracket or parenthesis to the one closing it.
+    fn group(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::LeftBracket | Token::LeftParenthesis => depth += 1,
+                Token::RightBracket | Token::RightParenthesis => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            self.bump();
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::{ops::Range, rc::Rc};
+
+use crate::parsing::syntax::{
+    GreenElement, GreenNode, SyntaxNode,
+    builder::{self, Leaf},
+};
+
+/// Bytes after a word a token may have looked at to find its end, like `e-` after a number.
+const LOOKAHEAD: usize = 3;
+
+/// A change of a source: the bytes of a range replaced by a text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn apply(&self, src: &str) -> String {
+        let mut edited = String::with_capacity(src.len() + self.text.len() - self.range.len());
+        edited.push_str(&src[..self.range.start]);
+        edited.push_str(&self.text);
+        edited.push_str(&src[self.range.end..]);
+        edited
+    }
+
+    /// Whether the edit may close a string, comment or pragma left open before it, which changes
+    /// tokens far before the edit.
+    fn closes(&self, edited: &str) -> bool {
+        let joined = edited[..self.range.start].ends_with('*')
+            && edited[self.range.start + self.text.len()..].starts_with(')');
+        joined || self.text.contains(['\'', '"', '*', ')', '}'])
+    }
+}
+
+impl SyntaxNode {
+    /// The tree of the source after an edit, sharing every node the edit leaves alone with this
+    /// tree, which must be a root.
+    ///
+    /// Only the tokens from shortly before the edit up to the first token boundary after it are
+    /// lexed again. Then the innermost block, declaration or statement around them is built
+    /// again, as long as it still ends on its own END keyword or semicolon, or else the next one
+    /// around it, up to the whole source. The result is the tree [`parse`](Self::parse) builds
+    /// of the edited source.
+    pub fn reparse(&self, edit: &TextEdit) -> SyntaxNode {
+        let old = self.text();
+        let src = edit.apply(&old);
+        if edit.closes(&src) {
+            let leaves = builder::leaves_until(&src, |_| false);
+            return SyntaxNode::new_root(Rc::new(builder::build_leaves(leaves)));
+        }
+
+        let mut node = self.clone();
+        loop {
+            let inner = node.child_nodes().find(|c| {
+                let range = c.range();
+                range.start < edit.range.start && edit.range.end < range.end
+            });
+            match inner {
+                Some(inner) => node = inner,
+                None => break,
+            }
+        }
+        // Tokens may look through a whole word for its end, like `16#` for its digits
+        let word = src[..edit.range.start].trim_end_matches(|c: char| {
+            c.is_ascii_alphanumeric() || matches!(c, '_' | '#' | '.' | '%')
+        });
+        let from = word.len().saturating_sub(LOOKAHEAD);
+        // Pragmas in braces end on their line, so joining lines may close one opened before
+        let from = match old[edit.range.clone()].contains('\n') {
+            true => from.min(word.rfind('\n').map_or(0, |i| i + 1)),
+            false => from,
+        };
+        for node in node.ancestors() {
+            if let Some(green) = rebuild(&node, &src, edit, from) {
+                return splice(&node, green);
+            }
+        }
+        unreachable!("The root is always built again")
+    }
+}
+
+/// The node after an edit, from the tokens of the node before `from`, the tokens lexed again
+/// and the tokens after the edit.
+fn rebuild(node: &SyntaxNode, src: &str, edit: &TextEdit, from: usize) -> Option<GreenNode> {
+    let parent = node.parent();
+    let tokens = node.tokens();
+    let range = node.range();
+    let first = tokens
+        .iter()
+        .position(|t| t.range().end >= from)
+        .unwrap_or(tokens.len());
+    // The first token decides what the node is, so it must stay as it was
+    if parent.is_some() && first == 0 {
+        return None;
+    }
+    let start = tokens.get(first).map_or(range.start, |t| t.range().start);
+
+    let inserted_end = edit.range.start + edit.text.len();
+    let old = |new: usize| new + edit.range.len() - edit.text.len();
+    let mut next = first;
+    let mut synced = None;
+    let lexed = builder::leaves_until(&src[start..], |offset| {
+        let new = start + offset;
+        if new < inserted_end {
+            return false;
+        }
+        let old = old(new);
+        while tokens.get(next).is_some_and(|t| t.range().start < old) {
+            next += 1;
+        }
+        if tokens.get(next).is_some_and(|t| t.range().start == old) || old == range.end {
+            synced = Some(next);
+        }
+        old >= range.end || synced.is_some()
+    });
+    let last = match (synced, &parent) {
+        (Some(last), _) => last,
+        (None, None) => tokens.len(),
+        (None, Some(_)) => return None,
+    };
+
+    let leaves = tokens[..first]
+        .iter()
+        .map(|t| Leaf::lexed(t.kind(), t.text()))
+        .chain(lexed)
+        .chain(
+            tokens[last..]
+                .iter()
+                .map(|t| Leaf::lexed(t.kind(), t.text())),
+        )
+        .collect();
+    match parent {
+        Some(parent) => builder::rebuild(leaves, parent.kind(), node.kind()),
+        None => Some(builder::build_leaves(leaves)),
+    }
+}
+
+/// The root of a tree with a node replaced, rebuilding the nodes from it to the root.
+fn splice(node: &SyntaxNode, green: GreenNode) -> SyntaxNode {
+    let mut green = Rc::new(green);
+    let mut node = node.clone();
+    while let Some(parent) = node.parent() {
+        let mut children = parent.green().children().to_vec();
+        let mut offset = parent.range().start;
+        for child in &mut children {
+            if offset == node.range().start
+                && let GreenElement::Node(old) = child
+                && Rc::ptr_eq(old, node.green())
+            {
+                *child = GreenElement::Node(green);
+                break;
+            }
+            offset += child.len();
+        }
+        green = Rc::new(GreenNode::new(parent.kind(), children));
+        node = parent;
+    }
+    SyntaxNode::new_root(green)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "TYPE Mode : (Off, Run); END_TYPE
+FUNCTION_BLOCK Motor
+    VAR_INPUT speed : INT; END_VAR
+    IF speed > 10 THEN speed := 10; (* limit *) END_IF;
+END_FUNCTION_BLOCK
+PROGRAM Main
+    VAR m : Motor; s : STRING := 'x'; END_VAR
+    {IF defined(SIM)}
+    m(speed := 1..2);
+    CASE s OF 1: m(); ELSE s := 16#FF; END_CASE;
+END_PROGRAM
+";
+
+    #[test]
+    fn test_reparses_like_a_full_parse() {
+        let tree = SyntaxNode::parse(SRC);
+        let texts = [
+            "", "x", "\n", ";", "END_IF", "(*", "'", "}", "{", "..", "#", "e", "VAR",
+        ];
+        for start in (0..=SRC.len()).step_by(2) {
+            for (i, text) in texts.iter().enumerate() {
+                let end = (start + i % 4).min(SRC.len());
+                let edit = TextEdit {
+                    range: start..end,
+                    text: text.to_string(),
+                };
+                let edited = edit.apply(SRC);
+                let reparsed = tree.reparse(&edit);
+                assert_eq!(
+                    reparsed.green(),
+                    SyntaxNode::parse(&edited).green(),
+                    "{edit:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reuses_unchanged_subtrees() {
+        let tree = SyntaxNode::parse(SRC);
+        let at = SRC.find(":= 10;").unwrap() + 3;
+        let edit = TextEdit {
+            range: at..at + 2,
+            text: "100".to_string(),
+        };
+        let reparsed = tree.reparse(&edit);
+        assert_eq!(reparsed.text(), edit.apply(SRC));
+
+        let pous = |tree: &SyntaxNode| tree.child_nodes().collect::<Vec<_>>();
+        let (before, after) = (pous(&tree), pous(&reparsed));
+        assert!(Rc::ptr_eq(before[0].green(), after[0].green()));
+        assert!(!Rc::ptr_eq(before[1].green(), after[1].green()));
+        assert!(Rc::ptr_eq(before[2].green(), after[2].green()));
+        // Within the function block only the IF statement is new
+        let parts = |pou: &SyntaxNode| pou.child_nodes().collect::<Vec<_>>();
+        assert!(Rc::ptr_eq(
+            parts(&before[1])[0].green(),
+            parts(&after[1])[0].green()
+        ));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    formats::json::Json,
+    parsing::token::{DATE_PREFIXES, DateKind, KEYWORDS, TIME_PREFIXES, TIME_UNITS, Token},
+    semantic::symbols::ELEMENTARY_TYPES,
+};
+
+/// TextMate scope of a keyword.
+fn scope_of(keyword: &Token) -> &'static str {
+    match keyword {
+        Token::True | Token::False => "constant.language.boolean.st",
+        Token::Not | Token::And | Token::Or | Token::Xor | Token::Mod => "keyword.operator.word.st",
+        Token::If
+        | Token::Then
+        | Token::Elsif
+        | Token::Else
+        | Token::EndIf
+        | Token::Case
+        | Token::Of
+        | Token::EndCase
+        | Token::For
+        | Token::To
+        | Token::By
+        | Token::Do
+        | Token::EndFor
+        | Token::While
+        | Token::EndWhile
+        | Token::Repeat
+        | Token::Until
+        | Token::EndRepeat
+        | Token::Continue
+        | Token::Exit
+        | Token::Return => "keyword.control.st",
+        Token::Constant | Token::Retain | Token::Persistent | Token::At => "storage.modifier.st",
+        Token::Struct
+        | Token::EndStruct
+        | Token::Union
+        | Token::EndUnion
+        | Token::Array
+        | Token::Pointer
+        | Token::Reference => "storage.type.st",
+        _ => "keyword.other.st",
+    }
+}
+
+/// A pattern matching any of the words case-insensitively, as the lexer does.
+fn words(scope: &str, words: &[&str]) -> Json {
+    let pattern = format!("(?i)\\b(?:{})\\b", words.join("|"));
+    Json::object([("name", scope.into()), ("match", pattern.into())])
+}
+
+fn pattern(scope: &str, pattern: &str) -> Json {
+    Json::object([("name", scope.into()), ("match", pattern.into())])
+}
+
+/// A pattern matching the duration literals the lexer reads, like `T#1h30m` or `LTIME#250us`.
+fn time_pattern() -> String {
+    // Longer units first, so that `ms` is not taken for minutes
+    let mut units = TIME_UNITS.to_vec();
+    units.sort_by_key(|u| std::cmp::Reverse(u.len()));
+    format!(
+        "(?i)\\b(?:{})(?:\\d+(?:{}))+\\b",
+        TIME_PREFIXES.join("|"),
+        units.join("|")
+    )
+}
+
+/// A pattern matching the date and time of day literals the lexer reads, like `D#2024-01-15`.
+fn date_pattern() -> String {
+    let date = "\\d+-\\d+-\\d+";
+    let time = "\\d+:\\d+:\\d+(?:\\.\\d+)?";
+    let literals = DATE_PREFIXES
+        .iter()
+        .map(|(prefix, kind)| match kind {
+            DateKind::Date => format!("{prefix}{date}"),
+            DateKind::TimeOfDay => format!("{prefix}{time}"),
+            DateKind::DateAndTime => format!("{prefix}{date}-{time}"),
+        })
+        .collect::<Vec<_>>();
+    format!("(?i)\\b(?:{})", literals.join("|"))
+}
+
+/// A TextMate grammar of Structured Text, for editors to highlight sources the way the lexer
+/// reads them.
+///
+/// The keywords come from the keyword table of the lexer, grouped by their role, so the grammar
+/// stays in step with the language as keywords are added.
+pub fn grammar() -> Json {
+    let mut patterns = vec![
+        pattern("comment.line.double-slash.st", "//.*$"),
+        Json::object([
+            ("name", "comment.block.st".into()),
+            ("begin", "\\(\\*".into()),
+            ("end", "\\*\\)".into()),
+        ]),
+        pattern("meta.attribute.st", "(?i)\\{attribute\\s[^}]*\\}"),
+        pattern("meta.preprocessor.st", "\\{[^}\\n]*\\}"),
+        pattern("string.quoted.single.st", "'[^']*'"),
+        pattern("string.quoted.double.st", "\"[^\"]*\""),
+        pattern("constant.numeric.time.st", &time_pattern()),
+        pattern("constant.numeric.date.st", &date_pattern()),
+        pattern("constant.numeric.st", "\\b(?:2|8|16)#[0-9A-Fa-f_]+"),
+        pattern("support.type.st", "\\b[A-Za-z_][A-Za-z0-9_]*#"),
+        pattern(
+            "constant.numeric.st",
+            "\\b\\d+(?:\\.\\d+)?(?:[eE][+-]?\\d+)?\\b",
+        ),
+        pattern("variable.other.address.st", "%[IQM][A-Za-z0-9.*%]*"),
+        pattern("meta.preprocessor.st", "#\\S*"),
+    ];
+
+    let mut scopes = Vec::<(&str, Vec<&str>)>::new();
+    for (keyword, token) in KEYWORDS {
+        let scope = scope_of(token);
+        match scopes.iter_mut().find(|(s, _)| *s == scope) {
+            Some((_, keywords)) => keywords.push(keyword),
+            None => scopes.push((scope, vec![keyword])),
+        }
+    }
+    patterns.extend(
+        scopes
+            .iter()
+            .map(|(scope, keywords)| words(scope, keywords)),
+    );
+    patterns.push(words("support.type.primitive.st", ELEMENTARY_TYPES));
+    patterns.push(pattern(
+        "keyword.operator.st",
+        ":=|=>|<>|>=|<=|\\*\\*|[-+*/^&=<>]",
+    ));
+
+    Json::object([
+        ("name", "Structured Text".into()),
+        ("scopeName", "source.st".into()),
+        (
+            "fileTypes",
+            Json::Array(vec!["st".into(), "typ".into(), "var".into()]),
+        ),
+        ("patterns", Json::Array(patterns)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::lexer::Lexer;
+
+    /// An element of the few regular expressions the tests match, with its quantifier.
+    enum Node {
+        Char(char),
+        Digit,
+        Boundary,
+        Group(Vec<Vec<(Node, Option<char>)>>),
+    }
+
+    /// Parses alternatives up to the end of a group.
+    fn parse(pattern: &mut std::iter::Peekable<std::str::Chars>) -> Vec<Vec<(Node, Option<char>)>> {
+        let mut alternatives = vec![Vec::new()];
+        while let Some(c) = pattern.next() {
+            let node = match c {
+                ')' => break,
+                '|' => {
+                    alternatives.push(Vec::new());
+                    continue;
+                }
+                '(' => {
+                    assert_eq!((pattern.next(), pattern.next()), (Some('?'), Some(':')));
+                    Node::Group(parse(pattern))
+                }
+                '\\' => match pattern.next() {
+                    Some('d') => Node::Digit,
+                    Some('b') => Node::Boundary,
+                    c => Node::Char(c.unwrap()),
+                },
+                c => Node::Char(c),
+            };
+            let quantifier = pattern.next_if(|c| "+*?".contains(*c));
+            alternatives.last_mut().unwrap().push((node, quantifier));
+        }
+        alternatives
+    }
+
+    fn one(node: &Node, text: &[char], at: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        let word = |i: usize| {
+            text.get(i)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+        };
+        match node {
+            Node::Char(c) => text.get(at).is_some_and(|t| t.eq_ignore_ascii_case(c)) && k(at + 1),
+            Node::Digit => text.get(at).is_some_and(char::is_ascii_digit) && k(at + 1),
+            Node::Boundary => (at > 0 && word(at - 1)) != word(at) && k(at),
+            Node::Group(alternatives) => alternatives.iter().any(|a| sequence(a, text, at, k)),
+        }
+    }
+
+    fn sequence(
+        items: &[(Node, Option<char>)],
+        text: &[char],
+        at: usize,
+        k: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        let Some(((node, quantifier), rest)) = items.split_first() else {
+            return k(at);
+        };
+        let mut next = |n| sequence(rest, text, n, k);
+        match quantifier {
+            None => one(node, text, at, &mut next),
+            Some('?') => one(node, text, at, &mut next) || next(at),
+            Some(q) => {
+                let mut again = |n| n > at && sequence(&items[..1], text, n, &mut next);
+                let repeated = match q {
+                    '+' => one(node, text, at, &mut again),
+                    _ => one(node, text, at, &mut again) || next(at),
+                };
+                repeated || *q == '+' && one(node, text, at, &mut next)
+            }
+        }
+    }
+
+    /// Whether a regular expression of the grammar matches all of the text.
+    fn matches(pattern: &str, text: &str) -> bool {
+        let nodes = parse(&mut pattern.trim_start_matches("(?i)").chars().peekable());
+        let text = text.chars().collect::<Vec<_>>();
+        nodes
+            .iter()
+            .any(|a| sequence(a, &text, 0, &mut |end| end == text.len()))
+    }
+
+    #[test]
+    fn test_grammar_covers_every_keyword() {
+        let grammar = Json::parse(&grammar().to_string()).unwrap();
+        assert_eq!(
+            grammar.get("scopeName").and_then(Json::as_str),
+            Some("source.st")
+        );
+        let patterns = grammar.get("patterns").and_then(Json::as_array).unwrap();
+        let alternatives = patterns
+            .iter()
+            .filter_map(|p| p.get("match")?.as_str()?.strip_prefix("(?i)\\b(?:"))
+            .flat_map(|m| m.trim_end_matches(")\\b").split('|'))
+            .collect::<Vec<_>>();
+        for (keyword, _) in KEYWORDS {
+            let count = alternatives.iter().filter(|a| *a == keyword).count();
+            assert_eq!(count, 1, "{keyword}");
+        }
+        assert!(alternatives.contains(&"LREAL"));
+
+        let control = patterns
+            .iter()
+            .find(|p| p.get("name").and_then(Json::as_str) == Some("keyword.control.st"))
+            .and_then(|p| p.get("match")?.as_str());
+        assert!(
+            control.is_some_and(|m| m.contains("|END_IF|")),
+            "{control:?}"
+        );
+    }
+
+    #[test]
+    fn test_time_pattern_matches_every_time_literal() {
+        let pattern = time_pattern();
+        for prefix in TIME_PREFIXES
+            .iter()
+            .flat_map(|p| [p.to_string(), p.to_lowercase()])
+        {
+            for units in 1..1u32 << TIME_UNITS.len() {
+                let literal = TIME_UNITS
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| units & 1 << i != 0)
+                    .fold(prefix.clone(), |l, (i, unit)| format!("{l}{}{unit}", i * 7));
+                let tokens = Lexer::create("test.st", &literal)
+                    .map(|t| t.token)
+                    .collect::<Vec<_>>();
+                assert!(
+                    matches!(tokens[..], [Token::Time(text, _)] if text == literal),
+                    "{literal}: {tokens:?}"
+                );
+                assert!(
+                    matches(&pattern, &literal),
+                    "{literal} does not match {pattern}"
+                );
+            }
+        }
+        for rejected in ["T#1.5s", "T#5", "T#s", "TIMES#1s"] {
+            assert!(!matches(&pattern, rejected), "{rejected}");
+        }
+    }
+
+    #[test]
+    fn test_date_pattern_matches_every_date_literal() {
+        let pattern = date_pattern();
+        for (prefix, kind) in DATE_PREFIXES {
+            let value = match kind {
+                DateKind::Date => "2024-1-15",
+                DateKind::TimeOfDay => "7:05:00.25",
+                DateKind::DateAndTime => "2024-02-29-23:59:59",
+            };
+            for literal in [
+                format!("{prefix}{value}"),
+                format!("{prefix}{value}").to_lowercase(),
+            ] {
+                let tokens = Lexer::create("test.st", &literal)
+                    .map(|t| t.token)
+                    .collect::<Vec<_>>();
+                assert!(
+                    matches!(tokens[..], [Token::Date(text, _)] if text == literal),
+                    "{literal}: {tokens:?}"
+                );
+                assert!(
+                    matches(&pattern, &literal),
+                    "{literal} does not match {pattern}"
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, PartialEq)]
+use std::fmt;
+
+use crate::parsing::ast::Span;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token<'a> {
     Illegal,
     Comment(&'a str),
@@ -8,6 +12,9 @@ pub enum Token<'a> {
     Number(&'a str, NumberValue),
     String(&'a str),
     Time(&'a str, TimeValue),
+    Date(&'a str, DateValue),
+    DirectAddress(&'a str),
+    LiteralPrefix(&'a str),
 
     // Operators
     Plus,
@@ -15,7 +22,11 @@ pub enum Token<'a> {
     Asterisk,
     Slash,
     Percent,
+    Power,
+    Caret,
+    Ampersand,
     Assign,
+    Arrow,
 
     Equals,
     NotEquals,
@@ -26,6 +37,7 @@ pub enum Token<'a> {
 
     // Delimiters
     Dot,
+    Range,
     Comma,
     Colon,
     SemiColon,
@@ -45,6 +57,7 @@ pub enum Token<'a> {
     And,
     Or,
     Xor,
+    Mod,
 
     // Control flow
     If,
@@ -62,6 +75,10 @@ pub enum Token<'a> {
     EndFor,
     While,
     EndWhile,
+    Repeat,
+    Until,
+    EndRepeat,
+    Continue,
 
     // Program / Action / Function / Function block
     Program,
@@ -73,13 +90,33 @@ pub enum Token<'a> {
     EndFunction,
     FunctionBlock,
     EndFunctionBlock,
+    Method,
+    EndMethod,
     Return,
+    Namespace,
+    EndNamespace,
+
+    // Configurations
+    Configuration,
+    EndConfiguration,
+    Resource,
+    EndResource,
+    On,
+    Task,
+    With,
 
     // Variable declarations
     Var,
     VarInput,
     VarOutput,
+    VarInOut,
+    VarTemp,
+    VarGlobal,
+    VarExternal,
     Constant,
+    Retain,
+    Persistent,
+    At,
     EndVar,
 
     // Type declarations
@@ -89,11 +126,94 @@ pub enum Token<'a> {
     EndStruct,
     Union,
     EndUnion,
+    Array,
+    Pointer,
+    Reference,
 
     // Addons
     Pragma(&'a str),
+    /// A pragma like `{attribute 'test'}`, including its braces
+    Attribute(&'a str),
 }
 
+/// Spelling of every keyword token. Keywords are matched case-insensitively.
+pub const KEYWORDS: &[(&str, Token<'static>)] = &[
+    // Logic
+    ("NOT", Token::Not),
+    ("TRUE", Token::True),
+    ("FALSE", Token::False),
+    ("AND", Token::And),
+    ("OR", Token::Or),
+    ("XOR", Token::Xor),
+    ("MOD", Token::Mod),
+    // Control flow
+    ("IF", Token::If),
+    ("THEN", Token::Then),
+    ("ELSIF", Token::Elsif),
+    ("ELSE", Token::Else),
+    ("END_IF", Token::EndIf),
+    ("CASE", Token::Case),
+    ("OF", Token::Of),
+    ("END_CASE", Token::EndCase),
+    ("FOR", Token::For),
+    ("TO", Token::To),
+    ("BY", Token::By),
+    ("DO", Token::Do),
+    ("END_FOR", Token::EndFor),
+    ("WHILE", Token::While),
+    ("END_WHILE", Token::EndWhile),
+    ("REPEAT", Token::Repeat),
+    ("UNTIL", Token::Until),
+    ("END_REPEAT", Token::EndRepeat),
+    ("CONTINUE", Token::Continue),
+    // Program / Action / Function / Function block
+    ("PROGRAM", Token::Program),
+    ("END_PROGRAM", Token::EndProgram),
+    ("EXIT", Token::Exit),
+    ("ACTION", Token::Action),
+    ("END_ACTION", Token::EndAction),
+    ("FUNCTION", Token::Function),
+    ("END_FUNCTION", Token::EndFunction),
+    ("FUNCTION_BLOCK", Token::FunctionBlock),
+    ("END_FUNCTION_BLOCK", Token::EndFunctionBlock),
+    ("METHOD", Token::Method),
+    ("END_METHOD", Token::EndMethod),
+    ("RETURN", Token::Return),
+    ("NAMESPACE", Token::Namespace),
+    ("END_NAMESPACE", Token::EndNamespace),
+    // Configurations
+    ("CONFIGURATION", Token::Configuration),
+    ("END_CONFIGURATION", Token::EndConfiguration),
+    ("RESOURCE", Token::Resource),
+    ("END_RESOURCE", Token::EndResource),
+    ("ON", Token::On),
+    ("TASK", Token::Task),
+    ("WITH", Token::With),
+    // Variable declarations
+    ("VAR", Token::Var),
+    ("VAR_INPUT", Token::VarInput),
+    ("VAR_OUTPUT", Token::VarOutput),
+    ("VAR_IN_OUT", Token::VarInOut),
+    ("VAR_TEMP", Token::VarTemp),
+    ("VAR_GLOBAL", Token::VarGlobal),
+    ("VAR_EXTERNAL", Token::VarExternal),
+    ("CONSTANT", Token::Constant),
+    ("RETAIN", Token::Retain),
+    ("PERSISTENT", Token::Persistent),
+    ("AT", Token::At),
+    ("END_VAR", Token::EndVar),
+    // Type declarations
+    ("TYPE", Token::Type),
+    ("END_TYPE", Token::EndType),
+    ("STRUCT", Token::Struct),
+    ("END_STRUCT", Token::EndStruct),
+    ("UNION", Token::Union),
+    ("END_UNION", Token::EndUnion),
+    ("ARRAY", Token::Array),
+    ("POINTER", Token::Pointer),
+    ("REFERENCE", Token::Reference),
+];
+
 impl<'a> Token<'a> {
     pub fn mark(self, marker: Marker<'a>) -> MarkedToken<'a> {
         MarkedToken {
@@ -101,6 +221,41 @@ impl<'a> Token<'a> {
             marker,
         }
     }
+
+    pub fn keyword(word: &str) -> Option<Token<'static>> {
+        KEYWORDS
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(word))
+            .map(|(_, t)| t.clone())
+    }
+
+    pub fn keyword_text(&self) -> Option<&'static str> {
+        KEYWORDS.iter().find(|(_, t)| t == self).map(|(k, _)| *k)
+    }
+
+    /// Number of source bytes the token spans.
+    pub fn text_len(&self) -> usize {
+        match self {
+            Token::Comment(s)
+            | Token::Identifier(s)
+            | Token::Number(s, _)
+            | Token::String(s)
+            | Token::Time(s, _)
+            | Token::Date(s, _)
+            | Token::DirectAddress(s)
+            | Token::LiteralPrefix(s)
+            | Token::Pragma(s)
+            | Token::Attribute(s) => s.len(),
+            Token::Power
+            | Token::Assign
+            | Token::Arrow
+            | Token::Range
+            | Token::NotEquals
+            | Token::GreaterThanOrEquals
+            | Token::LessThanOrEquals => 2,
+            t => t.keyword_text().map_or(1, str::len),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -109,13 +264,165 @@ pub enum NumberValue {
     Float(f64),
 }
 
+/// Prefixes of duration literals like `T#1s`.
+pub const TIME_PREFIXES: [&str; 4] = ["T#", "TIME#", "LT#", "LTIME#"];
+
+/// Units of duration literals, in the order they follow each other.
+pub const TIME_UNITS: [&str; 7] = ["d", "h", "m", "s", "ms", "us", "ns"];
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct TimeValue {
-    pub days: usize,
-    pub hours: u8,
-    pub minutes: u8,
-    pub seconds: u8,
-    pub milli_seconds: u16,
+    pub days: u64,
+    pub hours: u64,
+    pub minutes: u64,
+    pub seconds: u64,
+    pub milli_seconds: u64,
+    pub micro_seconds: u64,
+    pub nano_seconds: u64,
+}
+
+impl TimeValue {
+    /// The duration in nanoseconds, unless it exceeds the range of TIME.
+    pub fn nanoseconds(&self) -> Option<i64> {
+        let units = [
+            (self.days, 86_400_000_000_000),
+            (self.hours, 3_600_000_000_000),
+            (self.minutes, 60_000_000_000),
+            (self.seconds, 1_000_000_000),
+            (self.milli_seconds, 1_000_000),
+            (self.micro_seconds, 1_000),
+            (self.nano_seconds, 1),
+        ];
+        units.iter().try_fold(0i64, |total, (value, scale)| {
+            let value = i64::try_from(*value).ok()?;
+            total.checked_add(value.checked_mul(*scale)?)
+        })
+    }
+}
+
+/// Prefixes of date and time of day literals like `D#2024-01-15`, with what they denote.
+pub const DATE_PREFIXES: [(&str, DateKind); 6] = [
+    ("D#", DateKind::Date),
+    ("DATE#", DateKind::Date),
+    ("TOD#", DateKind::TimeOfDay),
+    ("TIME_OF_DAY#", DateKind::TimeOfDay),
+    ("DT#", DateKind::DateAndTime),
+    ("DATE_AND_TIME#", DateKind::DateAndTime),
+];
+
+const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateKind {
+    Date,
+    TimeOfDay,
+    DateAndTime,
+}
+
+/// A date, time of day or both, as written in a literal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateValue {
+    pub kind: DateKind,
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+}
+
+impl DateValue {
+    /// Nanoseconds since 1970-01-01 of dates, or since midnight of times of day, unless the date
+    /// or time does not exist or is out of the range of 64 bit.
+    pub fn nanoseconds(&self) -> Option<i64> {
+        let days_in_month = match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+        if !(1..=days_in_month).contains(&self.day)
+            || self.hour >= 24
+            || self.minute >= 60
+            || self.second >= 60
+            || self.nanosecond >= 1_000_000_000
+        {
+            return None;
+        }
+        let seconds = (self.hour * 60 + self.minute) * 60 + self.second;
+        let time = seconds as i64 * 1_000_000_000 + self.nanosecond as i64;
+        match self.kind {
+            DateKind::TimeOfDay => Some(time),
+            _ => days_from_civil(self.year, self.month, self.day)
+                .checked_mul(NANOSECONDS_PER_DAY)?
+                .checked_add(time),
+        }
+    }
+
+    /// The date or time of day a count of nanoseconds denotes, the inverse of
+    /// [`DateValue::nanoseconds`].
+    pub fn from_nanoseconds(kind: DateKind, nanoseconds: i64) -> Self {
+        let days = nanoseconds.div_euclid(NANOSECONDS_PER_DAY);
+        let time = nanoseconds.rem_euclid(NANOSECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let seconds = (time / 1_000_000_000) as u32;
+        Self {
+            kind,
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            nanosecond: (time % 1_000_000_000) as u32,
+        }
+    }
+}
+
+/// Writes the value as a literal like `DT#2024-01-15-12:30:00.5`.
+impl fmt::Display for DateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = format!("{:04}-{:02}-{:02}", self.year, self.month, self.day);
+        let mut time = format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second);
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            time = format!("{time}.{}", fraction.trim_end_matches('0'));
+        }
+        match self.kind {
+            DateKind::Date => write!(f, "D#{date}"),
+            DateKind::TimeOfDay => write!(f, "TOD#{time}"),
+            DateKind::DateAndTime => write!(f, "DT#{date}-{time}"),
+        }
+    }
+}
+
+/// Days of a date since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    // Years start in March, so that leap days end them
+    let month = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date some days after 1970-01-01, the inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -123,16 +430,24 @@ pub struct Marker<'a> {
     pos: usize,
     line: usize,
     col: usize,
+    /// Position and line of the start of the source within a project, which spans include
+    origin: (usize, usize),
     src_file: &'a str,
     src: &'a str,
 }
 
 impl<'a> Marker<'a> {
     pub fn create(src_file: &'a str, src: &'a str) -> Self {
+        Self::create_at(src_file, src, 0, 0)
+    }
+
+    /// Marker of a source starting at a position and line of a project.
+    pub fn create_at(src_file: &'a str, src: &'a str, pos: usize, line: usize) -> Self {
         Self {
             pos: 0,
             line: 0,
             col: 0,
+            origin: (pos, line),
             src_file,
             src,
         }
@@ -144,8 +459,8 @@ impl<'a> Marker<'a> {
         self.col = col;
     }
 
-    pub fn advance(&mut self) {
-        self.pos += 1;
+    pub fn advance(&mut self, byte_len: usize) {
+        self.pos += byte_len;
         self.col += 1;
     }
 
@@ -170,20 +485,23 @@ impl<'a> Marker<'a> {
     }
 
     fn get_line(&self) -> &str {
-        let start_idx = self.src[..self.pos]
-            .chars()
-            .enumerate()
-            .filter(|(_, c)| *c == '\n')
-            .last()
-            .map_or(0, |(i, _)| i + 1);
-
+        let start_idx = self.src[..self.pos].rfind('\n').map_or(0, |i| i + 1);
         let line = &self.src[start_idx..];
 
-        line.chars()
-            .enumerate()
-            .filter(|(_, c)| *c == '\n')
-            .next()
-            .map_or(line, |(i, _)| &line[..i])
+        line.find('\n').map_or(line, |i| &line[..i])
+    }
+
+    pub fn src_file(&self) -> &'a str {
+        self.src_file
+    }
+
+    pub fn span(&self, len: usize) -> Span {
+        Span {
+            pos: self.origin.0 + self.pos,
+            len,
+            line: self.origin.1 + self.line,
+            col: self.col,
+        }
     }
 }
 
@@ -193,6 +511,12 @@ pub struct MarkedToken<'a> {
     pub marker: Marker<'a>,
 }
 
+impl MarkedToken<'_> {
+    pub fn span(&self) -> Span {
+        self.marker.span(self.token.text_len())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +527,7 @@ mod tests {
             pos: 8,
             line: 2,
             col: 1,
+            origin: (0, 0),
             src_file: "Some file.st",
             src: "hel\nlo\nwor\nld",
         };
@@ -0,0 +1,348 @@
+use std::path::Path;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::{
+        ast::{Ast, Span},
+        lexer::Lexer,
+        parser::SyntaxError,
+        syntax::SyntaxNode,
+        token::Marker,
+    },
+    runtime::scheduler,
+    semantic::{checker::check, resolver::resolve, symbols::SymbolTable},
+};
+
+/// Extensions of the files searched for in directories: programs, types and global variables.
+pub const SOURCE_EXTENSIONS: [&str; 3] = ["st", "typ", "var"];
+
+pub mod conditional;
+pub mod manifest;
+
+/// Index of a file in a [`SourceDb`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(pub usize);
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    /// Position and line of the start of the file in the spans of the project
+    pub origin: (usize, usize),
+}
+
+/// The source files of a project.
+///
+/// Spans of all files share one range of positions, each file following the one added before
+/// it. So the syntax trees of the files merge into one, which is resolved as a whole, while every
+/// span still leads back to its file.
+#[derive(Debug, Default)]
+pub struct SourceDb {
+    files: Vec<SourceFile>,
+    /// Active conditional compilation defines
+    defines: Vec<String>,
+}
+
+impl SourceDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the files and the source files below the directories, in the order of their paths.
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self, String> {
+        fn collect(path: &Path, files: &mut Vec<String>) -> Result<(), String> {
+            if !path.is_dir() {
+                files.push(path.display().to_string());
+                return Ok(());
+            }
+            let entries = std::fs::read_dir(path)
+                .map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+            let mut entries = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .collect::<Vec<_>>();
+            entries.sort();
+            for entry in entries {
+                let source = entry
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| SOURCE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+                if entry.is_dir() || source {
+                    collect(&entry, files)?;
+                }
+            }
+            Ok(())
+        }
+
+        let mut names = Vec::new();
+        for path in paths {
+            collect(path.as_ref(), &mut names)?;
+        }
+        let mut db = Self::new();
+        for name in names {
+            let text =
+                std::fs::read_to_string(&name).map_err(|e| format!("Cannot read {name}: {e}"))?;
+            db.add(name, text);
+        }
+        Ok(db)
+    }
+
+    /// Adds a file after all others.
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> FileId {
+        // One position past the end of a file still belongs to it, for spans at its end
+        let origin = self.files.last().map_or((0, 0), |last| {
+            let lines = last.text.matches('\n').count() + 1;
+            (last.origin.0 + last.text.len() + 1, last.origin.1 + lines)
+        });
+        self.files.push(SourceFile {
+            name: name.into(),
+            text: text.into(),
+            origin,
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    /// Activates conditional compilation defines, which select the code that is parsed.
+    pub fn set_defines(&mut self, defines: Vec<String>) {
+        self.defines = defines;
+    }
+
+    pub fn defines(&self) -> &[String] {
+        &self.defines
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.iter().enumerate().map(|(i, f)| (FileId(i), f))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Finds a file by its name.
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files.iter().position(|f| f.name == name).map(FileId)
+    }
+
+    /// Lexer of a file whose spans are positions in the project.
+    pub fn lexer(&self, id: FileId) -> Lexer<'_> {
+        let file = self.file(id);
+        Lexer::create_at(&file.name, &file.text, file.origin.0, file.origin.1)
+    }
+
+    /// The file a span of the project points into.
+    pub fn file_of(&self, span: Span) -> FileId {
+        let index = self
+            .files
+            .iter()
+            .rposition(|f| f.origin.0 <= span.pos)
+            .unwrap_or_default();
+        FileId(index)
+    }
+
+    /// A span of the project relative to the start of its file.
+    pub fn local(&self, span: Span) -> (FileId, Span) {
+        let id = self.file_of(span);
+        let (pos, line) = self.files.get(id.0).map_or((0, 0), |f| f.origin);
+        let local = Span {
+            pos: span.pos.saturating_sub(pos),
+            line: span.line.saturating_sub(line),
+            ..span
+        };
+        (id, local)
+    }
+
+    /// Formats a diagnostic, each of its spans within the file it points into.
+    pub fn format(&self, diagnostic: &Diagnostic) -> String {
+        diagnostic.format_located(|span| {
+            let (id, local) = self.local(span);
+            match self.files.get(id.0) {
+                Some(file) => (file.name.as_str(), file.text.as_str(), local),
+                None => ("", "", local),
+            }
+        })
+    }
+
+    /// Parses every file and merges their declarations in the order of the files.
+    pub fn parse(&self) -> Result<Ast, Vec<String>> {
+        let mut ast = Ast::default();
+        let mut errors = Vec::new();
+        for (id, _) in self.files() {
+            match self.parse_file(id) {
+                Ok(file) => ast.blocks.extend(file.blocks),
+                Err(e) => errors.extend(e.into_iter().map(|e| e.printable)),
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(ast),
+            false => Err(errors),
+        }
+    }
+
+    /// Parses a file, only the code selected by the conditional compilation pragmas.
+    pub fn parse_file(&self, id: FileId) -> Result<Ast, Vec<SyntaxError>> {
+        self.parse_tree(id, &SyntaxNode::parse(&self.file(id).text))
+    }
+
+    /// Derives the syntax tree of a file from its lossless syntax tree, which must be that of the
+    /// text of the file, like one an editor keeps up to date by its edits.
+    pub fn parse_tree(&self, id: FileId, tree: &SyntaxNode) -> Result<Ast, Vec<SyntaxError>> {
+        let file = self.file(id);
+        let text = self.active_text(id)?;
+        tree.ast_located(&file.name, file.origin, &text)
+    }
+
+    /// The text of a file with the code of the conditional branches not taken replaced by spaces,
+    /// so that positions stay those of the file.
+    pub fn active_text(&self, id: FileId) -> Result<String, Vec<SyntaxError>> {
+        let file = self.file(id);
+        let (pos, line) = file.origin;
+        conditional::apply(&file.text, &self.defines).map_err(|(at, message)| {
+            let before = &file.text[..at];
+            let mut marker = Marker::create_at(&file.name, &file.text, pos, line);
+            marker.set(
+                at,
+                before.matches('\n').count(),
+                before.rsplit('\n').next().map_or(0, |l| l.chars().count()),
+            );
+            vec![SyntaxError {
+                span: Some(marker.span(1)),
+                printable: format!("[ERR] in {} {message}", marker.format_as_printable()),
+                message,
+            }]
+        })
+    }
+}
+
+/// The files of a project resolved and checked as one program.
+///
+/// Declarations of every file are visible in all others, declaring a name twice in different
+/// files is reported like it is within one file, with a note pointing to the other file.
+#[derive(Debug)]
+pub struct Project {
+    pub sources: SourceDb,
+    pub ast: Ast,
+    pub table: SymbolTable,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Project {
+    /// Parses all files, then resolves and checks them unless they have syntax errors.
+    pub fn analyze(sources: SourceDb) -> Result<Self, Vec<String>> {
+        let ast = sources.parse()?;
+        Ok(Self::from_ast(sources, ast))
+    }
+
+    /// Resolves and checks the files by their syntax trees parsed before, merged in the order
+    /// of the files. Program instances the runtime cannot run are errors as well.
+    pub fn from_ast(sources: SourceDb, ast: Ast) -> Self {
+        let (table, mut diagnostics) = resolve(&ast);
+        diagnostics.extend(check(&ast, &table));
+        diagnostics.extend(scheduler::check_instances(&ast, &table));
+        Self {
+            sources,
+            ast,
+            table,
+            diagnostics,
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::ast::Block;
+
+    fn sources(files: &[(&str, &str)]) -> SourceDb {
+        let mut db = SourceDb::new();
+        for (name, text) in files {
+            db.add(*name, *text);
+        }
+        db
+    }
+
+    #[test]
+    fn test_spans_lead_back_to_their_file() {
+        let db = sources(&[("a.st", "TYPE T : INT; END_TYPE\n"), ("b.st", "\nX")]);
+        let b = db.file(FileId(1));
+        assert_eq!(b.origin, (24, 2));
+
+        let tokens = db.lexer(FileId(1)).map(|t| t.span()).collect::<Vec<_>>();
+        assert_eq!((tokens[0].pos, tokens[0].line), (25, 3));
+        let (id, local) = db.local(tokens[0]);
+        assert_eq!(id, FileId(1));
+        assert_eq!((local.pos, local.line, local.col), (1, 1, 0));
+
+        let diagnostic = Diagnostic::error(tokens[0], "Odd.");
+        assert_eq!(
+            db.format(&diagnostic),
+            "[ERR] in b.st:2:0\n    |\n 2  | X\n    |  ^ Odd."
+        );
+    }
+
+    #[test]
+    fn test_merges_declarations_of_all_files() {
+        let db = sources(&[
+            ("types.typ", "TYPE Speed : INT; END_TYPE"),
+            ("globals.var", "VAR_GLOBAL limit : Speed := 10; END_VAR"),
+            (
+                "main.st",
+                "PROGRAM Main VAR s : Speed; END_VAR s := MIN(s + 1, limit); END_PROGRAM",
+            ),
+        ]);
+        let project = Project::analyze(db).unwrap();
+        assert!(!project.has_errors(), "{:?}", project.diagnostics);
+        assert_eq!(project.ast.blocks.len(), 3);
+    }
+
+    #[test]
+    fn test_reports_duplicates_across_files() {
+        let db = sources(&[
+            ("a.st", "PROGRAM Main END_PROGRAM"),
+            ("b.st", "\nPROGRAM Main END_PROGRAM"),
+        ]);
+        let project = Project::analyze(db).unwrap();
+        let [duplicate] = &project.diagnostics[..] else {
+            panic!("{:?}", project.diagnostics);
+        };
+        let printed = project.sources.format(duplicate);
+        assert!(printed.starts_with("[ERR] in b.st:2:8"), "{printed}");
+        assert!(printed.contains("[NOTE] in a.st:1:8"), "{printed}");
+
+        let broken = sources(&[("a.st", "PROGRAM A END_PROGRAM"), ("b.st", "PROGRAM")]);
+        let errors = broken.parse().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "[ERR] at the end of b.st Expected an identifier token after the PROGRAM declaration."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_derives_files_from_their_syntax_trees() {
+        let text = "{IF defined(SIM)}\nPROGRAM Sim END_PROGRAM\n{END_IF}\nPROGRAM Main END_PROGRAM";
+        let mut db = sources(&[("a.st", "TYPE T : INT; END_TYPE\n"), ("b.st", text)]);
+        let tree = SyntaxNode::parse(text);
+        let main = |ast: &Ast| match &ast.blocks[..] {
+            [Block::Program(pou)] => pou.name.span,
+            blocks => panic!("{blocks:?}"),
+        };
+        let span = main(&db.parse_tree(FileId(1), &tree).unwrap());
+        assert_eq!((span.pos, span.line, span.col), (24 + 59, 2 + 3, 8));
+
+        db.set_defines(vec!["SIM".to_string()]);
+        assert_eq!(db.parse_tree(FileId(1), &tree).unwrap().blocks.len(), 2);
+    }
+}
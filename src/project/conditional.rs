@@ -0,0 +1,265 @@
+/// Applies conditional compilation pragmas to a source.
+///
+/// `{IF defined(NAME)}`, `{ELSIF ...}`, `{ELSE}` and `{END_IF}` select code by the active defines.
+/// Conditions combine `defined(NAME)` with NOT, AND, OR and parentheses. The pragmas and the code
+/// of branches not taken are replaced by spaces, keeping line breaks, so that positions in the
+/// result are those of the original source.
+///
+/// Errors give the byte position of the offending pragma.
+pub fn apply(src: &str, defines: &[String]) -> Result<String, (usize, String)> {
+    struct Branch {
+        /// Whether the enclosing code is compiled
+        outer: bool,
+        /// Whether a branch was taken already
+        taken: bool,
+        active: bool,
+        has_else: bool,
+    }
+
+    let mut out = String::with_capacity(src.len());
+    let mut branches: Vec<Branch> = Vec::new();
+    let active = |branches: &[Branch]| branches.last().is_none_or(|b| b.active);
+    let blank = |out: &mut String, text: &str| {
+        for c in text.chars() {
+            match c {
+                '\n' | '\r' => out.push(c),
+                c => out.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            }
+        }
+    };
+
+    let mut pos = 0;
+    while pos < src.len() {
+        let rest = &src[pos..];
+        // Comments and strings are copied as they are, braces in them are no pragmas
+        let skipped = [
+            ("(*", "*)"),
+            ("/*", "*/"),
+            ("//", "\n"),
+            ("'", "'"),
+            ("\"", "\""),
+        ]
+        .iter()
+        .find(|(open, _)| rest.starts_with(open))
+        .map(|(open, close)| {
+            rest[open.len()..]
+                .find(close)
+                .map_or(rest.len(), |end| open.len() + end + close.len())
+        });
+        let (len, directive) = match skipped {
+            Some(len) => (len, None),
+            None if rest.starts_with('{') => {
+                let Some(end) = rest.find('}') else {
+                    return Err((pos, "The pragma is missing its closing brace.".to_string()));
+                };
+                (end + 1, directive(&rest[1..end]))
+            }
+            None => (rest.chars().next().map_or(1, char::len_utf8), None),
+        };
+        let text = &rest[..len];
+
+        let Some((keyword, condition)) = directive else {
+            match active(&branches) {
+                true => out.push_str(text),
+                false => blank(&mut out, text),
+            }
+            pos += len;
+            continue;
+        };
+        let error = |message: &str| (pos, message.to_string());
+        let holds = |condition: &str| evaluate(condition, defines).map_err(|e| error(&e));
+        match keyword.as_str() {
+            "IF" => {
+                let outer = active(&branches);
+                let active = outer && holds(condition)?;
+                branches.push(Branch {
+                    outer,
+                    taken: active,
+                    active,
+                    has_else: false,
+                });
+            }
+            "ELSIF" => {
+                let branch = branches
+                    .last_mut()
+                    .ok_or_else(|| error("ELSIF without IF."))?;
+                if branch.has_else {
+                    return Err(error("ELSIF after ELSE."));
+                }
+                branch.active = branch.outer && !branch.taken && holds(condition)?;
+                branch.taken |= branch.active;
+            }
+            "ELSE" => {
+                let branch = branches
+                    .last_mut()
+                    .ok_or_else(|| error("ELSE without IF."))?;
+                if branch.has_else {
+                    return Err(error("IF has more than one ELSE."));
+                }
+                branch.has_else = true;
+                branch.active = branch.outer && !branch.taken;
+                branch.taken = true;
+            }
+            _ => {
+                branches.pop().ok_or_else(|| error("END_IF without IF."))?;
+            }
+        }
+        blank(&mut out, text);
+        pos += len;
+    }
+
+    match branches.is_empty() {
+        true => Ok(out),
+        false => Err((src.len(), "IF is missing its END_IF.".to_string())),
+    }
+}
+
+/// Keyword and condition of a conditional compilation pragma, given the text within its braces.
+pub fn directive(text: &str) -> Option<(String, &str)> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !c.is_ascii_alphabetic() && c != '_')
+        .unwrap_or(text.len());
+    let keyword = text[..end].to_ascii_uppercase();
+    match keyword.as_str() {
+        "IF" | "ELSIF" => Some((keyword, &text[end..])),
+        "ELSE" | "END_IF" if text[end..].trim().is_empty() => Some((keyword, "")),
+        _ => None,
+    }
+}
+
+/// Evaluates a condition like `defined(A) AND NOT (defined(B) OR defined(C))`.
+fn evaluate(condition: &str, defines: &[String]) -> Result<bool, String> {
+    let mut tokens = Vec::new();
+    let mut rest = condition.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '(' | ')' => 1,
+            c if c.is_alphanumeric() || c == '_' => rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len()),
+            c => return Err(format!("Unexpected '{c}' in a condition.")),
+        };
+        tokens.push(rest[..len].to_ascii_uppercase());
+        rest = rest[len..].trim_start();
+    }
+
+    let mut parser = Condition {
+        tokens,
+        pos: 0,
+        defines,
+    };
+    let value = parser.or()?;
+    match parser.pos == parser.tokens.len() {
+        true => Ok(value),
+        false => Err("Unexpected content after a condition.".to_string()),
+    }
+}
+
+struct Condition<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    defines: &'a [String],
+}
+
+impl Condition<'_> {
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.tokens.get(self.pos).is_some_and(|t| t == token);
+        self.pos += found as usize;
+        found
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let mut value = self.and()?;
+        while self.eat("OR") {
+            value |= self.and()?;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut value = self.unary()?;
+        while self.eat("AND") {
+            value &= self.unary()?;
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<bool, String> {
+        if self.eat("NOT") {
+            return self.unary().map(|v| !v);
+        }
+        if self.eat("(") {
+            let value = self.or()?;
+            return match self.eat(")") {
+                true => Ok(value),
+                false => Err("Expected ')' in a condition.".to_string()),
+            };
+        }
+        let expected = "Expected defined(NAME) in a condition.";
+        if !self.eat("DEFINED") || !self.eat("(") {
+            return Err(expected.to_string());
+        }
+        let name = self.tokens.get(self.pos).cloned().ok_or(expected)?;
+        self.pos += 1;
+        if !self.eat(")") {
+            return Err(expected.to_string());
+        }
+        Ok(self.defines.iter().any(|d| d.eq_ignore_ascii_case(&name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selects_branches_by_defines() {
+        let src = "A;\n{IF defined(SIM) AND NOT defined(REAL_IO)}\nB;\n{ELSIF defined(REAL_IO)}\nC;\n{ELSE}\nD;\n{END_IF}\n(* {IF} *) {attribute 'x'}";
+        let simulated = apply(src, &["sim".to_string()]).unwrap();
+        assert_eq!(simulated.len(), src.len());
+        let code = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(code(&simulated), "A; B; (* {IF} *) {attribute 'x'}");
+        assert_eq!(simulated.lines().count(), src.lines().count());
+        assert_eq!(
+            code(&apply(src, &["REAL_IO".to_string()]).unwrap()),
+            "A; C; (* {IF} *) {attribute 'x'}"
+        );
+        assert_eq!(
+            code(&apply(src, &[]).unwrap()),
+            "A; D; (* {IF} *) {attribute 'x'}"
+        );
+    }
+
+    #[test]
+    fn test_nests_and_reports_errors() {
+        let src = "{IF defined(A)}{IF defined(B)}AB{ELSE}A{END_IF}{END_IF}";
+        let code = |defines: &[&str]| {
+            let defines = defines.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            apply(src, &defines).unwrap().trim().to_string()
+        };
+        assert_eq!(code(&["A", "B"]), "AB");
+        assert_eq!(code(&["A"]), "A");
+        assert_eq!(code(&["B"]), "");
+
+        assert_eq!(
+            apply("x {END_IF}", &[]),
+            Err((2, "END_IF without IF.".to_string()))
+        );
+        assert_eq!(
+            apply("{IF defined(A}", &[]),
+            Err((0, "Expected defined(NAME) in a condition.".to_string()))
+        );
+        assert_eq!(
+            apply("{IF defined(A)}", &[]),
+            Err((15, "IF is missing its END_IF.".to_string()))
+        );
+        for src in ["PROGRAM Main\nEND_PROGRAM\n{", "{ä", "x := 1; { y"] {
+            let at = src.find('{').unwrap();
+            assert_eq!(
+                apply(src, &[]),
+                Err((at, "The pragma is missing its closing brace.".to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    formats::{json::Json, toml},
+    project::{SOURCE_EXTENSIONS, SourceDb},
+    runtime::{EngineKind, scheduler::Configuration},
+};
+
+/// How strictly a lint is enforced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// A library the project depends on, a directory with a manifest of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Settings of the PLCopen coding guidelines check.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Guidelines {
+    /// Prefixes the names of kinds of elements must have, like `function-block = "FB_"`
+    pub prefixes: Vec<(String, String)>,
+    /// Most statements a POU, method or action may have
+    pub max_statements: Option<usize>,
+}
+
+/// Where the commands write what they produce.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outputs {
+    /// JUnit XML report of `strooct test`
+    pub junit: Option<PathBuf>,
+}
+
+/// A project described by a `strooct.toml` manifest.
+///
+/// ```toml
+/// [project]
+/// name = "conveyor"
+///
+/// [sources]
+/// include = ["src/**/*.st", "types/*.typ"]
+/// exclude = ["src/legacy/**"]
+///
+/// [dependencies]
+/// motion = { path = "../motion" }
+///
+/// [defines]
+/// SIMULATION = true
+///
+/// [lint]
+/// unused-variable = "deny"
+///
+/// [plcopen]
+/// max-statements = 80
+/// function-block = "FB_"
+///
+/// [target]
+/// engine = "vm"
+///
+/// [output]
+/// junit = "build/tests.xml"
+///
+/// [[task]]
+/// name = "Fast"
+/// interval = "10ms"
+/// programs = ["Main"]
+/// ```
+///
+/// Paths are relative to the directory of the manifest. Sources default to all `.st`, `.typ` and
+/// `.var` files below it, the tasks and their `[costs]` have the format of a task configuration.
+/// `[lint]` sets the levels of the lints of [`crate::lint::LINTS`], `[plcopen]` the prefixes and
+/// limits of [`crate::lint::plcopen`]. Other tables and keys are errors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    /// Directory of the manifest
+    pub root: PathBuf,
+    /// Glob patterns of the source files, `**` matching any number of directories
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    /// Names of the active conditional compilation defines
+    pub defines: Vec<String>,
+    pub tasks: Option<Configuration>,
+    pub lints: Vec<(String, LintLevel)>,
+    pub guidelines: Guidelines,
+    pub engine: EngineKind,
+    pub outputs: Outputs,
+}
+
+impl Manifest {
+    pub const FILE_NAME: &str = "strooct.toml";
+
+    /// Reads a manifest, given its path or the directory holding it.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let path = match path.is_dir() {
+            true => path.join(Self::FILE_NAME),
+            false => path.to_path_buf(),
+        };
+        let src = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+        let root = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        Self::parse(&src, root).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(src: &str, root: PathBuf) -> Result<Self, String> {
+        let doc = toml::parse(src)?;
+        check_keys(&doc)?;
+        let table = |key: &str| -> Result<&[(String, Json)], String> {
+            match doc.get(key) {
+                None => Ok(&[]),
+                Some(table) => table
+                    .as_object()
+                    .ok_or_else(|| format!("[{key}] must be a table.")),
+            }
+        };
+        let text = |value: &Json, what: &str| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("{what} must be a string."))
+        };
+        let texts = |value: Option<&Json>, what: &str| match value {
+            None => Ok(None),
+            Some(value) => value
+                .as_array()
+                .and_then(|items| {
+                    items
+                        .iter()
+                        .map(|i| i.as_str().map(str::to_string))
+                        .collect()
+                })
+                .map(Some)
+                .ok_or_else(|| format!("{what} must be an array of strings.")),
+        };
+
+        let project = doc.get("project");
+        let name = match project.and_then(|p| p.get("name")) {
+            Some(name) => text(name, "The project name")?,
+            None => root
+                .file_name()
+                .map_or("project".to_string(), |n| n.to_string_lossy().into_owned()),
+        };
+        let version = match project.and_then(|p| p.get("version")) {
+            Some(version) => Some(text(version, "The project version")?),
+            None => None,
+        };
+
+        let sources = doc.get("sources");
+        let include = texts(sources.and_then(|s| s.get("include")), "sources.include")?
+            .unwrap_or_else(|| {
+                SOURCE_EXTENSIONS
+                    .iter()
+                    .map(|e| format!("**/*.{e}"))
+                    .collect()
+            });
+        let exclude =
+            texts(sources.and_then(|s| s.get("exclude")), "sources.exclude")?.unwrap_or_default();
+
+        let dependencies = table("dependencies")?
+            .iter()
+            .map(|(name, value)| {
+                let path = match value {
+                    Json::String(path) => Some(path.as_str()),
+                    value => value.get("path").and_then(Json::as_str),
+                }
+                .ok_or_else(|| format!("Dependency '{name}' needs a path."))?;
+                Ok(Dependency {
+                    name: name.clone(),
+                    path: root.join(path),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // Defines set to FALSE are inactive, any other value activates them
+        let defines = table("defines")?
+            .iter()
+            .filter(|(_, value)| *value != Json::Bool(false))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let lints = table("lint")?
+            .iter()
+            .map(|(name, level)| {
+                level
+                    .as_str()
+                    .and_then(LintLevel::from_name)
+                    .map(|level| (name.clone(), level))
+                    .ok_or_else(|| {
+                        format!("The level of lint '{name}' must be allow, warn or deny.")
+                    })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut guidelines = Guidelines::default();
+        for (name, value) in table("plcopen")? {
+            match name.as_str() {
+                "max-statements" => {
+                    let max = value
+                        .as_i64()
+                        .filter(|n| *n > 0)
+                        .ok_or("plcopen.max-statements must be a positive number.".to_string())?;
+                    guidelines.max_statements = Some(max as usize);
+                }
+                _ => {
+                    let prefix = text(value, &format!("The prefix of {name}"))?;
+                    guidelines.prefixes.push((name.clone(), prefix));
+                }
+            }
+        }
+
+        let engine = match doc.get("target").and_then(|t| t.get("engine")) {
+            None => EngineKind::default(),
+            Some(engine) => match engine.as_str().map(str::to_ascii_lowercase).as_deref() {
+                Some("vm") => EngineKind::Vm,
+                Some("interpreter") => EngineKind::Interpreter,
+                _ => return Err("The target engine must be vm or interpreter.".to_string()),
+            },
+        };
+        let outputs = Outputs {
+            junit: match doc.get("output").and_then(|o| o.get("junit")) {
+                Some(path) => Some(root.join(text(path, "output.junit")?)),
+                None => None,
+            },
+        };
+
+        let tasks = match doc.get("task").or_else(|| doc.get("tasks")) {
+            Some(_) => Some(Configuration::from_document(&doc)?),
+            None => None,
+        };
+
+        Ok(Self {
+            name,
+            version,
+            root,
+            include,
+            exclude,
+            dependencies,
+            defines,
+            tasks,
+            lints,
+            guidelines,
+            engine,
+            outputs,
+        })
+    }
+
+    /// Source files of the project itself, in the order of their paths.
+    pub fn source_files(&self) -> Result<Vec<PathBuf>, String> {
+        fn walk(dir: &Path, relative: &str, files: &mut Vec<String>) -> Result<(), String> {
+            let entries = std::fs::read_dir(dir)
+                .map_err(|e| format!("Cannot read {}: {e}", dir.display()))?;
+            for entry in entries.filter_map(Result::ok) {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path = format!("{relative}{name}");
+                match entry.path().is_dir() {
+                    true => walk(&entry.path(), &format!("{path}/"), files)?,
+                    false => files.push(path),
+                }
+            }
+            Ok(())
+        }
+
+        let mut files = Vec::new();
+        walk(&self.root, "", &mut files)?;
+        files.sort();
+        let matching = |patterns: &[String], file: &str| patterns.iter().any(|p| glob(p, file));
+        Ok(files
+            .into_iter()
+            .filter(|f| matching(&self.include, f) && !matching(&self.exclude, f))
+            .map(|f| self.root.join(f))
+            .collect())
+    }
+
+    /// Loads the sources of the libraries, each before the projects depending on it, and then
+    /// those of the project. The defines of the project apply to all of them.
+    pub fn sources(&self) -> Result<SourceDb, String> {
+        fn add(
+            manifest: &Manifest,
+            db: &mut SourceDb,
+            loaded: &mut Vec<PathBuf>,
+        ) -> Result<(), String> {
+            let root = manifest
+                .root
+                .canonicalize()
+                .unwrap_or(manifest.root.clone());
+            if loaded.contains(&root) {
+                return Ok(());
+            }
+            loaded.push(root);
+            for dependency in &manifest.dependencies {
+                let library = Manifest::load(&dependency.path)
+                    .map_err(|e| format!("Library '{}' cannot be loaded: {e}", dependency.name))?;
+                add(&library, db, loaded)?;
+            }
+            for path in manifest.source_files()? {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+                db.add(path.display().to_string(), text);
+            }
+            Ok(())
+        }
+
+        let mut db = SourceDb::new();
+        db.set_defines(self.defines.clone());
+        add(self, &mut db, &mut Vec::new())?;
+        Ok(db)
+    }
+}
+
+/// Tables of a manifest and the keys they may have, `None` for tables with names of their own
+const TABLES: [(&str, Option<&[&str]>); 11] = [
+    ("project", Some(&["name", "version"])),
+    ("sources", Some(&["include", "exclude"])),
+    ("dependencies", None),
+    ("defines", None),
+    ("lint", None),
+    ("plcopen", None),
+    ("target", Some(&["engine"])),
+    ("output", Some(&["junit"])),
+    ("task", Some(&TASK_KEYS)),
+    ("tasks", Some(&TASK_KEYS)),
+    ("costs", None),
+];
+
+const TASK_KEYS: [&str; 4] = ["name", "interval", "priority", "programs"];
+
+/// Rejects the tables and keys a manifest cannot have, which would otherwise be ignored.
+fn check_keys(doc: &Json) -> Result<(), String> {
+    let unknown = |keys: &[(String, Json)], known: &[&str], table: &str| match keys
+        .iter()
+        .find(|(key, _)| !known.contains(&key.as_str()))
+    {
+        Some((key, _)) => Err(format!("Unknown key '{key}' in {table}.")),
+        None => Ok(()),
+    };
+
+    for (name, value) in doc.as_object().unwrap_or_default() {
+        let Some((_, known)) = TABLES.iter().find(|(table, _)| table == name) else {
+            return Err(match value {
+                Json::Object(_) => format!("Unknown table [{name}]."),
+                Json::Array(items) if items.iter().all(|i| i.as_object().is_some()) => {
+                    format!("Unknown table [[{name}]].")
+                }
+                _ => format!("Unknown key '{name}'."),
+            });
+        };
+        match (known, value) {
+            (Some(known), Json::Object(keys)) => unknown(keys, known, &format!("[{name}]"))?,
+            (Some(known), Json::Array(items)) => {
+                for keys in items.iter().filter_map(Json::as_object) {
+                    unknown(keys, known, &format!("[[{name}]]"))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    for (name, value) in doc
+        .get("dependencies")
+        .and_then(Json::as_object)
+        .unwrap_or_default()
+    {
+        if let Some(keys) = value.as_object() {
+            unknown(keys, &["path"], &format!("dependency '{name}'"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a path with `/` separators matches a glob pattern of `*`, `?` and `**`.
+fn glob(pattern: &str, path: &str) -> bool {
+    fn segments(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| segments(rest, &path[i..])),
+            Some((first, rest)) => {
+                path.first()
+                    .is_some_and(|p| wildcard(first.as_bytes(), p.as_bytes()))
+                    && segments(rest, &path[1..])
+            }
+        }
+    }
+    fn wildcard(pattern: &[u8], name: &[u8]) -> bool {
+        match (pattern.split_first(), name.split_first()) {
+            (None, _) => name.is_empty(),
+            (Some((b'*', rest)), _) => (0..=name.len()).any(|i| wildcard(rest, &name[i..])),
+            (Some((b'?', rest)), Some((_, name))) => wildcard(rest, name),
+            (Some((p, rest)), Some((n, name))) => p == n && wildcard(rest, name),
+            (Some(_), None) => false,
+        }
+    }
+
+    let pattern = pattern.trim_start_matches("./");
+    segments(
+        &pattern.split('/').collect::<Vec<_>>(),
+        &path.split('/').collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_manifest() {
+        let src = r#"
+            [project]
+            name = "conveyor"
+            version = "1.2.0"
+
+            [sources]
+            include = ["src/**/*.st"]
+            exclude = ["src/legacy/**"]
+
+            [dependencies]
+            motion = { path = "../motion" }
+            util = "libs/util"
+
+            [defines]
+            SIMULATION = true
+            REAL_IO = false
+            AXES = 4
+
+            [lint]
+            unused-variable = "deny"
+
+            [plcopen]
+            max-statements = 80
+            input = "in"
+
+            [target]
+            engine = "interpreter"
+
+            [output]
+            junit = "build/tests.xml"
+
+            [[task]]
+            name = "Fast"
+            interval = "10ms"
+            programs = ["Main"]
+        "#;
+        let manifest = Manifest::parse(src, PathBuf::from("plant")).unwrap();
+        assert_eq!(manifest.name, "conveyor");
+        assert_eq!(manifest.version.as_deref(), Some("1.2.0"));
+        assert_eq!(manifest.exclude, vec!["src/legacy/**"]);
+        assert_eq!(
+            manifest.dependencies,
+            vec![
+                Dependency {
+                    name: "motion".to_string(),
+                    path: PathBuf::from("plant/../motion"),
+                },
+                Dependency {
+                    name: "util".to_string(),
+                    path: PathBuf::from("plant/libs/util"),
+                },
+            ]
+        );
+        assert_eq!(manifest.defines, vec!["SIMULATION", "AXES"]);
+        assert_eq!(
+            manifest.lints,
+            vec![("unused-variable".to_string(), LintLevel::Deny)]
+        );
+        assert_eq!(
+            manifest.guidelines,
+            Guidelines {
+                prefixes: vec![("input".to_string(), "in".to_string())],
+                max_statements: Some(80),
+            }
+        );
+        assert_eq!(manifest.engine, EngineKind::Interpreter);
+        assert_eq!(
+            manifest.outputs.junit,
+            Some(PathBuf::from("plant/build/tests.xml"))
+        );
+        assert_eq!(manifest.tasks.unwrap().tasks[0].interval, 10_000_000);
+
+        let defaults = Manifest::parse("", PathBuf::from("plant")).unwrap();
+        assert_eq!(defaults.name, "plant");
+        assert_eq!(defaults.include, vec!["**/*.st", "**/*.typ", "**/*.var"]);
+        assert_eq!(defaults.tasks, None);
+
+        assert_eq!(
+            Manifest::parse("[lint]\nnaming = \"loud\"", PathBuf::new()),
+            Err("The level of lint 'naming' must be allow, warn or deny.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_tables_and_keys() {
+        let error = |src: &str| Manifest::parse(src, PathBuf::new()).unwrap_err();
+        assert_eq!(
+            error("[package]\nname = \"conveyor\""),
+            "Unknown table [package]."
+        );
+        assert_eq!(error("name = \"conveyor\""), "Unknown key 'name'.");
+        assert_eq!(
+            error("[project]\nnmae = \"conveyor\""),
+            "Unknown key 'nmae' in [project]."
+        );
+        assert_eq!(
+            error("[[task]]\nname = \"Fast\"\ninterval = \"10ms\"\ncycle = 2"),
+            "Unknown key 'cycle' in [[task]]."
+        );
+        assert_eq!(
+            error("[dependencies]\nmotion = { dir = \"../motion\" }"),
+            "Unknown key 'dir' in dependency 'motion'."
+        );
+    }
+
+    #[test]
+    fn test_matches_globs() {
+        assert!(glob("**/*.st", "main.st"));
+        assert!(glob("**/*.st", "src/a/b/main.st"));
+        assert!(glob("src/*.st", "src/main.st"));
+        assert!(!glob("src/*.st", "src/a/main.st"));
+        assert!(glob("src/legacy/**", "src/legacy/old.st"));
+        assert!(glob("./types/?.typ", "types/a.typ"));
+        assert!(!glob("**/*.st", "main.typ"));
+    }
+
+    #[test]
+    fn test_loads_libraries_before_the_project() {
+        let dir = std::env::temp_dir().join(format!("strooct-manifest-{}", std::process::id()));
+        let write = |path: &str, text: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        };
+        write(
+            "app/strooct.toml",
+            "[dependencies]\nlib = { path = \"../lib\" }\n[defines]\nSIM = true\n[sources]\nexclude = [\"old/**\"]",
+        );
+        write(
+            "app/src/main.st",
+            "PROGRAM Main VAR x : INT; END_VAR\n{IF defined(SIM)} x := Twice(x); {ELSE} x := Missing(); {END_IF}\nEND_PROGRAM",
+        );
+        write("app/old/broken.st", "PROGRAM");
+        write("lib/strooct.toml", "[project]\nname = \"lib\"");
+        write(
+            "lib/twice.st",
+            "FUNCTION Twice : INT VAR_INPUT x : INT; END_VAR Twice := x * 2; END_FUNCTION",
+        );
+
+        let manifest = Manifest::load(&dir.join("app")).unwrap();
+        let db = manifest.sources().unwrap();
+        let names = db
+            .files()
+            .map(|(_, f)| f.name.rsplit(['/', '\\']).next().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["twice.st", "main.st"]);
+        let project = crate::project::Project::analyze(db).unwrap();
+        assert!(!project.has_errors(), "{:?}", project.diagnostics);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+use std::ops::Range;
+
+use crate::{
+    parsing::{
+        ast::{Block, CaseLabel, Expression, Pou, Span, Statement, Statements, VariableBlock},
+        lexer::Lexer,
+        syntax::incremental::TextEdit,
+        token::Token,
+    },
+    project::{FileId, Project, SourceDb},
+    semantic::symbols::is_elementary_type,
+};
+
+pub mod arguments;
+pub mod extract;
+pub mod inline;
+pub mod rename;
+
+/// Changes of the files of a project, each against the text the project was analyzed from.
+///
+/// Edits of one file do not overlap. They only replace the text a refactoring is about, so the
+/// comments and the layout around it stay as they are.
+pub type Edits = Vec<(FileId, TextEdit)>;
+
+/// The texts of the files the edits change, in the order of the files.
+pub fn apply(sources: &SourceDb, edits: &Edits) -> Vec<(FileId, String)> {
+    sources
+        .files()
+        .filter_map(|(id, file)| {
+            let mut changes = edits
+                .iter()
+                .filter(|(file, _)| *file == id)
+                .map(|(_, edit)| edit)
+                .collect::<Vec<_>>();
+            if changes.is_empty() {
+                return None;
+            }
+            // From the end, so the ranges of the edits still to apply stay where they are
+            changes.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+            let text = changes
+                .into_iter()
+                .fold(file.text.clone(), |text, edit| edit.apply(&text));
+            Some((id, text))
+        })
+        .collect()
+}
+
+/// Analyzes the project again with the edits applied, which fails on syntax errors and on errors
+/// the project did not have before.
+fn reanalyze(project: &Project, edits: &Edits) -> Result<Project, String> {
+    let mut texts = apply(&project.sources, edits).into_iter().peekable();
+    let mut sources = SourceDb::new();
+    sources.set_defines(project.sources.defines().to_vec());
+    for (id, file) in project.sources.files() {
+        let text = match texts.next_if(|(edited, _)| *edited == id) {
+            Some((_, text)) => text,
+            None => file.text.clone(),
+        };
+        sources.add(file.name.clone(), text);
+    }
+
+    let after = Project::analyze(sources).map_err(|errors| {
+        format!(
+            "The change breaks the syntax: {}",
+            errors.first().map_or("", String::as_str)
+        )
+    })?;
+    let mut known = project
+        .diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|d| d.message.as_str())
+        .collect::<Vec<_>>();
+    for error in after.diagnostics.iter().filter(|d| d.is_error()) {
+        match known.iter().position(|m| *m == error.message) {
+            Some(index) => {
+                known.swap_remove(index);
+            }
+            None => return Err(format!("The change causes an error: {}", error.message)),
+        }
+    }
+    Ok(after)
+}
+
+/// The range of a span of the project within its file.
+fn local_range(sources: &SourceDb, span: Span) -> (FileId, Range<usize>) {
+    let (id, local) = sources.local(span);
+    (id, local.pos..local.end())
+}
+
+/// Whether a text is a single identifier that names nothing of the language itself.
+fn is_identifier(name: &str) -> bool {
+    let mut tokens = Lexer::create("", name);
+    match (tokens.next(), tokens.next()) {
+        (Some(only), None) => {
+            matches!(only.token, Token::Identifier(text) if text == name)
+                && !is_elementary_type(name)
+        }
+        _ => false,
+    }
+}
+
+/// Calls the function with every expression in the statements and variable initializers of the
+/// blocks, each expression before the ones within it.
+fn visit_expressions<'a>(blocks: &'a [Block], f: &mut impl FnMut(&'a Expression)) {
+    fn variables<'a>(blocks: &'a [VariableBlock], f: &mut impl FnMut(&'a Expression)) {
+        let initializers = blocks
+            .iter()
+            .flat_map(|b| &b.declarations)
+            .filter_map(|d| d.initializer.as_ref());
+        for initializer in initializers {
+            expression(initializer, f);
+        }
+    }
+
+    fn pou<'a>(p: &'a Pou, f: &mut impl FnMut(&'a Expression)) {
+        variables(&p.variables, f);
+        statements(&p.statements, f);
+        for method in &p.methods {
+            pou(method, f);
+        }
+        for action in &p.actions {
+            statements(&action.statements, f);
+        }
+    }
+
+    fn statements<'a>(list: &'a Statements, f: &mut impl FnMut(&'a Expression)) {
+        for statement in list {
+            match statement {
+                Statement::Empty(_)
+                | Statement::Return(_)
+                | Statement::Exit(_)
+                | Statement::Continue(_) => {}
+                Statement::Expression(x) => expression(x, f),
+                Statement::Assignment(a) => {
+                    expression(&a.target, f);
+                    expression(&a.value, f);
+                }
+                Statement::If(x) => {
+                    for branch in std::iter::once(&x.branch).chain(&x.alt_branches) {
+                        expression(&branch.condition, f);
+                        statements(&branch.statements, f);
+                    }
+                    if let Some(fallback) = &x.fallback {
+                        statements(fallback, f);
+                    }
+                }
+                Statement::Case(x) => {
+                    expression(&x.selector, f);
+                    for branch in &x.branches {
+                        for label in &branch.labels {
+                            match label {
+                                CaseLabel::Value(value) => expression(value, f),
+                                CaseLabel::Range(range) => {
+                                    expression(&range.lower, f);
+                                    expression(&range.upper, f);
+                                }
+                            }
+                        }
+                        statements(&branch.statements, f);
+                    }
+                    if let Some(fallback) = &x.fallback {
+                        statements(fallback, f);
+                    }
+                }
+                Statement::For(x) => {
+                    expression(&x.start, f);
+                    expression(&x.end, f);
+                    if let Some(step) = &x.step {
+                        expression(step, f);
+                    }
+                    statements(&x.statements, f);
+                }
+                Statement::While(x) => {
+                    expression(&x.condition, f);
+                    statements(&x.statements, f);
+                }
+                Statement::Repeat(x) => {
+                    statements(&x.statements, f);
+                    expression(&x.condition, f);
+                }
+            }
+        }
+    }
+
+    fn expression<'a>(e: &'a Expression, f: &mut impl FnMut(&'a Expression)) {
+        f(e);
+        match e {
+            Expression::Literal(..) | Expression::Identifier(_) => {}
+            Expression::Prefix(x) => expression(&x.operand, f),
+            Expression::Infix(x) => {
+                expression(&x.left, f);
+                expression(&x.right, f);
+            }
+            Expression::Deref(target, _) => expression(target, f),
+            Expression::Member(x) => expression(&x.target, f),
+            Expression::Index(x) => {
+                expression(&x.target, f);
+                for index in &x.indices {
+                    expression(index, f);
+                }
+            }
+            Expression::Call(x) => {
+                expression(&x.callee, f);
+                for argument in &x.arguments {
+                    expression(argument.value(), f);
+                }
+            }
+            Expression::TypedLiteral(x) => expression(&x.value, f),
+            Expression::Array(elements, _) => {
+                for element in elements {
+                    expression(element, f);
+                }
+            }
+            Expression::Struct(fields, _) => {
+                for (_, value) in fields {
+                    expression(value, f);
+                }
+            }
+        }
+    }
+
+    for block in blocks {
+        match block {
+            Block::Program(p) | Block::Function(p) | Block::FunctionBlock(p) => pou(p, f),
+            Block::Action(action) => statements(&action.statements, f),
+            Block::GlobalVariables(block) => variables(std::slice::from_ref(block), f),
+            Block::Namespace(namespace) => visit_expressions(&namespace.blocks, f),
+            Block::Type(_) | Block::Configuration(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applies_edits_of_several_files() {
+        let mut sources = SourceDb::new();
+        let a = sources.add("a.st", "x := 1; y := 2;");
+        sources.add("b.st", "unchanged");
+        let c = sources.add("c.st", "z");
+        let edit = |range: Range<usize>, text: &str| TextEdit {
+            range,
+            text: text.to_string(),
+        };
+        let edits = vec![
+            (a, edit(0..1, "first")),
+            (c, edit(1..1, "!")),
+            (a, edit(8..9, "second")),
+        ];
+        assert_eq!(
+            apply(&sources, &edits),
+            [
+                (a, "first := 1; second := 2;".to_string()),
+                (c, "z!".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_accepts_only_plain_identifiers() {
+        assert!(is_identifier("motor_1"));
+        assert!(!is_identifier("END_IF"));
+        assert!(!is_identifier("int"));
+        assert!(!is_identifier("a b"));
+        assert!(!is_identifier("1st"));
+        assert!(!is_identifier(""));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    parsing::{
+        ast::{Argument, CallExpression, Expression, VariableKind},
+        lexer::Lexer,
+        syntax::incremental::TextEdit,
+        token::Token,
+    },
+    project::Project,
+    refactor::{Edits, local_range, reanalyze, visit_expressions},
+    semantic::{
+        symbols::SymbolKind,
+        types::{Type, TypeEnv},
+    },
+};
+
+/// Edits naming the positional arguments of the innermost call around a position of the
+/// project, like `Scale(p, 2.0)` to `Scale(p := p, factor := 2.0)`.
+///
+/// Positional arguments go to the inputs and in-outs of the called POU in the order they are
+/// declared. Only the parameter names are inserted, the arguments and any comments between them
+/// stay as they are.
+pub fn name_arguments(project: &Project, pos: usize) -> Result<Edits, String> {
+    let mut call = None::<&CallExpression>;
+    visit_expressions(&project.ast.blocks, &mut |expression| {
+        if let Expression::Call(x) = expression
+            && x.callee.span().pos <= pos
+            && pos <= x.span.end()
+        {
+            // Calls within the arguments of a call come after it
+            call = Some(x);
+        }
+    });
+    let call = call.ok_or("There is no call here.")?;
+    if !call
+        .arguments
+        .iter()
+        .any(|a| matches!(a, Argument::Positional(_)))
+    {
+        return Err("The call has no positional arguments.".to_string());
+    }
+
+    let callee = match call.callee.as_ref() {
+        Expression::Identifier(name) => name,
+        Expression::Member(x) => &x.member,
+        _ => return Err("Only calls of named POUs have parameters to name.".to_string()),
+    };
+    let table = &project.table;
+    let Some(id) = table.symbol_at(callee.span.pos) else {
+        return Err(format!(
+            "'{}' is no declared POU, standard functions take positional arguments only.",
+            callee.name
+        ));
+    };
+    let pou = match table.symbol(id).kind {
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Program => id,
+        SymbolKind::Variable { .. } => {
+            match TypeEnv::new(table).type_of_symbol(id).dereferenced() {
+                Type::FunctionBlock(fb) => *fb,
+                _ => return Err(format!("'{}' cannot be called.", callee.name)),
+            }
+        }
+        _ => return Err(format!("'{}' has no parameters.", callee.name)),
+    };
+    let parameters = table
+        .symbol(pou)
+        .members
+        .map(|m| table.symbols_in(m))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| {
+            matches!(
+                table.symbol(*p).kind,
+                SymbolKind::Variable {
+                    kind: VariableKind::Input | VariableKind::InOut,
+                    ..
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // The arguments start after the opening parenthesis and after each comma between them
+    let (file, callee_range) = local_range(&project.sources, call.callee.span());
+    let (_, close) = local_range(&project.sources, call.span);
+    let text = &project.sources.file(file).text;
+    let mut starts = Vec::new();
+    let mut depth = 0;
+    let mut expecting = false;
+    for marked in Lexer::create("", &text[callee_range.end..close.start]) {
+        if expecting {
+            starts.push(callee_range.end + marked.span().pos);
+            expecting = false;
+        }
+        match marked.token {
+            Token::LeftParenthesis | Token::LeftBracket => {
+                depth += 1;
+                expecting = depth == 1;
+            }
+            Token::RightParenthesis | Token::RightBracket => depth -= 1,
+            Token::Comma => expecting = depth == 1,
+            _ => {}
+        }
+    }
+
+    let mut edits = Edits::new();
+    let positional = call
+        .arguments
+        .iter()
+        .zip(starts)
+        .filter(|(a, _)| matches!(a, Argument::Positional(_)));
+    for (index, (_, start)) in positional.enumerate() {
+        let Some(parameter) = parameters.get(index) else {
+            return Err(format!(
+                "'{}' takes {} argument(s), but more are given.",
+                callee.name,
+                parameters.len()
+            ));
+        };
+        let edit = TextEdit {
+            range: start..start,
+            text: format!("{} := ", table.symbol(*parameter).name),
+        };
+        edits.push((file, edit));
+    }
+    reanalyze(project, &edits)?;
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::SourceDb, refactor::apply};
+
+    fn named(src: &str, at: &str) -> Result<String, String> {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", src);
+        let project = Project::analyze(sources).unwrap();
+        let edits = name_arguments(&project, src.find(at).unwrap())?;
+        Ok(apply(&project.sources, &edits).remove(0).1)
+    }
+
+    const SRC: &str = "FUNCTION Scale : REAL
+VAR_INPUT value : REAL; END_VAR
+VAR_IN_OUT count : INT; END_VAR
+VAR_INPUT factor : REAL; END_VAR
+Scale := value * factor;
+END_FUNCTION
+FUNCTION_BLOCK Motor VAR_INPUT speed : REAL; END_VAR END_FUNCTION_BLOCK
+PROGRAM Main
+VAR m : Motor; n : INT; r : REAL; a : ARRAY[0..1] OF REAL; END_VAR
+r := Scale((r + 1.0) * 2.0, n, (* half *) Scale(a[0], n, 0.5));
+m(Scale(r, n, factor := 1.0));
+r := MAX(r, 1.0);
+END_PROGRAM";
+
+    #[test]
+    fn test_names_positional_arguments() {
+        assert_eq!(
+            named(SRC, "Scale((r").unwrap().lines().nth(9),
+            Some(
+                "r := Scale(value := (r + 1.0) * 2.0, count := n, factor := (* half *) Scale(a[0], n, 0.5));"
+            )
+        );
+        assert_eq!(
+            named(SRC, "Scale(a[0]").unwrap().lines().nth(9),
+            Some(
+                "r := Scale((r + 1.0) * 2.0, n, (* half *) Scale(value := a[0], count := n, factor := 0.5));"
+            )
+        );
+        assert_eq!(
+            named(SRC, "m(").unwrap().lines().nth(10),
+            Some("m(speed := Scale(r, n, factor := 1.0));")
+        );
+        assert_eq!(
+            named(SRC, "r, n, factor").unwrap().lines().nth(10),
+            Some("m(Scale(value := r, count := n, factor := 1.0));")
+        );
+    }
+
+    #[test]
+    fn test_refuses_calls_without_parameters_to_name() {
+        assert_eq!(
+            named(SRC, "MAX"),
+            Err(
+                "'MAX' is no declared POU, standard functions take positional arguments only."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            named(SRC, "r := MAX").map(|_| ()),
+            Err("There is no call here.".to_string())
+        );
+        let extra = SRC.replace("m(Scale", "m(1.0, Scale");
+        assert_eq!(
+            named(&extra, "m("),
+            Err("'m' takes 1 argument(s), but more are given.".to_string())
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::ops::Range;
+
+use crate::{
+    parsing::{
+        ast::Span,
+        formatter::INDENT,
+        syntax::{SyntaxKind, SyntaxNode, incremental::TextEdit},
+        token::Token,
+    },
+    project::Project,
+    refactor::{Edits, is_identifier, local_range, reanalyze},
+};
+
+/// What extracted statements become.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extraction {
+    Action,
+    Method,
+}
+
+/// Edits moving the statements in a range of the project into a new action or method of the
+/// PROGRAM or FUNCTION_BLOCK they are in, and calling it in their place.
+///
+/// The range must cover whole statements of one statement list. Their text moves as it is,
+/// comments included, only indented for its new place at the end of the POU. Statements that
+/// return, or exit or continue a loop they leave behind, cannot move, nor can statements using
+/// variables of a method or action they are in, which the new one cannot see.
+pub fn extract(
+    project: &Project,
+    range: Range<usize>,
+    kind: Extraction,
+    name: &str,
+) -> Result<Edits, String> {
+    if !is_identifier(name) {
+        return Err(format!("'{name}' is not a valid name."));
+    }
+    let span = Span {
+        pos: range.start,
+        len: range.len(),
+        ..Span::default()
+    };
+    let (file, range) = local_range(&project.sources, span);
+    let text = &project.sources.file(file).text;
+    let Some(selected) = text.get(range.clone()) else {
+        return Err("The range must lie within one file.".to_string());
+    };
+    let start = range.start + (selected.len() - selected.trim_start().len());
+    let end = start + selected.trim().len();
+
+    let tree = SyntaxNode::parse(text);
+    let statements = statements(&tree, start..end)
+        .ok_or("Select whole statements of one statement list to extract.")?;
+    let moved = statements[0].range().start..statements[statements.len() - 1].range().end;
+    let owner = statements[0]
+        .ancestors()
+        .filter(|n| n.kind() == SyntaxKind::Pou)
+        .last();
+    let keyword = |node: &SyntaxNode| {
+        let tokens = node.tokens();
+        let first = tokens.into_iter().find(|t| !t.kind().is_trivia());
+        first.map(|t| t.text().to_ascii_uppercase())
+    };
+    let owner = owner
+        .filter(|o| matches!(keyword(o).as_deref(), Some("PROGRAM" | "FUNCTION_BLOCK")))
+        .ok_or("Only statements of a PROGRAM or FUNCTION_BLOCK can move into its own action or method.")?;
+
+    for token in statements.iter().flat_map(SyntaxNode::tokens) {
+        match token.token() {
+            Some(Token::Return) => {
+                return Err(
+                    "The statements RETURN, which would only leave the new one.".to_string()
+                );
+            }
+            Some(Token::Exit | Token::Continue) => {
+                let in_loop = token
+                    .parent()
+                    .ancestors()
+                    .take_while(|n| n.range().start >= moved.start)
+                    .any(|n| {
+                        matches!(
+                            n.kind(),
+                            SyntaxKind::For | SyntaxKind::While | SyntaxKind::Repeat
+                        )
+                    });
+                if !in_loop {
+                    return Err(format!(
+                        "The statements {} a loop around them.",
+                        token.text().to_ascii_uppercase()
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let leading = |pos: usize| {
+        let line = &text[text[..pos].rfind('\n').map_or(0, |i| i + 1)..pos];
+        match line.trim().is_empty() {
+            true => line,
+            false => &line[..line.len() - line.trim_start().len()],
+        }
+    };
+    let tokens = owner.tokens();
+    let end_keyword = tokens.iter().rfind(|t| !t.kind().is_trivia()).unwrap();
+    let at = end_keyword.range().start;
+    let (base, outer) = (leading(moved.start), leading(at));
+    let unit = match base.starts_with('\t') || outer.starts_with('\t') {
+        true => "\t".to_string(),
+        false => " ".repeat(INDENT),
+    };
+    let member = format!("{outer}{unit}");
+    let body = text[moved.clone()]
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let line = match i {
+                0 => line,
+                _ => line.strip_prefix(base).unwrap_or(line),
+            };
+            match line.trim().is_empty() {
+                true => String::new(),
+                false => format!("{member}{unit}{line}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (header, footer) = match kind {
+        Extraction::Action => (format!("ACTION {name}:"), "END_ACTION"),
+        Extraction::Method => (format!("METHOD {name}"), "END_METHOD"),
+    };
+    let declaration = format!("\n{member}{header}\n{body}\n{member}{footer}\n");
+    let (at, declaration) =
+        match text[..at].ends_with(outer) && text[..at - outer.len()].ends_with('\n') {
+            true => (at - outer.len(), declaration),
+            false => (at, format!("{declaration}{outer}")),
+        };
+
+    let edits = vec![
+        (
+            file,
+            TextEdit {
+                range: moved,
+                text: format!("{name}();"),
+            },
+        ),
+        (
+            file,
+            TextEdit {
+                range: at..at,
+                text: declaration,
+            },
+        ),
+    ];
+    reanalyze(project, &edits)?;
+    Ok(edits)
+}
+
+/// The statements of one statement list a range covers, which holds no other tokens.
+fn statements(tree: &SyntaxNode, range: Range<usize>) -> Option<Vec<SyntaxNode>> {
+    if range.is_empty() {
+        return None;
+    }
+    let overlaps = |r: Range<usize>| r.start < range.end && range.start < r.end;
+    let token = tree.token_at(range.start)?;
+    for node in token.parent().ancestors() {
+        let statements = node
+            .child_nodes()
+            .filter(|c| c.kind() == SyntaxKind::Statement && overlaps(c.range()))
+            .collect::<Vec<_>>();
+        if statements.is_empty() {
+            continue;
+        }
+        let covered = |r: Range<usize>| {
+            statements
+                .iter()
+                .any(|s| s.range().start <= r.start && r.end <= s.range().end)
+        };
+        let whole = statements
+            .iter()
+            .all(|s| range.start <= s.range().start && s.range().end <= range.end);
+        let alone = tree
+            .tokens()
+            .into_iter()
+            .filter(|t| !t.kind().is_trivia() && overlaps(t.range()))
+            .all(|t| covered(t.range()));
+        if whole && alone {
+            return Some(statements);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::SourceDb, refactor::apply};
+
+    const SRC: &str = "FUNCTION_BLOCK Motor
+    VAR speed : INT; running : BOOL; i : INT; END_VAR
+    METHOD Stop
+        VAR_INPUT hard : BOOL; END_VAR
+        speed := 0;
+        running := hard;
+    END_METHOD
+    IF running THEN
+        // ramp up
+        speed := speed + 1;
+        IF speed > 100 THEN
+            speed := 100; (* limit *)
+        END_IF;
+    END_IF;
+    FOR i := 0 TO 3 DO
+        IF i = 2 THEN EXIT; END_IF;
+    END_FOR;
+    RETURN;
+END_FUNCTION_BLOCK
+FUNCTION Twice : INT VAR_INPUT x : INT; END_VAR Twice := x * 2; END_FUNCTION
+";
+
+    fn extracted(from: &str, to: &str, kind: Extraction, name: &str) -> Result<String, String> {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", SRC);
+        let project = Project::analyze(sources).unwrap();
+        let range = SRC.find(from).unwrap()..SRC.find(to).unwrap() + to.len();
+        let edits = extract(&project, range, kind, name)?;
+        Ok(apply(&project.sources, &edits).remove(0).1)
+    }
+
+    #[test]
+    fn test_moves_statements_with_their_comments() {
+        let text = extracted(
+            "// ramp up",
+            "(* limit *)\n        END_IF;",
+            Extraction::Action,
+            "Ramp",
+        );
+        assert_eq!(
+            text.unwrap(),
+            SRC.replace(
+                "// ramp up
+        speed := speed + 1;
+        IF speed > 100 THEN
+            speed := 100; (* limit *)
+        END_IF;",
+                "// ramp up
+        Ramp();"
+            )
+            .replace(
+                "    RETURN;\n",
+                "    RETURN;
+
+    ACTION Ramp:
+        speed := speed + 1;
+        IF speed > 100 THEN
+            speed := 100; (* limit *)
+        END_IF;
+    END_ACTION
+"
+            )
+        );
+
+        let text = extracted("speed := 0;", "speed := 0;", Extraction::Method, "Halt");
+        let text = text.unwrap();
+        assert!(
+            text.contains("        Halt();\n        running := hard;"),
+            "{text}"
+        );
+        assert!(text.contains(
+            "\n    METHOD Halt\n        speed := 0;\n    END_METHOD\nEND_FUNCTION_BLOCK"
+        ));
+    }
+
+    #[test]
+    fn test_refuses_statements_that_cannot_move() {
+        let refused = |from, to| extracted(from, to, Extraction::Action, "Part").unwrap_err();
+        assert_eq!(
+            refused("speed + 1", "speed + 1"),
+            "Select whole statements of one statement list to extract."
+        );
+        assert_eq!(
+            refused("speed := speed", "END_IF;\n    END_IF;"),
+            "Select whole statements of one statement list to extract."
+        );
+        assert_eq!(
+            refused("EXIT;", "EXIT;"),
+            "The statements EXIT a loop around them."
+        );
+        assert!(
+            extracted(
+                "IF i = 2",
+                "END_IF;\n    END_FOR",
+                Extraction::Action,
+                "Part"
+            )
+            .is_err()
+        );
+        assert!(extracted("FOR i", "END_FOR;", Extraction::Action, "Part").is_ok());
+        assert_eq!(
+            refused("RETURN;", "RETURN;"),
+            "The statements RETURN, which would only leave the new one."
+        );
+        assert_eq!(
+            refused("Twice := x * 2;", "Twice := x * 2;"),
+            "Only statements of a PROGRAM or FUNCTION_BLOCK can move into its own action or method."
+        );
+        assert!(
+            refused("running := hard;", "running := hard;")
+                .starts_with("The change causes an error: ")
+        );
+        assert!(
+            extracted("speed := 0;", "speed := 0;", Extraction::Method, "speed")
+                .unwrap_err()
+                .starts_with("The change causes an error: ")
+        );
+    }
+}
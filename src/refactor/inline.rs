@@ -0,0 +1,276 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    parsing::{
+        ast::{Expression, VariableKind},
+        syntax::{SyntaxKind, SyntaxNode, SyntaxToken, incremental::TextEdit},
+        token::Token,
+    },
+    project::{FileId, Project},
+    refactor::{Edits, local_range, reanalyze, visit_expressions},
+    semantic::symbols::SymbolKind,
+};
+
+/// Edits replacing every use of the constant at a position of the project by its value, and
+/// removing its declaration.
+///
+/// The value is copied as it is written in the declaration, in parentheses unless it is a single
+/// literal or name. Uses through a namespace, like `Limits.max`, are replaced as a whole. The
+/// declaration, and the VAR_EXTERNAL declarations of a global constant, are only removed if they
+/// declare no other names, and leave the comments on their lines behind.
+pub fn inline_constant(project: &Project, pos: usize) -> Result<Edits, String> {
+    let table = &project.table;
+    let mut id = table.symbol_at(pos).ok_or("There is no constant here.")?;
+    let symbol = table.symbol(id);
+    if !matches!(symbol.kind, SymbolKind::Variable { constant: true, .. }) {
+        return Err(format!("'{}' is not a constant.", symbol.name));
+    }
+    // A VAR_EXTERNAL declaration stands for the global it refers to
+    if let SymbolKind::Variable {
+        kind: VariableKind::External,
+        ..
+    } = symbol.kind
+        && let Some(global) = table
+            .references
+            .iter()
+            .find(|r| r.span.pos == symbol.span.pos)
+    {
+        id = global.symbol;
+    }
+    let symbol = table.symbol(id);
+
+    let mut trees = HashMap::new();
+    let mut declaration_at = |file: FileId, range: &Range<usize>| {
+        let tree = trees
+            .entry(file)
+            .or_insert_with(|| SyntaxNode::parse(&project.sources.file(file).text));
+        let parent = tree.token_at(range.start)?.parent();
+        parent
+            .ancestors()
+            .find(|n| n.kind() == SyntaxKind::Declaration)
+    };
+    let (file, range) = local_range(&project.sources, symbol.span);
+    let declaration = declaration_at(file, &range).ok_or("The constant has no declaration.")?;
+    let tokens = language_tokens(&declaration);
+    let value = tokens
+        .iter()
+        .position(|t| t.text() == ":=")
+        .map(|assign| &tokens[assign + 1..tokens.len() - 1])
+        .filter(|value| !value.is_empty())
+        .ok_or(format!("'{}' has no value to inline.", symbol.name))?;
+    let text = &project.sources.file(file).text
+        [value[0].range().start..value[value.len() - 1].range().end];
+    let value = match atomic(value) {
+        true => text.to_string(),
+        false => format!("({text})"),
+    };
+
+    let mut declarations = vec![(file, declaration)];
+    let mut uses = Vec::new();
+    for reference in table.references_to(id) {
+        let (file, range) = local_range(&project.sources, reference.span);
+        let Some(external) = declaration_at(file, &range) else {
+            uses.push(reference.span);
+            continue;
+        };
+        // Within the POU of a VAR_EXTERNAL declaration the names refer to it
+        declarations.push((file, external));
+        let local = table
+            .symbols
+            .iter()
+            .position(|s| s.span.pos == reference.span.pos);
+        if let Some(local) = local {
+            uses.extend(table.references_to(local).map(|r| r.span));
+        }
+    }
+
+    let mut edits = Edits::new();
+    visit_expressions(&project.ast.blocks, &mut |expression| {
+        if let Expression::Member(x) = expression
+            && let Some(index) = uses.iter().position(|u| u.pos == x.member.span.pos)
+        {
+            let (file, range) = local_range(&project.sources, expression.span());
+            edits.push((
+                file,
+                TextEdit {
+                    range,
+                    text: value.clone(),
+                },
+            ));
+            uses.swap_remove(index);
+        }
+    });
+    for span in uses {
+        let (file, range) = local_range(&project.sources, span);
+        edits.push((
+            file,
+            TextEdit {
+                range,
+                text: value.clone(),
+            },
+        ));
+    }
+
+    let single = declarations.iter().all(|(_, declaration)| {
+        let tokens = language_tokens(declaration);
+        let names = tokens.iter().take_while(|t| t.text() != ":");
+        names.filter(|t| t.kind() == SyntaxKind::Identifier).count() == 1
+    });
+    if single {
+        for (file, declaration) in declarations {
+            let text = &project.sources.file(file).text;
+            let tokens = language_tokens(&declaration);
+            let range = tokens[0].range().start..tokens[tokens.len() - 1].range().end;
+            edits.push((
+                file,
+                TextEdit {
+                    range: removal(text, range),
+                    text: String::new(),
+                },
+            ));
+        }
+    }
+    reanalyze(project, &edits)?;
+    Ok(edits)
+}
+
+fn language_tokens(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    let tokens = node.tokens().into_iter();
+    tokens.filter(|t| !t.kind().is_trivia()).collect()
+}
+
+/// Whether a value needs no parentheses wherever it is used: a single literal or name, a typed
+/// literal or a value in parentheses already.
+fn atomic(value: &[SyntaxToken]) -> bool {
+    match value {
+        [_] => true,
+        [prefix, _] => matches!(prefix.token(), Some(Token::LiteralPrefix(_))),
+        [open, .., close] if open.text() == "(" && close.text() == ")" => {
+            let mut depth = 0;
+            value.iter().enumerate().all(|(i, t)| {
+                match t.text() {
+                    "(" => depth += 1,
+                    ")" => depth -= 1,
+                    _ => {}
+                }
+                depth > 0 || i == value.len() - 1
+            })
+        }
+        _ => false,
+    }
+}
+
+/// The range to remove for a declaration: its whole line if nothing else is on it, otherwise the
+/// declaration and the blanks after it.
+fn removal(text: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i + 1);
+    let after = &text[range.end..line_end];
+    match text[line_start..range.start].trim().is_empty() && after.trim().is_empty() {
+        true => line_start..line_end,
+        false => range.start..range.end + after.len() - after.trim_start_matches([' ', '\t']).len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::SourceDb, refactor::apply};
+
+    const GLOBALS: &str = "VAR_GLOBAL CONSTANT
+    limit : INT := 10 * 2; // twenty
+    low, high : INT := 1;
+END_VAR
+NAMESPACE Cfg
+    VAR_GLOBAL CONSTANT
+        gain : REAL := REAL#1.5;
+    END_VAR
+END_NAMESPACE
+";
+    const MAIN: &str = "PROGRAM Main
+VAR_EXTERNAL CONSTANT limit : INT; END_VAR
+VAR CONSTANT
+    step : INT := (2);
+END_VAR
+VAR n : INT; r : REAL; END_VAR
+n := limit / step; (* scaled *)
+r := Cfg.gain * n;
+n := low + high;
+END_PROGRAM
+";
+
+    fn inlined(name: &str) -> Result<Vec<String>, String> {
+        let mut sources = SourceDb::new();
+        sources.add("globals.st", GLOBALS);
+        sources.add("main.st", MAIN);
+        let project = Project::analyze(sources).unwrap();
+        let pos = GLOBALS
+            .find(name)
+            .or_else(|| {
+                MAIN.find(name)
+                    .map(|p| p + project.sources.file(FileId(1)).origin.0)
+            })
+            .unwrap();
+        let edits = inline_constant(&project, pos)?;
+        let edited = apply(&project.sources, &edits);
+        let texts = project.sources.files().map(|(id, f)| {
+            edited
+                .iter()
+                .find(|(e, _)| *e == id)
+                .map_or(f.text.clone(), |(_, text)| text.clone())
+        });
+        Ok(texts.collect())
+    }
+
+    #[test]
+    fn test_inlines_values_and_removes_declarations() {
+        let texts = inlined("limit").unwrap();
+        assert_eq!(
+            texts[0],
+            GLOBALS.replace("limit : INT := 10 * 2; // twenty", "// twenty")
+        );
+        assert_eq!(
+            texts[1],
+            MAIN.replace("CONSTANT limit : INT; END_VAR", "CONSTANT END_VAR")
+                .replace("n := limit / step;", "n := (10 * 2) / step;")
+        );
+        assert_eq!(inlined("limit / step").unwrap(), texts);
+
+        let texts = inlined("step").unwrap();
+        assert_eq!(
+            texts[1],
+            MAIN.replace("    step : INT := (2);\n", "")
+                .replace("n := limit / step;", "n := limit / (2);")
+        );
+
+        let texts = inlined("gain").unwrap();
+        assert_eq!(
+            texts[0],
+            GLOBALS.replace("        gain : REAL := REAL#1.5;\n", "")
+        );
+        assert!(texts[1].contains("r := REAL#1.5 * n;"));
+
+        // Declared together with another name, the declaration stays
+        let texts = inlined("high").unwrap();
+        assert_eq!(texts[0], GLOBALS);
+        assert!(texts[1].contains("n := low + 1;"));
+    }
+
+    #[test]
+    fn test_refuses_what_is_not_a_constant() {
+        assert_eq!(
+            inlined("n : INT"),
+            Err("'n' is not a constant.".to_string())
+        );
+        assert_eq!(
+            inlined("Main"),
+            Err("'Main' is not a constant.".to_string())
+        );
+        assert_eq!(
+            inlined("VAR_GLOBAL"),
+            Err("There is no constant here.".to_string())
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    parsing::{
+        ast::{Block, Span},
+        syntax::incremental::TextEdit,
+    },
+    project::Project,
+    refactor::{Edits, is_identifier, local_range, reanalyze},
+    semantic::symbols::{ScopeId, SymbolId, SymbolKind, SymbolTable},
+};
+
+/// Edits renaming the symbol declared or used at a position of the project, at its declaration
+/// and at every use in all files.
+///
+/// Names match case-insensitively, so uses spelled in another case are renamed too. Renaming
+/// fails if the new name is not an identifier, is declared in the same scope already, or makes
+/// any name of the project refer to something else, like a local variable of the new name that
+/// would hide a renamed global.
+pub fn rename(project: &Project, pos: usize, name: &str) -> Result<Edits, String> {
+    let table = &project.table;
+    let id = table
+        .symbol_at(pos)
+        .ok_or("There is no declared name to rename here.")?;
+    let symbol = table.symbol(id);
+    if !is_identifier(name) {
+        return Err(format!("'{name}' is not a valid name."));
+    }
+    if let Some(existing) = table.lookup_local(symbol.scope, name)
+        && existing != id
+    {
+        return Err(format!(
+            "'{}' is already declared in the scope of '{}'.",
+            table.symbol(existing).name,
+            symbol.name
+        ));
+    }
+
+    let mut spans = std::iter::once(symbol.span)
+        .chain(table.references_to(id).map(|r| r.span))
+        .collect::<Vec<_>>();
+    if symbol.kind == SymbolKind::Namespace {
+        reopened(
+            &project.ast.blocks,
+            table,
+            SymbolTable::GLOBAL,
+            id,
+            &mut spans,
+        );
+    }
+    spans.sort_by_key(|s| s.pos);
+    spans.dedup_by_key(|s| s.pos);
+    let edits = spans
+        .into_iter()
+        .map(|span| {
+            let (file, range) = local_range(&project.sources, span);
+            let text = name.to_string();
+            (file, TextEdit { range, text })
+        })
+        .collect::<Edits>();
+
+    let after = reanalyze(project, &edits)?;
+    // Each use must refer to the same declaration as before, found where the edits moved both
+    let moved = |pos: usize| {
+        let (file, local) = local_range(
+            &project.sources,
+            Span {
+                pos,
+                ..Span::default()
+            },
+        );
+        let shift = edits
+            .iter()
+            .filter(|(f, e)| *f == file && e.range.end <= local.start)
+            .map(|(_, e)| e.text.len() as isize - e.range.len() as isize)
+            .sum::<isize>();
+        after.sources.file(file).origin.0 + local.start.saturating_add_signed(shift)
+    };
+    let bindings = |table: &SymbolTable, moved: &dyn Fn(usize) -> usize| {
+        let mut bindings = table
+            .references
+            .iter()
+            .map(|r| {
+                (
+                    moved(r.span.pos),
+                    moved(table.symbol(r.symbol).span.pos),
+                    r.span,
+                )
+            })
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|(pos, declaration, _)| (*pos, *declaration));
+        bindings.dedup_by_key(|(pos, declaration, _)| (*pos, *declaration));
+        bindings
+    };
+    let before = bindings(table, &moved);
+    let now = bindings(&after.table, &|pos| pos);
+    let changed = before
+        .iter()
+        .zip(&now)
+        .find(|(b, n)| (b.0, b.1) != (n.0, n.1))
+        .map(|(_, n)| (&after, n.2))
+        .or_else(|| match before.len().cmp(&now.len()) {
+            std::cmp::Ordering::Less => now.get(before.len()).map(|n| (&after, n.2)),
+            std::cmp::Ordering::Greater => before.get(now.len()).map(|b| (project, b.2)),
+            std::cmp::Ordering::Equal => None,
+        });
+    if let Some((changed, span)) = changed {
+        let (file, local) = changed.sources.local(span);
+        return Err(format!(
+            "Renaming '{}' to '{name}' changes what the name in {} at line {} refers to.",
+            symbol.name,
+            changed.sources.file(file).name,
+            local.line + 1
+        ));
+    }
+    Ok(edits)
+}
+
+/// Adds the names of the namespace blocks that open a namespace again, which are no uses the
+/// resolver records.
+fn reopened(
+    blocks: &[Block],
+    table: &SymbolTable,
+    scope: ScopeId,
+    namespace: SymbolId,
+    spans: &mut Vec<Span>,
+) {
+    for block in blocks {
+        let Block::Namespace(block) = block else {
+            continue;
+        };
+        let mut inner = Some(scope);
+        for part in &block.name.parts {
+            let id = inner.and_then(|s| table.lookup_local(s, &part.name));
+            if id == Some(namespace) {
+                spans.push(part.span);
+            }
+            inner = id.and_then(|id| table.symbol(id).members);
+        }
+        if let Some(inner) = inner {
+            reopened(&block.blocks, table, inner, namespace, spans);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project::SourceDb, refactor::apply};
+
+    fn project(files: &[&str]) -> Project {
+        let mut sources = SourceDb::new();
+        for (i, text) in files.iter().enumerate() {
+            sources.add(format!("{i}.st"), *text);
+        }
+        Project::analyze(sources).unwrap()
+    }
+
+    fn renamed(project: &Project, at: &str, name: &str) -> Result<Vec<String>, String> {
+        let (_, file) = project
+            .sources
+            .files()
+            .find(|(_, f)| f.text.contains(at))
+            .unwrap();
+        let edits = rename(project, file.origin.0 + file.text.find(at).unwrap(), name)?;
+        let edited = apply(&project.sources, &edits);
+        let texts = project.sources.files().map(|(id, f)| {
+            edited
+                .iter()
+                .find(|(e, _)| *e == id)
+                .map_or(f.text.clone(), |(_, text)| text.clone())
+        });
+        Ok(texts.collect())
+    }
+
+    #[test]
+    fn test_renames_across_files_in_any_case() {
+        let project = project(&[
+            "TYPE Mode : (Off, Run); END_TYPE
+VAR_GLOBAL speed : INT; END_VAR
+NAMESPACE Lib FUNCTION Half : INT VAR_INPUT x : INT; END_VAR Half := x / 2; END_FUNCTION END_NAMESPACE",
+            "NAMESPACE Lib FUNCTION Twice : INT VAR_INPUT x : INT; END_VAR Twice := x * 2; END_FUNCTION END_NAMESPACE
+PROGRAM Main
+VAR m : MODE := Mode#Run; END_VAR
+(* keep SPEED *) SPEED := lib.Half(x := Speed) + Lib.Twice(2);
+m := off;
+END_PROGRAM",
+        ]);
+        let texts = renamed(&project, "speed", "velocity").unwrap();
+        assert!(texts[0].contains("VAR_GLOBAL velocity : INT;"));
+        assert!(texts[1].contains("(* keep SPEED *) velocity := lib.Half(x := velocity)"));
+
+        let texts = renamed(&project, "Off", "Stopped").unwrap();
+        assert!(texts[0].starts_with("TYPE Mode : (Stopped, Run);"));
+        assert!(texts[1].contains("m := Stopped;"));
+
+        let texts = renamed(&project, "x : INT", "value").unwrap();
+        assert!(texts[0].contains("VAR_INPUT value : INT; END_VAR Half := value / 2;"));
+        assert!(texts[1].contains("Half(value := Speed)"));
+        assert!(texts[1].contains("VAR_INPUT x : INT; END_VAR Twice := x * 2;"));
+
+        let texts = renamed(&project, "Lib", "Library").unwrap();
+        assert!(texts[0].contains("NAMESPACE Library FUNCTION Half"));
+        assert!(texts[1].starts_with("NAMESPACE Library FUNCTION Twice"));
+        assert!(texts[1].contains("Library.Half(x := Speed) + Library.Twice(2)"));
+    }
+
+    #[test]
+    fn test_renames_within_configurations() {
+        let project = project(&[
+            "PROGRAM Main VAR_EXTERNAL speed : INT; END_VAR speed := speed + 1; END_PROGRAM",
+            "CONFIGURATION Plant VAR_GLOBAL speed : INT; END_VAR
+RESOURCE Cpu ON PLC
+TASK Fast(INTERVAL := T#10ms);
+PROGRAM Main WITH Fast : Main;
+END_RESOURCE
+END_CONFIGURATION",
+        ]);
+        let texts = renamed(&project, "Main VAR", "Control").unwrap();
+        assert!(texts[0].starts_with("PROGRAM Control VAR_EXTERNAL"));
+        assert!(texts[1].contains("PROGRAM Main WITH Fast : Control;"));
+
+        let texts = renamed(&project, "Fast(", "Quick").unwrap();
+        assert!(texts[1].contains("TASK Quick(INTERVAL := T#10ms);"));
+        assert!(texts[1].contains("PROGRAM Main WITH Quick : Main;"));
+    }
+
+    #[test]
+    fn test_refuses_names_that_clash_or_change_bindings() {
+        let project = project(&["VAR_GLOBAL limit : INT; END_VAR
+FUNCTION_BLOCK Motor
+VAR speed : INT; torque : INT; END_VAR
+speed := limit;
+END_FUNCTION_BLOCK"]);
+        assert_eq!(
+            renamed(&project, "speed", "Torque"),
+            Err("'torque' is already declared in the scope of 'speed'.".to_string())
+        );
+        assert_eq!(
+            renamed(&project, "speed", "limit"),
+            Err(
+                "Renaming 'speed' to 'limit' changes what the name in 0.st at line 4 refers to."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            renamed(&project, "speed", "END_IF"),
+            Err("'END_IF' is not a valid name.".to_string())
+        );
+        assert!(renamed(&project, "VAR_GLOBAL", "x").is_err());
+        assert!(renamed(&project, "speed", "SPEED").is_ok());
+    }
+}
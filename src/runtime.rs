@@ -0,0 +1,88 @@
+use crate::{diagnostic::Diagnostic, runtime::value::Value, semantic::symbols::SymbolId};
+
+pub mod bytecode;
+pub mod compiler;
+pub mod interpreter;
+pub mod library;
+pub mod scheduler;
+pub mod testing;
+pub mod value;
+pub mod vm;
+
+/// Deepest nesting of calls the engines run, deeper recursion is reported as a stack overflow.
+pub const MAX_CALL_DEPTH: usize = 256;
+
+/// Instructions of the VM, or statements of the interpreter, one scan cycle may run before it is
+/// stopped like a watchdog stops a PLC stuck in a loop.
+pub const CYCLE_BUDGET: u64 = 10_000_000;
+
+/// The engine programs run on.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EngineKind {
+    /// The bytecode VM, falling back to the interpreter for what it cannot compile
+    #[default]
+    Vm,
+    Interpreter,
+}
+
+/// Common interface of the [`Interpreter`](interpreter::Interpreter) and the [`Vm`](vm::Vm).
+pub trait Engine {
+    /// Programs in declaration order.
+    fn programs(&self) -> Vec<SymbolId>;
+
+    /// Executes one scan cycle of a program.
+    fn run_cycle(&mut self, program: SymbolId) -> Result<(), Diagnostic>;
+
+    /// Reads a variable by a path like `Main.counter`, `Main.timer.Q` or a global name.
+    fn read(&self, path: &str) -> Option<Value>;
+
+    /// Overwrites a variable by its path, converting the value to the type of the variable.
+    fn write(&mut self, path: &str, value: Value) -> Option<()>;
+
+    /// Sets the virtual clock the standard timers read, in nanoseconds.
+    fn set_clock(&mut self, now: u64);
+}
+
+impl Engine for interpreter::Interpreter<'_> {
+    fn programs(&self) -> Vec<SymbolId> {
+        self.programs()
+    }
+
+    fn run_cycle(&mut self, program: SymbolId) -> Result<(), Diagnostic> {
+        self.run_cycle(program)
+    }
+
+    fn read(&self, path: &str) -> Option<Value> {
+        self.read(path)
+    }
+
+    fn write(&mut self, path: &str, value: Value) -> Option<()> {
+        self.write(path, value)
+    }
+
+    fn set_clock(&mut self, now: u64) {
+        self.set_clock(now)
+    }
+}
+
+impl Engine for vm::Vm<'_> {
+    fn programs(&self) -> Vec<SymbolId> {
+        self.programs()
+    }
+
+    fn run_cycle(&mut self, program: SymbolId) -> Result<(), Diagnostic> {
+        self.run_cycle(program)
+    }
+
+    fn read(&self, path: &str) -> Option<Value> {
+        self.read(path)
+    }
+
+    fn write(&mut self, path: &str, value: Value) -> Option<()> {
+        self.write(path, value)
+    }
+
+    fn set_clock(&mut self, now: u64) {
+        self.set_clock(now)
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use crate::{
+    parsing::ast::{InfixOperator, Span},
+    semantic::{library, symbols::SymbolId, types::Elementary},
+};
+
+/// A memory operand relative to one of the base registers of the VM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Address {
+    /// Slot of a global variable or program instance
+    Static(u32),
+    /// Slot within the instance of the function block or program whose code runs
+    This(u32),
+    /// Slot within the frame of the running function or method
+    Local(u32),
+    /// Slot within the frame prepared for the next call, to pass arguments
+    Callee(u32),
+}
+
+impl Address {
+    pub fn offset(self, n: u32) -> Self {
+        match self {
+            Address::Static(a) => Address::Static(a + n),
+            Address::This(a) => Address::This(a + n),
+            Address::Local(a) => Address::Local(a + n),
+            Address::Callee(a) => Address::Callee(a + n),
+        }
+    }
+}
+
+/// Precision of real arithmetic, REAL results are rounded to single precision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+/// How two values are ordered, for the instructions choosing between them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Int,
+    Unsigned,
+    Real,
+    String,
+}
+
+/// Instructions of the stack machine.
+///
+/// Every slot of the memory and the operand stack is a 64 bit word. Integers, bit strings,
+/// durations, dates and enum values are stored as integers, BOOL as 0 or 1, reals as the bits
+/// of an `f64`, strings as an index into the string pool and pointers as absolute addresses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    Const(i64),
+    ConstReal(f64),
+    Pop,
+    Dup,
+    Swap,
+
+    Load(Address),
+    Store(Address),
+    /// Pushes the absolute address of an operand
+    AddressOf(Address),
+    /// Replaces an address by the value it points to
+    LoadIndirect,
+    /// Pops a value and an address and stores the value there
+    StoreIndirect,
+    /// Moves the address on top of the stack by a number of slots
+    Offset(u32),
+    /// Pops an index and the address of an array, pushes the address of the element
+    Index {
+        lower: i64,
+        upper: i64,
+        stride: u32,
+    },
+    /// Pops a source and a destination address and copies a number of slots
+    Copy(u32),
+
+    /// Integer operations which wrap around at the bounds of the type
+    Add(Elementary),
+    Sub(Elementary),
+    Mul(Elementary),
+    Div(Elementary),
+    Mod(Elementary),
+    Neg(Elementary),
+    /// Bitwise operations, which are logical ones for BOOL
+    And,
+    Or,
+    Xor,
+    Not(Elementary),
+
+    AddReal(Precision),
+    SubReal(Precision),
+    MulReal(Precision),
+    DivReal(Precision),
+    PowReal(Precision),
+    NegReal,
+
+    CompareInt(InfixOperator),
+    CompareUnsigned(InfixOperator),
+    CompareReal(InfixOperator),
+    CompareString(InfixOperator),
+    /// Pops two values and pushes the greater or the smaller one
+    Max(Comparison),
+    Min(Comparison),
+    /// Pops two values and a BOOL, pushes the second value if it is TRUE and the first otherwise
+    Select,
+    /// Pops a number of values and an index, pushes the value at the index
+    Choose(u32),
+
+    /// Wraps an integer into the range of a narrower type
+    Wrap(Elementary),
+    IntToReal(Elementary, Precision),
+    /// Truncates a real towards zero
+    RealToInt(Elementary),
+    RoundReal,
+    /// Rounds a real to the nearest integer, ties to even, like the `*_TO_*` conversions do
+    RoundToInt(Elementary),
+    /// Converts a value of an elementary type into its text
+    ToString(Elementary),
+    /// Parses a string into a value of an elementary type, zero if it holds none
+    FromString(Elementary),
+    /// Cuts a string down to a number of characters
+    Truncate(u32),
+
+    /// One of the standard functions on reals from SQRT to ATAN
+    RealFunction(library::Function, Precision),
+    /// SHL, SHR, ROL or ROR of a bit string by an integer
+    Shift(library::Function, Elementary),
+    /// One of the standard functions on strings, CONCAT taking two of them
+    StringFunction(library::Function),
+    /// Runs a standard function block on the variables of the instance, at the virtual clock
+    StandardBlock(library::StandardBlock),
+    /// Checks an assertion of a unit test on operands of the scalar type, failing unless it holds
+    Assert(library::Function, library::Scalar),
+
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    /// Leaves a FOR loop once its variable passed the end, with end and step on the stack
+    ForTest {
+        variable: Address,
+        exit: u32,
+    },
+    /// Adds the step to the variable of a FOR loop, leaving it before the variable overflows
+    ForStep {
+        variable: Address,
+        ty: Elementary,
+        test: u32,
+        exit: u32,
+    },
+
+    /// Allocates and initializes the frame of a function or method, which becomes the callee
+    Enter(u32),
+    /// Runs a function in the callee frame
+    Call(u32),
+    /// Pops the address of an instance and runs a function block, program, method or action on it
+    CallWith(u32),
+    /// Frees the callee frame, optionally pushing the value of its first slot
+    Leave {
+        result: bool,
+    },
+    Return,
+}
+
+/// A compiled POU, action or initializer.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    /// Initialization of the frame, which `Enter` runs, followed by the body at `body`
+    pub code: Vec<Instruction>,
+    /// Source location of every instruction, for runtime errors
+    pub spans: Vec<Span>,
+    pub body: u32,
+    /// Number of slots of the frame, zero for code running on an instance
+    pub frame_size: u32,
+}
+
+/// The compiled form of a whole source.
+#[derive(Debug)]
+pub struct Module {
+    pub functions: Vec<Function>,
+    pub strings: Vec<String>,
+    /// Number of slots holding globals and program instances, slot 0 is the null pointer
+    pub static_size: u32,
+    /// Function initializing globals and program instances
+    pub init: u32,
+    pub globals: HashMap<SymbolId, u32>,
+    /// Address and code of every program instance
+    pub programs: HashMap<SymbolId, (u32, u32)>,
+    /// Offsets of variables within their structure, instance or frame
+    pub offsets: HashMap<SymbolId, u32>,
+}
+
+/// Estimated execution time of instructions on a target, in nanoseconds per instruction class.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostModel {
+    /// Constants and operand stack manipulation
+    pub stack: u64,
+    /// Loads, stores and address arithmetic, per slot for copies
+    pub memory: u64,
+    /// Integer, bitwise and conversion operations
+    pub integer: u64,
+    /// Real arithmetic and comparisons
+    pub real: u64,
+    /// Jumps and loop control
+    pub branch: u64,
+    /// Calls and returns, including frame setup
+    pub call: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            stack: 2,
+            memory: 5,
+            integer: 3,
+            real: 8,
+            branch: 4,
+            call: 40,
+        }
+    }
+}
+
+impl CostModel {
+    /// Estimated execution time of an instruction.
+    pub fn cost(&self, instruction: &Instruction) -> u64 {
+        use Instruction::*;
+
+        match instruction {
+            Const(_) | ConstReal(_) | Pop | Dup | Swap => self.stack,
+            Load(_)
+            | Store(_)
+            | AddressOf(_)
+            | LoadIndirect
+            | StoreIndirect
+            | Offset(_)
+            | Index { .. } => self.memory,
+            Copy(n) => self.memory * (*n as u64).max(1),
+            Add(_) | Sub(_) | Mul(_) | Div(_) | Mod(_) | Neg(_) | And | Or | Xor | Not(_)
+            | CompareInt(_) | CompareUnsigned(_) | Wrap(_) | Truncate(_) | Shift(..) => {
+                self.integer
+            }
+            Max(Comparison::Int | Comparison::Unsigned)
+            | Min(Comparison::Int | Comparison::Unsigned) => self.integer,
+            AddReal(_) | SubReal(_) | MulReal(_) | DivReal(_) | PowReal(_) | NegReal
+            | CompareReal(_) | CompareString(_) | IntToReal(..) | RealToInt(_) | RoundReal
+            | RoundToInt(_) | RealFunction(..) | Max(_) | Min(_) => self.real,
+            Select
+            | Choose(_)
+            | Jump(_)
+            | JumpIfFalse(_)
+            | JumpIfTrue(_)
+            | ForTest { .. }
+            | ForStep { .. } => self.branch,
+            // Functions on strings allocate like a call does
+            ToString(_) | FromString(_) | StringFunction(_) => self.call,
+            StandardBlock(_) | Assert(..) => self.call,
+            Enter(_) | Call(_) | CallWith(_) | Leave { .. } | Return => self.call,
+        }
+    }
+}
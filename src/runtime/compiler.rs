@@ -0,0 +1,1613 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{
+        Action, Argument, Ast, Block, CallExpression, CaseLabel, CaseStatement, Expression,
+        ForLoop, InfixExpression, InfixOperator, Pou, PrefixOperator, Span, Statement, Statements,
+        VariableKind,
+    },
+    runtime::{
+        bytecode::{Address, Comparison, Function, Instruction, Module, Precision},
+        library::DAY,
+        value,
+    },
+    semantic::{
+        consteval::{self, ConstValue},
+        library::{self, MILLISECOND, Scalar, StandardBlock},
+        symbols::{SymbolId, SymbolKind, SymbolTable},
+        types::{Elementary, Generic, Type, TypeEnv, common_type, infix_type},
+    },
+};
+
+type Result<T> = std::result::Result<T, Diagnostic>;
+
+/// How values of a type are represented on the operand stack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Bool,
+    Int(Elementary),
+    Real(Precision),
+    String,
+    /// Structures, arrays and instances, which are passed around by their address
+    Block(u32),
+    Void,
+}
+
+/// Where the value of an expression lives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Location {
+    Direct(Address),
+    /// The absolute address is on top of the operand stack
+    Indirect,
+}
+
+/// Where the arguments of a call are passed to.
+enum Parameters {
+    /// The callee frame of a function or method
+    Frame,
+    Instance(Location),
+}
+
+#[derive(Default)]
+struct Loop {
+    exits: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// Compiles a checked program into bytecode for the [`Vm`](crate::runtime::vm::Vm).
+pub fn compile(ast: &Ast, table: &SymbolTable) -> Result<Module> {
+    let env = TypeEnv::new(table);
+    let aliases = table
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| match s.kind {
+            SymbolKind::Variable {
+                kind: VariableKind::InOut,
+                ..
+            } => true,
+            SymbolKind::Variable { .. } => matches!(
+                s.data_type.as_ref().map(|t| env.lower(t)),
+                Some(Type::Reference(_))
+            ),
+            _ => false,
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let mut compiler = Compiler {
+        env,
+        pous: HashMap::new(),
+        actions: HashMap::new(),
+        aliases,
+        functions: HashMap::new(),
+        sizes: HashMap::new(),
+        offsets: HashMap::new(),
+        globals: HashMap::new(),
+        programs: HashMap::new(),
+        strings: vec![String::new()],
+        string_ids: HashMap::from([(String::new(), 0)]),
+        code: Vec::new(),
+        spans: Vec::new(),
+        loops: Vec::new(),
+        pou: None,
+    };
+    compiler.collect(&ast.blocks);
+    compiler.compile_module()
+}
+
+struct Compiler<'a> {
+    env: TypeEnv<'a>,
+    pous: HashMap<SymbolId, &'a Pou>,
+    actions: HashMap<SymbolId, &'a Action>,
+    /// Variables which hold the address of another place, like VAR_IN_OUT and REFERENCE TO
+    aliases: HashSet<SymbolId>,
+    functions: HashMap<SymbolId, u32>,
+    /// Number of slots of structures and instances, and of the frames of functions and methods
+    sizes: HashMap<SymbolId, u32>,
+    offsets: HashMap<SymbolId, u32>,
+    globals: HashMap<SymbolId, u32>,
+    programs: HashMap<SymbolId, (u32, u32)>,
+    strings: Vec<String>,
+    string_ids: HashMap<String, u32>,
+
+    // State of the function being compiled
+    code: Vec<Instruction>,
+    spans: Vec<Span>,
+    loops: Vec<Loop>,
+    pou: Option<SymbolId>,
+}
+
+impl<'a> Compiler<'a> {
+    fn collect(&mut self, blocks: &'a [Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                    self.collect_pou(pou)
+                }
+                Block::Action(action) => self.collect_action(action),
+                Block::Namespace(namespace) => self.collect(&namespace.blocks),
+                Block::Type(_) | Block::GlobalVariables(_) | Block::Configuration(_) => {}
+            }
+        }
+    }
+
+    fn collect_pou(&mut self, pou: &'a Pou) {
+        if let Some(id) = self.env.binding(pou.name.span) {
+            self.pous.insert(id, pou);
+        }
+        for method in &pou.methods {
+            self.collect_pou(method);
+        }
+        for action in &pou.actions {
+            self.collect_action(action);
+        }
+    }
+
+    fn collect_action(&mut self, action: &'a Action) {
+        if let Some(id) = self.env.binding(action.name.span) {
+            self.actions.insert(id, action);
+        }
+    }
+
+    fn compile_module(mut self) -> Result<Module> {
+        let table = self.env.table;
+        let units = (0..table.symbols.len())
+            .filter(|id| {
+                matches!(
+                    table.symbol(*id).kind,
+                    SymbolKind::Function
+                        | SymbolKind::FunctionBlock
+                        | SymbolKind::Program
+                        | SymbolKind::Method
+                        | SymbolKind::Action
+                )
+            })
+            .collect::<Vec<_>>();
+        for (i, id) in units.iter().enumerate() {
+            self.functions.insert(*id, i as u32 + 1);
+            if matches!(
+                table.symbol(*id).kind,
+                SymbolKind::Function | SymbolKind::Method
+            ) {
+                self.frame_layout(*id);
+            }
+        }
+
+        // Slot 0 stays unused, so that address 0 is the null pointer
+        let mut static_size = 1;
+        for (id, symbol) in table.symbols.iter().enumerate() {
+            if let SymbolKind::Variable {
+                kind: VariableKind::Global,
+                ..
+            } = symbol.kind
+            {
+                self.globals.insert(id, static_size);
+                static_size += self.slots_of_variable(id);
+            }
+        }
+        for id in &units {
+            if table.symbol(*id).kind == SymbolKind::Program {
+                self.programs.insert(*id, (static_size, self.functions[id]));
+                static_size += self.layout(*id);
+            }
+        }
+
+        let mut functions = vec![self.compile_initializer()?];
+        for id in units {
+            functions.push(self.compile_function(id)?);
+        }
+
+        Ok(Module {
+            functions,
+            strings: self.strings,
+            static_size,
+            init: 0,
+            globals: self.globals,
+            programs: self.programs,
+            offsets: self.offsets,
+        })
+    }
+
+    /// Variables of a POU, structure or instance which occupy memory, in declaration order.
+    fn variables_of(&self, id: SymbolId) -> Vec<SymbolId> {
+        let table = self.env.table;
+        table
+            .symbol(id)
+            .members
+            .map(|m| table.symbols_in(m))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| match table.symbol(*m).kind {
+                SymbolKind::Variable { kind, .. } => kind != VariableKind::External,
+                _ => false,
+            })
+            .collect()
+    }
+
+    fn slots_of_variable(&mut self, id: SymbolId) -> u32 {
+        match self.aliases.contains(&id) {
+            true => 1,
+            false => {
+                let ty = self.env.type_of_symbol(id);
+                self.size_of(&ty)
+            }
+        }
+    }
+
+    fn size_of(&mut self, ty: &Type) -> u32 {
+        match ty {
+            Type::Array {
+                dimensions,
+                element,
+            } => element_count(dimensions) * self.size_of(element),
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => self.layout(*id),
+            Type::Void => 0,
+            _ => 1,
+        }
+    }
+
+    /// Assigns offsets to the variables of a structure or instance and returns its size.
+    fn layout(&mut self, id: SymbolId) -> u32 {
+        if let Some(size) = self.sizes.get(&id) {
+            return *size;
+        }
+
+        // Recursive structures are reported by the checker, give them no size meanwhile
+        self.sizes.insert(id, 0);
+        let mut size = 0;
+        for member in self.variables_of(id) {
+            self.offsets.insert(member, size);
+            size += self.slots_of_variable(member);
+        }
+        self.sizes.insert(id, size);
+        size
+    }
+
+    /// Frames of functions and methods start with the return value, followed by the variables.
+    fn frame_layout(&mut self, id: SymbolId) {
+        let return_type = self.env.type_of_symbol(id);
+        let mut size = self.size_of(&return_type);
+        for member in self.variables_of(id) {
+            self.offsets.insert(member, size);
+            size += self.slots_of_variable(member);
+        }
+        self.sizes.insert(id, size);
+    }
+
+    fn kind(&mut self, ty: &Type) -> Kind {
+        match ty.dereferenced() {
+            Type::Elementary(e) => match e {
+                Elementary::Bool => Kind::Bool,
+                Elementary::Real => Kind::Real(Precision::Single),
+                Elementary::Lreal => Kind::Real(Precision::Double),
+                e if e.is_temporal() => Kind::Int(Elementary::Lint),
+                e => Kind::Int(*e),
+            },
+            Type::Subrange { base, .. } => Kind::Int(*base),
+            Type::String { .. } => Kind::String,
+            Type::Enum(_) => Kind::Int(Elementary::Dint),
+            Type::Pointer(_) | Type::Reference(_) | Type::IntegerLiteral => {
+                Kind::Int(Elementary::Lint)
+            }
+            Type::RealLiteral => Kind::Real(Precision::Double),
+            Type::Void => Kind::Void,
+            t => Kind::Block(self.size_of(&t.clone())),
+        }
+    }
+
+    fn string(&mut self, s: &str) -> i64 {
+        if let Some(id) = self.string_ids.get(s) {
+            return *id as i64;
+        }
+        self.strings.push(s.to_string());
+        let id = self.strings.len() as u32 - 1;
+        self.string_ids.insert(s.to_string(), id);
+        id as i64
+    }
+
+    fn unsupported(span: Span, what: &str) -> Diagnostic {
+        Diagnostic::error(
+            span,
+            format!("{what} is not supported by the bytecode compiler."),
+        )
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        self.code.push(instruction);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    /// Points a previously emitted jump to the given target.
+    fn patch(&mut self, at: usize, target: u32) {
+        match &mut self.code[at] {
+            Instruction::Jump(t) | Instruction::JumpIfFalse(t) | Instruction::JumpIfTrue(t) => {
+                *t = target
+            }
+            Instruction::ForTest { exit, .. } | Instruction::ForStep { exit, .. } => *exit = target,
+            other => unreachable!("{other:?} is not a jump"),
+        }
+    }
+
+    fn finish(&mut self, name: String, body: u32, frame_size: u32) -> Function {
+        Function {
+            name,
+            code: std::mem::take(&mut self.code),
+            spans: std::mem::take(&mut self.spans),
+            body,
+            frame_size,
+        }
+    }
+
+    fn compile_initializer(&mut self) -> Result<Function> {
+        let table = self.env.table;
+        let mut globals = self
+            .globals
+            .iter()
+            .map(|(id, a)| (*id, *a))
+            .collect::<Vec<_>>();
+        globals.sort();
+        for (id, address) in globals {
+            let symbol = table.symbol(id);
+            let ty = self.env.type_of_symbol(id);
+            self.emit_init(
+                Address::Static(address),
+                &ty,
+                symbol.value.as_ref(),
+                false,
+                symbol.span,
+            )?;
+        }
+
+        let mut programs = self
+            .programs
+            .iter()
+            .map(|(id, (a, _))| (*id, *a))
+            .collect::<Vec<_>>();
+        programs.sort();
+        for (id, address) in programs {
+            let span = table.symbol(id).span;
+            self.emit_init(
+                Address::Static(address),
+                &Type::Program(id),
+                None,
+                false,
+                span,
+            )?;
+        }
+
+        self.emit(Instruction::Return, Span::default());
+        Ok(self.finish("<init>".to_string(), 0, 0))
+    }
+
+    fn compile_function(&mut self, id: SymbolId) -> Result<Function> {
+        let table = self.env.table;
+        let symbol = table.symbol(id);
+        let span = symbol.span;
+        self.pou = Some(id);
+
+        let stateless = matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method);
+        if stateless {
+            let return_type = self.env.type_of_symbol(id);
+            self.emit_init(Address::Local(0), &return_type, None, false, span)?;
+            for member in self.variables_of(id) {
+                if !self.aliases.contains(&member) {
+                    let address = Address::Local(self.offsets[&member]);
+                    let ty = self.env.type_of_symbol(member);
+                    let value = table.symbol(member).value.as_ref();
+                    self.emit_init(address, &ty, value, false, span)?;
+                }
+            }
+        }
+        self.emit(Instruction::Return, span);
+
+        let body = self.here();
+        if !stateless {
+            // Temporaries of instances start from their initial value on every call
+            for member in self.variables_of(id) {
+                let member_symbol = table.symbol(member);
+                if let SymbolKind::Variable {
+                    kind: VariableKind::Temp,
+                    ..
+                } = member_symbol.kind
+                {
+                    let address = Address::This(self.offsets[&member]);
+                    let ty = self.env.type_of_symbol(member);
+                    let value = member_symbol.value.as_ref();
+                    self.emit_init(address, &ty, value, true, span)?;
+                }
+            }
+        }
+
+        let statements = match (self.pous.get(&id), self.actions.get(&id)) {
+            (Some(pou), _) => Some(&pou.statements),
+            (_, Some(action)) => Some(&action.statements),
+            _ => None,
+        };
+        if let Some(statements) = statements {
+            self.emit_statements(statements)?;
+        } else if let Some(block) = StandardBlock::of(table, id) {
+            self.emit(Instruction::StandardBlock(block), span);
+        }
+        self.emit(Instruction::Return, span);
+
+        let frame_size = match stateless {
+            true => self.sizes[&id],
+            false => 0,
+        };
+        Ok(self.finish(table.qualified_name(id), body, frame_size))
+    }
+
+    /// Stores the initial value of a variable. Memory starts zeroed, so zero values are only
+    /// stored when resetting a variable which was used before.
+    fn emit_init(
+        &mut self,
+        base: Address,
+        ty: &Type,
+        initializer: Option<&Expression>,
+        reset: bool,
+        span: Span,
+    ) -> Result<()> {
+        match ty {
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => {
+                let table = self.env.table;
+                for member in self.variables_of(*id) {
+                    let address = base.offset(self.offsets[&member]);
+                    if self.aliases.contains(&member) {
+                        if reset {
+                            self.emit(Instruction::Const(0), span);
+                            self.emit(Instruction::Store(address), span);
+                        }
+                        continue;
+                    }
+
+                    let member_symbol = table.symbol(member);
+                    let value = match initializer {
+                        Some(Expression::Struct(members, _)) => members
+                            .iter()
+                            .find(|(name, _)| name.name.eq_ignore_ascii_case(&member_symbol.name))
+                            .map(|(_, value)| value)
+                            .or(member_symbol.value.as_ref()),
+                        _ => member_symbol.value.as_ref(),
+                    };
+                    let member_type = self.env.type_of_symbol(member);
+                    self.emit_init(address, &member_type, value, reset, span)?;
+                }
+            }
+            Type::Array {
+                dimensions,
+                element,
+            } => {
+                let items = match initializer {
+                    Some(Expression::Array(items, _)) => items.as_slice(),
+                    _ => &[],
+                };
+                let stride = self.size_of(element);
+                let needs_init = reset || self.needs_init(element);
+                for i in 0..element_count(dimensions) {
+                    let item = items.get(i as usize);
+                    if item.is_some() || needs_init {
+                        let address = base.offset(i * stride);
+                        self.emit_init(address, element, item, reset, span)?;
+                    }
+                }
+            }
+            ty => {
+                match initializer {
+                    Some(value) => {
+                        let kind = self.kind(ty);
+                        self.emit_value(value, kind)?;
+                        self.truncate(ty, span);
+                    }
+                    None => {
+                        let default = self.default_of(ty);
+                        if default == 0 && !reset {
+                            return Ok(());
+                        }
+                        self.emit(Instruction::Const(default), span);
+                    }
+                }
+                self.emit(Instruction::Store(base), span);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a type has a non-zero initial value somewhere.
+    fn needs_init(&mut self, ty: &Type) -> bool {
+        match ty {
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => {
+                self.variables_of(*id).into_iter().any(|member| {
+                    !self.aliases.contains(&member)
+                        && (self.env.table.symbol(member).value.is_some()
+                            || self.needs_init(&self.env.type_of_symbol(member)))
+                })
+            }
+            Type::Array { element, .. } => self.needs_init(element),
+            ty => self.default_of(ty) != 0,
+        }
+    }
+
+    fn default_of(&self, ty: &Type) -> i64 {
+        match ty {
+            Type::Subrange { lower, .. } => lower.unwrap_or(0) as i64,
+            Type::Enum(scope) => self
+                .env
+                .table
+                .symbols_in(*scope)
+                .first()
+                .and_then(|id| consteval::enum_value(&self.env, *id))
+                .unwrap_or(0) as i64,
+            _ => 0,
+        }
+    }
+
+    fn emit_statements(&mut self, statements: &Statements) -> Result<()> {
+        for statement in statements {
+            self.emit_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn emit_statement(&mut self, statement: &Statement) -> Result<()> {
+        let span = statement.span();
+        match statement {
+            Statement::Empty(_) => {}
+            Statement::Return(_) => {
+                self.emit(Instruction::Return, span);
+            }
+            Statement::Exit(_) => {
+                let at = self.emit(Instruction::Jump(0), span);
+                if let Some(l) = self.loops.last_mut() {
+                    l.exits.push(at);
+                }
+            }
+            Statement::Continue(_) => {
+                let at = self.emit(Instruction::Jump(0), span);
+                if let Some(l) = self.loops.last_mut() {
+                    l.continues.push(at);
+                }
+            }
+            Statement::Expression(expression) => {
+                if self.emit_expression(expression)? != Kind::Void {
+                    self.emit(Instruction::Pop, span);
+                }
+            }
+            Statement::Assignment(assignment) => {
+                let ty = self.env.type_of(&assignment.target);
+                match self.kind(&ty) {
+                    Kind::Block(size) => {
+                        let location = self.location(&assignment.target)?;
+                        self.address_of(location, span);
+                        self.emit_value(&assignment.value, Kind::Block(size))?;
+                        self.emit(Instruction::Copy(size), span);
+                    }
+                    kind => {
+                        self.emit_value(&assignment.value, kind)?;
+                        self.truncate(&ty, span);
+                        self.store_top(&assignment.target)?;
+                    }
+                }
+            }
+            Statement::If(condition) => {
+                let mut ends = Vec::new();
+                for branch in std::iter::once(&condition.branch).chain(&condition.alt_branches) {
+                    self.emit_value(&branch.condition, Kind::Bool)?;
+                    let skip = self.emit(Instruction::JumpIfFalse(0), span);
+                    self.emit_statements(&branch.statements)?;
+                    ends.push(self.emit(Instruction::Jump(0), span));
+                    self.patch(skip, self.here());
+                }
+                if let Some(fallback) = &condition.fallback {
+                    self.emit_statements(fallback)?;
+                }
+                for end in ends {
+                    self.patch(end, self.here());
+                }
+            }
+            Statement::Case(case) => self.emit_case(case)?,
+            Statement::For(for_loop) => self.emit_for(for_loop)?,
+            Statement::While(while_loop) => {
+                let top = self.here();
+                self.emit_value(&while_loop.condition, Kind::Bool)?;
+                let exit = self.emit(Instruction::JumpIfFalse(0), span);
+                self.loops.push(Loop::default());
+                self.emit_statements(&while_loop.statements)?;
+                self.emit(Instruction::Jump(top), span);
+                let l = self.loops.pop().unwrap_or_default();
+                let end = self.here();
+                self.patch(exit, end);
+                self.patch_loop(l, top, end);
+            }
+            Statement::Repeat(repeat_loop) => {
+                let top = self.here();
+                self.loops.push(Loop::default());
+                self.emit_statements(&repeat_loop.statements)?;
+                let l = self.loops.pop().unwrap_or_default();
+                let condition = self.here();
+                self.emit_value(&repeat_loop.condition, Kind::Bool)?;
+                self.emit(Instruction::JumpIfFalse(top), span);
+                self.patch_loop(l, condition, self.here());
+            }
+        }
+        Ok(())
+    }
+
+    fn patch_loop(&mut self, l: Loop, continue_target: u32, exit_target: u32) {
+        for at in l.continues {
+            self.patch(at, continue_target);
+        }
+        for at in l.exits {
+            self.patch(at, exit_target);
+        }
+    }
+
+    /// The selector stays on the stack while the labels are compared and is dropped on entering a branch.
+    fn emit_case(&mut self, case: &CaseStatement) -> Result<()> {
+        let span = case.span;
+        let ty = self.env.type_of(&case.selector);
+        let kind = match self.kind(&ty) {
+            kind @ (Kind::Bool | Kind::Int(_)) => kind,
+            _ => return Err(Self::unsupported(case.selector.span(), "CASE on this type")),
+        };
+        self.emit_value(&case.selector, kind)?;
+
+        let mut entries = Vec::new();
+        for branch in &case.branches {
+            let mut jumps = Vec::new();
+            for label in &branch.labels {
+                match label {
+                    CaseLabel::Value(value) => {
+                        let n = self.constant_int(value)?;
+                        self.emit(Instruction::Dup, span);
+                        self.emit(Instruction::Const(n), span);
+                        self.emit(compare(kind, InfixOperator::Equals), span);
+                        jumps.push(self.emit(Instruction::JumpIfTrue(0), span));
+                    }
+                    CaseLabel::Range(range) => {
+                        let lower = self.constant_int(&range.lower)?;
+                        let upper = self.constant_int(&range.upper)?;
+                        self.emit(Instruction::Dup, span);
+                        self.emit(Instruction::Const(lower), span);
+                        self.emit(compare(kind, InfixOperator::GreaterThanOrEquals), span);
+                        let skip = self.emit(Instruction::JumpIfFalse(0), span);
+                        self.emit(Instruction::Dup, span);
+                        self.emit(Instruction::Const(upper), span);
+                        self.emit(compare(kind, InfixOperator::LessThanOrEquals), span);
+                        jumps.push(self.emit(Instruction::JumpIfTrue(0), span));
+                        self.patch(skip, self.here());
+                    }
+                }
+            }
+            entries.push(jumps);
+        }
+        let fallback = self.emit(Instruction::Jump(0), span);
+
+        let mut ends = Vec::new();
+        for (branch, jumps) in case.branches.iter().zip(entries) {
+            for jump in jumps {
+                self.patch(jump, self.here());
+            }
+            self.emit(Instruction::Pop, span);
+            self.emit_statements(&branch.statements)?;
+            ends.push(self.emit(Instruction::Jump(0), span));
+        }
+
+        self.patch(fallback, self.here());
+        self.emit(Instruction::Pop, span);
+        if let Some(statements) = &case.fallback {
+            self.emit_statements(statements)?;
+        }
+        for end in ends {
+            self.patch(end, self.here());
+        }
+        Ok(())
+    }
+
+    /// End and step stay on the stack for the whole loop.
+    fn emit_for(&mut self, for_loop: &ForLoop) -> Result<()> {
+        let span = for_loop.span;
+        let variable = Expression::Identifier(for_loop.variable.clone());
+        let ty = self.env.type_of(&variable);
+        let (Location::Direct(address), Kind::Int(int)) =
+            (self.location(&variable)?, self.kind(&ty))
+        else {
+            return Err(Self::unsupported(
+                for_loop.variable.span,
+                "This loop variable",
+            ));
+        };
+
+        self.emit_value(&for_loop.start, Kind::Int(int))?;
+        self.emit(Instruction::Store(address), span);
+        self.emit_value(&for_loop.end, Kind::Int(Elementary::Lint))?;
+        match &for_loop.step {
+            Some(step) => self.emit_value(step, Kind::Int(Elementary::Lint))?,
+            None => {
+                self.emit(Instruction::Const(1), span);
+            }
+        }
+
+        let test = self.here();
+        let exit = self.emit(
+            Instruction::ForTest {
+                variable: address,
+                exit: 0,
+            },
+            span,
+        );
+        self.loops.push(Loop::default());
+        self.emit_statements(&for_loop.statements)?;
+        let l = self.loops.pop().unwrap_or_default();
+        let step = self.here();
+        let step_at = self.emit(
+            Instruction::ForStep {
+                variable: address,
+                ty: int,
+                test,
+                exit: 0,
+            },
+            span,
+        );
+        let end = self.here();
+        self.emit(Instruction::Pop, span);
+        self.emit(Instruction::Pop, span);
+
+        self.patch(exit, end);
+        self.patch(step_at, end);
+        self.patch_loop(l, step, end);
+        Ok(())
+    }
+
+    fn constant_int(&self, expression: &Expression) -> Result<i64> {
+        let value = match consteval::evaluate(&self.env, expression) {
+            Ok(ConstValue::Int(n)) => Some(n),
+            Ok(ConstValue::Bool(b)) => Some(b as i128),
+            Ok(ConstValue::Enum(id)) => consteval::enum_value(&self.env, id),
+            _ => None,
+        };
+        value.map(|n| n as i64).ok_or_else(|| {
+            Diagnostic::error(
+                expression.span(),
+                "CASE labels must be constant expressions.",
+            )
+        })
+    }
+
+    /// Pushes the value of an expression, converted to the given representation.
+    fn emit_value(&mut self, expression: &Expression, kind: Kind) -> Result<()> {
+        let span = expression.span();
+        if !matches!(kind, Kind::Block(_) | Kind::Void)
+            && let Ok(constant) = consteval::evaluate(&self.env, expression)
+        {
+            self.emit_constant(constant, kind, span);
+            return Ok(());
+        }
+
+        let from = self.emit_expression(expression)?;
+        self.convert(from, kind, span)
+    }
+
+    fn emit_constant(&mut self, constant: ConstValue, kind: Kind, span: Span) {
+        let instruction = match (constant, kind) {
+            (ConstValue::Bool(b), _) => Instruction::Const(b as i64),
+            (ConstValue::Int(n), Kind::Real(p)) => Instruction::ConstReal(round(n as f64, p)),
+            (ConstValue::Int(n), Kind::Int(e)) => Instruction::Const(value::wrap(n, e) as i64),
+            (ConstValue::Int(n), _) => Instruction::Const(n as i64),
+            (ConstValue::Real(r), Kind::Real(p)) => Instruction::ConstReal(round(r, p)),
+            (ConstValue::Real(r), Kind::Int(_)) => Instruction::Const(r.trunc() as i64),
+            (ConstValue::Real(r), _) => Instruction::ConstReal(r),
+            (ConstValue::String(s), _) => Instruction::Const(self.string(&s)),
+            (ConstValue::Time(t) | ConstValue::Date(t, _), Kind::Real(p)) => {
+                Instruction::ConstReal(round(t as f64, p))
+            }
+            (ConstValue::Time(t) | ConstValue::Date(t, _), _) => Instruction::Const(t as i64),
+            (ConstValue::Enum(id), _) => {
+                Instruction::Const(consteval::enum_value(&self.env, id).unwrap_or(0) as i64)
+            }
+        };
+        self.emit(instruction, span);
+    }
+
+    fn convert(&mut self, from: Kind, to: Kind, span: Span) -> Result<()> {
+        let instruction = match (from, to) {
+            _ if from == to => return Ok(()),
+            (Kind::Int(a), Kind::Int(b)) if a.widens_to(b) || b.bit_width() == Some(64) => {
+                return Ok(());
+            }
+            (Kind::Int(_), Kind::Int(b)) => Instruction::Wrap(b),
+            (Kind::Bool, Kind::Int(_)) => return Ok(()),
+            (Kind::Int(a), Kind::Real(p)) => Instruction::IntToReal(a, p),
+            (Kind::Real(Precision::Single), Kind::Real(Precision::Double)) => return Ok(()),
+            (Kind::Real(_), Kind::Real(Precision::Single)) => Instruction::RoundReal,
+            (Kind::Real(_), Kind::Int(e)) => Instruction::RealToInt(e),
+            (Kind::Block(_), Kind::Block(_)) => return Ok(()),
+            _ => return Err(Diagnostic::error(span, "Invalid conversion of a value.")),
+        };
+        self.emit(instruction, span);
+        Ok(())
+    }
+
+    /// Pushes the value of an expression in the representation of its own type.
+    fn emit_expression(&mut self, expression: &Expression) -> Result<Kind> {
+        let span = expression.span();
+        match expression {
+            Expression::Literal(..) | Expression::TypedLiteral(_) => {
+                let ty = self.env.type_of(expression);
+                let kind = self.kind(&ty);
+                match consteval::evaluate(&self.env, expression) {
+                    Ok(constant) => {
+                        self.emit_constant(constant, kind, span);
+                        Ok(kind)
+                    }
+                    Err(_) => Err(Diagnostic::error(span, "Invalid literal.")),
+                }
+            }
+            Expression::Identifier(_)
+            | Expression::Member(_)
+            | Expression::Index(_)
+            | Expression::Deref(..) => {
+                let ty = self.env.type_of(expression);
+                let kind = self.kind(&ty);
+                let location = self.location(expression)?;
+                self.load(location, kind, span);
+                Ok(kind)
+            }
+            Expression::Prefix(prefix) => {
+                let kind = self.emit_expression(&prefix.operand)?;
+                let instruction = match (prefix.op, kind) {
+                    (PrefixOperator::Negation, Kind::Int(e)) => Instruction::Neg(e),
+                    (PrefixOperator::Negation, Kind::Real(_)) => Instruction::NegReal,
+                    (PrefixOperator::Not, Kind::Bool) => Instruction::Not(Elementary::Bool),
+                    (PrefixOperator::Not, Kind::Int(e)) => Instruction::Not(e),
+                    _ => return Err(Diagnostic::error(span, "Invalid operand.")),
+                };
+                self.emit(instruction, span);
+                Ok(kind)
+            }
+            Expression::Infix(infix) => self.emit_infix(infix, span),
+            Expression::Call(call) => self.emit_call(call),
+            Expression::Array(..) | Expression::Struct(..) => Err(Diagnostic::error(
+                span,
+                "Structure and array initializers are only allowed in declarations.",
+            )),
+        }
+    }
+
+    fn emit_infix(&mut self, infix: &InfixExpression, span: Span) -> Result<Kind> {
+        use InfixOperator::*;
+
+        let left = self.env.type_of(&infix.left).dereferenced().clone();
+        let right = self.env.type_of(&infix.right).dereferenced().clone();
+        let Some(result) = infix_type(infix.op, &left, &right) else {
+            return Err(Diagnostic::error(span, "Invalid operands."));
+        };
+
+        // Durations and dates compute in nanoseconds
+        let temporal = left.elementary().is_some_and(|e| e.is_temporal());
+        if temporal && !infix.op.is_comparison() {
+            let lint = Kind::Int(Elementary::Lint);
+            let scaled_by_real =
+                matches!(infix.op, Multiplication | Division) && Generic::AnyReal.contains(&right);
+            if scaled_by_real {
+                let double = Kind::Real(Precision::Double);
+                self.emit_value(&infix.left, double)?;
+                self.emit_value(&infix.right, double)?;
+                let instruction = match infix.op {
+                    Multiplication => Instruction::MulReal(Precision::Double),
+                    _ => Instruction::DivReal(Precision::Double),
+                };
+                self.emit(instruction, span);
+                self.emit(Instruction::RealToInt(Elementary::Lint), span);
+            } else {
+                self.emit_value(&infix.left, lint)?;
+                self.emit_value(&infix.right, lint)?;
+                self.emit(int_operation(infix.op, Elementary::Lint), span);
+            }
+            return Ok(lint);
+        }
+
+        let operands = match infix.op {
+            op if op.is_comparison() => common_type(&left, &right).unwrap_or(Type::Unknown),
+            _ => result.clone(),
+        };
+        let kind = self.kind(&operands);
+        self.emit_value(&infix.left, kind)?;
+        self.emit_value(&infix.right, kind)?;
+
+        let instruction = match (infix.op, kind) {
+            (op, kind) if op.is_comparison() => compare(kind, op),
+            (And, _) => Instruction::And,
+            (Or, _) => Instruction::Or,
+            (Xor, _) => Instruction::Xor,
+            (op, Kind::Int(e)) => int_operation(op, e),
+            (Addition, Kind::Real(p)) => Instruction::AddReal(p),
+            (Subtraction, Kind::Real(p)) => Instruction::SubReal(p),
+            (Multiplication, Kind::Real(p)) => Instruction::MulReal(p),
+            (Division, Kind::Real(p)) => Instruction::DivReal(p),
+            (Power, Kind::Real(p)) => Instruction::PowReal(p),
+            _ => return Err(Diagnostic::error(span, "Invalid operands.")),
+        };
+        self.emit(instruction, span);
+
+        match infix.op.is_comparison() {
+            true => Ok(Kind::Bool),
+            false => Ok(kind),
+        }
+    }
+
+    fn emit_call(&mut self, call: &CallExpression) -> Result<Kind> {
+        let span = call.span;
+        let callee = call.callee.as_ref();
+        let (binding, target) = match callee {
+            Expression::Identifier(name) => (self.env.binding(name.span), None),
+            Expression::Member(member) => (
+                self.env.binding(member.member.span),
+                Some(member.target.as_ref()),
+            ),
+            _ => (None, None),
+        };
+        if binding.is_none()
+            && !matches!(callee, Expression::Identifier(_))
+            && let Type::FunctionBlock(fb) = self.env.type_of(callee).dereferenced()
+        {
+            return self.emit_instance_call(*fb, call);
+        }
+        let Some(id) = binding else {
+            if let Expression::Identifier(name) = callee
+                && let Some(function) = library::lookup(&name.name)
+            {
+                return self.emit_standard_call(function, call);
+            }
+            let message = "The called function is not available at runtime.";
+            return Err(Diagnostic::error(callee.span(), message));
+        };
+
+        match self.env.table.symbol(id).kind {
+            SymbolKind::Function | SymbolKind::Method => {
+                let function = self.functions[&id];
+                let result = self.env.type_of_symbol(id);
+                let kind = match self.kind(&result) {
+                    Kind::Block(_) => {
+                        return Err(Self::unsupported(span, "Returning structured values"));
+                    }
+                    kind => kind,
+                };
+
+                let method = self.env.table.symbol(id).kind == SymbolKind::Method;
+                if method {
+                    self.instance_address(target, span)?;
+                }
+                self.emit(Instruction::Enter(function), span);
+                self.bind_inputs(id, call, &Parameters::Frame)?;
+                match method {
+                    true => self.emit(Instruction::CallWith(function), span),
+                    false => self.emit(Instruction::Call(function), span),
+                };
+                self.bind_outputs(call, &Parameters::Frame)?;
+                let result = kind != Kind::Void;
+                self.emit(Instruction::Leave { result }, span);
+                Ok(kind)
+            }
+            SymbolKind::Action => {
+                self.instance_address(target, span)?;
+                self.emit(Instruction::CallWith(self.functions[&id]), span);
+                Ok(Kind::Void)
+            }
+            SymbolKind::Program | SymbolKind::Variable { .. } => {
+                match self.env.type_of_symbol(id).dereferenced() {
+                    Type::Program(fb) | Type::FunctionBlock(fb) => {
+                        self.emit_instance_call(*fb, call)
+                    }
+                    _ => Err(Diagnostic::error(callee.span(), "This cannot be called.")),
+                }
+            }
+            _ => Err(Diagnostic::error(callee.span(), "This cannot be called.")),
+        }
+    }
+
+    /// Calls the body of a function block or program with the instance the callee denotes.
+    fn emit_instance_call(&mut self, fb: SymbolId, call: &CallExpression) -> Result<Kind> {
+        let span = call.span;
+        let function = self.functions[&fb];
+        let instance = self.location(&call.callee)?;
+        let parameters = Parameters::Instance(instance);
+        self.bind_inputs(fb, call, &parameters)?;
+        match instance {
+            Location::Direct(address) => self.emit(Instruction::AddressOf(address), span),
+            Location::Indirect => self.emit(Instruction::Dup, span),
+        };
+        self.emit(Instruction::CallWith(function), span);
+        self.bind_outputs(call, &parameters)?;
+        if instance == Location::Indirect {
+            self.emit(Instruction::Pop, span);
+        }
+        Ok(Kind::Void)
+    }
+
+    /// Standard functions compile to instructions instead of calls.
+    fn emit_standard_call(
+        &mut self,
+        function: library::Function,
+        call: &CallExpression,
+    ) -> Result<Kind> {
+        use library::Function::*;
+
+        let span = call.callee.span();
+        let arguments = call
+            .arguments
+            .iter()
+            .map(Argument::value)
+            .collect::<Vec<_>>();
+        let types = arguments
+            .iter()
+            .map(|a| self.env.type_of(a))
+            .collect::<Vec<_>>();
+        let Ok(result) = function.result_type(&types) else {
+            return Err(Diagnostic::error(span, "Invalid arguments."));
+        };
+        let kind = match self.kind(&result) {
+            Kind::Void if function.is_assertion() => Kind::Void,
+            Kind::Block(_) | Kind::Void => {
+                return Err(Self::unsupported(span, "Selecting structured values"));
+            }
+            kind => kind,
+        };
+        let lint = Kind::Int(Elementary::Lint);
+        let precision = match kind {
+            Kind::Real(p) => p,
+            _ => Precision::Double,
+        };
+
+        match function {
+            Abs => {
+                self.emit_value(arguments[0], kind)?;
+                self.emit(Instruction::Dup, span);
+                let (zero, negate) = match kind {
+                    Kind::Real(_) => (Instruction::ConstReal(0.0), Instruction::NegReal),
+                    Kind::Int(e) => (Instruction::Const(0), Instruction::Neg(e)),
+                    _ => return Err(Diagnostic::error(span, "Invalid arguments.")),
+                };
+                self.emit(zero, span);
+                self.emit(compare(kind, InfixOperator::LessThan), span);
+                let skip = self.emit(Instruction::JumpIfFalse(0), span);
+                self.emit(negate, span);
+                self.patch(skip, self.here());
+            }
+            Sqrt | Ln | Log | Exp | Sin | Cos | Tan | Asin | Acos | Atan => {
+                self.emit_value(arguments[0], kind)?;
+                self.emit(Instruction::RealFunction(function, precision), span);
+            }
+            Expt => {
+                self.emit_value(arguments[0], kind)?;
+                self.emit_value(arguments[1], Kind::Real(Precision::Double))?;
+                self.emit(Instruction::PowReal(precision), span);
+            }
+            Trunc => {
+                self.emit_value(arguments[0], Kind::Real(Precision::Double))?;
+                self.emit(Instruction::RealToInt(Elementary::Dint), span);
+            }
+            Sel => {
+                self.emit_value(arguments[0], Kind::Bool)?;
+                self.emit_value(arguments[1], kind)?;
+                self.emit_value(arguments[2], kind)?;
+                self.emit(Instruction::Select, span);
+            }
+            Max | Min | Limit => {
+                self.emit_value(arguments[0], kind)?;
+                for (i, argument) in arguments.iter().enumerate().skip(1) {
+                    self.emit_value(argument, kind)?;
+                    // LIMIT(MN, IN, MX) is MIN(MAX(MN, IN), MX)
+                    let instruction = match (function, i) {
+                        (Max, _) | (Limit, 1) => Instruction::Max(comparison(kind)),
+                        _ => Instruction::Min(comparison(kind)),
+                    };
+                    self.emit(instruction, span);
+                }
+            }
+            Mux => {
+                self.emit_value(arguments[0], lint)?;
+                for argument in &arguments[1..] {
+                    self.emit_value(argument, kind)?;
+                }
+                self.emit(Instruction::Choose(arguments.len() as u32 - 1), span);
+            }
+            Shl | Shr | Rol | Ror => {
+                self.emit_value(arguments[0], kind)?;
+                self.emit_value(arguments[1], lint)?;
+                let ty = match kind {
+                    Kind::Int(e) => e,
+                    _ => Elementary::Bool,
+                };
+                self.emit(Instruction::Shift(function, ty), span);
+            }
+            Concat => {
+                self.emit_value(arguments[0], Kind::String)?;
+                for argument in &arguments[1..] {
+                    self.emit_value(argument, Kind::String)?;
+                    self.emit(Instruction::StringFunction(Concat), span);
+                }
+            }
+            Len | Left | Right | Mid | Insert | Delete | Replace | Find => {
+                for (argument, ty) in arguments.iter().zip(&types) {
+                    let kind = match ty.is_string() {
+                        true => Kind::String,
+                        false => lint,
+                    };
+                    self.emit_value(argument, kind)?;
+                }
+                self.emit(Instruction::StringFunction(function), span);
+            }
+            Convert(from, to) => {
+                let source = self.kind(&from.to_type());
+                self.emit_value(arguments[0], source)?;
+                match (from, to) {
+                    (Scalar::Elementary(from), Scalar::String { .. }) => {
+                        self.emit(Instruction::ToString(from), span);
+                    }
+                    (Scalar::String { .. }, Scalar::Elementary(to)) => {
+                        self.emit(Instruction::FromString(to), span);
+                    }
+                    (Scalar::String { .. }, Scalar::String { .. }) => {}
+                    (Scalar::Elementary(from), Scalar::Elementary(to)) => {
+                        // Durations and dates convert to other types in milliseconds
+                        if from.is_temporal() && !to.is_temporal() && to != Elementary::Bool {
+                            self.emit(Instruction::Const(MILLISECOND as i64), span);
+                            self.emit(Instruction::Div(Elementary::Lint), span);
+                        }
+                        match (source, kind) {
+                            (Kind::Real(_), Kind::Bool) => {
+                                self.emit(Instruction::ConstReal(0.0), span);
+                                self.emit(Instruction::CompareReal(InfixOperator::NotEquals), span);
+                            }
+                            (Kind::Int(_), Kind::Bool) => {
+                                self.emit(Instruction::Const(0), span);
+                                self.emit(Instruction::CompareInt(InfixOperator::NotEquals), span);
+                            }
+                            (Kind::Bool, Kind::Real(p)) => {
+                                self.emit(Instruction::IntToReal(Elementary::Bool, p), span);
+                            }
+                            (Kind::Real(_), Kind::Int(e)) => {
+                                self.emit(Instruction::RoundToInt(e), span);
+                            }
+                            _ => self.convert(source, kind, span)?,
+                        }
+                        if to.is_temporal() && !from.is_temporal() {
+                            self.emit(Instruction::Const(MILLISECOND as i64), span);
+                            self.emit(Instruction::Mul(Elementary::Lint), span);
+                        }
+                    }
+                }
+                // Dates keep the day, times of day the time within it
+                match to {
+                    Scalar::Elementary(Elementary::Date) => {
+                        self.emit(Instruction::Dup, span);
+                        self.emit(Instruction::Const(DAY as i64), span);
+                        self.emit(Instruction::Mod(Elementary::Lint), span);
+                        self.emit(Instruction::Sub(Elementary::Lint), span);
+                    }
+                    Scalar::Elementary(Elementary::TimeOfDay) => {
+                        self.emit(Instruction::Const(DAY as i64), span);
+                        self.emit(Instruction::Mod(Elementary::Lint), span);
+                    }
+                    _ => {}
+                }
+            }
+            AssertTrue | AssertFalse | AssertEq | AssertNe | AssertNear => {
+                self.emit_assertion(function, &arguments, &types, span)?;
+            }
+        }
+        Ok(kind)
+    }
+
+    /// Assertions compare their operands in their common type.
+    fn emit_assertion(
+        &mut self,
+        function: library::Function,
+        arguments: &[&Expression],
+        types: &[Type],
+        span: Span,
+    ) -> Result<()> {
+        let operand = types[1..]
+            .iter()
+            .fold(types[0].dereferenced().clone(), |common, ty| {
+                common_type(&common, ty).unwrap_or(common)
+            });
+        let scalar = match &operand {
+            Type::Elementary(e) | Type::Subrange { base: e, .. } => Scalar::Elementary(*e),
+            Type::String { wide, .. } => Scalar::String { wide: *wide },
+            Type::Enum(_) => Scalar::Elementary(Elementary::Dint),
+            Type::IntegerLiteral => Scalar::Elementary(Elementary::Lint),
+            Type::RealLiteral => Scalar::Elementary(Elementary::Lreal),
+            _ => return Err(Self::unsupported(span, "Asserting structured values")),
+        };
+        let kind = self.kind(&operand);
+        for argument in arguments {
+            self.emit_value(argument, kind)?;
+        }
+        self.emit(Instruction::Assert(function, scalar), span);
+        Ok(())
+    }
+
+    /// Pushes the address of the instance a method or action is called on.
+    fn instance_address(&mut self, target: Option<&Expression>, span: Span) -> Result<()> {
+        let location = match target {
+            Some(target) => self.location(target)?,
+            None => Location::Direct(Address::This(0)),
+        };
+        self.address_of(location, span);
+        Ok(())
+    }
+
+    fn parameter_location(
+        &mut self,
+        parameters: &Parameters,
+        id: SymbolId,
+        span: Span,
+    ) -> Location {
+        let offset = self.offsets.get(&id).copied().unwrap_or(0);
+        match parameters {
+            Parameters::Frame => Location::Direct(Address::Callee(offset)),
+            Parameters::Instance(Location::Direct(address)) => {
+                Location::Direct(address.offset(offset))
+            }
+            Parameters::Instance(Location::Indirect) => {
+                self.emit(Instruction::Dup, span);
+                self.field(Location::Indirect, offset, span)
+            }
+        }
+    }
+
+    fn bind_inputs(
+        &mut self,
+        pou: SymbolId,
+        call: &CallExpression,
+        parameters: &Parameters,
+    ) -> Result<()> {
+        let table = self.env.table;
+        let inputs = self
+            .variables_of(pou)
+            .into_iter()
+            .filter(|id| {
+                matches!(
+                    table.symbol(*id).kind,
+                    SymbolKind::Variable {
+                        kind: VariableKind::Input | VariableKind::InOut,
+                        ..
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut positional = inputs.iter();
+
+        for argument in &call.arguments {
+            let parameter = match argument {
+                Argument::Positional(_) => positional.next().copied(),
+                Argument::Named(name, _) => self.env.binding(name.span),
+                Argument::Output(..) => continue,
+            };
+            let Some(parameter) = parameter else {
+                continue;
+            };
+
+            let value = argument.value();
+            let span = value.span();
+            let location = self.parameter_location(parameters, parameter, span);
+            if self.aliases.contains(&parameter) {
+                let target = self.location(value)?;
+                self.address_of(target, span);
+                self.store(location, span);
+                continue;
+            }
+
+            let ty = self.env.type_of_symbol(parameter);
+            match self.kind(&ty) {
+                Kind::Block(size) => {
+                    self.address_of(location, span);
+                    self.emit_value(value, Kind::Block(size))?;
+                    self.emit(Instruction::Copy(size), span);
+                }
+                kind => {
+                    self.emit_value(value, kind)?;
+                    self.truncate(&ty, span);
+                    self.store(location, span);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_outputs(&mut self, call: &CallExpression, parameters: &Parameters) -> Result<()> {
+        for argument in &call.arguments {
+            let Argument::Output(name, target) = argument else {
+                continue;
+            };
+            let Some(parameter) = self.env.binding(name.span) else {
+                continue;
+            };
+
+            let span = target.span();
+            let from = self.env.type_of_symbol(parameter);
+            let to = self.env.type_of(target);
+            let (from_kind, to_kind) = (self.kind(&from), self.kind(&to));
+            if matches!(from_kind, Kind::Block(_)) {
+                return Err(Self::unsupported(span, "Connecting structured outputs"));
+            }
+
+            let location = self.parameter_location(parameters, parameter, span);
+            self.load(location, from_kind, span);
+            self.convert(from_kind, to_kind, span)?;
+            self.truncate(&to, span);
+            self.store_top(target)?;
+        }
+        Ok(())
+    }
+
+    /// Stores the value on top of the stack into a variable. The value is computed before the
+    /// address of the variable, like the interpreter does.
+    fn store_top(&mut self, target: &Expression) -> Result<()> {
+        let span = target.span();
+        match self.location(target)? {
+            Location::Direct(address) => {
+                self.emit(Instruction::Store(address), span);
+            }
+            Location::Indirect => {
+                self.emit(Instruction::Swap, span);
+                self.emit(Instruction::StoreIndirect, span);
+            }
+        }
+        Ok(())
+    }
+
+    /// Location of a variable, emitting the code computing its address if that is dynamic.
+    fn location(&mut self, expression: &Expression) -> Result<Location> {
+        let span = expression.span();
+        let table = self.env.table;
+        match expression {
+            Expression::Identifier(name) => {
+                let Some(id) = self.env.binding(name.span) else {
+                    return Err(Diagnostic::error(span, "Unresolved variable."));
+                };
+                match table.symbol(id).kind {
+                    SymbolKind::Variable { .. } => {
+                        let location = self.symbol_location(id, span)?;
+                        Ok(self.alias(id, location, span))
+                    }
+                    // The return value of the function or method being compiled
+                    SymbolKind::Function | SymbolKind::Method if self.pou == Some(id) => {
+                        Ok(Location::Direct(Address::Local(0)))
+                    }
+                    SymbolKind::Program => {
+                        Ok(Location::Direct(Address::Static(self.programs[&id].0)))
+                    }
+                    _ => Err(Diagnostic::error(span, "This is not a variable.")),
+                }
+            }
+            Expression::Member(member) => {
+                let Some(id) = self.env.binding(member.member.span) else {
+                    return Err(Diagnostic::error(span, "Unresolved member."));
+                };
+                match table.symbol(id).kind {
+                    SymbolKind::Variable {
+                        kind: VariableKind::Global,
+                        ..
+                    } => self.symbol_location(id, span),
+                    SymbolKind::Program => {
+                        Ok(Location::Direct(Address::Static(self.programs[&id].0)))
+                    }
+                    SymbolKind::Variable { .. } => {
+                        let target = self.location(&member.target)?;
+                        let offset = self.offsets.get(&id).copied().unwrap_or(0);
+                        let location = self.field(target, offset, span);
+                        Ok(self.alias(id, location, span))
+                    }
+                    _ => Err(Diagnostic::error(span, "This is not a variable.")),
+                }
+            }
+            Expression::Index(index) => {
+                let Type::Array {
+                    dimensions,
+                    element,
+                } = self.env.type_of(&index.target).dereferenced().clone()
+                else {
+                    return Err(Diagnostic::error(span, "Only arrays can be indexed."));
+                };
+                let bounds = dimensions.iter().flatten().copied().collect::<Vec<_>>();
+                let element_size = self.size_of(&element);
+                let strides = (0..bounds.len())
+                    .map(|i| {
+                        let inner = bounds[i + 1..]
+                            .iter()
+                            .map(|(l, u)| (u - l + 1).max(0) as u32)
+                            .product::<u32>();
+                        inner * element_size
+                    })
+                    .collect::<Vec<_>>();
+
+                let target = self.location(&index.target)?;
+
+                // Constant indices into static places are resolved right away
+                let constant = index
+                    .indices
+                    .iter()
+                    .map(|i| self.env.integer_value(i))
+                    .collect::<Option<Vec<_>>>();
+                if let (Location::Direct(address), Some(indices)) = (target, constant)
+                    && indices.len() == bounds.len()
+                    && indices
+                        .iter()
+                        .zip(&bounds)
+                        .all(|(i, (l, u))| l <= i && i <= u)
+                {
+                    let offset = indices
+                        .iter()
+                        .zip(&bounds)
+                        .zip(&strides)
+                        .map(|((i, (l, _)), stride)| (i - l) as u32 * stride)
+                        .sum();
+                    return Ok(Location::Direct(address.offset(offset)));
+                }
+
+                self.address_of(target, span);
+                for ((i, (lower, upper)), stride) in index.indices.iter().zip(&bounds).zip(strides)
+                {
+                    self.emit_value(i, Kind::Int(Elementary::Lint))?;
+                    let instruction = Instruction::Index {
+                        lower: *lower as i64,
+                        upper: *upper as i64,
+                        stride,
+                    };
+                    self.emit(instruction, span);
+                }
+                Ok(Location::Indirect)
+            }
+            Expression::Deref(target, _) => {
+                self.emit_value(target, Kind::Int(Elementary::Lint))?;
+                Ok(Location::Indirect)
+            }
+            _ => Err(Diagnostic::error(span, "This is not a variable.")),
+        }
+    }
+
+    /// Location of a variable by its declaration, without following references.
+    fn symbol_location(&self, id: SymbolId, span: Span) -> Result<Location> {
+        let table = self.env.table;
+        let symbol = table.symbol(id);
+        let address = match symbol.kind {
+            SymbolKind::Variable {
+                kind: VariableKind::Global,
+                ..
+            } => self.globals.get(&id).map(|a| Address::Static(*a)),
+            // Externals are bound to their global at the declaration
+            SymbolKind::Variable {
+                kind: VariableKind::External,
+                ..
+            } => self
+                .env
+                .binding(symbol.span)
+                .and_then(|g| self.globals.get(&g))
+                .map(|a| Address::Static(*a)),
+            SymbolKind::Variable { .. } => {
+                let owner = table
+                    .scope(symbol.scope)
+                    .owner
+                    .map(|o| table.symbol(o).kind);
+                let offset = self.offsets.get(&id).copied();
+                match owner {
+                    Some(SymbolKind::Function | SymbolKind::Method) => offset.map(Address::Local),
+                    _ => offset.map(Address::This),
+                }
+            }
+            _ => None,
+        };
+        address
+            .map(Location::Direct)
+            .ok_or_else(|| Diagnostic::error(span, "Variable is not accessible here."))
+    }
+
+    /// Follows variables which hold the address of another place.
+    fn alias(&mut self, id: SymbolId, location: Location, span: Span) -> Location {
+        if self.aliases.contains(&id) {
+            self.load(location, Kind::Int(Elementary::Lint), span);
+            return Location::Indirect;
+        }
+        location
+    }
+
+    fn field(&mut self, location: Location, offset: u32, span: Span) -> Location {
+        match location {
+            Location::Direct(address) => Location::Direct(address.offset(offset)),
+            Location::Indirect => {
+                if offset > 0 {
+                    self.emit(Instruction::Offset(offset), span);
+                }
+                Location::Indirect
+            }
+        }
+    }
+
+    fn address_of(&mut self, location: Location, span: Span) {
+        if let Location::Direct(address) = location {
+            self.emit(Instruction::AddressOf(address), span);
+        }
+    }
+
+    fn load(&mut self, location: Location, kind: Kind, span: Span) {
+        match (location, kind) {
+            (location, Kind::Block(_)) => self.address_of(location, span),
+            (Location::Direct(address), _) => {
+                self.emit(Instruction::Load(address), span);
+            }
+            (Location::Indirect, _) => {
+                self.emit(Instruction::LoadIndirect, span);
+            }
+        }
+    }
+
+    /// Cuts a string on top of the stack down to the length of the type it is stored as.
+    fn truncate(&mut self, ty: &Type, span: Span) {
+        if let Some(length) = ty.string_length() {
+            self.emit(Instruction::Truncate(length as u32), span);
+        }
+    }
+
+    fn store(&mut self, location: Location, span: Span) {
+        match location {
+            Location::Direct(address) => self.emit(Instruction::Store(address), span),
+            Location::Indirect => self.emit(Instruction::StoreIndirect, span),
+        };
+    }
+}
+
+fn element_count(dimensions: &[Option<(i128, i128)>]) -> u32 {
+    dimensions
+        .iter()
+        .map(|d| d.map_or(0, |(l, u)| (u - l + 1).max(0) as u32))
+        .product()
+}
+
+fn round(r: f64, precision: Precision) -> f64 {
+    match precision {
+        Precision::Single => r as f32 as f64,
+        Precision::Double => r,
+    }
+}
+
+fn int_operation(op: InfixOperator, ty: Elementary) -> Instruction {
+    match op {
+        InfixOperator::Addition => Instruction::Add(ty),
+        InfixOperator::Subtraction => Instruction::Sub(ty),
+        InfixOperator::Multiplication => Instruction::Mul(ty),
+        InfixOperator::Division => Instruction::Div(ty),
+        InfixOperator::Modulo => Instruction::Mod(ty),
+        InfixOperator::And => Instruction::And,
+        InfixOperator::Or => Instruction::Or,
+        _ => Instruction::Xor,
+    }
+}
+
+fn comparison(kind: Kind) -> Comparison {
+    match kind {
+        Kind::Int(e) if e.is_unsigned() || (e.is_bit() && e.bit_width() == Some(64)) => {
+            Comparison::Unsigned
+        }
+        Kind::Real(_) => Comparison::Real,
+        Kind::String => Comparison::String,
+        _ => Comparison::Int,
+    }
+}
+
+fn compare(kind: Kind, op: InfixOperator) -> Instruction {
+    match comparison(kind) {
+        Comparison::Int => Instruction::CompareInt(op),
+        Comparison::Unsigned => Instruction::CompareUnsigned(op),
+        Comparison::Real => Instruction::CompareReal(op),
+        Comparison::String => Instruction::CompareString(op),
+    }
+}
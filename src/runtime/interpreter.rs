@@ -0,0 +1,1237 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{
+        Action, Argument, Ast, Block, CallExpression, CaseLabel, Expression, LiteralExpression,
+        Pou, Span, Statement, Statements, VariableKind,
+    },
+    parsing::token::NumberValue,
+    runtime::{
+        CYCLE_BUDGET, MAX_CALL_DEPTH, library as runtime_library,
+        value::{self, ArrayValue, Place, Step, Value},
+    },
+    semantic::{
+        consteval::{self, ConstValue},
+        library::{self, StandardBlock},
+        symbols::{SymbolId, SymbolKind, SymbolTable},
+        types::{Elementary, Type, TypeEnv},
+    },
+};
+
+type Result<T> = std::result::Result<T, Diagnostic>;
+
+/// Executes programs by walking their syntax tree.
+///
+/// Globals and program instances live in root slots of the memory which persist between scan
+/// cycles, calls of functions and methods push their local variables on top of them.
+pub struct Interpreter<'a> {
+    env: TypeEnv<'a>,
+    pous: HashMap<SymbolId, &'a Pou>,
+    actions: HashMap<SymbolId, &'a Action>,
+    /// Variables which refer to another place, like VAR_IN_OUT and REFERENCE TO
+    aliases: HashSet<SymbolId>,
+    memory: Vec<Value>,
+    globals: HashMap<SymbolId, usize>,
+    programs: HashMap<SymbolId, usize>,
+    /// Virtual time the standard timers read, in nanoseconds
+    clock: u64,
+    /// Number of calls being executed
+    depth: usize,
+    /// Statements a scan cycle may execute
+    cycle_budget: u64,
+    /// Statements the running scan cycle may still execute
+    budget: u64,
+}
+
+/// Variables visible to the code being executed.
+struct Frame {
+    /// Instance of the function block or program whose code runs
+    this: Option<Place>,
+    locals: HashMap<SymbolId, usize>,
+}
+
+impl Frame {
+    fn empty() -> Self {
+        Self {
+            this: None,
+            locals: HashMap::new(),
+        }
+    }
+}
+
+/// How execution continues after a statement.
+#[derive(PartialEq)]
+enum Flow {
+    Next,
+    Exit,
+    Continue,
+    Return,
+}
+
+impl<'a> Interpreter<'a> {
+    /// Instantiates all global variables and programs with their initial values.
+    pub fn new(ast: &'a Ast, table: &'a SymbolTable) -> Result<Self> {
+        let env = TypeEnv::new(table);
+        let aliases = table
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| match s.kind {
+                SymbolKind::Variable {
+                    kind: VariableKind::InOut,
+                    ..
+                } => true,
+                SymbolKind::Variable { .. } => {
+                    matches!(
+                        s.data_type.as_ref().map(|t| env.lower(t)),
+                        Some(Type::Reference(_))
+                    )
+                }
+                _ => false,
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut interpreter = Self {
+            env,
+            pous: HashMap::new(),
+            actions: HashMap::new(),
+            aliases,
+            memory: Vec::new(),
+            globals: HashMap::new(),
+            programs: HashMap::new(),
+            clock: 0,
+            depth: 0,
+            cycle_budget: CYCLE_BUDGET,
+            budget: CYCLE_BUDGET,
+        };
+        interpreter.collect(&ast.blocks);
+
+        let table = interpreter.env.table;
+        for (id, symbol) in table.symbols.iter().enumerate() {
+            if let SymbolKind::Variable {
+                kind: VariableKind::Global,
+                ..
+            } = symbol.kind
+            {
+                let value = interpreter.initial_value(id)?;
+                interpreter.memory.push(value);
+                interpreter.globals.insert(id, interpreter.memory.len() - 1);
+            }
+        }
+        for (id, symbol) in table.symbols.iter().enumerate() {
+            if symbol.kind == SymbolKind::Program {
+                let value = interpreter.instance(id)?;
+                interpreter.memory.push(value);
+                interpreter
+                    .programs
+                    .insert(id, interpreter.memory.len() - 1);
+            }
+        }
+
+        Ok(interpreter)
+    }
+
+    fn collect(&mut self, blocks: &'a [Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                    self.collect_pou(pou)
+                }
+                Block::Action(action) => self.collect_action(action),
+                Block::Namespace(namespace) => self.collect(&namespace.blocks),
+                Block::Type(_) | Block::GlobalVariables(_) | Block::Configuration(_) => {}
+            }
+        }
+    }
+
+    fn collect_pou(&mut self, pou: &'a Pou) {
+        if let Some(id) = self.env.binding(pou.name.span) {
+            self.pous.insert(id, pou);
+        }
+        for method in &pou.methods {
+            self.collect_pou(method);
+        }
+        for action in &pou.actions {
+            self.collect_action(action);
+        }
+    }
+
+    fn collect_action(&mut self, action: &'a Action) {
+        if let Some(id) = self.env.binding(action.name.span) {
+            self.actions.insert(id, action);
+        }
+    }
+
+    /// Programs in declaration order.
+    pub fn programs(&self) -> Vec<SymbolId> {
+        let mut programs = self.programs.keys().copied().collect::<Vec<_>>();
+        programs.sort();
+        programs
+    }
+
+    /// Finds a program by its possibly qualified name.
+    pub fn program(&self, name: &str) -> Option<SymbolId> {
+        self.programs().into_iter().find(|id| {
+            self.env
+                .table
+                .qualified_name(*id)
+                .eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Executes one scan cycle of a program.
+    pub fn run_cycle(&mut self, program: SymbolId) -> Result<()> {
+        let this = Place::root(self.programs[&program]);
+        self.budget = self.cycle_budget;
+        self.invoke(program, Some(this), None, &Frame::empty())?;
+        Ok(())
+    }
+
+    /// Limits the statements one scan cycle may execute, [`CYCLE_BUDGET`] by default.
+    pub fn set_cycle_budget(&mut self, statements: u64) {
+        self.cycle_budget = statements;
+    }
+
+    /// Sets the virtual clock the standard timers read, in nanoseconds.
+    pub fn set_clock(&mut self, now: u64) {
+        self.clock = now;
+    }
+
+    /// Reads a variable by a path like `Main.counter`, `Main.timer.Q` or a global name.
+    pub fn read(&self, path: &str) -> Option<Value> {
+        let (place, _) = self.place_of_path(path)?;
+        self.get(&place).cloned()
+    }
+
+    /// Overwrites a variable by its path, converting the value to the type of the variable.
+    pub fn write(&mut self, path: &str, value: Value) -> Option<()> {
+        let (place, ty) = self.place_of_path(path)?;
+        let slot = self.get_mut(&place)?;
+        *slot = value.stored_like(slot).truncated(&ty);
+        Some(())
+    }
+
+    /// The place of a variable by its path and its type.
+    fn place_of_path(&self, path: &str) -> Option<(Place, Type)> {
+        let table = self.env.table;
+        let parts = path.split('.').collect::<Vec<_>>();
+        for split in (1..=parts.len()).rev() {
+            let root = parts[..split].join(".");
+            let found = self
+                .programs
+                .iter()
+                .chain(&self.globals)
+                .find(|(id, _)| table.qualified_name(**id).eq_ignore_ascii_case(&root));
+            if let Some((id, slot)) = found {
+                let mut place = Place::root(*slot);
+                let mut ty = self.env.type_of_symbol(*id);
+                for part in &parts[split..] {
+                    place = place.field(part);
+                    ty = match ty {
+                        Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => table
+                            .symbol(id)
+                            .members
+                            .and_then(|members| table.lookup_local(members, part))
+                            .map_or(Type::Unknown, |member| self.env.type_of_symbol(member)),
+                        _ => Type::Unknown,
+                    };
+                }
+                return Some((place, ty));
+            }
+        }
+
+        None
+    }
+
+    fn get(&self, place: &Place) -> Option<&Value> {
+        let mut value = self.memory.get(place.root)?;
+        for step in &place.path {
+            value = match (step, value) {
+                (Step::Field(name), v) => v.field(name)?,
+                (Step::Index(i), Value::Array(array)) => array.items.get(*i)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn get_mut(&mut self, place: &Place) -> Option<&mut Value> {
+        let mut value = self.memory.get_mut(place.root)?;
+        for step in &place.path {
+            value = match (step, value) {
+                (Step::Field(name), v) => v.field_mut(name)?,
+                (Step::Index(i), Value::Array(array)) => array.items.get_mut(*i)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn load(&self, place: &Place, span: Span) -> Result<Value> {
+        self.get(place)
+            .cloned()
+            .ok_or_else(|| Diagnostic::error(span, "Access to an invalid memory location."))
+    }
+
+    fn store(&mut self, place: &Place, value: Value, span: Span) -> Result<()> {
+        let slot = self
+            .get_mut(place)
+            .ok_or_else(|| Diagnostic::error(span, "Access to an invalid memory location."))?;
+        *slot = value.stored_like(slot);
+        Ok(())
+    }
+
+    /// Value of a variable before the first cycle, from its initializer or the type default.
+    fn initial_value(&mut self, id: SymbolId) -> Result<Value> {
+        let symbol = self.env.table.symbol(id);
+        let ty = match &symbol.data_type {
+            Some(t) => self.env.lower(t),
+            None => Type::Unknown,
+        };
+        let mut value = self.default_value(&ty)?;
+        if let Some(initializer) = &symbol.value {
+            self.initialize(&mut value, &ty, initializer)?;
+        }
+        Ok(value)
+    }
+
+    fn default_value(&mut self, ty: &Type) -> Result<Value> {
+        Ok(match ty {
+            Type::Elementary(e) => match e {
+                Elementary::Bool => Value::Bool(false),
+                e if e.is_real() => Value::Real(0.0, *e),
+                e if e.is_duration() => Value::Time(0),
+                e if e.is_date() => Value::Date(0, *e),
+                e => Value::Int(0, *e),
+            },
+            Type::Subrange { base, lower, .. } => Value::Int(lower.unwrap_or(0), *base),
+            Type::String { .. } => Value::String(String::new()),
+            Type::Array {
+                dimensions,
+                element,
+            } => {
+                let dimensions = dimensions.iter().flatten().copied().collect::<Vec<_>>();
+                let count = dimensions
+                    .iter()
+                    .map(|(l, u)| (u - l + 1).max(0))
+                    .product::<i128>();
+                let element = self.default_value(element)?;
+                Value::Array(Box::new(ArrayValue {
+                    dimensions,
+                    items: vec![element; count as usize],
+                }))
+            }
+            Type::Pointer(_) | Type::Reference(_) => Value::Pointer(None),
+            Type::Enum(scope) => {
+                let first = self.env.table.symbols_in(*scope).first().copied();
+                let n = first.and_then(|id| consteval::enum_value(&self.env, id));
+                Value::Enum(n.unwrap_or(0), *scope)
+            }
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => self.instance(*id)?,
+            Type::IntegerLiteral => Value::Int(0, Elementary::Dint),
+            Type::RealLiteral => Value::Real(0.0, Elementary::Lreal),
+            Type::Void | Type::Unknown => Value::Void,
+        })
+    }
+
+    /// Structure value holding all variables of a structure, function block or program.
+    fn instance(&mut self, id: SymbolId) -> Result<Value> {
+        let table = self.env.table;
+        let Some(members) = table.symbol(id).members else {
+            return Ok(Value::Struct(Vec::new()));
+        };
+
+        let mut fields = Vec::new();
+        for member in table.symbols_in(members) {
+            if let SymbolKind::Variable { kind, .. } = table.symbol(member).kind
+                && kind != VariableKind::External
+            {
+                fields.push((
+                    table.symbol(member).name.clone(),
+                    self.initial_value(member)?,
+                ));
+            }
+        }
+        Ok(Value::Struct(fields))
+    }
+
+    /// Applies an initializer, which may be a structure or array initializer, onto a value.
+    fn initialize(&mut self, value: &mut Value, ty: &Type, initializer: &Expression) -> Result<()> {
+        match (initializer, value) {
+            (Expression::Struct(members, _), value @ Value::Struct(_)) => {
+                for (name, member) in members {
+                    let member_ty = match self.env.binding(name.span) {
+                        Some(id) => self.env.type_of_symbol(id),
+                        None => Type::Unknown,
+                    };
+                    if let Some(field) = value.field_mut(&name.name) {
+                        self.initialize(field, &member_ty, member)?;
+                    }
+                }
+            }
+            (Expression::Array(items, _), Value::Array(array)) => {
+                let element = match ty {
+                    Type::Array { element, .. } => element.as_ref().clone(),
+                    _ => Type::Unknown,
+                };
+                for (slot, item) in array.items.iter_mut().zip(items) {
+                    self.initialize(slot, &element, item)?;
+                }
+            }
+            (initializer, value) => {
+                let new = match consteval::evaluate(&self.env, initializer) {
+                    Ok(constant) => self.const_to_value(constant),
+                    Err(_) => self.eval(&Frame::empty(), initializer)?,
+                };
+                *value = new.stored_like(value).truncated(ty);
+            }
+        }
+        Ok(())
+    }
+
+    fn const_to_value(&self, value: ConstValue) -> Value {
+        match value {
+            ConstValue::Bool(b) => Value::Bool(b),
+            ConstValue::Int(n) => Value::Int(n, value::literal_type(n)),
+            ConstValue::Real(r) => Value::Real(r, Elementary::Lreal),
+            ConstValue::String(s) => Value::String(s),
+            ConstValue::Time(t) => Value::Time(t),
+            ConstValue::Date(d, e) => Value::Date(d, e),
+            ConstValue::Enum(id) => self.enum_value(id),
+        }
+    }
+
+    fn enum_value(&self, id: SymbolId) -> Value {
+        let n = consteval::enum_value(&self.env, id).unwrap_or(0);
+        Value::Enum(n, self.env.table.symbol(id).scope)
+    }
+
+    /// Runs a POU with the given arguments and returns the value of a function or method.
+    fn invoke(
+        &mut self,
+        pou: SymbolId,
+        this: Option<Place>,
+        call: Option<&CallExpression>,
+        caller: &Frame,
+    ) -> Result<Value> {
+        let table = self.env.table;
+        let symbol = table.symbol(pou);
+        if self.depth >= MAX_CALL_DEPTH {
+            let span = call.map_or(symbol.span, |c| c.callee.span());
+            return Err(Diagnostic::error(span, "Call stack overflow."));
+        }
+        self.depth += 1;
+        let result = self.invoke_in_frame(pou, this, call, caller);
+        self.depth -= 1;
+        result
+    }
+
+    fn invoke_in_frame(
+        &mut self,
+        pou: SymbolId,
+        this: Option<Place>,
+        call: Option<&CallExpression>,
+        caller: &Frame,
+    ) -> Result<Value> {
+        let table = self.env.table;
+        let symbol = table.symbol(pou);
+        let mark = self.memory.len();
+        let mut frame = Frame {
+            this,
+            locals: HashMap::new(),
+        };
+
+        let variables = symbol
+            .members
+            .map(|m| table.symbols_in(m))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| matches!(table.symbol(*id).kind, SymbolKind::Variable { .. }))
+            .collect::<Vec<_>>();
+
+        // Functions and methods start from scratch, instances only reset their temporaries
+        let stateless = matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method);
+        for id in &variables {
+            let SymbolKind::Variable { kind, .. } = table.symbol(*id).kind else {
+                continue;
+            };
+            if stateless && kind != VariableKind::External {
+                let value = self.initial_value(*id)?;
+                self.memory.push(value);
+                frame.locals.insert(*id, self.memory.len() - 1);
+            } else if kind == VariableKind::Temp
+                && let Some(this) = &frame.this
+            {
+                let value = self.initial_value(*id)?;
+                let place = this.field(&table.symbol(*id).name);
+                self.store(&place, value, symbol.span)?;
+            }
+        }
+        if stateless && let Some(return_type) = &symbol.data_type {
+            let value = self.default_value(&self.env.lower(return_type))?;
+            self.memory.push(value);
+            frame.locals.insert(pou, self.memory.len() - 1);
+        }
+
+        if let Some(call) = call {
+            self.bind_inputs(&frame, &variables, call, caller)?;
+        }
+
+        let body = match (self.pous.get(&pou), self.actions.get(&pou)) {
+            (Some(p), _) => Some(&p.statements),
+            (_, Some(a)) => Some(&a.statements),
+            _ => None,
+        };
+        match (body, StandardBlock::of(table, pou), &frame.this) {
+            (Some(body), _, _) => {
+                self.exec_statements(&frame, body)?;
+            }
+            (None, Some(block), Some(this)) => self.execute_block(block, this, call)?,
+            _ => {
+                let message = format!("'{}' has no body to execute.", symbol.name);
+                return Err(Diagnostic::error(symbol.span, message));
+            }
+        }
+
+        if let Some(call) = call {
+            self.bind_outputs(&frame, call, caller)?;
+        }
+
+        let result = match frame.locals.get(&pou) {
+            Some(slot) => self.memory[*slot].clone(),
+            None => Value::Void,
+        };
+        self.memory.truncate(mark);
+        Ok(result)
+    }
+
+    /// Runs a standard function block on its instance.
+    fn execute_block(
+        &mut self,
+        block: StandardBlock,
+        this: &Place,
+        call: Option<&CallExpression>,
+    ) -> Result<()> {
+        let now = self.clock as i128;
+        let Some(Value::Struct(fields)) = self.get_mut(this) else {
+            let span = call.map(|c| c.span).unwrap_or_default();
+            return Err(Diagnostic::error(
+                span,
+                "Access to an invalid memory location.",
+            ));
+        };
+        let mut values = fields
+            .iter()
+            .map(|(_, v)| v.as_int().unwrap_or_default())
+            .collect::<Vec<_>>();
+        runtime_library::execute(block, &mut values, now);
+        for ((_, field), n) in fields.iter_mut().zip(values) {
+            *field = Value::Int(n, Elementary::Lint).stored_like(field);
+        }
+        Ok(())
+    }
+
+    fn bind_inputs(
+        &mut self,
+        frame: &Frame,
+        variables: &[SymbolId],
+        call: &CallExpression,
+        caller: &Frame,
+    ) -> Result<()> {
+        let table = self.env.table;
+        let mut positional = variables.iter().filter(|id| {
+            matches!(
+                table.symbol(**id).kind,
+                SymbolKind::Variable {
+                    kind: VariableKind::Input | VariableKind::InOut,
+                    ..
+                }
+            )
+        });
+
+        for argument in &call.arguments {
+            let parameter = match argument {
+                Argument::Positional(_) => positional.next().copied(),
+                Argument::Named(name, _) => self.env.binding(name.span),
+                Argument::Output(..) => continue,
+            };
+            let Some(parameter) = parameter else {
+                continue;
+            };
+
+            let place = self.raw_place_of_symbol(frame, parameter, call.span)?;
+            let value = argument.value();
+            if self.aliases.contains(&parameter) {
+                let target = self.place_of(caller, value)?;
+                self.store(&place, Value::Pointer(Some(target)), value.span())?;
+            } else {
+                let v = self.eval(caller, value)?;
+                let v = v.truncated(&self.env.type_of_symbol(parameter));
+                self.store(&place, v, value.span())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_outputs(&mut self, frame: &Frame, call: &CallExpression, caller: &Frame) -> Result<()> {
+        for argument in &call.arguments {
+            if let Argument::Output(name, target) = argument
+                && let Some(parameter) = self.env.binding(name.span)
+            {
+                let place = self.place_of_symbol(frame, parameter, name.span)?;
+                let value = self.load(&place, name.span)?;
+                let value = value.truncated(&self.env.type_of(target));
+                let target_place = self.place_of(caller, target)?;
+                self.store(&target_place, value, target.span())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_statements(&mut self, frame: &Frame, statements: &Statements) -> Result<Flow> {
+        for statement in statements {
+            let flow = self.exec(frame, statement)?;
+            if flow != Flow::Next {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Counts a step of the scan cycle against its budget, stopping code that loops forever.
+    fn tick(&mut self, span: Span) -> Result<()> {
+        match self.budget.checked_sub(1) {
+            Some(budget) => {
+                self.budget = budget;
+                Ok(())
+            }
+            None => {
+                let message = format!(
+                    "The scan cycle ran more than {} statements, it may loop forever.",
+                    self.cycle_budget
+                );
+                Err(Diagnostic::error(span, message))
+            }
+        }
+    }
+
+    fn exec(&mut self, frame: &Frame, statement: &Statement) -> Result<Flow> {
+        self.tick(statement.span())?;
+        match statement {
+            Statement::Empty(_) => {}
+            Statement::Return(_) => return Ok(Flow::Return),
+            Statement::Exit(_) => return Ok(Flow::Exit),
+            Statement::Continue(_) => return Ok(Flow::Continue),
+            Statement::Expression(expression) => {
+                self.eval(frame, expression)?;
+            }
+            Statement::Assignment(assignment) => {
+                let value = self.eval(frame, &assignment.value)?;
+                let value = value.truncated(&self.env.type_of(&assignment.target));
+                let place = self.place_of(frame, &assignment.target)?;
+                self.store(&place, value, assignment.target.span())?;
+            }
+            Statement::If(condition) => {
+                for branch in std::iter::once(&condition.branch).chain(&condition.alt_branches) {
+                    if self.eval_condition(frame, &branch.condition)? {
+                        return self.exec_statements(frame, &branch.statements);
+                    }
+                }
+                if let Some(fallback) = &condition.fallback {
+                    return self.exec_statements(frame, fallback);
+                }
+            }
+            Statement::Case(case) => {
+                let selector = self.eval(frame, &case.selector)?;
+                for branch in &case.branches {
+                    for label in &branch.labels {
+                        if self.matches_label(frame, &selector, label)? {
+                            return self.exec_statements(frame, &branch.statements);
+                        }
+                    }
+                }
+                if let Some(fallback) = &case.fallback {
+                    return self.exec_statements(frame, fallback);
+                }
+            }
+            Statement::For(for_loop) => {
+                let span = for_loop.variable.span;
+                let variable = Expression::Identifier(for_loop.variable.clone());
+                let place = self.place_of(frame, &variable)?;
+                let start = self.eval(frame, &for_loop.start)?;
+                let end = self.eval_int(frame, &for_loop.end)?;
+                let step = match &for_loop.step {
+                    Some(step) => self.eval_int(frame, step)?,
+                    None => 1,
+                };
+                self.store(&place, start, span)?;
+
+                loop {
+                    self.tick(span)?;
+                    let current = self.load(&place, span)?;
+                    let n = current.as_int().unwrap_or_default();
+                    if (step >= 0 && n > end) || (step < 0 && n < end) {
+                        break;
+                    }
+
+                    match self.exec_statements(frame, &for_loop.statements)? {
+                        Flow::Exit => break,
+                        Flow::Return => return Ok(Flow::Return),
+                        Flow::Next | Flow::Continue => {}
+                    }
+
+                    // Stop instead of wrapping around at the end of the range of the variable
+                    let next = n + step;
+                    if let Value::Int(_, ty) = current
+                        && ty.int_range().is_some_and(|(l, u)| next < l || next > u)
+                    {
+                        break;
+                    }
+                    self.store(&place, Value::Int(next, Elementary::Lint), span)?;
+                }
+            }
+            Statement::While(while_loop) => {
+                while self.eval_condition(frame, &while_loop.condition)? {
+                    self.tick(while_loop.condition.span())?;
+                    match self.exec_statements(frame, &while_loop.statements)? {
+                        Flow::Exit => break,
+                        Flow::Return => return Ok(Flow::Return),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Statement::Repeat(repeat_loop) => loop {
+                self.tick(repeat_loop.condition.span())?;
+                match self.exec_statements(frame, &repeat_loop.statements)? {
+                    Flow::Exit => break,
+                    Flow::Return => return Ok(Flow::Return),
+                    Flow::Next | Flow::Continue => {}
+                }
+                if self.eval_condition(frame, &repeat_loop.condition)? {
+                    break;
+                }
+            },
+        }
+        Ok(Flow::Next)
+    }
+
+    fn matches_label(
+        &mut self,
+        frame: &Frame,
+        selector: &Value,
+        label: &CaseLabel,
+    ) -> Result<bool> {
+        let n = selector.as_int();
+        Ok(match label {
+            CaseLabel::Value(value) => self.eval(frame, value)?.as_int() == n,
+            CaseLabel::Range(range) => {
+                let lower = self.eval_int(frame, &range.lower)?;
+                let upper = self.eval_int(frame, &range.upper)?;
+                n.is_some_and(|n| lower <= n && n <= upper)
+            }
+        })
+    }
+
+    fn eval_condition(&mut self, frame: &Frame, condition: &Expression) -> Result<bool> {
+        self.eval(frame, condition)?
+            .as_bool()
+            .ok_or_else(|| Diagnostic::error(condition.span(), "Condition is not a BOOL."))
+    }
+
+    fn eval_int(&mut self, frame: &Frame, expression: &Expression) -> Result<i128> {
+        self.eval(frame, expression)?
+            .as_int()
+            .ok_or_else(|| Diagnostic::error(expression.span(), "Expected an integer."))
+    }
+
+    fn eval(&mut self, frame: &Frame, expression: &Expression) -> Result<Value> {
+        let span = expression.span();
+        match expression {
+            Expression::Literal(literal, _) => Ok(match literal {
+                LiteralExpression::Number(NumberValue::Int(n)) => {
+                    let n = *n as i128;
+                    Value::Int(n, value::literal_type(n))
+                }
+                LiteralExpression::Number(NumberValue::Float(f)) => {
+                    Value::Real(*f, Elementary::Lreal)
+                }
+                LiteralExpression::True => Value::Bool(true),
+                LiteralExpression::False => Value::Bool(false),
+                LiteralExpression::String(s, _) => Value::String(s.clone()),
+                LiteralExpression::Time(_) => match consteval::evaluate(&self.env, expression) {
+                    Ok(ConstValue::Time(t)) => Value::Time(t),
+                    _ => Value::Time(0),
+                },
+                LiteralExpression::Date(d) => {
+                    let e = Elementary::of_date(d.kind);
+                    Value::Date(d.nanoseconds().unwrap_or_default().into(), e)
+                }
+            }),
+            Expression::Identifier(name) => match self.env.binding(name.span) {
+                Some(id) if self.env.table.symbol(id).kind == SymbolKind::EnumValue => {
+                    Ok(self.enum_value(id))
+                }
+                _ => {
+                    let place = self.place_of(frame, expression)?;
+                    self.load(&place, span)
+                }
+            },
+            Expression::Member(member) => match self.env.binding(member.member.span) {
+                Some(id) if self.env.table.symbol(id).kind == SymbolKind::EnumValue => {
+                    Ok(self.enum_value(id))
+                }
+                _ => {
+                    let place = self.place_of(frame, expression)?;
+                    self.load(&place, span)
+                }
+            },
+            Expression::Index(_) | Expression::Deref(..) => {
+                let place = self.place_of(frame, expression)?;
+                self.load(&place, span)
+            }
+            Expression::Prefix(prefix) => {
+                let operand = self.eval(frame, &prefix.operand)?;
+                value::unary(prefix.op, operand, span)
+            }
+            Expression::Infix(infix) => {
+                let mut left = self.eval(frame, &infix.left)?;
+                let mut right = self.eval(frame, &infix.right)?;
+                if is_untyped_literal(&infix.left) {
+                    left = left.retyped(&right);
+                } else if is_untyped_literal(&infix.right) {
+                    right = right.retyped(&left);
+                }
+                value::binary(infix.op, left, right, span)
+            }
+            Expression::Call(call) => self.call(frame, call),
+            Expression::TypedLiteral(literal) => match consteval::evaluate(&self.env, expression) {
+                Ok(constant) => {
+                    let name = literal.type_name.last();
+                    let like = match Elementary::from_name(&name.name) {
+                        Some(e) => self.default_value(&Type::Elementary(e))?,
+                        None => Value::Void,
+                    };
+                    Ok(self.const_to_value(constant).stored_like(&like))
+                }
+                Err(_) => Err(Diagnostic::error(span, "Invalid typed literal.")),
+            },
+            Expression::Array(..) | Expression::Struct(..) => Err(Diagnostic::error(
+                span,
+                "Structure and array initializers are only allowed in declarations.",
+            )),
+        }
+    }
+
+    fn call(&mut self, frame: &Frame, call: &CallExpression) -> Result<Value> {
+        let callee = call.callee.as_ref();
+        let (binding, target) = match callee {
+            Expression::Identifier(name) => (self.env.binding(name.span), None),
+            Expression::Member(member) => (
+                self.env.binding(member.member.span),
+                Some(member.target.as_ref()),
+            ),
+            _ => (None, None),
+        };
+        if binding.is_none()
+            && !matches!(callee, Expression::Identifier(_))
+            && let Type::FunctionBlock(fb) = self.env.type_of(callee).dereferenced().clone()
+        {
+            let place = self.instance_place(frame, callee)?;
+            return self.invoke(fb, Some(place), Some(call), frame);
+        }
+        let Some(id) = binding else {
+            if let Expression::Identifier(name) = callee
+                && let Some(function) = library::lookup(&name.name)
+            {
+                let mut arguments = Vec::new();
+                let mut untyped = Vec::new();
+                for argument in &call.arguments {
+                    arguments.push(self.eval(frame, argument.value())?);
+                    untyped.push(is_untyped_literal(argument.value()));
+                }
+                return runtime_library::call(function, arguments, &untyped, callee.span());
+            }
+            let message = "The called function is not available at runtime.";
+            return Err(Diagnostic::error(callee.span(), message));
+        };
+
+        match self.env.table.symbol(id).kind {
+            SymbolKind::Function => self.invoke(id, None, Some(call), frame),
+            SymbolKind::Program => {
+                let this = Place::root(self.programs[&id]);
+                self.invoke(id, Some(this), Some(call), frame)
+            }
+            SymbolKind::Method | SymbolKind::Action => {
+                let this = match target {
+                    Some(target) => Some(self.instance_place(frame, target)?),
+                    None => frame.this.clone(),
+                };
+                self.invoke(id, this, Some(call), frame)
+            }
+            SymbolKind::Variable { .. } => {
+                let place = self.place_of(frame, callee)?;
+                let Type::FunctionBlock(fb) = self.env.type_of_symbol(id).dereferenced().clone()
+                else {
+                    return Err(Diagnostic::error(callee.span(), "This cannot be called."));
+                };
+                self.invoke(fb, Some(place), Some(call), frame)
+            }
+            _ => Err(Diagnostic::error(callee.span(), "This cannot be called.")),
+        }
+    }
+
+    /// Place of a function block instance or program named by an expression.
+    fn instance_place(&mut self, frame: &Frame, target: &Expression) -> Result<Place> {
+        let binding = match target {
+            Expression::Identifier(name) => self.env.binding(name.span),
+            Expression::Member(member) => self.env.binding(member.member.span),
+            _ => None,
+        };
+        match binding.and_then(|id| self.programs.get(&id)) {
+            Some(slot) => Ok(Place::root(*slot)),
+            None => self.place_of(frame, target),
+        }
+    }
+
+    /// The location an expression denotes, for assignments and reading variables.
+    fn place_of(&mut self, frame: &Frame, expression: &Expression) -> Result<Place> {
+        let span = expression.span();
+        match expression {
+            Expression::Identifier(name) => {
+                let Some(id) = self.env.binding(name.span) else {
+                    return Err(Diagnostic::error(span, "Unresolved variable."));
+                };
+                self.place_of_symbol(frame, id, span)
+            }
+            Expression::Member(member) => {
+                let Some(id) = self.env.binding(member.member.span) else {
+                    return Err(Diagnostic::error(span, "Unresolved member."));
+                };
+                let symbol = self.env.table.symbol(id);
+                match symbol.kind {
+                    SymbolKind::Variable {
+                        kind: VariableKind::Global,
+                        ..
+                    } => self.place_of_symbol(frame, id, span),
+                    _ => {
+                        let target = self.instance_place(frame, &member.target)?;
+                        let place = target.field(&symbol.name);
+                        self.resolve_alias(id, place, span)
+                    }
+                }
+            }
+            Expression::Index(index) => {
+                let target = self.place_of(frame, &index.target)?;
+                let mut indices = Vec::new();
+                for i in &index.indices {
+                    indices.push(self.eval_int(frame, i)?);
+                }
+                let Some(Value::Array(array)) = self.get(&target) else {
+                    return Err(Diagnostic::error(span, "Only arrays can be indexed."));
+                };
+                match array.offset(&indices) {
+                    Some(offset) => Ok(target.index(offset)),
+                    None => {
+                        let bounds = array
+                            .dimensions
+                            .iter()
+                            .map(|(l, u)| format!("{l}..{u}"))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let message = format!("Index is out of the bounds [{bounds}].");
+                        Err(Diagnostic::error(span, message))
+                    }
+                }
+            }
+            Expression::Deref(target, _) => match self.eval(frame, target)? {
+                Value::Pointer(Some(place)) => Ok(place),
+                _ => Err(Diagnostic::error(span, "Dereferenced pointer is null.")),
+            },
+            _ => Err(Diagnostic::error(span, "This is not a variable.")),
+        }
+    }
+
+    fn place_of_symbol(&self, frame: &Frame, id: SymbolId, span: Span) -> Result<Place> {
+        let place = self.raw_place_of_symbol(frame, id, span)?;
+        self.resolve_alias(id, place, span)
+    }
+
+    /// Location of the variable itself, without following references.
+    fn raw_place_of_symbol(&self, frame: &Frame, id: SymbolId, span: Span) -> Result<Place> {
+        if let Some(slot) = frame.locals.get(&id) {
+            return Ok(Place::root(*slot));
+        }
+
+        let symbol = self.env.table.symbol(id);
+        match symbol.kind {
+            SymbolKind::Variable {
+                kind: VariableKind::Global,
+                ..
+            } => Ok(Place::root(self.globals[&id])),
+            // Externals are bound to their global at the declaration
+            SymbolKind::Variable {
+                kind: VariableKind::External,
+                ..
+            } => match self
+                .env
+                .binding(symbol.span)
+                .and_then(|g| self.globals.get(&g))
+            {
+                Some(slot) => Ok(Place::root(*slot)),
+                None => Err(Diagnostic::error(span, "Unresolved external variable.")),
+            },
+            SymbolKind::Variable { .. } => match &frame.this {
+                Some(this) => Ok(this.field(&symbol.name)),
+                None => Err(Diagnostic::error(span, "Variable is not accessible here.")),
+            },
+            _ => Err(Diagnostic::error(span, "This is not a variable.")),
+        }
+    }
+
+    fn resolve_alias(&self, id: SymbolId, place: Place, span: Span) -> Result<Place> {
+        if !self.aliases.contains(&id) {
+            return Ok(place);
+        }
+        match self.get(&place) {
+            Some(Value::Pointer(Some(target))) => Ok(target.clone()),
+            _ => Err(Diagnostic::error(
+                span,
+                "Reference is not bound to a variable.",
+            )),
+        }
+    }
+}
+
+fn is_untyped_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(LiteralExpression::Number(_), _) => true,
+        Expression::Prefix(prefix) => is_untyped_literal(&prefix.operand),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        semantic::resolver::resolve,
+    };
+
+    /// Runs `Main` for the given number of cycles and reads the given variables afterwards.
+    fn run(src: &str, cycles: usize, paths: &[&str]) -> Vec<String> {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, diagnostics) = resolve(&ast);
+        assert_eq!(diagnostics, Vec::new());
+
+        let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+        let main = interpreter.program("Main").unwrap();
+        for _ in 0..cycles {
+            interpreter.run_cycle(main).unwrap();
+        }
+        let env = TypeEnv::new(&table);
+        paths
+            .iter()
+            .map(|p| interpreter.read(p).unwrap().display(&env).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_state_persists_between_cycles() {
+        let values = run(
+            r#"
+        VAR_GLOBAL total : DINT := 100; END_VAR
+
+        PROGRAM Main
+            VAR count : INT; small : SINT := 126; END_VAR
+            VAR_TEMP scratch : INT; END_VAR
+            scratch := scratch + 1;
+            count := count + scratch;
+            small := small + 1;
+            total := total + count;
+        END_PROGRAM
+        "#,
+            3,
+            &["Main.count", "Main.small", "total"],
+        );
+
+        assert_eq!(values, vec!["3", "-127", "106"]);
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let values = run(
+            r#"
+        TYPE MachineState : (Idle, Running := 5, Done); END_TYPE
+
+        PROGRAM Main
+            VAR
+                i : INT;
+                sum : INT;
+                n : INT;
+                state : MachineState;
+                label : STRING;
+                values : ARRAY[1..5] OF INT := [1, 2, 3, 4, 5];
+            END_VAR
+            sum := 0;
+            FOR i := 1 TO 5 DO
+                IF i = 2 THEN CONTINUE; END_IF;
+                IF i = 5 THEN EXIT; END_IF;
+                sum := sum + values[i];
+            END_FOR;
+            n := 0;
+            WHILE n < 10 DO n := n + 3; END_WHILE;
+            REPEAT n := n - 1; UNTIL n <= 5 END_REPEAT;
+            CASE state OF
+                Idle: state := Running; label := 'started';
+                Running..Done: state := Done; label := 'finished';
+            ELSE
+                label := 'unknown';
+            END_CASE;
+        END_PROGRAM
+        "#,
+            2,
+            &["Main.sum", "Main.n", "Main.state", "Main.label", "Main.i"],
+        );
+
+        assert_eq!(
+            values,
+            vec!["8", "5", "MachineState#Done", "'finished'", "5"]
+        );
+    }
+
+    #[test]
+    fn test_calls_function_blocks_and_methods() {
+        let values = run(
+            r#"
+        FUNCTION Clamp : INT
+            VAR_INPUT value : INT; limit : INT := 10; END_VAR
+            Clamp := value;
+            IF value > limit THEN Clamp := limit; END_IF;
+        END_FUNCTION
+
+        FUNCTION Swap
+            VAR_IN_OUT a : INT; b : INT; END_VAR
+            VAR tmp : INT; END_VAR
+            tmp := a; a := b; b := tmp;
+        END_FUNCTION
+
+        FUNCTION_BLOCK UpCounter
+            VAR_INPUT step : INT := 1; END_VAR
+            VAR_OUTPUT count : INT; END_VAR
+            count := count + step;
+            METHOD Reset
+                count := 0;
+            END_METHOD
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR
+                counter : UpCounter;
+                seen : INT;
+                clamped : INT;
+                x : INT := 1;
+                y : INT := 2;
+            END_VAR
+            counter(step := 2, count => seen);
+            IF counter.count >= 6 THEN counter.Reset(); END_IF;
+            clamped := Clamp(seen * 5);
+            Swap(x, y);
+        END_PROGRAM
+        "#,
+            4,
+            &["Main.counter.count", "Main.seen", "Main.clamped", "Main.x"],
+        );
+
+        assert_eq!(values, vec!["2", "2", "10", "1"]);
+    }
+
+    #[test]
+    fn test_calls_function_block_array_elements() {
+        let values = run(
+            r#"
+        FUNCTION_BLOCK Acc
+            VAR_INPUT step : INT; END_VAR
+            VAR_OUTPUT total : INT; END_VAR
+            total := total + step;
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR
+                accs : ARRAY[1..3] OF Acc;
+                i : INT;
+                seen : INT;
+                first : INT;
+                second : INT;
+            END_VAR
+            accs[2](step := 5, total => seen);
+            FOR i := 1 TO 3 DO
+                accs[i](step := i);
+            END_FOR;
+            first := accs[1].total;
+            second := accs[2].total;
+        END_PROGRAM
+        "#,
+            2,
+            &["Main.first", "Main.second", "Main.seen"],
+        );
+
+        assert_eq!(values, vec!["2", "14", "12"]);
+    }
+
+    #[test]
+    fn test_reports_runtime_errors() {
+        let src = r#"
+        PROGRAM Main
+            VAR values : ARRAY[0..2] OF INT; i : INT; END_VAR
+            i := i + 1;
+            values[i * 2] := 1;
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+        let main = interpreter.program("Main").unwrap();
+
+        assert_eq!(interpreter.run_cycle(main), Ok(()));
+        let error = interpreter.run_cycle(main).unwrap_err();
+        assert_eq!(error.message, "Index is out of the bounds [0..2].");
+    }
+
+    #[test]
+    fn test_reports_call_stack_overflow() {
+        let src = r#"
+        FUNCTION Depth : DINT
+            VAR_INPUT n : DINT; END_VAR
+            IF n <= 0 THEN Depth := 0; ELSE Depth := 1 + Depth(n - 1); END_IF;
+        END_FUNCTION
+
+        PROGRAM Main
+            VAR n : DINT := 100; result : DINT; END_VAR
+            result := Depth(n);
+            n := 100000;
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        // Deep enough for the interpreter's frames in debug builds
+        let error = std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(move || {
+                let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+                let main = interpreter.program("Main").unwrap();
+                assert_eq!(interpreter.run_cycle(main), Ok(()));
+                assert_eq!(
+                    interpreter.read("Main.result"),
+                    Some(Value::Int(100, Elementary::Dint))
+                );
+                interpreter.run_cycle(main).unwrap_err()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(error.message, "Call stack overflow.");
+    }
+}
@@ -0,0 +1,567 @@
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{InfixOperator, Span},
+    runtime::value::{self, Value},
+    semantic::{
+        library::{self, Function, MILLISECOND, Scalar, StandardBlock},
+        types::Elementary,
+    },
+};
+
+type Result<T> = std::result::Result<T, Diagnostic>;
+
+/// Nanoseconds of a day, which DATE and TIME_OF_DAY values are split at.
+pub const DAY: i128 = 86_400_000 * MILLISECOND;
+
+/// Note on the errors of failed assertions.
+const ASSERTION_NOTE: &str = "The assertion does not hold.";
+
+/// Calls a standard function of the [`Interpreter`](crate::runtime::interpreter::Interpreter).
+///
+/// `untyped` tells which arguments are untyped literals, which take the type of the other
+/// arguments of the same generic type, like the operands of infix operators do.
+pub fn call(
+    function: Function,
+    arguments: Vec<Value>,
+    untyped: &[bool],
+    span: Span,
+) -> Result<Value> {
+    use Function::*;
+
+    let generic = match function {
+        Sel => 1..3,
+        Mux => 1..arguments.len(),
+        Shl | Shr | Rol | Ror => 0..1,
+        Abs | Max | Min | Limit | AssertEq | AssertNe | AssertNear => 0..arguments.len(),
+        _ => 0..0,
+    };
+    let mut arguments = arguments;
+    unify(&mut arguments[generic.clone()], &untyped[generic]);
+
+    let invalid = |arguments: &[Value]| {
+        let values = arguments
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Diagnostic::error(
+            span,
+            format!("{} is not defined for {values}.", function.name()),
+        )
+    };
+    let int = |value: &Value| value.as_int().ok_or_else(|| invalid(&arguments));
+    let string = |value: &Value| match value {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(invalid(&arguments)),
+    };
+
+    Ok(match (function, &arguments[..]) {
+        (Abs, [Value::Int(n, e)]) => Value::Int(value::wrap(n.abs(), *e), *e),
+        (Abs, [Value::Real(r, e)]) => Value::Real(r.abs(), *e),
+        (Sqrt | Ln | Log | Exp | Sin | Cos | Tan | Asin | Acos | Atan, [x]) => {
+            let (x, e) = real(x).ok_or_else(|| invalid(&arguments))?;
+            Value::Real(round(library::real_function(function, x), e), e)
+        }
+        (Expt, [x, y]) => {
+            let (x, e) = real(x).ok_or_else(|| invalid(&arguments))?;
+            let y = y.as_real().ok_or_else(|| invalid(&arguments))?;
+            Value::Real(round(x.powf(y), e), e)
+        }
+        (Trunc, [Value::Real(r, _)]) => Value::Int(
+            value::wrap(r.trunc() as i128, Elementary::Dint),
+            Elementary::Dint,
+        ),
+        (Sel, [g, a, b]) => match g.as_bool() {
+            Some(true) => b.clone(),
+            Some(false) => a.clone(),
+            None => return Err(invalid(&arguments)),
+        },
+        (Mux, [k, inputs @ ..]) => {
+            let k = int(k)?;
+            match usize::try_from(k).ok().and_then(|k| inputs.get(k)) {
+                Some(input) => input.clone(),
+                None => {
+                    let message = format!("Index is out of the bounds [0..{}].", inputs.len() - 1);
+                    return Err(Diagnostic::error(span, message));
+                }
+            }
+        }
+        (Max | Min, [first, rest @ ..]) => {
+            let op = match function {
+                Max => InfixOperator::GreaterThan,
+                _ => InfixOperator::LessThan,
+            };
+            let mut best = first.clone();
+            for value in rest {
+                best = extremum(op, &best, value, span)?;
+            }
+            best
+        }
+        (Limit, [low, x, high]) => {
+            let x = extremum(InfixOperator::GreaterThan, low, x, span)?;
+            extremum(InfixOperator::LessThan, &x, high, span)?
+        }
+        (Shl | Shr | Rol | Ror, [Value::Bool(b), by]) => {
+            Value::Bool(library::shift(function, *b as i128, int(by)?, 1) != 0)
+        }
+        (Shl | Shr | Rol | Ror, [Value::Int(n, e), by]) => {
+            let width = e.bit_width().unwrap_or(64);
+            let shifted = library::shift(function, *n, int(by)?, width);
+            Value::Int(value::wrap(shifted, *e), *e)
+        }
+        (Len, [s]) => Value::Int(string(s)?.chars().count() as i128, Elementary::Int),
+        (Left | Right, [s, l]) => {
+            Value::String(library::edit_string(function, &string(s)?, "", int(l)?, 0))
+        }
+        (Mid | Delete, [s, l, p]) => {
+            let edited = library::edit_string(function, &string(s)?, "", int(l)?, int(p)?);
+            Value::String(edited)
+        }
+        (Insert, [a, b, p]) => Value::String(library::edit_string(
+            function,
+            &string(a)?,
+            &string(b)?,
+            0,
+            int(p)?,
+        )),
+        (Replace, [a, b, l, p]) => {
+            let edited = library::edit_string(function, &string(a)?, &string(b)?, int(l)?, int(p)?);
+            Value::String(edited)
+        }
+        (Concat, strings) => Value::String(
+            strings
+                .iter()
+                .map(string)
+                .collect::<Result<Vec<_>>>()?
+                .concat(),
+        ),
+        (Find, [a, b]) => Value::Int(library::find(&string(a)?, &string(b)?), Elementary::Int),
+        (Convert(_, to), [x]) => convert(x, to).ok_or_else(|| invalid(&arguments))?,
+        (function, arguments) if function.is_assertion() => {
+            match assertion(function, arguments, span)? {
+                Some(message) => return Err(assertion_failure(span, message)),
+                None => Value::Void,
+            }
+        }
+        _ => return Err(invalid(&arguments)),
+    })
+}
+
+/// Checks an assertion of a unit test, giving the message of its failure.
+///
+/// ASSERT_EQ, ASSERT_NE and ASSERT_NEAR take the expected value first, then the actual one,
+/// ASSERT_NEAR then the tolerance.
+pub fn assertion(function: Function, arguments: &[Value], span: Span) -> Result<Option<String>> {
+    use Function::*;
+
+    let equal = |expected: &Value, actual: &Value| {
+        value::binary(
+            InfixOperator::Equals,
+            actual.clone(),
+            expected.clone(),
+            span,
+        )
+        .map(|equal| matches!(equal, Value::Bool(true)))
+    };
+    let message = match (function, arguments) {
+        (AssertTrue, [Value::Bool(false)]) => Some("Expected TRUE, found FALSE.".to_string()),
+        (AssertFalse, [Value::Bool(true)]) => Some("Expected FALSE, found TRUE.".to_string()),
+        (AssertTrue | AssertFalse, [Value::Bool(_)]) => None,
+        (AssertEq, [expected, actual]) => match equal(expected, actual)? {
+            true => None,
+            false => Some(format!("Expected {expected}, found {actual}.")),
+        },
+        (AssertNe, [expected, actual]) => match equal(expected, actual)? {
+            true => Some(format!("Expected a value other than {actual}.")),
+            false => None,
+        },
+        (AssertNear, [expected, actual, tolerance]) => {
+            match (expected.as_real(), actual.as_real(), tolerance.as_real()) {
+                (Some(e), Some(a), Some(t)) if (a - e).abs() <= t => None,
+                _ => Some(format!(
+                    "Expected {expected} within {tolerance}, found {actual}."
+                )),
+            }
+        }
+        _ => {
+            let message = format!("{} is not defined for these arguments.", function.name());
+            return Err(Diagnostic::error(span, message));
+        }
+    };
+    Ok(message)
+}
+
+/// Error of a failed assertion, which unit tests tell apart from other runtime errors by its note.
+pub fn assertion_failure(span: Span, message: String) -> Diagnostic {
+    Diagnostic::error(span, message).with_note(span, ASSERTION_NOTE)
+}
+
+/// Whether an error of a cycle is a failed assertion rather than another runtime error.
+pub fn is_assertion_failure(diagnostic: &Diagnostic) -> bool {
+    diagnostic
+        .notes
+        .iter()
+        .any(|(_, note)| note == ASSERTION_NOTE)
+}
+
+/// `b` if it compares to `a` by the operator, `a` otherwise.
+fn extremum(op: InfixOperator, a: &Value, b: &Value, span: Span) -> Result<Value> {
+    match value::binary(op, b.clone(), a.clone(), span)? {
+        Value::Bool(true) => Ok(b.clone()),
+        _ => Ok(a.clone()),
+    }
+}
+
+/// Converts the arguments of a generic parameter into the representation of their common type.
+fn unify(values: &mut [Value], untyped: &[bool]) {
+    if let Some(like) = values
+        .iter()
+        .zip(untyped)
+        .find(|(_, untyped)| !**untyped)
+        .map(|(v, _)| v.clone())
+    {
+        for (value, _) in values.iter_mut().zip(untyped).filter(|(_, u)| **u) {
+            *value = std::mem::replace(value, Value::Void).retyped(&like);
+        }
+    }
+
+    let common = values
+        .iter()
+        .fold(None, |common, value| match (common, value) {
+            (None, Value::Int(_, e) | Value::Real(_, e)) => Some(*e),
+            (Some(c), Value::Int(_, e) | Value::Real(_, e)) if c.widens_to(*e) => Some(*e),
+            (common, _) => common,
+        });
+    if let Some(common) = common {
+        for value in values.iter_mut() {
+            match value {
+                Value::Int(n, _) if common.is_real() => *value = Value::Real(*n as f64, common),
+                Value::Int(_, e) | Value::Real(_, e) => *e = common,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A real argument with its type, integer literals compute in LREAL.
+fn real(value: &Value) -> Option<(f64, Elementary)> {
+    match value {
+        Value::Real(r, e) => Some((*r, *e)),
+        Value::Int(n, _) => Some((*n as f64, Elementary::Lreal)),
+        _ => None,
+    }
+}
+
+fn round(r: f64, ty: Elementary) -> f64 {
+    match ty {
+        Elementary::Real => r as f32 as f64,
+        _ => r,
+    }
+}
+
+/// The `*_TO_*` conversions, reals are rounded to the nearest integer.
+///
+/// Durations and dates convert to and from the other types in milliseconds.
+pub fn convert(value: &Value, to: Scalar) -> Option<Value> {
+    let Scalar::Elementary(to) = to else {
+        return Some(Value::String(to_string(value)));
+    };
+
+    let int = match value {
+        Value::Bool(b) => *b as i128,
+        Value::Int(n, _) | Value::Time(n) | Value::Date(n, _) | Value::Enum(n, _) => *n,
+        Value::Real(r, _) if to.is_real() => return Some(Value::Real(round(*r, to), to)),
+        Value::Real(r, _) if to == Elementary::Bool => return Some(Value::Bool(*r != 0.0)),
+        Value::Real(r, _) => r.round_ties_even() as i128,
+        Value::String(s) => match to {
+            Elementary::Bool => return Some(Value::Bool(library::parse_bool(s))),
+            e if e.is_real() => return Some(Value::Real(round(library::parse_real(s), e), e)),
+            Elementary::Char | Elementary::WChar => s.chars().next().map_or(0, |c| c as i128),
+            _ => library::parse_int(s),
+        },
+        _ => return None,
+    };
+    let int = match (
+        matches!(value, Value::Time(_) | Value::Date(..)),
+        to.is_temporal(),
+    ) {
+        (true, false) if to != Elementary::Bool => int / MILLISECOND,
+        (false, true) => int * MILLISECOND,
+        _ => int,
+    };
+
+    Some(match to {
+        Elementary::Bool => Value::Bool(int != 0),
+        e if e.is_real() => Value::Real(round(int as f64, e), e),
+        e if e.is_duration() => Value::Time(int),
+        Elementary::Date => Value::Date(int - int.rem_euclid(DAY), to),
+        Elementary::TimeOfDay => Value::Date(int.rem_euclid(DAY), to),
+        Elementary::DateAndTime => Value::Date(int, to),
+        e => Value::Int(value::wrap(int, e), e),
+    })
+}
+
+/// Text of a value as the conversions to STRING produce it.
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Int(n, Elementary::Char | Elementary::WChar) => u32::try_from(*n)
+            .ok()
+            .and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default(),
+        Value::Real(r, e) => library::format_real(*r, *e),
+        Value::Time(t) => library::format_time(*t),
+        v => v.to_string(),
+    }
+}
+
+/// Executes one call of a standard function block on the values of its variables.
+///
+/// The values follow the order of [`StandardBlock::variables`] with BOOL as 0 or 1 and TIME in
+/// nanoseconds, `now` is the virtual clock in nanoseconds.
+pub fn execute(block: StandardBlock, values: &mut [i128], now: i128) {
+    let (min, max) = Elementary::Int.int_range().expect("INT has a range");
+    match (block, values) {
+        (StandardBlock::Ton, [input, pt, q, et, m, start]) => {
+            if *input != 0 {
+                if *m == 0 {
+                    *start = now;
+                }
+                *et = (now - *start).min(*pt);
+                *q = (*et >= *pt) as i128;
+            } else {
+                *q = 0;
+                *et = 0;
+            }
+            *m = *input;
+        }
+        (StandardBlock::Tof, [input, pt, q, et, m, start]) => {
+            if *input != 0 {
+                *q = 1;
+                *et = 0;
+            } else if *q != 0 {
+                if *m != 0 {
+                    *start = now;
+                }
+                *et = (now - *start).min(*pt);
+                *q = (*et < *pt) as i128;
+            }
+            *m = *input;
+        }
+        (StandardBlock::Tp, [input, pt, q, et, m, start]) => {
+            // Rising edges only start a pulse once the previous one ended
+            if *input != 0 && *m == 0 && *q == 0 {
+                *q = 1;
+                *start = now;
+            }
+            if *q != 0 {
+                *et = (now - *start).min(*pt);
+                *q = (*et < *pt) as i128;
+            } else if *input == 0 {
+                *et = 0;
+            }
+            *m = *input;
+        }
+        (StandardBlock::Ctu, [cu, r, pv, q, cv, m]) => {
+            if *r != 0 {
+                *cv = 0;
+            } else if *cu != 0 && *m == 0 && *cv < max {
+                *cv += 1;
+            }
+            *q = (*cv >= *pv) as i128;
+            *m = *cu;
+        }
+        (StandardBlock::Ctd, [cd, ld, pv, q, cv, m]) => {
+            if *ld != 0 {
+                *cv = *pv;
+            } else if *cd != 0 && *m == 0 && *cv > min {
+                *cv -= 1;
+            }
+            *q = (*cv <= 0) as i128;
+            *m = *cd;
+        }
+        (StandardBlock::Ctud, [cu, cd, r, ld, pv, qu, qd, cv, mu, md]) => {
+            let up = *cu != 0 && *mu == 0;
+            let down = *cd != 0 && *md == 0;
+            if *r != 0 {
+                *cv = 0;
+            } else if *ld != 0 {
+                *cv = *pv;
+            } else if up && !down && *cv < max {
+                *cv += 1;
+            } else if down && !up && *cv > min {
+                *cv -= 1;
+            }
+            *qu = (*cv >= *pv) as i128;
+            *qd = (*cv <= 0) as i128;
+            *mu = *cu;
+            *md = *cd;
+        }
+        (StandardBlock::RTrig, [clk, q, m]) => {
+            *q = (*clk != 0 && *m == 0) as i128;
+            *m = *clk;
+        }
+        // As the standard defines it, a first call with CLK FALSE reports a falling edge
+        (StandardBlock::FTrig, [clk, q, m]) => {
+            *q = (*clk == 0 && *m == 0) as i128;
+            *m = (*clk == 0) as i128;
+        }
+        (StandardBlock::Sr, [s1, r, q1]) => *q1 = (*s1 != 0 || (*r == 0 && *q1 != 0)) as i128,
+        (StandardBlock::Rs, [s, r1, q1]) => *q1 = (*r1 == 0 && (*s != 0 || *q1 != 0)) as i128,
+        (block, values) => unreachable!(
+            "{} has {} variables, not {}",
+            block.name(),
+            block.variables().len(),
+            values.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INT: Elementary = Elementary::Int;
+
+    fn run(function: &str, arguments: Vec<Value>, untyped: &[bool]) -> Result<Value> {
+        let function = library::lookup(function).unwrap();
+        call(function, arguments, untyped, Span::default())
+    }
+
+    #[test]
+    fn test_generic_arguments_take_the_common_type() {
+        let max = run(
+            "MAX",
+            vec![
+                Value::Int(5, Elementary::Dint),
+                Value::Int(3, Elementary::Sint),
+            ],
+            &[true, false],
+        );
+        assert_eq!(max, Ok(Value::Int(5, Elementary::Sint)));
+
+        let min = run(
+            "MIN",
+            vec![Value::Int(5, INT), Value::Real(2.5, Elementary::Real)],
+            &[false, false],
+        );
+        assert_eq!(min, Ok(Value::Real(2.5, Elementary::Real)));
+
+        let mux = run(
+            "MUX",
+            vec![Value::Int(3, INT), Value::Int(1, INT), Value::Int(2, INT)],
+            &[false, false, false],
+        );
+        assert_eq!(
+            mux.unwrap_err().message,
+            "Index is out of the bounds [0..1]."
+        );
+    }
+
+    #[test]
+    fn test_timers_follow_the_clock() {
+        // IN, PT, Q, ET after every call of the timer at the given time
+        let run = |block: StandardBlock, steps: &[(i128, bool)]| {
+            let mut values = [0, 100, 0, 0, 0, 0];
+            steps
+                .iter()
+                .map(|(now, input)| {
+                    values[0] = *input as i128;
+                    execute(block, &mut values, *now);
+                    (values[2] != 0, values[3])
+                })
+                .collect::<Vec<_>>()
+        };
+        let steps = [
+            (0, true),
+            (60, true),
+            (100, true),
+            (150, false),
+            (200, true),
+            (230, false),
+            (300, false),
+            (400, false),
+        ];
+
+        let ton = run(StandardBlock::Ton, &steps);
+        let tof = run(StandardBlock::Tof, &steps);
+        let tp = run(StandardBlock::Tp, &steps);
+        assert_eq!(
+            ton,
+            [
+                (false, 0),
+                (false, 60),
+                (true, 100),
+                (false, 0),
+                (false, 0),
+                (false, 0),
+                (false, 0),
+                (false, 0)
+            ]
+        );
+        assert_eq!(
+            tof,
+            [
+                (true, 0),
+                (true, 0),
+                (true, 0),
+                (true, 0),
+                (true, 0),
+                (true, 0),
+                (true, 70),
+                (false, 100)
+            ]
+        );
+        assert_eq!(
+            tp,
+            [
+                (true, 0),
+                (true, 60),
+                (false, 100),
+                (false, 0),
+                (true, 0),
+                (true, 30),
+                (false, 100),
+                (false, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let to = |v: Value, name: &str| convert(&v, Scalar::from_name(name).unwrap());
+        assert_eq!(
+            to(Value::Real(2.5, Elementary::Real), "INT"),
+            Some(Value::Int(2, INT))
+        );
+        assert_eq!(
+            to(Value::Int(300, INT), "SINT"),
+            Some(Value::Int(44, Elementary::Sint))
+        );
+        assert_eq!(
+            to(Value::Time(1500 * MILLISECOND), "DINT"),
+            Some(Value::Int(1500, Elementary::Dint))
+        );
+        assert_eq!(
+            to(Value::Int(2, INT), "TIME"),
+            Some(Value::Time(2 * MILLISECOND))
+        );
+        assert_eq!(
+            to(Value::Time(500_000), "STRING"),
+            Some(Value::String("T#500us".to_string()))
+        );
+        assert_eq!(
+            to(Value::Real(0.5, Elementary::Lreal), "STRING"),
+            Some(Value::String("0.5".to_string()))
+        );
+        assert_eq!(
+            to(Value::String(" 42 ".to_string()), "UINT"),
+            Some(Value::Int(42, Elementary::Uint))
+        );
+        assert_eq!(
+            to(Value::Date(DAY + 5, Elementary::DateAndTime), "TOD"),
+            Some(Value::Date(5, Elementary::TimeOfDay))
+        );
+    }
+}
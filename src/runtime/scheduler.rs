@@ -0,0 +1,776 @@
+use std::fmt::Write;
+
+use crate::{
+    diagnostic::Diagnostic,
+    formats::{json::Json, toml},
+    parsing::ast::{self, Ast, Block, Expression, Span},
+    runtime::{bytecode::CostModel, vm::Vm},
+    semantic::{
+        consteval::{self, ConstValue},
+        symbols::{SymbolId, SymbolKind, SymbolTable},
+        types::TypeEnv,
+    },
+};
+
+/// A cyclic task as configured, all times are in nanoseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskConfig {
+    pub name: String,
+    pub interval: u64,
+    /// Lower numbers are more urgent
+    pub priority: u32,
+    /// Programs run in this order on every activation
+    pub programs: Vec<String>,
+}
+
+/// Tasks of a resource together with the cost model of its target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Configuration {
+    pub tasks: Vec<TaskConfig>,
+    pub costs: CostModel,
+}
+
+impl Configuration {
+    /// Reads a configuration from a `.toml` or `.json` file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let src = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {path}: {e}"))?;
+        match path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+        {
+            Some(ext) if ext == "toml" => Self::from_toml(&src),
+            Some(ext) if ext == "json" => Self::from_json(&src),
+            _ => Err(format!("{path} is neither a TOML nor a JSON file.")),
+        }
+    }
+
+    /// Parses a configuration with a `[[task]]` table per task.
+    ///
+    /// ```toml
+    /// [[task]]
+    /// name = "Fast"
+    /// interval = "1ms"
+    /// priority = 1
+    /// programs = ["Main"]
+    /// ```
+    pub fn from_toml(src: &str) -> Result<Self, String> {
+        Self::from_document(&toml::parse(src)?)
+    }
+
+    /// Parses a configuration like `{"tasks": [{"name": "Fast", "interval": "1ms", ...}]}`.
+    pub fn from_json(src: &str) -> Result<Self, String> {
+        Self::from_document(&Json::parse(src)?)
+    }
+
+    /// Collects the cyclic tasks of all resources of the CONFIGURATION declarations in a source,
+    /// including those within namespaces. The tasks are empty if the source declares none.
+    ///
+    /// Instances without a task are not scheduled, those [`check_instances`] reports are errors.
+    pub fn from_source(ast: &Ast, table: &SymbolTable) -> Result<Self, Vec<Diagnostic>> {
+        let env = TypeEnv::new(table);
+        let mut tasks = Vec::new();
+        let mut diagnostics = check_instances(ast, table);
+        let mut instantiated = Vec::new();
+
+        let mut configurations = Vec::new();
+        collect_configurations(&ast.blocks, &mut configurations);
+        for resource in configurations.into_iter().flat_map(|c| &c.resources) {
+            let first = tasks.len();
+            for task in &resource.tasks {
+                match Self::task_of(&env, task) {
+                    Ok(task) => tasks.push(task),
+                    Err(diagnostic) => diagnostics.push(diagnostic),
+                }
+            }
+
+            for instance in &resource.programs {
+                let Some(program) = program_of(&env, instance) else {
+                    continue;
+                };
+                if instantiated.contains(&program) {
+                    continue;
+                }
+                instantiated.push(program);
+
+                // Tasks which do not resolve are reported by the resolver
+                if let Some(task) = &instance.task
+                    && let Some(task) = tasks[first..]
+                        .iter_mut()
+                        .find(|t| t.name.eq_ignore_ascii_case(&task.name))
+                {
+                    task.programs.push(table.qualified_name(program));
+                }
+            }
+        }
+
+        match diagnostics.is_empty() {
+            true => Ok(Self {
+                tasks,
+                costs: CostModel::default(),
+            }),
+            false => Err(diagnostics),
+        }
+    }
+
+    fn task_of(env: &TypeEnv, task: &ast::Task) -> Result<TaskConfig, Diagnostic> {
+        let name = task.name.name.clone();
+        let constant = |expression: &Option<Expression>| {
+            expression
+                .as_ref()
+                .map(|e| (consteval::evaluate(env, e).ok(), e.span()))
+        };
+
+        if let Some(single) = &task.single {
+            let message = format!("Event task '{name}' cannot be scheduled.");
+            return Err(Diagnostic::error(single.span(), message));
+        }
+        let interval = match constant(&task.interval) {
+            Some((Some(ConstValue::Time(ns)), _)) if ns > 0 => ns as u64,
+            interval => {
+                let span = interval.map_or(task.name.span, |(_, span)| span);
+                let message = format!("Task '{name}' needs a positive constant INTERVAL.");
+                return Err(Diagnostic::error(span, message));
+            }
+        };
+        let priority = match constant(&task.priority) {
+            None => 0,
+            Some((Some(ConstValue::Int(p)), _)) if u32::try_from(p).is_ok() => p as u32,
+            Some((_, span)) => {
+                let message = format!("The PRIORITY of task '{name}' is invalid.");
+                return Err(Diagnostic::error(span, message));
+            }
+        };
+
+        Ok(TaskConfig {
+            name,
+            interval,
+            priority,
+            programs: Vec::new(),
+        })
+    }
+
+    /// Reads the tasks and costs of a parsed TOML or JSON document, like a project manifest.
+    pub fn from_document(doc: &Json) -> Result<Self, String> {
+        let tasks = doc
+            .get("tasks")
+            .or_else(|| doc.get("task"))
+            .and_then(Json::as_array)
+            .ok_or("The configuration declares no tasks.")?;
+
+        let tasks = tasks
+            .iter()
+            .map(|task| {
+                let name = task
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .ok_or("Every task needs a name.")?
+                    .to_string();
+                let interval = match task.get("interval") {
+                    Some(Json::String(s)) => parse_duration(s),
+                    // Plain numbers are milliseconds, like durations in ST
+                    Some(Json::Number(n)) if *n > 0.0 => Some((n * 1e6) as u64),
+                    _ => None,
+                }
+                .filter(|interval| *interval > 0)
+                .ok_or_else(|| format!("Task '{name}' needs a positive interval."))?;
+                let priority = match task.get("priority") {
+                    None => 0,
+                    Some(p) => p
+                        .as_i64()
+                        .and_then(|p| u32::try_from(p).ok())
+                        .ok_or_else(|| format!("The priority of task '{name}' is invalid."))?,
+                };
+                let programs = match task.get("programs") {
+                    None => Vec::new(),
+                    Some(p) => p
+                        .as_array()
+                        .and_then(|p| p.iter().map(|p| p.as_str().map(str::to_string)).collect())
+                        .ok_or_else(|| format!("The programs of task '{name}' must be names."))?,
+                };
+                Ok(TaskConfig {
+                    name,
+                    interval,
+                    priority,
+                    programs,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut costs = CostModel::default();
+        if let Some(table) = doc.get("costs").and_then(Json::as_object) {
+            for (key, value) in table {
+                let slot = match key.as_str() {
+                    "stack" => &mut costs.stack,
+                    "memory" => &mut costs.memory,
+                    "integer" => &mut costs.integer,
+                    "real" => &mut costs.real,
+                    "branch" => &mut costs.branch,
+                    "call" => &mut costs.call,
+                    _ => return Err(format!("Unknown instruction class '{key}'.")),
+                };
+                *slot = value
+                    .as_i64()
+                    .and_then(|n| u64::try_from(n).ok())
+                    .ok_or_else(|| format!("The cost of '{key}' must be a positive integer."))?;
+            }
+        }
+
+        Ok(Self { tasks, costs })
+    }
+}
+
+/// Reports the program instances of the CONFIGURATION declarations in a source which the runtime
+/// cannot run.
+///
+/// The runtime keeps a single state per program, so every program may only be instantiated once
+/// and without arguments.
+pub fn check_instances(ast: &Ast, table: &SymbolTable) -> Vec<Diagnostic> {
+    let env = TypeEnv::new(table);
+    let mut diagnostics = Vec::new();
+    let mut instantiated: Vec<(SymbolId, Span)> = Vec::new();
+
+    let mut configurations = Vec::new();
+    collect_configurations(&ast.blocks, &mut configurations);
+    let instances = configurations
+        .into_iter()
+        .flat_map(|c| &c.resources)
+        .flat_map(|r| &r.programs);
+    for instance in instances {
+        let Some(program) = program_of(&env, instance) else {
+            continue;
+        };
+        let span = instance.program.span();
+        let name = table.qualified_name(program);
+        if let Some((_, previous)) = instantiated.iter().find(|(p, _)| *p == program) {
+            let message = format!("Program '{name}' is instantiated more than once.");
+            diagnostics.push(
+                Diagnostic::error(span, message)
+                    .with_note(*previous, format!("First instance of '{name}'.")),
+            );
+            continue;
+        }
+        instantiated.push((program, span));
+        if let Some(argument) = instance.arguments.first() {
+            let message = format!(
+                "Program instance '{}' cannot take arguments.",
+                instance.name.name
+            );
+            diagnostics.push(Diagnostic::error(argument.value().span(), message));
+        }
+    }
+    diagnostics
+}
+
+/// The program of an instance, programs which do not resolve are reported by the resolver.
+fn program_of(env: &TypeEnv, instance: &ast::ProgramInstance) -> Option<SymbolId> {
+    instance
+        .program
+        .parts
+        .last()
+        .and_then(|part| env.binding(part.span))
+        .filter(|id| env.table.symbol(*id).kind == SymbolKind::Program)
+}
+
+fn collect_configurations<'a>(blocks: &'a [Block], found: &mut Vec<&'a ast::Configuration>) {
+    for block in blocks {
+        match block {
+            Block::Configuration(configuration) => found.push(configuration),
+            Block::Namespace(namespace) => collect_configurations(&namespace.blocks, found),
+            _ => {}
+        }
+    }
+}
+
+/// Parses durations like `10ms`, `500us`, `1s` or `T#1m30s` into nanoseconds.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let upper = text.trim().to_ascii_uppercase();
+    let mut rest = upper
+        .strip_prefix("TIME#")
+        .or_else(|| upper.strip_prefix("T#"))
+        .unwrap_or(&upper)
+        .trim_start();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = 0f64;
+    while !rest.is_empty() {
+        let n = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+            .unwrap_or(rest.len());
+        let value = rest[..n].replace('_', "").parse::<f64>().ok()?;
+        rest = &rest[n..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit] {
+            "D" => 86_400e9,
+            "H" => 3_600e9,
+            "M" => 60e9,
+            "S" => 1e9,
+            "MS" => 1e6,
+            "US" => 1e3,
+            "NS" => 1.0,
+            _ => return None,
+        };
+        total += value * factor;
+        rest = &rest[unit..];
+    }
+    Some(total.round() as u64)
+}
+
+/// Formats nanoseconds with the largest unit that keeps the value readable.
+pub fn format_duration(ns: u64) -> String {
+    match ns {
+        0..1_000 => format!("{ns}ns"),
+        1_000..1_000_000 => format!("{:.1}us", ns as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.2}ms", ns as f64 / 1e6),
+        _ => format!("{:.3}s", ns as f64 / 1e9),
+    }
+}
+
+/// Minimum, maximum and average of a series of durations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub min: u64,
+    pub max: u64,
+    pub total: u64,
+    pub count: u64,
+}
+
+impl Summary {
+    fn add(&mut self, value: u64) {
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        self.max = self.max.max(value);
+        self.total += value;
+        self.count += 1;
+    }
+
+    pub fn average(&self) -> u64 {
+        self.total.checked_div(self.count).unwrap_or_default()
+    }
+}
+
+/// What happened to a task so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskStatistics {
+    pub name: String,
+    pub cycles: u64,
+    /// Activations which finished after the next one was due
+    pub overruns: u64,
+    /// Activations dropped because the previous one had not started yet
+    pub skipped: u64,
+    /// Delay between the release of an activation and its start
+    pub latency: Summary,
+    /// Estimated execution time of an activation
+    pub execution: Summary,
+}
+
+impl TaskStatistics {
+    /// Variation of the start latency, which is the jitter of the cycle.
+    pub fn jitter(&self) -> u64 {
+        self.latency.max - self.latency.min
+    }
+}
+
+/// An activation which did not finish within its interval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overrun {
+    pub task: String,
+    pub release: u64,
+    pub finish: u64,
+}
+
+struct Task {
+    config: TaskConfig,
+    programs: Vec<SymbolId>,
+    next_release: u64,
+    /// Release time of the activation waiting to run
+    pending: Option<u64>,
+    statistics: TaskStatistics,
+}
+
+/// Runs programs in cyclic tasks on a virtual clock.
+///
+/// Scheduling is non-preemptive with fixed priorities, as on a single core runtime: whenever the
+/// CPU is free, the most urgent released task runs all its programs to completion, ties go to the
+/// task declared first. Execution times come from the cost model of the VM, so runs are fully
+/// deterministic.
+pub struct Scheduler<'a> {
+    vm: Vm<'a>,
+    tasks: Vec<Task>,
+    clock: u64,
+    overruns: Vec<Overrun>,
+}
+
+impl<'a> Scheduler<'a> {
+    /// Assigns the programs of the VM to the configured tasks.
+    pub fn new(mut vm: Vm<'a>, config: &Configuration) -> Result<Self, String> {
+        vm.set_cost_model(config.costs);
+        let tasks = config
+            .tasks
+            .iter()
+            .map(|task| {
+                if task.interval == 0 {
+                    return Err(format!("Task '{}' needs a positive interval.", task.name));
+                }
+                let programs = task
+                    .programs
+                    .iter()
+                    .map(|name| {
+                        vm.program(name).ok_or_else(|| {
+                            format!("Task '{}' runs the unknown program '{name}'.", task.name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(Task {
+                    config: task.clone(),
+                    programs,
+                    next_release: 0,
+                    pending: None,
+                    statistics: TaskStatistics {
+                        name: task.name.clone(),
+                        ..Default::default()
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            vm,
+            tasks,
+            clock: 0,
+            overruns: Vec::new(),
+        })
+    }
+
+    /// Current time of the virtual clock in nanoseconds.
+    pub fn now(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn vm(&self) -> &Vm<'a> {
+        &self.vm
+    }
+
+    pub fn vm_mut(&mut self) -> &mut Vm<'a> {
+        &mut self.vm
+    }
+
+    pub fn statistics(&self) -> Vec<&TaskStatistics> {
+        self.tasks.iter().map(|t| &t.statistics).collect()
+    }
+
+    pub fn overruns(&self) -> &[Overrun] {
+        &self.overruns
+    }
+
+    /// Advances the virtual clock by a duration, running every activation released before its end.
+    ///
+    /// An activation which started before the end runs to completion, so the clock may end up
+    /// slightly behind the requested time.
+    pub fn run_for(&mut self, duration: u64) -> Result<(), Diagnostic> {
+        let end = self.clock + duration;
+        while self.clock < end {
+            self.release();
+
+            let next = self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.pending.is_some())
+                .min_by_key(|(i, t)| (t.config.priority, *i))
+                .map(|(i, _)| i);
+            match next {
+                Some(i) => self.activate(i)?,
+                // Idle until the next release
+                None => {
+                    let release = self.tasks.iter().map(|t| t.next_release).min();
+                    self.clock = release.unwrap_or(end).min(end);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Releases the activations which are due, skipping those whose predecessor still waits.
+    fn release(&mut self) {
+        for task in &mut self.tasks {
+            while task.next_release <= self.clock {
+                match task.pending {
+                    Some(_) => task.statistics.skipped += 1,
+                    None => task.pending = Some(task.next_release),
+                }
+                task.next_release += task.config.interval;
+            }
+        }
+    }
+
+    fn activate(&mut self, index: usize) -> Result<(), Diagnostic> {
+        let task = &mut self.tasks[index];
+        let release = task
+            .pending
+            .take()
+            .expect("Only released tasks are activated");
+        let start = self.clock;
+        let spent = self.vm.spent();
+        // Timers see the time the task starts at, like a PLC reading its clock once per cycle
+        self.vm.set_clock(start);
+        for program in &task.programs {
+            self.vm.run_cycle(*program)?;
+        }
+        let execution = self.vm.spent() - spent;
+        self.clock += execution;
+
+        let statistics = &mut task.statistics;
+        statistics.cycles += 1;
+        statistics.latency.add(start - release);
+        statistics.execution.add(execution);
+        if self.clock > release + task.config.interval {
+            statistics.overruns += 1;
+            self.overruns.push(Overrun {
+                task: task.config.name.clone(),
+                release,
+                finish: self.clock,
+            });
+        }
+        Ok(())
+    }
+
+    /// Statistics of all tasks as a table.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<16} {:>10} {:>5} {:>8} {:>8} {:>8} {:>28} {:>10} {:>28}\n",
+            "Task",
+            "Interval",
+            "Prio",
+            "Cycles",
+            "Overruns",
+            "Skipped",
+            "Latency min/avg/max",
+            "Jitter",
+            "Execution min/avg/max",
+        );
+        let triple = |s: &Summary| {
+            format!(
+                "{}/{}/{}",
+                format_duration(s.min),
+                format_duration(s.average()),
+                format_duration(s.max)
+            )
+        };
+        for task in &self.tasks {
+            let s = &task.statistics;
+            let _ = writeln!(
+                report,
+                "{:<16} {:>10} {:>5} {:>8} {:>8} {:>8} {:>28} {:>10} {:>28}",
+                s.name,
+                format_duration(task.config.interval),
+                task.config.priority,
+                s.cycles,
+                s.overruns,
+                s.skipped,
+                triple(&s.latency),
+                format_duration(s.jitter()),
+                triple(&s.execution),
+            );
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        semantic::resolver::resolve,
+    };
+
+    const SRC: &str = "
+        PROGRAM Fast
+        VAR count : DINT; END_VAR
+            count := count + 1;
+        END_PROGRAM
+
+        PROGRAM Slow
+        VAR count : DINT; i : DINT; sum : DINT; END_VAR
+            FOR i := 1 TO 100 DO
+                sum := sum + i;
+            END_FOR;
+            count := count + 1;
+        END_PROGRAM
+    ";
+
+    fn task(name: &str, interval: &str, priority: u32, program: &str) -> TaskConfig {
+        TaskConfig {
+            name: name.to_string(),
+            interval: parse_duration(interval).unwrap(),
+            priority,
+            programs: vec![program.to_string()],
+        }
+    }
+
+    fn with_scheduler(config: Configuration, f: impl FnOnce(Scheduler)) {
+        let ast = parse(Lexer::create("test.st", SRC)).unwrap();
+        let (table, diagnostics) = resolve(&ast);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let vm = Vm::new(&ast, &table).unwrap();
+        f(Scheduler::new(vm, &config).unwrap());
+    }
+
+    #[test]
+    fn test_priorities_and_cycles() {
+        let config = Configuration {
+            tasks: vec![
+                task("Slow", "10ms", 2, "Slow"),
+                task("Fast", "1ms", 1, "Fast"),
+            ],
+            costs: CostModel::default(),
+        };
+        with_scheduler(config, |mut scheduler| {
+            scheduler.run_for(parse_duration("100ms").unwrap()).unwrap();
+
+            assert_eq!(
+                scheduler.vm().read("Fast.count").unwrap().to_string(),
+                "100"
+            );
+            assert_eq!(scheduler.vm().read("Slow.count").unwrap().to_string(), "10");
+            let stats = scheduler.statistics();
+            assert_eq!(stats[0].cycles, 10);
+            assert_eq!(stats[1].cycles, 100);
+            assert!(scheduler.overruns().is_empty());
+            // Both are released at 0, the fast task is more urgent and delays the slow one
+            assert_eq!(stats[1].latency.max, 0);
+            assert_eq!(stats[0].latency.min, stats[1].execution.min);
+            assert!(scheduler.now() >= 100_000_000);
+        });
+    }
+
+    #[test]
+    fn test_overruns_and_jitter() {
+        // Make the loop of the slow program take longer than the interval of the fast task
+        let costs = CostModel {
+            branch: 20_000,
+            ..Default::default()
+        };
+        let config = Configuration {
+            tasks: vec![
+                task("Fast", "1ms", 1, "Fast"),
+                task("Slow", "5ms", 2, "Slow"),
+            ],
+            costs,
+        };
+        with_scheduler(config, |mut scheduler| {
+            scheduler.run_for(parse_duration("50ms").unwrap()).unwrap();
+
+            let stats = scheduler.statistics();
+            assert!(stats[0].overruns > 0);
+            assert!(stats[0].jitter() > 1_000_000);
+            assert_eq!(stats[1].overruns, 0);
+            assert!(scheduler.overruns().iter().all(|o| o.task == "Fast"));
+            assert!(
+                scheduler
+                    .overruns()
+                    .iter()
+                    .all(|o| o.finish > o.release + 1_000_000)
+            );
+            assert!(scheduler.report().contains("Fast"));
+        });
+    }
+
+    #[test]
+    fn test_configuration_in_source() {
+        let src = format!(
+            "{SRC}
+            NAMESPACE Site
+            CONFIGURATION Plant
+                RESOURCE Cpu ON PLC
+                    TASK Cyclic(INTERVAL := T#2ms, PRIORITY := 3);
+                    PROGRAM First WITH Cyclic : Fast;
+                    PROGRAM Second : Slow;
+                END_RESOURCE
+            END_CONFIGURATION
+            END_NAMESPACE
+            "
+        );
+        let config = |src: &str| {
+            let ast = parse(Lexer::create("test.st", src)).unwrap();
+            let (table, diagnostics) = resolve(&ast);
+            assert!(diagnostics.is_empty(), "{diagnostics:?}");
+            Configuration::from_source(&ast, &table)
+        };
+
+        let tasks = config(&src).unwrap().tasks;
+        assert_eq!(tasks, vec![task("Cyclic", "2ms", 3, "Fast")]);
+
+        let fine = src.replace("T#2ms", "T#500us");
+        let tasks = config(&fine).unwrap().tasks;
+        assert_eq!(tasks, vec![task("Cyclic", "500us", 3, "Fast")]);
+
+        let twice = src.replace("Second : Slow", "Second : Fast(count := 1)");
+        let diagnostics = config(&twice).unwrap_err();
+        let messages = diagnostics
+            .iter()
+            .map(|d| (d.span.line, d.span.col, d.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![(19, 37, "Program 'Fast' is instantiated more than once.")]
+        );
+        assert_eq!(diagnostics[0].notes[0].0.line, 18);
+
+        let arguments = src.replace("Second : Slow", "Second : Slow(count := 1)");
+        let diagnostics = config(&arguments).unwrap_err();
+        assert_eq!(
+            diagnostics[0].message,
+            "Program instance 'Second' cannot take arguments."
+        );
+        assert_eq!(
+            (diagnostics[0].span.line, diagnostics[0].span.col),
+            (19, 51)
+        );
+
+        let event = src.replace("INTERVAL := T#2ms", "SINGLE := TRUE");
+        let diagnostics = config(&event).unwrap_err();
+        assert_eq!(
+            diagnostics[0].message,
+            "Event task 'Cyclic' cannot be scheduled."
+        );
+    }
+
+    #[test]
+    fn test_configuration_files() {
+        let from_toml = Configuration::from_toml(
+            r#"
+            [[task]]
+            name = "Fast"
+            interval = "T#1ms"
+            priority = 1
+            programs = ["Fast"]
+
+            [costs]
+            call = 100
+            "#,
+        )
+        .unwrap();
+        let from_json = Configuration::from_json(
+            r#"{"tasks": [{"name": "Fast", "interval": 1, "priority": 1, "programs": ["Fast"]}],
+                "costs": {"call": 100}}"#,
+        )
+        .unwrap();
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml.tasks[0].interval, 1_000_000);
+        assert_eq!(from_toml.costs.call, 100);
+
+        assert_eq!(parse_duration("1m30s"), Some(90_000_000_000));
+        assert_eq!(parse_duration("500us"), Some(500_000));
+        assert_eq!(parse_duration("10 parsecs"), None);
+        assert_eq!(
+            Configuration::from_toml("[[task]]\nname = \"Idle\"\ninterval = \"0ms\""),
+            Err("Task 'Idle' needs a positive interval.".to_string())
+        );
+    }
+}
@@ -0,0 +1,436 @@
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{Ast, Block, Pou},
+    runtime::{Engine, EngineKind, interpreter::Interpreter, library, scheduler, vm::Vm},
+    semantic::symbols::SymbolTable,
+};
+
+/// A PROGRAM or FUNCTION_BLOCK tagged `{attribute 'test'}`, run as a unit test.
+///
+/// The attributes `cycles` and `cycle_time` set how many scan cycles it runs and how far the
+/// virtual clock advances between them, like `{attribute 'cycle_time' := 'T#10ms'}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+    /// Qualified name of the tagged POU
+    pub name: String,
+    /// Qualified name of the program that runs the test
+    pub program: String,
+    /// Whether the test is a function block, which needs a [`harness`] program
+    pub block: bool,
+    pub cycles: u64,
+    /// Advance of the virtual clock per cycle in nanoseconds
+    pub cycle_time: u64,
+}
+
+impl TestCase {
+    fn of(pou: &Pou, prefix: &str, block: bool) -> Result<Option<Self>, String> {
+        if pou.attribute("test").is_none() {
+            return Ok(None);
+        }
+        let name = format!("{prefix}{}", pou.name.name);
+        let value = |attribute: &str| {
+            pou.attribute(attribute)
+                .map(|a| a.value.as_deref().unwrap_or_default())
+        };
+        let cycles = match value("cycles") {
+            Some(cycles) => cycles
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|c| *c > 0)
+                .ok_or_else(|| {
+                    format!("The cycles of test '{name}' must be a positive integer.")
+                })?,
+            None => 1,
+        };
+        let cycle_time = match value("cycle_time") {
+            Some(time) => scheduler::parse_duration(time).ok_or_else(|| {
+                format!("The cycle time of test '{name}' must look like 10ms or T#1s.")
+            })?,
+            None => 0,
+        };
+        let program = match block {
+            true => format!("TEST_{}", name.replace('.', "_")),
+            false => name.clone(),
+        };
+        Ok(Some(Self {
+            name,
+            program,
+            block,
+            cycles,
+            cycle_time,
+        }))
+    }
+}
+
+/// Finds the tests of a source in declaration order, also within namespaces.
+pub fn discover(ast: &Ast) -> Result<Vec<TestCase>, String> {
+    fn visit(blocks: &[Block], prefix: &str, tests: &mut Vec<TestCase>) -> Result<(), String> {
+        for block in blocks {
+            let test = match block {
+                Block::Program(pou) => TestCase::of(pou, prefix, false)?,
+                Block::FunctionBlock(pou) => TestCase::of(pou, prefix, true)?,
+                Block::Namespace(namespace) => {
+                    let prefix = format!("{prefix}{}.", namespace.name.to_printable());
+                    visit(&namespace.blocks, &prefix, tests)?;
+                    None
+                }
+                _ => None,
+            };
+            tests.extend(test);
+        }
+        Ok(())
+    }
+
+    let mut tests = Vec::new();
+    visit(&ast.blocks, "", &mut tests)?;
+    Ok(tests)
+}
+
+/// Source of the programs that run the function block tests, one instance per test.
+///
+/// It is appended to the source under test before that is parsed again, so diagnostics keep
+/// their positions.
+pub fn harness(tests: &[TestCase]) -> String {
+    let mut src = String::new();
+    for test in tests.iter().filter(|t| t.block) {
+        let _ = write!(
+            src,
+            "\nPROGRAM {}\nVAR\n    instance : {};\nEND_VAR\n    instance();\nEND_PROGRAM\n",
+            test.program, test.name
+        );
+    }
+    src
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// The first failed assertion
+    Failed(Diagnostic),
+    /// A runtime error other than a failed assertion, or a test that cannot start
+    Error(Diagnostic),
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub outcome: Outcome,
+    /// Cycles run until the test passed or failed
+    pub cycles: u64,
+    pub duration: Duration,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+}
+
+/// Runs every test on a fresh engine, so tests cannot influence each other.
+///
+/// Each cycle the virtual clock is set to the cycle number times the cycle time before the
+/// program runs. A test fails at the first failed assertion or other runtime error.
+pub fn run(
+    ast: &Ast,
+    table: &SymbolTable,
+    tests: &[TestCase],
+    engine: EngineKind,
+) -> Vec<TestResult> {
+    tests
+        .iter()
+        .map(|test| {
+            let start = Instant::now();
+            let (outcome, cycles) = match run_test(ast, table, test, engine) {
+                Ok(cycles) => (Outcome::Passed, cycles),
+                Err((diagnostic, cycles)) if library::is_assertion_failure(&diagnostic) => {
+                    (Outcome::Failed(diagnostic), cycles)
+                }
+                Err((diagnostic, cycles)) => (Outcome::Error(diagnostic), cycles),
+            };
+            TestResult {
+                name: test.name.clone(),
+                outcome,
+                cycles,
+                duration: start.elapsed(),
+            }
+        })
+        .collect()
+}
+
+fn run_test(
+    ast: &Ast,
+    table: &SymbolTable,
+    test: &TestCase,
+    engine: EngineKind,
+) -> Result<u64, (Diagnostic, u64)> {
+    // Tests the bytecode compiler cannot handle yet still run on the interpreter
+    let vm = match engine {
+        EngineKind::Vm => Vm::new(ast, table).ok(),
+        EngineKind::Interpreter => None,
+    };
+    let mut engine: Box<dyn Engine> = match vm {
+        Some(vm) => Box::new(vm),
+        None => Box::new(Interpreter::new(ast, table).map_err(|e| (e, 0))?),
+    };
+    let program = engine
+        .programs()
+        .into_iter()
+        .find(|id| {
+            table
+                .qualified_name(*id)
+                .eq_ignore_ascii_case(&test.program)
+        })
+        .ok_or_else(|| {
+            let message = format!("The program of test '{}' is missing.", test.name);
+            (Diagnostic::error(Default::default(), message), 0)
+        })?;
+
+    for cycle in 0..test.cycles {
+        engine.set_clock(cycle * test.cycle_time);
+        engine.run_cycle(program).map_err(|e| (e, cycle + 1))?;
+    }
+    Ok(test.cycles)
+}
+
+/// Human readable summary of test results, listing the failures as `describe` prints them.
+pub fn report(results: &[TestResult], describe: &dyn Fn(&Diagnostic) -> String) -> String {
+    let mut report = format!("running {} tests\n", results.len());
+    for result in results {
+        let status = match result.passed() {
+            true => "ok",
+            false => "FAILED",
+        };
+        let _ = writeln!(report, "test {} ... {status}", result.name);
+    }
+
+    let failures = results
+        .iter()
+        .filter_map(|r| match &r.outcome {
+            Outcome::Failed(diagnostic) | Outcome::Error(diagnostic) => Some((r, diagnostic)),
+            Outcome::Passed => None,
+        })
+        .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        report += "\nfailures:\n";
+        for (result, diagnostic) in &failures {
+            let _ = writeln!(
+                report,
+                "    {} in cycle {}: {}",
+                result.name,
+                result.cycles,
+                describe(diagnostic)
+            );
+        }
+    }
+
+    let status = match failures.is_empty() {
+        true => "ok",
+        false => "FAILED",
+    };
+    let elapsed = results.iter().map(|r| r.duration).sum::<Duration>();
+    let _ = writeln!(
+        report,
+        "\ntest result: {status}. {} passed; {} failed; finished in {:.2}s",
+        results.len() - failures.len(),
+        failures.len(),
+        elapsed.as_secs_f64()
+    );
+    report
+}
+
+/// Test results as JUnit XML, the format CI servers read.
+pub fn junit(
+    suite: &str,
+    results: &[TestResult],
+    describe: &dyn Fn(&Diagnostic) -> String,
+) -> String {
+    let count = |error: bool| {
+        results
+            .iter()
+            .filter(|r| match r.outcome {
+                Outcome::Passed => false,
+                Outcome::Failed(_) => !error,
+                Outcome::Error(_) => error,
+            })
+            .count()
+    };
+    let elapsed = results.iter().map(|r| r.duration).sum::<Duration>();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">",
+        escape(suite),
+        results.len(),
+        count(false),
+        count(true),
+        elapsed.as_secs_f64()
+    );
+    for result in results {
+        let _ = write!(
+            xml,
+            "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\"",
+            escape(&result.name),
+            escape(suite),
+            result.duration.as_secs_f64()
+        );
+        let (element, diagnostic) = match &result.outcome {
+            Outcome::Passed => {
+                xml += "/>\n";
+                continue;
+            }
+            Outcome::Failed(diagnostic) => ("failure", diagnostic),
+            Outcome::Error(diagnostic) => ("error", diagnostic),
+        };
+        let _ = writeln!(
+            xml,
+            ">\n    <{element} message=\"{}\">{}</{element}>\n  </testcase>",
+            escape(&diagnostic.message),
+            escape(&describe(diagnostic))
+        );
+    }
+    xml += "</testsuite>\n";
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        semantic::{checker::check, resolver::resolve},
+    };
+
+    fn run_source(src: &str) -> Vec<TestResult> {
+        let ast = parse(Lexer::create("test.st", src)).unwrap();
+        let tests = discover(&ast).unwrap();
+        let src = format!("{src}{}", harness(&tests));
+        let ast = parse(Lexer::create("test.st", &src)).unwrap();
+        let (table, mut diagnostics) = resolve(&ast);
+        diagnostics.extend(check(&ast, &table));
+        assert!(diagnostics.iter().all(|d| !d.is_error()), "{diagnostics:?}");
+        let results = run(&ast, &table, &tests, EngineKind::Vm);
+        let interpreted = run(&ast, &table, &tests, EngineKind::Interpreter);
+        for (vm, interpreter) in results.iter().zip(&interpreted) {
+            assert_eq!(
+                (&vm.outcome, vm.cycles),
+                (&interpreter.outcome, interpreter.cycles)
+            );
+        }
+        results
+    }
+
+    fn message(result: &TestResult) -> &str {
+        match &result.outcome {
+            Outcome::Failed(diagnostic) | Outcome::Error(diagnostic) => &diagnostic.message,
+            Outcome::Passed => "",
+        }
+    }
+
+    #[test]
+    fn test_discovers_and_runs_tests() {
+        let src = "
+            FUNCTION Twice : INT VAR_INPUT x : INT; END_VAR Twice := x * 2; END_FUNCTION
+            PROGRAM Main VAR x : INT; END_VAR x := Twice(x); END_PROGRAM
+
+            {attribute 'test'}
+            PROGRAM TwiceDoubles
+                ASSERT_EQ(6, Twice(3));
+                ASSERT_NE(5, Twice(3));
+                ASSERT_TRUE(Twice(0) = 0);
+                ASSERT_NEAR(1.0, 0.999, 0.01);
+            END_PROGRAM
+
+            NAMESPACE Suite
+                {attribute 'test'}
+                FUNCTION_BLOCK TwiceFails
+                    ASSERT_FALSE(Twice(1) = 2);
+                END_FUNCTION_BLOCK
+            END_NAMESPACE
+
+            {attribute 'test'}
+            PROGRAM NearFails
+                ASSERT_NEAR(REAL#1.0, REAL#1.5, REAL#0.25);
+            END_PROGRAM
+        ";
+        let results = run_source(src);
+        let names = results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["TwiceDoubles", "Suite.TwiceFails", "NearFails"]);
+        assert!(results[0].passed());
+        assert_eq!(message(&results[1]), "Expected FALSE, found TRUE.");
+        assert_eq!(message(&results[2]), "Expected 1.0 within 0.25, found 1.5.");
+    }
+
+    #[test]
+    fn test_advances_the_virtual_clock_between_cycles() {
+        let src = "
+            {attribute 'test'}
+            {attribute 'cycles' := '4'}
+            {attribute 'cycle_time' := 'T#50ms'}
+            PROGRAM DelayElapses
+                VAR timer : TON; n : INT; END_VAR
+                timer(IN := TRUE, PT := T#100ms);
+                ASSERT_EQ(n >= 2, timer.Q);
+                n := n + 1;
+            END_PROGRAM
+
+            {attribute 'test'}
+            {attribute 'cycles' := '5'}
+            PROGRAM FailsInThirdCycle
+                VAR n : INT; END_VAR
+                n := n + 1;
+                ASSERT_EQ(n, MIN(n, 2));
+            END_PROGRAM
+        ";
+        let results = run_source(src);
+        assert!(results[0].passed(), "{:?}", results[0].outcome);
+        assert_eq!(results[0].cycles, 4);
+        assert_eq!(message(&results[1]), "Expected 3, found 2.");
+        assert_eq!(results[1].cycles, 3);
+    }
+
+    #[test]
+    fn test_reports_as_junit() {
+        let src = "{attribute 'test'} PROGRAM Ok END_PROGRAM
+{attribute 'test'} PROGRAM Bad ASSERT_EQ('<a>', 'b'); END_PROGRAM
+{attribute 'test'} PROGRAM Broken VAR d : INT; END_VAR d := 1 / d; END_PROGRAM";
+        let results = run_source(src);
+        assert!(matches!(results[1].outcome, Outcome::Failed(_)));
+        assert!(matches!(results[2].outcome, Outcome::Error(_)));
+        let describe = |d: &Diagnostic| d.format_as_printable("test.st", src);
+        let xml = junit("unit", &results, &describe);
+        assert!(xml.contains("<testsuite name=\"unit\" tests=\"3\" failures=\"1\" errors=\"1\""));
+        assert!(xml.contains("<testcase name=\"Ok\" classname=\"unit\""));
+        assert!(xml.contains(
+            "<failure message=\"Expected &apos;&lt;a&gt;&apos;, found &apos;b&apos;.\">"
+        ));
+        assert!(xml.contains("<error message=\"Division by zero.\">"));
+
+        let report = report(&results, &describe);
+        assert!(report.contains("test Bad ... FAILED"));
+        assert!(report.contains("test result: FAILED. 1 passed; 2 failed;"));
+
+        let ast = parse(Lexer::create(
+            "test.st",
+            "{attribute 'test'} {attribute 'cycles' := 'x'} PROGRAM P END_PROGRAM",
+        ))
+        .unwrap();
+        assert_eq!(
+            discover(&ast),
+            Err("The cycles of test 'P' must be a positive integer.".to_string())
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::fmt;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{InfixOperator, PrefixOperator, Span},
+    semantic::{
+        consteval, library,
+        symbols::ScopeId,
+        types::{Elementary, Type, TypeEnv},
+    },
+};
+
+/// Location of a value: a root slot of the memory and the path into its structure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Place {
+    pub root: usize,
+    pub path: Vec<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Field(String),
+    Index(usize),
+}
+
+impl Place {
+    pub fn root(root: usize) -> Self {
+        Self {
+            root,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Self {
+        self.with(Step::Field(name.to_string()))
+    }
+
+    pub fn index(&self, index: usize) -> Self {
+        self.with(Step::Index(index))
+    }
+
+    fn with(&self, step: Step) -> Self {
+        let mut path = self.path.clone();
+        path.push(step);
+        Self {
+            root: self.root,
+            path,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayValue {
+    pub dimensions: Vec<(i128, i128)>,
+    pub items: Vec<Value>,
+}
+
+impl ArrayValue {
+    /// Position of an element in `items`, if all indices are within their bounds.
+    pub fn offset(&self, indices: &[i128]) -> Option<usize> {
+        let mut offset = 0;
+        for (index, (lower, upper)) in indices.iter().zip(&self.dimensions) {
+            if index < lower || index > upper {
+                return None;
+            }
+            offset = offset * (upper - lower + 1) + (index - lower);
+        }
+        usize::try_from(offset).ok()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    /// Integers, bit strings and characters with their type
+    Int(i128, Elementary),
+    Real(f64, Elementary),
+    /// Durations in nanoseconds
+    Time(i128),
+    /// Dates and times of day in nanoseconds
+    Date(i128, Elementary),
+    String(String),
+    Enum(i128, ScopeId),
+    Array(Box<ArrayValue>),
+    /// Structures as well as instances of function blocks and programs
+    Struct(Vec<(String, Value)>),
+    Pointer(Option<Place>),
+    Void,
+}
+
+impl Value {
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match self {
+            Value::Struct(fields) => fields
+                .iter_mut()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i128> {
+        match self {
+            Value::Int(n, _) | Value::Time(n) | Value::Date(n, _) | Value::Enum(n, _) => Some(*n),
+            Value::Bool(b) => Some(*b as i128),
+            _ => None,
+        }
+    }
+
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Value::Real(r, _) => Some(*r),
+            Value::Int(n, _) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// Gives an untyped literal the type of the value it is combined with.
+    pub fn retyped(self, like: &Value) -> Value {
+        match (self, like) {
+            (Value::Int(n, _), Value::Int(_, e)) => Value::Int(wrap(n, *e), *e),
+            (Value::Int(n, _), Value::Real(_, e)) => Value::Real(n as f64, *e),
+            (Value::Real(r, _), Value::Real(_, e)) => Value::Real(round(r, *e), *e),
+            (v, _) => v,
+        }
+    }
+
+    /// Converts a value for storing it where `old` was, keeping the type of the location.
+    pub fn stored_like(self, old: &Value) -> Value {
+        match (self, old) {
+            (Value::Bool(b), Value::Int(_, e)) => Value::Int(b as i128, *e),
+            (Value::Int(n, _), Value::Bool(_)) => Value::Bool(n != 0),
+            (Value::Int(n, _), Value::Time(_)) => Value::Time(n),
+            (v, old) => v.retyped(old),
+        }
+    }
+
+    /// Displays the value with the names of its enum values, like `Mode#Fast` instead of `1`.
+    pub fn display<'v>(&'v self, env: &'v TypeEnv) -> impl fmt::Display + 'v {
+        Named {
+            value: self,
+            env: Some(env),
+        }
+    }
+
+    /// Cuts a string down to the length of the type it is stored as.
+    pub fn truncated(self, ty: &Type) -> Value {
+        match (self, ty.string_length()) {
+            (Value::String(s), Some(length)) if s.chars().count() > length => {
+                Value::String(s.chars().take(length).collect())
+            }
+            (v, _) => v,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Named {
+            value: self,
+            env: None,
+        }
+        .fmt(f)
+    }
+}
+
+/// A value displayed with the names of its enum values if their types are known.
+struct Named<'v, 'a> {
+    value: &'v Value,
+    env: Option<&'v TypeEnv<'a>>,
+}
+
+impl fmt::Display for Named<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let named = |value| Named {
+            value,
+            env: self.env,
+        };
+        match self.value {
+            Value::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            Value::Int(n, _) => write!(f, "{n}"),
+            Value::Enum(n, scope) => {
+                match self
+                    .env
+                    .and_then(|env| consteval::enum_name(env, *scope, *n))
+                {
+                    Some(name) => write!(f, "{name}"),
+                    None => write!(f, "{n}"),
+                }
+            }
+            Value::Real(r, _) => write!(f, "{r:?}"),
+            Value::Time(t) => write!(f, "{}", library::format_time(*t)),
+            Value::Date(d, e) => write!(f, "{}", library::format_date(*d, *e)),
+            Value::String(s) => write!(f, "'{s}'"),
+            Value::Array(array) => {
+                let items = array
+                    .items
+                    .iter()
+                    .map(|i| named(i).to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(n, v)| format!("{n} := {}", named(v)))
+                    .collect::<Vec<_>>();
+                write!(f, "({})", fields.join(", "))
+            }
+            Value::Pointer(Some(_)) => write!(f, "<pointer>"),
+            Value::Pointer(None) => write!(f, "<null>"),
+            Value::Void => write!(f, "<void>"),
+        }
+    }
+}
+
+/// Smallest of DINT and LINT an untyped integer literal fits into.
+pub fn literal_type(n: i128) -> Elementary {
+    match i32::try_from(n) {
+        Ok(_) => Elementary::Dint,
+        Err(_) => Elementary::Lint,
+    }
+}
+
+/// Wraps an integer around like the hardware does on an overflow of the type.
+pub fn wrap(n: i128, ty: Elementary) -> i128 {
+    let Some(bits) = ty.bit_width() else {
+        return n;
+    };
+    let modulus = 1i128 << bits;
+    let n = n.rem_euclid(modulus);
+    match ty.is_signed() && n >= modulus / 2 {
+        true => n - modulus,
+        false => n,
+    }
+}
+
+/// REAL values have single precision.
+fn round(r: f64, ty: Elementary) -> f64 {
+    match ty {
+        Elementary::Real => r as f32 as f64,
+        _ => r,
+    }
+}
+
+/// The wider of two types, which is what binary operations compute in.
+fn wider(a: Elementary, b: Elementary) -> Elementary {
+    match a.widens_to(b) {
+        true => b,
+        false => a,
+    }
+}
+
+pub fn unary(op: PrefixOperator, value: Value, span: Span) -> Result<Value, Diagnostic> {
+    Ok(match (op, value) {
+        (PrefixOperator::Negation, Value::Int(n, e)) => Value::Int(wrap(-n, e), e),
+        (PrefixOperator::Negation, Value::Real(r, e)) => Value::Real(-r, e),
+        (PrefixOperator::Negation, Value::Time(t)) => Value::Time(-t),
+        (PrefixOperator::Not, Value::Bool(b)) => Value::Bool(!b),
+        (PrefixOperator::Not, Value::Int(n, e)) => Value::Int(wrap(!n, e), e),
+        (_, value) => return Err(invalid_operands(span, &[value])),
+    })
+}
+
+pub fn binary(
+    op: InfixOperator,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, Diagnostic> {
+    if op.is_comparison() {
+        return compare(op, &left, &right, span).map(Value::Bool);
+    }
+
+    Ok(match (left, right) {
+        (Value::Bool(a), Value::Bool(b)) if op.is_logical() => Value::Bool(match op {
+            InfixOperator::And => a && b,
+            InfixOperator::Or => a || b,
+            _ => a ^ b,
+        }),
+        (Value::Int(a, ta), Value::Int(b, tb)) => {
+            let ty = wider(ta, tb);
+            let n = match op {
+                InfixOperator::Addition => a + b,
+                InfixOperator::Subtraction => a - b,
+                InfixOperator::Multiplication => a * b,
+                InfixOperator::Division | InfixOperator::Modulo if b == 0 => {
+                    return Err(Diagnostic::error(span, "Division by zero."));
+                }
+                InfixOperator::Division => a / b,
+                InfixOperator::Modulo => a % b,
+                InfixOperator::Power => {
+                    return Ok(Value::Real((a as f64).powf(b as f64), Elementary::Lreal));
+                }
+                InfixOperator::And => a & b,
+                InfixOperator::Or => a | b,
+                _ => a ^ b,
+            };
+            Value::Int(wrap(n, ty), ty)
+        }
+        (Value::Time(a), Value::Time(b)) if op == InfixOperator::Addition => Value::Time(a + b),
+        (Value::Time(a), Value::Time(b)) if op == InfixOperator::Subtraction => Value::Time(a - b),
+        (Value::Time(a), b @ (Value::Int(..) | Value::Real(..))) => {
+            let b = b.as_real().unwrap_or_default();
+            match op {
+                InfixOperator::Multiplication => Value::Time((a as f64 * b) as i128),
+                InfixOperator::Division if b == 0.0 => {
+                    return Err(Diagnostic::error(span, "Division by zero."));
+                }
+                InfixOperator::Division => Value::Time((a as f64 / b) as i128),
+                _ => return Err(invalid_operands(span, &[Value::Time(a)])),
+            }
+        }
+        (Value::Date(a, e), Value::Time(b)) => match op {
+            InfixOperator::Addition => Value::Date(a + b, e),
+            InfixOperator::Subtraction => Value::Date(a - b, e),
+            _ => return Err(invalid_operands(span, &[Value::Date(a, e)])),
+        },
+        (Value::Date(a, _), Value::Date(b, _)) if op == InfixOperator::Subtraction => {
+            Value::Time(a - b)
+        }
+        (left, right) => {
+            let ty = match (&left, &right) {
+                (Value::Real(_, a), Value::Real(_, b)) => wider(*a, *b),
+                (Value::Real(_, e), _) | (_, Value::Real(_, e)) => *e,
+                _ => return Err(invalid_operands(span, &[left, right])),
+            };
+            let (Some(a), Some(b)) = (left.as_real(), right.as_real()) else {
+                return Err(invalid_operands(span, &[left, right]));
+            };
+            let r = match op {
+                InfixOperator::Addition => a + b,
+                InfixOperator::Subtraction => a - b,
+                InfixOperator::Multiplication => a * b,
+                InfixOperator::Division => a / b,
+                InfixOperator::Power => a.powf(b),
+                _ => return Err(invalid_operands(span, &[left, right])),
+            };
+            Value::Real(round(r, ty), ty)
+        }
+    })
+}
+
+fn compare(op: InfixOperator, left: &Value, right: &Value, span: Span) -> Result<bool, Diagnostic> {
+    let ordering = match (left, right) {
+        (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+        (Value::Pointer(a), Value::Pointer(b)) => (a == b)
+            .then_some(std::cmp::Ordering::Equal)
+            .or(Some(std::cmp::Ordering::Less)),
+        (Value::Real(..), _) | (_, Value::Real(..)) => match (left.as_real(), right.as_real()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        },
+        _ => match (left.as_int(), right.as_int()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        },
+    };
+
+    let Some(ordering) = ordering else {
+        return Err(invalid_operands(span, &[left.clone(), right.clone()]));
+    };
+    Ok(match op {
+        InfixOperator::Equals => ordering.is_eq(),
+        InfixOperator::NotEquals => ordering.is_ne(),
+        InfixOperator::GreaterThan => ordering.is_gt(),
+        InfixOperator::GreaterThanOrEquals => ordering.is_ge(),
+        InfixOperator::LessThan => ordering.is_lt(),
+        _ => ordering.is_le(),
+    })
+}
+
+fn invalid_operands(span: Span, values: &[Value]) -> Diagnostic {
+    let values = values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" and ");
+    Diagnostic::error(span, format!("The operation is not defined for {values}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integers_wrap_around() {
+        assert_eq!(wrap(128, Elementary::Sint), -128);
+        assert_eq!(wrap(-1, Elementary::Usint), 255);
+        assert_eq!(wrap(70000, Elementary::Uint), 4464);
+
+        let sum = binary(
+            InfixOperator::Addition,
+            Value::Int(32767, Elementary::Int),
+            Value::Int(1, Elementary::Int),
+            Span::default(),
+        );
+        assert_eq!(sum, Ok(Value::Int(-32768, Elementary::Int)));
+    }
+
+    #[test]
+    fn test_mixed_operations() {
+        let product = binary(
+            InfixOperator::Multiplication,
+            Value::Int(3, Elementary::Int),
+            Value::Real(0.5, Elementary::Real),
+            Span::default(),
+        );
+        assert_eq!(product, Ok(Value::Real(1.5, Elementary::Real)));
+
+        let delay = binary(
+            InfixOperator::Multiplication,
+            Value::Time(200),
+            Value::Int(3, Elementary::Int),
+            Span::default(),
+        );
+        assert_eq!(delay, Ok(Value::Time(600)));
+
+        let division = binary(
+            InfixOperator::Division,
+            Value::Int(1, Elementary::Int),
+            Value::Int(0, Elementary::Int),
+            Span::default(),
+        );
+        assert!(division.is_err());
+    }
+}
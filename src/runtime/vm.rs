@@ -0,0 +1,1480 @@
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{Ast, InfixOperator, VariableKind},
+    runtime::{
+        CYCLE_BUDGET, MAX_CALL_DEPTH,
+        bytecode::{Address, Comparison, CostModel, Instruction, Module, Precision},
+        compiler, library as runtime_library,
+        value::{self, ArrayValue, Place, Value},
+    },
+    semantic::{
+        library::{self, Function, MILLISECOND, Scalar},
+        symbols::{SymbolId, SymbolKind, SymbolTable},
+        types::{Elementary, Type, TypeEnv},
+    },
+};
+
+type Result<T> = std::result::Result<T, Diagnostic>;
+
+/// Executes programs compiled to bytecode on a stack machine.
+///
+/// The memory is a flat array of 64 bit slots. Globals and program instances occupy its start,
+/// frames of functions and methods are pushed on top of them while they run.
+pub struct Vm<'a> {
+    env: TypeEnv<'a>,
+    module: Module,
+    /// Pool of all strings, string values are indices into it
+    strings: Vec<String>,
+    /// Indices of the strings computed at runtime, which keeps the pool from growing every cycle
+    string_ids: HashMap<String, i64>,
+    memory: Vec<i64>,
+    stack: Vec<i64>,
+    /// Bases of the frames of functions and methods, the last one is the callee frame
+    frames: Vec<usize>,
+    /// Estimated execution time of every instruction of every function, per the cost model
+    costs: Vec<Vec<u64>>,
+    /// Estimated execution time of all instructions run so far, in nanoseconds
+    spent: u64,
+    /// Virtual time the standard timers read, in nanoseconds
+    clock: u64,
+    /// Instructions a scan cycle may execute
+    cycle_budget: u64,
+}
+
+/// State of a caller while a call runs.
+struct Activation {
+    function: usize,
+    pc: usize,
+    bp: usize,
+    this: usize,
+    height: usize,
+}
+
+impl<'a> Vm<'a> {
+    /// Compiles the source and instantiates all global variables and programs.
+    pub fn new(ast: &Ast, table: &'a SymbolTable) -> Result<Self> {
+        let module = compiler::compile(ast, table)?;
+        let mut vm = Self {
+            env: TypeEnv::new(table),
+            strings: module.strings.clone(),
+            string_ids: HashMap::new(),
+            memory: vec![0; module.static_size as usize],
+            stack: Vec::new(),
+            frames: Vec::new(),
+            costs: Vec::new(),
+            spent: 0,
+            clock: 0,
+            cycle_budget: CYCLE_BUDGET,
+            module,
+        };
+        vm.set_cost_model(CostModel::default());
+        vm.execute(vm.module.init as usize, 0, 0)?;
+        Ok(vm)
+    }
+
+    /// Programs in declaration order.
+    pub fn programs(&self) -> Vec<SymbolId> {
+        let mut programs = self.module.programs.keys().copied().collect::<Vec<_>>();
+        programs.sort();
+        programs
+    }
+
+    /// Finds a program by its possibly qualified name.
+    pub fn program(&self, name: &str) -> Option<SymbolId> {
+        self.programs().into_iter().find(|id| {
+            self.env
+                .table
+                .qualified_name(*id)
+                .eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Replaces the model estimating the execution time of instructions.
+    pub fn set_cost_model(&mut self, costs: CostModel) {
+        self.costs = self
+            .module
+            .functions
+            .iter()
+            .map(|f| f.code.iter().map(|i| costs.cost(i)).collect())
+            .collect();
+    }
+
+    /// Estimated execution time of everything run so far in nanoseconds, per the cost model.
+    pub fn spent(&self) -> u64 {
+        self.spent
+    }
+
+    /// Limits the instructions one scan cycle may execute, [`CYCLE_BUDGET`] by default.
+    pub fn set_cycle_budget(&mut self, instructions: u64) {
+        self.cycle_budget = instructions;
+    }
+
+    /// Sets the virtual clock the standard timers read, in nanoseconds.
+    pub fn set_clock(&mut self, now: u64) {
+        self.clock = now;
+    }
+
+    /// Executes one scan cycle of a program.
+    pub fn run_cycle(&mut self, program: SymbolId) -> Result<()> {
+        let (base, function) = self.module.programs[&program];
+        let body = self.module.functions[function as usize].body as usize;
+        self.execute(function as usize, body, base as usize)
+    }
+
+    /// Reads a variable by a path like `Main.counter`, `Main.timer.Q` or a global name.
+    pub fn read(&self, path: &str) -> Option<Value> {
+        let (address, ty) = self.resolve_path(path)?;
+        Some(self.value_at(address, &ty))
+    }
+
+    /// Overwrites a variable by its path, converting the value to the type of the variable.
+    pub fn write(&mut self, path: &str, value: Value) -> Option<()> {
+        let (address, ty) = self.resolve_path(path)?;
+        let value = value
+            .stored_like(&self.value_at(address, &ty))
+            .truncated(&ty);
+        self.store_value(address, &ty, &value);
+        Some(())
+    }
+
+    fn resolve_path(&self, path: &str) -> Option<(usize, Type)> {
+        let table = self.env.table;
+        let parts = path.split('.').collect::<Vec<_>>();
+        for split in (1..=parts.len()).rev() {
+            let root = parts[..split].join(".");
+            let named = |id: &SymbolId| table.qualified_name(*id).eq_ignore_ascii_case(&root);
+            let (mut address, mut ty) = match (
+                self.module.programs.iter().find(|(id, _)| named(id)),
+                self.module.globals.iter().find(|(id, _)| named(id)),
+            ) {
+                (Some((id, (base, _))), _) => (*base as usize, Type::Program(*id)),
+                (_, Some((id, address))) => (*address as usize, self.env.type_of_symbol(*id)),
+                _ => continue,
+            };
+
+            for part in &parts[split..] {
+                let (Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id)) = ty else {
+                    return None;
+                };
+                let members = table.symbol(id).members?;
+                let member = table.lookup_local(members, part)?;
+                address += *self.module.offsets.get(&member)? as usize;
+                ty = self.env.type_of_symbol(member);
+                if self.is_alias(member) {
+                    address = self.memory[address] as usize;
+                }
+            }
+            return Some((address, ty));
+        }
+
+        None
+    }
+
+    fn is_alias(&self, id: SymbolId) -> bool {
+        let symbol = self.env.table.symbol(id);
+        matches!(
+            symbol.kind,
+            SymbolKind::Variable {
+                kind: VariableKind::InOut,
+                ..
+            }
+        ) || matches!(self.env.type_of_symbol(id), Type::Reference(_))
+    }
+
+    fn value_at(&self, address: usize, ty: &Type) -> Value {
+        let n = self.memory.get(address).copied().unwrap_or_default();
+        match ty.dereferenced() {
+            Type::Elementary(e) => elementary_value(n, *e),
+            Type::Subrange { base, .. } => Value::Int(n as i128, *base),
+            Type::String { .. } => {
+                Value::String(self.strings.get(n as usize).cloned().unwrap_or_default())
+            }
+            Type::Enum(scope) => Value::Enum(n as i128, *scope),
+            Type::Pointer(_) => Value::Pointer((n != 0).then(|| Place::root(n as usize))),
+            Type::Array {
+                dimensions,
+                element,
+            } => {
+                let dimensions = dimensions.iter().flatten().copied().collect::<Vec<_>>();
+                let count = dimensions
+                    .iter()
+                    .map(|(l, u)| (u - l + 1).max(0) as usize)
+                    .product::<usize>();
+                let stride = match count {
+                    0 => 0,
+                    _ => self.size_of(element),
+                };
+                let items = (0..count)
+                    .map(|i| self.value_at(address + i * stride, element))
+                    .collect();
+                Value::Array(Box::new(ArrayValue { dimensions, items }))
+            }
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => {
+                Value::Struct(self.fields(*id, address))
+            }
+            _ => Value::Void,
+        }
+    }
+
+    fn fields(&self, id: SymbolId, address: usize) -> Vec<(String, Value)> {
+        let table = self.env.table;
+        let Some(members) = table.symbol(id).members else {
+            return Vec::new();
+        };
+        table
+            .symbols_in(members)
+            .into_iter()
+            .filter_map(|member| {
+                let offset = *self.module.offsets.get(&member)? as usize;
+                let symbol = table.symbol(member);
+                let value = match self.is_alias(member) {
+                    true => {
+                        let target = self.memory[address + offset] as usize;
+                        Value::Pointer((target != 0).then(|| Place::root(target)))
+                    }
+                    false => self.value_at(address + offset, &self.env.type_of_symbol(member)),
+                };
+                Some((symbol.name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Slots a value of the type occupies, following the layout of the compiler.
+    fn size_of(&self, ty: &Type) -> usize {
+        match ty {
+            Type::Array {
+                dimensions,
+                element,
+            } => {
+                let count = dimensions
+                    .iter()
+                    .map(|d| d.map_or(0, |(l, u)| (u - l + 1).max(0) as usize))
+                    .product::<usize>();
+                count * self.size_of(element)
+            }
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => {
+                let table = self.env.table;
+                let Some(members) = table.symbol(*id).members else {
+                    return 0;
+                };
+                table
+                    .symbols_in(members)
+                    .into_iter()
+                    .filter(|m| self.module.offsets.contains_key(m))
+                    .map(|m| match self.is_alias(m) {
+                        true => 1,
+                        false => self.size_of(&self.env.type_of_symbol(m)),
+                    })
+                    .sum()
+            }
+            Type::Void => 0,
+            _ => 1,
+        }
+    }
+
+    fn store_value(&mut self, address: usize, ty: &Type, value: &Value) {
+        match (ty.dereferenced(), value) {
+            (Type::Array { element, .. }, Value::Array(array)) => {
+                let stride = self.size_of(element);
+                for (i, item) in array.items.iter().enumerate() {
+                    self.store_value(address + i * stride, element, item);
+                }
+            }
+            (
+                Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id),
+                Value::Struct(fields),
+            ) => {
+                let table = self.env.table;
+                let Some(members) = table.symbol(*id).members else {
+                    return;
+                };
+                for (name, field) in fields {
+                    let member = table.lookup_local(members, name);
+                    if let Some(member) = member
+                        && let Some(offset) = self.module.offsets.get(&member)
+                        && !self.is_alias(member)
+                    {
+                        let member_type = self.env.type_of_symbol(member);
+                        self.store_value(address + *offset as usize, &member_type, field);
+                    }
+                }
+            }
+            (_, value) => {
+                let n = match value {
+                    Value::Bool(b) => *b as i64,
+                    Value::Int(n, _) | Value::Time(n) | Value::Date(n, _) | Value::Enum(n, _) => {
+                        *n as i64
+                    }
+                    Value::Real(r, _) => r.to_bits() as i64,
+                    Value::String(s) => {
+                        self.strings.push(s.clone());
+                        self.strings.len() as i64 - 1
+                    }
+                    Value::Pointer(Some(place)) => place.root as i64,
+                    _ => 0,
+                };
+                if let Some(slot) = self.memory.get_mut(address) {
+                    *slot = n;
+                }
+            }
+        }
+    }
+
+    /// Runs a function from the given instruction until it returns.
+    fn execute(&mut self, function: usize, pc: usize, this: usize) -> Result<()> {
+        let result = self.run(function, pc, this);
+        if result.is_err() {
+            // Drop whatever the failed call left behind, the static memory stays as it was
+            self.stack.clear();
+            self.frames.clear();
+            self.memory.truncate(self.module.static_size as usize);
+        }
+        result
+    }
+
+    fn run(&mut self, function: usize, pc: usize, this: usize) -> Result<()> {
+        let Self {
+            module,
+            strings,
+            string_ids,
+            memory,
+            stack,
+            frames,
+            costs,
+            spent,
+            clock,
+            cycle_budget,
+            ..
+        } = self;
+
+        let mut function = function;
+        let mut code = &module.functions[function].code;
+        let mut cost = &costs[function];
+        let mut pc = pc;
+        let mut bp = 0;
+        let mut this = this;
+        let mut calls: Vec<Activation> = Vec::new();
+        let mut budget = *cycle_budget;
+
+        macro_rules! fail {
+            ($message:expr) => {
+                return Err(Diagnostic::error(
+                    module.functions[function].spans[pc - 1],
+                    $message,
+                ))
+            };
+        }
+        macro_rules! pop {
+            () => {
+                stack.pop().unwrap_or_default()
+            };
+        }
+        macro_rules! top {
+            () => {
+                stack.last_mut().expect("The operand stack is not empty")
+            };
+        }
+        macro_rules! address {
+            ($address:expr) => {
+                match $address {
+                    Address::Static(a) => a as usize,
+                    Address::This(a) => this + a as usize,
+                    Address::Local(a) => bp + a as usize,
+                    Address::Callee(a) => frames.last().copied().unwrap_or_default() + a as usize,
+                }
+            };
+        }
+        macro_rules! checked {
+            ($address:expr) => {{
+                let address = $address as usize;
+                if address == 0 {
+                    fail!("Dereferenced pointer is null.");
+                }
+                if address >= memory.len() {
+                    fail!("Access to an invalid memory location.");
+                }
+                address
+            }};
+        }
+        macro_rules! int {
+            (|$a:ident, $b:ident| $e:expr) => {{
+                let $b = pop!();
+                let top = top!();
+                let $a = *top;
+                *top = $e;
+            }};
+        }
+        macro_rules! unary {
+            (|$a:ident| $e:expr) => {{
+                let top = top!();
+                let $a = *top;
+                *top = $e;
+            }};
+        }
+        macro_rules! real {
+            ($precision:expr, |$a:ident, $b:ident| $e:expr) => {{
+                let $b = f64::from_bits(pop!() as u64);
+                let top = top!();
+                let $a = f64::from_bits(*top as u64);
+                *top = round($e, $precision).to_bits() as i64;
+            }};
+        }
+
+        macro_rules! intern {
+            ($s:expr) => {{
+                let s: String = $s;
+                match string_ids.get(&s) {
+                    Some(id) => *id,
+                    None => {
+                        strings.push(s.clone());
+                        let id = strings.len() as i64 - 1;
+                        string_ids.insert(s, id);
+                        id
+                    }
+                }
+            }};
+        }
+        macro_rules! string {
+            ($n:expr) => {
+                strings[$n as usize].as_str()
+            };
+        }
+
+        loop {
+            let instruction = code[pc];
+            pc += 1;
+            *spent += cost[pc - 1];
+            budget = match budget.checked_sub(1) {
+                Some(budget) => budget,
+                None => fail!(format!(
+                    "The scan cycle ran more than {cycle_budget} instructions, it may loop forever."
+                )),
+            };
+            match instruction {
+                Instruction::Const(n) => stack.push(n),
+                Instruction::ConstReal(r) => stack.push(r.to_bits() as i64),
+                Instruction::Pop => {
+                    stack.pop();
+                }
+                Instruction::Dup => {
+                    let n = *top!();
+                    stack.push(n);
+                }
+                Instruction::Swap => {
+                    let n = stack.len();
+                    stack.swap(n - 1, n - 2);
+                }
+
+                Instruction::Load(a) => stack.push(memory[address!(a)]),
+                Instruction::Store(a) => memory[address!(a)] = pop!(),
+                Instruction::AddressOf(a) => stack.push(address!(a) as i64),
+                Instruction::LoadIndirect => {
+                    let address = checked!(*top!());
+                    stack.push(memory[address]);
+                    stack.swap_remove(stack.len() - 2);
+                }
+                Instruction::StoreIndirect => {
+                    let value = pop!();
+                    let address = checked!(pop!());
+                    memory[address] = value;
+                }
+                Instruction::Offset(n) => unary!(|a| a + n as i64),
+                Instruction::Index {
+                    lower,
+                    upper,
+                    stride,
+                } => {
+                    let index = pop!();
+                    if index < lower || index > upper {
+                        fail!(format!("Index is out of the bounds [{lower}..{upper}]."));
+                    }
+                    let address = checked!(pop!());
+                    stack.push((address + (index - lower) as usize * stride as usize) as i64);
+                }
+                Instruction::Copy(n) => {
+                    let source = checked!(pop!());
+                    let destination = checked!(pop!());
+                    let n = n as usize;
+                    if source + n > memory.len() || destination + n > memory.len() {
+                        fail!("Access to an invalid memory location.");
+                    }
+                    memory.copy_within(source..source + n, destination);
+                }
+
+                Instruction::Add(ty) => int!(|a, b| wrap(a.wrapping_add(b), ty)),
+                Instruction::Sub(ty) => int!(|a, b| wrap(a.wrapping_sub(b), ty)),
+                Instruction::Mul(ty) => int!(|a, b| wrap(a.wrapping_mul(b), ty)),
+                Instruction::Div(ty) | Instruction::Mod(ty) => {
+                    if *top!() == 0 {
+                        fail!("Division by zero.");
+                    }
+                    let modulo = matches!(instruction, Instruction::Mod(_));
+                    let unsigned = is_unsigned_64(ty);
+                    int!(|a, b| match (unsigned, modulo) {
+                        (true, false) => (a as u64 / b as u64) as i64,
+                        (true, true) => (a as u64 % b as u64) as i64,
+                        (false, false) => wrap(a.wrapping_div(b), ty),
+                        (false, true) => wrap(a.wrapping_rem(b), ty),
+                    })
+                }
+                Instruction::Neg(ty) => unary!(|a| wrap(a.wrapping_neg(), ty)),
+                Instruction::And => int!(|a, b| a & b),
+                Instruction::Or => int!(|a, b| a | b),
+                Instruction::Xor => int!(|a, b| a ^ b),
+                Instruction::Not(Elementary::Bool) => unary!(|a| a ^ 1),
+                Instruction::Not(ty) => unary!(|a| wrap(!a, ty)),
+
+                Instruction::AddReal(p) => real!(p, |a, b| a + b),
+                Instruction::SubReal(p) => real!(p, |a, b| a - b),
+                Instruction::MulReal(p) => real!(p, |a, b| a * b),
+                Instruction::DivReal(p) => real!(p, |a, b| a / b),
+                Instruction::PowReal(p) => real!(p, |a, b| a.powf(b)),
+                Instruction::NegReal => unary!(|a| (-f64::from_bits(a as u64)).to_bits() as i64),
+
+                Instruction::CompareInt(op) => int!(|a, b| compare(op, &a, &b) as i64),
+                Instruction::CompareUnsigned(op) => {
+                    int!(|a, b| compare(op, &(a as u64), &(b as u64)) as i64)
+                }
+                Instruction::CompareReal(op) => int!(|a, b| {
+                    let (a, b) = (f64::from_bits(a as u64), f64::from_bits(b as u64));
+                    compare(op, &a, &b) as i64
+                }),
+                Instruction::CompareString(op) => {
+                    int!(|a, b| compare(
+                        op,
+                        strings[a as usize].as_str(),
+                        strings[b as usize].as_str()
+                    ) as i64)
+                }
+
+                Instruction::Max(comparison) | Instruction::Min(comparison) => {
+                    let op = match instruction {
+                        Instruction::Max(_) => InfixOperator::GreaterThan,
+                        _ => InfixOperator::LessThan,
+                    };
+                    int!(|a, b| {
+                        let replaces = match comparison {
+                            Comparison::Int => compare(op, &b, &a),
+                            Comparison::Unsigned => compare(op, &(b as u64), &(a as u64)),
+                            Comparison::Real => {
+                                compare(op, &f64::from_bits(b as u64), &f64::from_bits(a as u64))
+                            }
+                            Comparison::String => compare(op, string!(b), string!(a)),
+                        };
+                        if replaces { b } else { a }
+                    })
+                }
+                Instruction::Select => {
+                    let b = pop!();
+                    let a = pop!();
+                    let top = top!();
+                    *top = if *top != 0 { b } else { a };
+                }
+                Instruction::Choose(n) => {
+                    let inputs = stack.split_off(stack.len() - n as usize);
+                    let index = pop!();
+                    match usize::try_from(index).ok().and_then(|i| inputs.get(i)) {
+                        Some(input) => stack.push(*input),
+                        None => fail!(format!("Index is out of the bounds [0..{}].", n - 1)),
+                    }
+                }
+
+                Instruction::Wrap(ty) => unary!(|a| wrap(a, ty)),
+                Instruction::IntToReal(ty, p) => unary!(|a| {
+                    let r = match is_unsigned_64(ty) {
+                        true => a as u64 as f64,
+                        false => a as f64,
+                    };
+                    round(r, p).to_bits() as i64
+                }),
+                Instruction::RealToInt(ty) => unary!(|a| {
+                    let r = f64::from_bits(a as u64).trunc();
+                    match is_unsigned_64(ty) {
+                        true => r as u64 as i64,
+                        false => wrap(r as i64, ty),
+                    }
+                }),
+                Instruction::RoundReal => {
+                    unary!(|a| round(f64::from_bits(a as u64), Precision::Single).to_bits() as i64)
+                }
+                Instruction::RoundToInt(ty) => unary!(|a| {
+                    let r = f64::from_bits(a as u64).round_ties_even();
+                    match is_unsigned_64(ty) {
+                        true => r as u64 as i64,
+                        false => wrap(r as i64, ty),
+                    }
+                }),
+                Instruction::ToString(ty) => {
+                    let n = pop!();
+                    let text = match ty {
+                        Elementary::Bool => Value::Bool(n != 0).to_string(),
+                        ty if ty.is_real() => library::format_real(f64::from_bits(n as u64), ty),
+                        ty if ty.is_duration() => library::format_time(n as i128),
+                        ty if ty.is_date() => Value::Date(n as i128, ty).to_string(),
+                        Elementary::Char | Elementary::WChar => char::from_u32(n as u32)
+                            .map(String::from)
+                            .unwrap_or_default(),
+                        ty => value::wrap(n as i128, ty).to_string(),
+                    };
+                    stack.push(intern!(text));
+                }
+                Instruction::Truncate(length) => {
+                    let n = pop!();
+                    let s = string!(n);
+                    match s.char_indices().nth(length as usize) {
+                        Some((end, _)) => {
+                            let truncated = s[..end].to_string();
+                            stack.push(intern!(truncated));
+                        }
+                        None => stack.push(n),
+                    }
+                }
+                Instruction::FromString(ty) => {
+                    let s = string!(pop!());
+                    stack.push(match ty {
+                        Elementary::Bool => library::parse_bool(s) as i64,
+                        Elementary::Real => {
+                            round(library::parse_real(s), Precision::Single).to_bits() as i64
+                        }
+                        Elementary::Lreal => library::parse_real(s).to_bits() as i64,
+                        ty if ty.is_temporal() => (library::parse_int(s) * MILLISECOND) as i64,
+                        Elementary::Char | Elementary::WChar => {
+                            s.chars().next().map_or(0, |c| c as i64)
+                        }
+                        ty => value::wrap(library::parse_int(s), ty) as i64,
+                    });
+                }
+
+                Instruction::RealFunction(function, p) => unary!(|a| {
+                    let r = library::real_function(function, f64::from_bits(a as u64));
+                    round(r, p).to_bits() as i64
+                }),
+                Instruction::Shift(function, ty) => int!(|a, b| {
+                    let width = ty.bit_width().unwrap_or(1);
+                    let shifted = library::shift(function, a as i128, b as i128, width);
+                    value::wrap(shifted, ty) as i64
+                }),
+                Instruction::StringFunction(function) => {
+                    let text = match function {
+                        Function::Len => {
+                            let n = string!(pop!()).chars().count();
+                            stack.push(n as i64);
+                            continue;
+                        }
+                        Function::Find => {
+                            let b = pop!();
+                            let a = pop!();
+                            stack.push(library::find(string!(a), string!(b)) as i64);
+                            continue;
+                        }
+                        Function::Concat => {
+                            let b = pop!();
+                            let a = pop!();
+                            format!("{}{}", string!(a), string!(b))
+                        }
+                        Function::Left | Function::Right => {
+                            let l = pop!() as i128;
+                            let a = pop!();
+                            library::edit_string(function, string!(a), "", l, 0)
+                        }
+                        Function::Mid | Function::Delete => {
+                            let p = pop!() as i128;
+                            let l = pop!() as i128;
+                            let a = pop!();
+                            library::edit_string(function, string!(a), "", l, p)
+                        }
+                        Function::Insert => {
+                            let p = pop!() as i128;
+                            let b = pop!();
+                            let a = pop!();
+                            library::edit_string(function, string!(a), string!(b), 0, p)
+                        }
+                        Function::Replace => {
+                            let p = pop!() as i128;
+                            let l = pop!() as i128;
+                            let b = pop!();
+                            let a = pop!();
+                            library::edit_string(function, string!(a), string!(b), l, p)
+                        }
+                        f => unreachable!("{} is not a function on strings", f.name()),
+                    };
+                    stack.push(intern!(text));
+                }
+                Instruction::Assert(assertion, scalar) => {
+                    let n = match assertion {
+                        Function::AssertEq | Function::AssertNe => 2,
+                        Function::AssertNear => 3,
+                        _ => 1,
+                    };
+                    let operands = stack.split_off(stack.len() - n);
+                    let values = operands
+                        .into_iter()
+                        .map(|n| match scalar {
+                            Scalar::Elementary(e) => elementary_value(n, e),
+                            Scalar::String { .. } => Value::String(string!(n).to_string()),
+                        })
+                        .collect::<Vec<_>>();
+                    let span = module.functions[function].spans[pc - 1];
+                    if let Some(message) = runtime_library::assertion(assertion, &values, span)? {
+                        return Err(runtime_library::assertion_failure(span, message));
+                    }
+                }
+                Instruction::StandardBlock(block) => {
+                    let n = block.variables().len();
+                    let slots = &mut memory[this..this + n];
+                    let mut values = slots.iter().map(|v| *v as i128).collect::<Vec<_>>();
+                    runtime_library::execute(block, &mut values, *clock as i128);
+                    for (slot, value) in slots.iter_mut().zip(values) {
+                        *slot = value as i64;
+                    }
+                }
+
+                Instruction::Jump(target) => pc = target as usize,
+                Instruction::JumpIfFalse(target) => {
+                    if pop!() == 0 {
+                        pc = target as usize;
+                    }
+                }
+                Instruction::JumpIfTrue(target) => {
+                    if pop!() != 0 {
+                        pc = target as usize;
+                    }
+                }
+                Instruction::ForTest { variable, exit } => {
+                    let n = stack.len();
+                    let (end, step) = (stack[n - 2], stack[n - 1]);
+                    let value = memory[address!(variable)];
+                    if (step >= 0 && value > end) || (step < 0 && value < end) {
+                        pc = exit as usize;
+                    }
+                }
+                Instruction::ForStep {
+                    variable,
+                    ty,
+                    test,
+                    exit,
+                } => {
+                    let address = address!(variable);
+                    let next = memory[address] as i128 + *top!() as i128;
+                    // Stop instead of wrapping around at the end of the range of the variable
+                    match ty.int_range() {
+                        Some((lower, upper)) if next < lower || next > upper => {
+                            pc = exit as usize;
+                        }
+                        _ => {
+                            memory[address] = next as i64;
+                            pc = test as usize;
+                        }
+                    }
+                }
+
+                Instruction::Enter(callee) => {
+                    let base = memory.len();
+                    let size = module.functions[callee as usize].frame_size as usize;
+                    memory.resize(base + size, 0);
+                    frames.push(base);
+                    calls.push(Activation {
+                        function,
+                        pc,
+                        bp,
+                        this,
+                        height: stack.len(),
+                    });
+                    function = callee as usize;
+                    code = &module.functions[function].code;
+                    cost = &costs[function];
+                    pc = 0;
+                    bp = base;
+                }
+                Instruction::Call(callee) | Instruction::CallWith(callee) => {
+                    if calls.len() >= MAX_CALL_DEPTH {
+                        fail!("Call stack overflow.");
+                    }
+                    let instance = match instruction {
+                        Instruction::CallWith(_) => pop!() as usize,
+                        _ => this,
+                    };
+                    calls.push(Activation {
+                        function,
+                        pc,
+                        bp,
+                        this,
+                        height: stack.len(),
+                    });
+                    function = callee as usize;
+                    let target = &module.functions[function];
+                    code = &target.code;
+                    cost = &costs[function];
+                    pc = target.body as usize;
+                    this = instance;
+                    if target.frame_size > 0 {
+                        bp = frames.last().copied().unwrap_or_default();
+                    }
+                }
+                Instruction::Leave { result } => {
+                    let base = frames.pop().unwrap_or_default();
+                    if result {
+                        stack.push(memory[base]);
+                    }
+                    memory.truncate(base);
+                }
+                Instruction::Return => match calls.pop() {
+                    Some(caller) => {
+                        stack.truncate(caller.height);
+                        function = caller.function;
+                        code = &module.functions[function].code;
+                        cost = &costs[function];
+                        pc = caller.pc;
+                        bp = caller.bp;
+                        this = caller.this;
+                    }
+                    None => {
+                        stack.clear();
+                        return Ok(());
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Wraps an integer around like the hardware does on an overflow of the type.
+fn wrap(n: i64, ty: Elementary) -> i64 {
+    match ty.bit_width() {
+        Some(1) => n & 1,
+        Some(bits) if bits < 64 => match ty.is_signed() {
+            true => (n << (64 - bits)) >> (64 - bits),
+            false => n & ((1 << bits) - 1),
+        },
+        _ => n,
+    }
+}
+
+/// The value of an elementary type held by a slot.
+fn elementary_value(n: i64, ty: Elementary) -> Value {
+    match ty {
+        Elementary::Bool => Value::Bool(n != 0),
+        ty if ty.is_real() => Value::Real(f64::from_bits(n as u64), ty),
+        ty if ty.is_duration() => Value::Time(n as i128),
+        ty if ty.is_date() => Value::Date(n as i128, ty),
+        ty if is_unsigned_64(ty) => Value::Int(n as u64 as i128, ty),
+        ty => Value::Int(n as i128, ty),
+    }
+}
+
+/// ULINT and LWORD use all 64 bits of a slot, so they need unsigned arithmetic.
+fn is_unsigned_64(ty: Elementary) -> bool {
+    ty.bit_width() == Some(64) && !ty.is_signed()
+}
+
+fn round(r: f64, precision: Precision) -> f64 {
+    match precision {
+        Precision::Single => r as f32 as f64,
+        Precision::Double => r,
+    }
+}
+
+fn compare<T: PartialOrd + ?Sized>(op: InfixOperator, a: &T, b: &T) -> bool {
+    match op {
+        InfixOperator::Equals => a == b,
+        InfixOperator::NotEquals => a != b,
+        InfixOperator::GreaterThan => a > b,
+        InfixOperator::GreaterThanOrEquals => a >= b,
+        InfixOperator::LessThan => a < b,
+        _ => a <= b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        runtime::{Engine, interpreter::Interpreter},
+        semantic::{checker::check, resolver::resolve},
+    };
+
+    /// Runs all programs on the VM and the interpreter and returns what both read afterwards.
+    fn run_both(src: &str, cycles: usize, paths: &[&str]) -> (Vec<String>, Vec<String>) {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, mut diagnostics) = resolve(&ast);
+        diagnostics.extend(check(&ast, &table));
+        assert_eq!(diagnostics, Vec::new());
+
+        let mut vm = Vm::new(&ast, &table).unwrap();
+        let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+        for _ in 0..cycles {
+            for program in vm.programs() {
+                vm.run_cycle(program).unwrap();
+                interpreter.run_cycle(program).unwrap();
+            }
+        }
+
+        let env = TypeEnv::new(&table);
+        let read = |value: Option<Value>| {
+            value.map_or("<missing>".to_string(), |v| v.display(&env).to_string())
+        };
+        (
+            paths.iter().map(|p| read(vm.read(p))).collect(),
+            paths.iter().map(|p| read(interpreter.read(p))).collect(),
+        )
+    }
+
+    #[test]
+    fn test_matches_interpreter() {
+        let (vm, interpreter) = run_both(
+            r#"
+        TYPE MachineState : (Idle, Running := 5, Done); END_TYPE
+
+        FUNCTION Clamp : INT
+            VAR_INPUT value : INT; limit : INT := 10; END_VAR
+            Clamp := value;
+            IF value > limit THEN Clamp := limit; END_IF;
+        END_FUNCTION
+
+        FUNCTION Swap
+            VAR_IN_OUT a : INT; b : INT; END_VAR
+            VAR tmp : INT; END_VAR
+            tmp := a; a := b; b := tmp;
+        END_FUNCTION
+
+        FUNCTION_BLOCK UpCounter
+            VAR_INPUT step : INT := 1; END_VAR
+            VAR_OUTPUT count : INT; END_VAR
+            count := count + step;
+            METHOD Reset
+                count := 0;
+            END_METHOD
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR
+                i : INT;
+                sum : INT;
+                n : INT;
+                small : SINT := 120;
+                state : MachineState;
+                label : STRING;
+                values : ARRAY[1..5] OF INT := [1, 2, 3, 4, 5];
+                counter : UpCounter;
+                seen : INT;
+                clamped : INT;
+                x : INT := 1;
+                y : INT := 2;
+            END_VAR
+            VAR_TEMP scratch : INT; END_VAR
+            scratch := scratch + 1;
+            sum := 0;
+            FOR i := 1 TO 5 DO
+                IF i = 2 THEN CONTINUE; END_IF;
+                IF i = 5 THEN EXIT; END_IF;
+                sum := sum + values[i] * scratch;
+            END_FOR;
+            n := 0;
+            WHILE n < 10 DO n := n + 3; END_WHILE;
+            REPEAT n := n - 1; UNTIL n <= 5 END_REPEAT;
+            small := small + 3;
+            CASE state OF
+                Idle: state := Running; label := 'started';
+                Running..Done: state := Done; label := 'finished';
+            ELSE
+                label := 'unknown';
+            END_CASE;
+            counter(step := 2, count => seen);
+            IF counter.count >= 6 THEN counter.Reset(); END_IF;
+            clamped := Clamp(seen * 5);
+            Swap(x, y);
+        END_PROGRAM
+        "#,
+            4,
+            &["Main"],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert!(vm[0].contains("small := -124"), "{}", vm[0]);
+    }
+
+    #[test]
+    fn test_dates_and_times_of_day() {
+        let (vm, interpreter) = run_both(
+            r#"
+        PROGRAM Main
+            VAR
+                day : DATE := D#2024-01-15;
+                noon : TOD := TIME_OF_DAY#12:00:00;
+                stamp : DT := DT#2024-02-28-23:59:59.5;
+                later : BOOL;
+            END_VAR
+            stamp := stamp + T#1d;
+            noon := noon + T#90m;
+            later := stamp > DT#2024-02-29-00:00:00;
+        END_PROGRAM
+        "#,
+            1,
+            &["Main.day", "Main.noon", "Main.stamp", "Main.later"],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert_eq!(
+            vm,
+            [
+                "D#2024-01-15",
+                "TOD#13:30:00",
+                "DT#2024-02-29-23:59:59.5",
+                "TRUE"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_enum_values() {
+        let (vm, interpreter) = run_both(
+            r#"
+        TYPE
+            Mode : (Slow := 1, Fast := 10);
+            Drive : STRUCT mode : Mode; END_STRUCT;
+        END_TYPE
+        PROGRAM Main
+            VAR
+                m : Mode;
+                light : (Red, Green);
+                motor : Drive;
+            END_VAR
+            m := Fast;
+            light := Green;
+            motor.mode := m;
+        END_PROGRAM
+        "#,
+            1,
+            &["Main.m", "Main.light", "Main.motor"],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert_eq!(vm, ["Mode#Fast", "Green", "(mode := Mode#Fast)"]);
+    }
+
+    #[test]
+    fn test_calls_function_block_array_elements() {
+        let (vm, interpreter) = run_both(
+            r#"
+        FUNCTION_BLOCK Acc
+            VAR_INPUT step : INT; END_VAR
+            VAR_OUTPUT total : INT; END_VAR
+            total := total + step;
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR
+                accs : ARRAY[1..3] OF Acc;
+                i : INT;
+                seen : INT;
+                second : INT;
+            END_VAR
+            accs[2](step := 5, total => seen);
+            FOR i := 1 TO 3 DO
+                accs[i](step := i);
+            END_FOR;
+            second := accs[2].total;
+        END_PROGRAM
+        "#,
+            2,
+            &["Main.seen", "Main.second", "Main"],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert_eq!(vm[..2], ["12", "14"]);
+    }
+
+    #[test]
+    fn test_truncates_strings_to_their_length() {
+        let (vm, interpreter) = run_both(
+            r#"
+        FUNCTION_BLOCK Caption
+            VAR_INPUT text : STRING[8]; END_VAR
+            VAR_OUTPUT shown : STRING[4]; END_VAR
+            shown := text;
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR
+                short : STRING[20] := 'hello world world world';
+                grown : STRING[20];
+                length : INT;
+                plain : STRING;
+                view : Caption;
+                copy : STRING[6];
+            END_VAR
+            grown := CONCAT('hello', ' world');
+            grown := CONCAT(grown, ' world world');
+            length := LEN(grown);
+            plain := CONCAT(grown, grown, grown, grown, grown);
+            view(text := grown, shown => copy);
+        END_PROGRAM
+        "#,
+            1,
+            &[
+                "Main.short",
+                "Main.grown",
+                "Main.length",
+                "Main.plain",
+                "Main.view.text",
+                "Main.copy",
+            ],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert_eq!(
+            vm,
+            [
+                "'hello world world wo'",
+                "'hello world world wo'",
+                "20",
+                format!("'{}'", "hello world world wo".repeat(4)).as_str(),
+                "'hello wo'",
+                "'hell'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_standard_functions() {
+        let (vm, interpreter) = run_both(
+            r#"
+        PROGRAM Main
+            VAR
+                i : INT := -7;
+                r : REAL := 2.5;
+                w : BYTE := 16#81;
+                d : INT;
+                stamp : DINT := 86400000;
+                t : TIME := T#1500ms;
+                text : STRING := 'Hello';
+                magnitude : INT;
+                root : REAL;
+                power : LREAL;
+                truncated : DINT;
+                larger : INT;
+                smallest : REAL;
+                limited : INT;
+                selected : STRING;
+                chosen : INT;
+                shifted : BYTE;
+                rotated : BYTE;
+                length : INT;
+                edited : STRING;
+                position : INT;
+                rounded : INT;
+                wrapped : SINT;
+                flag : BOOL;
+                millis : DINT;
+                printed : STRING;
+                parsed : UINT;
+                time_of_day : TOD;
+                fine : STRING;
+                delay : TIME;
+            END_VAR
+            d := d + 1;
+            magnitude := ABS(i);
+            root := SQRT(r * 10.0);
+            power := EXPT(2.0, d);
+            truncated := TRUNC(-r);
+            larger := MAX(i, 3, d);
+            smallest := MIN(r, 1, d);
+            limited := LIMIT(0, i * -10, 50);
+            selected := SEL(d > 2, 'low', 'high');
+            chosen := MUX(d - 1, 10, 20, 30, 40);
+            shifted := SHL(w, 1);
+            rotated := ROL(w, d);
+            length := LEN(text);
+            edited := CONCAT(LEFT(text, 2), MID(text, 2, 3), REPLACE(text, 'J', 1, 1), 'x');
+            edited := INSERT(DELETE(edited, 1, 1), '-', 3);
+            position := FIND(edited, 'll');
+            rounded := REAL_TO_INT(r);
+            wrapped := INT_TO_SINT(300 * d);
+            flag := LREAL_TO_BOOL(power - 2.0);
+            millis := TIME_TO_DINT(t);
+            printed := CONCAT(REAL_TO_STRING(r), TIME_TO_STRING(t), INT_TO_STRING(i), BOOL_TO_STRING(flag));
+            parsed := STRING_TO_UINT(' 42 ');
+            time_of_day := DINT_TO_TOD(stamp + d);
+            fine := TIME_TO_STRING(T#1ms500us + t / 1000000);
+            delay := DINT_TO_TIME(d) + T#250ns;
+        END_PROGRAM
+        "#,
+            3,
+            &["Main"],
+        );
+
+        assert_eq!(vm, interpreter);
+        for expected in [
+            "magnitude := 7",
+            "root := 5.0",
+            "power := 8.0",
+            "truncated := -2",
+            "larger := 3",
+            "smallest := 1.0",
+            "limited := 50",
+            "selected := 'high'",
+            "chosen := 30",
+            "shifted := 2",
+            "rotated := 12",
+            "length := 5",
+            "edited := 'ell-Jellox'",
+            "position := 2",
+            "rounded := 2",
+            "wrapped := -124",
+            "flag := TRUE",
+            "millis := 1500",
+            "printed := '2.5T#1500ms-7TRUE'",
+            "parsed := 42",
+            "time_of_day := TOD#00:00:00.003",
+            "fine := 'T#1501500ns'",
+            "delay := T#3000250ns",
+        ] {
+            assert!(vm[0].contains(expected), "{expected} in {}", vm[0]);
+        }
+    }
+
+    #[test]
+    fn test_standard_function_blocks() {
+        let src = r#"
+        PROGRAM Main
+            VAR
+                start : BOOL;
+                delay : TON;
+                hold : TOF;
+                pulse : TP;
+                pulses : CTU;
+                left : CTD;
+                level : CTUD;
+                rising : R_TRIG;
+                falling : F_TRIG;
+                set_first : SR;
+                reset_first : RS;
+                elapsed : TIME;
+                edges : INT;
+            END_VAR
+            delay(IN := start, PT := T#100ms, ET => elapsed);
+            hold(IN := start, PT := T#50ms);
+            pulse(IN := start, PT := T#30ms);
+            rising(CLK := start);
+            falling(CLK := start);
+            IF rising.Q OR falling.Q THEN edges := edges + 1; END_IF;
+            pulses(CU := start, PV := 2);
+            left(CD := start, LD := edges = 0, PV := 3);
+            level(CU := start, CD := falling.Q, PV := 1);
+            set_first(S1 := start, R := pulse.Q);
+            reset_first(S := start, R1 := pulse.Q);
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, mut diagnostics) = resolve(&ast);
+        diagnostics.extend(check(&ast, &table));
+        assert_eq!(diagnostics, Vec::new());
+
+        let mut vm = Vm::new(&ast, &table).unwrap();
+        let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+        let engines: [&mut dyn Engine; 2] = [&mut vm, &mut interpreter];
+        let paths = [
+            "Main.delay.Q",
+            "Main.elapsed",
+            "Main.hold.Q",
+            "Main.pulse.Q",
+            "Main.pulses.CV",
+            "Main.pulses.Q",
+            "Main.left.CV",
+            "Main.level.CV",
+            "Main.edges",
+            "Main.set_first.Q1",
+            "Main.reset_first.Q1",
+        ];
+        let mut results = Vec::new();
+        for engine in engines {
+            let main = engine.programs()[0];
+            let mut states = Vec::new();
+            for (ms, start) in [
+                (0, true),
+                (40, true),
+                (120, false),
+                (150, true),
+                (160, false),
+            ] {
+                engine.set_clock(ms * 1_000_000);
+                engine.write("Main.start", Value::Bool(start)).unwrap();
+                engine.run_cycle(main).unwrap();
+                let state = paths
+                    .iter()
+                    .map(|p| engine.read(p).unwrap().to_string())
+                    .collect::<Vec<_>>();
+                states.push(state.join(" "));
+            }
+            results.push(states);
+        }
+
+        assert_eq!(results[0], results[1]);
+        assert_eq!(
+            results[0],
+            [
+                "FALSE T#0ms TRUE TRUE 1 FALSE -1 1 1 TRUE FALSE",
+                "FALSE T#40ms TRUE FALSE 1 FALSE -1 1 1 TRUE TRUE",
+                "FALSE T#0ms TRUE FALSE 1 FALSE -1 0 2 TRUE TRUE",
+                "FALSE T#0ms TRUE TRUE 2 TRUE -2 1 3 TRUE FALSE",
+                "FALSE T#0ms TRUE TRUE 2 TRUE -2 0 4 FALSE FALSE",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structured_data_and_instances() {
+        let (vm, interpreter) = run_both(
+            r#"
+        TYPE Point : STRUCT x : REAL; y : REAL := 1.5; END_STRUCT END_TYPE
+
+        VAR_GLOBAL
+            origin : Point := (x := 2.0);
+            elapsed : TIME;
+        END_VAR
+
+        FUNCTION_BLOCK Track
+            VAR_EXTERNAL elapsed : TIME; END_VAR
+            VAR_INPUT speed : REAL; END_VAR
+            VAR_OUTPUT done : BOOL; END_VAR
+            VAR points : ARRAY[0..2, 1..2] OF Point; i : INT; END_VAR
+            FOR i := 0 TO 2 DO
+                points[i, 1].x := points[i, 1].x + speed * i;
+                points[i, 2] := points[i, 1];
+            END_FOR;
+            elapsed := elapsed + T#100ms * 2;
+            done := points[2, 2].x > 3.0 AND elapsed >= T#1s;
+            Halve();
+        END_FUNCTION_BLOCK
+
+        ACTION Track.Halve
+            speed := speed / 2.0;
+        END_ACTION
+
+        FUNCTION Scale
+            VAR_IN_OUT p : Point; END_VAR
+            VAR_INPUT factor : REAL; END_VAR
+            p.x := p.x * factor;
+        END_FUNCTION
+
+        PROGRAM Main
+            VAR
+                fast : Track;
+                slow : Track;
+                copy : Point;
+                ok : BOOL;
+                word : WORD := 16#00F0;
+                name : STRING := 'b';
+            END_VAR
+            fast(speed := 1.0);
+            slow(speed := 0.5, done => ok);
+            copy := origin;
+            Scale(copy, 1.5);
+            word := NOT word XOR 16#0F0F;
+            ok := ok OR name > 'a';
+        END_PROGRAM
+        "#,
+            6,
+            &["Main", "origin", "elapsed"],
+        );
+
+        assert_eq!(vm, interpreter);
+        assert_eq!(vm[2], "T#2400ms");
+    }
+
+    #[test]
+    fn test_reports_runtime_errors() {
+        let src = r#"
+        PROGRAM Main
+            VAR values : ARRAY[0..2] OF INT; i : INT; d : INT := 2; END_VAR
+            i := i + 1;
+            values[i * 2] := 10 / (2 - i);
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let mut vm = Vm::new(&ast, &table).unwrap();
+        let main = vm.program("Main").unwrap();
+
+        assert_eq!(vm.run_cycle(main), Ok(()));
+        let error = vm.run_cycle(main).unwrap_err();
+        assert_eq!(error.message, "Division by zero.");
+        let error = vm.run_cycle(main).unwrap_err();
+        assert_eq!(error.message, "Index is out of the bounds [0..2].");
+        assert_eq!(vm.read("Main.values").unwrap().to_string(), "[0, 0, 10]");
+    }
+
+    #[test]
+    fn test_stops_runaway_recursion_and_loops() {
+        let src = r#"
+        FUNCTION Depth : DINT
+            VAR_INPUT n : DINT; END_VAR
+            IF n <= 0 THEN Depth := 0; ELSE Depth := 1 + Depth(n - 1); END_IF;
+        END_FUNCTION
+
+        PROGRAM Main
+            VAR n : DINT := 100; result : DINT; hang : BOOL; END_VAR
+            result := Depth(n);
+            WHILE hang DO END_WHILE;
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let mut vm = Vm::new(&ast, &table).unwrap();
+        let main = vm.program("Main").unwrap();
+
+        assert_eq!(vm.run_cycle(main), Ok(()));
+        assert_eq!(
+            vm.read("Main.result"),
+            Some(Value::Int(100, Elementary::Dint))
+        );
+        vm.write("Main.n", Value::Int(1_000_000, Elementary::Dint));
+        let error = vm.run_cycle(main).unwrap_err();
+        assert_eq!(error.message, "Call stack overflow.");
+
+        vm.write("Main.n", Value::Int(1, Elementary::Dint));
+        vm.write("Main.hang", Value::Bool(true));
+        vm.set_cycle_budget(10_000);
+        let error = vm.run_cycle(main).unwrap_err();
+        assert_eq!(
+            error.message,
+            "The scan cycle ran more than 10000 instructions, it may loop forever."
+        );
+        let mut interpreter = Interpreter::new(&ast, &table).unwrap();
+        interpreter.write("Main.n", Value::Int(1, Elementary::Dint));
+        interpreter.write("Main.hang", Value::Bool(true));
+        interpreter.set_cycle_budget(10_000);
+        let error = interpreter.run_cycle(main).unwrap_err();
+        assert_eq!(
+            error.message,
+            "The scan cycle ran more than 10000 statements, it may loop forever."
+        );
+
+        vm.write("Main.hang", Value::Bool(false));
+        assert_eq!(vm.run_cycle(main), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_what_it_cannot_compile() {
+        let src = r#"
+        FUNCTION_BLOCK Scaler
+            VAR_INPUT value : INT; END_VAR
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR x : INT; END_VAR
+            x := Rescale(x - 1);
+        END_PROGRAM
+        "#;
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let error = Vm::new(&ast, &table).err().unwrap();
+        assert_eq!(
+            error.message,
+            "The called function is not available at runtime."
+        );
+    }
+}
@@ -0,0 +1,8 @@
+pub mod checker;
+pub mod consteval;
+pub mod flow;
+pub mod highlight;
+pub mod library;
+pub mod resolver;
+pub mod symbols;
+pub mod types;
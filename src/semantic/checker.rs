@@ -0,0 +1,1294 @@
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{
+        Argument, Ast, Block, CallExpression, CaseLabel, Configuration, DataType, Expression,
+        InfixExpression, InfixOperator, LiteralExpression, Pou, PrefixOperator, Span, Statement,
+        Statements, Task, TypeDeclaration, VariableBlock, VariableKind,
+    },
+    parsing::token::NumberValue,
+    semantic::{
+        consteval::{self, ConstValue, EvalError},
+        library::{self, CallError, Function},
+        symbols::{SymbolKind, SymbolTable},
+        types::{Elementary, Generic, Type, TypeEnv, infix_type, is_assignable},
+    },
+};
+
+const BOOL: Type = Type::Elementary(Elementary::Bool);
+
+/// Checks the types of all declarations, statements and expressions of a resolved program.
+pub fn check(ast: &Ast, table: &SymbolTable) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        env: TypeEnv::new(table),
+        diagnostics: Vec::new(),
+    };
+    checker.check_blocks(&ast.blocks);
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    env: TypeEnv<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Derives the types of expressions like the checker does, leaving their errors to it.
+pub struct Typer<'a>(Checker<'a>);
+
+impl<'a> Typer<'a> {
+    pub fn new(table: &'a SymbolTable) -> Self {
+        Self(Checker {
+            env: TypeEnv::new(table),
+            diagnostics: Vec::new(),
+        })
+    }
+
+    pub fn env(&self) -> &TypeEnv<'a> {
+        &self.0.env
+    }
+
+    /// Type of an expression, [`Type::Unknown`] if it has errors.
+    pub fn type_of(&mut self, expression: &Expression) -> Type {
+        let ty = self.0.check_expression(expression);
+        self.0.diagnostics.clear();
+        ty
+    }
+}
+
+impl Checker<'_> {
+    fn error(&mut self, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic::error(span, message));
+    }
+
+    fn show(&self, ty: &Type) -> String {
+        ty.display(self.env.table).to_string()
+    }
+
+    fn check_blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) | Block::Function(pou) | Block::FunctionBlock(pou) => {
+                    self.check_pou(pou)
+                }
+                Block::Action(action) => self.check_statements(&action.statements),
+                Block::Type(types) => {
+                    for declaration in types {
+                        self.check_type_declaration(declaration);
+                    }
+                }
+                Block::GlobalVariables(variables) => self.check_variables(variables),
+                Block::Namespace(namespace) => self.check_blocks(&namespace.blocks),
+                Block::Configuration(configuration) => self.check_configuration(configuration),
+            }
+        }
+    }
+
+    fn check_configuration(&mut self, configuration: &Configuration) {
+        for variables in &configuration.variables {
+            self.check_variables(variables);
+        }
+        for resource in &configuration.resources {
+            for variables in &resource.variables {
+                self.check_variables(variables);
+            }
+            for task in &resource.tasks {
+                self.check_task(task);
+            }
+            for instance in &resource.programs {
+                let program = instance.program.parts.last().map(|p| p.span);
+                match program.and_then(|span| self.env.binding(span)) {
+                    Some(id) if self.env.table.symbol(id).kind == SymbolKind::Program => {
+                        self.check_arguments(id, &instance.arguments)
+                    }
+                    _ => {
+                        for argument in &instance.arguments {
+                            self.check_expression(argument.value());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_task(&mut self, task: &Task) {
+        let duration = |t: &Type| t.elementary().is_some_and(|e| e.is_duration());
+        self.check_task_property(&task.interval, "INTERVAL", "of type TIME", duration);
+        let boolean = |t: &Type| t.is(Elementary::Bool);
+        self.check_task_property(&task.single, "SINGLE", "of type BOOL", boolean);
+        let integer = |t: &Type| Generic::AnyInt.contains(t);
+        self.check_task_property(&task.priority, "PRIORITY", "an integer", integer);
+    }
+
+    fn check_task_property(
+        &mut self,
+        value: &Option<Expression>,
+        property: &str,
+        expected: &str,
+        accepts: fn(&Type) -> bool,
+    ) {
+        let Some(value) = value else {
+            return;
+        };
+        let ty = self.check_expression(value);
+        if !ty.is_unknown() && !accepts(ty.dereferenced()) {
+            let message = format!(
+                "Task property {property} must be {expected}, found {}.",
+                self.show(&ty)
+            );
+            self.error(value.span(), message);
+        }
+    }
+
+    fn check_pou(&mut self, pou: &Pou) {
+        for variables in &pou.variables {
+            self.check_variables(variables);
+        }
+        for method in &pou.methods {
+            self.check_pou(method);
+        }
+        for action in &pou.actions {
+            self.check_statements(&action.statements);
+        }
+        self.check_statements(&pou.statements);
+    }
+
+    fn check_variables(&mut self, variables: &VariableBlock) {
+        for declaration in &variables.declarations {
+            self.check_data_type(&declaration.data_type);
+            if let Some(initializer) = &declaration.initializer {
+                let ty = self.env.lower(&declaration.data_type);
+                // Failures of the evaluation itself are reported along with the assignment
+                if variables.constant
+                    && !matches!(initializer, Expression::Struct(..) | Expression::Array(..))
+                    && let Err(EvalError::NotConstant(span)) =
+                        consteval::evaluate(&self.env, initializer)
+                {
+                    let message = "The value of a constant must be a constant expression.";
+                    self.error(span, message.to_string());
+                }
+                self.check_initializer(initializer, &ty);
+            }
+        }
+    }
+
+    fn check_type_declaration(&mut self, declaration: &TypeDeclaration) {
+        self.check_data_type(&declaration.data_type);
+        if let Some(initializer) = &declaration.initializer {
+            let ty = match self.env.binding(declaration.name.span) {
+                Some(id) => self.env.type_of_symbol(id),
+                None => Type::Unknown,
+            };
+            self.check_initializer(initializer, &ty);
+        }
+    }
+
+    /// Structure and array initializers take their types from the initialized variable.
+    fn check_initializer(&mut self, initializer: &Expression, ty: &Type) {
+        match (initializer, ty.dereferenced()) {
+            (Expression::Struct(members, _), Type::Struct(_) | Type::FunctionBlock(_)) => {
+                for (name, value) in members {
+                    let member = match self.env.binding(name.span) {
+                        Some(id) => self.env.type_of_symbol(id),
+                        None => Type::Unknown,
+                    };
+                    self.check_initializer(value, &member);
+                }
+            }
+            (Expression::Array(items, _), Type::Array { element, .. }) => {
+                for item in items {
+                    self.check_initializer(item, element);
+                }
+            }
+            (Expression::Struct(_, span) | Expression::Array(_, span), ty) if !ty.is_unknown() => {
+                let message = format!(
+                    "A value of type {} cannot be initialized like this.",
+                    self.show(ty)
+                );
+                self.error(*span, message);
+            }
+            (Expression::Struct(..) | Expression::Array(..), _) => {}
+            _ => {
+                let value = self.check_expression(initializer);
+                self.check_assignable(initializer, &value, ty);
+            }
+        }
+    }
+
+    fn check_statements(&mut self, statements: &Statements) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Empty(_)
+            | Statement::Return(_)
+            | Statement::Exit(_)
+            | Statement::Continue(_) => {}
+            Statement::Expression(expression) => {
+                self.check_expression(expression);
+            }
+            Statement::Assignment(assignment) => {
+                let target = self.check_expression(&assignment.target);
+                let value = self.check_expression(&assignment.value);
+                if self.check_target(&assignment.target) {
+                    self.check_assignable(&assignment.value, &value, &target);
+                }
+            }
+            Statement::If(condition) => {
+                for branch in std::iter::once(&condition.branch).chain(&condition.alt_branches) {
+                    self.check_condition(&branch.condition);
+                    self.check_statements(&branch.statements);
+                }
+                if let Some(fallback) = &condition.fallback {
+                    self.check_statements(fallback);
+                }
+            }
+            Statement::Case(case) => {
+                let selector = self.check_expression(&case.selector);
+                let valid = selector.is_unknown()
+                    || matches!(
+                        selector.dereferenced(),
+                        Type::Enum(_) | Type::IntegerLiteral
+                    )
+                    || Generic::AnyInt.contains(&selector)
+                    || Generic::AnyBit.contains(&selector);
+                if !valid {
+                    let message = format!(
+                        "CASE selector must be an integer, bit string or enum, found {}.",
+                        self.show(&selector)
+                    );
+                    self.error(case.selector.span(), message);
+                }
+
+                // The values of earlier labels, as ranges
+                let mut covered = Vec::<(ConstValue, ConstValue, Span)>::new();
+                for branch in &case.branches {
+                    for label in &branch.labels {
+                        let (lower, upper, span) = match label {
+                            CaseLabel::Value(value) => {
+                                let lower = self.check_case_label(value, &selector);
+                                (lower.clone(), lower, value.span())
+                            }
+                            CaseLabel::Range(range) => (
+                                self.check_case_label(&range.lower, &selector),
+                                self.check_case_label(&range.upper, &selector),
+                                range.lower.span().to(range.upper.span()),
+                            ),
+                        };
+                        let (Some(lower), Some(upper)) = (lower, upper) else {
+                            continue;
+                        };
+                        if let Some((.., first)) = covered
+                            .iter()
+                            .find(|(l, u, _)| overlaps((l, u), (&lower, &upper)))
+                        {
+                            let message = match lower == upper {
+                                true => format!(
+                                    "The CASE value {} is already covered by another label.",
+                                    self.show_value(&lower)
+                                ),
+                                false => format!(
+                                    "The CASE values {}..{} are already covered by another label.",
+                                    self.show_value(&lower),
+                                    self.show_value(&upper)
+                                ),
+                            };
+                            self.diagnostics.push(
+                                Diagnostic::error(span, message)
+                                    .with_note(*first, "Covered by this label."),
+                            );
+                        }
+                        covered.push((lower, upper, span));
+                    }
+                    self.check_statements(&branch.statements);
+                }
+                if let Some(fallback) = &case.fallback {
+                    self.check_statements(fallback);
+                }
+            }
+            Statement::For(for_loop) => {
+                let variable = match self.env.binding(for_loop.variable.span) {
+                    Some(id) => self.env.type_of_symbol(id),
+                    None => Type::Unknown,
+                };
+                if !Generic::AnyInt.contains(&variable) {
+                    let message = format!(
+                        "FOR loop variable must be an integer, found {}.",
+                        self.show(&variable)
+                    );
+                    self.error(for_loop.variable.span, message);
+                }
+
+                for bound in [
+                    Some(&for_loop.start),
+                    Some(&for_loop.end),
+                    for_loop.step.as_ref(),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    let ty = self.check_expression(bound);
+                    self.check_assignable(bound, &ty, &variable);
+                }
+                self.check_statements(&for_loop.statements);
+            }
+            Statement::While(while_loop) => {
+                self.check_condition(&while_loop.condition);
+                self.check_statements(&while_loop.statements);
+            }
+            Statement::Repeat(repeat_loop) => {
+                self.check_statements(&repeat_loop.statements);
+                self.check_condition(&repeat_loop.condition);
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        let ty = self.check_expression(condition);
+        if !ty.is_unknown() && !ty.dereferenced().is(Elementary::Bool) {
+            let message = format!("Condition must be of type BOOL, found {}.", self.show(&ty));
+            self.error(condition.span(), message);
+        }
+    }
+
+    /// Checks a CASE label and returns its value.
+    fn check_case_label(&mut self, label: &Expression, selector: &Type) -> Option<ConstValue> {
+        let value = self.constant(label, "CASE label");
+        let ty = self.check_expression(label);
+        if !is_assignable(&ty, selector) {
+            let message = format!(
+                "CASE label of type {} does not match the selector of type {}.",
+                self.show(&ty),
+                self.show(selector)
+            );
+            self.error(label.span(), message);
+        }
+        value
+    }
+
+    /// A constant as it is written in the source.
+    fn show_value(&self, value: &ConstValue) -> String {
+        match value {
+            ConstValue::Enum(id) => self.env.table.symbol(*id).name.clone(),
+            ConstValue::Int(n) => n.to_string(),
+            value => format!("{value:?}"),
+        }
+    }
+
+    /// Reports values which cannot be stored in a location of the given type.
+    fn check_assignable(&mut self, value: &Expression, ty: &Type, target: &Type) {
+        if !is_assignable(ty, target) {
+            let message = format!(
+                "A value of type {} cannot be assigned to {}.",
+                self.show(ty),
+                self.show(target)
+            );
+            self.error(value.span(), message);
+            return;
+        }
+
+        self.check_constant_range(value, target);
+    }
+
+    /// Values known at compile time must fit into the range of their target.
+    fn check_constant_range(&mut self, value: &Expression, target: &Type) {
+        let n = match consteval::evaluate(&self.env, value) {
+            Ok(ConstValue::Int(n)) => n,
+            Err(EvalError::Failed(diagnostic)) => {
+                self.diagnostics.push(diagnostic);
+                return;
+            }
+            _ => return,
+        };
+        let range = match target.dereferenced() {
+            Type::Subrange { lower, upper, .. } => lower.zip(*upper),
+            t => t.elementary().and_then(|e| e.int_range()),
+        };
+
+        if let Some((lower, upper)) = range
+            && (n < lower || n > upper)
+        {
+            let message = format!(
+                "{n} is out of the range {lower}..{upper} of {}.",
+                self.show(target)
+            );
+            self.error(value.span(), message);
+        }
+    }
+
+    /// Evaluates an expression which has to be known at compile time.
+    fn constant(&mut self, expression: &Expression, what: &str) -> Option<ConstValue> {
+        match consteval::evaluate(&self.env, expression) {
+            Ok(value) => Some(value),
+            Err(EvalError::NotConstant(span)) => {
+                self.error(span, format!("{what} must be a constant expression."));
+                None
+            }
+            Err(EvalError::Failed(diagnostic)) => {
+                self.diagnostics.push(diagnostic);
+                None
+            }
+            Err(EvalError::Dependency) => None,
+        }
+    }
+
+    /// Array bounds, string lengths and subranges are fixed at compile time.
+    fn check_data_type(&mut self, data_type: &DataType) {
+        match data_type {
+            DataType::Named(_) => {}
+            DataType::Enum { values, .. } => {
+                for value in values.iter().filter_map(|v| v.value.as_ref()) {
+                    if self
+                        .constant(value, "Enum value")
+                        .is_some_and(|v| v.as_int().is_none())
+                    {
+                        self.error(value.span(), "Enum value must be an integer.".to_string());
+                    }
+                }
+            }
+            DataType::String { length, .. } => {
+                if let Some(length) = length
+                    && let Some(value) = self.constant(length, "String length")
+                    && value.as_int().is_none_or(|l| l <= 0)
+                {
+                    let message = "String length must be a positive integer.".to_string();
+                    self.error(length.span(), message);
+                }
+            }
+            DataType::Array {
+                ranges, element, ..
+            } => {
+                for range in ranges {
+                    self.check_range(&range.lower, &range.upper, "Array bound");
+                }
+                self.check_data_type(element);
+            }
+            DataType::Pointer(target, _) | DataType::Reference(target, _) => {
+                self.check_data_type(target)
+            }
+            DataType::Subrange { range, .. } => {
+                self.check_range(&range.lower, &range.upper, "Subrange bound")
+            }
+            DataType::Struct(members, _) | DataType::Union(members, _) => {
+                for member in members {
+                    self.check_data_type(&member.data_type);
+                    if let Some(initializer) = &member.initializer {
+                        let ty = self.env.lower(&member.data_type);
+                        self.check_initializer(initializer, &ty);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_range(&mut self, lower: &Expression, upper: &Expression, what: &str) {
+        let l = self.constant(lower, what);
+        let u = self.constant(upper, what);
+        for (bound, value) in [(lower, &l), (upper, &u)] {
+            if value.as_ref().is_some_and(|v| v.as_int().is_none()) {
+                self.error(bound.span(), format!("{what} must be an integer."));
+            }
+        }
+
+        if let (Some(l), Some(u)) = (
+            l.as_ref().and_then(ConstValue::as_int),
+            u.as_ref().and_then(ConstValue::as_int),
+        ) && l > u
+        {
+            let message = format!("The lower bound {l} is greater than the upper bound {u}.");
+            self.error(lower.span().to(upper.span()), message);
+        }
+    }
+
+    /// Only variables may be assigned to. Returns whether the target is valid.
+    fn check_target(&mut self, target: &Expression) -> bool {
+        let name = match target {
+            Expression::Identifier(name) => name,
+            Expression::Member(member) => &member.member,
+            Expression::Index(_) | Expression::Deref(..) => return true,
+            _ => {
+                self.error(
+                    target.span(),
+                    "Only variables can be assigned to.".to_string(),
+                );
+                return false;
+            }
+        };
+
+        let Some(id) = self.env.binding(name.span) else {
+            return false;
+        };
+        match self.env.table.symbol(id).kind {
+            SymbolKind::Variable { constant: true, .. } => {
+                let message = format!("'{}' is a constant and cannot be assigned to.", name.name);
+                self.error(target.span(), message);
+                false
+            }
+            SymbolKind::Variable { .. } | SymbolKind::Function | SymbolKind::Method => true,
+            _ => {
+                let message = format!(
+                    "'{}' is not a variable and cannot be assigned to.",
+                    name.name
+                );
+                self.error(target.span(), message);
+                false
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Type {
+        match expression {
+            Expression::Literal(literal, _) => match literal {
+                LiteralExpression::Number(NumberValue::Int(_)) => Type::IntegerLiteral,
+                LiteralExpression::Number(NumberValue::Float(_)) => Type::RealLiteral,
+                LiteralExpression::True | LiteralExpression::False => BOOL,
+                LiteralExpression::String(_, wide) => Type::String {
+                    wide: *wide,
+                    length: None,
+                },
+                LiteralExpression::Time(_) => Type::Elementary(Elementary::Time),
+                LiteralExpression::Date(d) => Type::Elementary(Elementary::of_date(d.kind)),
+            },
+            Expression::Identifier(name) => match self.env.binding(name.span) {
+                Some(id) => self.env.type_of_symbol(id),
+                None => Type::Unknown,
+            },
+            Expression::Prefix(prefix) => {
+                let operand = self.check_expression(&prefix.operand);
+                let valid = match prefix.op {
+                    PrefixOperator::Negation => {
+                        matches!(operand, Type::IntegerLiteral | Type::RealLiteral)
+                            || Generic::AnySigned.contains(&operand)
+                            || Generic::AnyReal.contains(&operand)
+                            || Generic::AnyDuration.contains(&operand)
+                    }
+                    PrefixOperator::Not => Generic::AnyBit.contains(&operand),
+                };
+                if valid {
+                    return operand.dereferenced().clone();
+                }
+
+                let op = match prefix.op {
+                    PrefixOperator::Negation => "-",
+                    PrefixOperator::Not => "NOT",
+                };
+                let message = format!(
+                    "Operator '{op}' cannot be applied to a value of type {}.",
+                    self.show(&operand)
+                );
+                self.error(expression.span(), message);
+                Type::Unknown
+            }
+            Expression::Infix(infix) => self.check_infix(infix),
+            Expression::Member(member) => {
+                self.check_expression(&member.target);
+                match self.env.binding(member.member.span) {
+                    Some(id) => self.env.type_of_symbol(id),
+                    None => Type::Unknown,
+                }
+            }
+            Expression::Index(index) => {
+                let target = self.check_expression(&index.target);
+                for i in &index.indices {
+                    let ty = self.check_expression(i);
+                    if !Generic::AnyInt.contains(&ty) {
+                        let message =
+                            format!("Index must be an integer, found {}.", self.show(&ty));
+                        self.error(i.span(), message);
+                    }
+                }
+
+                match target.dereferenced() {
+                    Type::Array {
+                        dimensions,
+                        element,
+                    } => {
+                        if dimensions.len() != index.indices.len() {
+                            let message = format!(
+                                "{} expects {} indices, found {}.",
+                                self.show(&target),
+                                dimensions.len(),
+                                index.indices.len()
+                            );
+                            self.error(expression.span(), message);
+                        }
+                        for (i, dimension) in index.indices.iter().zip(dimensions) {
+                            if let (Some(n), Some((lower, upper))) =
+                                (self.env.integer_value(i), dimension)
+                                && (n < *lower || n > *upper)
+                            {
+                                let message = format!(
+                                    "Index {n} is out of the bounds {lower}..{upper} of {}.",
+                                    self.show(&target)
+                                );
+                                self.error(i.span(), message);
+                            }
+                        }
+                        element.as_ref().clone()
+                    }
+                    Type::Unknown => Type::Unknown,
+                    t => {
+                        let message =
+                            format!("A value of type {} cannot be indexed.", self.show(t));
+                        self.error(expression.span(), message);
+                        Type::Unknown
+                    }
+                }
+            }
+            Expression::Deref(target, _) => {
+                let ty = self.check_expression(target);
+                match ty.dereferenced() {
+                    Type::Pointer(t) => t.as_ref().clone(),
+                    Type::Unknown => Type::Unknown,
+                    t => {
+                        let message =
+                            format!("A value of type {} cannot be dereferenced.", self.show(t));
+                        self.error(expression.span(), message);
+                        Type::Unknown
+                    }
+                }
+            }
+            Expression::Call(call) => self.check_call(call),
+            Expression::TypedLiteral(literal) => {
+                let type_name = &literal.type_name;
+                if type_name.parts.len() == 1 {
+                    let ty = self.env.lower(&DataType::Named(type_name.clone()));
+                    if ty.elementary().is_some() || ty.is_string() {
+                        let value = self.check_expression(&literal.value);
+                        self.check_assignable(&literal.value, &value, &ty);
+                        return ty;
+                    }
+                }
+
+                match self.env.binding(type_name.last().span) {
+                    Some(id) => self.env.type_of_symbol(id),
+                    None => Type::Unknown,
+                }
+            }
+            Expression::Array(_, span) | Expression::Struct(_, span) => {
+                self.error(
+                    *span,
+                    "Structure and array initializers are only allowed in declarations."
+                        .to_string(),
+                );
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_infix(&mut self, infix: &InfixExpression) -> Type {
+        let left = self.check_expression(&infix.left);
+        let right = self.check_expression(&infix.right);
+        if left.is_unknown() || right.is_unknown() {
+            return match infix.op.is_comparison() {
+                true => BOOL,
+                false => Type::Unknown,
+            };
+        }
+
+        let result = infix_type(infix.op, &left, &right);
+        result.unwrap_or_else(|| {
+            let message = format!(
+                "Operator '{}' cannot be applied to values of type {} and {}.",
+                operator_text(infix.op),
+                self.show(&left),
+                self.show(&right)
+            );
+            self.error(infix.left.span().to(infix.right.span()), message);
+            Type::Unknown
+        })
+    }
+
+    fn check_call(&mut self, call: &CallExpression) -> Type {
+        let callee = match call.callee.as_ref() {
+            Expression::Identifier(name) if self.env.binding(name.span).is_none() => {
+                match library::lookup(&name.name) {
+                    Some(function) => return self.check_standard_call(function, call),
+                    None => None,
+                }
+            }
+            Expression::Identifier(name) => self.env.binding(name.span),
+            Expression::Member(member) => {
+                self.check_expression(&member.target);
+                self.env.binding(member.member.span)
+            }
+            other => {
+                self.check_expression(other);
+                None
+            }
+        };
+
+        let Some(callee) = callee else {
+            // Unresolved callees are already reported, only look into the arguments
+            for argument in &call.arguments {
+                self.check_expression(argument.value());
+            }
+            return Type::Unknown;
+        };
+
+        let symbol = self.env.table.symbol(callee);
+        let (pou, result) = match symbol.kind {
+            SymbolKind::Function | SymbolKind::Method => {
+                (Some(callee), self.env.type_of_symbol(callee))
+            }
+            SymbolKind::Program | SymbolKind::Action => (Some(callee), Type::Void),
+            SymbolKind::Variable { .. } => match self.env.type_of_symbol(callee).dereferenced() {
+                Type::FunctionBlock(fb) => (Some(*fb), Type::Void),
+                Type::Unknown => (None, Type::Unknown),
+                t => {
+                    let message = format!(
+                        "'{}' is of type {} and cannot be called.",
+                        symbol.name,
+                        self.show(t)
+                    );
+                    self.error(call.callee.span(), message);
+                    (None, Type::Unknown)
+                }
+            },
+            _ => {
+                let message = format!("'{}' cannot be called.", symbol.name);
+                self.error(call.callee.span(), message);
+                (None, Type::Unknown)
+            }
+        };
+
+        match pou {
+            Some(pou) => self.check_arguments(pou, &call.arguments),
+            None => {
+                for argument in &call.arguments {
+                    self.check_expression(argument.value());
+                }
+            }
+        }
+        result
+    }
+
+    fn check_standard_call(&mut self, function: Function, call: &CallExpression) -> Type {
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|a| self.check_expression(a.value()))
+            .collect::<Vec<_>>();
+        if let Some(Argument::Named(name, _) | Argument::Output(name, _)) = call
+            .arguments
+            .iter()
+            .find(|a| !matches!(a, Argument::Positional(_)))
+        {
+            let message = format!(
+                "Standard function '{}' takes positional arguments only.",
+                function.name()
+            );
+            self.error(name.span, message);
+            return Type::Unknown;
+        }
+
+        match function.result_type(&arguments) {
+            Ok(result) => result,
+            Err(error) => {
+                let (span, message) = match error {
+                    CallError::Count(expected) => (
+                        call.callee.span(),
+                        format!(
+                            "'{}' takes {expected} argument(s), but {} are given.",
+                            function.name(),
+                            arguments.len()
+                        ),
+                    ),
+                    CallError::Argument { index, expected } => (
+                        call.arguments[index].value().span(),
+                        format!(
+                            "Argument {} of '{}' must be {expected}, found {}.",
+                            index + 1,
+                            function.name(),
+                            self.show(&arguments[index])
+                        ),
+                    ),
+                    CallError::Incompatible => (
+                        call.callee.span(),
+                        format!(
+                            "The arguments of '{}' have no common type: {}.",
+                            function.name(),
+                            arguments
+                                .iter()
+                                .map(|a| self.show(a))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    ),
+                };
+                self.error(span, message);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_arguments(&mut self, pou: usize, arguments: &[Argument]) {
+        let table = self.env.table;
+        let symbol = table.symbol(pou);
+        let parameters = symbol
+            .members
+            .map(|m| table.symbols_in(m))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| {
+                matches!(
+                    table.symbol(*id).kind,
+                    SymbolKind::Variable {
+                        kind: VariableKind::Input | VariableKind::InOut,
+                        ..
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut positional = 0;
+        for argument in arguments {
+            let value = argument.value();
+            let ty = self.check_expression(value);
+            match argument {
+                Argument::Positional(_) => {
+                    match parameters.get(positional) {
+                        Some(p) => {
+                            let parameter = self.env.type_of_symbol(*p);
+                            self.check_argument(value, &ty, *p, &parameter);
+                        }
+                        None if positional == parameters.len() => {
+                            let message = format!(
+                                "'{}' takes {} argument(s), but more are given.",
+                                symbol.name,
+                                parameters.len()
+                            );
+                            self.error(value.span(), message);
+                        }
+                        None => {}
+                    }
+                    positional += 1;
+                }
+                Argument::Named(name, _) => {
+                    let Some(p) = self.env.binding(name.span) else {
+                        continue;
+                    };
+                    if let SymbolKind::Variable {
+                        kind: VariableKind::Output,
+                        ..
+                    } = table.symbol(p).kind
+                    {
+                        let message = format!(
+                            "'{}' is an output, connect it like {} => target.",
+                            name.name, name.name
+                        );
+                        self.error(name.span, message);
+                        continue;
+                    }
+                    let parameter = self.env.type_of_symbol(p);
+                    self.check_argument(value, &ty, p, &parameter);
+                }
+                Argument::Output(name, _) => {
+                    let Some(p) = self.env.binding(name.span) else {
+                        continue;
+                    };
+                    if !matches!(
+                        table.symbol(p).kind,
+                        SymbolKind::Variable {
+                            kind: VariableKind::Output,
+                            ..
+                        }
+                    ) {
+                        let message = format!(
+                            "'{}' is not an output, connect it like {} := value.",
+                            name.name, name.name
+                        );
+                        self.error(name.span, message);
+                        continue;
+                    }
+                    let parameter = self.env.type_of_symbol(p);
+                    if self.check_target(value) && !is_assignable(&parameter, &ty) {
+                        let message = format!(
+                            "Output '{}' of type {} cannot be assigned to {}.",
+                            name.name,
+                            self.show(&parameter),
+                            self.show(&ty)
+                        );
+                        self.error(value.span(), message);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_argument(&mut self, value: &Expression, ty: &Type, parameter: usize, expected: &Type) {
+        if !is_assignable(ty, expected) {
+            let message = format!(
+                "Argument of type {} does not match parameter '{}' of type {}.",
+                self.show(ty),
+                self.env.table.symbol(parameter).name,
+                self.show(expected)
+            );
+            self.error(value.span(), message);
+            return;
+        }
+
+        self.check_constant_range(value, expected);
+    }
+}
+
+/// Result of `+ - * /`, including the arithmetic on durations and dates.
+fn operator_text(op: InfixOperator) -> &'static str {
+    match op {
+        InfixOperator::Addition => "+",
+        InfixOperator::Subtraction => "-",
+        InfixOperator::Multiplication => "*",
+        InfixOperator::Division => "/",
+        InfixOperator::Modulo => "MOD",
+        InfixOperator::Power => "**",
+        InfixOperator::Equals => "=",
+        InfixOperator::NotEquals => "<>",
+        InfixOperator::GreaterThan => ">",
+        InfixOperator::GreaterThanOrEquals => ">=",
+        InfixOperator::LessThan => "<",
+        InfixOperator::LessThanOrEquals => "<=",
+        InfixOperator::And => "AND",
+        InfixOperator::Or => "OR",
+        InfixOperator::Xor => "XOR",
+    }
+}
+
+/// Whether two ranges of CASE labels share a value.
+fn overlaps(a: (&ConstValue, &ConstValue), b: (&ConstValue, &ConstValue)) -> bool {
+    match (a, b) {
+        (
+            (ConstValue::Int(l1), ConstValue::Int(u1)),
+            (ConstValue::Int(l2), ConstValue::Int(u2)),
+        ) => l1 <= u2 && l2 <= u1,
+        ((l1, u1), (l2, u2)) => l1 == l2 || u1 == u2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        semantic::resolver::resolve,
+    };
+
+    fn check_src(src: &str) -> Vec<String> {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, diagnostics) = resolve(&ast);
+        assert_eq!(diagnostics, Vec::new());
+        check(&ast, &table)
+            .iter()
+            .map(|d| d.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_accepts_widening_and_literals() {
+        let messages = check_src(
+            r#"
+        TYPE
+            Color : (Red, Green);
+            Point : STRUCT x : INT; y : INT := 2; END_STRUCT
+        END_TYPE
+
+        PROGRAM Main
+            VAR
+                s : SINT := -128;
+                i : INT;
+                d : DINT;
+                r : LREAL := 1.5;
+                w : WORD := 16#FFFF;
+                t : TIME := T#1s;
+                c : Color := Color#Red;
+                p : Point := (x := 1);
+                a : ARRAY[0..2] OF INT := [1, 2, 3];
+            END_VAR
+            i := s;
+            d := i + s * 2;
+            r := d;
+            t := t * 2 + T#5ms;
+            w := w AND 16#00FF;
+            IF c = Red AND d > 5 THEN
+                a[1] := p.x;
+            END_IF;
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(messages, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_reports_mismatched_assignments() {
+        let messages = check_src(
+            r#"
+        PROGRAM Main
+            VAR
+                s : SINT := 300;
+                i : INT;
+                u : UINT;
+                r : REAL;
+                b : BOOL;
+            END_VAR
+            VAR CONSTANT limit : INT := 10; END_VAR
+            s := i;
+            u := i;
+            i := r;
+            b := 1;
+            limit := 5;
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "300 is out of the range -128..127 of SINT.",
+                "A value of type INT cannot be assigned to SINT.",
+                "A value of type INT cannot be assigned to UINT.",
+                "A value of type REAL cannot be assigned to INT.",
+                "A value of type integer literal cannot be assigned to BOOL.",
+                "'limit' is a constant and cannot be assigned to.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_comparisons_and_conditions() {
+        let messages = check_src(
+            r#"
+        TYPE Color : (Red, Green); END_TYPE
+
+        PROGRAM Main
+            VAR i : INT; s : STRING; c : Color; END_VAR
+            IF i = s THEN END_IF;
+            WHILE i DO END_WHILE;
+            IF c < Green OR c = Red THEN END_IF;
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "Operator '=' cannot be applied to values of type INT and STRING.",
+                "Condition must be of type BOOL, found INT.",
+                "Operator '<' cannot be applied to values of type Color and Color.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_call_arguments() {
+        let messages = check_src(
+            r#"
+        FUNCTION Scale : REAL
+            VAR_INPUT value : REAL; factor : INT; END_VAR
+            Scale := value * factor;
+        END_FUNCTION
+
+        FUNCTION_BLOCK Counter
+            VAR_INPUT enable : BOOL; END_VAR
+            VAR_OUTPUT count : DINT; END_VAR
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            VAR r : REAL; i : INT; fb : Counter; END_VAR
+            r := Scale(i, 2);
+            r := Scale(TRUE, 2, 3);
+            fb(enable := i, count => i);
+            fb(count := 1);
+            i();
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "Argument of type BOOL does not match parameter 'value' of type REAL.",
+                "'Scale' takes 2 argument(s), but more are given.",
+                "Argument of type INT does not match parameter 'enable' of type BOOL.",
+                "Output 'count' of type DINT cannot be assigned to INT.",
+                "'count' is an output, connect it like count => target.",
+                "'i' is of type INT and cannot be called.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_types_standard_function_calls() {
+        let messages = check_src(
+            r#"
+        PROGRAM Main
+            VAR
+                i : INT;
+                r : REAL;
+                b : BYTE;
+                s : STRING;
+                t : TIME;
+            END_VAR
+            i := MAX(i, 3, 7);
+            r := SQRT(2) + ABS(r);
+            b := ROL(b, i);
+            s := CONCAT(s, 'x', MID(s, 2, 1));
+            i := REAL_TO_INT(r) + LEN(s);
+            t := DINT_TO_TIME(i);
+            i := SQRT(r);
+            r := SEL(i, r, 1.0);
+            i := LIMIT(i, 2);
+            b := SHL(r, 1);
+            i := MAX(i, s);
+            i := INT_TO_DINT(r);
+            i := MUX(idx := 0, 1, 2);
+            ASSERT_EQ(i, 3);
+            ASSERT_TRUE(i);
+            ASSERT_NEAR(r, s, 0.1);
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "A value of type REAL cannot be assigned to INT.",
+                "Argument 1 of 'SEL' must be BOOL, found INT.",
+                "'LIMIT' takes 3 argument(s), but 2 are given.",
+                "Argument 1 of 'SHL' must be ANY_BIT, found REAL.",
+                "The arguments of 'MAX' have no common type: INT, STRING.",
+                "Argument 1 of 'INT_TO_DINT' must be INT, found REAL.",
+                "Standard function 'MUX' takes positional arguments only.",
+                "Argument 1 of 'ASSERT_TRUE' must be BOOL, found INT.",
+                "Argument 2 of 'ASSERT_NEAR' must be ANY_NUM, found STRING.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluates_constant_bounds_and_labels() {
+        let messages = check_src(
+            r#"
+        VAR_GLOBAL CONSTANT
+            MAX_AXES : INT := 4;
+            LIMIT : SINT := 100;
+        END_VAR
+
+        PROGRAM Main
+            VAR
+                axes : ARRAY[0..MAX_AXES - 1] OF INT;
+                name : STRING(MAX_AXES * 8);
+                wrong : ARRAY[MAX_AXES..0] OF INT;
+                percent : INT(0..LIMIT) := LIMIT + 1;
+                i : INT;
+            END_VAR
+            axes[MAX_AXES - 1] := 10 / 0;
+            CASE i OF
+                MAX_AXES: i := 1;
+                i: i := 2;
+            END_CASE;
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "The lower bound 4 is greater than the upper bound 0.",
+                "101 is out of the range 0..100 of INT(0..100).",
+                "Division by zero in constant expression.",
+                "CASE label must be a constant expression.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_folded_labels_and_indexes() {
+        let src = "VAR_GLOBAL CONSTANT MAX_AXES : INT := 4; END_VAR
+PROGRAM Main
+VAR axes : ARRAY[0..MAX_AXES - 1] OF INT; i : INT; END_VAR
+axes[MAX_AXES] := axes[MAX_AXES - 1] + axes[-1];
+CASE i OF
+    1..3: i := 1;
+    MAX_AXES, 5: i := 2;
+    4: i := 3;
+    2 + 4..9, 0..1: i := 4;
+END_CASE;
+END_PROGRAM";
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let diagnostics = check(&ast, &table)
+            .into_iter()
+            .map(|d| {
+                let notes = d.notes.iter().map(|(span, _)| (span.line, span.col));
+                (
+                    d.span.line,
+                    d.span.col,
+                    d.message,
+                    notes.collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let bounds = "of ARRAY[0..3] OF INT.";
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    3,
+                    5,
+                    format!("Index 4 is out of the bounds 0..3 {bounds}"),
+                    vec![]
+                ),
+                (
+                    3,
+                    44,
+                    format!("Index -1 is out of the bounds 0..3 {bounds}"),
+                    vec![]
+                ),
+                (
+                    7,
+                    4,
+                    "The CASE value 4 is already covered by another label.".to_string(),
+                    vec![(6, 4)]
+                ),
+                (
+                    8,
+                    14,
+                    "The CASE values 0..1 are already covered by another label.".to_string(),
+                    vec![(5, 4)]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checks_configurations() {
+        let messages = check_src(
+            r#"
+        PROGRAM Main
+            VAR_INPUT limit : INT; END_VAR
+        END_PROGRAM
+
+        CONFIGURATION Plant
+            VAR_GLOBAL speed : INT := TRUE; END_VAR
+            RESOURCE Cpu ON PLC
+                TASK Fast(INTERVAL := 5, PRIORITY := TRUE);
+                TASK Event(SINGLE := speed, PRIORITY := 2);
+                PROGRAM Main WITH Fast : Main(limit := 'fast');
+            END_RESOURCE
+        END_CONFIGURATION
+        "#,
+        );
+
+        assert_eq!(
+            messages,
+            vec![
+                "A value of type BOOL cannot be assigned to INT.",
+                "Task property INTERVAL must be of type TIME, found integer literal.",
+                "Task property PRIORITY must be an integer, found BOOL.",
+                "Task property SINGLE must be of type BOOL, found INT.",
+                "Argument of type STRING does not match parameter 'limit' of type INT.",
+            ]
+        );
+    }
+}
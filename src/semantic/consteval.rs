@@ -0,0 +1,632 @@
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::{
+        ast::{
+            Argument, Expression, InfixExpression, InfixOperator, LiteralExpression,
+            PrefixOperator, Span,
+        },
+        token::NumberValue,
+    },
+    semantic::{
+        library::{self, Function, Scalar},
+        symbols::{ScopeId, SymbolId, SymbolKind},
+        types::{Elementary, TypeEnv},
+    },
+};
+
+/// Value of an expression known at compile time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstValue {
+    Bool(bool),
+    Int(i128),
+    Real(f64),
+    String(String),
+    /// Duration in nanoseconds
+    Time(i128),
+    /// Nanoseconds since 1970-01-01, or since midnight of times of day
+    Date(i128, Elementary),
+    Enum(SymbolId),
+}
+
+impl ConstValue {
+    pub fn as_int(&self) -> Option<i128> {
+        match self {
+            ConstValue::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_real(&self) -> Option<f64> {
+        match self {
+            ConstValue::Int(n) => Some(*n as f64),
+            ConstValue::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    /// The expression depends on something only known at runtime
+    NotConstant(Span),
+    /// The evaluation itself failed, like on an overflow or a division by zero
+    Failed(Diagnostic),
+    /// A constant the expression refers to failed, which is reported at that constant
+    Dependency,
+}
+
+/// Range of integer results which are not bound to a type.
+const UNTYPED_RANGE: (i128, i128) = (i64::MIN as i128, u64::MAX as i128);
+
+/// Evaluates an expression made of literals, constants, enum values and standard functions.
+pub fn evaluate(env: &TypeEnv, expression: &Expression) -> Result<ConstValue, EvalError> {
+    Evaluator { env }.eval(expression).map(|(value, _)| value)
+}
+
+/// Integer value of an enum value. Values without an explicit value follow their predecessor.
+pub fn enum_value(env: &TypeEnv, id: SymbolId) -> Option<i128> {
+    let table = env.table;
+    let mut next = 0;
+    for member in table.symbols_in(table.symbol(id).scope) {
+        let value = match &table.symbol(member).value {
+            Some(v) => evaluate(env, v).ok()?.as_int()?,
+            None => next,
+        };
+        if member == id {
+            return Some(value);
+        }
+        next = value + 1;
+    }
+
+    None
+}
+
+/// Name of the enum value of an enum with the value `n`, like `Mode#Fast`. The values of inline
+/// enums have no type name.
+pub fn enum_name(env: &TypeEnv, scope: ScopeId, n: i128) -> Option<String> {
+    let table = env.table;
+    let member = table
+        .symbols_in(scope)
+        .into_iter()
+        .find(|id| enum_value(env, *id) == Some(n))?;
+    let name = &table.symbol(member).name;
+    Some(match table.scope(scope).owner {
+        Some(owner) => format!("{}#{name}", table.symbol(owner).name),
+        None => name.clone(),
+    })
+}
+
+/// A value with the elementary type it is bound to, untyped literals have none.
+type Typed = (ConstValue, Option<Elementary>);
+
+struct Evaluator<'e, 'a> {
+    env: &'e TypeEnv<'a>,
+}
+
+impl Evaluator<'_, '_> {
+    fn eval(&self, expression: &Expression) -> Result<Typed, EvalError> {
+        let span = expression.span();
+        match expression {
+            Expression::Literal(literal, _) => Ok(match literal {
+                LiteralExpression::Number(NumberValue::Int(n)) => {
+                    (ConstValue::Int(*n as i128), None)
+                }
+                LiteralExpression::Number(NumberValue::Float(f)) => (ConstValue::Real(*f), None),
+                LiteralExpression::True => (ConstValue::Bool(true), Some(Elementary::Bool)),
+                LiteralExpression::False => (ConstValue::Bool(false), Some(Elementary::Bool)),
+                LiteralExpression::String(s, _) => (ConstValue::String(s.clone()), None),
+                LiteralExpression::Time(t) => (
+                    ConstValue::Time(t.nanoseconds().unwrap_or_default().into()),
+                    Some(Elementary::Time),
+                ),
+                LiteralExpression::Date(d) => {
+                    let ty = Elementary::of_date(d.kind);
+                    let value = d.nanoseconds().unwrap_or_default().into();
+                    (ConstValue::Date(value, ty), Some(ty))
+                }
+            }),
+            Expression::Identifier(name) => self.eval_binding(name.span, span),
+            Expression::Member(member) => self.eval_binding(member.member.span, span),
+            Expression::TypedLiteral(literal) => {
+                let type_name = literal.type_name.last();
+                let elementary = match literal.type_name.parts.len() {
+                    1 => Elementary::from_name(&type_name.name),
+                    _ => None,
+                };
+                match elementary {
+                    Some(e) => {
+                        let (value, _) = self.eval(&literal.value)?;
+                        self.convert(value, e, span)
+                    }
+                    None => match literal.value.as_ref() {
+                        Expression::Identifier(value) => self.eval_binding(value.span, span),
+                        _ => Err(EvalError::NotConstant(span)),
+                    },
+                }
+            }
+            Expression::Prefix(prefix) => {
+                let (value, ty) = self.eval(&prefix.operand)?;
+                match (prefix.op, value) {
+                    (PrefixOperator::Negation, ConstValue::Int(n)) => {
+                        self.checked(n.checked_neg(), ty, span)
+                    }
+                    (PrefixOperator::Negation, ConstValue::Real(r)) => {
+                        Ok((ConstValue::Real(-r), ty))
+                    }
+                    (PrefixOperator::Negation, ConstValue::Time(t)) => {
+                        Ok((ConstValue::Time(-t), ty))
+                    }
+                    (PrefixOperator::Not, ConstValue::Bool(b)) => Ok((ConstValue::Bool(!b), ty)),
+                    (PrefixOperator::Not, ConstValue::Int(n)) => {
+                        let bits = ty.and_then(|t| t.bit_width()).unwrap_or(64);
+                        Ok((ConstValue::Int(!n & ((1i128 << bits) - 1)), ty))
+                    }
+                    _ => Err(EvalError::NotConstant(span)),
+                }
+            }
+            Expression::Infix(infix) => self.eval_infix(infix, span),
+            Expression::Call(call) => self.eval_call(call.callee.as_ref(), &call.arguments, span),
+            Expression::Index(_)
+            | Expression::Deref(..)
+            | Expression::Array(..)
+            | Expression::Struct(..) => Err(EvalError::NotConstant(span)),
+        }
+    }
+
+    fn eval_binding(&self, name: Span, span: Span) -> Result<Typed, EvalError> {
+        let Some(id) = self.env.binding(name) else {
+            return Err(EvalError::Dependency);
+        };
+
+        let symbol = self.env.table.symbol(id);
+        match (symbol.kind, &symbol.value) {
+            (SymbolKind::EnumValue, _) => Ok((ConstValue::Enum(id), None)),
+            (SymbolKind::Variable { constant: true, .. }, Some(value)) => {
+                if !self.env.enter_constant() {
+                    return Err(EvalError::Dependency);
+                }
+                let ty = self.env.type_of_symbol(id).elementary();
+                let result = self.eval(value);
+                self.env.leave_constant();
+
+                match result {
+                    Ok((value, _)) => match ty {
+                        Some(ty) => self
+                            .convert(value, ty, span)
+                            .map_err(|_| EvalError::Dependency),
+                        None => Ok((value, None)),
+                    },
+                    Err(EvalError::NotConstant(_)) => Err(EvalError::NotConstant(span)),
+                    Err(_) => Err(EvalError::Dependency),
+                }
+            }
+            _ => Err(EvalError::NotConstant(span)),
+        }
+    }
+
+    fn eval_infix(&self, infix: &InfixExpression, span: Span) -> Result<Typed, EvalError> {
+        let (left, lt) = self.eval(&infix.left)?;
+        let (right, rt) = self.eval(&infix.right)?;
+        let ty = combine(lt, rt);
+        let op = infix.op;
+
+        if op.is_comparison() {
+            let ordering = match (&left, &right) {
+                (ConstValue::Int(a), ConstValue::Int(b))
+                | (ConstValue::Time(a), ConstValue::Time(b))
+                | (ConstValue::Date(a, _), ConstValue::Date(b, _)) => a.partial_cmp(b),
+                (ConstValue::String(a), ConstValue::String(b)) => a.partial_cmp(b),
+                (ConstValue::Bool(a), ConstValue::Bool(b)) => a.partial_cmp(b),
+                (ConstValue::Enum(a), ConstValue::Enum(b)) => (a == b)
+                    .then_some(std::cmp::Ordering::Equal)
+                    .or(Some(std::cmp::Ordering::Less)),
+                (a, b) => a
+                    .as_real()
+                    .zip(b.as_real())
+                    .and_then(|(a, b)| a.partial_cmp(&b)),
+            };
+            let Some(ordering) = ordering else {
+                return Err(EvalError::NotConstant(span));
+            };
+            let result = match op {
+                InfixOperator::Equals => ordering.is_eq(),
+                InfixOperator::NotEquals => ordering.is_ne(),
+                InfixOperator::GreaterThan => ordering.is_gt(),
+                InfixOperator::GreaterThanOrEquals => ordering.is_ge(),
+                InfixOperator::LessThan => ordering.is_lt(),
+                _ => ordering.is_le(),
+            };
+            return Ok((ConstValue::Bool(result), Some(Elementary::Bool)));
+        }
+
+        match (left, right) {
+            (ConstValue::Bool(a), ConstValue::Bool(b)) if op.is_logical() => {
+                let result = match op {
+                    InfixOperator::And => a && b,
+                    InfixOperator::Or => a || b,
+                    _ => a ^ b,
+                };
+                Ok((ConstValue::Bool(result), ty))
+            }
+            (ConstValue::Int(a), ConstValue::Int(b)) => {
+                let result = match op {
+                    InfixOperator::Addition => a.checked_add(b),
+                    InfixOperator::Subtraction => a.checked_sub(b),
+                    InfixOperator::Multiplication => a.checked_mul(b),
+                    InfixOperator::Division | InfixOperator::Modulo if b == 0 => {
+                        return Err(division_by_zero(span));
+                    }
+                    InfixOperator::Division => a.checked_div(b),
+                    InfixOperator::Modulo => a.checked_rem(b),
+                    InfixOperator::Power => {
+                        return Ok((ConstValue::Real((a as f64).powf(b as f64)), ty));
+                    }
+                    InfixOperator::And => Some(a & b),
+                    InfixOperator::Or => Some(a | b),
+                    InfixOperator::Xor => Some(a ^ b),
+                    _ => None,
+                };
+                self.checked(result, ty, span)
+            }
+            (ConstValue::Time(a), ConstValue::Time(b)) => match op {
+                InfixOperator::Addition => self.checked_time(a.checked_add(b), span),
+                InfixOperator::Subtraction => self.checked_time(a.checked_sub(b), span),
+                _ => Err(EvalError::NotConstant(span)),
+            },
+            (ConstValue::Time(a), ConstValue::Int(b)) => match op {
+                InfixOperator::Multiplication => self.checked_time(a.checked_mul(b), span),
+                InfixOperator::Division if b == 0 => Err(division_by_zero(span)),
+                InfixOperator::Division => self.checked_time(a.checked_div(b), span),
+                _ => Err(EvalError::NotConstant(span)),
+            },
+            (a, b) => {
+                let (Some(a), Some(b)) = (a.as_real(), b.as_real()) else {
+                    return Err(EvalError::NotConstant(span));
+                };
+                let result = match op {
+                    InfixOperator::Addition => a + b,
+                    InfixOperator::Subtraction => a - b,
+                    InfixOperator::Multiplication => a * b,
+                    InfixOperator::Division if b == 0.0 => return Err(division_by_zero(span)),
+                    InfixOperator::Division => a / b,
+                    InfixOperator::Power => a.powf(b),
+                    _ => return Err(EvalError::NotConstant(span)),
+                };
+                Ok((ConstValue::Real(result), ty.filter(|t| t.is_real())))
+            }
+        }
+    }
+
+    /// Standard functions, selected by name as long as nothing user-defined shadows them.
+    fn eval_call(
+        &self,
+        callee: &Expression,
+        arguments: &[Argument],
+        span: Span,
+    ) -> Result<Typed, EvalError> {
+        let Expression::Identifier(name) = callee else {
+            return Err(EvalError::NotConstant(span));
+        };
+        if self.env.binding(name.span).is_some() {
+            return Err(EvalError::NotConstant(span));
+        }
+
+        let mut args = Vec::new();
+        for argument in arguments {
+            match argument {
+                Argument::Positional(value) => args.push(self.eval(value)?),
+                _ => return Err(EvalError::NotConstant(span)),
+            }
+        }
+
+        let Some(function) = library::lookup(&name.name) else {
+            return Err(EvalError::NotConstant(span));
+        };
+        if let Function::Convert(_, to) = function
+            && let [(value, _)] = &args[..]
+        {
+            return match to {
+                Scalar::Elementary(to) => self.convert(value.clone(), to, span),
+                Scalar::String { .. } => match value {
+                    ConstValue::String(_) => Ok((value.clone(), None)),
+                    _ => Err(EvalError::NotConstant(span)),
+                },
+            };
+        }
+
+        let ty = args.iter().fold(None, |ty, (_, t)| combine(ty, *t));
+        let real = |value: f64| match value.is_finite() {
+            true => Ok((ConstValue::Real(value), ty.filter(|t| t.is_real()))),
+            false => Err(EvalError::NotConstant(span)),
+        };
+        use Function::*;
+        match (function, &args[..]) {
+            (Abs, [(ConstValue::Int(n), t)]) => self.checked(n.checked_abs(), *t, span),
+            (Abs, [(ConstValue::Real(r), t)]) => Ok((ConstValue::Real(r.abs()), *t)),
+            (Sqrt | Ln | Log | Exp | Sin | Cos | Tan | Asin | Acos | Atan, [(v, _)]) => {
+                match v.as_real() {
+                    Some(r) => real(library::real_function(function, r)),
+                    None => Err(EvalError::NotConstant(span)),
+                }
+            }
+            (Expt, [(a, _), (b, _)]) => match (a.as_real(), b.as_real()) {
+                (Some(a), Some(b)) => real(a.powf(b)),
+                _ => Err(EvalError::NotConstant(span)),
+            },
+            (Min | Max, [first, rest @ ..]) => {
+                let mut best = first.0.clone();
+                for (value, _) in rest {
+                    let better = match (value.as_real(), best.as_real()) {
+                        (Some(v), Some(b)) if function == Min => v < b,
+                        (Some(v), Some(b)) => v > b,
+                        _ => return Err(EvalError::NotConstant(span)),
+                    };
+                    if better {
+                        best = value.clone();
+                    }
+                }
+                Ok((best, ty))
+            }
+            // LIMIT(MN, IN, MX) is MIN(MAX(MN, IN), MX), also when MN exceeds MX
+            (Limit, [(low, _), (value, _), (high, _)]) => {
+                match (low.as_real(), value.as_real(), high.as_real()) {
+                    (Some(l), Some(v), Some(h)) => {
+                        let (bounded, b) = if v > l { (value, v) } else { (low, l) };
+                        Ok((if h < b { high } else { bounded }.clone(), ty))
+                    }
+                    _ => Err(EvalError::NotConstant(span)),
+                }
+            }
+            (Sel, [(ConstValue::Bool(g), _), a, b]) => Ok(if *g { b.clone() } else { a.clone() }),
+            (Mux, [(ConstValue::Int(k), _), inputs @ ..]) => match usize::try_from(*k) {
+                Ok(k) if k < inputs.len() => Ok(inputs[k].clone()),
+                _ => Err(EvalError::NotConstant(span)),
+            },
+            (Shl | Shr | Rol | Ror, [(ConstValue::Int(n), t), (ConstValue::Int(by), _)]) => {
+                let width = t.and_then(|t| t.bit_width()).unwrap_or(64);
+                let shifted = library::shift(function, *n, *by, width);
+                Ok((ConstValue::Int(shifted), *t))
+            }
+            (Trunc, [(ConstValue::Real(r), _)]) => {
+                self.checked(Some(r.trunc() as i128), Some(Elementary::Dint), span)
+            }
+            (Len, [(ConstValue::String(s), _)]) => Ok((
+                ConstValue::Int(s.chars().count() as i128),
+                Some(Elementary::Int),
+            )),
+            (Concat, _) => {
+                let mut result = String::new();
+                for (value, _) in &args {
+                    match value {
+                        ConstValue::String(s) => result.push_str(s),
+                        _ => return Err(EvalError::NotConstant(span)),
+                    }
+                }
+                Ok((ConstValue::String(result), None))
+            }
+            (Left | Right, [(ConstValue::String(a), _), (ConstValue::Int(l), _)]) => {
+                let edited = library::edit_string(function, a, "", *l, 0);
+                Ok((ConstValue::String(edited), None))
+            }
+            (Mid | Delete, [(ConstValue::String(a), _), (l, _), (p, _)]) => {
+                let (Some(l), Some(p)) = (l.as_int(), p.as_int()) else {
+                    return Err(EvalError::NotConstant(span));
+                };
+                let edited = library::edit_string(function, a, "", l, p);
+                Ok((ConstValue::String(edited), None))
+            }
+            (
+                Insert,
+                [
+                    (ConstValue::String(a), _),
+                    (ConstValue::String(b), _),
+                    (p, _),
+                ],
+            ) => {
+                let Some(p) = p.as_int() else {
+                    return Err(EvalError::NotConstant(span));
+                };
+                let edited = library::edit_string(function, a, b, 0, p);
+                Ok((ConstValue::String(edited), None))
+            }
+            (
+                Replace,
+                [
+                    (ConstValue::String(a), _),
+                    (ConstValue::String(b), _),
+                    (l, _),
+                    (p, _),
+                ],
+            ) => {
+                let (Some(l), Some(p)) = (l.as_int(), p.as_int()) else {
+                    return Err(EvalError::NotConstant(span));
+                };
+                let edited = library::edit_string(function, a, b, l, p);
+                Ok((ConstValue::String(edited), None))
+            }
+            (Find, [(ConstValue::String(a), _), (ConstValue::String(b), _)]) => {
+                Ok((ConstValue::Int(library::find(a, b)), Some(Elementary::Int)))
+            }
+            _ => Err(EvalError::NotConstant(span)),
+        }
+    }
+
+    /// Converts a value into an elementary type, like the *_TO_* functions and typed literals do.
+    fn convert(&self, value: ConstValue, to: Elementary, span: Span) -> Result<Typed, EvalError> {
+        let target = Some(to);
+        match value {
+            ConstValue::Int(n) if to == Elementary::Bool => Ok((ConstValue::Bool(n != 0), target)),
+            ConstValue::Int(n) if to.is_real() => Ok((ConstValue::Real(n as f64), target)),
+            ConstValue::Int(n) if to.int_range().is_some() => self.checked(Some(n), target, span),
+            ConstValue::Int(n) if to.is_duration() => self
+                .checked_time(n.checked_mul(library::MILLISECOND), span)
+                .map(|(time, _)| (time, target)),
+            ConstValue::Real(r) if to.is_real() => Ok((ConstValue::Real(r), target)),
+            // Conversions from REAL round to the nearest integer
+            ConstValue::Real(r) if to.int_range().is_some() && r.is_finite() => {
+                self.checked(Some(r.round_ties_even() as i128), target, span)
+            }
+            ConstValue::Bool(b) if to == Elementary::Bool => Ok((ConstValue::Bool(b), target)),
+            ConstValue::Bool(b) if to.int_range().is_some() => {
+                Ok((ConstValue::Int(b as i128), target))
+            }
+            ConstValue::Bool(b) if to.is_real() => Ok((ConstValue::Real(b as u8 as f64), target)),
+            ConstValue::Time(t) if to.is_duration() => Ok((ConstValue::Time(t), target)),
+            ConstValue::Time(t) if to.int_range().is_some() => {
+                self.checked(Some(t / library::MILLISECOND), target, span)
+            }
+            _ => Err(EvalError::NotConstant(span)),
+        }
+    }
+
+    /// Integer results must fit into their type, or into 64 bit when they are untyped.
+    fn checked(
+        &self,
+        value: Option<i128>,
+        ty: Option<Elementary>,
+        span: Span,
+    ) -> Result<Typed, EvalError> {
+        let (lower, upper) = ty.and_then(|t| t.int_range()).unwrap_or(UNTYPED_RANGE);
+        match value {
+            Some(n) if lower <= n && n <= upper => Ok((ConstValue::Int(n), ty)),
+            _ => {
+                let type_name = ty.map_or("any integer type", |t| t.name());
+                let message = match value {
+                    Some(n) => format!(
+                        "Overflow in constant expression: {n} does not fit into {type_name}."
+                    ),
+                    None => format!(
+                        "Overflow in constant expression: the result does not fit into {type_name}."
+                    ),
+                };
+                Err(EvalError::Failed(Diagnostic::error(span, message)))
+            }
+        }
+    }
+
+    fn checked_time(&self, value: Option<i128>, span: Span) -> Result<Typed, EvalError> {
+        match value.filter(|t| i64::try_from(*t).is_ok()) {
+            Some(t) => Ok((ConstValue::Time(t), Some(Elementary::Time))),
+            None => Err(EvalError::Failed(Diagnostic::error(
+                span,
+                "Overflow in constant expression: the duration does not fit into TIME.",
+            ))),
+        }
+    }
+}
+
+/// Type of an operation on two typed values, the wider type wins.
+fn combine(a: Option<Elementary>, b: Option<Elementary>) -> Option<Elementary> {
+    match (a, b) {
+        (Some(a), Some(b)) if a.widens_to(b) => Some(b),
+        (Some(a), _) => Some(a),
+        (None, b) => b,
+    }
+}
+
+fn division_by_zero(span: Span) -> EvalError {
+    EvalError::Failed(Diagnostic::error(
+        span,
+        "Division by zero in constant expression.",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::{lexer::Lexer, parser::parse},
+        semantic::{resolver::resolve, symbols::SymbolTable},
+    };
+
+    fn table_of(src: &str) -> SymbolTable {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, diagnostics) = resolve(&ast);
+        assert_eq!(diagnostics, Vec::new());
+        table
+    }
+
+    /// Evaluates the initial value of the variable with the given name.
+    fn value_of(table: &SymbolTable, name: &str) -> Result<ConstValue, EvalError> {
+        let env = TypeEnv::new(table);
+        let symbol = table.symbols.iter().find(|s| s.name == name).unwrap();
+        evaluate(&env, symbol.value.as_ref().unwrap())
+    }
+
+    #[test]
+    fn test_folds_literals_constants_and_functions() {
+        let table = table_of(
+            r#"
+        VAR_GLOBAL CONSTANT
+            MAX_AXES : INT := 4;
+            LAST : INT := MAX_AXES - 1;
+            HALF : LREAL := MAX_AXES / 8.0;
+            MASK : WORD := SHL(WORD#16#0F, 4) OR 16#1;
+            CYCLE : TIME := T#1s / 4 + T#5ms;
+            TICK : TIME := T#1ms500us + T#250ns;
+            TICKS : DINT := TIME_TO_DINT(T#2s) + 1;
+            CLAMPED : INT := LIMIT(0, MAX(LAST, 10), 7);
+            ROUNDED : INT := REAL_TO_INT(2.5) + TRUNC(-1.7);
+            BIG : BOOL := LAST >= 3 AND NOT FALSE;
+        END_VAR
+        "#,
+        );
+
+        assert_eq!(value_of(&table, "LAST"), Ok(ConstValue::Int(3)));
+        assert_eq!(value_of(&table, "HALF"), Ok(ConstValue::Real(0.5)));
+        assert_eq!(value_of(&table, "MASK"), Ok(ConstValue::Int(0xF1)));
+        assert_eq!(value_of(&table, "CYCLE"), Ok(ConstValue::Time(255_000_000)));
+        assert_eq!(value_of(&table, "TICK"), Ok(ConstValue::Time(1_500_250)));
+        assert_eq!(value_of(&table, "TICKS"), Ok(ConstValue::Int(2001)));
+        assert_eq!(value_of(&table, "CLAMPED"), Ok(ConstValue::Int(7)));
+        assert_eq!(value_of(&table, "ROUNDED"), Ok(ConstValue::Int(1)));
+        assert_eq!(value_of(&table, "BIG"), Ok(ConstValue::Bool(true)));
+    }
+
+    #[test]
+    fn test_reports_overflow_and_division_by_zero() {
+        let table = table_of(
+            r#"
+        VAR_GLOBAL CONSTANT
+            SMALL : SINT := 100;
+            SUM : SINT := SMALL + SMALL;
+            RATIO : INT := 10 / (SMALL - 100);
+            WRAPPED : INT := SUM + 1;
+        END_VAR
+        VAR_GLOBAL
+            input : INT;
+            derived : INT := input + 1;
+        END_VAR
+        "#,
+        );
+
+        let message = |r: Result<ConstValue, EvalError>| match r {
+            Err(EvalError::Failed(d)) => d.message,
+            other => panic!("Expected a failure, got {other:?}"),
+        };
+        assert_eq!(
+            message(value_of(&table, "SUM")),
+            "Overflow in constant expression: 200 does not fit into SINT."
+        );
+        assert_eq!(
+            message(value_of(&table, "RATIO")),
+            "Division by zero in constant expression."
+        );
+        assert_eq!(value_of(&table, "WRAPPED"), Err(EvalError::Dependency));
+        assert!(matches!(
+            value_of(&table, "derived"),
+            Err(EvalError::NotConstant(_))
+        ));
+    }
+
+    #[test]
+    fn test_numbers_enum_values() {
+        let table = table_of("TYPE State : (Idle, Busy := 10, Done); END_TYPE");
+        let env = TypeEnv::new(&table);
+        let id = |name: &str| table.symbols.iter().position(|s| s.name == name).unwrap();
+
+        assert_eq!(enum_value(&env, id("Idle")), Some(0));
+        assert_eq!(enum_value(&env, id("Busy")), Some(10));
+        assert_eq!(enum_value(&env, id("Done")), Some(11));
+    }
+}
@@ -0,0 +1,666 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    parsing::ast::{
+        Argument, CallExpression, Expression, ForLoop, Span, Statement, Statements, VariableKind,
+    },
+    semantic::{
+        symbols::{SymbolId, SymbolKind},
+        types::{Type, TypeEnv},
+    },
+};
+
+pub type NodeId = usize;
+
+/// A step of the control flow of statements.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Node<'a> {
+    Entry,
+    Exit,
+    /// An assignment, a call, or a RETURN, EXIT or CONTINUE
+    Statement(&'a Statement),
+    /// A condition of an IF, WHILE or REPEAT, or the selector of a CASE, before it branches
+    Condition(&'a Expression),
+    /// Evaluation of the bounds of a FOR loop and assignment of the start to its variable
+    ForStart(&'a ForLoop),
+    /// Increment of the variable of a FOR loop and the test whether to repeat
+    ForStep(&'a ForLoop),
+}
+
+impl<'a> Node<'a> {
+    /// The expressions a node evaluates.
+    pub fn expressions(&self) -> Vec<&'a Expression> {
+        match *self {
+            Node::Entry | Node::Exit => Vec::new(),
+            Node::Statement(Statement::Assignment(x)) => vec![&x.target, &x.value],
+            Node::Statement(Statement::Expression(e)) | Node::Condition(e) => vec![e],
+            Node::Statement(_) => Vec::new(),
+            Node::ForStart(x) | Node::ForStep(x) => [Some(&x.start), Some(&x.end), x.step.as_ref()]
+                .into_iter()
+                .flatten()
+                .collect(),
+        }
+    }
+}
+
+/// Control flow graph of the statements of a POU, method or action.
+///
+/// Statements following a RETURN, EXIT or CONTINUE have nodes as well, which cannot be reached
+/// from the entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Graph<'a> {
+    pub nodes: Vec<Node<'a>>,
+    pub successors: Vec<Vec<NodeId>>,
+}
+
+impl<'a> Graph<'a> {
+    pub const ENTRY: NodeId = 0;
+    pub const EXIT: NodeId = 1;
+
+    pub fn new(statements: &'a Statements) -> Self {
+        let mut builder = Builder {
+            graph: Graph {
+                nodes: vec![Node::Entry, Node::Exit],
+                successors: vec![Vec::new(), Vec::new()],
+            },
+            loops: Vec::new(),
+        };
+        let ends = builder.statements(statements, vec![Self::ENTRY]);
+        builder.connect(&ends, Self::EXIT);
+        builder.graph
+    }
+
+    pub fn predecessors(&self) -> Vec<Vec<NodeId>> {
+        let mut predecessors = vec![Vec::new(); self.nodes.len()];
+        for (node, successors) in self.successors.iter().enumerate() {
+            for successor in successors {
+                predecessors[*successor].push(node);
+            }
+        }
+        predecessors
+    }
+
+    /// Whether the control flow reaches each node from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![Self::ENTRY];
+        while let Some(node) = stack.pop() {
+            if !std::mem::replace(&mut reachable[node], true) {
+                stack.extend(&self.successors[node]);
+            }
+        }
+        reachable
+    }
+}
+
+/// The nodes that EXIT and CONTINUE leave a loop from.
+#[derive(Default)]
+struct Loop {
+    exits: Vec<NodeId>,
+    continues: Vec<NodeId>,
+}
+
+struct Builder<'a> {
+    graph: Graph<'a>,
+    loops: Vec<Loop>,
+}
+
+impl<'a> Builder<'a> {
+    fn add(&mut self, node: Node<'a>, predecessors: &[NodeId]) -> NodeId {
+        let id = self.graph.nodes.len();
+        self.graph.nodes.push(node);
+        self.graph.successors.push(Vec::new());
+        self.connect(predecessors, id);
+        id
+    }
+
+    fn connect(&mut self, from: &[NodeId], to: NodeId) {
+        for node in from {
+            if !self.graph.successors[*node].contains(&to) {
+                self.graph.successors[*node].push(to);
+            }
+        }
+    }
+
+    /// Adds the nodes of statements following the nodes `ends`, returning the nodes the control
+    /// flow leaves them from.
+    fn statements(&mut self, statements: &'a Statements, mut ends: Vec<NodeId>) -> Vec<NodeId> {
+        for statement in statements {
+            ends = self.statement(statement, ends);
+        }
+        ends
+    }
+
+    /// Adds the nodes of a loop body, the first of which starts the body unless it is empty.
+    fn body(&mut self, statements: &'a Statements, ends: Vec<NodeId>) -> (NodeId, Vec<NodeId>) {
+        let first = self.graph.nodes.len();
+        let ends = self.statements(statements, ends);
+        (first, ends)
+    }
+
+    fn statement(&mut self, statement: &'a Statement, ends: Vec<NodeId>) -> Vec<NodeId> {
+        match statement {
+            Statement::Empty(_) => ends,
+            Statement::Expression(_) | Statement::Assignment(_) => {
+                vec![self.add(Node::Statement(statement), &ends)]
+            }
+            Statement::Return(_) => {
+                let node = self.add(Node::Statement(statement), &ends);
+                self.connect(&[node], Graph::EXIT);
+                Vec::new()
+            }
+            Statement::Exit(_) | Statement::Continue(_) => {
+                let node = self.add(Node::Statement(statement), &ends);
+                // The checker reports jumps outside of loops
+                if let Some(current) = self.loops.last_mut() {
+                    match statement {
+                        Statement::Exit(_) => current.exits.push(node),
+                        _ => current.continues.push(node),
+                    }
+                }
+                Vec::new()
+            }
+            Statement::If(x) => {
+                let mut after = Vec::new();
+                let mut otherwise = ends;
+                for branch in std::iter::once(&x.branch).chain(&x.alt_branches) {
+                    let condition = self.add(Node::Condition(&branch.condition), &otherwise);
+                    after.extend(self.statements(&branch.statements, vec![condition]));
+                    otherwise = vec![condition];
+                }
+                match &x.fallback {
+                    Some(fallback) => after.extend(self.statements(fallback, otherwise)),
+                    None => after.extend(otherwise),
+                }
+                after
+            }
+            Statement::Case(x) => {
+                let selector = self.add(Node::Condition(&x.selector), &ends);
+                let mut after = Vec::new();
+                for branch in &x.branches {
+                    after.extend(self.statements(&branch.statements, vec![selector]));
+                }
+                match &x.fallback {
+                    Some(fallback) => after.extend(self.statements(fallback, vec![selector])),
+                    None => after.push(selector),
+                }
+                after
+            }
+            Statement::For(x) => {
+                let start = self.add(Node::ForStart(x), &ends);
+                self.loops.push(Loop::default());
+                let (first, body) = self.body(&x.statements, vec![start]);
+                let current = self.loops.pop().unwrap_or_default();
+                let step = self.add(Node::ForStep(x), &[body, current.continues].concat());
+                self.connect(&[step], first.min(step));
+                [vec![start, step], current.exits].concat()
+            }
+            Statement::While(x) => {
+                let condition = self.add(Node::Condition(&x.condition), &ends);
+                self.loops.push(Loop::default());
+                let body = self.statements(&x.statements, vec![condition]);
+                let current = self.loops.pop().unwrap_or_default();
+                self.connect(&[body, current.continues].concat(), condition);
+                [vec![condition], current.exits].concat()
+            }
+            Statement::Repeat(x) => {
+                self.loops.push(Loop::default());
+                let (first, body) = self.body(&x.statements, ends);
+                let current = self.loops.pop().unwrap_or_default();
+                let condition = self.add(
+                    Node::Condition(&x.condition),
+                    &[body, current.continues].concat(),
+                );
+                self.connect(&[condition], first.min(condition));
+                [vec![condition], current.exits].concat()
+            }
+        }
+    }
+}
+
+/// How a node accesses a variable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessKind {
+    Read,
+    /// An assignment of a whole new value
+    Write,
+    /// An assignment of an element or member, or a change through an in-out parameter
+    PartialWrite,
+}
+
+/// A use of a variable by a node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Access {
+    pub symbol: SymbolId,
+    pub kind: AccessKind,
+    pub span: Span,
+}
+
+/// The variables, and return values of functions and methods, a node accesses, in the order of
+/// evaluation, where reads precede the writes of their node.
+pub fn accesses(node: &Node, env: &TypeEnv) -> Vec<Access> {
+    let mut collector = Accesses {
+        env,
+        accesses: Vec::new(),
+    };
+    match node {
+        Node::Entry | Node::Exit => {}
+        Node::Statement(Statement::Assignment(assignment)) => {
+            collector.read(&assignment.value);
+            collector.write(&assignment.target, AccessKind::Write);
+        }
+        Node::Statement(Statement::Expression(expression)) => collector.read(expression),
+        Node::Condition(expression) => collector.read(expression),
+        Node::Statement(_) => {}
+        Node::ForStart(x) => {
+            for bound in [Some(&x.start), Some(&x.end), x.step.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                collector.read(bound);
+            }
+            collector.access(x.variable.span, AccessKind::Write);
+        }
+        Node::ForStep(x) => {
+            collector.access(x.variable.span, AccessKind::Read);
+            for bound in [Some(&x.end), x.step.as_ref()].into_iter().flatten() {
+                collector.read(bound);
+            }
+            collector.access(x.variable.span, AccessKind::Write);
+        }
+    }
+    // Outputs of calls are assigned after all arguments are evaluated
+    collector
+        .accesses
+        .sort_by_key(|a| a.kind != AccessKind::Read);
+    collector.accesses
+}
+
+struct Accesses<'a, 'b> {
+    env: &'b TypeEnv<'a>,
+    accesses: Vec<Access>,
+}
+
+impl Accesses<'_, '_> {
+    fn access(&mut self, span: Span, kind: AccessKind) {
+        let Some(id) = self.env.binding(span) else {
+            return;
+        };
+        let symbol = self.env.table.symbol(id);
+        let value = match symbol.kind {
+            SymbolKind::Variable { .. } => true,
+            // Only the return value within its own function can be assigned
+            SymbolKind::Function | SymbolKind::Method => {
+                kind != AccessKind::Read || symbol.data_type.is_some()
+            }
+            _ => false,
+        };
+        if value {
+            self.accesses.push(Access {
+                symbol: id,
+                kind,
+                span,
+            });
+        }
+    }
+
+    fn read(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Literal(..) => {}
+            Expression::Identifier(name) => self.access(name.span, AccessKind::Read),
+            Expression::Prefix(x) => self.read(&x.operand),
+            Expression::Infix(x) => {
+                self.read(&x.left);
+                self.read(&x.right);
+            }
+            Expression::Member(x) => self.read(&x.target),
+            Expression::Index(x) => {
+                self.read(&x.target);
+                for index in &x.indices {
+                    self.read(index);
+                }
+            }
+            Expression::Call(x) => {
+                // The name of a function is no read of its return value
+                if !matches!(x.callee.as_ref(), Expression::Identifier(_)) {
+                    self.read(&x.callee);
+                }
+                for argument in &x.arguments {
+                    match argument {
+                        Argument::Output(_, target) => self.write(target, AccessKind::Write),
+                        Argument::Named(name, value) => {
+                            self.read(value);
+                            let in_out = self.env.binding(name.span).is_some_and(|id| {
+                                matches!(
+                                    self.env.table.symbol(id).kind,
+                                    SymbolKind::Variable {
+                                        kind: VariableKind::InOut,
+                                        ..
+                                    }
+                                )
+                            });
+                            if in_out {
+                                self.write(value, AccessKind::PartialWrite);
+                            }
+                        }
+                        // Without names the parameter may be an in-out one, or take an address
+                        Argument::Positional(value) => {
+                            self.read(value);
+                            self.write(value, AccessKind::PartialWrite);
+                        }
+                    }
+                }
+            }
+            Expression::Deref(target, _) => self.read(target),
+            Expression::TypedLiteral(_) => {}
+            Expression::Array(elements, _) => {
+                for element in elements {
+                    self.read(element);
+                }
+            }
+            Expression::Struct(fields, _) => {
+                for (_, value) in fields {
+                    self.read(value);
+                }
+            }
+        }
+    }
+
+    /// Records the assignment of a target, a whole write only if it is a bare name.
+    fn write(&mut self, target: &Expression, kind: AccessKind) {
+        match target {
+            Expression::Identifier(name) => self.access(name.span, kind),
+            Expression::Member(x) => self.write(&x.target, AccessKind::PartialWrite),
+            Expression::Index(x) => {
+                for index in &x.indices {
+                    self.read(index);
+                }
+                self.write(&x.target, AccessKind::PartialWrite);
+            }
+            // Assigning what a pointer points to reads the pointer
+            Expression::Deref(pointer, _) => self.read(pointer),
+            _ => {}
+        }
+    }
+}
+
+/// An assignment of a variable by a node, or its value before the statements at the entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Definition {
+    pub node: NodeId,
+    pub symbol: SymbolId,
+    pub kind: AccessKind,
+    pub span: Span,
+}
+
+/// The assignments of variables that may still hold when each node runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReachingDefinitions {
+    pub definitions: Vec<Definition>,
+    /// Indices of the definitions reaching the start of each node
+    pub reaching: Vec<BTreeSet<usize>>,
+}
+
+impl ReachingDefinitions {
+    /// Solves reaching definitions for the variables `tracked`, which are defined at the entry by
+    /// the spans given with them.
+    ///
+    /// A whole write kills the other definitions of its variable, a partial one only the
+    /// definition at the entry.
+    pub fn new(graph: &Graph, accesses: &[Vec<Access>], tracked: &[(SymbolId, Span)]) -> Self {
+        let mut definitions = tracked
+            .iter()
+            .map(|(symbol, span)| Definition {
+                node: Graph::ENTRY,
+                symbol: *symbol,
+                kind: AccessKind::Write,
+                span: *span,
+            })
+            .collect::<Vec<_>>();
+        for (node, accesses) in accesses.iter().enumerate() {
+            let writes = accesses.iter().filter(|a| {
+                a.kind != AccessKind::Read && tracked.iter().any(|(s, _)| *s == a.symbol)
+            });
+            definitions.extend(writes.map(|a| Definition {
+                node,
+                symbol: a.symbol,
+                kind: a.kind,
+                span: a.span,
+            }));
+        }
+
+        let transfer = |node: NodeId, reaching: &BTreeSet<usize>| {
+            let mut out = reaching.clone();
+            for (index, definition) in definitions.iter().enumerate() {
+                if definition.node != node || node == Graph::ENTRY {
+                    continue;
+                }
+                out.retain(|other| {
+                    let other = &definitions[*other];
+                    other.symbol != definition.symbol
+                        || definition.kind == AccessKind::PartialWrite && other.node != Graph::ENTRY
+                });
+                out.insert(index);
+            }
+            out
+        };
+
+        let predecessors = graph.predecessors();
+        let mut reaching = vec![BTreeSet::new(); graph.nodes.len()];
+        let mut leaving = vec![BTreeSet::new(); graph.nodes.len()];
+        leaving[Graph::ENTRY] = (0..tracked.len()).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in 1..graph.nodes.len() {
+                let within = predecessors[node]
+                    .iter()
+                    .flat_map(|p| leaving[*p].iter().copied())
+                    .collect::<BTreeSet<_>>();
+                let out = transfer(node, &within);
+                reaching[node] = within;
+                if out != leaving[node] {
+                    leaving[node] = out;
+                    changed = true;
+                }
+            }
+        }
+        Self {
+            definitions,
+            reaching,
+        }
+    }
+
+    /// The definitions of a variable reaching a node.
+    pub fn of(&self, node: NodeId, symbol: SymbolId) -> impl Iterator<Item = usize> + '_ {
+        self.reaching[node]
+            .iter()
+            .copied()
+            .filter(move |d| self.definitions[*d].symbol == symbol)
+    }
+}
+
+/// The POU, method or action a call runs, and the variable holding the instance it runs on.
+pub fn callee(env: &TypeEnv, call: &CallExpression) -> Option<(SymbolId, Option<SymbolId>)> {
+    let (name, instance) = match &*call.callee {
+        Expression::Identifier(name) => (name, None),
+        Expression::Member(x) => (&x.member, root(&x.target)),
+        _ => return None,
+    };
+    let id = env.binding(name.span)?;
+    let table = env.table;
+    match table.symbol(id).kind {
+        SymbolKind::Function | SymbolKind::Program | SymbolKind::Method | SymbolKind::Action => {
+            Some((id, instance.and_then(|i| env.binding(i))))
+        }
+        SymbolKind::Variable { .. } => match env.type_of_symbol(id).dereferenced() {
+            Type::FunctionBlock(fb) => {
+                let instance = instance.and_then(|i| env.binding(i)).unwrap_or(id);
+                Some((*fb, Some(instance)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The span of the first name of an access path like `a.b[1]`.
+fn root(target: &Expression) -> Option<Span> {
+    match target {
+        Expression::Identifier(name) => Some(name.span),
+        Expression::Member(x) => root(&x.target),
+        Expression::Index(x) => root(&x.target),
+        _ => None,
+    }
+}
+
+/// Calls the function with every call within an expression.
+pub fn visit_calls(expression: &Expression, f: &mut impl FnMut(&CallExpression)) {
+    match expression {
+        Expression::Literal(..) | Expression::Identifier(_) | Expression::TypedLiteral(_) => {}
+        Expression::Prefix(x) => visit_calls(&x.operand, f),
+        Expression::Infix(x) => {
+            visit_calls(&x.left, f);
+            visit_calls(&x.right, f);
+        }
+        Expression::Member(x) => visit_calls(&x.target, f),
+        Expression::Index(x) => {
+            visit_calls(&x.target, f);
+            for index in &x.indices {
+                visit_calls(index, f);
+            }
+        }
+        Expression::Call(x) => {
+            f(x);
+            visit_calls(&x.callee, f);
+            for argument in &x.arguments {
+                if !matches!(argument, Argument::Output(..)) {
+                    visit_calls(argument.value(), f);
+                }
+            }
+        }
+        Expression::Deref(target, _) => visit_calls(target, f),
+        Expression::Array(elements, _) => {
+            for element in elements {
+                visit_calls(element, f);
+            }
+        }
+        Expression::Struct(fields, _) => {
+            for (_, value) in fields {
+                visit_calls(value, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parsing::ast::Block,
+        project::{Project, SourceDb},
+    };
+
+    fn analyzed(src: &str) -> Project {
+        let mut sources = SourceDb::new();
+        sources.add("main.st", src);
+        Project::analyze(sources).unwrap()
+    }
+
+    fn body(project: &Project) -> &Statements {
+        match &project.ast.blocks[0] {
+            Block::Program(pou) | Block::Function(pou) => &pou.statements,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_builds_edges_of_jumps_and_loops() {
+        let project = analyzed(
+            "PROGRAM Main
+VAR i, n : INT; END_VAR
+FOR i := 0 TO 9 DO
+    IF i = n THEN EXIT; END_IF;
+    n := n + 1;
+END_FOR;
+RETURN;
+n := 0;
+END_PROGRAM",
+        );
+        let graph = Graph::new(body(&project));
+        // Entry, exit, start, condition, EXIT, assignment, step, RETURN and the last assignment
+        assert_eq!(
+            graph.successors,
+            [
+                vec![2],
+                vec![],
+                vec![3, 7],
+                vec![4, 5],
+                vec![7],
+                vec![6],
+                vec![3, 7],
+                vec![1],
+                vec![1]
+            ]
+        );
+        assert_eq!(
+            graph.reachable(),
+            [true, true, true, true, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_finds_definitions_reaching_reads() {
+        let project = analyzed(
+            "PROGRAM Main
+VAR a, b : INT; END_VAR
+a := 1;
+IF b > 0 THEN a := 2; END_IF;
+b := a;
+END_PROGRAM",
+        );
+        let env = TypeEnv::new(&project.table);
+        let graph = Graph::new(body(&project));
+        let accesses = graph
+            .nodes
+            .iter()
+            .map(|n| accesses(n, &env))
+            .collect::<Vec<_>>();
+        let a = accesses[2][0].symbol;
+        let b = accesses[3][0].symbol;
+        let definitions = ReachingDefinitions::new(&graph, &accesses, &[(a, Span::default())]);
+        let nodes = |node| {
+            definitions
+                .of(node, a)
+                .map(|d| definitions.definitions[d].node)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(nodes(3), [2]);
+        assert_eq!(nodes(5), [2, 4]);
+        assert_eq!(
+            accesses[5],
+            [
+                Access {
+                    symbol: a,
+                    kind: AccessKind::Read,
+                    span: Span {
+                        pos: 80,
+                        len: 1,
+                        line: 4,
+                        col: 5
+                    }
+                },
+                Access {
+                    symbol: b,
+                    kind: AccessKind::Write,
+                    span: Span {
+                        pos: 75,
+                        len: 1,
+                        line: 4,
+                        col: 0
+                    }
+                }
+            ]
+        );
+    }
+}
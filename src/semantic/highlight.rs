@@ -0,0 +1,153 @@
+use crate::{
+    parsing::{
+        ast::{Span, VariableKind},
+        lexer::Lexer,
+        token::Token,
+    },
+    semantic::{
+        symbols::{SymbolId, SymbolKind, is_elementary_type},
+        types::{Type, TypeEnv},
+    },
+};
+
+/// Role of a token for editors, finer than its token kind where names are resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Keyword,
+    Type,
+    Variable,
+    Parameter,
+    /// A variable holding a function block or program instance
+    Instance,
+    EnumMember,
+    Constant,
+    Comment,
+    /// Programs, functions, methods and actions
+    Function,
+    Namespace,
+    String,
+    Number,
+}
+
+/// Classifies the tokens of a lexer, resolving identifiers by the bindings of an environment.
+///
+/// Without an environment, or for names that do not resolve, only elementary type names are
+/// classified. Operators, delimiters, pragmas and unresolved names are left out.
+pub fn highlight(tokens: Lexer, env: Option<&TypeEnv>) -> Vec<(Span, Highlight)> {
+    tokens
+        .filter_map(|marked| {
+            let span = marked.span();
+            let highlight = match &marked.token {
+                Token::Comment(_) => Highlight::Comment,
+                Token::String(_) => Highlight::String,
+                Token::Number(..) | Token::Time(..) | Token::Date(..) => Highlight::Number,
+                Token::DirectAddress(_) => Highlight::Variable,
+                Token::LiteralPrefix(_) => Highlight::Type,
+                Token::Identifier(name) => {
+                    match env.and_then(|env| Some((env, env.binding(span)?))) {
+                        Some((env, id)) => classify(env, id),
+                        None if is_elementary_type(name) => Highlight::Type,
+                        None => return None,
+                    }
+                }
+                token if token.keyword_text().is_some() => Highlight::Keyword,
+                _ => return None,
+            };
+            Some((span, highlight))
+        })
+        .collect()
+}
+
+fn classify(env: &TypeEnv, id: SymbolId) -> Highlight {
+    match env.table.symbol(id).kind {
+        SymbolKind::Variable { constant: true, .. } => Highlight::Constant,
+        SymbolKind::Variable {
+            kind: VariableKind::Input | VariableKind::Output | VariableKind::InOut,
+            ..
+        } => Highlight::Parameter,
+        SymbolKind::Variable { .. } => match env.type_of_symbol(id).dereferenced() {
+            Type::FunctionBlock(_) | Type::Program(_) => Highlight::Instance,
+            _ => Highlight::Variable,
+        },
+        SymbolKind::Program | SymbolKind::Function | SymbolKind::Method | SymbolKind::Action => {
+            Highlight::Function
+        }
+        SymbolKind::FunctionBlock | SymbolKind::Type => Highlight::Type,
+        SymbolKind::EnumValue => Highlight::EnumMember,
+        SymbolKind::Namespace | SymbolKind::Configuration | SymbolKind::Resource => {
+            Highlight::Namespace
+        }
+        SymbolKind::Task => Highlight::Variable,
+        SymbolKind::ProgramInstance => Highlight::Instance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parsing::parser::parse, semantic::resolver::resolve};
+
+    fn highlights(src: &str, resolved: bool) -> Vec<(&str, Highlight)> {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        let (table, _) = resolve(&ast);
+        let env = TypeEnv::new(&table);
+        highlight(Lexer::create("main.st", src), resolved.then_some(&env))
+            .into_iter()
+            .map(|(span, h)| (&src[span.pos..span.end()], h))
+            .collect()
+    }
+
+    #[test]
+    fn test_classifies_resolved_names() {
+        let src = "TYPE Mode : (Off, On_); END_TYPE
+FUNCTION_BLOCK Motor VAR_INPUT speed : INT; END_VAR END_FUNCTION_BLOCK
+PROGRAM Main
+VAR CONSTANT max : INT := 10; END_VAR
+VAR m : Motor; n : INT; state : Mode; END_VAR
+// run
+m(speed := max);
+n := INT#5 + m.speed;
+state := Off;
+END_PROGRAM";
+        let highlights = highlights(src, true);
+        let of = |text: &str| {
+            highlights
+                .iter()
+                .filter(|(t, _)| *t == text)
+                .map(|(_, h)| *h)
+                .collect::<Vec<_>>()
+        };
+        use Highlight::*;
+        assert_eq!(of("Mode"), [Type, Type]);
+        assert_eq!(of("Off"), [EnumMember, EnumMember]);
+        assert_eq!(of("Motor"), [Type, Type]);
+        assert_eq!(of("speed"), [Parameter, Parameter, Parameter]);
+        assert_eq!(of("Main"), [Function]);
+        assert_eq!(of("max"), [Constant, Constant]);
+        assert_eq!(of("m"), [Instance, Instance, Instance]);
+        assert_eq!(of("n"), [Variable, Variable]);
+        assert_eq!(of("INT"), [Type, Type, Type]);
+        assert_eq!(of("INT#"), [Type]);
+        assert_eq!(of("// run"), [Comment]);
+        assert_eq!(of("10"), [Number]);
+        assert_eq!(of("END_PROGRAM"), [Keyword]);
+        assert_eq!(of(":="), []);
+    }
+
+    #[test]
+    fn test_classifies_tokens_without_resolution() {
+        let src = "PROGRAM Main VAR s : STRING; x : Unknown; END_VAR s := 'a'; END_PROGRAM";
+        let classified = highlights(src, false);
+        assert_eq!(
+            classified,
+            [
+                ("PROGRAM", Highlight::Keyword),
+                ("VAR", Highlight::Keyword),
+                ("STRING", Highlight::Type),
+                ("END_VAR", Highlight::Keyword),
+                ("'a'", Highlight::String),
+                ("END_PROGRAM", Highlight::Keyword),
+            ]
+        );
+    }
+}
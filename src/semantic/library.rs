@@ -0,0 +1,693 @@
+use crate::{
+    parsing::{
+        ast::{DataType, Identifier, QualifiedName, Span, VariableKind},
+        token::{DateKind, DateValue},
+    },
+    semantic::{
+        symbols::{ScopeKind, SymbolId, SymbolKind, SymbolTable},
+        types::{Elementary, Generic, Type, common_type, is_assignable},
+    },
+};
+
+/// A standard function of IEC 61131-3, available without a declaration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    Ln,
+    Log,
+    Exp,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Expt,
+    Trunc,
+    Sel,
+    Max,
+    Min,
+    Limit,
+    Mux,
+    Shl,
+    Shr,
+    Rol,
+    Ror,
+    Len,
+    Left,
+    Right,
+    Mid,
+    Concat,
+    Insert,
+    Delete,
+    Replace,
+    Find,
+    /// One of the `*_TO_*` conversions
+    Convert(Scalar, Scalar),
+    /// Assertions of unit tests, which fail the cycle unless they hold
+    AssertTrue,
+    AssertFalse,
+    AssertEq,
+    AssertNe,
+    AssertNear,
+}
+
+/// The types the `*_TO_*` conversions convert between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    Elementary(Elementary),
+    String { wide: bool },
+}
+
+impl Scalar {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "STRING" => Some(Scalar::String { wide: false }),
+            "WSTRING" => Some(Scalar::String { wide: true }),
+            name => Elementary::from_name(name).map(Scalar::Elementary),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scalar::Elementary(e) => e.name(),
+            Scalar::String { wide: false } => "STRING",
+            Scalar::String { wide: true } => "WSTRING",
+        }
+    }
+
+    pub fn to_type(self) -> Type {
+        match self {
+            Scalar::Elementary(e) => Type::Elementary(e),
+            Scalar::String { wide } => Type::String { wide, length: None },
+        }
+    }
+}
+
+const FUNCTION_NAMES: &[(&str, Function)] = &[
+    ("ABS", Function::Abs),
+    ("SQRT", Function::Sqrt),
+    ("LN", Function::Ln),
+    ("LOG", Function::Log),
+    ("EXP", Function::Exp),
+    ("SIN", Function::Sin),
+    ("COS", Function::Cos),
+    ("TAN", Function::Tan),
+    ("ASIN", Function::Asin),
+    ("ACOS", Function::Acos),
+    ("ATAN", Function::Atan),
+    ("EXPT", Function::Expt),
+    ("TRUNC", Function::Trunc),
+    ("SEL", Function::Sel),
+    ("MAX", Function::Max),
+    ("MIN", Function::Min),
+    ("LIMIT", Function::Limit),
+    ("MUX", Function::Mux),
+    ("SHL", Function::Shl),
+    ("SHR", Function::Shr),
+    ("ROL", Function::Rol),
+    ("ROR", Function::Ror),
+    ("LEN", Function::Len),
+    ("LEFT", Function::Left),
+    ("RIGHT", Function::Right),
+    ("MID", Function::Mid),
+    ("CONCAT", Function::Concat),
+    ("INSERT", Function::Insert),
+    ("DELETE", Function::Delete),
+    ("REPLACE", Function::Replace),
+    ("FIND", Function::Find),
+    ("ASSERT_TRUE", Function::AssertTrue),
+    ("ASSERT_FALSE", Function::AssertFalse),
+    ("ASSERT_EQ", Function::AssertEq),
+    ("ASSERT_NE", Function::AssertNe),
+    ("ASSERT_NEAR", Function::AssertNear),
+];
+
+/// Finds a standard function by its case-insensitive name.
+pub fn lookup(name: &str) -> Option<Function> {
+    if let Some((from, to)) = name.to_ascii_uppercase().split_once("_TO_") {
+        return Some(Function::Convert(
+            Scalar::from_name(from)?,
+            Scalar::from_name(to)?,
+        ));
+    }
+    FUNCTION_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, f)| *f)
+}
+
+/// Why the arguments of a call do not fit the signature of a standard function.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallError {
+    /// The number of arguments is wrong, with a description of the expected count
+    Count(&'static str),
+    /// An argument does not match the type of its parameter
+    Argument {
+        index: usize,
+        expected: &'static str,
+    },
+    /// The arguments which share a generic type have no common type
+    Incompatible,
+}
+
+const STRING: Type = Type::String {
+    wide: false,
+    length: None,
+};
+const INT: Type = Type::Elementary(Elementary::Int);
+
+impl Function {
+    /// Whether the function is one of the assertions of unit tests.
+    pub fn is_assertion(&self) -> bool {
+        use Function::*;
+        matches!(
+            self,
+            AssertTrue | AssertFalse | AssertEq | AssertNe | AssertNear
+        )
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Function::Convert(from, to) => format!("{}_TO_{}", from.name(), to.name()),
+            f => FUNCTION_NAMES
+                .iter()
+                .find(|(_, g)| g == f)
+                .map(|(n, _)| n.to_string())
+                .expect("Every standard function has a name"),
+        }
+    }
+
+    /// Type of the result of a call with arguments of the given types.
+    pub fn result_type(&self, arguments: &[Type]) -> Result<Type, CallError> {
+        use Function::*;
+
+        let (min, max, count) = match self {
+            Sel | Limit | Mid | Insert | Delete | AssertNear => (3, 3, "3"),
+            Expt | Shl | Shr | Rol | Ror | Left | Right | Find | AssertEq | AssertNe => (2, 2, "2"),
+            Replace => (4, 4, "4"),
+            Max | Min | Mux | Concat => (2, usize::MAX, "at least 2"),
+            _ => (1, 1, "1"),
+        };
+        if arguments.len() < min || arguments.len() > max {
+            return Err(CallError::Count(count));
+        }
+
+        let expect = |index: usize, generic: Generic| match generic.contains(&arguments[index]) {
+            true => Ok(()),
+            false => Err(CallError::Argument {
+                index,
+                expected: generic.name(),
+            }),
+        };
+        // Arguments sharing a generic type are converted to their common type
+        let common = |indices: &mut dyn Iterator<Item = usize>, generic: Generic| {
+            let mut result = Type::Unknown;
+            for index in indices {
+                expect(index, generic)?;
+                result = match result {
+                    Type::Unknown => arguments[index].dereferenced().clone(),
+                    r => common_type(&r, &arguments[index]).ok_or(CallError::Incompatible)?,
+                };
+            }
+            Ok(result)
+        };
+        // Integer literals are valid reals, which keeps SQRT(2) legal
+        let real = |index: usize| match arguments[index].dereferenced() {
+            Type::IntegerLiteral => Ok(Type::RealLiteral),
+            t => expect(index, Generic::AnyReal).map(|_| t.clone()),
+        };
+        let string = |index: usize| {
+            expect(index, Generic::AnyString)?;
+            Ok(match arguments[index].dereferenced() {
+                Type::String { wide, .. } => Type::String {
+                    wide: *wide,
+                    length: None,
+                },
+                _ => STRING,
+            })
+        };
+
+        match self {
+            Abs => common(&mut (0..1), Generic::AnyNum),
+            Sqrt | Ln | Log | Exp | Sin | Cos | Tan | Asin | Acos | Atan => real(0),
+            Expt => {
+                expect(1, Generic::AnyNum)?;
+                real(0)
+            }
+            Trunc => real(0).map(|_| Type::Elementary(Elementary::Dint)),
+            Sel => {
+                if !is_assignable(&arguments[0], &Type::Elementary(Elementary::Bool)) {
+                    return Err(CallError::Argument {
+                        index: 0,
+                        expected: "BOOL",
+                    });
+                }
+                common(&mut (1..3), Generic::Any)
+            }
+            Max | Min => common(&mut (0..arguments.len()), Generic::AnyElementary),
+            Limit => common(&mut (0..3), Generic::AnyElementary),
+            Mux => {
+                expect(0, Generic::AnyInt)?;
+                common(&mut (1..arguments.len()), Generic::Any)
+            }
+            Shl | Shr | Rol | Ror => {
+                expect(1, Generic::AnyInt)?;
+                common(&mut (0..1), Generic::AnyBit)
+            }
+            Len => string(0).map(|_| INT),
+            Left | Right => {
+                expect(1, Generic::AnyInt)?;
+                string(0)
+            }
+            Mid | Delete => {
+                expect(1, Generic::AnyInt)?;
+                expect(2, Generic::AnyInt)?;
+                string(0)
+            }
+            Concat => {
+                let result = string(0)?;
+                for index in 1..arguments.len() {
+                    if string(index)? != result {
+                        return Err(CallError::Incompatible);
+                    }
+                }
+                Ok(result)
+            }
+            Insert | Find => {
+                let result = string(0)?;
+                if string(1)? != result {
+                    return Err(CallError::Incompatible);
+                }
+                match self {
+                    Find => Ok(INT),
+                    _ => {
+                        expect(2, Generic::AnyInt)?;
+                        Ok(result)
+                    }
+                }
+            }
+            Replace => {
+                let result = string(0)?;
+                if string(1)? != result {
+                    return Err(CallError::Incompatible);
+                }
+                expect(2, Generic::AnyInt)?;
+                expect(3, Generic::AnyInt)?;
+                Ok(result)
+            }
+            Convert(from, to) => match is_assignable(&arguments[0], &from.to_type()) {
+                true => Ok(to.to_type()),
+                false => Err(CallError::Argument {
+                    index: 0,
+                    expected: from.name(),
+                }),
+            },
+            AssertTrue | AssertFalse => {
+                match is_assignable(&arguments[0], &Type::Elementary(Elementary::Bool)) {
+                    true => Ok(Type::Void),
+                    false => Err(CallError::Argument {
+                        index: 0,
+                        expected: "BOOL",
+                    }),
+                }
+            }
+            AssertEq | AssertNe => common(&mut (0..2), Generic::AnyElementary).map(|_| Type::Void),
+            AssertNear => common(&mut (0..3), Generic::AnyNum).map(|_| Type::Void),
+        }
+    }
+}
+
+/// The functions on reals from SQRT to ATAN, LOG being the decimal logarithm.
+pub fn real_function(function: Function, x: f64) -> f64 {
+    match function {
+        Function::Sqrt => x.sqrt(),
+        Function::Ln => x.ln(),
+        Function::Log => x.log10(),
+        Function::Exp => x.exp(),
+        Function::Sin => x.sin(),
+        Function::Cos => x.cos(),
+        Function::Tan => x.tan(),
+        Function::Asin => x.asin(),
+        Function::Acos => x.acos(),
+        Function::Atan => x.atan(),
+        f => unreachable!("{} is not a function on reals", f.name()),
+    }
+}
+
+/// SHL, SHR, ROL and ROR on the bits of a value of the given width.
+pub fn shift(function: Function, n: i128, by: i128, width: u32) -> i128 {
+    let width = width.clamp(1, 64);
+    let mask = (1i128 << width) - 1;
+    let n = n & mask;
+    let by = by.max(0);
+    match function {
+        _ if by == 0 => n,
+        Function::Shl if by >= width as i128 => 0,
+        Function::Shr if by >= width as i128 => 0,
+        Function::Shl => (n << by) & mask,
+        Function::Shr => n >> by,
+        Function::Rol | Function::Ror => {
+            let by = (by % width as i128) as u32;
+            let by = match function {
+                Function::Rol => by,
+                _ => (width - by) % width,
+            };
+            ((n << by) | (n >> (width - by))) & mask
+        }
+        f => unreachable!("{} is not a shift", f.name()),
+    }
+}
+
+/// Clamps a length or position argument of the string functions into the string.
+fn clamp(n: i128, len: usize) -> usize {
+    n.clamp(0, len as i128) as usize
+}
+
+/// The string functions LEFT, RIGHT, MID, INSERT, DELETE and REPLACE, positions start at 1.
+///
+/// Lengths and positions outside the string are clamped into it.
+pub fn edit_string(function: Function, a: &str, b: &str, l: i128, p: i128) -> String {
+    let chars = a.chars().collect::<Vec<_>>();
+    let len = chars.len();
+    let collect = |parts: &[&[char]]| parts.concat().into_iter().collect::<String>();
+    match function {
+        Function::Left => collect(&[&chars[..clamp(l, len)]]),
+        Function::Right => collect(&[&chars[len - clamp(l, len)..]]),
+        Function::Mid => {
+            let start = clamp(p - 1, len);
+            collect(&[&chars[start..start + clamp(l, len - start)]])
+        }
+        Function::Insert => {
+            let at = clamp(p, len);
+            let b = b.chars().collect::<Vec<_>>();
+            collect(&[&chars[..at], &b, &chars[at..]])
+        }
+        Function::Delete | Function::Replace => {
+            let start = clamp(p - 1, len);
+            let end = start + clamp(l, len - start);
+            let b = match function {
+                Function::Replace => b.chars().collect::<Vec<_>>(),
+                _ => Vec::new(),
+            };
+            collect(&[&chars[..start], &b, &chars[end..]])
+        }
+        f => unreachable!("{} does not edit strings", f.name()),
+    }
+}
+
+/// Position of the first occurrence of `b` in `a` in characters starting at 1, 0 if there is none.
+pub fn find(a: &str, b: &str) -> i128 {
+    match b.is_empty() {
+        true => 0,
+        false => a.find(b).map_or(0, |i| a[..i].chars().count() as i128 + 1),
+    }
+}
+
+/// Text of a real as the conversions to STRING produce it, REAL with single precision.
+pub fn format_real(r: f64, ty: Elementary) -> String {
+    match ty {
+        Elementary::Real => format!("{:?}", r as f32),
+        _ => format!("{r:?}"),
+    }
+}
+
+/// Nanoseconds of a millisecond, durations and dates count in nanoseconds and convert to and
+/// from other types in milliseconds.
+pub const MILLISECOND: i128 = 1_000_000;
+
+/// Text of a duration in nanoseconds as the conversions to STRING produce it, in the largest of
+/// ms, us and ns that holds it without a fraction.
+pub fn format_time(ns: i128) -> String {
+    match ns {
+        _ if ns % MILLISECOND == 0 => format!("T#{}ms", ns / MILLISECOND),
+        _ if ns % 1_000 == 0 => format!("T#{}us", ns / 1_000),
+        _ => format!("T#{ns}ns"),
+    }
+}
+
+/// Text of a date, time of day or both as a literal like `D#2024-01-15`.
+pub fn format_date(ns: i128, ty: Elementary) -> String {
+    let kind = ty.date_kind().unwrap_or(DateKind::DateAndTime);
+    DateValue::from_nanoseconds(kind, ns as i64).to_string()
+}
+
+/// BOOL a string holds, which is TRUE for `TRUE` and `1`.
+pub fn parse_bool(s: &str) -> bool {
+    let s = s.trim();
+    s.eq_ignore_ascii_case("TRUE") || s == "1"
+}
+
+/// Integer a string holds, zero if it holds none.
+pub fn parse_int(s: &str) -> i128 {
+    let s = s.trim().replace('_', "");
+    s.parse::<i128>()
+        .ok()
+        .or_else(|| s.parse::<f64>().ok().map(|r| r.round_ties_even() as i128))
+        .unwrap_or(0)
+}
+
+/// Real a string holds, zero if it holds none.
+pub fn parse_real(s: &str) -> f64 {
+    s.trim().replace('_', "").parse::<f64>().unwrap_or(0.0)
+}
+
+/// A standard function block of IEC 61131-3, available without a declaration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StandardBlock {
+    Ton,
+    Tof,
+    Tp,
+    Ctu,
+    Ctd,
+    Ctud,
+    RTrig,
+    FTrig,
+    Sr,
+    Rs,
+}
+
+const BLOCK_NAMES: &[(&str, StandardBlock)] = &[
+    ("TON", StandardBlock::Ton),
+    ("TOF", StandardBlock::Tof),
+    ("TP", StandardBlock::Tp),
+    ("CTU", StandardBlock::Ctu),
+    ("CTD", StandardBlock::Ctd),
+    ("CTUD", StandardBlock::Ctud),
+    ("R_TRIG", StandardBlock::RTrig),
+    ("F_TRIG", StandardBlock::FTrig),
+    ("SR", StandardBlock::Sr),
+    ("RS", StandardBlock::Rs),
+];
+
+type Variables = &'static [(&'static str, VariableKind, Elementary)];
+
+const TIMER: Variables = &[
+    ("IN", VariableKind::Input, Elementary::Bool),
+    ("PT", VariableKind::Input, Elementary::Time),
+    ("Q", VariableKind::Output, Elementary::Bool),
+    ("ET", VariableKind::Output, Elementary::Time),
+    ("M", VariableKind::Local, Elementary::Bool),
+    ("StartTime", VariableKind::Local, Elementary::Time),
+];
+
+impl StandardBlock {
+    pub fn from_name(name: &str) -> Option<Self> {
+        BLOCK_NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, b)| *b)
+    }
+
+    pub fn name(&self) -> &'static str {
+        BLOCK_NAMES
+            .iter()
+            .find(|(_, b)| b == self)
+            .map(|(n, _)| *n)
+            .expect("Every standard function block has a name")
+    }
+
+    /// Variables in declaration order, the local ones keep the state between calls.
+    pub fn variables(&self) -> Variables {
+        use Elementary::{Bool, Int};
+        use VariableKind::{Input, Local, Output};
+
+        match self {
+            StandardBlock::Ton | StandardBlock::Tof | StandardBlock::Tp => TIMER,
+            StandardBlock::Ctu => &[
+                ("CU", Input, Bool),
+                ("R", Input, Bool),
+                ("PV", Input, Int),
+                ("Q", Output, Bool),
+                ("CV", Output, Int),
+                ("M", Local, Bool),
+            ],
+            StandardBlock::Ctd => &[
+                ("CD", Input, Bool),
+                ("LD", Input, Bool),
+                ("PV", Input, Int),
+                ("Q", Output, Bool),
+                ("CV", Output, Int),
+                ("M", Local, Bool),
+            ],
+            StandardBlock::Ctud => &[
+                ("CU", Input, Bool),
+                ("CD", Input, Bool),
+                ("R", Input, Bool),
+                ("LD", Input, Bool),
+                ("PV", Input, Int),
+                ("QU", Output, Bool),
+                ("QD", Output, Bool),
+                ("CV", Output, Int),
+                ("MU", Local, Bool),
+                ("MD", Local, Bool),
+            ],
+            StandardBlock::RTrig | StandardBlock::FTrig => &[
+                ("CLK", Input, Bool),
+                ("Q", Output, Bool),
+                ("M", Local, Bool),
+            ],
+            StandardBlock::Sr => &[
+                ("S1", Input, Bool),
+                ("R", Input, Bool),
+                ("Q1", Output, Bool),
+            ],
+            StandardBlock::Rs => &[
+                ("S", Input, Bool),
+                ("R1", Input, Bool),
+                ("Q1", Output, Bool),
+            ],
+        }
+    }
+
+    /// The standard function block a symbol declares, if it is one.
+    pub fn of(table: &SymbolTable, id: SymbolId) -> Option<Self> {
+        let symbol = table.symbol(id);
+        match symbol.kind == SymbolKind::FunctionBlock && symbol.scope == SymbolTable::GLOBAL {
+            true => StandardBlock::from_name(&symbol.name),
+            false => None,
+        }
+    }
+}
+
+/// A symbol table holding the standard function blocks, which name resolution starts from.
+pub fn standard_table() -> SymbolTable {
+    let mut table = SymbolTable::new();
+    let named = |name: &str| {
+        DataType::Named(QualifiedName {
+            parts: vec![Identifier {
+                name: name.to_string(),
+                span: Span::default(),
+            }],
+        })
+    };
+
+    for (name, block) in BLOCK_NAMES {
+        let Ok(id) = table.declare(
+            SymbolTable::GLOBAL,
+            name,
+            SymbolKind::FunctionBlock,
+            Span::default(),
+            None,
+        ) else {
+            continue;
+        };
+        let members = table.add_scope(ScopeKind::Pou, Some(SymbolTable::GLOBAL), Some(id));
+        table.set_members(id, members);
+        for (variable, kind, ty) in block.variables() {
+            let kind = SymbolKind::Variable {
+                kind: *kind,
+                constant: false,
+            };
+            let _ = table.declare(
+                members,
+                variable,
+                kind,
+                Span::default(),
+                Some(named(ty.name())),
+            );
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DINT: Type = Type::Elementary(Elementary::Dint);
+    const WORD: Type = Type::Elementary(Elementary::Word);
+
+    #[test]
+    fn test_lookup_and_generic_typing() {
+        assert_eq!(lookup("max"), Some(Function::Max));
+        assert_eq!(
+            lookup("Int_To_String"),
+            Some(Function::Convert(
+                Scalar::Elementary(Elementary::Int),
+                Scalar::String { wide: false }
+            ))
+        );
+        assert_eq!(lookup("INT_TO_FOO"), None);
+        assert_eq!(lookup("MAXIMUM"), None);
+
+        assert_eq!(
+            Function::Max.result_type(&[INT, DINT, Type::IntegerLiteral]),
+            Ok(DINT)
+        );
+        assert_eq!(
+            Function::Sqrt.result_type(&[Type::IntegerLiteral]),
+            Ok(Type::RealLiteral)
+        );
+        assert_eq!(Function::Shl.result_type(&[WORD, INT]), Ok(WORD));
+        assert_eq!(Function::Len.result_type(&[STRING]), Ok(INT));
+        assert_eq!(
+            Function::Sel.result_type(&[Type::Elementary(Elementary::Bool), INT, DINT]),
+            Ok(DINT)
+        );
+
+        assert_eq!(
+            Function::Max.result_type(&[INT]),
+            Err(CallError::Count("at least 2"))
+        );
+        assert_eq!(
+            Function::Shl.result_type(&[INT, INT]),
+            Err(CallError::Argument {
+                index: 0,
+                expected: "ANY_BIT"
+            })
+        );
+        assert_eq!(
+            Function::Min.result_type(&[INT, STRING]),
+            Err(CallError::Incompatible)
+        );
+    }
+
+    #[test]
+    fn test_kernels() {
+        assert_eq!(shift(Function::Shl, 0x81, 1, 8), 0x02);
+        assert_eq!(shift(Function::Rol, 0x81, 1, 8), 0x03);
+        assert_eq!(shift(Function::Ror, 0x81, 1, 8), 0xC0);
+        assert_eq!(shift(Function::Shr, 0x80, 9, 8), 0);
+
+        assert_eq!(edit_string(Function::Left, "Hello", "", 2, 0), "He");
+        assert_eq!(edit_string(Function::Right, "Hello", "", 10, 0), "Hello");
+        assert_eq!(edit_string(Function::Mid, "Hello", "", 3, 2), "ell");
+        assert_eq!(
+            edit_string(Function::Insert, "Hello", "XY", 0, 2),
+            "HeXYllo"
+        );
+        assert_eq!(edit_string(Function::Delete, "Hello", "", 2, 2), "Hlo");
+        assert_eq!(
+            edit_string(Function::Replace, "Hello", "ipp", 3, 2),
+            "Hippo"
+        );
+        assert_eq!(find("Hello", "llo"), 3);
+        assert_eq!(find("Hello", "x"), 0);
+        assert_eq!(parse_int(" 1_000 "), 1000);
+        assert_eq!(format_time(1_500 * MILLISECOND), "T#1500ms");
+        assert_eq!(format_time(500_000), "T#500us");
+        assert_eq!(format_time(-250), "T#-250ns");
+    }
+}
@@ -0,0 +1,1185 @@
+use crate::{
+    diagnostic::Diagnostic,
+    parsing::ast::{
+        Action, Argument, Ast, Block, CaseLabel, Configuration, DataType, Expression, Identifier,
+        Pou, QualifiedName, Resource, Statement, Statements, TypeDeclaration, VariableBlock,
+        VariableDeclaration, VariableKind,
+    },
+    semantic::{
+        library,
+        symbols::{ScopeId, ScopeKind, SymbolId, SymbolKind, SymbolTable, is_elementary_type},
+    },
+};
+
+/// Builds the scopes of the program and binds every identifier to its declaration.
+///
+/// The standard function blocks like TON and CTU are declared before the program.
+pub fn resolve(ast: &Ast) -> (SymbolTable, Vec<Diagnostic>) {
+    resolve_with(ast, library::standard_table())
+}
+
+/// Like `resolve` but starts from a table that already holds built-in declarations.
+pub fn resolve_with(ast: &Ast, table: SymbolTable) -> (SymbolTable, Vec<Diagnostic>) {
+    let mut resolver = Resolver {
+        table,
+        diagnostics: Vec::new(),
+        pending: Vec::new(),
+        detached_actions: Vec::new(),
+    };
+
+    resolver.declare_blocks(&ast.blocks, SymbolTable::GLOBAL);
+    resolver.declare_detached_actions();
+    resolver.resolve_pending();
+
+    (resolver.table, resolver.diagnostics)
+}
+
+/// Bodies are resolved once all declarations are known, so that forward references work.
+enum Pending<'a> {
+    Pou(ScopeId, &'a Pou),
+    Action(ScopeId, &'a Action),
+    Variables(ScopeId, &'a VariableBlock),
+    Type(ScopeId, &'a TypeDeclaration),
+    /// The scope of a resource and the scope enclosing its configuration
+    Resource(ScopeId, ScopeId, &'a Resource),
+}
+
+/// What an expression evaluates to, as far as name resolution is concerned.
+#[derive(Clone)]
+enum Value {
+    Unknown,
+    Typed(DataType, ScopeId),
+    Symbol(SymbolId),
+}
+
+const MAX_ALIAS_DEPTH: usize = 16;
+
+struct Resolver<'a> {
+    table: SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+    pending: Vec<Pending<'a>>,
+    detached_actions: Vec<(ScopeId, &'a Action)>,
+}
+
+impl<'a> Resolver<'a> {
+    fn declare(
+        &mut self,
+        scope: ScopeId,
+        name: &Identifier,
+        kind: SymbolKind,
+        data_type: Option<DataType>,
+    ) -> Option<SymbolId> {
+        match self
+            .table
+            .declare(scope, &name.name, kind, name.span, data_type)
+        {
+            Ok(id) => Some(id),
+            Err(existing) => {
+                let first = self.table.symbol(existing).span;
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        name.span,
+                        format!("'{}' is already declared in this scope.", name.name),
+                    )
+                    .with_note(first, format!("First declaration of '{}'.", name.name)),
+                );
+                None
+            }
+        }
+    }
+
+    fn declare_blocks(&mut self, blocks: &'a [Block], scope: ScopeId) {
+        for block in blocks {
+            match block {
+                Block::Program(pou) => self.declare_pou(scope, pou, SymbolKind::Program),
+                Block::Function(pou) => self.declare_pou(scope, pou, SymbolKind::Function),
+                Block::FunctionBlock(pou) => {
+                    self.declare_pou(scope, pou, SymbolKind::FunctionBlock)
+                }
+                Block::Action(action) => self.detached_actions.push((scope, action)),
+                Block::Type(types) => {
+                    for declaration in types {
+                        self.declare_type(scope, declaration);
+                    }
+                }
+                Block::GlobalVariables(variables) => {
+                    self.declare_variables(scope, variables);
+                    self.pending.push(Pending::Variables(scope, variables));
+                }
+                Block::Namespace(namespace) => {
+                    let mut ns_scope = scope;
+                    for part in &namespace.name.parts {
+                        ns_scope = self.declare_namespace(ns_scope, part);
+                    }
+                    self.declare_blocks(&namespace.blocks, ns_scope);
+                }
+                Block::Configuration(configuration) => {
+                    self.declare_configuration(scope, configuration)
+                }
+            }
+        }
+    }
+
+    /// The global variables of a configuration and its resources are visible to all programs,
+    /// tasks and program instances only within their resource.
+    fn declare_configuration(&mut self, scope: ScopeId, configuration: &'a Configuration) {
+        let symbol = self.declare(scope, &configuration.name, SymbolKind::Configuration, None);
+        let members = self
+            .table
+            .add_scope(ScopeKind::Configuration, Some(scope), symbol);
+        if let Some(symbol) = symbol {
+            self.table.set_members(symbol, members);
+        }
+        for variables in &configuration.variables {
+            self.declare_variables(scope, variables);
+            self.pending.push(Pending::Variables(scope, variables));
+        }
+
+        for resource in &configuration.resources {
+            let symbol = self.declare(members, &resource.name, SymbolKind::Resource, None);
+            let resource_scope =
+                self.table
+                    .add_scope(ScopeKind::Configuration, Some(members), symbol);
+            if let Some(symbol) = symbol {
+                self.table.set_members(symbol, resource_scope);
+            }
+            for variables in &resource.variables {
+                self.declare_variables(scope, variables);
+                self.pending.push(Pending::Variables(scope, variables));
+            }
+            for task in &resource.tasks {
+                self.declare(resource_scope, &task.name, SymbolKind::Task, None);
+            }
+            for instance in &resource.programs {
+                self.declare(
+                    resource_scope,
+                    &instance.name,
+                    SymbolKind::ProgramInstance,
+                    None,
+                );
+            }
+            self.pending
+                .push(Pending::Resource(resource_scope, scope, resource));
+        }
+    }
+
+    /// Namespaces may be reopened, so an existing namespace of the same name is reused.
+    fn declare_namespace(&mut self, scope: ScopeId, name: &Identifier) -> ScopeId {
+        if let Some(existing) = self.table.lookup_local(scope, &name.name)
+            && let symbol = self.table.symbol(existing)
+            && symbol.kind == SymbolKind::Namespace
+            && let Some(members) = symbol.members
+        {
+            return members;
+        }
+
+        let symbol = self.declare(scope, name, SymbolKind::Namespace, None);
+        let members = self
+            .table
+            .add_scope(ScopeKind::Namespace, Some(scope), symbol);
+        if let Some(symbol) = symbol {
+            self.table.set_members(symbol, members);
+        }
+        members
+    }
+
+    fn declare_pou(&mut self, scope: ScopeId, pou: &'a Pou, kind: SymbolKind) {
+        let symbol = self.declare(scope, &pou.name, kind, pou.return_type.clone());
+        let scope_kind = match kind {
+            SymbolKind::Method => ScopeKind::Method,
+            _ => ScopeKind::Pou,
+        };
+        let pou_scope = self.table.add_scope(scope_kind, Some(scope), symbol);
+        if let Some(symbol) = symbol {
+            self.table.set_members(symbol, pou_scope);
+        }
+
+        for variables in &pou.variables {
+            self.declare_variables(pou_scope, variables);
+        }
+        for method in &pou.methods {
+            self.declare_pou(pou_scope, method, SymbolKind::Method);
+        }
+        for action in &pou.actions {
+            self.declare_action(pou_scope, action);
+        }
+
+        self.pending.push(Pending::Pou(pou_scope, pou));
+    }
+
+    fn declare_action(&mut self, pou_scope: ScopeId, action: &'a Action) {
+        let symbol = self.declare(pou_scope, &action.name, SymbolKind::Action, None);
+        let action_scope = self
+            .table
+            .add_scope(ScopeKind::Action, Some(pou_scope), symbol);
+        if let Some(symbol) = symbol {
+            self.table.set_members(symbol, action_scope);
+        }
+
+        self.pending.push(Pending::Action(action_scope, action));
+    }
+
+    /// Top level actions name their owner, which may be declared anywhere.
+    fn declare_detached_actions(&mut self) {
+        for (scope, action) in std::mem::take(&mut self.detached_actions) {
+            let Some(owner) = &action.owner else {
+                self.diagnostics.push(Diagnostic::error(
+                    action.name.span,
+                    format!(
+                        "Action '{}' must belong to a PROGRAM or FUNCTION_BLOCK. Try naming it like Owner.{}.",
+                        action.name.name, action.name.name
+                    ),
+                ));
+                continue;
+            };
+
+            let Some(owner_id) = self.resolve_identifier_in(scope, owner) else {
+                continue;
+            };
+            let symbol = self.table.symbol(owner_id);
+            match (symbol.kind, symbol.members) {
+                (SymbolKind::Program | SymbolKind::FunctionBlock, Some(members)) => {
+                    self.declare_action(members, action)
+                }
+                _ => self.diagnostics.push(Diagnostic::error(
+                    owner.span,
+                    format!(
+                        "'{}' is neither a PROGRAM nor a FUNCTION_BLOCK and cannot own actions.",
+                        owner.name
+                    ),
+                )),
+            }
+        }
+    }
+
+    fn declare_type(&mut self, scope: ScopeId, declaration: &'a TypeDeclaration) {
+        let symbol = self.declare(
+            scope,
+            &declaration.name,
+            SymbolKind::Type,
+            Some(declaration.data_type.clone()),
+        );
+
+        if let Some(members) = self.declare_type_members(scope, symbol, &declaration.data_type)
+            && let Some(symbol) = symbol
+        {
+            self.table.set_members(symbol, members);
+        }
+
+        self.pending.push(Pending::Type(scope, declaration));
+    }
+
+    /// Creates the member scope of structures, unions and enums.
+    fn declare_type_members(
+        &mut self,
+        scope: ScopeId,
+        owner: Option<SymbolId>,
+        data_type: &DataType,
+    ) -> Option<ScopeId> {
+        match data_type {
+            DataType::Struct(members, _) | DataType::Union(members, _) => {
+                let members_scope = self.table.add_scope(ScopeKind::Struct, Some(scope), owner);
+                for member in members {
+                    self.declare_declaration(members_scope, member, VariableKind::Local, false);
+                }
+                Some(members_scope)
+            }
+            DataType::Enum { values, .. } => {
+                let members_scope = self.table.add_scope(ScopeKind::Enum, Some(scope), owner);
+                for value in values {
+                    let symbol =
+                        self.declare(members_scope, &value.name, SymbolKind::EnumValue, None);
+                    if let (Some(symbol), Some(v)) = (symbol, &value.value) {
+                        self.table.set_value(symbol, v.clone());
+                    }
+                }
+                Some(members_scope)
+            }
+            _ => None,
+        }
+    }
+
+    fn declare_variables(&mut self, scope: ScopeId, variables: &'a VariableBlock) {
+        for declaration in &variables.declarations {
+            self.declare_declaration(scope, declaration, variables.kind, variables.constant);
+        }
+    }
+
+    fn declare_declaration(
+        &mut self,
+        scope: ScopeId,
+        declaration: &VariableDeclaration,
+        kind: VariableKind,
+        constant: bool,
+    ) {
+        // Inline enums make their values known like a named enum does
+        if let DataType::Enum { .. } = declaration.data_type {
+            self.declare_type_members(scope, None, &declaration.data_type);
+        }
+
+        for name in &declaration.names {
+            let symbol = self.declare(
+                scope,
+                name,
+                SymbolKind::Variable { kind, constant },
+                Some(declaration.data_type.clone()),
+            );
+            if let (Some(symbol), Some(initializer)) = (symbol, &declaration.initializer) {
+                self.table.set_value(symbol, initializer.clone());
+            }
+        }
+    }
+
+    fn resolve_pending(&mut self) {
+        for pending in std::mem::take(&mut self.pending) {
+            match pending {
+                Pending::Pou(scope, pou) => {
+                    if let Some(return_type) = &pou.return_type {
+                        self.resolve_data_type(scope, return_type);
+                    }
+                    for variables in &pou.variables {
+                        self.resolve_variables(scope, variables);
+                    }
+                    self.resolve_statements(scope, &pou.statements);
+                }
+                Pending::Action(scope, action) => {
+                    self.resolve_statements(scope, &action.statements)
+                }
+                Pending::Variables(scope, variables) => self.resolve_variables(scope, variables),
+                Pending::Type(scope, declaration) => {
+                    self.resolve_data_type(scope, &declaration.data_type);
+                    if let Some(initializer) = &declaration.initializer {
+                        let value = Value::Typed(declaration.data_type.clone(), scope);
+                        self.resolve_initializer(scope, initializer, value);
+                    }
+                }
+                Pending::Resource(scope, outer, resource) => {
+                    self.resolve_resource(scope, outer, resource)
+                }
+            }
+        }
+    }
+
+    /// Programs are looked up outside of the configuration, as instances often share their names.
+    fn resolve_resource(&mut self, scope: ScopeId, outer: ScopeId, resource: &Resource) {
+        for task in &resource.tasks {
+            for value in [&task.interval, &task.single, &task.priority]
+                .into_iter()
+                .flatten()
+            {
+                self.resolve_expression(scope, value);
+            }
+        }
+
+        for instance in &resource.programs {
+            if let Some(task) = &instance.task {
+                match self.table.lookup_local(scope, &task.name) {
+                    Some(id) if self.table.symbol(id).kind == SymbolKind::Task => {
+                        self.table.add_reference(task.span, id)
+                    }
+                    _ => {
+                        let message = format!("'{}' is not a TASK of this resource.", task.name);
+                        self.diagnostics.push(Diagnostic::error(task.span, message));
+                    }
+                }
+            }
+
+            let program = self.resolve_qualified(outer, &instance.program);
+            let parameters = match program.map(|id| self.table.symbol(id)) {
+                Some(symbol) if symbol.kind == SymbolKind::Program => symbol.members,
+                Some(_) => {
+                    let message = format!(
+                        "'{}' is not a PROGRAM and cannot be instantiated.",
+                        instance.program.to_printable()
+                    );
+                    let span = instance.program.span();
+                    self.diagnostics.push(Diagnostic::error(span, message));
+                    None
+                }
+                None => None,
+            };
+            self.resolve_arguments(scope, parameters, &instance.arguments);
+        }
+    }
+
+    fn resolve_variables(&mut self, scope: ScopeId, variables: &VariableBlock) {
+        for declaration in &variables.declarations {
+            self.resolve_data_type(scope, &declaration.data_type);
+            if let Some(initializer) = &declaration.initializer {
+                let value = Value::Typed(declaration.data_type.clone(), scope);
+                self.resolve_initializer(scope, initializer, value);
+            }
+
+            if variables.kind == VariableKind::External {
+                for name in &declaration.names {
+                    self.check_external(scope, name);
+                }
+            }
+        }
+    }
+
+    fn check_external(&mut self, scope: ScopeId, name: &Identifier) {
+        let global = self
+            .table
+            .scope(scope)
+            .parent
+            .and_then(|parent| self.table.lookup(parent, &name.name))
+            .filter(|id| {
+                matches!(
+                    self.table.symbol(*id).kind,
+                    SymbolKind::Variable {
+                        kind: VariableKind::Global,
+                        ..
+                    }
+                )
+            });
+
+        match global {
+            Some(global) => self.table.add_reference(name.span, global),
+            None => self.diagnostics.push(Diagnostic::error(
+                name.span,
+                format!("There is no global variable '{}' to refer to.", name.name),
+            )),
+        }
+    }
+
+    /// Structure initializers name the members of the initialized type.
+    fn resolve_initializer(&mut self, scope: ScopeId, initializer: &Expression, target: Value) {
+        match initializer {
+            Expression::Struct(members, _) => {
+                for (name, value) in members {
+                    let member = self.resolve_member(target.clone(), name);
+                    self.resolve_initializer(scope, value, member);
+                }
+            }
+            Expression::Array(items, _) => {
+                let element = self.element_of(target);
+                for item in items {
+                    self.resolve_initializer(scope, item, element.clone());
+                }
+            }
+            _ => {
+                self.resolve_expression(scope, initializer);
+            }
+        }
+    }
+
+    fn resolve_data_type(&mut self, scope: ScopeId, data_type: &DataType) {
+        match data_type {
+            DataType::Named(name) => {
+                self.resolve_type_name(scope, name);
+            }
+            DataType::String { length, .. } => {
+                if let Some(length) = length {
+                    self.resolve_expression(scope, length);
+                }
+            }
+            DataType::Array {
+                ranges, element, ..
+            } => {
+                for range in ranges {
+                    self.resolve_expression(scope, &range.lower);
+                    self.resolve_expression(scope, &range.upper);
+                }
+                self.resolve_data_type(scope, element);
+            }
+            DataType::Pointer(target, _) | DataType::Reference(target, _) => {
+                self.resolve_data_type(scope, target)
+            }
+            DataType::Subrange { base, range } => {
+                self.resolve_type_name(scope, base);
+                self.resolve_expression(scope, &range.lower);
+                self.resolve_expression(scope, &range.upper);
+            }
+            DataType::Enum { base, values, .. } => {
+                if let Some(base) = base {
+                    self.resolve_type_name(scope, base);
+                }
+                for value in values {
+                    if let Some(value) = &value.value {
+                        self.resolve_expression(scope, value);
+                    }
+                }
+            }
+            DataType::Struct(members, _) | DataType::Union(members, _) => {
+                for member in members {
+                    self.resolve_data_type(scope, &member.data_type);
+                    if let Some(initializer) = &member.initializer {
+                        self.resolve_expression(scope, initializer);
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve_type_name(&mut self, scope: ScopeId, name: &QualifiedName) -> Option<SymbolId> {
+        if name.parts.len() == 1 && is_elementary_type(&name.parts[0].name) {
+            return None;
+        }
+
+        let id = self.resolve_qualified(scope, name)?;
+        match self.table.symbol(id).kind {
+            SymbolKind::Type | SymbolKind::FunctionBlock => Some(id),
+            _ => {
+                self.diagnostics.push(Diagnostic::error(
+                    name.span(),
+                    format!("'{}' is not a data type.", name.to_printable()),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Resolves `A.B.C` by looking up `A` and walking the members of each part.
+    fn resolve_qualified(&mut self, scope: ScopeId, name: &QualifiedName) -> Option<SymbolId> {
+        let mut id = self.resolve_identifier_in(scope, &name.parts[0])?;
+        for part in &name.parts[1..] {
+            let owner = self.table.symbol(id);
+            let found = owner
+                .members
+                .and_then(|members| self.table.lookup_local(members, &part.name));
+            match found {
+                Some(member) => {
+                    self.table.add_reference(part.span, member);
+                    id = member;
+                }
+                None => {
+                    let message = format!("'{}' is not a member of '{}'.", part.name, owner.name);
+                    self.diagnostics.push(Diagnostic::error(part.span, message));
+                    return None;
+                }
+            }
+        }
+
+        Some(id)
+    }
+
+    fn resolve_identifier_in(&mut self, scope: ScopeId, name: &Identifier) -> Option<SymbolId> {
+        if let Some(id) = self.table.lookup(scope, &name.name) {
+            self.table.add_reference(name.span, id);
+            return Some(id);
+        }
+
+        // Enum values may be used without their type as long as they are unambiguous
+        match self.table.lookup_enum_values(&name.name)[..] {
+            [id] => {
+                self.table.add_reference(name.span, id);
+                Some(id)
+            }
+            [] => {
+                self.diagnostics.push(Diagnostic::error(
+                    name.span,
+                    format!("Undeclared identifier '{}'.", name.name),
+                ));
+                None
+            }
+            [..] => {
+                self.diagnostics.push(Diagnostic::error(
+                    name.span,
+                    format!(
+                        "'{}' is ambiguous between several enums. Qualify it like Type#{}.",
+                        name.name, name.name
+                    ),
+                ));
+                None
+            }
+        }
+    }
+
+    fn resolve_statements(&mut self, scope: ScopeId, statements: &Statements) {
+        for statement in statements {
+            self.resolve_statement(scope, statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, scope: ScopeId, statement: &Statement) {
+        match statement {
+            Statement::Empty(_)
+            | Statement::Return(_)
+            | Statement::Exit(_)
+            | Statement::Continue(_) => {}
+            Statement::Expression(expression) => {
+                self.resolve_expression(scope, expression);
+            }
+            Statement::Assignment(assignment) => {
+                self.resolve_expression(scope, &assignment.target);
+                self.resolve_expression(scope, &assignment.value);
+            }
+            Statement::If(condition) => {
+                for branch in std::iter::once(&condition.branch).chain(&condition.alt_branches) {
+                    self.resolve_expression(scope, &branch.condition);
+                    self.resolve_statements(scope, &branch.statements);
+                }
+                if let Some(fallback) = &condition.fallback {
+                    self.resolve_statements(scope, fallback);
+                }
+            }
+            Statement::Case(case) => {
+                self.resolve_expression(scope, &case.selector);
+                for branch in &case.branches {
+                    for label in &branch.labels {
+                        match label {
+                            CaseLabel::Value(value) => {
+                                self.resolve_expression(scope, value);
+                            }
+                            CaseLabel::Range(range) => {
+                                self.resolve_expression(scope, &range.lower);
+                                self.resolve_expression(scope, &range.upper);
+                            }
+                        }
+                    }
+                    self.resolve_statements(scope, &branch.statements);
+                }
+                if let Some(fallback) = &case.fallback {
+                    self.resolve_statements(scope, fallback);
+                }
+            }
+            Statement::For(for_loop) => {
+                self.resolve_identifier_in(scope, &for_loop.variable);
+                self.resolve_expression(scope, &for_loop.start);
+                self.resolve_expression(scope, &for_loop.end);
+                if let Some(step) = &for_loop.step {
+                    self.resolve_expression(scope, step);
+                }
+
+                let loop_scope = self.table.add_scope(ScopeKind::ForLoop, Some(scope), None);
+                self.resolve_statements(loop_scope, &for_loop.statements);
+            }
+            Statement::While(while_loop) => {
+                self.resolve_expression(scope, &while_loop.condition);
+                self.resolve_statements(scope, &while_loop.statements);
+            }
+            Statement::Repeat(repeat_loop) => {
+                self.resolve_statements(scope, &repeat_loop.statements);
+                self.resolve_expression(scope, &repeat_loop.condition);
+            }
+        }
+    }
+
+    /// Binds the names of named arguments to the parameters of the called POU.
+    fn resolve_arguments(
+        &mut self,
+        scope: ScopeId,
+        parameters: Option<ScopeId>,
+        arguments: &[Argument],
+    ) {
+        for argument in arguments {
+            if let (Argument::Named(name, _) | Argument::Output(name, _), Some(p)) =
+                (argument, parameters)
+            {
+                match self.table.lookup_local(p, &name.name) {
+                    Some(id) => self.table.add_reference(name.span, id),
+                    None => self.diagnostics.push(Diagnostic::error(
+                        name.span,
+                        format!("'{}' is not a parameter of the called POU.", name.name),
+                    )),
+                }
+            }
+            self.resolve_expression(scope, argument.value());
+        }
+    }
+
+    fn resolve_expression(&mut self, scope: ScopeId, expression: &Expression) -> Value {
+        match expression {
+            Expression::Literal(..) => Value::Unknown,
+            Expression::Identifier(name) => match self.resolve_identifier_in(scope, name) {
+                Some(id) if self.is_return_variable(scope, id) => {
+                    let symbol = self.table.symbol(id);
+                    match &symbol.data_type {
+                        Some(t) => Value::Typed(t.clone(), symbol.scope),
+                        None => Value::Unknown,
+                    }
+                }
+                Some(id) => self.value_of(id),
+                None => Value::Unknown,
+            },
+            Expression::Prefix(prefix) => {
+                self.resolve_expression(scope, &prefix.operand);
+                Value::Unknown
+            }
+            Expression::Infix(infix) => {
+                self.resolve_expression(scope, &infix.left);
+                self.resolve_expression(scope, &infix.right);
+                Value::Unknown
+            }
+            Expression::Member(member) => {
+                let target = self.resolve_expression(scope, &member.target);
+                self.resolve_member(target, &member.member)
+            }
+            Expression::Index(index) => {
+                let target = self.resolve_expression(scope, &index.target);
+                for i in &index.indices {
+                    self.resolve_expression(scope, i);
+                }
+                self.element_of(target)
+            }
+            Expression::Deref(target, _) => {
+                let target = self.resolve_expression(scope, target);
+                match self.definition_of(target) {
+                    Some((DataType::Pointer(t, _) | DataType::Reference(t, _), s)) => {
+                        Value::Typed(*t, s)
+                    }
+                    _ => Value::Unknown,
+                }
+            }
+            Expression::Call(call) => {
+                let callee = match call.callee.as_ref() {
+                    // Standard functions are known without a declaration
+                    Expression::Identifier(name)
+                        if self.table.lookup(scope, &name.name).is_none()
+                            && library::lookup(&name.name).is_some() =>
+                    {
+                        Value::Unknown
+                    }
+                    callee => self.resolve_expression(scope, callee),
+                };
+                let (parameters, result) = match &callee {
+                    Value::Symbol(id) => {
+                        let symbol = self.table.symbol(*id);
+                        let result = match (symbol.kind, &symbol.data_type) {
+                            (SymbolKind::Function | SymbolKind::Method, Some(t)) => {
+                                Value::Typed(t.clone(), symbol.scope)
+                            }
+                            _ => Value::Unknown,
+                        };
+                        (symbol.members, result)
+                    }
+                    Value::Typed(..) => (self.members_of(callee.clone()), Value::Unknown),
+                    Value::Unknown => (None, Value::Unknown),
+                };
+
+                self.resolve_arguments(scope, parameters, &call.arguments);
+                result
+            }
+            Expression::TypedLiteral(literal) => {
+                let type_name = &literal.type_name;
+                if type_name.parts.len() == 1 && is_elementary_type(&type_name.parts[0].name) {
+                    self.resolve_expression(scope, &literal.value);
+                    return Value::Unknown;
+                }
+
+                let Some(id) = self.resolve_qualified(scope, type_name) else {
+                    return Value::Unknown;
+                };
+                let members = self
+                    .table
+                    .symbol(id)
+                    .members
+                    .filter(|m| self.table.scope(*m).kind == ScopeKind::Enum);
+                match (members, literal.value.as_ref()) {
+                    (Some(members), Expression::Identifier(value)) => {
+                        match self.table.lookup_local(members, &value.name) {
+                            Some(v) => self.table.add_reference(value.span, v),
+                            None => self.diagnostics.push(Diagnostic::error(
+                                value.span,
+                                format!(
+                                    "'{}' is not a value of enum '{}'.",
+                                    value.name,
+                                    type_name.to_printable()
+                                ),
+                            )),
+                        }
+                    }
+                    _ => self.diagnostics.push(Diagnostic::error(
+                        type_name.span(),
+                        format!(
+                            "'{}' is neither an elementary type nor an enum and cannot prefix a literal.",
+                            type_name.to_printable()
+                        ),
+                    )),
+                }
+                Value::Unknown
+            }
+            Expression::Array(items, _) => {
+                for item in items {
+                    self.resolve_expression(scope, item);
+                }
+                Value::Unknown
+            }
+            Expression::Struct(members, _) => {
+                for (_, value) in members {
+                    self.resolve_expression(scope, value);
+                }
+                Value::Unknown
+            }
+        }
+    }
+
+    fn resolve_member(&mut self, target: Value, member: &Identifier) -> Value {
+        let owner_name = match &target {
+            Value::Unknown => return Value::Unknown,
+            Value::Symbol(id) => self.table.symbol(*id).name.clone(),
+            Value::Typed(t, _) => match t {
+                DataType::Named(n) => n.to_printable(),
+                _ => "the value".to_string(),
+            },
+        };
+
+        let Some(members) = self.members_of(target.clone()) else {
+            // Values of unresolvable types are already reported at their declaration
+            if matches!(target, Value::Typed(..)) && self.definition_of(target).is_none() {
+                return Value::Unknown;
+            }
+
+            self.diagnostics.push(Diagnostic::error(
+                member.span,
+                format!(
+                    "'{owner_name}' has no members, so '{}' cannot be accessed.",
+                    member.name
+                ),
+            ));
+            return Value::Unknown;
+        };
+
+        match self.table.lookup_local(members, &member.name) {
+            Some(id) => {
+                self.table.add_reference(member.span, id);
+                self.value_of(id)
+            }
+            None => {
+                self.diagnostics.push(Diagnostic::error(
+                    member.span,
+                    format!("'{}' is not a member of '{owner_name}'.", member.name),
+                ));
+                Value::Unknown
+            }
+        }
+    }
+
+    /// Inside a function or method its own name denotes the return value.
+    fn is_return_variable(&self, scope: ScopeId, id: SymbolId) -> bool {
+        let symbol = self.table.symbol(id);
+        if !matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method) {
+            return false;
+        }
+
+        let mut cur = Some(scope);
+        while let Some(s) = cur {
+            if Some(s) == symbol.members {
+                return true;
+            }
+            cur = self.table.scope(s).parent;
+        }
+        false
+    }
+
+    fn value_of(&self, id: SymbolId) -> Value {
+        let symbol = self.table.symbol(id);
+        match (symbol.kind, &symbol.data_type) {
+            (SymbolKind::Variable { .. }, Some(t)) => Value::Typed(t.clone(), symbol.scope),
+            (SymbolKind::Variable { .. } | SymbolKind::EnumValue, _) => Value::Unknown,
+            _ => Value::Symbol(id),
+        }
+    }
+
+    /// Member scope of namespaces, POUs and values of structured or function block types.
+    fn members_of(&self, value: Value) -> Option<ScopeId> {
+        match value {
+            Value::Unknown => None,
+            Value::Symbol(id) => self.table.symbol(id).members,
+            Value::Typed(data_type, scope) => {
+                let id = self.type_symbol_of(data_type, scope)?;
+                let members = self.table.symbol(id).members?;
+                match self.table.scope(members).kind {
+                    ScopeKind::Enum => None,
+                    _ => Some(members),
+                }
+            }
+        }
+    }
+
+    /// The declaration of a named type with members, looking through aliases and references.
+    fn type_symbol_of(&self, mut data_type: DataType, mut scope: ScopeId) -> Option<SymbolId> {
+        for _ in 0..MAX_ALIAS_DEPTH {
+            match data_type {
+                DataType::Reference(target, _) => data_type = *target,
+                DataType::Named(name) => {
+                    let id = self.find_qualified(scope, &name)?;
+                    let symbol = self.table.symbol(id);
+                    match (symbol.kind, symbol.members, &symbol.data_type) {
+                        (_, Some(_), _) => return Some(id),
+                        (SymbolKind::Type, None, Some(t)) => {
+                            data_type = t.clone();
+                            scope = symbol.scope;
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        None
+    }
+
+    fn element_of(&self, value: Value) -> Value {
+        match self.definition_of(value) {
+            Some((DataType::Array { element, .. }, s)) => Value::Typed(*element, s),
+            _ => Value::Unknown,
+        }
+    }
+
+    /// Expands named type aliases down to the defining data type.
+    fn definition_of(&self, value: Value) -> Option<(DataType, ScopeId)> {
+        let Value::Typed(mut data_type, mut scope) = value else {
+            return None;
+        };
+
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let DataType::Named(name) = &data_type else {
+                return Some((data_type, scope));
+            };
+            if name.parts.len() == 1 && is_elementary_type(&name.parts[0].name) {
+                return Some((data_type, scope));
+            }
+
+            let id = self.find_qualified(scope, name)?;
+            let symbol = self.table.symbol(id);
+            match (symbol.kind, &symbol.data_type) {
+                (SymbolKind::Type, Some(t)) => {
+                    data_type = t.clone();
+                    scope = symbol.scope;
+                }
+                _ => return Some((data_type, scope)),
+            }
+        }
+
+        None
+    }
+
+    /// Looks up a qualified name without reporting anything.
+    fn find_qualified(&self, scope: ScopeId, name: &QualifiedName) -> Option<SymbolId> {
+        let mut id = self.table.lookup(scope, &name.parts[0].name)?;
+        for part in &name.parts[1..] {
+            let members = self.table.symbol(id).members?;
+            id = self.table.lookup_local(members, &part.name)?;
+        }
+
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::{lexer::Lexer, parser::parse};
+
+    fn resolve_src(src: &str) -> (SymbolTable, Vec<Diagnostic>) {
+        let ast = parse(Lexer::create("main.st", src)).unwrap();
+        resolve(&ast)
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| d.message.clone()).collect()
+    }
+
+    /// Qualified names of the symbols referenced at every occurrence of `needle` in `src`.
+    fn bindings(src: &str, table: &SymbolTable, needle: &str) -> Vec<String> {
+        src.match_indices(needle)
+            .filter_map(|(pos, _)| {
+                table
+                    .references
+                    .iter()
+                    .find(|r| r.span.pos == pos)
+                    .map(|r| table.qualified_name(r.symbol))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_binds_locals_globals_and_calls() {
+        let src = r#"
+        VAR_GLOBAL counter : INT; END_VAR
+
+        FUNCTION Twice : INT
+            VAR_INPUT x : INT; END_VAR
+            Twice := x * 2;
+        END_FUNCTION
+
+        PROGRAM Main
+            VAR value : INT; END_VAR
+            value := Twice(x := counter);
+        END_PROGRAM
+        "#;
+        let (table, diagnostics) = resolve_src(src);
+
+        assert_eq!(messages(&diagnostics), Vec::<String>::new());
+        assert_eq!(bindings(src, &table, "counter)"), vec!["counter"]);
+        assert_eq!(bindings(src, &table, "x :="), vec!["Twice.x"]);
+        assert_eq!(bindings(src, &table, "x * 2"), vec!["Twice.x"]);
+        assert_eq!(bindings(src, &table, "Twice :="), vec!["Twice"]);
+        assert_eq!(bindings(src, &table, "value :="), vec!["Main.value"]);
+    }
+
+    #[test]
+    fn test_reports_undeclared_and_duplicates() {
+        let (_, diagnostics) = resolve_src(
+            r#"
+        PROGRAM Main
+            VAR a : INT; A : BOOL; END_VAR
+            b := a;
+        END_PROGRAM
+        "#,
+        );
+
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "'A' is already declared in this scope.",
+                "Undeclared identifier 'b'.",
+            ]
+        );
+        assert_eq!(diagnostics[0].notes.len(), 1);
+    }
+
+    #[test]
+    fn test_resolves_enum_values() {
+        let src = r#"
+        TYPE
+            Color : (Red, Green, Blue);
+            Light : (Off, Red);
+        END_TYPE
+
+        PROGRAM Main
+            VAR c : Color := Color#Green; END_VAR
+            c := Color#Red;
+            c := Color.Blue;
+            c := Blue;
+            c := Red;
+            c := Color#Purple;
+        END_PROGRAM
+        "#;
+        let (table, diagnostics) = resolve_src(src);
+
+        assert_eq!(bindings(src, &table, "Green;"), vec!["Color.Green"]);
+        assert_eq!(bindings(src, &table, "Red;"), vec!["Color.Red"]);
+        assert_eq!(
+            bindings(src, &table, "Blue;"),
+            vec!["Color.Blue", "Color.Blue"]
+        );
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "'Red' is ambiguous between several enums. Qualify it like Type#Red.",
+                "'Purple' is not a value of enum 'Color'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolves_namespaces() {
+        let src = r#"
+        NAMESPACE Lib.Geometry
+            TYPE Point : STRUCT x, y : REAL; END_STRUCT END_TYPE
+
+            FUNCTION Origin : Point
+                Origin.x := 0.0;
+            END_FUNCTION
+        END_NAMESPACE
+
+        PROGRAM Main
+            VAR p : Lib.Geometry.Point; END_VAR
+            p.y := Lib.Geometry.Origin().x;
+            p.z := 1.0;
+            Lib.Missing();
+        END_PROGRAM
+        "#;
+        let (table, diagnostics) = resolve_src(src);
+
+        assert_eq!(
+            bindings(src, &table, "Point; END_VAR"),
+            vec!["Lib.Geometry.Point"]
+        );
+        assert_eq!(
+            bindings(src, &table, "Origin()"),
+            vec!["Lib.Geometry.Origin"]
+        );
+        assert_eq!(bindings(src, &table, "x;"), vec!["Lib.Geometry.Point.x"]);
+        assert_eq!(bindings(src, &table, "y :="), vec!["Lib.Geometry.Point.y"]);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "'z' is not a member of 'Lib.Geometry.Point'.",
+                "'Missing' is not a member of 'Lib'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolves_configurations() {
+        let src = r#"
+        PROGRAM Main
+            VAR_INPUT limit : INT; END_VAR
+            VAR_EXTERNAL speed : INT; END_VAR
+            speed := limit;
+        END_PROGRAM
+
+        CONFIGURATION Plant
+            VAR_GLOBAL speed : INT; END_VAR
+            RESOURCE Cpu ON PLC
+                TASK Fast(INTERVAL := T#10ms, PRIORITY := 1);
+                PROGRAM Main WITH Fast : Main(limit := speed);
+                PROGRAM Inst WITH Nope : Mian;
+                PROGRAM Other WITH Main : Plant;
+            END_RESOURCE
+        END_CONFIGURATION
+        "#;
+        let (table, diagnostics) = resolve_src(src);
+
+        assert_eq!(bindings(src, &table, "speed : INT"), vec!["speed"]);
+        assert_eq!(bindings(src, &table, "speed);"), vec!["speed"]);
+        assert_eq!(bindings(src, &table, "Fast :"), vec!["Plant.Cpu.Fast"]);
+        assert_eq!(bindings(src, &table, "Main("), vec!["Main"]);
+        assert_eq!(bindings(src, &table, "limit :="), vec!["Main.limit"]);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "'Nope' is not a TASK of this resource.",
+                "Undeclared identifier 'Mian'.",
+                "'Main' is not a TASK of this resource.",
+                "'Plant' is not a PROGRAM and cannot be instantiated.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolves_function_block_members_methods_and_actions() {
+        let src = r#"
+        FUNCTION_BLOCK Motor
+            VAR_INPUT enable : BOOL; END_VAR
+            VAR_OUTPUT running : BOOL; END_VAR
+
+            METHOD Start : BOOL
+                running := enable;
+            END_METHOD
+        END_FUNCTION_BLOCK
+
+        ACTION Motor.Reset:
+            running := FALSE;
+        END_ACTION
+
+        PROGRAM Main
+            VAR m : Motor; ok : BOOL; i : INT; END_VAR
+            m(enable := TRUE);
+            ok := m.running AND m.Start();
+            m.Reset();
+            FOR i := 0 TO 10 DO
+                ok := NOT ok;
+            END_FOR;
+            FOR j := 0 TO 1 DO
+            END_FOR;
+        END_PROGRAM
+        "#;
+        let (table, diagnostics) = resolve_src(src);
+
+        assert_eq!(bindings(src, &table, "enable :="), vec!["Motor.enable"]);
+        assert_eq!(
+            bindings(src, &table, "running"),
+            vec!["Motor.running", "Motor.running", "Motor.running"]
+        );
+        assert_eq!(bindings(src, &table, "Start()"), vec!["Motor.Start"]);
+        assert_eq!(bindings(src, &table, "Reset()"), vec!["Motor.Reset"]);
+        assert_eq!(messages(&diagnostics), vec!["Undeclared identifier 'j'."]);
+        assert!(table.scopes.iter().any(|s| s.kind == ScopeKind::ForLoop));
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use crate::parsing::ast::{DataType, Expression, Span, VariableKind};
+
+pub type ScopeId = usize;
+pub type SymbolId = usize;
+
+/// Names of the IEC elementary data types, which are known without a declaration.
+pub const ELEMENTARY_TYPES: &[&str] = &[
+    "BOOL",
+    "SINT",
+    "INT",
+    "DINT",
+    "LINT",
+    "USINT",
+    "UINT",
+    "UDINT",
+    "ULINT",
+    "BYTE",
+    "WORD",
+    "DWORD",
+    "LWORD",
+    "REAL",
+    "LREAL",
+    "TIME",
+    "LTIME",
+    "DATE",
+    "TIME_OF_DAY",
+    "TOD",
+    "DATE_AND_TIME",
+    "DT",
+    "STRING",
+    "WSTRING",
+    "CHAR",
+    "WCHAR",
+];
+
+pub fn is_elementary_type(name: &str) -> bool {
+    ELEMENTARY_TYPES
+        .iter()
+        .any(|t| t.eq_ignore_ascii_case(name))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScopeKind {
+    Global,
+    Namespace,
+    Pou,
+    Method,
+    Action,
+    ForLoop,
+    Struct,
+    Enum,
+    /// A CONFIGURATION or one of its RESOURCEs
+    Configuration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Variable {
+        kind: VariableKind,
+        constant: bool,
+    },
+    Program,
+    Function,
+    FunctionBlock,
+    Method,
+    Action,
+    Type,
+    EnumValue,
+    Namespace,
+    Configuration,
+    Resource,
+    Task,
+    /// A program instance of a resource, like `PROGRAM Main WITH Fast : MainProgram;`
+    ProgramInstance,
+}
+
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    /// Scope the symbol is declared in
+    pub scope: ScopeId,
+    /// Scope holding the members of namespaces, configurations, POUs, structs and enums
+    pub members: Option<ScopeId>,
+    /// Declared type of variables, return type of functions and definition of types
+    pub data_type: Option<DataType>,
+    /// Initial value of variables and explicit value of enum values
+    pub value: Option<Expression>,
+}
+
+#[derive(Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    /// Symbol whose members live in this scope
+    pub owner: Option<SymbolId>,
+    symbols: HashMap<String, SymbolId>,
+}
+
+/// A use of an identifier bound to its declaration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reference {
+    pub span: Span,
+    pub symbol: SymbolId,
+}
+
+#[derive(Debug)]
+pub struct SymbolTable {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolTable {
+    pub const GLOBAL: ScopeId = 0;
+
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                parent: None,
+                owner: None,
+                symbols: HashMap::new(),
+            }],
+            symbols: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+
+    pub fn add_scope(
+        &mut self,
+        kind: ScopeKind,
+        parent: Option<ScopeId>,
+        owner: Option<SymbolId>,
+    ) -> ScopeId {
+        self.scopes.push(Scope {
+            kind,
+            parent,
+            owner,
+            symbols: HashMap::new(),
+        });
+        self.scopes.len() - 1
+    }
+
+    /// Declares a symbol in the given scope. Fails with the already declared symbol on a name clash.
+    pub fn declare(
+        &mut self,
+        scope: ScopeId,
+        name: &str,
+        kind: SymbolKind,
+        span: Span,
+        data_type: Option<DataType>,
+    ) -> Result<SymbolId, SymbolId> {
+        let key = name.to_ascii_uppercase();
+        if let Some(existing) = self.scopes[scope].symbols.get(&key) {
+            return Err(*existing);
+        }
+
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            span,
+            scope,
+            members: None,
+            data_type,
+            value: None,
+        });
+        let id = self.symbols.len() - 1;
+        self.scopes[scope].symbols.insert(key, id);
+        Ok(id)
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id]
+    }
+
+    pub fn set_members(&mut self, symbol: SymbolId, members: ScopeId) {
+        self.symbols[symbol].members = Some(members);
+    }
+
+    pub fn set_value(&mut self, symbol: SymbolId, value: Expression) {
+        self.symbols[symbol].value = Some(value);
+    }
+
+    pub fn lookup_local(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        self.scopes[scope]
+            .symbols
+            .get(&name.to_ascii_uppercase())
+            .copied()
+    }
+
+    /// Looks the name up in the scope and all of its parents.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        let mut cur = Some(scope);
+        while let Some(s) = cur {
+            if let Some(id) = self.lookup_local(s, name) {
+                return Some(id);
+            }
+            cur = self.scopes[s].parent;
+        }
+
+        None
+    }
+
+    /// All enum values with the given name, regardless of the enum they belong to.
+    pub fn lookup_enum_values(&self, name: &str) -> Vec<SymbolId> {
+        self.scopes
+            .iter()
+            .filter(|s| s.kind == ScopeKind::Enum)
+            .filter_map(|s| s.symbols.get(&name.to_ascii_uppercase()).copied())
+            .collect()
+    }
+
+    /// Symbols declared directly in the scope, in declaration order.
+    pub fn symbols_in(&self, scope: ScopeId) -> Vec<SymbolId> {
+        let mut ids = self.scopes[scope]
+            .symbols
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    pub fn add_reference(&mut self, span: Span, symbol: SymbolId) {
+        self.references.push(Reference { span, symbol });
+    }
+
+    /// The symbol referenced or declared at the given source position.
+    pub fn symbol_at(&self, pos: usize) -> Option<SymbolId> {
+        self.references
+            .iter()
+            .find(|r| r.span.pos <= pos && pos < r.span.end())
+            .map(|r| r.symbol)
+            .or_else(|| {
+                self.symbols
+                    .iter()
+                    .position(|s| s.span.pos <= pos && pos < s.span.end())
+            })
+    }
+
+    pub fn references_to(&self, symbol: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |r| r.symbol == symbol)
+    }
+
+    /// Dotted path of a symbol through its enclosing namespaces and POUs.
+    pub fn qualified_name(&self, id: SymbolId) -> String {
+        let symbol = &self.symbols[id];
+        match self.scopes[symbol.scope].owner {
+            Some(owner) => format!("{}.{}", self.qualified_name(owner), symbol.name),
+            None => symbol.name.clone(),
+        }
+    }
+}
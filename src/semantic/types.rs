@@ -0,0 +1,821 @@
+use std::{cell::Cell, collections::HashMap, fmt};
+
+use crate::{
+    parsing::{
+        ast::{DataType, Expression, InfixOperator, LiteralExpression, Span},
+        token::{DateKind, NumberValue},
+    },
+    semantic::{
+        consteval::{self, ConstValue},
+        library,
+        symbols::{ScopeId, ScopeKind, SymbolId, SymbolKind, SymbolTable},
+    },
+};
+
+/// Length of strings declared without one.
+pub const DEFAULT_STRING_LENGTH: usize = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Elementary {
+    Bool,
+    Sint,
+    Int,
+    Dint,
+    Lint,
+    Usint,
+    Uint,
+    Udint,
+    Ulint,
+    Byte,
+    Word,
+    Dword,
+    Lword,
+    Real,
+    Lreal,
+    Time,
+    Ltime,
+    Date,
+    TimeOfDay,
+    DateAndTime,
+    Char,
+    WChar,
+}
+
+const ELEMENTARY_NAMES: &[(&str, Elementary)] = &[
+    ("BOOL", Elementary::Bool),
+    ("SINT", Elementary::Sint),
+    ("INT", Elementary::Int),
+    ("DINT", Elementary::Dint),
+    ("LINT", Elementary::Lint),
+    ("USINT", Elementary::Usint),
+    ("UINT", Elementary::Uint),
+    ("UDINT", Elementary::Udint),
+    ("ULINT", Elementary::Ulint),
+    ("BYTE", Elementary::Byte),
+    ("WORD", Elementary::Word),
+    ("DWORD", Elementary::Dword),
+    ("LWORD", Elementary::Lword),
+    ("REAL", Elementary::Real),
+    ("LREAL", Elementary::Lreal),
+    ("TIME", Elementary::Time),
+    ("LTIME", Elementary::Ltime),
+    ("DATE", Elementary::Date),
+    ("TIME_OF_DAY", Elementary::TimeOfDay),
+    ("TOD", Elementary::TimeOfDay),
+    ("DATE_AND_TIME", Elementary::DateAndTime),
+    ("DT", Elementary::DateAndTime),
+    ("CHAR", Elementary::Char),
+    ("WCHAR", Elementary::WChar),
+];
+
+/// Implicit conversions of IEC 61131-3, the transitive closure is allowed as well.
+const WIDENINGS: &[(Elementary, Elementary)] = &[
+    (Elementary::Sint, Elementary::Int),
+    (Elementary::Int, Elementary::Dint),
+    (Elementary::Dint, Elementary::Lint),
+    (Elementary::Int, Elementary::Real),
+    (Elementary::Dint, Elementary::Lreal),
+    (Elementary::Usint, Elementary::Uint),
+    (Elementary::Uint, Elementary::Udint),
+    (Elementary::Udint, Elementary::Ulint),
+    (Elementary::Usint, Elementary::Int),
+    (Elementary::Uint, Elementary::Dint),
+    (Elementary::Udint, Elementary::Lint),
+    (Elementary::Uint, Elementary::Real),
+    (Elementary::Udint, Elementary::Lreal),
+    (Elementary::Real, Elementary::Lreal),
+    (Elementary::Bool, Elementary::Byte),
+    (Elementary::Byte, Elementary::Word),
+    (Elementary::Word, Elementary::Dword),
+    (Elementary::Dword, Elementary::Lword),
+    (Elementary::Time, Elementary::Ltime),
+    (Elementary::Char, Elementary::WChar),
+];
+
+impl Elementary {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ELEMENTARY_NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, e)| *e)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ELEMENTARY_NAMES
+            .iter()
+            .find(|(_, e)| e == self)
+            .map(|(n, _)| *n)
+            .expect("Every elementary type has a name")
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            Elementary::Sint | Elementary::Int | Elementary::Dint | Elementary::Lint
+        )
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Elementary::Usint | Elementary::Uint | Elementary::Udint | Elementary::Ulint
+        )
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.is_signed() || self.is_unsigned()
+    }
+
+    pub fn is_real(&self) -> bool {
+        matches!(self, Elementary::Real | Elementary::Lreal)
+    }
+
+    pub fn is_bit(&self) -> bool {
+        matches!(
+            self,
+            Elementary::Bool
+                | Elementary::Byte
+                | Elementary::Word
+                | Elementary::Dword
+                | Elementary::Lword
+        )
+    }
+
+    pub fn is_duration(&self) -> bool {
+        matches!(self, Elementary::Time | Elementary::Ltime)
+    }
+
+    pub fn is_date(&self) -> bool {
+        matches!(
+            self,
+            Elementary::Date | Elementary::TimeOfDay | Elementary::DateAndTime
+        )
+    }
+
+    /// The type of a date or time of day literal.
+    pub fn of_date(kind: DateKind) -> Self {
+        match kind {
+            DateKind::Date => Elementary::Date,
+            DateKind::TimeOfDay => Elementary::TimeOfDay,
+            DateKind::DateAndTime => Elementary::DateAndTime,
+        }
+    }
+
+    /// What the values of a date type denote, the inverse of [`Elementary::of_date`].
+    pub fn date_kind(&self) -> Option<DateKind> {
+        match self {
+            Elementary::Date => Some(DateKind::Date),
+            Elementary::TimeOfDay => Some(DateKind::TimeOfDay),
+            Elementary::DateAndTime => Some(DateKind::DateAndTime),
+            _ => None,
+        }
+    }
+
+    /// Durations and dates, which count in nanoseconds.
+    pub fn is_temporal(&self) -> bool {
+        self.is_duration() || self.is_date()
+    }
+
+    /// Number of bits of the integer and bit string types.
+    pub fn bit_width(&self) -> Option<u32> {
+        match self {
+            Elementary::Bool => Some(1),
+            Elementary::Sint | Elementary::Usint | Elementary::Byte => Some(8),
+            Elementary::Int | Elementary::Uint | Elementary::Word => Some(16),
+            Elementary::Dint | Elementary::Udint | Elementary::Dword => Some(32),
+            Elementary::Lint | Elementary::Ulint | Elementary::Lword => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Inclusive range of values an integer or bit string type can hold.
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        let bits = self.bit_width()?;
+        match self.is_signed() {
+            true => Some((-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)),
+            false => Some((0, (1i128 << bits) - 1)),
+        }
+    }
+
+    pub fn widens_to(&self, other: Elementary) -> bool {
+        let mut reachable = vec![*self];
+        let mut i = 0;
+        while i < reachable.len() {
+            if reachable[i] == other {
+                return true;
+            }
+            for (from, to) in WIDENINGS {
+                if *from == reachable[i] && !reachable.contains(to) {
+                    reachable.push(*to);
+                }
+            }
+            i += 1;
+        }
+
+        false
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Elementary(Elementary),
+    String {
+        wide: bool,
+        length: Option<usize>,
+    },
+    Array {
+        dimensions: Vec<Option<(i128, i128)>>,
+        element: Box<Type>,
+    },
+    Pointer(Box<Type>),
+    Reference(Box<Type>),
+    Subrange {
+        base: Elementary,
+        lower: Option<i128>,
+        upper: Option<i128>,
+    },
+    /// Enums are identified by the scope holding their values
+    Enum(ScopeId),
+    Struct(SymbolId),
+    FunctionBlock(SymbolId),
+    Program(SymbolId),
+    /// Untyped integer literal which adapts to the context it is used in
+    IntegerLiteral,
+    /// Untyped real literal which adapts to the context it is used in
+    RealLiteral,
+    /// Result of calls which do not return a value
+    Void,
+    /// Type of erroneous expressions, which is compatible to everything to avoid follow-up errors
+    Unknown,
+}
+
+impl Type {
+    pub fn elementary(&self) -> Option<Elementary> {
+        match self {
+            Type::Elementary(e) => Some(*e),
+            Type::Subrange { base, .. } => Some(*base),
+            _ => None,
+        }
+    }
+
+    pub fn is(&self, e: Elementary) -> bool {
+        self.elementary() == Some(e)
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        *self == Type::Unknown
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, Type::String { .. })
+    }
+
+    /// Number of characters a string of this type holds at most.
+    pub fn string_length(&self) -> Option<usize> {
+        match self.dereferenced() {
+            Type::String { length, .. } => Some(length.unwrap_or(DEFAULT_STRING_LENGTH)),
+            _ => None,
+        }
+    }
+
+    /// Strips references, which are dereferenced implicitly.
+    pub fn dereferenced(&self) -> &Type {
+        match self {
+            Type::Reference(t) => t.dereferenced(),
+            t => t,
+        }
+    }
+
+    pub fn display<'a>(&'a self, table: &'a SymbolTable) -> TypeDisplay<'a> {
+        TypeDisplay { ty: self, table }
+    }
+}
+
+pub struct TypeDisplay<'a> {
+    ty: &'a Type,
+    table: &'a SymbolTable,
+}
+
+impl fmt::Display for TypeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ty {
+            Type::Elementary(e) => write!(f, "{}", e.name()),
+            Type::String { wide, length } => {
+                write!(f, "{}", if *wide { "WSTRING" } else { "STRING" })?;
+                match length {
+                    Some(l) => write!(f, "({l})"),
+                    None => Ok(()),
+                }
+            }
+            Type::Array {
+                dimensions,
+                element,
+            } => {
+                let dimensions = dimensions
+                    .iter()
+                    .map(|d| match d {
+                        Some((l, u)) => format!("{l}..{u}"),
+                        None => "?".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "ARRAY[{dimensions}] OF {}", element.display(self.table))
+            }
+            Type::Pointer(t) => write!(f, "POINTER TO {}", t.display(self.table)),
+            Type::Reference(t) => write!(f, "REFERENCE TO {}", t.display(self.table)),
+            Type::Subrange { base, lower, upper } => {
+                let bound = |b: &Option<i128>| b.map_or("?".to_string(), |b| b.to_string());
+                write!(f, "{}({}..{})", base.name(), bound(lower), bound(upper))
+            }
+            Type::Enum(scope) => match self.table.scope(*scope).owner {
+                Some(owner) => write!(f, "{}", self.table.qualified_name(owner)),
+                None => write!(f, "anonymous enum"),
+            },
+            Type::Struct(id) | Type::FunctionBlock(id) | Type::Program(id) => {
+                write!(f, "{}", self.table.qualified_name(*id))
+            }
+            Type::IntegerLiteral => write!(f, "integer literal"),
+            Type::RealLiteral => write!(f, "real literal"),
+            Type::Void => write!(f, "nothing"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The generic ANY_* types used to describe standard function signatures.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generic {
+    Any,
+    AnyDerived,
+    AnyElementary,
+    AnyMagnitude,
+    AnyNum,
+    AnyReal,
+    AnyInt,
+    AnySigned,
+    AnyUnsigned,
+    AnyDuration,
+    AnyBit,
+    AnyChars,
+    AnyString,
+    AnyChar,
+    AnyDate,
+}
+
+const GENERIC_NAMES: &[(&str, Generic)] = &[
+    ("ANY", Generic::Any),
+    ("ANY_DERIVED", Generic::AnyDerived),
+    ("ANY_ELEMENTARY", Generic::AnyElementary),
+    ("ANY_MAGNITUDE", Generic::AnyMagnitude),
+    ("ANY_NUM", Generic::AnyNum),
+    ("ANY_REAL", Generic::AnyReal),
+    ("ANY_INT", Generic::AnyInt),
+    ("ANY_SIGNED", Generic::AnySigned),
+    ("ANY_UNSIGNED", Generic::AnyUnsigned),
+    ("ANY_DURATION", Generic::AnyDuration),
+    ("ANY_BIT", Generic::AnyBit),
+    ("ANY_CHARS", Generic::AnyChars),
+    ("ANY_STRING", Generic::AnyString),
+    ("ANY_CHAR", Generic::AnyChar),
+    ("ANY_DATE", Generic::AnyDate),
+];
+
+impl Generic {
+    pub fn from_name(name: &str) -> Option<Self> {
+        GENERIC_NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, g)| *g)
+    }
+
+    pub fn name(&self) -> &'static str {
+        GENERIC_NAMES
+            .iter()
+            .find(|(_, g)| g == self)
+            .map(|(n, _)| *n)
+            .expect("Every generic type has a name")
+    }
+
+    pub fn contains(&self, ty: &Type) -> bool {
+        let ty = ty.dereferenced();
+        match (self, ty) {
+            (_, Type::Unknown) => true,
+            (Generic::Any, _) => !matches!(ty, Type::Void),
+            (Generic::AnyDerived, t) => matches!(
+                t,
+                Type::Array { .. } | Type::Struct(_) | Type::Enum(_) | Type::FunctionBlock(_)
+            ),
+            (Generic::AnyInt | Generic::AnyNum | Generic::AnyMagnitude, Type::IntegerLiteral) => {
+                true
+            }
+            (Generic::AnyBit | Generic::AnyElementary, Type::IntegerLiteral) => true,
+            (
+                Generic::AnyReal | Generic::AnyNum | Generic::AnyMagnitude | Generic::AnyElementary,
+                Type::RealLiteral,
+            ) => true,
+            (
+                Generic::AnyChars | Generic::AnyString | Generic::AnyElementary,
+                Type::String { .. },
+            ) => true,
+            (g, t) => match t.elementary() {
+                Some(e) => match g {
+                    Generic::AnyElementary => true,
+                    Generic::AnyMagnitude => e.is_integer() || e.is_real() || e.is_duration(),
+                    Generic::AnyNum => e.is_integer() || e.is_real(),
+                    Generic::AnyReal => e.is_real(),
+                    Generic::AnyInt => e.is_integer(),
+                    Generic::AnySigned => e.is_signed(),
+                    Generic::AnyUnsigned => e.is_unsigned(),
+                    Generic::AnyDuration => e.is_duration(),
+                    Generic::AnyBit => e.is_bit(),
+                    Generic::AnyChars | Generic::AnyChar => {
+                        matches!(e, Elementary::Char | Elementary::WChar)
+                    }
+                    Generic::AnyDate => e.is_date(),
+                    _ => false,
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+/// Whether a value of type `from` may be stored in a location of type `to` without conversion.
+pub fn is_assignable(from: &Type, to: &Type) -> bool {
+    let (from, to) = (from.dereferenced(), to.dereferenced());
+    if from == to || from.is_unknown() || to.is_unknown() {
+        return true;
+    }
+
+    match (from, to) {
+        (Type::IntegerLiteral, t) => t.elementary().is_some_and(|e| {
+            e.is_integer() || e.is_real() || (e.is_bit() && e != Elementary::Bool)
+        }),
+        (Type::RealLiteral, t) => t.elementary().is_some_and(|e| e.is_real()),
+        (Type::String { wide: a, .. }, Type::String { wide: b, .. }) => a == b,
+        (Type::Array { element: a, .. }, Type::Array { element: b, .. }) => a == b,
+        (Type::Pointer(_), Type::Pointer(b)) if **b == Type::Void => true,
+        (Type::Pointer(a), Type::Pointer(b)) => a == b,
+        (f, t) => match (f.elementary(), t.elementary()) {
+            (Some(a), Some(b)) => a == b || a.widens_to(b),
+            _ => false,
+        },
+    }
+}
+
+/// The type both operands of a binary operation are converted to, if there is one.
+pub fn common_type(a: &Type, b: &Type) -> Option<Type> {
+    let (a, b) = (a.dereferenced(), b.dereferenced());
+    match (a, b) {
+        (Type::Unknown, _) | (_, Type::Unknown) => Some(Type::Unknown),
+        (Type::IntegerLiteral, Type::RealLiteral) | (Type::RealLiteral, Type::IntegerLiteral) => {
+            Some(Type::RealLiteral)
+        }
+        // Real literals turn integers into the smallest real type they widen to
+        (Type::RealLiteral, t) | (t, Type::RealLiteral)
+            if t.elementary().is_some_and(|e| e.is_integer()) =>
+        {
+            [Elementary::Real, Elementary::Lreal]
+                .into_iter()
+                .find(|r| t.elementary().is_some_and(|e| e.widens_to(*r)))
+                .map(Type::Elementary)
+        }
+        _ if is_assignable(a, b) => Some(base_of(b)),
+        _ if is_assignable(b, a) => Some(base_of(a)),
+        _ => None,
+    }
+}
+
+/// Type of the result of a binary operation, if the operator is defined for the operands.
+pub fn infix_type(op: InfixOperator, left: &Type, right: &Type) -> Option<Type> {
+    let (l, r) = (left.dereferenced(), right.dereferenced());
+    match op {
+        op if op.is_comparison() => {
+            let ordered = !matches!(op, InfixOperator::Equals | InfixOperator::NotEquals);
+            common_type(l, r)
+                .filter(|t| {
+                    Generic::AnyElementary.contains(t)
+                        || (!ordered && matches!(t, Type::Enum(_) | Type::Pointer(_)))
+                })
+                .map(|_| Type::Elementary(Elementary::Bool))
+        }
+        op if op.is_logical() => common_type(l, r).filter(|t| Generic::AnyBit.contains(t)),
+        InfixOperator::Modulo => common_type(l, r).filter(|t| Generic::AnyInt.contains(t)),
+        InfixOperator::Power => match (l, r) {
+            (Type::IntegerLiteral | Type::RealLiteral, r) if Generic::AnyNum.contains(r) => {
+                Some(Type::RealLiteral)
+            }
+            (l, r) if Generic::AnyReal.contains(l) && Generic::AnyNum.contains(r) => {
+                Some(l.clone())
+            }
+            _ => None,
+        },
+        op => arithmetic_type(op, l, r),
+    }
+}
+
+fn arithmetic_type(op: InfixOperator, l: &Type, r: &Type) -> Option<Type> {
+    use Elementary::*;
+
+    let additive = matches!(op, InfixOperator::Addition | InfixOperator::Subtraction);
+    match (l.elementary(), r.elementary()) {
+        (Some(a), Some(b)) if a.is_duration() && b.is_duration() && additive => common_type(l, r),
+        (Some(a), _) if a.is_duration() && !additive && Generic::AnyNum.contains(r) => {
+            Some(l.clone())
+        }
+        (Some(DateAndTime | TimeOfDay), Some(Time)) if additive => Some(l.clone()),
+        (Some(a), Some(b)) if a == b && a.is_date() && op == InfixOperator::Subtraction => {
+            Some(Type::Elementary(Time))
+        }
+        _ => common_type(l, r).filter(|t| Generic::AnyNum.contains(t)),
+    }
+}
+
+/// Subranges compute in their base type.
+fn base_of(ty: &Type) -> Type {
+    match ty {
+        Type::Subrange { base, .. } => Type::Elementary(*base),
+        t => t.clone(),
+    }
+}
+
+const MAX_ALIAS_DEPTH: usize = 16;
+const MAX_CONSTANT_DEPTH: usize = 32;
+
+/// Derives the types of declarations from the bindings of a resolved program.
+pub struct TypeEnv<'a> {
+    pub table: &'a SymbolTable,
+    bindings: HashMap<usize, SymbolId>,
+    /// Nesting of constants currently being evaluated, to stop on cyclic definitions
+    constant_depth: Cell<usize>,
+}
+
+impl<'a> TypeEnv<'a> {
+    pub fn new(table: &'a SymbolTable) -> Self {
+        let mut bindings = HashMap::new();
+        for (id, symbol) in table.symbols.iter().enumerate() {
+            bindings.insert(symbol.span.pos, id);
+        }
+        for reference in &table.references {
+            bindings.insert(reference.span.pos, reference.symbol);
+        }
+
+        Self {
+            table,
+            bindings,
+            constant_depth: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn enter_constant(&self) -> bool {
+        if self.constant_depth.get() >= MAX_CONSTANT_DEPTH {
+            return false;
+        }
+        self.constant_depth.set(self.constant_depth.get() + 1);
+        true
+    }
+
+    pub(crate) fn leave_constant(&self) {
+        self.constant_depth.set(self.constant_depth.get() - 1);
+    }
+
+    /// Integer value of a constant expression like an array bound.
+    pub fn integer_value(&self, expression: &Expression) -> Option<i128> {
+        consteval::evaluate(self, expression)
+            .ok()
+            .as_ref()
+            .and_then(ConstValue::as_int)
+    }
+
+    /// The symbol an identifier at the given span refers to or declares.
+    pub fn binding(&self, span: Span) -> Option<SymbolId> {
+        self.bindings.get(&span.pos).copied()
+    }
+
+    /// Type of the values a symbol denotes when used in an expression.
+    pub fn type_of_symbol(&self, id: SymbolId) -> Type {
+        let symbol = self.table.symbol(id);
+        match symbol.kind {
+            SymbolKind::Variable { .. } | SymbolKind::Function | SymbolKind::Method => symbol
+                .data_type
+                .as_ref()
+                .map_or(Type::Void, |t| self.lower(t)),
+            SymbolKind::EnumValue => Type::Enum(symbol.scope),
+            SymbolKind::Program => Type::Program(id),
+            SymbolKind::FunctionBlock => Type::FunctionBlock(id),
+            SymbolKind::Type => self.lower_type_symbol(id, 0),
+            SymbolKind::Action
+            | SymbolKind::Namespace
+            | SymbolKind::Configuration
+            | SymbolKind::Resource
+            | SymbolKind::Task
+            | SymbolKind::ProgramInstance => Type::Unknown,
+        }
+    }
+
+    /// Type of an expression, following the rules of the checker, for the engines running it.
+    pub fn type_of(&self, expression: &Expression) -> Type {
+        let env = self;
+        match expression {
+            Expression::Literal(literal, _) => match literal {
+                LiteralExpression::Number(NumberValue::Int(_)) => Type::IntegerLiteral,
+                LiteralExpression::Number(NumberValue::Float(_)) => Type::RealLiteral,
+                LiteralExpression::True | LiteralExpression::False => {
+                    Type::Elementary(Elementary::Bool)
+                }
+                LiteralExpression::String(_, wide) => Type::String {
+                    wide: *wide,
+                    length: None,
+                },
+                LiteralExpression::Time(_) => Type::Elementary(Elementary::Time),
+                LiteralExpression::Date(d) => Type::Elementary(Elementary::of_date(d.kind)),
+            },
+            Expression::Identifier(name) => match env.binding(name.span) {
+                Some(id) => env.type_of_symbol(id),
+                None => Type::Unknown,
+            },
+            Expression::Member(member) => match env.binding(member.member.span) {
+                Some(id) => env.type_of_symbol(id),
+                None => Type::Unknown,
+            },
+            Expression::Prefix(prefix) => self.type_of(&prefix.operand).dereferenced().clone(),
+            Expression::Infix(infix) => {
+                let (left, right) = (self.type_of(&infix.left), self.type_of(&infix.right));
+                infix_type(infix.op, &left, &right).unwrap_or(Type::Unknown)
+            }
+            Expression::Index(index) => match self.type_of(&index.target).dereferenced() {
+                Type::Array { element, .. } => element.as_ref().clone(),
+                _ => Type::Unknown,
+            },
+            Expression::Deref(target, _) => match self.type_of(target).dereferenced() {
+                Type::Pointer(t) => t.as_ref().clone(),
+                _ => Type::Unknown,
+            },
+            Expression::Call(call) => {
+                let callee = match call.callee.as_ref() {
+                    Expression::Identifier(name) => env.binding(name.span),
+                    Expression::Member(member) => env.binding(member.member.span),
+                    _ => None,
+                };
+                match callee.map(|id| (id, env.table.symbol(id).kind)) {
+                    Some((id, SymbolKind::Function | SymbolKind::Method)) => env.type_of_symbol(id),
+                    Some(_) => Type::Void,
+                    None => match call.callee.as_ref() {
+                        Expression::Identifier(name) => library::lookup(&name.name)
+                            .and_then(|function| {
+                                let arguments = call
+                                    .arguments
+                                    .iter()
+                                    .map(|a| self.type_of(a.value()))
+                                    .collect::<Vec<_>>();
+                                function.result_type(&arguments).ok()
+                            })
+                            .unwrap_or(Type::Unknown),
+                        _ => Type::Unknown,
+                    },
+                }
+            }
+            Expression::TypedLiteral(literal) => {
+                let type_name = &literal.type_name;
+                let ty = env.lower(&DataType::Named(type_name.clone()));
+                match ty.elementary().is_some() || ty.is_string() {
+                    true => ty,
+                    false => env
+                        .binding(type_name.last().span)
+                        .map_or(Type::Unknown, |id| env.type_of_symbol(id)),
+                }
+            }
+            Expression::Array(..) | Expression::Struct(..) => Type::Unknown,
+        }
+    }
+
+    pub fn lower(&self, data_type: &DataType) -> Type {
+        self.lower_with_depth(data_type, 0)
+    }
+
+    fn lower_with_depth(&self, data_type: &DataType, depth: usize) -> Type {
+        match data_type {
+            DataType::Named(name) => {
+                if name.parts.len() == 1 {
+                    let name = &name.parts[0].name;
+                    if let Some(e) = Elementary::from_name(name) {
+                        return Type::Elementary(e);
+                    }
+                    if name.eq_ignore_ascii_case("STRING") || name.eq_ignore_ascii_case("WSTRING") {
+                        return Type::String {
+                            wide: name.eq_ignore_ascii_case("WSTRING"),
+                            length: None,
+                        };
+                    }
+                }
+
+                match self.binding(name.last().span) {
+                    Some(id) => match self.table.symbol(id).kind {
+                        SymbolKind::Type => self.lower_type_symbol(id, depth + 1),
+                        SymbolKind::FunctionBlock => Type::FunctionBlock(id),
+                        _ => Type::Unknown,
+                    },
+                    None => Type::Unknown,
+                }
+            }
+            DataType::String { wide, length, .. } => Type::String {
+                wide: *wide,
+                length: length
+                    .as_deref()
+                    .and_then(|l| self.integer_value(l))
+                    .and_then(|l| usize::try_from(l).ok()),
+            },
+            DataType::Array {
+                ranges, element, ..
+            } => Type::Array {
+                dimensions: ranges
+                    .iter()
+                    .map(|r| Some((self.integer_value(&r.lower)?, self.integer_value(&r.upper)?)))
+                    .collect(),
+                element: Box::new(self.lower_with_depth(element, depth)),
+            },
+            DataType::Pointer(target, _) => {
+                Type::Pointer(Box::new(self.lower_with_depth(target, depth)))
+            }
+            DataType::Reference(target, _) => {
+                Type::Reference(Box::new(self.lower_with_depth(target, depth)))
+            }
+            DataType::Subrange { base, range } => match Elementary::from_name(&base.last().name) {
+                Some(e) if e.is_integer() => Type::Subrange {
+                    base: e,
+                    lower: self.integer_value(&range.lower),
+                    upper: self.integer_value(&range.upper),
+                },
+                _ => Type::Unknown,
+            },
+            // Inline enums are found through the declaration of their first value
+            DataType::Enum { values, .. } => values
+                .first()
+                .and_then(|v| self.binding(v.name.span))
+                .map_or(Type::Unknown, |id| Type::Enum(self.table.symbol(id).scope)),
+            DataType::Struct(..) | DataType::Union(..) => Type::Unknown,
+        }
+    }
+
+    fn lower_type_symbol(&self, id: SymbolId, depth: usize) -> Type {
+        let symbol = self.table.symbol(id);
+        if let Some(members) = symbol.members {
+            return match self.table.scope(members).kind {
+                ScopeKind::Enum => Type::Enum(members),
+                _ => Type::Struct(id),
+            };
+        }
+
+        match &symbol.data_type {
+            Some(t) if depth < MAX_ALIAS_DEPTH => self.lower_with_depth(t, depth),
+            _ => Type::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widening_is_transitive() {
+        assert!(Elementary::Sint.widens_to(Elementary::Lint));
+        assert!(Elementary::Usint.widens_to(Elementary::Lreal));
+        assert!(Elementary::Int.widens_to(Elementary::Real));
+        assert!(!Elementary::Dint.widens_to(Elementary::Real));
+        assert!(!Elementary::Int.widens_to(Elementary::Uint));
+        assert!(!Elementary::Lreal.widens_to(Elementary::Real));
+    }
+
+    #[test]
+    fn test_generics() {
+        let int = Type::Elementary(Elementary::Int);
+        let real = Type::Elementary(Elementary::Real);
+        let word = Type::Elementary(Elementary::Word);
+
+        assert!(Generic::AnyNum.contains(&int));
+        assert!(Generic::AnyNum.contains(&real));
+        assert!(!Generic::AnyNum.contains(&word));
+        assert!(Generic::AnyBit.contains(&word));
+        assert!(Generic::AnySigned.contains(&int));
+        assert!(!Generic::AnyUnsigned.contains(&int));
+        assert!(Generic::AnyInt.contains(&Type::IntegerLiteral));
+        assert!(!Generic::AnyInt.contains(&Type::RealLiteral));
+        assert_eq!(Generic::from_name("any_real"), Some(Generic::AnyReal));
+    }
+
+    #[test]
+    fn test_common_type() {
+        let int = Type::Elementary(Elementary::Int);
+        let dint = Type::Elementary(Elementary::Dint);
+        let uint = Type::Elementary(Elementary::Uint);
+
+        assert_eq!(common_type(&int, &dint), Some(dint.clone()));
+        assert_eq!(common_type(&Type::IntegerLiteral, &int), Some(int.clone()));
+        assert_eq!(common_type(&int, &uint), None);
+        assert_eq!(
+            common_type(&int, &Type::RealLiteral),
+            Some(Type::Elementary(Elementary::Real))
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Exit codes of the command line: 0 on success, 1 for sources with errors or failures and 2 for
+//! invalid command lines.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+const VALID: &str = "PROGRAM Main
+    VAR
+        count : INT;
+    END_VAR
+    count := count + 1;
+END_PROGRAM
+";
+
+/// A directory of sources for one test, removed when the test ends.
+struct Sources(PathBuf);
+
+impl Sources {
+    fn new(test: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("strooct-{test}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, text) in files {
+            fs::write(dir.join(name), text).unwrap();
+        }
+        Self(dir)
+    }
+
+    fn strooct(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_strooct"))
+            .args(args)
+            .current_dir(&self.0)
+            .output()
+            .unwrap()
+    }
+
+    fn exit_code(&self, args: &[&str]) -> i32 {
+        self.strooct(args).status.code().unwrap()
+    }
+}
+
+impl Drop for Sources {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_check_exit_codes() {
+    let sources = Sources::new(
+        "check",
+        &[
+            ("valid.st", VALID),
+            ("broken.st", "PROGRAM P x := ; END_PROGRAM"),
+            (
+                "twice.st",
+                "CONFIGURATION Plant
+    RESOURCE Cpu ON PLC
+        TASK Fast(INTERVAL := T#10ms, PRIORITY := 1);
+        PROGRAM First WITH Fast : Main;
+        PROGRAM Second WITH Fast : Main;
+    END_RESOURCE
+END_CONFIGURATION
+",
+            ),
+        ],
+    );
+    assert_eq!(sources.exit_code(&["check", "valid.st"]), 0);
+    assert_eq!(sources.exit_code(&["check", "broken.st"]), 1);
+    // Configurations the runtime cannot run are errors before running them
+    let twice = sources.strooct(&["check", "valid.st", "twice.st"]);
+    assert_eq!(twice.status.code(), Some(1));
+    assert!(
+        stderr(&twice).contains("Program 'Main' is instantiated more than once."),
+        "{}",
+        stderr(&twice)
+    );
+    assert_eq!(sources.exit_code(&["check", "missing.st"]), 2);
+    assert_eq!(sources.exit_code(&["check", "valid.st", "--json"]), 2);
+    assert_eq!(sources.exit_code(&["frobnicate", "valid.st"]), 2);
+}
+
+#[test]
+fn test_run_exit_codes() {
+    let sources = Sources::new(
+        "run",
+        &[
+            ("valid.st", VALID),
+            (
+                "failing.st",
+                "PROGRAM Main VAR x : INT; END_VAR x := 1 / x; END_PROGRAM",
+            ),
+            (
+                "fallback.st",
+                "TYPE Point : STRUCT x, y : INT; END_STRUCT END_TYPE
+FUNCTION Origin : Point Origin.x := 0; END_FUNCTION
+PROGRAM Main VAR p : Point; END_VAR p := Origin(); END_PROGRAM",
+            ),
+        ],
+    );
+    let run = sources.strooct(&["run", "valid.st", "--cycles", "3"]);
+    assert_eq!(run.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&run.stdout),
+        "Main = (count := 3)\n"
+    );
+
+    assert_eq!(sources.exit_code(&["run", "failing.st"]), 1);
+    assert_eq!(sources.exit_code(&["run", "valid.st", "--cycles", "0"]), 2);
+    assert_eq!(sources.exit_code(&["run", "valid.st", "--cycles", "x"]), 2);
+
+    // What the VM cannot compile runs on the interpreter, telling why
+    let fallback = sources.strooct(&["run", "fallback.st"]);
+    assert_eq!(fallback.status.code(), Some(0));
+    let note = stderr(&fallback);
+    assert!(note.starts_with("[INFO] in fallback.st:3"), "{note}");
+    assert!(
+        note.contains("The programs run on the interpreter instead."),
+        "{note}"
+    );
+}
+
+#[test]
+fn test_test_exit_codes() {
+    let test = |assertion: &str| {
+        format!(
+            "{{attribute 'test'}}
+PROGRAM Checks
+    ASSERT_TRUE({assertion});
+END_PROGRAM
+"
+        )
+    };
+    let sources = Sources::new(
+        "test",
+        &[
+            ("passing.st", &test("1 < 2")),
+            ("failing.st", &test("1 > 2")),
+        ],
+    );
+    assert_eq!(sources.exit_code(&["test", "passing.st"]), 0);
+    assert_eq!(sources.exit_code(&["test", "failing.st"]), 1);
+    assert_eq!(sources.exit_code(&["test", "passing.st", "--junit"]), 2);
+}
+
+#[test]
+fn test_fmt_check_exit_codes() {
+    let sources = Sources::new(
+        "fmt",
+        &[
+            ("formatted.st", VALID),
+            ("messy.st", "program Main\nx:=1;\nend_program\n"),
+        ],
+    );
+    assert_eq!(sources.exit_code(&["fmt", "--check", "formatted.st"]), 0);
+
+    let messy = sources.strooct(&["fmt", "--check", "messy.st"]);
+    assert_eq!(messy.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&messy.stdout).contains("messy.st"));
+    // Checking leaves the files as they are
+    let text = fs::read_to_string(sources.0.join("messy.st")).unwrap();
+    assert_eq!(text, "program Main\nx:=1;\nend_program\n");
+}